@@ -0,0 +1,36 @@
+// Starts `poly lsp` for .poly files and connects the debugger to `poly debug` / `poly dev --debug`
+const vscode = require('vscode');
+const { LanguageClient } = require('vscode-languageclient/node');
+
+let client;
+
+function activate(context) {
+  const command = vscode.workspace.getConfiguration('poly').get('serverPath') || 'poly';
+  const serverOptions = {
+    run: { command, args: ['lsp'] },
+    debug: { command, args: ['lsp'] },
+  };
+  const clientOptions = {
+    documentSelector: [{ scheme: 'file', language: 'poly' }],
+  };
+  client = new LanguageClient('poly', 'Poly Language Server', serverOptions, clientOptions);
+  client.start();
+  context.subscriptions.push({ dispose: () => client && client.stop() });
+
+  // launch: run `poly debug --dap <program>`; attach: connect to the port given to `poly dev --debug`
+  context.subscriptions.push(vscode.debug.registerDebugAdapterDescriptorFactory('poly', {
+    createDebugAdapterDescriptor(session) {
+      const config = session.configuration;
+      if (config.request === 'attach') {
+        return new vscode.DebugAdapterServer(config.port || 4711, config.host || '127.0.0.1');
+      }
+      return new vscode.DebugAdapterExecutable(command, ['debug', '--dap', config.program]);
+    },
+  }));
+}
+
+function deactivate() {
+  return client ? client.stop() : undefined;
+}
+
+module.exports = { activate, deactivate };
@@ -62,6 +62,7 @@ impl std::fmt::Display for Value {
             }
             Value::Function { name, .. } => write!(f, "<fn {}>", name),
             Value::NativeFunction(name) => write!(f, "<native fn {}>", name),
+            Value::Instance { .. } if crate::datetime::is_temporal(self) => write!(f, "{}", crate::datetime::display(self)),
             Value::Instance { class_name, .. } => write!(f, "<{} instance>", class_name),
             Value::Class { name, .. } => write!(f, "<class {}>", name),
             Value::Widget(node) => write!(f, "<Widget {}>", node.widget_type),
@@ -70,6 +71,24 @@ impl std::fmt::Display for Value {
 }
 
 impl Value {
+    /// Name returned by the `type()` builtin
+    pub fn type_name(&self) -> &str {
+        match self {
+            Value::None => "NoneType",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::String(_) => "str",
+            Value::List(_) => "list",
+            Value::Dict(_) => "dict",
+            Value::Function { .. } => "function",
+            Value::NativeFunction(_) => "builtin_function",
+            Value::Instance { class_name, .. } => class_name,
+            Value::Class { name, .. } => name,
+            Value::Widget(node) => &node.widget_type,
+        }
+    }
+
     /// Convert Value to valid JSON string
     pub fn to_json(&self) -> String {
         match self {
@@ -113,6 +132,9 @@ impl Value {
             }
             Value::Function { name, .. } => format!("\"<fn {}>\"", name),
             Value::NativeFunction(name) => format!("\"<native fn {}>\"", name),
+            Value::Instance { .. } if crate::datetime::is_temporal(self) => {
+                format!("\"{}\"", crate::datetime::isoformat(self))
+            }
             Value::Instance { class_name, fields } => {
                 let inner: Vec<String> = fields.iter().map(|(k, v)| {
                     format!("\"{}\":{}", k, v.to_json())
@@ -123,6 +145,60 @@ impl Value {
             Value::Widget(node) => format!("\"<Widget {}>\"", node.widget_type),
         }
     }
+
+    /// Convert Value to a serde_json value (same mapping as `to_json`)
+    pub fn to_serde_json(&self) -> serde_json::Value {
+        match self {
+            Value::None => serde_json::Value::Null,
+            Value::Bool(b) => serde_json::Value::Bool(*b),
+            Value::Int(i) => serde_json::Value::Number((*i).into()),
+            Value::Float(f) => serde_json::Number::from_f64(*f)
+                .map(serde_json::Value::Number)
+                .unwrap_or(serde_json::Value::Null),
+            Value::String(s) => serde_json::Value::String(s.clone()),
+            Value::List(items) => serde_json::Value::Array(items.iter().map(|v| v.to_serde_json()).collect()),
+            Value::Dict(pairs) => {
+                let mut map = serde_json::Map::new();
+                for (k, v) in pairs {
+                    let key = match k {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    map.insert(key, v.to_serde_json());
+                }
+                serde_json::Value::Object(map)
+            }
+            Value::Instance { .. } if crate::datetime::is_temporal(self) => {
+                serde_json::Value::String(crate::datetime::isoformat(self))
+            }
+            Value::Instance { class_name, fields } => {
+                let mut map = serde_json::Map::new();
+                map.insert("__class__".to_string(), serde_json::Value::String(class_name.clone()));
+                for (k, v) in fields {
+                    map.insert(k.clone(), v.to_serde_json());
+                }
+                serde_json::Value::Object(map)
+            }
+            other => serde_json::Value::String(other.to_string()),
+        }
+    }
+
+    /// Convert a serde_json value into a Value
+    pub fn from_serde_json(json: &serde_json::Value) -> Value {
+        match json {
+            serde_json::Value::Null => Value::None,
+            serde_json::Value::Bool(b) => Value::Bool(*b),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(i) => Value::Int(i),
+                None => Value::Float(n.as_f64().unwrap_or(0.0)),
+            },
+            serde_json::Value::String(s) => Value::String(s.clone()),
+            serde_json::Value::Array(items) => Value::List(items.iter().map(Value::from_serde_json).collect()),
+            serde_json::Value::Object(map) => Value::Dict(
+                map.iter().map(|(k, v)| (Value::String(k.clone()), Value::from_serde_json(v))).collect(),
+            ),
+        }
+    }
 }
 
 /// Function parameter with optional default value
@@ -130,6 +206,8 @@ impl Value {
 pub struct Param {
     pub name: String,
     pub default: Option<Expr>,
+    /// Type annotation as written (`name: list[int]`); not checked at runtime
+    pub annotation: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -248,6 +326,8 @@ pub enum Statement {
         name: String,
         params: Vec<Param>,
         body: Vec<Statement>,
+        /// Return annotation (`-> str`), if any
+        returns: Option<String>,
     },
     Return(Option<Expr>),
     
@@ -256,6 +336,7 @@ pub enum Statement {
         name: String,
         parent: Option<String>,
         methods: Vec<Method>,
+        doc: Option<String>,
     },
     
     // Expression statement
@@ -286,6 +367,19 @@ pub enum Statement {
         except_body: Vec<Statement>,
     },
     Raise(Expr),
+
+    // Context manager: with expr as name: body
+    With {
+        context: Expr,
+        alias: Option<String>,
+        body: Vec<Statement>,
+    },
+
+    // Source line of the statement that follows (only emitted by `Parser::with_lines`)
+    Line(usize),
+    // File the following statements come from, at the start of a program and
+    // of every function body (only emitted by `Parser::with_source`)
+    Source(String),
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,293 @@
+//! Typed frontend bindings (`poly gen bindings`)
+//!
+//! Writes a JavaScript module with one function per IPC command and a
+//! matching `.d.ts`. Commands are the public top-level functions of the entry
+//! module; parameter and return types come from annotations
+//! (`fn add(a: int, b: int) -> int`), or from a literal default value, and
+//! are `any` otherwise. The `__poly_*` system APIs from
+//! `reference::POLY_APIS` are grouped by namespace under `system`, so
+//! `__poly_fs_read` is `system.fs.read({ path })`; their argument types come
+//! from the command's schema on the router.
+
+use std::path::{Path, PathBuf};
+
+use crate::ast::{Expr, Statement};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::reference::{ApiDoc, POLY_APIS};
+use crate::router::{Arg, ArgKind};
+
+/// File name of the generated module, without extension
+pub const FILE_STEM: &str = "poly-bindings";
+
+/// A backend function callable with `poly.invoke`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    pub name: String,
+    pub params: Vec<CommandParam>,
+    /// TypeScript type of the result
+    pub returns: String,
+    pub doc: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandParam {
+    pub name: String,
+    /// TypeScript type
+    pub ty: String,
+    pub optional: bool,
+}
+
+/// Commands defined by the entry module's source
+pub fn commands(source: &str) -> Result<Vec<Command>, String> {
+    let program = Parser::new(Lexer::new(source).tokenize()).parse()?;
+    Ok(program.statements.iter().filter_map(|stmt| match stmt {
+        Statement::FnDef { name, params, body, returns } if !name.starts_with('_') => Some(Command {
+            name: name.clone(),
+            params: params.iter().map(|p| CommandParam {
+                name: p.name.clone(),
+                ty: match (&p.annotation, &p.default) {
+                    (Some(annotation), _) => ts_type(annotation),
+                    (None, Some(default)) => literal_type(default),
+                    (None, None) => "any".to_string(),
+                },
+                optional: p.default.is_some(),
+            }).collect(),
+            returns: returns.as_deref().map_or_else(|| "any".to_string(), ts_type),
+            doc: crate::doc::docstring(body),
+        }),
+        _ => None,
+    }).collect())
+}
+
+/// TypeScript type for a Poly annotation such as `list[int] | none`
+pub fn ts_type(annotation: &str) -> String {
+    let types: Vec<String> = split_top(annotation, '|').iter().map(|single| {
+        let (name, args) = match single.split_once('[') {
+            Some((name, rest)) => (name.trim(), split_top(rest.strip_suffix(']').unwrap_or(rest), ',')),
+            None => (single.trim(), Vec::new()),
+        };
+        let arg = |i: usize| args.get(i).map_or_else(|| "any".to_string(), |a| ts_type(a));
+        match name {
+            "int" | "float" | "number" => "number".to_string(),
+            "str" | "string" => "string".to_string(),
+            "bool" => "boolean".to_string(),
+            "none" | "None" => "null".to_string(),
+            "list" if args.is_empty() => "any[]".to_string(),
+            "list" => match arg(0) {
+                inner if inner.contains(' ') => format!("({})[]", inner),
+                inner => format!("{}[]", inner),
+            },
+            "tuple" => format!("[{}]", args.iter().map(|a| ts_type(a)).collect::<Vec<_>>().join(", ")),
+            "dict" => format!("Record<string, {}>", arg(1)),
+            _ => "any".to_string(),
+        }
+    }).collect();
+    types.join(" | ")
+}
+
+/// Split on `sep` outside brackets
+fn split_top(text: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in text.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            c if c == sep && depth == 0 => {
+                parts.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(text[start..].trim());
+    parts
+}
+
+fn literal_type(default: &Expr) -> String {
+    match default {
+        Expr::Int(_) | Expr::Float(_) => "number",
+        Expr::String(_) | Expr::FString(_) => "string",
+        Expr::Bool(_) => "boolean",
+        Expr::List(_) => "any[]",
+        Expr::Dict(_) => "Record<string, any>",
+        _ => "any",
+    }.to_string()
+}
+
+const RESERVED: &[&str] = &[
+    "break", "case", "catch", "class", "const", "continue", "debugger", "default", "delete", "do", "else",
+    "enum", "export", "extends", "false", "finally", "for", "function", "if", "import", "in", "instanceof",
+    "new", "null", "return", "super", "switch", "this", "throw", "true", "try", "typeof", "var", "void",
+    "while", "with", "yield", "let", "static", "await", "system",
+];
+
+/// JavaScript identifier for a Poly name
+fn ident(name: &str) -> String {
+    if RESERVED.contains(&name) { format!("{}_", name) } else { name.to_string() }
+}
+
+fn jsdoc(doc: Option<&str>, indent: &str) -> String {
+    match doc {
+        Some(doc) if !doc.contains('\n') => format!("{}/** {} */\n", indent, doc.replace("*/", "*\\/")),
+        Some(doc) => {
+            let lines: Vec<String> = doc.replace("*/", "*\\/").lines().map(|l| format!("{} * {}", indent, l).trim_end().to_string()).collect();
+            format!("{}/**\n{}\n{} */\n", indent, lines.join("\n"), indent)
+        }
+        None => String::new(),
+    }
+}
+
+/// TypeScript type of a command argument
+fn arg_type(kind: ArgKind) -> &'static str {
+    match kind {
+        ArgKind::Any => "any",
+        ArgKind::String => "string",
+        ArgKind::Number => "number",
+        ArgKind::Bool => "boolean",
+        ArgKind::Array => "any[]",
+        ArgKind::Object => "Record<string, any>",
+    }
+}
+
+/// Schema of a system API, or its documented parameters as optional `any`
+/// when no command of that name is registered
+fn system_args(api: &ApiDoc) -> Vec<Arg> {
+    crate::router::args(api.name).unwrap_or_else(|| {
+        api.params.iter().map(|name| Arg { name: name.to_string(), kind: ArgKind::Any, required: false }).collect()
+    })
+}
+
+/// System APIs grouped by namespace, with names inside the namespace
+fn system_apis() -> Vec<(&'static str, Vec<(&'static str, &'static ApiDoc)>)> {
+    crate::register_builtin_commands();
+    let mut namespaces: Vec<(&str, Vec<(&str, &ApiDoc)>)> = Vec::new();
+    for api in POLY_APIS {
+        let method = api.name.strip_prefix("__poly_").and_then(|n| n.strip_prefix(api.namespace)).and_then(|n| n.strip_prefix('_')).unwrap_or(api.name);
+        match namespaces.iter_mut().find(|(ns, _)| *ns == api.namespace) {
+            Some((_, apis)) => apis.push((method, api)),
+            None => namespaces.push((api.namespace, vec![(method, api)])),
+        }
+    }
+    namespaces
+}
+
+fn header(entry: &str) -> String {
+    format!("// Generated by `poly gen bindings` from {}. Do not edit.\n", entry)
+}
+
+/// The `.d.ts` declarations
+pub fn declarations(commands: &[Command], entry: &str) -> String {
+    let mut out = header(entry);
+    for command in commands {
+        let params: Vec<String> = command.params.iter()
+            .map(|p| format!("{}{}: {}", ident(&p.name), if p.optional { "?" } else { "" }, p.ty))
+            .collect();
+        out.push('\n');
+        out.push_str(&jsdoc(command.doc.as_deref(), ""));
+        out.push_str(&format!("export declare function {}({}): Promise<{}>;\n", ident(&command.name), params.join(", "), command.returns));
+    }
+    out.push_str("\n/** Built-in `__poly_*` system APIs */\nexport declare const system: {\n");
+    for (namespace, apis) in system_apis() {
+        out.push_str(&format!("  {}: {{\n", namespace));
+        for (method, api) in apis {
+            out.push_str(&jsdoc(Some(api.summary), "    "));
+            let args = system_args(api);
+            let args = if args.is_empty() {
+                String::new()
+            } else {
+                let keys: Vec<String> = args.iter().map(|arg| format!("{}{}: {}", arg.name, if arg.required { "" } else { "?" }, arg_type(arg.kind))).collect();
+                format!("args{}: {{ {} }}", if args.iter().any(|arg| arg.required) { "" } else { "?" }, keys.join("; "))
+            };
+            out.push_str(&format!("    {}({}): Promise<any>;\n", method, args));
+        }
+        out.push_str("  };\n");
+    }
+    out.push_str("};\n");
+    out
+}
+
+/// The JavaScript client module
+pub fn client(commands: &[Command], entry: &str) -> String {
+    let mut out = header(entry);
+    out.push_str("\nconst invoke = (fn, args) => window.poly.invoke(fn, args);\n");
+    for command in commands {
+        let names: Vec<String> = command.params.iter().map(|p| ident(&p.name)).collect();
+        let fields: Vec<String> = command.params.iter().zip(&names)
+            .map(|(p, name)| if *name == p.name { name.clone() } else { format!("{}: {}", p.name, name) })
+            .collect();
+        out.push('\n');
+        out.push_str(&jsdoc(command.doc.as_deref(), ""));
+        out.push_str(&format!(
+            "export function {}({}) {{\n  return invoke('{}', {{ {} }});\n}}\n",
+            ident(&command.name), names.join(", "), command.name, fields.join(", ")
+        ));
+    }
+    out.push_str("\nexport const system = {\n");
+    for (namespace, apis) in system_apis() {
+        out.push_str(&format!("  {}: {{\n", namespace));
+        for (method, api) in apis {
+            out.push_str(&format!("    {}: (args = {{}}) => invoke('{}', args),\n", method, api.name));
+        }
+        out.push_str("  },\n");
+    }
+    out.push_str("};\n");
+    out
+}
+
+/// Whether `path` is one of the files `write` produces, which file watchers
+/// should not treat as edits
+pub fn is_generated(path: &Path) -> bool {
+    path.file_name().and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix(FILE_STEM))
+        .is_some_and(|ext| ext == ".js" || ext == ".d.ts")
+}
+
+/// Write `poly-bindings.js` and `poly-bindings.d.ts` for `entry` into `out`;
+/// returns the files whose contents changed
+pub fn write(entry: &Path, out: &Path) -> Result<Vec<PathBuf>, String> {
+    let source = std::fs::read_to_string(entry).map_err(|e| format!("{}: {}", entry.display(), e))?;
+    let commands = commands(&source).map_err(|e| format!("{}: {}", entry.display(), e))?;
+    let name = entry.file_name().map_or_else(|| entry.display().to_string(), |n| n.to_string_lossy().to_string());
+    std::fs::create_dir_all(out).map_err(|e| format!("{}: {}", out.display(), e))?;
+    let mut written = Vec::new();
+    for (ext, contents) in [("js", client(&commands, &name)), ("d.ts", declarations(&commands, &name))] {
+        let path = out.join(format!("{}.{}", FILE_STEM, ext));
+        if std::fs::read_to_string(&path).ok().as_deref() != Some(contents.as_str()) {
+            std::fs::write(&path, contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+            written.push(path);
+        }
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commands_and_types() {
+        let source = "fn add(a: int, b: list[str] | none = none) -> dict[str, float]:\n    \"Add things\"\n    return {}\nfn greet(name, loud=false):\n    return name\nfn _private():\n    return 1\nfn delete(id):\n    return id\n";
+        let commands = commands(source).unwrap();
+        assert_eq!(commands.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), vec!["add", "greet", "delete"]);
+        assert_eq!(commands[0].params[1], CommandParam { name: "b".into(), ty: "string[] | null".into(), optional: true });
+        assert_eq!(commands[0].returns, "Record<string, number>");
+        assert_eq!(commands[1].params[1].ty, "boolean");
+        assert_eq!(ts_type("list[int | str]"), "(number | string)[]");
+        assert_eq!(ts_type("tuple[int, Todo]"), "[number, any]");
+
+        let dts = declarations(&commands, "main.poly");
+        assert!(dts.contains("/** Add things */\nexport declare function add(a: number, b?: string[] | null): Promise<Record<string, number>>;"));
+        assert!(dts.contains("    read(args: { path: string }): Promise<any>;"));
+        assert!(dts.contains("    read_dir(args?: { path?: string }): Promise<any>;"));
+        assert!(dts.contains("    write(args: { path: string; content: string }): Promise<any>;"));
+        assert!(dts.contains("    execute(args: { id: number; sql: string; params?: any[] }): Promise<any>;"));
+        assert!(dts.contains("    get(args: { url: string; headers?: Record<string, any>; timeout?: number }): Promise<any>;"));
+        assert!(dts.contains("    platform(): Promise<any>;"));
+        let js = client(&commands, "main.poly");
+        assert!(js.contains("export function greet(name, loud) {\n  return invoke('greet', { name, loud });\n}"));
+        assert!(js.contains("export function delete_(id) {"));
+        assert!(js.contains("    read: (args = {}) => invoke('__poly_fs_read', args),"));
+    }
+}
@@ -0,0 +1,426 @@
+//! Binary transfer between pages and the backend
+//!
+//! IPC replies are JSON, so bytes inside them would have to be base64, a
+//! third larger and held in memory several times over. Binary data moves
+//! over dedicated endpoints of the local server instead:
+//!
+//! - `GET /__poly_file?path=...` streams a file from disk with its content
+//!   type, honouring `Range` so `<video>` and `<audio>` can seek
+//! - `GET /__poly_blob/<id>` serves bytes a command produced in memory (a
+//!   clipboard image, a database blob); such commands return a blob
+//!   reference `{"$blob": url, "size": n, "type": mime}` instead of the bytes
+//! - `PUT /__poly_upload/<id>?offset=n` appends one chunk of an upload to a
+//!   temporary file; `__poly_upload_finish` then moves it into place
+//!
+//! Like other IPC requests, these need the session token.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use serde_json::json;
+
+use crate::ipc::Error;
+use crate::router::{ArgKind, Command};
+use crate::sovereignty::{checks, Permission};
+
+/// Bytes kept for in-memory blobs; the oldest are dropped beyond this
+pub const BLOB_LIMIT: usize = 256 * 1024 * 1024;
+
+/// Largest upload accepted
+pub const UPLOAD_LIMIT: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Uploads that receive no chunk for this long are dropped with their files
+pub const UPLOAD_EXPIRY: Duration = Duration::from_secs(30 * 60);
+
+/// Bytes produced by a command, waiting to be fetched
+#[derive(Debug, PartialEq)]
+pub struct Blob {
+    pub bytes: Vec<u8>,
+    pub content_type: String,
+}
+
+#[derive(Default)]
+struct Store {
+    next_id: u64,
+    blobs: VecDeque<(String, Arc<Blob>)>,
+    size: usize,
+}
+
+static BLOBS: Lazy<Mutex<Store>> = Lazy::new(|| Mutex::new(Store::default()));
+
+/// Keep `bytes` for the page to fetch; returns the blob reference
+pub fn put(bytes: Vec<u8>, content_type: &str) -> serde_json::Value {
+    let size = bytes.len();
+    let mut store = BLOBS.lock().unwrap();
+    store.next_id += 1;
+    let id = format!("{}-{}", store.next_id, crate::crypto::hex_encode(&crate::crypto::random_bytes(8).unwrap_or_default()));
+    store.size += size;
+    store.blobs.push_back((id.clone(), Arc::new(Blob { bytes, content_type: content_type.to_string() })));
+    while store.size > BLOB_LIMIT && store.blobs.len() > 1 {
+        if let Some((_, old)) = store.blobs.pop_front() {
+            store.size -= old.bytes.len();
+        }
+    }
+    json!({"$blob": format!("/__poly_blob/{}", id), "size": size, "type": content_type})
+}
+
+pub fn get(id: &str) -> Option<Arc<Blob>> {
+    BLOBS.lock().unwrap().blobs.iter().find(|(key, _)| key == id).map(|(_, blob)| Arc::clone(blob))
+}
+
+/// Drop a blob by id or by its `/__poly_blob/<id>` URL
+pub fn release(id: &str) -> bool {
+    let id = id.rsplit('/').next().unwrap_or(id);
+    let mut store = BLOBS.lock().unwrap();
+    match store.blobs.iter().position(|(key, _)| key == id) {
+        Some(index) => {
+            let (_, blob) = store.blobs.remove(index).unwrap();
+            store.size -= blob.bytes.len();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Content type for a file name
+pub fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+        Some("html") | Some("htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") | Some("mjs") => "application/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("txt") | Some("md") | Some("csv") => "text/plain; charset=utf-8",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        Some("ico") => "image/x-icon",
+        Some("mp3") => "audio/mpeg",
+        Some("wav") => "audio/wav",
+        Some("ogg") => "audio/ogg",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("pdf") => "application/pdf",
+        Some("zip") => "application/zip",
+        Some("wasm") => "application/wasm",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+/// What a `Range` header asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Range {
+    /// No header, or one this server does not handle (several ranges)
+    Whole,
+    /// Inclusive byte offsets
+    Part(u64, u64),
+    /// Outside the body
+    Unsatisfiable,
+}
+
+pub fn parse_range(header: Option<&str>, size: u64) -> Range {
+    let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else { return Range::Whole };
+    let Some((start, end)) = spec.split_once('-').filter(|_| !spec.contains(',')) else { return Range::Whole };
+    let last = size.saturating_sub(1);
+    let bounds = match (start.trim(), end.trim()) {
+        ("", "") => return Range::Whole,
+        // The last `n` bytes
+        ("", suffix) => suffix.parse::<u64>().ok().filter(|n| *n > 0).map(|n| (size.saturating_sub(n), last)),
+        (start, "") => start.parse().ok().map(|start| (start, last)),
+        (start, end) => start.parse().ok().zip(end.parse::<u64>().ok()).map(|(start, end)| (start, end.min(last))),
+    };
+    match bounds {
+        Some((start, end)) if size > 0 && start < size && start <= end => Range::Part(start, end),
+        _ => Range::Unsatisfiable,
+    }
+}
+
+type Body = Box<dyn Read + Send>;
+
+fn header(name: &str, value: &str) -> tiny_http::Header {
+    tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+fn error_response(status: u16, code: &str, message: &str) -> tiny_http::Response<Body> {
+    let body = json!({"error": message, "code": code}).to_string().into_bytes();
+    let len = body.len();
+    tiny_http::Response::new(status.into(), vec![header("Content-Type", "application/json")], Box::new(std::io::Cursor::new(body)) as Body, Some(len), None)
+}
+
+/// Respond with `size` bytes of `body`, or the part `range` asks for
+fn ranged(mut body: impl Read + Seek + Send + 'static, size: u64, content_type: &str, range: Option<&str>) -> tiny_http::Response<Body> {
+    let mut headers = vec![header("Content-Type", content_type), header("Accept-Ranges", "bytes"), header("Cache-Control", "no-store")];
+    match parse_range(range, size) {
+        Range::Part(start, end) => {
+            if body.seek(SeekFrom::Start(start)).is_err() {
+                return error_response(500, "RUNTIME_ERROR", "Cannot seek");
+            }
+            let len = end - start + 1;
+            headers.push(header("Content-Range", &format!("bytes {}-{}/{}", start, end, size)));
+            tiny_http::Response::new(206.into(), headers, Box::new(body.take(len)) as Body, Some(len as usize), None)
+        }
+        Range::Whole => tiny_http::Response::new(200.into(), headers, Box::new(body) as Body, Some(size as usize), None),
+        Range::Unsatisfiable => {
+            let mut response = error_response(416, "INVALID_RANGE", "Requested range not satisfiable");
+            response.add_header(header("Content-Range", &format!("bytes */{}", size)));
+            response
+        }
+    }
+}
+
+fn query_param(url: &str, key: &str) -> Option<String> {
+    let query = url.split_once('?')?.1;
+    query.split('&')
+        .find_map(|pair| pair.strip_prefix(key).and_then(|rest| rest.strip_prefix('=')))
+        .and_then(|value| urlencoding::decode(value).ok())
+        .map(|value| value.into_owned())
+}
+
+/// Whether `url` is one of the binary endpoints
+pub fn handles(url: &str) -> bool {
+    let path = url.split('?').next().unwrap_or("");
+    path == "/__poly_file" || path.starts_with("/__poly_blob/") || path.starts_with("/__poly_upload/")
+}
+
+/// Answer a request for one of the binary endpoints
+pub fn serve(mut request: tiny_http::Request) {
+    let url = request.url().to_string();
+    let path = url.split('?').next().unwrap_or("").to_string();
+    let range = request.headers().iter()
+        .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case("Range"))
+        .map(|h| h.value.as_str().to_string());
+
+    let response = if path == "/__poly_file" {
+        match query_param(&url, "path") {
+            None => error_response(400, "INVALID_ARGUMENTS", "Missing 'path' parameter"),
+            Some(file) => match checks::fs_read(&file) {
+                Err(e) => error_response(403, "PERMISSION_DENIED", &e),
+                Ok(()) => match File::open(&file).and_then(|f| f.metadata().map(|m| (f, m))) {
+                    Ok((f, meta)) if meta.is_file() => ranged(f, meta.len(), content_type(Path::new(&file)), range.as_deref()),
+                    Ok(_) => error_response(400, "INVALID_ARGUMENTS", "Not a file"),
+                    Err(e) => error_response(404, "NOT_FOUND", &e.to_string()),
+                },
+            },
+        }
+    } else if let Some(id) = path.strip_prefix("/__poly_blob/") {
+        match get(id) {
+            Some(blob) => {
+                let size = blob.bytes.len() as u64;
+                let content_type = blob.content_type.clone();
+                ranged(std::io::Cursor::new(SharedBytes(blob)), size, &content_type, range.as_deref())
+            }
+            None => error_response(404, "NOT_FOUND", "Blob not found or released"),
+        }
+    } else if let Some(id) = path.strip_prefix("/__poly_upload/") {
+        let offset = query_param(&url, "offset").and_then(|o| o.parse().ok()).unwrap_or(0);
+        match append_chunk(id, offset, request.as_reader()) {
+            Ok(received) => {
+                let body = json!({"result": {"received": received}}).to_string().into_bytes();
+                let len = body.len();
+                tiny_http::Response::new(200.into(), vec![header("Content-Type", "application/json")], Box::new(std::io::Cursor::new(body)) as Body, Some(len), None)
+            }
+            Err((status, e)) => error_response(status, e.code, &e.message),
+        }
+    } else {
+        error_response(404, "NOT_FOUND", "Unknown endpoint")
+    };
+    let _ = request.respond(response);
+}
+
+/// Blob bytes shared with the store while a response streams them
+struct SharedBytes(Arc<Blob>);
+
+impl AsRef<[u8]> for SharedBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0.bytes
+    }
+}
+
+// ============================================================================
+// Uploads
+// ============================================================================
+
+struct Upload {
+    file: PathBuf,
+    received: u64,
+    touched: Instant,
+    /// Set once finished, cancelled or expired, for a chunk that was waiting
+    closed: bool,
+}
+
+/// Each upload has its own lock, held while a chunk is checked, written and counted
+static UPLOADS: Lazy<Mutex<HashMap<String, Arc<Mutex<Upload>>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn unknown_upload() -> (u16, Error) {
+    (404, Error::new("NOT_FOUND", "Unknown upload"))
+}
+
+/// Take an upload out of the registry and close it, after any chunk being
+/// written; returns its file and size
+fn take_upload(id: &str) -> Option<(PathBuf, u64)> {
+    let upload = UPLOADS.lock().unwrap().remove(id)?;
+    let mut upload = upload.lock().unwrap_or_else(|e| e.into_inner());
+    upload.closed = true;
+    Some((upload.file.clone(), upload.received))
+}
+
+/// Drop uploads idle for longer than `max_idle` and delete their files
+fn expire_uploads(max_idle: Duration) {
+    let idle: Vec<String> = UPLOADS.lock().unwrap().iter()
+        .filter(|(_, upload)| upload.try_lock().is_ok_and(|u| u.touched.elapsed() > max_idle))
+        .map(|(id, _)| id.clone())
+        .collect();
+    for id in idle {
+        if let Some((file, _)) = take_upload(&id) {
+            std::fs::remove_file(file).ok();
+        }
+    }
+}
+
+fn upload_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("poly-uploads-{}", std::process::id()))
+}
+
+/// Begin an upload; returns its id. Abandoned uploads are cleaned up here.
+pub fn start_upload() -> Result<String, String> {
+    expire_uploads(UPLOAD_EXPIRY);
+    let id = crate::crypto::hex_encode(&crate::crypto::random_bytes(16)?);
+    std::fs::create_dir_all(upload_dir()).map_err(|e| e.to_string())?;
+    let file = upload_dir().join(&id);
+    File::create(&file).map_err(|e| e.to_string())?;
+    let upload = Upload { file, received: 0, touched: Instant::now(), closed: false };
+    UPLOADS.lock().unwrap().insert(id.clone(), Arc::new(Mutex::new(upload)));
+    Ok(id)
+}
+
+/// Append a chunk that starts at `offset`; returns the bytes received so far.
+/// A chunk at the wrong offset is refused with 409 so the client can resume,
+/// and one that would take the upload past [`UPLOAD_LIMIT`] with 413.
+pub fn append_chunk(id: &str, offset: u64, chunk: &mut dyn Read) -> Result<u64, (u16, Error)> {
+    append_limited(id, offset, chunk, UPLOAD_LIMIT)
+}
+
+fn append_limited(id: &str, offset: u64, chunk: &mut dyn Read, limit: u64) -> Result<u64, (u16, Error)> {
+    let upload = UPLOADS.lock().unwrap().get(id).cloned().ok_or_else(unknown_upload)?;
+    let mut upload = upload.lock().unwrap_or_else(|e| e.into_inner());
+    if upload.closed {
+        return Err(unknown_upload());
+    }
+    if upload.received != offset {
+        return Err((409, Error::new("INVALID_ARGUMENTS", format!("Expected offset {}", upload.received))));
+    }
+    let runtime = |e: std::io::Error| (500, Error::new("RUNTIME_ERROR", e.to_string()));
+    let mut out = std::fs::OpenOptions::new().append(true).open(&upload.file).map_err(runtime)?;
+    // One byte past the limit is enough to tell that the chunk is too large
+    let room = limit - upload.received;
+    let written = std::io::copy(&mut chunk.take(room + 1), &mut out).map_err(runtime)?;
+    upload.touched = Instant::now();
+    if written > room {
+        out.set_len(upload.received).map_err(runtime)?;
+        return Err((413, Error::new("INVALID_ARGUMENTS", format!("Upload exceeds {} bytes", limit))));
+    }
+    upload.received += written;
+    Ok(upload.received)
+}
+
+/// Move a finished upload to `dest`; returns its size
+pub fn finish_upload(id: &str, dest: &Path) -> Result<u64, String> {
+    let (file, received) = take_upload(id).ok_or("Unknown upload")?;
+    if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    // rename fails across file systems; copy then
+    if std::fs::rename(&file, dest).is_err() {
+        std::fs::copy(&file, dest).map_err(|e| e.to_string())?;
+        std::fs::remove_file(&file).ok();
+    }
+    Ok(received)
+}
+
+pub fn cancel_upload(id: &str) -> bool {
+    match take_upload(id) {
+        Some((file, _)) => {
+            std::fs::remove_file(file).ok();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Register the upload and blob commands with the IPC router
+pub fn register_commands() {
+    let runtime = |e: String| Error::new("RUNTIME_ERROR", e);
+    crate::router::register(Command::new("__poly_upload_start", move |_| start_upload().map(|id| json!({"id": id})).map_err(runtime)));
+    crate::router::register(
+        Command::new("__poly_upload_finish", move |call| {
+            let path = call.str("path").unwrap_or_default();
+            finish_upload(call.str("id").unwrap_or_default(), Path::new(path))
+                .map(|size| json!({"path": path, "size": size}))
+                .map_err(runtime)
+        })
+        .arg("id", ArgKind::String)
+        .arg("path", ArgKind::String)
+        .permission_for(|args| Some(Permission::FsWrite(checks::path_to_scope(args["path"].as_str().unwrap_or(""))))),
+    );
+    crate::router::register(Command::new("__poly_upload_cancel", |call| Ok(json!(cancel_upload(call.str("id").unwrap_or_default())))).arg("id", ArgKind::String));
+    crate::router::register(Command::new("__poly_blob_release", |call| Ok(json!(release(call.str("url").unwrap_or_default())))).arg("url", ArgKind::String));
+    crate::router::register(
+        Command::new("__poly_clipboard_read_image", move |_| crate::clipboard::read_image().map(|png| put(png, "image/png")).map_err(runtime))
+            .permission(Permission::ClipboardRead),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!(parse_range(None, 100), Range::Whole);
+        assert_eq!(parse_range(Some("bytes=0-9"), 100), Range::Part(0, 9));
+        assert_eq!(parse_range(Some("bytes=90-"), 100), Range::Part(90, 99));
+        assert_eq!(parse_range(Some("bytes=-10"), 100), Range::Part(90, 99));
+        assert_eq!(parse_range(Some("bytes=50-500"), 100), Range::Part(50, 99));
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), Range::Whole);
+        assert_eq!(parse_range(Some("bytes=100-"), 100), Range::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=9-2"), 100), Range::Unsatisfiable);
+    }
+
+    #[test]
+    fn test_blobs_and_chunked_upload() {
+        let reference = put(vec![1, 2, 3], "image/png");
+        let url = reference["$blob"].as_str().unwrap();
+        let id = url.strip_prefix("/__poly_blob/").unwrap();
+        assert_eq!(get(id).unwrap().bytes, vec![1, 2, 3]);
+        assert!(release(url));
+        assert!(get(id).is_none());
+
+        let upload = start_upload().unwrap();
+        assert_eq!(append_chunk(&upload, 0, &mut &b"hello "[..]).unwrap(), 6);
+        assert_eq!(append_chunk(&upload, 0, &mut &b"again"[..]).unwrap_err().0, 409);
+        assert_eq!(append_chunk(&upload, 6, &mut &b"world"[..]).unwrap(), 11);
+        let dest = upload_dir().join("done.txt");
+        assert_eq!(finish_upload(&upload, &dest).unwrap(), 11);
+        assert_eq!(std::fs::read_to_string(&dest).unwrap(), "hello world");
+        std::fs::remove_file(dest).ok();
+        assert_eq!(append_chunk(&upload, 11, &mut &b"!"[..]).unwrap_err().0, 404);
+
+        let upload = start_upload().unwrap();
+        assert_eq!(append_limited(&upload, 0, &mut &b"1234"[..], 6).unwrap(), 4);
+        assert_eq!(append_limited(&upload, 4, &mut &b"567"[..], 6).unwrap_err().0, 413);
+        assert_eq!(append_limited(&upload, 4, &mut &b"56"[..], 6).unwrap(), 6);
+        let file = upload_dir().join(&upload);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "123456");
+        expire_uploads(Duration::ZERO);
+        assert_eq!(append_chunk(&upload, 6, &mut &b"7"[..]).unwrap_err().0, 404);
+        assert!(!file.exists());
+    }
+}
@@ -6,6 +6,8 @@
 use std::path::Path;
 use std::fs;
 
+use toml::Value;
+
 /// Complete Poly project configuration from poly.toml
 #[derive(Debug, Clone)]
 pub struct PolyConfig {
@@ -18,6 +20,10 @@ pub struct PolyConfig {
     pub tray: TrayConfig,
     pub build: BuildConfig,
     pub browser: BrowserConfig,
+    pub lint: LintConfig,
+    pub coverage: CoverageConfig,
+    pub ipc: IpcConfig,
+    pub server: ServerConfig,
 }
 
 /// [package] section
@@ -63,6 +69,8 @@ pub struct DevConfig {
     pub reload_interval: u32,
     pub inject_alpine: bool,
     pub inject_lucide: bool,
+    /// Global dict whose values survive backend hot reloads ("" to disable)
+    pub preserve_state: String,
 }
 
 /// [network] section - HTTP client settings
@@ -106,6 +114,57 @@ pub struct BrowserConfig {
     pub height: u32,
 }
 
+/// [ipc] section - how calls from pages into Poly functions run
+#[derive(Debug, Clone)]
+pub struct IpcConfig {
+    /// Interpreters serving calls at once; each runs the entry module itself
+    pub workers: usize,
+    /// Calls allowed to wait for a worker before new ones are refused
+    pub queue_limit: usize,
+}
+
+/// [server] section - where `poly run` serves the routes the app declares
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+}
+
+/// [lint] section - `poly lint` rule levels and extra known globals
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    /// Rule name -> "off", "warning" or "error"
+    pub rules: std::collections::HashMap<String, String>,
+    /// Names defined at runtime that the linter cannot see (e.g. injected by the host)
+    pub globals: Vec<String>,
+}
+
+/// [coverage] section - minimum coverage per file for `poly test --coverage`,
+/// with per-file overrides in [coverage.files]
+#[derive(Debug, Clone, Default)]
+pub struct CoverageConfig {
+    /// Minimum line coverage in percent
+    pub lines: Option<f64>,
+    /// Minimum branch coverage in percent
+    pub branches: Option<f64>,
+    /// File, or directory ending in `/`, -> (lines, branches) overriding the defaults
+    pub files: Vec<(String, Option<f64>, Option<f64>)>,
+}
+
+impl CoverageConfig {
+    /// Line and branch thresholds for a file path relative to the project
+    pub fn thresholds(&self, file: &str) -> (Option<f64>, Option<f64>) {
+        let matching = self.files.iter().filter(|(pattern, _, _)| {
+            pattern == file || (pattern.ends_with('/') && file.starts_with(pattern.as_str()))
+        });
+        // The most specific (longest) pattern wins
+        match matching.max_by_key(|(pattern, _, _)| pattern.len()) {
+            Some((_, lines, branches)) => (lines.or(self.lines), branches.or(self.branches)),
+            None => (self.lines, self.branches),
+        }
+    }
+}
+
 /// Predefined window configuration
 #[derive(Debug, Clone)]
 pub struct PredefinedWindow {
@@ -148,6 +207,10 @@ impl Default for PolyConfig {
             tray: TrayConfig::default(),
             build: BuildConfig::default(),
             browser: BrowserConfig::default(),
+            lint: LintConfig::default(),
+            coverage: CoverageConfig::default(),
+            ipc: IpcConfig::default(),
+            server: ServerConfig::default(),
         }
     }
 }
@@ -201,6 +264,7 @@ impl Default for DevConfig {
             reload_interval: 2000,
             inject_alpine: false,
             inject_lucide: false,
+            preserve_state: "state".to_string(),
         }
     }
 }
@@ -256,6 +320,24 @@ impl Default for BrowserConfig {
     }
 }
 
+impl Default for IpcConfig {
+    fn default() -> Self {
+        Self {
+            workers: 1,
+            queue_limit: 1000,
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 8000,
+        }
+    }
+}
+
 impl PolyConfig {
     /// Load configuration from poly.toml file
     pub fn load(path: &Path) -> Self {
@@ -277,37 +359,39 @@ impl PolyConfig {
         Self::parse(&content)
     }
     
-    /// Parse configuration from TOML content
+    /// Parse configuration from TOML content; invalid TOML gives the defaults
     pub fn parse(content: &str) -> Self {
         let mut config = Self::default();
-        let mut current_section = String::new();
-        
-        for line in content.lines() {
-            let line = line.trim();
-            
-            // Skip comments and empty lines
-            if line.is_empty() || line.starts_with('#') {
-                continue;
+        let table = match content.parse::<toml::Table>() {
+            Ok(table) => table,
+            Err(e) => {
+                eprintln!("warning: poly.toml: {}", e.message());
+                return config;
             }
-            
-            // Section header
-            if line.starts_with('[') && line.ends_with(']') {
-                current_section = line[1..line.len()-1].to_string();
-                continue;
-            }
-            
-            // Key = value
-            if let Some((key, value)) = parse_key_value(line) {
-                match current_section.as_str() {
-                    "package" => config.parse_package(&key, &value),
-                    "web" => config.parse_web(&key, &value),
-                    "window" => config.parse_window(&key, &value),
-                    "dev" => config.parse_dev(&key, &value),
-                    "network" => config.parse_network(&key, &value),
-                    "app" => config.parse_app(&key, &value),
-                    "tray" => config.parse_tray(&key, &value),
-                    "build" => config.parse_build(&key, &value),
-                    "browser" => config.parse_browser(&key, &value),
+        };
+        
+        for (section, values) in &table {
+            let Some(values) = values.as_table() else { continue };
+            for (key, value) in values {
+                match section.as_str() {
+                    "package" => config.parse_package(key, value),
+                    "web" => config.parse_web(key, value),
+                    "window" => config.parse_window(key, value),
+                    "dev" => config.parse_dev(key, value),
+                    "network" => config.parse_network(key, value),
+                    "app" => config.parse_app(key, value),
+                    "tray" => config.parse_tray(key, value),
+                    "build" => config.parse_build(key, value),
+                    "browser" => config.parse_browser(key, value),
+                    "lint" => config.parse_lint(key, value),
+                    "coverage" if key == "files" => {
+                        for (file, thresholds) in value.as_table().into_iter().flatten() {
+                            config.parse_coverage_file(file, thresholds);
+                        }
+                    }
+                    "coverage" => config.parse_coverage(key, value),
+                    "ipc" => config.parse_ipc(key, value),
+                    "server" => config.parse_server(key, value),
                     _ => {}
                 }
             }
@@ -316,101 +400,144 @@ impl PolyConfig {
         config
     }
     
-    fn parse_package(&mut self, key: &str, value: &str) {
+    fn parse_package(&mut self, key: &str, value: &Value) {
+        match key {
+            "name" => self.package.name = text(value).unwrap_or_default(),
+            "version" => self.package.version = text(value).unwrap_or_default(),
+            _ => {}
+        }
+    }
+    
+    fn parse_web(&mut self, key: &str, value: &Value) {
         match key {
-            "name" => self.package.name = value.to_string(),
-            "version" => self.package.version = value.to_string(),
+            "dir" => self.web.dir = text(value).unwrap_or_default(),
+            "packages_dir" => self.web.packages_dir = text(value).unwrap_or_default(),
             _ => {}
         }
     }
     
-    fn parse_web(&mut self, key: &str, value: &str) {
+    fn parse_window(&mut self, key: &str, value: &Value) {
         match key {
-            "dir" => self.web.dir = value.to_string(),
-            "packages_dir" => self.web.packages_dir = value.to_string(),
+            "title" => self.window.title = text(value),
+            "width" => self.window.width = number(value).unwrap_or(1024),
+            "height" => self.window.height = number(value).unwrap_or(768),
+            "resizable" => self.window.resizable = flag(value),
+            "background_color" => self.window.background_color = text(value).unwrap_or_default(),
+            "transparent" => self.window.transparent = flag(value),
+            "decorations" => self.window.decorations = flag(value),
+            "always_on_top" => self.window.always_on_top = flag(value),
+            "fullscreen" => self.window.fullscreen = flag(value),
+            "min_width" => self.window.min_width = number(value),
+            "min_height" => self.window.min_height = number(value),
+            "max_width" => self.window.max_width = number(value),
+            "max_height" => self.window.max_height = number(value),
+            "default_popup_width" => self.window.default_popup_width = number(value).unwrap_or(800),
+            "default_popup_height" => self.window.default_popup_height = number(value).unwrap_or(600),
+            "icon" | "icon_path" => self.window.icon_path = text(value),
             _ => {}
         }
     }
     
-    fn parse_window(&mut self, key: &str, value: &str) {
+    fn parse_dev(&mut self, key: &str, value: &Value) {
         match key {
-            "title" => self.window.title = Some(value.to_string()),
-            "width" => self.window.width = value.parse().unwrap_or(1024),
-            "height" => self.window.height = value.parse().unwrap_or(768),
-            "resizable" => self.window.resizable = value == "true",
-            "background_color" => self.window.background_color = value.to_string(),
-            "transparent" => self.window.transparent = value == "true",
-            "decorations" => self.window.decorations = value == "true",
-            "always_on_top" => self.window.always_on_top = value == "true",
-            "fullscreen" => self.window.fullscreen = value == "true",
-            "min_width" => self.window.min_width = value.parse().ok(),
-            "min_height" => self.window.min_height = value.parse().ok(),
-            "max_width" => self.window.max_width = value.parse().ok(),
-            "max_height" => self.window.max_height = value.parse().ok(),
-            "default_popup_width" => self.window.default_popup_width = value.parse().unwrap_or(800),
-            "default_popup_height" => self.window.default_popup_height = value.parse().unwrap_or(600),
-            "icon" | "icon_path" => self.window.icon_path = Some(value.to_string()),
+            "port" => self.dev.port = number(value).unwrap_or(3000),
+            "devtools" => self.dev.devtools = flag(value),
+            "reload_interval" => self.dev.reload_interval = number(value).unwrap_or(2000),
+            "inject_alpine" => self.dev.inject_alpine = flag(value),
+            "inject_lucide" => self.dev.inject_lucide = flag(value),
+            "preserve_state" => self.dev.preserve_state = text(value).unwrap_or_default(),
             _ => {}
         }
     }
     
-    fn parse_dev(&mut self, key: &str, value: &str) {
+    fn parse_ipc(&mut self, key: &str, value: &Value) {
         match key {
-            "port" => self.dev.port = value.parse().unwrap_or(3000),
-            "devtools" => self.dev.devtools = value == "true",
-            "reload_interval" => self.dev.reload_interval = value.parse().unwrap_or(2000),
-            "inject_alpine" => self.dev.inject_alpine = value == "true",
-            "inject_lucide" => self.dev.inject_lucide = value == "true",
+            "workers" => self.ipc.workers = number(value).unwrap_or(1).max(1),
+            "queue_limit" => self.ipc.queue_limit = number(value).unwrap_or(1000),
             _ => {}
         }
     }
     
-    fn parse_network(&mut self, key: &str, value: &str) {
+    fn parse_server(&mut self, key: &str, value: &Value) {
         match key {
-            "timeout" => self.network.timeout = value.parse().unwrap_or(30),
-            "user_agent" => self.network.user_agent = Some(value.to_string()),
-            "max_body_size" => self.network.max_body_size = value.parse().unwrap_or(50_000_000),
+            "host" => self.server.host = text(value).unwrap_or_default(),
+            "port" => self.server.port = number(value).unwrap_or(8000),
             _ => {}
         }
     }
     
-    fn parse_app(&mut self, key: &str, value: &str) {
+    fn parse_network(&mut self, key: &str, value: &Value) {
         match key {
-            "notification_timeout" => self.app.notification_timeout = value.parse().unwrap_or(5000),
+            "timeout" => self.network.timeout = number(value).unwrap_or(30),
+            "user_agent" => self.network.user_agent = text(value),
+            "max_body_size" => self.network.max_body_size = number(value).unwrap_or(50_000_000),
             _ => {}
         }
     }
     
-    fn parse_tray(&mut self, key: &str, value: &str) {
+    fn parse_app(&mut self, key: &str, value: &Value) {
         match key {
-            "enabled" => self.tray.enabled = value == "true",
-            "tooltip" => self.tray.tooltip = Some(value.to_string()),
-            "icon" | "icon_path" => self.tray.icon_path = Some(value.to_string()),
-            "icon_size" => self.tray.icon_size = value.parse().unwrap_or(32),
-            "minimize_to_tray" => self.tray.minimize_to_tray = value == "true",
-            "close_to_tray" => self.tray.close_to_tray = value == "true",
+            "notification_timeout" => self.app.notification_timeout = number(value).unwrap_or(5000),
             _ => {}
         }
     }
     
-    fn parse_build(&mut self, key: &str, value: &str) {
+    fn parse_tray(&mut self, key: &str, value: &Value) {
         match key {
-            "icon_size" => self.build.icon_size = value.parse().unwrap_or(64),
-            "icon" | "icon_path" => self.build.icon_path = Some(value.to_string()),
+            "enabled" => self.tray.enabled = flag(value),
+            "tooltip" => self.tray.tooltip = text(value),
+            "icon" | "icon_path" => self.tray.icon_path = text(value),
+            "icon_size" => self.tray.icon_size = number(value).unwrap_or(32),
+            "minimize_to_tray" => self.tray.minimize_to_tray = flag(value),
+            "close_to_tray" => self.tray.close_to_tray = flag(value),
             _ => {}
         }
     }
     
-    fn parse_browser(&mut self, key: &str, value: &str) {
+    fn parse_build(&mut self, key: &str, value: &Value) {
+        match key {
+            "icon_size" => self.build.icon_size = number(value).unwrap_or(64),
+            "icon" | "icon_path" => self.build.icon_path = text(value),
+            _ => {}
+        }
+    }
+    
+    fn parse_browser(&mut self, key: &str, value: &Value) {
         self.browser.enabled = true;
         match key {
-            "ui_height" => self.browser.ui_height = value.parse().unwrap_or(80),
-            "width" => self.browser.width = value.parse().unwrap_or(1200),
-            "height" => self.browser.height = value.parse().unwrap_or(800),
+            "ui_height" => self.browser.ui_height = number(value).unwrap_or(80),
+            "width" => self.browser.width = number(value).unwrap_or(1200),
+            "height" => self.browser.height = number(value).unwrap_or(800),
+            _ => {}
+        }
+    }
+    
+    fn parse_lint(&mut self, key: &str, value: &Value) {
+        match key {
+            "globals" => self.lint.globals = strings(value),
+            rule => {
+                self.lint.rules.insert(rule.to_string(), text(value).unwrap_or_default().to_lowercase());
+            }
+        }
+    }
+    
+    fn parse_coverage(&mut self, key: &str, value: &Value) {
+        match key {
+            "lines" => self.coverage.lines = percent(value),
+            "branches" => self.coverage.branches = percent(value),
             _ => {}
         }
     }
     
+    /// `"src/app.poly" = 90` (lines) or `"src/legacy/" = { lines = 50, branches = 0 }`
+    fn parse_coverage_file(&mut self, file: &str, value: &Value) {
+        let (lines, branches) = match value.as_table() {
+            Some(table) => (table.get("lines").and_then(percent), table.get("branches").and_then(percent)),
+            None => (percent(value), None),
+        };
+        self.coverage.files.push((file.to_string(), lines, branches));
+    }
+    
     /// Get the effective window title
     pub fn get_title(&self) -> &str {
         self.window.title.as_deref().unwrap_or(&self.package.name)
@@ -435,23 +562,35 @@ impl PolyConfig {
     }
 }
 
-/// Parse a key = value line
-fn parse_key_value(line: &str) -> Option<(String, String)> {
-    let parts: Vec<&str> = line.splitn(2, '=').collect();
-    if parts.len() != 2 {
-        return None;
-    }
-    
-    let key = parts[0].trim().to_string();
-    let mut value = parts[1].trim().to_string();
-    
-    // Remove quotes from string values
-    if (value.starts_with('"') && value.ends_with('"')) ||
-       (value.starts_with('\'') && value.ends_with('\'')) {
-        value = value[1..value.len()-1].to_string();
+/// A string setting
+fn text(value: &Value) -> Option<String> {
+    value.as_str().map(str::to_string)
+}
+
+/// A whole number setting, also accepted as a string (`port = "4000"`)
+fn number<T: TryFrom<i64> + std::str::FromStr>(value: &Value) -> Option<T> {
+    match value {
+        Value::Integer(n) => T::try_from(*n).ok(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
     }
-    
-    Some((key, value))
+}
+
+fn percent(value: &Value) -> Option<f64> {
+    value.as_float().or_else(|| value.as_integer().map(|n| n as f64))
+}
+
+fn flag(value: &Value) -> bool {
+    value.as_bool().unwrap_or(false)
+}
+
+/// An array of strings; other items are skipped
+fn strings(value: &Value) -> Vec<String> {
+    value.as_array().into_iter().flatten()
+        .filter_map(|item| item.as_str())
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
 }
 
 /// Parse hex color string to RGBA
@@ -507,6 +646,15 @@ mod tests {
         assert_eq!(config.dev.port, 4000);
         assert_eq!(config.dev.devtools, true);
         assert_eq!(config.network.timeout, 60);
+
+        let toml = "[lint]\nglobals = [\"host_value\", \"env\"]\nunused-variable = \"Error\"\n\n[coverage]\nlines = 80\n\n[coverage.files]\n\"src/app.poly\" = 90.5\n\"src/legacy/\" = { lines = 50, branches = 0 }\n\n[window]\ntitle = \"A = B # not a comment\"\nwidth = \"900\"\n";
+        let config = PolyConfig::parse(toml);
+        assert_eq!(config.lint.globals, vec!["host_value", "env"]);
+        assert_eq!(config.lint.rules["unused-variable"], "error");
+        assert_eq!(config.coverage.thresholds("src/app.poly"), (Some(90.5), None));
+        assert_eq!(config.coverage.thresholds("src/legacy/old.poly"), (Some(50.0), Some(0.0)));
+        assert_eq!(config.window.title.as_deref(), Some("A = B # not a comment"));
+        assert_eq!(config.window.width, 900);
     }
     
     #[test]
@@ -0,0 +1,377 @@
+//! Line and branch coverage for `poly test --coverage`
+//!
+//! Programs are parsed with `Parser::with_lines` and `Parser::with_source`,
+//! so the interpreter reports every statement together with the file it
+//! came from, even for functions imported from another module. `if`/`elif`/
+//! `else`, `while` and `for` also report which branch they took. Results are
+//! written as LCOV and Cobertura XML and checked against the `[coverage]`
+//! thresholds in poly.toml.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::ast::{Program, Statement};
+use crate::config::CoverageConfig;
+
+/// Hits for one file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileCoverage {
+    /// Executable line -> times it ran
+    pub lines: BTreeMap<usize, u64>,
+    /// Line of an `if`, `while` or `for` -> times each branch was taken.
+    /// An `if` has one branch per body plus one for falling through (or
+    /// `else`); loops have "entered the body" and "left the loop".
+    pub branches: BTreeMap<usize, Vec<u64>>,
+}
+
+impl FileCoverage {
+    pub fn lines_hit(&self) -> usize {
+        self.lines.values().filter(|hits| **hits > 0).count()
+    }
+
+    pub fn branches_total(&self) -> usize {
+        self.branches.values().map(|arms| arms.len()).sum()
+    }
+
+    pub fn branches_hit(&self) -> usize {
+        self.branches.values().flatten().filter(|hits| **hits > 0).count()
+    }
+
+    /// Percentage of lines run (100 for a file without statements)
+    pub fn line_percent(&self) -> f64 {
+        percent(self.lines_hit(), self.lines.len())
+    }
+
+    pub fn branch_percent(&self) -> f64 {
+        percent(self.branches_hit(), self.branches_total())
+    }
+
+    /// Lines never run, as `3, 7-9`
+    pub fn missing(&self) -> String {
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        let mut previous_hit = true;
+        for (&line, &hits) in &self.lines {
+            if hits == 0 {
+                match ranges.last_mut() {
+                    Some(range) if !previous_hit => range.1 = line,
+                    _ => ranges.push((line, line)),
+                }
+            }
+            previous_hit = hits > 0;
+        }
+        ranges
+            .iter()
+            .map(|(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+fn percent(hit: usize, total: usize) -> f64 {
+    if total == 0 {
+        100.0
+    } else {
+        hit as f64 * 100.0 / total as f64
+    }
+}
+
+fn register_statements(stmts: &[Statement], file: &mut FileCoverage) {
+    let mut line = None;
+    for stmt in stmts {
+        match stmt {
+            Statement::Line(n) => {
+                line = Some(*n);
+                file.lines.entry(*n).or_insert(0);
+            }
+            Statement::If { then_body, elif_branches, else_body, .. } => {
+                if let Some(line) = line {
+                    file.branches.entry(line).or_insert_with(|| vec![0; elif_branches.len() + 2]);
+                }
+                register_statements(then_body, file);
+                for (_, body) in elif_branches {
+                    register_statements(body, file);
+                }
+                if let Some(body) = else_body {
+                    register_statements(body, file);
+                }
+            }
+            Statement::While { body, .. } | Statement::For { body, .. } => {
+                if let Some(line) = line {
+                    file.branches.entry(line).or_insert_with(|| vec![0; 2]);
+                }
+                register_statements(body, file);
+            }
+            Statement::FnDef { body, .. } | Statement::With { body, .. } => register_statements(body, file),
+            Statement::Try { try_body, except_body, .. } => {
+                register_statements(try_body, file);
+                register_statements(except_body, file);
+            }
+            Statement::ClassDef { methods, .. } => {
+                for method in methods {
+                    register_statements(&method.body, file);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    /// File name (as given to `Parser::with_source`) -> hits
+    pub files: BTreeMap<String, FileCoverage>,
+    /// Files of the code currently running, with the scope depth each started at
+    active: Vec<(usize, String)>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the executable lines and branches of a program, so code that
+    /// never runs shows up as missed
+    pub fn register(&mut self, file: &str, program: &Program) {
+        register_statements(&program.statements, self.files.entry(file.to_string()).or_default());
+    }
+
+    /// A `Statement::Source` marker ran at scope `depth`
+    pub fn enter(&mut self, file: &str, depth: usize) {
+        self.active.retain(|(d, _)| *d < depth);
+        self.active.push((depth, file.to_string()));
+    }
+
+    /// The running files, to restore after executing another module in the
+    /// same scope (`import`)
+    pub fn active_files(&self) -> Vec<(usize, String)> {
+        self.active.clone()
+    }
+
+    pub fn set_active_files(&mut self, active: Vec<(usize, String)>) {
+        self.active = active;
+    }
+
+    fn file(&mut self, depth: usize) -> Option<&mut FileCoverage> {
+        self.active.retain(|(d, _)| *d <= depth);
+        let (_, name) = self.active.last()?;
+        Some(self.files.entry(name.clone()).or_default())
+    }
+
+    /// A statement on `line` is about to run at scope `depth`
+    pub fn line(&mut self, line: usize, depth: usize) {
+        if let Some(file) = self.file(depth) {
+            *file.lines.entry(line).or_insert(0) += 1;
+        }
+    }
+
+    /// The `if`/`while`/`for` on `line` took branch `arm` of `arms`
+    pub fn branch(&mut self, line: usize, depth: usize, arm: usize, arms: usize) {
+        if let Some(file) = self.file(depth) {
+            let hits = file.branches.entry(line).or_default();
+            if hits.len() < arms {
+                hits.resize(arms, 0);
+            }
+            hits[arm] += 1;
+        }
+    }
+
+    /// Add the hits of another run (e.g. a test on another worker)
+    pub fn merge(&mut self, other: &Coverage) {
+        for (name, theirs) in &other.files {
+            let ours = self.files.entry(name.clone()).or_default();
+            for (line, hits) in &theirs.lines {
+                *ours.lines.entry(*line).or_insert(0) += hits;
+            }
+            for (line, arms) in &theirs.branches {
+                let hits = ours.branches.entry(*line).or_default();
+                if hits.len() < arms.len() {
+                    hits.resize(arms.len(), 0);
+                }
+                for (total, hit) in hits.iter_mut().zip(arms) {
+                    *total += hit;
+                }
+            }
+        }
+    }
+
+    fn totals(&self) -> (usize, usize, usize, usize) {
+        self.files.values().fold((0, 0, 0, 0), |(lh, lt, bh, bt), f| {
+            (lh + f.lines_hit(), lt + f.lines.len(), bh + f.branches_hit(), bt + f.branches_total())
+        })
+    }
+
+    /// Terminal table: coverage per file and the lines never run
+    pub fn summary(&self) -> String {
+        let width = self.files.keys().map(|name| name.len()).max().unwrap_or(0).max(5);
+        let mut out = format!("{:<width$}  {:>7}  {:>8}  missing\n", "file", "lines", "branches", width = width);
+        for (name, file) in &self.files {
+            out.push_str(&format!(
+                "{:<width$}  {:>6.1}%  {:>7.1}%  {}\n",
+                name,
+                file.line_percent(),
+                file.branch_percent(),
+                file.missing(),
+                width = width
+            ));
+        }
+        let (lh, lt, bh, bt) = self.totals();
+        out.push_str(&format!("{:<width$}  {:>6.1}%  {:>7.1}%\n", "total", percent(lh, lt), percent(bh, bt), width = width));
+        out
+    }
+
+    pub fn to_lcov(&self) -> String {
+        let mut out = String::new();
+        for (name, file) in &self.files {
+            out.push_str(&format!("TN:\nSF:{}\n", name));
+            for (line, arms) in &file.branches {
+                let reached = file.lines.get(line).is_some_and(|hits| *hits > 0);
+                for (arm, hits) in arms.iter().enumerate() {
+                    let taken = if reached { hits.to_string() } else { "-".to_string() };
+                    out.push_str(&format!("BRDA:{},0,{},{}\n", line, arm, taken));
+                }
+            }
+            out.push_str(&format!("BRF:{}\nBRH:{}\n", file.branches_total(), file.branches_hit()));
+            for (line, hits) in &file.lines {
+                out.push_str(&format!("DA:{},{}\n", line, hits));
+            }
+            out.push_str(&format!("LF:{}\nLH:{}\nend_of_record\n", file.lines.len(), file.lines_hit()));
+        }
+        out
+    }
+
+    /// Cobertura XML, one class per file, with `source` as the base directory
+    pub fn to_cobertura(&self, source: &str) -> String {
+        let rate = |hit: usize, total: usize| format!("{:.4}", percent(hit, total) / 100.0);
+        let (lh, lt, bh, bt) = self.totals();
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<coverage line-rate=\"{}\" branch-rate=\"{}\" lines-covered=\"{}\" lines-valid=\"{}\" branches-covered=\"{}\" branches-valid=\"{}\" complexity=\"0\" version=\"{}\" timestamp=\"{}\">\n",
+            rate(lh, lt), rate(bh, bt), lh, lt, bh, bt, env!("CARGO_PKG_VERSION"), timestamp
+        ));
+        xml.push_str(&format!("  <sources>\n    <source>{}</source>\n  </sources>\n  <packages>\n", xml_escape(source)));
+        xml.push_str(&format!("    <package name=\"poly\" line-rate=\"{}\" branch-rate=\"{}\" complexity=\"0\">\n      <classes>\n", rate(lh, lt), rate(bh, bt)));
+        for (name, file) in &self.files {
+            xml.push_str(&format!(
+                "        <class name=\"{0}\" filename=\"{0}\" line-rate=\"{1}\" branch-rate=\"{2}\" complexity=\"0\">\n          <methods/>\n          <lines>\n",
+                xml_escape(name),
+                rate(file.lines_hit(), file.lines.len()),
+                rate(file.branches_hit(), file.branches_total())
+            ));
+            for (line, hits) in &file.lines {
+                match file.branches.get(line) {
+                    Some(arms) => {
+                        let taken = arms.iter().filter(|h| **h > 0).count();
+                        xml.push_str(&format!(
+                            "            <line number=\"{}\" hits=\"{}\" branch=\"true\" condition-coverage=\"{:.0}% ({}/{})\"/>\n",
+                            line, hits, percent(taken, arms.len()), taken, arms.len()
+                        ));
+                    }
+                    None => xml.push_str(&format!("            <line number=\"{}\" hits=\"{}\" branch=\"false\"/>\n", line, hits)),
+                }
+            }
+            xml.push_str("          </lines>\n        </class>\n");
+        }
+        xml.push_str("      </classes>\n    </package>\n  </packages>\n</coverage>\n");
+        xml
+    }
+
+    /// Files below their `[coverage]` threshold, as messages
+    pub fn check(&self, config: &CoverageConfig) -> Vec<String> {
+        let mut failures = Vec::new();
+        for (name, file) in &self.files {
+            let (lines, branches) = config.thresholds(name);
+            if let Some(min) = lines.filter(|min| file.line_percent() < *min) {
+                failures.push(format!("{}: line coverage {:.1}% is below {}%", name, file.line_percent(), min));
+            }
+            if let Some(min) = branches.filter(|min| file.branch_percent() < *min) {
+                failures.push(format!("{}: branch coverage {:.1}% is below {}%", name, file.branch_percent(), min));
+            }
+        }
+        failures
+    }
+
+    /// Keep only files under `root`, renamed relative to it with `/` separators
+    pub fn relative_to(&mut self, root: &Path) {
+        let files = std::mem::take(&mut self.files);
+        for (name, file) in files {
+            if let Ok(relative) = Path::new(&name).strip_prefix(root) {
+                self.files.insert(relative.to_string_lossy().replace('\\', "/"), file);
+            }
+        }
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn parse(source: &str, file: &str) -> Program {
+        Parser::new(Lexer::new(source).tokenize()).with_lines().with_source(file).parse().unwrap()
+    }
+
+    #[test]
+    fn test_line_and_branch_coverage() {
+        let library = parse("\
+fn classify(n):
+    if n < 0:
+        return \"negative\"
+    elif n == 0:
+        return \"zero\"
+    return \"positive\"
+
+fn total(items):
+    let sum = 0
+    for item in items:
+        sum = sum + item
+    return sum
+
+fn unused():
+    return 1
+", "lib.poly");
+        let tests = parse("\
+print(classify(5))
+print(classify(-1))
+print(total([1, 2]))
+", "test_lib.poly");
+
+        let mut coverage = Coverage::new();
+        coverage.register("lib.poly", &library);
+        let mut interp = Interpreter::new();
+        interp.set_echo_output(false);
+        interp.set_coverage(Some(coverage));
+        interp.run(&library).unwrap();
+        interp.run(&tests).unwrap();
+        let mut coverage = interp.take_coverage().unwrap();
+        coverage.files.remove("test_lib.poly");
+
+        let lib = &coverage.files["lib.poly"];
+        assert_eq!(lib.lines[&2], 2);
+        assert_eq!(lib.lines[&5], 0);
+        assert_eq!(lib.lines[&11], 2);
+        assert_eq!(lib.missing(), "5, 15");
+        assert_eq!(lib.branches[&2], vec![1, 0, 1]);
+        assert_eq!(lib.branches[&10], vec![2, 1]);
+        assert_eq!((lib.lines_hit(), lib.lines.len(), lib.branches_hit(), lib.branches_total()), (10, 12, 4, 5));
+
+        let mut merged = coverage.clone();
+        merged.merge(&coverage);
+        assert_eq!(merged.files["lib.poly"].lines[&2], 4);
+
+        let lcov = coverage.to_lcov();
+        assert!(lcov.contains("SF:lib.poly\nBRDA:2,0,0,1\nBRDA:2,0,1,0\n"));
+        assert!(lcov.contains("DA:5,0\n") && lcov.contains("LF:12\nLH:10\nend_of_record"));
+        let xml = coverage.to_cobertura(".");
+        assert!(xml.contains("<line number=\"2\" hits=\"2\" branch=\"true\" condition-coverage=\"67% (2/3)\"/>"));
+
+        let config = crate::config::PolyConfig::parse("[coverage]\nlines = 90\n\n[coverage.files]\n\"lib.poly\" = { lines = 80, branches = 90 }\n").coverage;
+        assert_eq!(coverage.check(&config), vec!["lib.poly: branch coverage 80.0% is below 90%"]);
+    }
+}
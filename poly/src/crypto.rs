@@ -0,0 +1,423 @@
+//! Cryptography for Poly (`import crypto`)
+//!
+//! Hashing (SHA-256/512, BLAKE3), HMAC, authenticated encryption
+//! (AES-256-GCM, ChaCha20-Poly1305), Argon2id password hashing, constant-time
+//! comparison and OS-backed secure random numbers.
+//!
+//! Poly has no bytes type, so binary data crosses into scripts as text:
+//! digests are lowercase hex, keys and ciphertexts are standard base64.
+//! Inputs may be strings (UTF-8) or lists of byte values.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chacha20poly1305::ChaCha20Poly1305;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256, Sha512};
+use subtle::ConstantTimeEq;
+
+use crate::ast::Value;
+
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+const MODULE_FUNCTIONS: &[&str] = &[
+    "sha256", "sha512", "blake3", "md5", "hmac", "hmac_sha256", "hmac_sha512",
+    "encrypt", "decrypt", "generate_key", "hash_password", "verify_password",
+    "compare_digest", "random_bytes", "token_hex", "token_urlsafe", "random",
+    "randint", "uuid4",
+];
+
+/// Build the `crypto` module value
+pub fn module() -> Value {
+    crate::interpreter::module_value(
+        "crypto",
+        MODULE_FUNCTIONS
+            .iter()
+            .map(|name| (Value::String(name.to_string()), Value::NativeFunction(format!("crypto.{}", name))))
+            .collect(),
+    )
+}
+
+// ============================================================================
+// Encoding helpers
+// ============================================================================
+
+pub fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+const B64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (chunk.get(1).copied().unwrap_or(0) as u32) << 8
+            | chunk.get(2).copied().unwrap_or(0) as u32;
+        out.push(B64[(n >> 18) as usize & 63] as char);
+        out.push(B64[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 { B64[(n >> 6) as usize & 63] as char } else { '=' });
+        out.push(if chunk.len() > 2 { B64[n as usize & 63] as char } else { '=' });
+    }
+    out
+}
+
+pub fn base64_decode(s: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(s.len() / 4 * 3);
+    let mut buf = 0u32;
+    let mut bits = 0;
+    for c in s.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return Err("Invalid base64 data".to_string()),
+        };
+        buf = (buf << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buf >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+/// Bytes of a string (UTF-8) or list of ints argument
+fn bytes_arg(value: Option<&Value>, func: &str, name: &str) -> Result<Vec<u8>, String> {
+    match value {
+        Some(Value::String(s)) => Ok(s.as_bytes().to_vec()),
+        Some(Value::List(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::Int(n) if (0..=255).contains(n) => Ok(*n as u8),
+                _ => Err(format!("{}(): {} must contain byte values 0-255", func, name)),
+            })
+            .collect(),
+        _ => Err(format!("{}() requires {} as a string or list of bytes", func, name)),
+    }
+}
+
+fn str_arg<'a>(value: Option<&'a Value>, func: &str, name: &str) -> Result<&'a str, String> {
+    match value {
+        Some(Value::String(s)) => Ok(s),
+        _ => Err(format!("{}() requires {} as a string", func, name)),
+    }
+}
+
+fn int_arg(value: Option<&Value>, func: &str, default: i64) -> Result<i64, String> {
+    match value {
+        None | Some(Value::None) => Ok(default),
+        Some(Value::Int(n)) => Ok(*n),
+        _ => Err(format!("{}() requires an integer", func)),
+    }
+}
+
+// ============================================================================
+// Primitives
+// ============================================================================
+
+/// Fill a buffer from the operating system CSPRNG
+pub fn random_bytes(len: usize) -> Result<Vec<u8>, String> {
+    let mut buf = vec![0u8; len];
+    getrandom::getrandom(&mut buf).map_err(|e| format!("Secure random unavailable: {}", e))?;
+    Ok(buf)
+}
+
+/// Uniform float in [0, 1)
+pub fn random_float() -> Result<f64, String> {
+    let bytes = random_bytes(8)?;
+    let n = u64::from_le_bytes(bytes.try_into().unwrap_or_default());
+    Ok((n >> 11) as f64 / (1u64 << 53) as f64)
+}
+
+/// Uniform integer in [low, high] without modulo bias
+pub fn random_range(low: i64, high: i64) -> Result<i64, String> {
+    if high < low {
+        return Err(format!("empty range for randint({}, {})", low, high));
+    }
+    let span = (high as i128 - low as i128 + 1) as u128;
+    let limit = u64::MAX as u128 + 1 - ((u64::MAX as u128 + 1) % span);
+    loop {
+        let n = u64::from_le_bytes(random_bytes(8)?.try_into().unwrap_or_default()) as u128;
+        if n < limit {
+            return Ok((low as i128 + (n % span) as i128) as i64);
+        }
+    }
+}
+
+/// Random (version 4) UUID
+pub fn uuid4() -> Result<String, String> {
+    let mut b = random_bytes(16)?;
+    b[6] = (b[6] & 0x0f) | 0x40;
+    b[8] = (b[8] & 0x3f) | 0x80;
+    let h = hex_encode(&b);
+    Ok(format!("{}-{}-{}-{}-{}", &h[0..8], &h[8..12], &h[12..16], &h[16..20], &h[20..32]))
+}
+
+pub fn sha256(data: &[u8]) -> String {
+    hex_encode(&Sha256::digest(data))
+}
+
+pub fn sha512(data: &[u8]) -> String {
+    hex_encode(&Sha512::digest(data))
+}
+
+pub fn md5(data: &[u8]) -> String {
+    use md5::Md5;
+    hex_encode(&Md5::digest(data))
+}
+
+pub fn blake3(data: &[u8]) -> String {
+    blake3::hash(data).to_hex().to_string()
+}
+
+pub fn hmac(algorithm: &str, key: &[u8], data: &[u8]) -> Result<String, String> {
+    match algorithm {
+        "sha256" => {
+            let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).map_err(|e| e.to_string())?;
+            mac.update(data);
+            Ok(hex_encode(&mac.finalize().into_bytes()))
+        }
+        "sha512" => {
+            let mut mac = <Hmac<Sha512> as Mac>::new_from_slice(key).map_err(|e| e.to_string())?;
+            mac.update(data);
+            Ok(hex_encode(&mac.finalize().into_bytes()))
+        }
+        "blake3" => {
+            let key: [u8; KEY_LEN] = key
+                .try_into()
+                .map_err(|_| "blake3 keyed hashing requires a 32-byte key".to_string())?;
+            Ok(blake3::keyed_hash(&key, data).to_hex().to_string())
+        }
+        _ => Err(format!("Unsupported HMAC algorithm: {} (use sha256, sha512 or blake3)", algorithm)),
+    }
+}
+
+/// Constant-time equality of two byte strings
+pub fn compare_digest(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+fn decode_key(key: &str) -> Result<[u8; KEY_LEN], String> {
+    base64_decode(key)?
+        .try_into()
+        .map_err(|_| "Encryption key must be 32 bytes, base64 encoded (see crypto.generate_key())".to_string())
+}
+
+/// Encrypt with an explicit nonce; output is nonce || ciphertext || tag
+fn seal(algorithm: &str, key: &[u8; KEY_LEN], nonce: &[u8; NONCE_LEN], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let nonce_ref = Nonce::from_slice(nonce);
+    let ciphertext = match algorithm {
+        "aes-256-gcm" => Aes256Gcm::new(key.into()).encrypt(nonce_ref, plaintext),
+        "chacha20-poly1305" => ChaCha20Poly1305::new(key.into()).encrypt(nonce_ref, plaintext),
+        _ => return Err(format!("Unsupported cipher: {} (use aes-256-gcm or chacha20-poly1305)", algorithm)),
+    }
+    .map_err(|_| "Encryption failed".to_string())?;
+    let mut out = nonce.to_vec();
+    out.extend(ciphertext);
+    Ok(out)
+}
+
+fn open(algorithm: &str, key: &[u8; KEY_LEN], sealed: &[u8]) -> Result<Vec<u8>, String> {
+    if sealed.len() < NONCE_LEN + 16 {
+        return Err("Ciphertext is too short".to_string());
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let nonce = Nonce::from_slice(nonce);
+    match algorithm {
+        "aes-256-gcm" => Aes256Gcm::new(key.into()).decrypt(nonce, ciphertext),
+        "chacha20-poly1305" => ChaCha20Poly1305::new(key.into()).decrypt(nonce, ciphertext),
+        _ => return Err(format!("Unsupported cipher: {} (use aes-256-gcm or chacha20-poly1305)", algorithm)),
+    }
+    .map_err(|_| "Decryption failed: wrong key or tampered ciphertext".to_string())
+}
+
+/// Encrypt text with a base64 key; returns base64(nonce || ciphertext || tag)
+pub fn encrypt(plaintext: &[u8], key: &str, algorithm: &str) -> Result<String, String> {
+    let key = decode_key(key)?;
+    let nonce: [u8; NONCE_LEN] = random_bytes(NONCE_LEN)?.try_into().unwrap_or_default();
+    seal(algorithm, &key, &nonce, plaintext).map(|sealed| base64_encode(&sealed))
+}
+
+pub fn decrypt(token: &str, key: &str, algorithm: &str) -> Result<Vec<u8>, String> {
+    let key = decode_key(key)?;
+    open(algorithm, &key, &base64_decode(token)?)
+}
+
+/// Argon2id hash in PHC string format
+pub fn hash_password(password: &[u8]) -> Result<String, String> {
+    let salt = SaltString::encode_b64(&random_bytes(16)?).map_err(|e| e.to_string())?;
+    Argon2::default()
+        .hash_password(password, &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("Password hashing failed: {}", e))
+}
+
+pub fn verify_password(password: &[u8], hash: &str) -> Result<bool, String> {
+    let parsed = PasswordHash::new(hash).map_err(|e| format!("Invalid password hash: {}", e))?;
+    Ok(Argon2::default().verify_password(password, &parsed).is_ok())
+}
+
+// ============================================================================
+// Module functions
+// ============================================================================
+
+/// Split a trailing kwargs dict (`algorithm="..."`) off the arguments
+fn algorithm_kwarg(args: &mut Vec<Value>, default: &str) -> String {
+    if let Some(Value::Dict(pairs)) = args.last() {
+        let found = pairs.iter().find_map(|(k, v)| match (k, v) {
+            (Value::String(k), Value::String(v)) if k == "algorithm" => Some(v.clone()),
+            _ => None,
+        });
+        if let Some(algorithm) = found {
+            args.pop();
+            return algorithm.to_lowercase();
+        }
+    }
+    default.to_string()
+}
+
+/// Call a `crypto.<name>` module function
+pub fn call(name: &str, mut args: Vec<Value>) -> Result<Value, String> {
+    match name {
+        "sha256" => Ok(Value::String(sha256(&bytes_arg(args.first(), name, "data")?))),
+        "sha512" => Ok(Value::String(sha512(&bytes_arg(args.first(), name, "data")?))),
+        "blake3" => Ok(Value::String(blake3(&bytes_arg(args.first(), name, "data")?))),
+        "md5" => Ok(Value::String(md5(&bytes_arg(args.first(), name, "data")?))),
+        "hmac" | "hmac_sha256" | "hmac_sha512" => {
+            let default = if name == "hmac_sha512" { "sha512" } else { "sha256" };
+            let algorithm = algorithm_kwarg(&mut args, default);
+            let algorithm = match args.get(2) {
+                Some(Value::String(a)) if name == "hmac" => a.to_lowercase(),
+                _ => algorithm,
+            };
+            let key = bytes_arg(args.first(), name, "key")?;
+            let data = bytes_arg(args.get(1), name, "data")?;
+            hmac(&algorithm, &key, &data).map(Value::String)
+        }
+        "encrypt" => {
+            let algorithm = algorithm_kwarg(&mut args, "aes-256-gcm");
+            let plaintext = bytes_arg(args.first(), name, "plaintext")?;
+            let key = str_arg(args.get(1), name, "key")?;
+            encrypt(&plaintext, key, &algorithm).map(Value::String)
+        }
+        "decrypt" => {
+            let algorithm = algorithm_kwarg(&mut args, "aes-256-gcm");
+            let token = str_arg(args.first(), name, "ciphertext")?;
+            let key = str_arg(args.get(1), name, "key")?;
+            let plaintext = decrypt(token, key, &algorithm)?;
+            String::from_utf8(plaintext)
+                .map(Value::String)
+                .map_err(|_| "decrypt(): plaintext is not valid UTF-8".to_string())
+        }
+        "generate_key" => Ok(Value::String(base64_encode(&random_bytes(KEY_LEN)?))),
+        "hash_password" => hash_password(&bytes_arg(args.first(), name, "password")?).map(Value::String),
+        "verify_password" => {
+            let password = bytes_arg(args.first(), name, "password")?;
+            let hash = str_arg(args.get(1), name, "hash")?;
+            verify_password(&password, hash).map(Value::Bool)
+        }
+        "compare_digest" => {
+            let a = bytes_arg(args.first(), name, "a")?;
+            let b = bytes_arg(args.get(1), name, "b")?;
+            Ok(Value::Bool(compare_digest(&a, &b)))
+        }
+        "random_bytes" => {
+            let len = int_arg(args.first(), name, 32)?.max(0) as usize;
+            Ok(Value::List(random_bytes(len)?.into_iter().map(|b| Value::Int(b as i64)).collect()))
+        }
+        "token_hex" => {
+            let len = int_arg(args.first(), name, 32)?.max(0) as usize;
+            Ok(Value::String(hex_encode(&random_bytes(len)?)))
+        }
+        "token_urlsafe" => {
+            let len = int_arg(args.first(), name, 32)?.max(0) as usize;
+            let token = base64_encode(&random_bytes(len)?)
+                .trim_end_matches('=')
+                .replace('+', "-")
+                .replace('/', "_");
+            Ok(Value::String(token))
+        }
+        "random" => random_float().map(Value::Float),
+        "randint" => match (args.first(), args.get(1)) {
+            (Some(Value::Int(a)), Some(Value::Int(b))) => random_range(*a, *b).map(Value::Int),
+            _ => Err("randint() requires two integers".to_string()),
+        },
+        "uuid4" => uuid4().map(Value::String),
+        _ => Err(format!("crypto has no function '{}'", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_vectors() {
+        assert_eq!(sha256(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(
+            sha512(b"abc"),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+        assert_eq!(blake3(b""), "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262");
+        assert_eq!(md5(b"abc"), "900150983cd24fb0d6963f7d28e17f72");
+    }
+
+    #[test]
+    fn test_hmac_rfc4231() {
+        let data = b"what do ya want for nothing?";
+        assert_eq!(
+            hmac("sha256", b"Jefe", data).unwrap(),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hmac("sha512", b"Jefe", data).unwrap(),
+            "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+             9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+        );
+    }
+
+    #[test]
+    fn test_aes_gcm_vector() {
+        // NIST GCM test case 14: zero key, zero IV, 16 zero bytes of plaintext
+        let sealed = seal("aes-256-gcm", &[0u8; KEY_LEN], &[0u8; NONCE_LEN], &[0u8; 16]).unwrap();
+        assert_eq!(
+            hex_encode(&sealed[NONCE_LEN..]),
+            "cea7403d4d606b6e074ec5d3baf39d18d0d1c8a799996bf0265b98b5d48ab919"
+        );
+    }
+
+    #[test]
+    fn test_encrypt_roundtrip_and_tamper() {
+        let key = base64_encode(&random_bytes(KEY_LEN).unwrap());
+        for algorithm in ["aes-256-gcm", "chacha20-poly1305"] {
+            let token = encrypt(b"sk-secret-api-key", &key, algorithm).unwrap();
+            assert_eq!(decrypt(&token, &key, algorithm).unwrap(), b"sk-secret-api-key");
+
+            let mut tampered = base64_decode(&token).unwrap();
+            let last = tampered.len() - 1;
+            tampered[last] ^= 1;
+            assert!(decrypt(&base64_encode(&tampered), &key, algorithm).is_err());
+        }
+    }
+
+    #[test]
+    fn test_password_hash_and_helpers() {
+        let hash = hash_password(b"hunter2").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(verify_password(b"hunter2", &hash).unwrap());
+        assert!(!verify_password(b"hunter3", &hash).unwrap());
+        assert!(compare_digest(b"abc", b"abc"));
+        assert!(!compare_digest(b"abc", b"abd"));
+        let uuid = uuid4().unwrap();
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+        assert!((0..100).all(|_| (1..=6).contains(&random_range(1, 6).unwrap())));
+    }
+}
@@ -0,0 +1,819 @@
+//! Date and time support for Poly (`import datetime`)
+//!
+//! `date`, `time`, `datetime` and `timedelta` values are plain instances whose
+//! fields double as attributes (`dt.year`, `delta.days`). Timezones come from
+//! the IANA database compiled in via chrono-tz, so no system tzdata is needed.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime,
+    Offset, TimeZone, Timelike, Utc,
+};
+use chrono_tz::Tz;
+
+use crate::ast::{BinOp, Value};
+use crate::interpreter::split_kwargs;
+
+pub const DATE: &str = "date";
+pub const TIME: &str = "time";
+pub const DATETIME: &str = "datetime";
+pub const TIMEDELTA: &str = "timedelta";
+
+const MODULE_FUNCTIONS: &[&str] = &[
+    "now", "utcnow", "today", "datetime", "date", "time", "timedelta",
+    "fromisoformat", "strptime", "fromtimestamp",
+];
+
+/// Build the `datetime` module value
+pub fn module() -> Value {
+    let mut pairs: Vec<(Value, Value)> = MODULE_FUNCTIONS
+        .iter()
+        .map(|name| (Value::String(name.to_string()), Value::NativeFunction(format!("datetime.{}", name))))
+        .collect();
+    pairs.push((Value::String("UTC".to_string()), Value::String("UTC".to_string())));
+    crate::interpreter::module_value("datetime", pairs)
+}
+
+/// Check whether a value is one of the datetime module types
+pub fn is_temporal(value: &Value) -> bool {
+    match value {
+        Value::Instance { class_name, fields } => match class_name.as_str() {
+            DATE | DATETIME => fields.contains_key("year"),
+            TIME => fields.contains_key("hour") && !fields.contains_key("year"),
+            TIMEDELTA => fields.contains_key("days"),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+// ============================================================================
+// Timezones
+// ============================================================================
+
+enum Zone {
+    Utc,
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+fn parse_zone(name: &str) -> Result<Zone, String> {
+    match name {
+        "UTC" | "utc" | "Z" => return Ok(Zone::Utc),
+        _ => {}
+    }
+    if name.starts_with('+') || name.starts_with('-') {
+        return parse_offset(name).map(Zone::Fixed);
+    }
+    Tz::from_str(name)
+        .map(Zone::Named)
+        .map_err(|_| format!("Unknown timezone: {}", name))
+}
+
+/// Parse "+05:30", "-0800" or "+05" into a fixed offset
+fn parse_offset(s: &str) -> Result<FixedOffset, String> {
+    let sign = if s.starts_with('-') { -1 } else { 1 };
+    let digits: String = s[1..].chars().filter(|c| c.is_ascii_digit()).collect();
+    let (hours, minutes) = match digits.len() {
+        2 => (&digits[..2], "0"),
+        4 => (&digits[..2], &digits[2..]),
+        _ => return Err(format!("Invalid UTC offset: {}", s)),
+    };
+    let secs = hours.parse::<i32>().unwrap_or(0) * 3600 + minutes.parse::<i32>().unwrap_or(0) * 60;
+    FixedOffset::east_opt(sign * secs).ok_or_else(|| format!("Invalid UTC offset: {}", s))
+}
+
+fn format_offset(secs: i32) -> String {
+    let sign = if secs < 0 { '-' } else { '+' };
+    let abs = secs.abs();
+    format!("{}{:02}:{:02}", sign, abs / 3600, (abs % 3600) / 60)
+}
+
+/// Offset in seconds for a UTC instant in the given zone
+fn offset_at_utc(zone: &Zone, utc: &NaiveDateTime) -> i32 {
+    match zone {
+        Zone::Utc => 0,
+        Zone::Fixed(offset) => offset.local_minus_utc(),
+        Zone::Named(tz) => tz.offset_from_utc_datetime(utc).fix().local_minus_utc(),
+    }
+}
+
+/// Offset in seconds for a wall-clock time in the given zone
+fn offset_at_local(zone: &Zone, local: &NaiveDateTime) -> Result<i32, String> {
+    match zone {
+        Zone::Utc => Ok(0),
+        Zone::Fixed(offset) => Ok(offset.local_minus_utc()),
+        Zone::Named(tz) => tz
+            .from_local_datetime(local)
+            .earliest()
+            .map(|dt| dt.offset().fix().local_minus_utc())
+            .ok_or_else(|| format!("{} does not exist in {}", local, tz.name())),
+    }
+}
+
+// ============================================================================
+// Conversions between values and chrono types
+// ============================================================================
+
+fn field_int(fields: &HashMap<String, Value>, name: &str) -> i64 {
+    match fields.get(name) {
+        Some(Value::Int(n)) => *n,
+        _ => 0,
+    }
+}
+
+fn instance(class_name: &str, fields: Vec<(&str, Value)>) -> Value {
+    Value::Instance {
+        class_name: class_name.to_string(),
+        fields: fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect(),
+    }
+}
+
+fn date_value(date: NaiveDate) -> Value {
+    instance(DATE, vec![
+        ("year", Value::Int(date.year() as i64)),
+        ("month", Value::Int(date.month() as i64)),
+        ("day", Value::Int(date.day() as i64)),
+    ])
+}
+
+fn time_value(time: NaiveTime) -> Value {
+    instance(TIME, vec![
+        ("hour", Value::Int(time.hour() as i64)),
+        ("minute", Value::Int(time.minute() as i64)),
+        ("second", Value::Int(time.second() as i64)),
+        ("microsecond", Value::Int((time.nanosecond() / 1000) as i64)),
+    ])
+}
+
+/// Build a datetime value; `zone` is the tz label and its UTC offset in seconds
+fn datetime_value(naive: NaiveDateTime, zone: Option<(String, i32)>) -> Value {
+    let (tzinfo, utcoffset) = match zone {
+        Some((name, offset)) => (Value::String(name), Value::Int(offset as i64)),
+        None => (Value::None, Value::None),
+    };
+    instance(DATETIME, vec![
+        ("year", Value::Int(naive.year() as i64)),
+        ("month", Value::Int(naive.month() as i64)),
+        ("day", Value::Int(naive.day() as i64)),
+        ("hour", Value::Int(naive.hour() as i64)),
+        ("minute", Value::Int(naive.minute() as i64)),
+        ("second", Value::Int(naive.second() as i64)),
+        ("microsecond", Value::Int((naive.nanosecond() / 1000) as i64)),
+        ("tzinfo", tzinfo),
+        ("utcoffset", utcoffset),
+    ])
+}
+
+/// Build an aware datetime from a UTC instant, expressed in `zone_name`
+fn datetime_from_utc(utc: NaiveDateTime, zone_name: &str) -> Result<Value, String> {
+    let zone = parse_zone(zone_name)?;
+    let offset = offset_at_utc(&zone, &utc);
+    let local = utc.checked_add_signed(Duration::seconds(offset as i64)).ok_or_else(|| overflow("date"))?;
+    Ok(datetime_value(local, Some((zone_name.to_string(), offset))))
+}
+
+/// Build an aware datetime from a wall-clock time in `zone_name`
+fn datetime_from_local(local: NaiveDateTime, zone_name: &str) -> Result<Value, String> {
+    let zone = parse_zone(zone_name)?;
+    let offset = offset_at_local(&zone, &local)?;
+    Ok(datetime_value(local, Some((zone_name.to_string(), offset))))
+}
+
+fn timedelta_value(delta: Duration) -> Value {
+    let total_us = delta.num_microseconds().unwrap_or(i64::MAX);
+    let days = total_us.div_euclid(86_400_000_000);
+    let rem = total_us.rem_euclid(86_400_000_000);
+    instance(TIMEDELTA, vec![
+        ("days", Value::Int(days)),
+        ("seconds", Value::Int(rem / 1_000_000)),
+        ("microseconds", Value::Int(rem % 1_000_000)),
+    ])
+}
+
+fn to_date(fields: &HashMap<String, Value>) -> Result<NaiveDate, String> {
+    let (y, m, d) = (field_int(fields, "year"), field_int(fields, "month"), field_int(fields, "day"));
+    NaiveDate::from_ymd_opt(y as i32, m as u32, d as u32)
+        .ok_or_else(|| format!("Invalid date: {}-{}-{}", y, m, d))
+}
+
+fn to_time(fields: &HashMap<String, Value>) -> Result<NaiveTime, String> {
+    let (h, m, s, us) = (
+        field_int(fields, "hour"),
+        field_int(fields, "minute"),
+        field_int(fields, "second"),
+        field_int(fields, "microsecond"),
+    );
+    NaiveTime::from_hms_micro_opt(h as u32, m as u32, s as u32, us as u32)
+        .ok_or_else(|| format!("Invalid time: {}:{}:{}.{}", h, m, s, us))
+}
+
+/// Wall-clock time plus (tz label, offset) for aware datetimes
+fn to_datetime(fields: &HashMap<String, Value>) -> Result<(NaiveDateTime, Option<(String, i32)>), String> {
+    let naive = to_date(fields)?.and_time(to_time(fields)?);
+    let zone = match fields.get("tzinfo") {
+        Some(Value::String(name)) => Some((name.clone(), field_int(fields, "utcoffset") as i32)),
+        _ => None,
+    };
+    Ok((naive, zone))
+}
+
+fn to_duration(fields: &HashMap<String, Value>) -> Result<Duration, String> {
+    Duration::try_days(field_int(fields, "days"))
+        .zip(Duration::try_seconds(field_int(fields, "seconds")))
+        .and_then(|(days, seconds)| days.checked_add(&seconds))
+        .and_then(|delta| delta.checked_add(&Duration::microseconds(field_int(fields, "microseconds"))))
+        .ok_or_else(|| overflow("timedelta"))
+}
+
+/// Error for arithmetic that leaves the range chrono can represent
+fn overflow(kind: &str) -> String {
+    format!("OverflowError: {} value out of range", kind)
+}
+
+/// UTC instant of an aware datetime, or None when naive
+fn utc_instant(naive: &NaiveDateTime, zone: &Option<(String, i32)>) -> Option<NaiveDateTime> {
+    zone.as_ref().map(|(_, offset)| *naive - Duration::seconds(*offset as i64))
+}
+
+/// Interpret a naive datetime as system local time and return its UTC instant
+fn local_to_utc(naive: &NaiveDateTime) -> Result<NaiveDateTime, String> {
+    Local
+        .from_local_datetime(naive)
+        .earliest()
+        .map(|dt| dt.naive_utc())
+        .ok_or_else(|| format!("{} does not exist in the local timezone", naive))
+}
+
+// ============================================================================
+// Formatting
+// ============================================================================
+
+fn micros_suffix(us: u32) -> String {
+    if us == 0 { String::new() } else { format!(".{:06}", us) }
+}
+
+/// ISO-8601 representation used by `isoformat()`, JSON and IPC
+pub fn isoformat(value: &Value) -> String {
+    iso_with_separator(value, 'T')
+}
+
+fn iso_with_separator(value: &Value, sep: char) -> String {
+    let Value::Instance { class_name, fields } = value else { return value.to_string() };
+    match class_name.as_str() {
+        DATE => to_date(fields).map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+        TIME => to_time(fields)
+            .map(|t| format!("{}{}", t.format("%H:%M:%S"), micros_suffix(t.nanosecond() / 1000)))
+            .unwrap_or_default(),
+        DATETIME => match to_datetime(fields) {
+            Ok((naive, zone)) => {
+                let mut out = format!(
+                    "{}{}{}{}",
+                    naive.format("%Y-%m-%d"),
+                    sep,
+                    naive.format("%H:%M:%S"),
+                    micros_suffix(naive.nanosecond() / 1000)
+                );
+                if let Some((_, offset)) = zone {
+                    out.push_str(&format_offset(offset));
+                }
+                out
+            }
+            Err(_) => String::new(),
+        },
+        TIMEDELTA => to_duration(fields).map(format_timedelta).unwrap_or_default(),
+        _ => value.to_string(),
+    }
+}
+
+/// `str()` of a datetime value (space separator, like Python)
+pub fn display(value: &Value) -> String {
+    iso_with_separator(value, ' ')
+}
+
+fn format_timedelta(delta: Duration) -> String {
+    let total_us = delta.num_microseconds().unwrap_or(0);
+    let days = total_us.div_euclid(86_400_000_000);
+    let rem = total_us.rem_euclid(86_400_000_000);
+    let secs = rem / 1_000_000;
+    let mut out = String::new();
+    if days != 0 {
+        let _ = write!(out, "{} day{}, ", days, if days.abs() == 1 { "" } else { "s" });
+    }
+    let _ = write!(out, "{}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60);
+    out.push_str(&micros_suffix((rem % 1_000_000) as u32));
+    out
+}
+
+/// Replace %Z with the timezone label (chrono would print the numeric offset)
+fn substitute_tzname(fmt: &str, tzname: &str) -> String {
+    let mut out = String::with_capacity(fmt.len());
+    let mut chars = fmt.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '%' {
+            match chars.next() {
+                Some('Z') => out.push_str(&tzname.replace('%', "%%")),
+                Some(next) => {
+                    out.push('%');
+                    out.push(next);
+                }
+                None => out.push('%'),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn strftime(value: &Value, fmt: &str) -> Result<String, String> {
+    let Value::Instance { class_name, fields } = value else { return Err("strftime() requires a date or time".to_string()) };
+    let mut out = String::new();
+    let written = match class_name.as_str() {
+        DATE => write!(out, "{}", to_date(fields)?.format(&substitute_tzname(fmt, ""))),
+        TIME => write!(out, "{}", to_time(fields)?.format(&substitute_tzname(fmt, ""))),
+        DATETIME => {
+            let (naive, zone) = to_datetime(fields)?;
+            match zone {
+                Some((name, offset)) => {
+                    let offset = FixedOffset::east_opt(offset).ok_or("Invalid UTC offset")?;
+                    let aware = offset
+                        .from_local_datetime(&naive)
+                        .single()
+                        .ok_or("Invalid datetime")?;
+                    write!(out, "{}", aware.format(&substitute_tzname(fmt, &name)))
+                }
+                None => write!(out, "{}", naive.format(&substitute_tzname(fmt, ""))),
+            }
+        }
+        _ => return Err(format!("strftime() not supported on {}", class_name)),
+    };
+    written.map_err(|_| format!("Invalid format string for {}: {}", class_name, fmt))?;
+    Ok(out)
+}
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+fn strptime(s: &str, fmt: &str) -> Result<Value, String> {
+    if let Ok(aware) = DateTime::parse_from_str(s, fmt) {
+        let offset = aware.offset().local_minus_utc();
+        return Ok(datetime_value(aware.naive_local(), Some((format_offset(offset), offset))));
+    }
+    if let Ok(naive) = NaiveDateTime::parse_from_str(s, fmt) {
+        return Ok(datetime_value(naive, None));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(s, fmt) {
+        return Ok(datetime_value(date.and_time(NaiveTime::MIN), None));
+    }
+    Err(format!("time data '{}' does not match format '{}'", s, fmt))
+}
+
+/// Parse an ISO-8601 string into a datetime, date or time depending on its shape
+pub fn fromisoformat(s: &str) -> Result<Value, String> {
+    let s = s.trim();
+    if let Ok(aware) = DateTime::parse_from_rfc3339(s) {
+        let offset = aware.offset().local_minus_utc();
+        let name = if s.ends_with('Z') || s.ends_with('z') { "UTC".to_string() } else { format_offset(offset) };
+        return Ok(datetime_value(aware.naive_local(), Some((name, offset))));
+    }
+    for fmt in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(s, fmt) {
+            return Ok(datetime_value(naive, None));
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(date_value(date));
+    }
+    for fmt in ["%H:%M:%S%.f", "%H:%M"] {
+        if let Ok(time) = NaiveTime::parse_from_str(s, fmt) {
+            return Ok(time_value(time));
+        }
+    }
+    Err(format!("Invalid isoformat string: '{}'", s))
+}
+
+// ============================================================================
+// Arguments
+// ============================================================================
+
+/// Resolve a parameter from positional or keyword arguments
+fn arg<'a>(args: &'a [Value], kwargs: &'a [(String, Value)], index: usize, name: &str) -> Option<&'a Value> {
+    kwargs
+        .iter()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v)
+        .or_else(|| args.get(index))
+}
+
+fn int_arg(args: &[Value], kwargs: &[(String, Value)], index: usize, name: &str, default: i64) -> Result<i64, String> {
+    match arg(args, kwargs, index, name) {
+        None | Some(Value::None) => Ok(default),
+        Some(Value::Int(n)) => Ok(*n),
+        Some(other) => Err(format!("{} must be an integer, got {}", name, other)),
+    }
+}
+
+fn number_arg(args: &[Value], kwargs: &[(String, Value)], index: usize, name: &str) -> Result<f64, String> {
+    match arg(args, kwargs, index, name) {
+        None | Some(Value::None) => Ok(0.0),
+        Some(Value::Int(n)) => Ok(*n as f64),
+        Some(Value::Float(f)) => Ok(*f),
+        Some(other) => Err(format!("{} must be a number, got {}", name, other)),
+    }
+}
+
+fn tz_arg(args: &[Value], kwargs: &[(String, Value)], index: usize) -> Result<Option<String>, String> {
+    match arg(args, kwargs, index, "tz").or_else(|| arg(&[], kwargs, 0, "tzinfo")) {
+        None | Some(Value::None) => Ok(None),
+        Some(Value::String(name)) => Ok(Some(name.clone())),
+        Some(other) => Err(format!("tz must be a timezone name, got {}", other)),
+    }
+}
+
+fn str_arg<'a>(args: &'a [Value], index: usize, func: &str) -> Result<&'a str, String> {
+    match args.get(index) {
+        Some(Value::String(s)) => Ok(s),
+        _ => Err(format!("{}() requires a string argument", func)),
+    }
+}
+
+// ============================================================================
+// Module functions
+// ============================================================================
+
+/// Call a `datetime.<name>` module function
+pub fn call(name: &str, args: Vec<Value>) -> Result<Value, String> {
+    let (args, kwargs) = split_kwargs(args, 0);
+    match name {
+        "now" => match tz_arg(&args, &kwargs, 0)? {
+            Some(zone) => datetime_from_utc(Utc::now().naive_utc(), &zone),
+            None => Ok(datetime_value(Local::now().naive_local(), None)),
+        },
+        "utcnow" => datetime_from_utc(Utc::now().naive_utc(), "UTC"),
+        "today" => Ok(date_value(Local::now().date_naive())),
+        "datetime" => {
+            let date = NaiveDate::from_ymd_opt(
+                int_arg(&args, &kwargs, 0, "year", 1)? as i32,
+                int_arg(&args, &kwargs, 1, "month", 1)? as u32,
+                int_arg(&args, &kwargs, 2, "day", 1)? as u32,
+            ).ok_or("datetime(): day is out of range for month")?;
+            let time = NaiveTime::from_hms_micro_opt(
+                int_arg(&args, &kwargs, 3, "hour", 0)? as u32,
+                int_arg(&args, &kwargs, 4, "minute", 0)? as u32,
+                int_arg(&args, &kwargs, 5, "second", 0)? as u32,
+                int_arg(&args, &kwargs, 6, "microsecond", 0)? as u32,
+            ).ok_or("datetime(): time component out of range")?;
+            let naive = date.and_time(time);
+            match tz_arg(&args, &kwargs, 7)? {
+                Some(zone) => datetime_from_local(naive, &zone),
+                None => Ok(datetime_value(naive, None)),
+            }
+        }
+        "date" => NaiveDate::from_ymd_opt(
+            int_arg(&args, &kwargs, 0, "year", 1)? as i32,
+            int_arg(&args, &kwargs, 1, "month", 1)? as u32,
+            int_arg(&args, &kwargs, 2, "day", 1)? as u32,
+        ).map(date_value).ok_or_else(|| "date(): day is out of range for month".to_string()),
+        "time" => NaiveTime::from_hms_micro_opt(
+            int_arg(&args, &kwargs, 0, "hour", 0)? as u32,
+            int_arg(&args, &kwargs, 1, "minute", 0)? as u32,
+            int_arg(&args, &kwargs, 2, "second", 0)? as u32,
+            int_arg(&args, &kwargs, 3, "microsecond", 0)? as u32,
+        ).map(time_value).ok_or_else(|| "time(): component out of range".to_string()),
+        "timedelta" => {
+            let units = [
+                ("days", 86_400_000_000.0),
+                ("seconds", 1_000_000.0),
+                ("microseconds", 1.0),
+                ("milliseconds", 1_000.0),
+                ("minutes", 60_000_000.0),
+                ("hours", 3_600_000_000.0),
+                ("weeks", 604_800_000_000.0),
+            ];
+            let mut total = 0.0;
+            for (i, (unit, scale)) in units.iter().enumerate() {
+                total += number_arg(&args, &kwargs, i, unit)? * scale;
+            }
+            if !total.is_finite() || total.abs() >= i64::MAX as f64 {
+                return Err(overflow("timedelta"));
+            }
+            Ok(timedelta_value(Duration::microseconds(total.round() as i64)))
+        }
+        "fromisoformat" => fromisoformat(str_arg(&args, 0, "fromisoformat")?),
+        "strptime" => strptime(str_arg(&args, 0, "strptime")?, str_arg(&args, 1, "strptime")?),
+        "fromtimestamp" => {
+            let secs = number_arg(&args, &kwargs, 0, "timestamp")?;
+            let utc = DateTime::from_timestamp_micros((secs * 1_000_000.0).round() as i64)
+                .ok_or("fromtimestamp(): timestamp out of range")?
+                .naive_utc();
+            match tz_arg(&args, &kwargs, 1)? {
+                Some(zone) => datetime_from_utc(utc, &zone),
+                None => Ok(datetime_value(Local.from_utc_datetime(&utc).naive_local(), None)),
+            }
+        }
+        _ => Err(format!("datetime has no function '{}'", name)),
+    }
+}
+
+// ============================================================================
+// Methods
+// ============================================================================
+
+/// Call a method on a datetime value; returns None if `value` isn't one
+pub fn call_method(value: &Value, method: &str, args: Vec<Value>) -> Option<Result<Value, String>> {
+    if !is_temporal(value) {
+        return None;
+    }
+    let Value::Instance { class_name, fields } = value else { return None };
+    let (args, kwargs) = split_kwargs(args, 0);
+    let result = match (class_name.as_str(), method) {
+        (_, "isoformat") => Ok(Value::String(isoformat(value))),
+        (DATE | TIME | DATETIME, "strftime") => str_arg(&args, 0, "strftime")
+            .and_then(|fmt| strftime(value, fmt))
+            .map(Value::String),
+        (DATE, "weekday") => to_date(fields).map(|d| Value::Int(d.weekday().num_days_from_monday() as i64)),
+        (DATE, "isoweekday") => to_date(fields).map(|d| Value::Int(d.weekday().number_from_monday() as i64)),
+        (DATE, "replace") => to_date(fields).and_then(|d| {
+            NaiveDate::from_ymd_opt(
+                int_arg(&[], &kwargs, 0, "year", d.year() as i64)? as i32,
+                int_arg(&[], &kwargs, 0, "month", d.month() as i64)? as u32,
+                int_arg(&[], &kwargs, 0, "day", d.day() as i64)? as u32,
+            ).map(date_value).ok_or_else(|| "replace(): day is out of range for month".to_string())
+        }),
+        (DATETIME, "weekday") => to_date(fields).map(|d| Value::Int(d.weekday().num_days_from_monday() as i64)),
+        (DATETIME, "isoweekday") => to_date(fields).map(|d| Value::Int(d.weekday().number_from_monday() as i64)),
+        (DATETIME, "date") => to_date(fields).map(date_value),
+        (DATETIME, "time") => to_time(fields).map(time_value),
+        (DATETIME, "tzname") => Ok(fields.get("tzinfo").cloned().unwrap_or(Value::None)),
+        (DATETIME, "timestamp") => to_datetime(fields).and_then(|(naive, zone)| {
+            let utc = match utc_instant(&naive, &zone) {
+                Some(utc) => utc,
+                None => local_to_utc(&naive)?,
+            };
+            Ok(Value::Float(utc.and_utc().timestamp_micros() as f64 / 1_000_000.0))
+        }),
+        (DATETIME, "astimezone") => to_datetime(fields).and_then(|(naive, zone)| {
+            let target = tz_arg(&args, &kwargs, 0)?.unwrap_or_else(|| "UTC".to_string());
+            let utc = match utc_instant(&naive, &zone) {
+                Some(utc) => utc,
+                None => local_to_utc(&naive)?,
+            };
+            datetime_from_utc(utc, &target)
+        }),
+        (DATETIME, "replace") => to_datetime(fields).and_then(|(naive, zone)| {
+            let date = NaiveDate::from_ymd_opt(
+                int_arg(&[], &kwargs, 0, "year", naive.year() as i64)? as i32,
+                int_arg(&[], &kwargs, 0, "month", naive.month() as i64)? as u32,
+                int_arg(&[], &kwargs, 0, "day", naive.day() as i64)? as u32,
+            ).ok_or("replace(): day is out of range for month")?;
+            let time = NaiveTime::from_hms_micro_opt(
+                int_arg(&[], &kwargs, 0, "hour", naive.hour() as i64)? as u32,
+                int_arg(&[], &kwargs, 0, "minute", naive.minute() as i64)? as u32,
+                int_arg(&[], &kwargs, 0, "second", naive.second() as i64)? as u32,
+                int_arg(&[], &kwargs, 0, "microsecond", (naive.nanosecond() / 1000) as i64)? as u32,
+            ).ok_or("replace(): time component out of range")?;
+            let replaced = date.and_time(time);
+            let zone_name = if kwargs.iter().any(|(k, _)| k == "tz" || k == "tzinfo") {
+                tz_arg(&[], &kwargs, 0)?
+            } else {
+                zone.map(|(name, _)| name)
+            };
+            match zone_name {
+                Some(name) => datetime_from_local(replaced, &name),
+                None => Ok(datetime_value(replaced, None)),
+            }
+        }),
+        (TIMEDELTA, "total_seconds") => {
+            let us = match to_duration(fields) {
+                Ok(delta) => delta.num_microseconds().unwrap_or(0),
+                Err(e) => return Some(Err(e)),
+            };
+            Ok(Value::Float(us as f64 / 1_000_000.0))
+        }
+        _ => Err(format!("'{}' object has no method '{}'", class_name, method)),
+    };
+    Some(result)
+}
+
+// ============================================================================
+// Operators
+// ============================================================================
+
+fn ordering_result(ordering: std::cmp::Ordering, op: &BinOp) -> Option<Value> {
+    use std::cmp::Ordering::*;
+    let result = match op {
+        BinOp::Eq => ordering == Equal,
+        BinOp::NotEq => ordering != Equal,
+        BinOp::Lt => ordering == Less,
+        BinOp::Gt => ordering == Greater,
+        BinOp::LtEq => ordering != Greater,
+        BinOp::GtEq => ordering != Less,
+        _ => return None,
+    };
+    Some(Value::Bool(result))
+}
+
+/// Order two values of the same datetime type
+pub fn compare(a: &Value, b: &Value) -> Result<std::cmp::Ordering, String> {
+    let (Value::Instance { class_name: ca, fields: fa }, Value::Instance { class_name: cb, fields: fb }) = (a, b) else {
+        return Err("cannot compare non-datetime values".to_string());
+    };
+    if ca != cb {
+        return Err(format!("cannot compare {} to {}", ca, cb));
+    }
+    match ca.as_str() {
+        DATE => Ok(to_date(fa)?.cmp(&to_date(fb)?)),
+        TIME => Ok(to_time(fa)?.cmp(&to_time(fb)?)),
+        TIMEDELTA => Ok(to_duration(fa)?.cmp(&to_duration(fb)?)),
+        DATETIME => {
+            let (na, za) = to_datetime(fa)?;
+            let (nb, zb) = to_datetime(fb)?;
+            match (utc_instant(&na, &za), utc_instant(&nb, &zb)) {
+                (Some(ua), Some(ub)) => Ok(ua.cmp(&ub)),
+                (None, None) => Ok(na.cmp(&nb)),
+                _ => Err("can't compare offset-naive and offset-aware datetimes".to_string()),
+            }
+        }
+        _ => Err(format!("cannot compare {}", ca)),
+    }
+}
+
+/// Shift a datetime by a duration, keeping its timezone
+fn shift_datetime(fields: &HashMap<String, Value>, delta: Duration) -> Result<Value, String> {
+    let (naive, zone) = to_datetime(fields)?;
+    match (utc_instant(&naive, &zone), zone) {
+        (Some(utc), Some((name, _))) => datetime_from_utc(utc.checked_add_signed(delta).ok_or_else(|| overflow("date"))?, &name),
+        _ => Ok(datetime_value(naive.checked_add_signed(delta).ok_or_else(|| overflow("date"))?, None)),
+    }
+}
+
+/// Shift a date by whole days
+fn shift_date(fields: &HashMap<String, Value>, days: i64) -> Result<Value, String> {
+    let delta = Duration::try_days(days).ok_or_else(|| overflow("date"))?;
+    to_date(fields)?.checked_add_signed(delta).map(date_value).ok_or_else(|| overflow("date"))
+}
+
+/// Apply a binary operator where at least one side is a datetime value
+pub fn binary_op(left: &Value, op: &BinOp, right: &Value) -> Result<Value, String> {
+    let class_of = |v: &Value| match v {
+        Value::Instance { class_name, .. } if is_temporal(v) => Some(class_name.clone()),
+        _ => None,
+    };
+    let fields_of = |v: &Value| match v {
+        Value::Instance { fields, .. } => fields.clone(),
+        _ => HashMap::new(),
+    };
+    let (lc, rc) = (class_of(left), class_of(right));
+
+    if matches!(op, BinOp::Eq | BinOp::NotEq) && lc != rc {
+        return Ok(Value::Bool(matches!(op, BinOp::NotEq)));
+    }
+    if lc.is_some() && lc == rc {
+        if let Some(result) = match compare(left, right) {
+            Ok(ordering) => ordering_result(ordering, op),
+            // Mixed naive/aware datetimes are simply unequal
+            Err(_) if matches!(op, BinOp::Eq | BinOp::NotEq) => Some(Value::Bool(matches!(op, BinOp::NotEq))),
+            Err(e) if matches!(op, BinOp::Lt | BinOp::Gt | BinOp::LtEq | BinOp::GtEq) => return Err(e),
+            Err(_) => None,
+        } {
+            return Ok(result);
+        }
+    }
+
+    let (lf, rf) = (fields_of(left), fields_of(right));
+    match (lc.as_deref(), op, rc.as_deref()) {
+        (Some(DATETIME), BinOp::Add, Some(TIMEDELTA)) => shift_datetime(&lf, to_duration(&rf)?),
+        (Some(TIMEDELTA), BinOp::Add, Some(DATETIME)) => shift_datetime(&rf, to_duration(&lf)?),
+        (Some(DATETIME), BinOp::Sub, Some(TIMEDELTA)) => shift_datetime(&lf, -to_duration(&rf)?),
+        (Some(DATETIME), BinOp::Sub, Some(DATETIME)) => {
+            let (na, za) = to_datetime(&lf)?;
+            let (nb, zb) = to_datetime(&rf)?;
+            match (utc_instant(&na, &za), utc_instant(&nb, &zb)) {
+                (Some(ua), Some(ub)) => Ok(timedelta_value(ua - ub)),
+                (None, None) => Ok(timedelta_value(na - nb)),
+                _ => Err("can't subtract offset-naive and offset-aware datetimes".to_string()),
+            }
+        }
+        (Some(DATE), BinOp::Add, Some(TIMEDELTA)) => shift_date(&lf, field_int(&rf, "days")),
+        (Some(TIMEDELTA), BinOp::Add, Some(DATE)) => shift_date(&rf, field_int(&lf, "days")),
+        (Some(DATE), BinOp::Sub, Some(TIMEDELTA)) => shift_date(&lf, field_int(&rf, "days").checked_neg().ok_or_else(|| overflow("date"))?),
+        (Some(DATE), BinOp::Sub, Some(DATE)) => Ok(timedelta_value(to_date(&lf)? - to_date(&rf)?)),
+        (Some(TIMEDELTA), BinOp::Add, Some(TIMEDELTA)) => {
+            to_duration(&lf)?.checked_add(&to_duration(&rf)?).map(timedelta_value).ok_or_else(|| overflow("timedelta"))
+        }
+        (Some(TIMEDELTA), BinOp::Sub, Some(TIMEDELTA)) => {
+            to_duration(&lf)?.checked_sub(&to_duration(&rf)?).map(timedelta_value).ok_or_else(|| overflow("timedelta"))
+        }
+        (Some(TIMEDELTA), BinOp::Div, Some(TIMEDELTA)) => {
+            let divisor = to_duration(&rf)?.num_microseconds().unwrap_or(0);
+            if divisor == 0 {
+                return Err("division by zero".to_string());
+            }
+            Ok(Value::Float(to_duration(&lf)?.num_microseconds().unwrap_or(0) as f64 / divisor as f64))
+        }
+        (Some(TIMEDELTA), BinOp::Mul, None) | (None, BinOp::Mul, Some(TIMEDELTA)) => {
+            let (delta, factor) = if lc.is_some() { (&lf, right) } else { (&rf, left) };
+            let us = to_duration(delta)?.num_microseconds().unwrap_or(0) as f64;
+            match factor {
+                Value::Int(n) => Ok(timedelta_value(Duration::microseconds((us * *n as f64).round() as i64))),
+                Value::Float(f) => Ok(timedelta_value(Duration::microseconds((us * f).round() as i64))),
+                _ => Err(format!("Invalid operation: timedelta * {}", factor)),
+            }
+        }
+        (Some(TIMEDELTA), BinOp::Div, None) => {
+            let us = to_duration(&lf)?.num_microseconds().unwrap_or(0) as f64;
+            let divisor = match right {
+                Value::Int(n) => *n as f64,
+                Value::Float(f) => *f,
+                _ => return Err(format!("Invalid operation: timedelta / {}", right)),
+            };
+            if divisor == 0.0 {
+                return Err("division by zero".to_string());
+            }
+            Ok(timedelta_value(Duration::microseconds((us / divisor).round() as i64)))
+        }
+        _ => Err(format!(
+            "Invalid operation: {} {:?} {}",
+            lc.unwrap_or_else(|| left.to_string()),
+            op,
+            rc.unwrap_or_else(|| right.to_string())
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dt(args: Vec<Value>) -> Value {
+        call("datetime", args).unwrap()
+    }
+
+    #[test]
+    fn test_isoformat_roundtrip() {
+        let value = fromisoformat("2024-03-10T12:30:45.250000+05:30").unwrap();
+        assert_eq!(isoformat(&value), "2024-03-10T12:30:45.250000+05:30");
+        let date = fromisoformat("2024-02-29").unwrap();
+        assert_eq!(display(&date), "2024-02-29");
+        let utc = fromisoformat("2024-01-01T00:00:00Z").unwrap();
+        assert_eq!(call_method(&utc, "tzname", vec![]).unwrap().unwrap(), Value::String("UTC".to_string()));
+    }
+
+    #[test]
+    fn test_strftime_strptime() {
+        let value = strptime("2023-07-04 09:05", "%Y-%m-%d %H:%M").unwrap();
+        assert_eq!(strftime(&value, "%d/%m/%Y %H:%M:%S").unwrap(), "04/07/2023 09:05:00");
+        assert!(strftime(&value, "%Q").is_err());
+        assert!(strptime("not a date", "%Y-%m-%d").is_err());
+    }
+
+    #[test]
+    fn test_timezone_conversion_across_dst() {
+        // 2024-03-10 01:30 in New York is EST (-05:00); one hour later is EDT (-04:00)
+        let ny = dt(vec![
+            Value::Int(2024), Value::Int(3), Value::Int(10), Value::Int(1), Value::Int(30),
+            Value::Dict(vec![(Value::String("tz".to_string()), Value::String("America/New_York".to_string()))]),
+        ]);
+        assert_eq!(isoformat(&ny), "2024-03-10T01:30:00-05:00");
+        let hour = call("timedelta", vec![Value::Dict(vec![(Value::String("hours".to_string()), Value::Int(1))])]).unwrap();
+        let later = binary_op(&ny, &BinOp::Add, &hour).unwrap();
+        assert_eq!(isoformat(&later), "2024-03-10T03:30:00-04:00");
+        let tokyo = call_method(&later, "astimezone", vec![Value::String("Asia/Tokyo".to_string())]).unwrap().unwrap();
+        assert_eq!(isoformat(&tokyo), "2024-03-10T16:30:00+09:00");
+        assert_eq!(binary_op(&tokyo, &BinOp::Eq, &later).unwrap(), Value::Bool(true));
+    }
+
+    #[test]
+    fn test_timedelta_arithmetic() {
+        let a = dt(vec![Value::Int(2024), Value::Int(1), Value::Int(31)]);
+        let b = dt(vec![Value::Int(2024), Value::Int(3), Value::Int(1), Value::Int(6)]);
+        let diff = binary_op(&b, &BinOp::Sub, &a).unwrap();
+        assert_eq!(display(&diff), "30 days, 6:00:00");
+        assert_eq!(call_method(&diff, "total_seconds", vec![]).unwrap().unwrap(), Value::Float(2613600.0));
+        assert_eq!(binary_op(&a, &BinOp::Lt, &b).unwrap(), Value::Bool(true));
+    }
+
+    #[test]
+    fn test_arithmetic_overflow_is_an_error() {
+        let days = |n: i64| call("timedelta", vec![Value::Int(n)]).unwrap();
+        let date = call("date", vec![Value::Int(2024), Value::Int(1), Value::Int(1)]).unwrap();
+        let moment = dt(vec![Value::Int(2024), Value::Int(1), Value::Int(1)]);
+        let aware = fromisoformat("2024-01-01T00:00:00+00:00").unwrap();
+        for value in [&date, &moment, &aware] {
+            for op in [BinOp::Add, BinOp::Sub] {
+                let error = binary_op(value, &op, &days(100_000_000)).unwrap_err();
+                assert!(error.starts_with("OverflowError"), "{}", error);
+            }
+        }
+        let huge = instance(TIMEDELTA, vec![("days", Value::Int(i64::MAX)), ("seconds", Value::Int(0)), ("microseconds", Value::Int(0))]);
+        assert!(binary_op(&date, &BinOp::Add, &huge).unwrap_err().starts_with("OverflowError"));
+        assert!(binary_op(&huge, &BinOp::Add, &days(1)).unwrap_err().starts_with("OverflowError"));
+        assert!(call("timedelta", vec![Value::Int(10_000_000_000_000)]).unwrap_err().starts_with("OverflowError"));
+        assert_eq!(display(&binary_op(&date, &BinOp::Add, &days(366)).unwrap()), "2025-01-01");
+    }
+}
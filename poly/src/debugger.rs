@@ -0,0 +1,931 @@
+//! Debugger for Poly programs (`poly debug`, `poly dev --debug`)
+//!
+//! Programs are parsed with `Parser::with_lines`, so the interpreter calls the
+//! installed `DebugHook` before every statement. `Session` decides where to
+//! stop (line and conditional breakpoints, step in/over/out, pause requests)
+//! and hands control to a frontend while stopped:
+//! - `Terminal`: an interactive prompt for `poly debug file.poly`
+//! - `Dap`: the Debug Adapter Protocol, over stdio for `poly debug --dap`
+//!   or over TCP so VS Code can attach to `poly dev --debug`
+
+use std::collections::{BTreeSet, HashMap};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value as Json};
+
+use crate::ast::{Program, Statement, Value};
+use crate::interpreter::Interpreter;
+use crate::lexer::Lexer;
+use crate::lsp::{read_message, write_message};
+use crate::parser::Parser;
+
+const TERMINATED: &str = "Error: Execution terminated by the debugger";
+
+/// Called by the interpreter before each statement of a program parsed with
+/// `Parser::with_lines`. Returning an error stops execution.
+pub trait DebugHook: Send {
+    fn on_line(&mut self, interpreter: &mut Interpreter, line: usize) -> Result<(), String>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breakpoint {
+    pub line: usize,
+    /// Only stop when this expression is truthy in the current frame
+    pub condition: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    Entry,
+    Breakpoint,
+    Step,
+    Pause,
+}
+
+impl StopReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StopReason::Entry => "entry",
+            StopReason::Breakpoint => "breakpoint",
+            StopReason::Step => "step",
+            StopReason::Pause => "pause",
+        }
+    }
+}
+
+/// How execution continues after a stop
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resume {
+    Continue,
+    StepIn,
+    StepOver,
+    StepOut,
+    Terminate,
+}
+
+/// User interface that takes over while execution is stopped
+pub trait Frontend: Send {
+    fn stopped(&mut self, interpreter: &mut Interpreter, reason: StopReason, line: usize) -> Resume;
+
+    /// Called before every statement while running
+    fn running(&mut self, _interpreter: &Interpreter) {}
+}
+
+/// Breakpoints and pause requests, shared between a session and whoever
+/// edits them (possibly from another thread)
+#[derive(Default)]
+pub struct Breakpoints {
+    lines: Mutex<HashMap<usize, Breakpoint>>,
+    /// Lines that start a statement; breakpoints move to the next one
+    executable: Mutex<BTreeSet<usize>>,
+    pause: AtomicBool,
+}
+
+impl Breakpoints {
+    pub fn set_program(&self, program: &Program) {
+        let mut lines = BTreeSet::new();
+        executable_lines(&program.statements, &mut lines);
+        *self.executable.lock().unwrap() = lines;
+    }
+
+    /// Replace all breakpoints. Returns the line each one was placed on, or
+    /// `None` when there is no statement at or after the requested line.
+    pub fn set(&self, requested: Vec<Breakpoint>) -> Vec<Option<usize>> {
+        let executable = self.executable.lock().unwrap();
+        let mut lines = HashMap::new();
+        let placed = requested
+            .into_iter()
+            .map(|mut breakpoint| {
+                let line = executable.range(breakpoint.line..).next().copied()?;
+                breakpoint.line = line;
+                lines.insert(line, breakpoint);
+                Some(line)
+            })
+            .collect();
+        *self.lines.lock().unwrap() = lines;
+        placed
+    }
+
+    pub fn add(&self, mut breakpoint: Breakpoint) -> Option<usize> {
+        let line = self.executable.lock().unwrap().range(breakpoint.line..).next().copied()?;
+        breakpoint.line = line;
+        self.lines.lock().unwrap().insert(line, breakpoint);
+        Some(line)
+    }
+
+    pub fn remove(&self, line: usize) -> bool {
+        self.lines.lock().unwrap().remove(&line).is_some()
+    }
+
+    pub fn list(&self) -> Vec<Breakpoint> {
+        let mut list: Vec<_> = self.lines.lock().unwrap().values().cloned().collect();
+        list.sort_by_key(|b| b.line);
+        list
+    }
+
+    /// Stop at the next statement
+    pub fn request_pause(&self) {
+        self.pause.store(true, Ordering::SeqCst);
+    }
+
+    fn get(&self, line: usize) -> Option<Breakpoint> {
+        self.lines.lock().unwrap().get(&line).cloned()
+    }
+}
+
+fn executable_lines(stmts: &[Statement], lines: &mut BTreeSet<usize>) {
+    for stmt in stmts {
+        match stmt {
+            Statement::Line(line) => {
+                lines.insert(*line);
+            }
+            Statement::If { then_body, elif_branches, else_body, .. } => {
+                executable_lines(then_body, lines);
+                for (_, body) in elif_branches {
+                    executable_lines(body, lines);
+                }
+                if let Some(body) = else_body {
+                    executable_lines(body, lines);
+                }
+            }
+            Statement::While { body, .. }
+            | Statement::For { body, .. }
+            | Statement::FnDef { body, .. }
+            | Statement::With { body, .. } => executable_lines(body, lines),
+            Statement::Try { try_body, except_body, .. } => {
+                executable_lines(try_body, lines);
+                executable_lines(except_body, lines);
+            }
+            Statement::ClassDef { methods, .. } => {
+                for method in methods {
+                    executable_lines(&method.body, lines);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parse a program with the line markers the debugger needs
+pub fn parse(source: &str) -> Result<Program, String> {
+    let mut parser = Parser::new(Lexer::new(source).tokenize()).with_lines();
+    parser.parse().map_err(|e| match parser.error_location() {
+        Some((line, column, _)) => format!("line {}, column {}: {}", line, column, e),
+        None => e,
+    })
+}
+
+/// Value as shown by the debugger: strings quoted, everything else as printed
+pub fn repr(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("{:?}", s),
+        other => other.to_string(),
+    }
+}
+
+/// Members of a list, dict or instance, for expanding it in a variables view
+fn children(value: &Value) -> Vec<(String, Value)> {
+    match value {
+        Value::List(items) => items.iter().enumerate().map(|(i, v)| (format!("[{}]", i), v.clone())).collect(),
+        Value::Dict(pairs) => pairs.iter().map(|(k, v)| (repr(k), v.clone())).collect(),
+        Value::Instance { fields, .. } => {
+            let mut fields: Vec<_> = fields.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+            fields.sort_by(|a, b| a.0.cmp(&b.0));
+            fields
+        }
+        _ => Vec::new(),
+    }
+}
+
+// ============================================================================
+// Session
+// ============================================================================
+
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    Run,
+    In,
+    /// Stop once the call stack is at most this deep
+    Over(usize),
+    /// Stop once the call stack is shallower than this
+    Out(usize),
+}
+
+/// Decides where execution stops and hands control to a frontend
+pub struct Session<F: Frontend> {
+    frontend: F,
+    breakpoints: Arc<Breakpoints>,
+    step: Step,
+    stop_on_entry: bool,
+}
+
+impl<F: Frontend> Session<F> {
+    pub fn new(frontend: F, breakpoints: Arc<Breakpoints>) -> Self {
+        Self { frontend, breakpoints, step: Step::Run, stop_on_entry: false }
+    }
+
+    pub fn stop_on_entry(mut self, stop: bool) -> Self {
+        self.stop_on_entry = stop;
+        self
+    }
+
+    fn breakpoint_hit(&self, interpreter: &mut Interpreter, line: usize) -> bool {
+        let Some(breakpoint) = self.breakpoints.get(line) else { return false };
+        let Some(condition) = breakpoint.condition else { return true };
+        let frame = interpreter.frames().len().saturating_sub(1);
+        match interpreter.evaluate_in_frame(&condition, frame) {
+            Ok(value) => interpreter.is_truthy(&value),
+            // A broken condition stops so it can be fixed
+            Err(_) => true,
+        }
+    }
+}
+
+impl<F: Frontend> DebugHook for Session<F> {
+    fn on_line(&mut self, interpreter: &mut Interpreter, line: usize) -> Result<(), String> {
+        self.frontend.running(interpreter);
+        let depth = interpreter.frames().len();
+        let stepped = match self.step {
+            Step::Run => false,
+            Step::In => true,
+            Step::Over(d) => depth <= d,
+            Step::Out(d) => depth < d,
+        };
+        let reason = if std::mem::take(&mut self.stop_on_entry) {
+            StopReason::Entry
+        } else if self.breakpoints.pause.swap(false, Ordering::SeqCst) {
+            StopReason::Pause
+        } else if self.breakpoint_hit(interpreter, line) {
+            StopReason::Breakpoint
+        } else if stepped {
+            StopReason::Step
+        } else {
+            return Ok(());
+        };
+
+        self.step = match self.frontend.stopped(interpreter, reason, line) {
+            Resume::Continue => Step::Run,
+            Resume::StepIn => Step::In,
+            Resume::StepOver => Step::Over(depth),
+            Resume::StepOut => Step::Out(depth),
+            Resume::Terminate => return Err(TERMINATED.to_string()),
+        };
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Terminal frontend
+// ============================================================================
+
+const HELP: &str = "\
+  c, continue        resume until the next breakpoint
+  n, next            step over the current line
+  s, step            step into calls
+  o, out             run until the current function returns
+  b LINE [if EXPR]   set a breakpoint (b alone lists them)
+  d LINE             delete a breakpoint
+  bt, where          show the call stack
+  up, down           select the caller / callee frame
+  l, locals          show variables of the selected frame
+  g, globals         show global variables
+  p EXPR             evaluate an expression in the selected frame
+  list               show source around the selected frame
+  q, quit            stop the program";
+
+/// Interactive prompt used by `poly debug`
+pub struct Terminal<R, W> {
+    input: R,
+    output: W,
+    path: String,
+    source: Vec<String>,
+    breakpoints: Arc<Breakpoints>,
+}
+
+impl<R: BufRead + Send, W: Write + Send> Terminal<R, W> {
+    pub fn new(input: R, output: W, path: &str, source: &str, breakpoints: Arc<Breakpoints>) -> Self {
+        Self { input, output, path: path.to_string(), source: source.lines().map(String::from).collect(), breakpoints }
+    }
+
+    fn say(&mut self, text: impl std::fmt::Display) {
+        let _ = writeln!(self.output, "{}", text);
+    }
+
+    fn show_source(&mut self, line: usize, context: usize) {
+        let first = line.saturating_sub(context).max(1);
+        let last = (line + context).min(self.source.len());
+        for n in first..=last {
+            let marker = if n == line { "->" } else { "  " };
+            let text = format!("{} {:>4}  {}", marker, n, self.source[n - 1]);
+            self.say(text);
+        }
+    }
+
+    fn show_variables(&mut self, variables: Vec<(String, Value)>) {
+        if variables.is_empty() {
+            self.say("  (none)");
+        }
+        for (name, value) in variables {
+            self.say(format!("  {} = {}", name, repr(&value)));
+        }
+    }
+}
+
+impl<R: BufRead + Send, W: Write + Send> Frontend for Terminal<R, W> {
+    fn stopped(&mut self, interpreter: &mut Interpreter, reason: StopReason, line: usize) -> Resume {
+        let mut selected = interpreter.frames().len().saturating_sub(1);
+        let name = interpreter.frames().last().map_or("<module>".to_string(), |f| f.name.clone());
+        self.say(format!("stopped at {}:{} in {} ({})", self.path, line, name, reason.as_str()));
+        self.show_source(line, 2);
+
+        loop {
+            let _ = write!(self.output, "(poly-debug) ");
+            let _ = self.output.flush();
+            let mut command = String::new();
+            if self.input.read_line(&mut command).unwrap_or(0) == 0 {
+                return Resume::Terminate;
+            }
+            let command = command.trim();
+            let (word, rest) = command.split_once(' ').map_or((command, ""), |(w, r)| (w, r.trim()));
+            let frames = interpreter.frames().to_vec();
+            match word {
+                "" => {}
+                "c" | "continue" => return Resume::Continue,
+                "n" | "next" => return Resume::StepOver,
+                "s" | "step" => return Resume::StepIn,
+                "o" | "out" | "finish" => return Resume::StepOut,
+                "q" | "quit" => return Resume::Terminate,
+                "b" | "break" if rest.is_empty() => {
+                    let list = self.breakpoints.list();
+                    if list.is_empty() {
+                        self.say("  no breakpoints");
+                    }
+                    for breakpoint in list {
+                        match breakpoint.condition {
+                            Some(condition) => self.say(format!("  line {} if {}", breakpoint.line, condition)),
+                            None => self.say(format!("  line {}", breakpoint.line)),
+                        }
+                    }
+                }
+                "b" | "break" => {
+                    let (line, condition) = match rest.split_once(" if ") {
+                        Some((line, condition)) => (line, Some(condition.trim().to_string())),
+                        None => (rest, None),
+                    };
+                    match line.trim().parse::<usize>() {
+                        Ok(line) => match self.breakpoints.add(Breakpoint { line, condition }) {
+                            Some(placed) => self.say(format!("  breakpoint at line {}", placed)),
+                            None => self.say(format!("  no code at or after line {}", line)),
+                        },
+                        Err(_) => self.say("  usage: b LINE [if EXPR]"),
+                    }
+                }
+                "d" | "delete" => match rest.parse::<usize>() {
+                    Ok(line) if self.breakpoints.remove(line) => self.say(format!("  deleted breakpoint at line {}", line)),
+                    Ok(line) => self.say(format!("  no breakpoint at line {}", line)),
+                    Err(_) => self.say("  usage: d LINE"),
+                },
+                "bt" | "where" => {
+                    for (i, frame) in frames.iter().enumerate().rev() {
+                        let marker = if i == selected { ">" } else { " " };
+                        self.say(format!("{} #{} {} at {}:{}", marker, i, frame.name, self.path, frame.line));
+                    }
+                }
+                "up" | "down" => {
+                    if word == "up" && selected > 0 {
+                        selected -= 1;
+                    } else if word == "down" && selected + 1 < frames.len() {
+                        selected += 1;
+                    }
+                    if let Some(frame) = frames.get(selected) {
+                        self.say(format!("#{} {} at {}:{}", selected, frame.name, self.path, frame.line));
+                    }
+                }
+                "l" | "locals" => self.show_variables(interpreter.frame_locals(selected)),
+                "g" | "globals" => self.show_variables(interpreter.user_globals()),
+                "p" | "print" => match interpreter.evaluate_in_frame(rest, selected) {
+                    Ok(value) => self.say(format!("  {}", repr(&value))),
+                    Err(e) => self.say(format!("  {}", e)),
+                },
+                "list" => {
+                    let line = frames.get(selected).map_or(line, |f| f.line);
+                    self.show_source(line, 5);
+                }
+                "h" | "help" => self.say(HELP),
+                _ => self.say(format!("  unknown command '{}' (type help)", word)),
+            }
+        }
+    }
+}
+
+/// Run a file under the terminal debugger. Without breakpoints it stops on
+/// the first statement.
+pub fn run_terminal(path: &Path, breakpoints: &[usize]) -> Result<(), String> {
+    let source = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let program = parse(&source)?;
+    let shared = Arc::new(Breakpoints::default());
+    shared.set_program(&program);
+    for &line in breakpoints {
+        if shared.add(Breakpoint { line, condition: None }).is_none() {
+            return Err(format!("No code at or after line {}", line));
+        }
+    }
+
+    let frontend = Terminal::new(
+        BufReader::new(std::io::stdin()),
+        std::io::stdout(),
+        &path.display().to_string(),
+        &source,
+        Arc::clone(&shared),
+    );
+    let session = Session::new(frontend, shared).stop_on_entry(breakpoints.is_empty());
+    let mut interpreter = Interpreter::new();
+    interpreter.set_debug_hook(Some(Box::new(session)));
+    match interpreter.run(&program) {
+        Err(e) if e == TERMINATED => Ok(()),
+        result => result.map(|_| ()),
+    }
+}
+
+// ============================================================================
+// Debug Adapter Protocol
+// ============================================================================
+
+/// Outgoing half of a DAP connection
+struct Client {
+    writer: Mutex<Box<dyn Write + Send>>,
+    seq: AtomicI64,
+}
+
+impl Client {
+    fn send(&self, mut message: Json) {
+        message["seq"] = json!(self.seq.fetch_add(1, Ordering::SeqCst) + 1);
+        let _ = write_message(&mut *self.writer.lock().unwrap(), &message);
+    }
+
+    fn respond(&self, request: &Json, body: Json) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": true,
+            "command": request["command"],
+            "body": body,
+        }));
+    }
+
+    fn fail(&self, request: &Json, message: &str) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": false,
+            "command": request["command"],
+            "message": message,
+        }));
+    }
+
+    fn event(&self, event: &str, body: Json) {
+        self.send(json!({"type": "event", "event": event, "body": body}));
+    }
+}
+
+/// State shared by the connection reader and the session running the program
+struct DapState {
+    breakpoints: Arc<Breakpoints>,
+    path: PathBuf,
+    /// Attached to a running app: disconnecting detaches instead of terminating
+    attach: bool,
+    client: Mutex<Option<Arc<Client>>>,
+    /// Requests that need the stopped interpreter (stack, variables, stepping)
+    requests: Mutex<Option<Receiver<Json>>>,
+    stop_on_entry: AtomicBool,
+    output_seen: AtomicUsize,
+}
+
+impl DapState {
+    fn new(path: &Path, attach: bool) -> Self {
+        Self {
+            breakpoints: Arc::new(Breakpoints::default()),
+            path: path.to_path_buf(),
+            attach,
+            client: Mutex::new(None),
+            requests: Mutex::new(None),
+            stop_on_entry: AtomicBool::new(false),
+            output_seen: AtomicUsize::new(0),
+        }
+    }
+
+    fn client(&self) -> Option<Arc<Client>> {
+        self.client.lock().unwrap().clone()
+    }
+
+    fn is_entry(&self, path: &str) -> bool {
+        let path = Path::new(path);
+        match (path.canonicalize(), self.path.canonicalize()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => path == self.path,
+        }
+    }
+
+    /// Forward new program output as `output` events
+    fn flush_output(&self, interpreter: &Interpreter) {
+        let output = interpreter.get_output();
+        let seen = self.output_seen.swap(output.len(), Ordering::SeqCst).min(output.len());
+        if let Some(client) = self.client() {
+            for line in &output[seen..] {
+                client.event("output", json!({"category": "stdout", "output": format!("{}\n", line)}));
+            }
+        }
+    }
+
+    fn set_breakpoints(&self, client: &Client, request: &Json) {
+        let args = &request["arguments"];
+        let requested: Vec<Breakpoint> = args["breakpoints"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|b| {
+                Some(Breakpoint {
+                    line: b["line"].as_u64()? as usize,
+                    condition: b["condition"].as_str().filter(|c| !c.trim().is_empty()).map(String::from),
+                })
+            })
+            .collect();
+
+        if !args["source"]["path"].as_str().is_some_and(|p| self.is_entry(p)) {
+            let breakpoints: Vec<Json> = requested
+                .iter()
+                .map(|b| json!({"verified": false, "line": b.line, "message": "Breakpoints are only supported in the entry file"}))
+                .collect();
+            client.respond(request, json!({"breakpoints": breakpoints}));
+            return;
+        }
+        let lines: Vec<usize> = requested.iter().map(|b| b.line).collect();
+        let placed = self.breakpoints.set(requested);
+        let breakpoints: Vec<Json> = placed
+            .iter()
+            .zip(lines)
+            .map(|(placed, line)| match placed {
+                Some(placed) => json!({"verified": true, "line": placed}),
+                None => json!({"verified": false, "line": line, "message": "No code at or after this line"}),
+            })
+            .collect();
+        client.respond(request, json!({"breakpoints": breakpoints}));
+    }
+}
+
+/// Read requests from one connection. Configuration requests are answered
+/// here; the rest are forwarded to the session through `requests`.
+fn serve(state: Arc<DapState>, mut input: impl BufRead, writer: Box<dyn Write + Send>, requests: Sender<Json>) {
+    let client = Arc::new(Client { writer: Mutex::new(writer), seq: AtomicI64::new(0) });
+    *state.client.lock().unwrap() = Some(Arc::clone(&client));
+
+    while let Ok(Some(request)) = read_message(&mut input) {
+        if request["type"] != "request" {
+            continue;
+        }
+        match request["command"].as_str().unwrap_or_default() {
+            "initialize" => {
+                client.respond(
+                    &request,
+                    json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsConditionalBreakpoints": true,
+                        "supportsEvaluateForHovers": true,
+                        "supportsTerminateRequest": true,
+                    }),
+                );
+                client.event("initialized", json!({}));
+            }
+            "launch" | "attach" => {
+                let stop = request["arguments"]["stopOnEntry"].as_bool().unwrap_or(false);
+                state.stop_on_entry.store(stop, Ordering::SeqCst);
+                client.respond(&request, json!({}));
+            }
+            "setBreakpoints" => state.set_breakpoints(&client, &request),
+            "setExceptionBreakpoints" => client.respond(&request, json!({})),
+            "threads" => client.respond(&request, json!({"threads": [{"id": 1, "name": "main"}]})),
+            "pause" => {
+                state.breakpoints.request_pause();
+                client.respond(&request, json!({}));
+            }
+            "configurationDone" => {
+                client.respond(&request, json!({}));
+                let _ = requests.send(request);
+            }
+            "disconnect" | "terminate" => {
+                client.respond(&request, json!({}));
+                let _ = requests.send(request);
+                break;
+            }
+            _ => {
+                let _ = requests.send(request);
+            }
+        }
+    }
+
+    if state.attach {
+        state.breakpoints.set(Vec::new());
+    }
+    let mut current = state.client.lock().unwrap();
+    if current.as_ref().is_some_and(|c| Arc::ptr_eq(c, &client)) {
+        *current = None;
+    }
+}
+
+/// What a DAP `variablesReference` points at; valid until the next resume
+enum Container {
+    Locals(usize),
+    Globals,
+    Value(Value),
+}
+
+/// Frontend that drives a DAP client
+pub struct Dap {
+    state: Arc<DapState>,
+    containers: Vec<Container>,
+}
+
+impl Dap {
+    fn reference(&mut self, container: Container) -> usize {
+        self.containers.push(container);
+        self.containers.len()
+    }
+
+    fn value_reference(&mut self, value: &Value) -> usize {
+        if children(value).is_empty() {
+            0
+        } else {
+            self.reference(Container::Value(value.clone()))
+        }
+    }
+
+    fn variables(&mut self, interpreter: &Interpreter, reference: usize) -> Vec<Json> {
+        let members = match self.containers.get(reference.wrapping_sub(1)) {
+            Some(Container::Locals(frame)) => interpreter.frame_locals(*frame),
+            Some(Container::Globals) => interpreter.user_globals(),
+            Some(Container::Value(value)) => children(value),
+            None => Vec::new(),
+        };
+        members
+            .into_iter()
+            .map(|(name, value)| {
+                json!({
+                    "name": name,
+                    "value": repr(&value),
+                    "variablesReference": self.value_reference(&value),
+                })
+            })
+            .collect()
+    }
+
+    fn detached(&self) -> Resume {
+        if self.state.attach {
+            Resume::Continue
+        } else {
+            Resume::Terminate
+        }
+    }
+}
+
+impl Frontend for Dap {
+    fn running(&mut self, interpreter: &Interpreter) {
+        if !self.state.attach {
+            self.state.flush_output(interpreter);
+        }
+    }
+
+    fn stopped(&mut self, interpreter: &mut Interpreter, reason: StopReason, _line: usize) -> Resume {
+        let Some(client) = self.state.client() else { return Resume::Continue };
+        self.containers.clear();
+        client.event("stopped", json!({"reason": reason.as_str(), "threadId": 1, "allThreadsStopped": true}));
+
+        let state = Arc::clone(&self.state);
+        let requests = state.requests.lock().unwrap();
+        let Some(requests) = requests.as_ref() else { return Resume::Continue };
+        loop {
+            let Ok(request) = requests.recv() else { return self.detached() };
+            let args = &request["arguments"];
+            let top = interpreter.frames().len().saturating_sub(1);
+            let frame = args["frameId"].as_u64().map_or(top, |f| f as usize);
+            let resume = match request["command"].as_str().unwrap_or_default() {
+                "stackTrace" => {
+                    let source = json!({
+                        "name": self.state.path.file_name().map(|n| n.to_string_lossy().to_string()),
+                        "path": self.state.path.display().to_string(),
+                    });
+                    let frames: Vec<Json> = interpreter
+                        .frames()
+                        .iter()
+                        .enumerate()
+                        .rev()
+                        .map(|(id, f)| json!({"id": id, "name": f.name, "line": f.line, "column": 1, "source": source}))
+                        .collect();
+                    client.respond(&request, json!({"stackFrames": frames, "totalFrames": frames.len()}));
+                    None
+                }
+                "scopes" => {
+                    let locals = self.reference(Container::Locals(frame));
+                    let globals = self.reference(Container::Globals);
+                    client.respond(
+                        &request,
+                        json!({"scopes": [
+                            {"name": "Locals", "presentationHint": "locals", "variablesReference": locals, "expensive": false},
+                            {"name": "Globals", "variablesReference": globals, "expensive": false},
+                        ]}),
+                    );
+                    None
+                }
+                "variables" => {
+                    let reference = args["variablesReference"].as_u64().unwrap_or(0) as usize;
+                    let variables = self.variables(interpreter, reference);
+                    client.respond(&request, json!({"variables": variables}));
+                    None
+                }
+                "evaluate" => {
+                    let expression = args["expression"].as_str().unwrap_or_default();
+                    match interpreter.evaluate_in_frame(expression, frame) {
+                        Ok(value) => {
+                            let reference = self.value_reference(&value);
+                            client.respond(&request, json!({"result": repr(&value), "variablesReference": reference}));
+                        }
+                        Err(e) => client.fail(&request, &e),
+                    }
+                    None
+                }
+                "continue" => Some(Resume::Continue),
+                "next" => Some(Resume::StepOver),
+                "stepIn" => Some(Resume::StepIn),
+                "stepOut" => Some(Resume::StepOut),
+                "disconnect" | "terminate" => return self.detached(),
+                "configurationDone" => None,
+                _ => {
+                    client.fail(&request, "Unsupported request");
+                    None
+                }
+            };
+            if let Some(resume) = resume {
+                let body = if resume == Resume::Continue { json!({"allThreadsContinued": true}) } else { json!({}) };
+                client.respond(&request, body);
+                return resume;
+            }
+        }
+    }
+}
+
+/// Debug a file as a DAP server on stdin/stdout (`poly debug --dap`)
+pub fn run_dap_stdio(path: &Path) -> Result<(), String> {
+    let source = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let program = parse(&source)?;
+    let state = Arc::new(DapState::new(path, false));
+    state.breakpoints.set_program(&program);
+
+    let (sender, receiver) = channel();
+    *state.requests.lock().unwrap() = Some(receiver);
+    let reader = Arc::clone(&state);
+    std::thread::spawn(move || serve(reader, BufReader::new(std::io::stdin()), Box::new(std::io::stdout()), sender));
+
+    // Breakpoints are configured before the program starts
+    loop {
+        let request = state.requests.lock().unwrap().as_ref().and_then(|r| r.recv().ok());
+        match request.as_ref().and_then(|r| r["command"].as_str()) {
+            Some("configurationDone") => break,
+            Some("disconnect") | Some("terminate") | None => return Ok(()),
+            Some(_) => {}
+        }
+    }
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_echo_output(false);
+    let session = Session::new(Dap { state: Arc::clone(&state), containers: Vec::new() }, Arc::clone(&state.breakpoints))
+        .stop_on_entry(state.stop_on_entry.load(Ordering::SeqCst));
+    interpreter.set_debug_hook(Some(Box::new(session)));
+    let result = match interpreter.run(&program) {
+        Err(e) if e == TERMINATED => Ok(()),
+        result => result.map(|_| ()),
+    };
+
+    state.flush_output(&interpreter);
+    if let Some(client) = state.client() {
+        if let Err(e) = &result {
+            client.event("output", json!({"category": "stderr", "output": format!("{}\n", e)}));
+        }
+        client.event("exited", json!({"exitCode": if result.is_ok() { 0 } else { 1 }}));
+        client.event("terminated", json!({}));
+    }
+    result
+}
+
+/// DAP server that VS Code can attach to while `poly dev` runs
+pub struct DebugServer {
+    state: Arc<DapState>,
+}
+
+impl DebugServer {
+    /// Listen on 127.0.0.1:`port`, debugging `entry`
+    pub fn listen(port: u16, entry: &Path) -> Result<Self, String> {
+        let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|e| format!("Cannot listen on port {}: {}", port, e))?;
+        let state = Arc::new(DapState::new(entry, true));
+        let accept = Arc::clone(&state);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let Ok(writer) = stream.try_clone() else { continue };
+                let (sender, receiver) = channel();
+                *accept.requests.lock().unwrap() = Some(receiver);
+                let state = Arc::clone(&accept);
+                std::thread::spawn(move || serve(state, BufReader::new(stream), Box::new(writer), sender));
+            }
+        });
+        Ok(Self { state })
+    }
+
+    /// Parse and run `source` in `interpreter` with the debugger installed, so
+    /// later IPC calls stop at breakpoints
+    pub fn load(&self, interpreter: &mut Interpreter, source: &str) -> Result<(), String> {
+        let program = parse(source)?;
+        self.state.breakpoints.set_program(&program);
+        let frontend = Dap { state: Arc::clone(&self.state), containers: Vec::new() };
+        interpreter.set_debug_hook(Some(Box::new(Session::new(frontend, Arc::clone(&self.state.breakpoints)))));
+        interpreter.run(&program).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "fn add(a, b):\n    let total = a + b\n    return total\n\nlet x = 1\nfor i in range(3):\n    x = add(x, i)\nprint(x)\n";
+
+    fn debug(commands: &str, breakpoints: &[Breakpoint], stop_on_entry: bool) -> (String, Vec<String>) {
+        let program = parse(SOURCE).unwrap();
+        let shared = Arc::new(Breakpoints::default());
+        shared.set_program(&program);
+        shared.set(breakpoints.to_vec());
+        let output = Arc::new(Mutex::new(Vec::new()));
+
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let terminal = Terminal::new(
+            std::io::Cursor::new(commands.to_string()),
+            Shared(Arc::clone(&output)),
+            "main.poly",
+            SOURCE,
+            Arc::clone(&shared),
+        );
+        let mut interpreter = Interpreter::new();
+        interpreter.set_echo_output(false);
+        interpreter.set_debug_hook(Some(Box::new(Session::new(terminal, shared).stop_on_entry(stop_on_entry))));
+        let _ = interpreter.run(&program);
+        let text = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        (text, interpreter.get_output().to_vec())
+    }
+
+    #[test]
+    fn test_conditional_breakpoint_stack_and_eval() {
+        let breakpoint = Breakpoint { line: 2, condition: Some("b == 2".to_string()) };
+        let (text, output) = debug("bt\nl\np a * 10\nup\np x\nc\n", &[breakpoint], false);
+        assert!(text.starts_with("stopped at main.poly:2 in add (breakpoint)"), "{}", text);
+        assert!(text.contains("> #1 add at main.poly:2\n  #0 <module> at main.poly:7"));
+        assert!(text.contains("  a = 2\n  b = 2\n"));
+        assert!(text.contains("  20\n"));
+        assert!(text.contains("#0 <module> at main.poly:7\n(poly-debug)   2\n"));
+        assert_eq!(text.matches("stopped at").count(), 1);
+        assert_eq!(output, vec!["4"]);
+    }
+
+    #[test]
+    fn test_stepping() {
+        // Entry is line 1 (the fn definition); breakpoints move to the next statement
+        let (text, output) = debug("n\nn\ns\ns\no\nb 8\nc\nq\n", &[], true);
+        let stops: Vec<&str> = text.lines().filter(|l| l.contains("stopped at")).map(|l| l.trim_start_matches("(poly-debug) ")).collect();
+        assert_eq!(
+            stops,
+            vec![
+                "stopped at main.poly:1 in <module> (entry)",
+                "stopped at main.poly:5 in <module> (step)",
+                "stopped at main.poly:6 in <module> (step)",
+                "stopped at main.poly:7 in <module> (step)",
+                "stopped at main.poly:2 in add (step)",
+                "stopped at main.poly:7 in <module> (step)",
+                "stopped at main.poly:8 in <module> (breakpoint)",
+            ]
+        );
+        assert!(output.is_empty());
+
+        let shared = Breakpoints::default();
+        shared.set_program(&parse(SOURCE).unwrap());
+        assert_eq!(shared.set(vec![Breakpoint { line: 4, condition: None }, Breakpoint { line: 9, condition: None }]), vec![Some(5), None]);
+    }
+}
@@ -0,0 +1,177 @@
+//! Request checks for the local servers behind `poly dev` and native mode
+//!
+//! These servers can call backend functions, the `__poly_fs_*` and
+//! `__poly_shell_*` system APIs and read project files, so they only answer
+//! the page they served:
+//!
+//! - the `Host` header must name the address the server listens on, which
+//!   defeats DNS rebinding
+//! - an `Origin` header, when present, must be that same host, so other sites
+//!   cannot send requests from the user's browser
+//! - IPC endpoints need the per-session token injected into the page, sent as
+//!   `X-Poly-Token` (or `?token=` where headers cannot be set)
+//! - static files resolve only inside `web/` and `packages/`, after
+//!   canonicalisation, so `..` and symlinks cannot leave them
+
+use std::path::{Component, Path, PathBuf};
+
+/// Header carrying the session token
+pub const TOKEN_HEADER: &str = "X-Poly-Token";
+
+/// Host and token checks for one server session
+#[derive(Debug, Clone)]
+pub struct DevGuard {
+    token: String,
+    /// `host:port` values accepted in `Host`; empty when bound to all interfaces
+    hosts: Vec<String>,
+}
+
+impl DevGuard {
+    /// Guard for a server bound to `host:port`, with a fresh token
+    pub fn new(host: &str, port: u16) -> Self {
+        let bytes = crate::crypto::random_bytes(32).expect("no OS random number generator for the session token");
+        Self::with_token(host, port, crate::crypto::hex_encode(&bytes))
+    }
+
+    pub fn with_token(host: &str, port: u16, token: String) -> Self {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let hosts = if host == "0.0.0.0" || host == "::" {
+            Vec::new()
+        } else {
+            let mut names = vec!["localhost".to_string(), "127.0.0.1".to_string(), "[::1]".to_string()];
+            let bound = if host.contains(':') { format!("[{}]", host) } else { host.to_string() };
+            if !names.contains(&bound) {
+                names.push(bound);
+            }
+            names.iter().map(|name| format!("{}:{}", name, port)).collect()
+        };
+        DevGuard { token, hosts }
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// `<script>` that hands the token to the injected bridge
+    pub fn token_script(&self) -> String {
+        format!("<script>window.__POLY_TOKEN__ = \"{}\";</script>", self.token)
+    }
+
+    /// Check the `Host` and `Origin` headers of any request
+    pub fn check_origin(&self, host: Option<&str>, origin: Option<&str>) -> Result<(), String> {
+        let host = host.ok_or("Missing Host header")?.to_ascii_lowercase();
+        if !self.hosts.is_empty() && !self.hosts.contains(&host) {
+            return Err(format!("Host '{}' is not allowed", host));
+        }
+        match origin {
+            None => Ok(()),
+            Some(origin) if origin.eq_ignore_ascii_case(&format!("http://{}", host)) => Ok(()),
+            Some(origin) => Err(format!("Origin '{}' is not allowed", origin)),
+        }
+    }
+
+    /// Check the session token of an IPC request, from the header or `?token=`
+    pub fn check_token(&self, header: Option<&str>, url: &str) -> Result<(), String> {
+        let query = url.split_once('?').map_or("", |(_, query)| query);
+        let from_query = query.split('&').find_map(|pair| pair.strip_prefix("token="));
+        match header.or(from_query) {
+            Some(token) if crate::crypto::compare_digest(token.as_bytes(), self.token.as_bytes()) => Ok(()),
+            Some(_) => Err("Invalid session token".to_string()),
+            None => Err("Missing session token".to_string()),
+        }
+    }
+
+    /// Check a request; IPC requests (`needs_token`) must also carry the token
+    pub fn check(&self, request: &tiny_http::Request, needs_token: bool) -> Result<(), String> {
+        let header = |name: &str| request.headers().iter()
+            .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case(name))
+            .map(|h| h.value.as_str().to_string());
+        self.check_origin(header("Host").as_deref(), header("Origin").as_deref())?;
+        if needs_token {
+            self.check_token(header(TOKEN_HEADER).as_deref(), request.url())?;
+        }
+        Ok(())
+    }
+}
+
+/// 403 response for a request that failed a check
+pub fn forbidden(reason: &str) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    tiny_http::Response::from_string(serde_json::json!({"error": reason, "code": "FORBIDDEN"}).to_string())
+        .with_status_code(403)
+        .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap())
+}
+
+/// File for a static request: `/packages/...` from `packages`, anything else
+/// from `web`; `None` if it does not exist or would leave its root
+pub fn static_path(web: &Path, packages: &Path, url: &str) -> Option<PathBuf> {
+    let path = url.split(['?', '#']).next().unwrap_or("");
+    let path = urlencoding::decode(path).ok()?;
+    let path = path.trim_start_matches('/');
+    let path = if path.is_empty() { "index.html" } else { path };
+    let (root, relative) = match path.strip_prefix("packages/") {
+        Some(rest) => (packages, rest),
+        None => (web, path),
+    };
+    let relative = Path::new(relative);
+    if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        return None;
+    }
+    let root = std::fs::canonicalize(root).ok()?;
+    let file = std::fs::canonicalize(root.join(relative)).ok()?;
+    (file.starts_with(&root) && file.is_file()).then_some(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_foreign_hosts_origins_and_tokens() {
+        let guard = DevGuard::with_token("127.0.0.1", 3000, "secret".to_string());
+        assert!(guard.check_origin(Some("localhost:3000"), None).is_ok());
+        assert!(guard.check_origin(Some("127.0.0.1:3000"), Some("http://127.0.0.1:3000")).is_ok());
+        // DNS rebinding: the attacker's name resolves to 127.0.0.1
+        assert!(guard.check_origin(Some("evil.example:3000"), Some("http://evil.example:3000")).is_err());
+        // Cross-site request from a page the user has open
+        assert!(guard.check_origin(Some("localhost:3000"), Some("https://evil.example")).is_err());
+        assert!(guard.check_origin(Some("localhost:3000"), Some("null")).is_err());
+        assert!(guard.check_origin(None, None).is_err());
+
+        assert!(guard.check_token(Some("secret"), "/__poly_invoke").is_ok());
+        assert!(guard.check_token(None, "/__poly_events?window=main&token=secret").is_ok());
+        assert!(guard.check_token(Some("guess"), "/__poly_invoke").is_err());
+        assert!(guard.check_token(None, "/__poly_invoke").is_err());
+
+        let lan = DevGuard::with_token("0.0.0.0", 3000, "secret".to_string());
+        assert!(lan.check_origin(Some("192.168.1.20:3000"), Some("http://192.168.1.20:3000")).is_ok());
+        assert!(lan.check_origin(Some("192.168.1.20:3000"), Some("https://evil.example")).is_err());
+    }
+
+    #[test]
+    fn test_static_paths_stay_inside_roots() {
+        let dir = std::env::temp_dir().join(format!("poly_dev_security_{}", std::process::id()));
+        let (web, packages) = (dir.join("web"), dir.join("packages"));
+        std::fs::create_dir_all(web.join("css")).unwrap();
+        std::fs::create_dir_all(packages.join("lib")).unwrap();
+        std::fs::write(web.join("index.html"), "ok").unwrap();
+        std::fs::write(web.join("css/app.css"), "ok").unwrap();
+        std::fs::write(packages.join("lib/lib.js"), "ok").unwrap();
+        std::fs::write(dir.join("poly.toml"), "secret").unwrap();
+
+        let web_file = |name: &str| std::fs::canonicalize(web.join(name)).ok();
+        assert_eq!(static_path(&web, &packages, "/"), web_file("index.html"));
+        assert_eq!(static_path(&web, &packages, "/css/app.css?v=2"), web_file("css/app.css"));
+        assert_eq!(static_path(&web, &packages, "/packages/lib/lib.js"), std::fs::canonicalize(packages.join("lib/lib.js")).ok());
+
+        for attack in ["/../poly.toml", "/css/../../poly.toml", "/%2e%2e/poly.toml", "/..%2fpoly.toml",
+                       "/packages/../poly.toml", "//etc/passwd", "/main.poly", "/css"] {
+            assert_eq!(static_path(&web, &packages, attack), None, "{}", attack);
+        }
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.join("poly.toml"), web.join("link.txt")).unwrap();
+            assert_eq!(static_path(&web, &packages, "/link.txt"), None);
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
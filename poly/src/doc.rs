@@ -0,0 +1,618 @@
+//! Documentation generator (`poly doc`)
+//!
+//! Collects module, function and class docstrings (a string literal as the
+//! first statement) with signatures and defaults from `ast::Param`, and
+//! renders them as a static HTML site or JSON. The `__poly_*` system API
+//! reference comes from `reference::POLY_APIS` and can also be written into
+//! a Markdown file such as `docs/API.md`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::ast::{BinOp, Expr, Param, Statement, UnaryOp};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::reference::{ApiDoc, POLY_APIS};
+
+/// Markers around the generated part of a Markdown file
+pub const BEGIN_MARKER: &str = "<!-- BEGIN GENERATED: poly doc --api-md -->";
+pub const END_MARKER: &str = "<!-- END GENERATED -->";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    /// Dotted path relative to the project root (`src/models.poly` -> `src.models`)
+    pub name: String,
+    pub path: PathBuf,
+    pub doc: Option<String>,
+    pub functions: Vec<Function>,
+    pub classes: Vec<Class>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    /// Parameter names with their default values as source text
+    pub params: Vec<(String, Option<String>)>,
+    pub doc: Option<String>,
+    /// Only known for top-level definitions
+    pub line: Option<usize>,
+}
+
+impl Function {
+    pub fn signature(&self) -> String {
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .map(|(name, default)| match default {
+                Some(value) => format!("{}={}", name, value),
+                None => name.clone(),
+            })
+            .collect();
+        format!("{}({})", self.name, params.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Class {
+    pub name: String,
+    pub parent: Option<String>,
+    pub doc: Option<String>,
+    pub line: Option<usize>,
+    /// Constructor parameters from `__init__`, without `self`
+    pub params: Vec<(String, Option<String>)>,
+    pub methods: Vec<Function>,
+}
+
+// ============================================================================
+// Extraction
+// ============================================================================
+
+/// The docstring of a body: a string literal as its first statement
+pub(crate) fn docstring(body: &[Statement]) -> Option<String> {
+    match body.iter().find(|stmt| !matches!(stmt, Statement::Line(_))) {
+        Some(Statement::Expr(Expr::String(s))) => Some(clean(s)),
+        _ => None,
+    }
+}
+
+/// Strip the indentation shared by every line after the first, and
+/// surrounding blank lines. The line holding the closing quotes counts, so a
+/// docstring may consist of a summary and an indented example.
+fn clean(doc: &str) -> String {
+    let doc = crate::interpreter::process_escapes(doc);
+    let lines: Vec<&str> = doc.split('\n').collect();
+    let last = lines.len() - 1;
+    let indent = lines
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(i, line)| !line.trim().is_empty() || (*i == last && !line.is_empty()))
+        .map(|(_, line)| line)
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut out: Vec<&str> = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| if i == 0 { line.trim() } else { line.get(indent..).unwrap_or("").trim_end() })
+        .collect();
+    while out.first().is_some_and(|line| line.is_empty()) {
+        out.remove(0);
+    }
+    while out.last().is_some_and(|line| line.is_empty()) {
+        out.pop();
+    }
+    out.join("\n")
+}
+
+fn params(params: &[Param]) -> Vec<(String, Option<String>)> {
+    params.iter().map(|p| (p.name.clone(), p.default.as_ref().map(expr_source))).collect()
+}
+
+fn binop(op: &BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "+",
+        BinOp::Sub => "-",
+        BinOp::Mul => "*",
+        BinOp::Div => "/",
+        BinOp::FloorDiv => "//",
+        BinOp::Mod => "%",
+        BinOp::Pow => "**",
+        BinOp::Eq => "==",
+        BinOp::NotEq => "!=",
+        BinOp::Lt => "<",
+        BinOp::Gt => ">",
+        BinOp::LtEq => "<=",
+        BinOp::GtEq => ">=",
+        BinOp::And => "and",
+        BinOp::Or => "or",
+        BinOp::In => "in",
+        BinOp::Is => "is",
+        BinOp::BitAnd => "&",
+        BinOp::BitOr => "|",
+        BinOp::BitXor => "^",
+        BinOp::LShift => "<<",
+        BinOp::RShift => ">>",
+    }
+}
+
+/// Source text for a default value
+fn expr_source(expr: &Expr) -> String {
+    let list = |items: &[Expr]| items.iter().map(expr_source).collect::<Vec<_>>().join(", ");
+    match expr {
+        Expr::None => "none".to_string(),
+        Expr::Bool(b) => b.to_string(),
+        Expr::Int(n) => n.to_string(),
+        Expr::Float(f) => format!("{:?}", f),
+        // Literals keep their escapes until they are used
+        Expr::String(s) => format!("\"{}\"", s),
+        Expr::List(items) => format!("[{}]", list(items)),
+        Expr::Dict(pairs) => {
+            let pairs: Vec<String> = pairs.iter().map(|(k, v)| format!("{}: {}", expr_source(k), expr_source(v))).collect();
+            format!("{{{}}}", pairs.join(", "))
+        }
+        Expr::Identifier(name) => name.clone(),
+        Expr::Attribute(target, attr) => format!("{}.{}", expr_source(target), attr),
+        Expr::Index(target, index) => format!("{}[{}]", expr_source(target), expr_source(index)),
+        Expr::BinaryOp(left, op, right) => format!("{} {} {}", expr_source(left), binop(op), expr_source(right)),
+        Expr::UnaryOp(UnaryOp::Neg, operand) => format!("-{}", expr_source(operand)),
+        Expr::UnaryOp(UnaryOp::Not, operand) => format!("not {}", expr_source(operand)),
+        Expr::UnaryOp(UnaryOp::BitNot, operand) => format!("~{}", expr_source(operand)),
+        Expr::Call(func, args) => format!("{}({})", expr_source(func), list(args)),
+        Expr::CallWithKwargs(func, args, kwargs) => {
+            let mut all: Vec<String> = args.iter().map(expr_source).collect();
+            all.extend(kwargs.iter().map(|(k, v)| format!("{}={}", k, expr_source(v))));
+            format!("{}({})", expr_source(func), all.join(", "))
+        }
+        _ => "...".to_string(),
+    }
+}
+
+fn is_public(name: &str) -> bool {
+    !name.starts_with('_')
+}
+
+/// Document one module's source
+pub fn document(source: &str, name: &str, path: &Path) -> Result<Module, String> {
+    let program = Parser::new(Lexer::new(source).tokenize()).with_lines().parse()?;
+    let mut module = Module {
+        name: name.to_string(),
+        path: path.to_path_buf(),
+        doc: docstring(&program.statements),
+        functions: Vec::new(),
+        classes: Vec::new(),
+    };
+    let mut line = None;
+    for stmt in &program.statements {
+        match stmt {
+            Statement::Line(n) => line = Some(*n),
+            Statement::FnDef { name, params: fn_params, body, .. } if is_public(name) => {
+                module.functions.push(Function { name: name.clone(), params: params(fn_params), doc: docstring(body), line });
+            }
+            Statement::ClassDef { name, parent, methods, doc } if is_public(name) => {
+                let init = methods.iter().find(|m| m.name == "__init__");
+                module.classes.push(Class {
+                    name: name.clone(),
+                    parent: parent.clone(),
+                    doc: doc.as_deref().map(clean),
+                    line,
+                    params: init.map(|m| params(&m.params).into_iter().skip(1).collect()).unwrap_or_default(),
+                    methods: methods
+                        .iter()
+                        .filter(|m| is_public(&m.name))
+                        .map(|m| Function {
+                            name: m.name.clone(),
+                            params: params(&m.params).into_iter().skip(1).collect(),
+                            doc: docstring(&m.body),
+                            line: None,
+                        })
+                        .collect(),
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(module)
+}
+
+/// Document every `.poly` file under `root`. Files that fail to parse are
+/// returned as errors.
+pub fn collect(root: &Path) -> (Vec<Module>, Vec<String>) {
+    let mut modules = Vec::new();
+    let mut errors = Vec::new();
+    for path in crate::fmt::discover(root) {
+        let relative = path.strip_prefix(root).ok().filter(|p| !p.as_os_str().is_empty()).unwrap_or(&path);
+        let name = relative.with_extension("").components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join(".");
+        let result = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|source| document(&source, &name, relative));
+        match result {
+            Ok(module) => modules.push(module),
+            Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    (modules, errors)
+}
+
+/// `__poly_*` APIs grouped by namespace, in namespace order
+fn api_namespaces() -> Vec<(&'static str, Vec<&'static ApiDoc>)> {
+    let mut namespaces: Vec<(&str, Vec<&ApiDoc>)> = Vec::new();
+    for api in POLY_APIS {
+        match namespaces.iter_mut().find(|(ns, _)| *ns == api.namespace) {
+            Some((_, apis)) => apis.push(api),
+            None => namespaces.push((api.namespace, vec![api])),
+        }
+    }
+    namespaces.sort_by_key(|(ns, _)| *ns);
+    namespaces
+}
+
+// ============================================================================
+// JSON
+// ============================================================================
+
+fn function_json(f: &Function) -> serde_json::Value {
+    let params: Vec<_> = f.params.iter().map(|(name, default)| serde_json::json!({"name": name, "default": default})).collect();
+    serde_json::json!({"name": f.name, "signature": f.signature(), "params": params, "doc": f.doc, "line": f.line})
+}
+
+pub fn to_json(modules: &[Module]) -> String {
+    let modules: Vec<_> = modules
+        .iter()
+        .map(|m| {
+            let classes: Vec<_> = m
+                .classes
+                .iter()
+                .map(|c| {
+                    let params: Vec<_> = c.params.iter().map(|(name, default)| serde_json::json!({"name": name, "default": default})).collect();
+                    serde_json::json!({
+                        "name": c.name,
+                        "parent": c.parent,
+                        "doc": c.doc,
+                        "line": c.line,
+                        "params": params,
+                        "methods": c.methods.iter().map(function_json).collect::<Vec<_>>(),
+                    })
+                })
+                .collect();
+            serde_json::json!({
+                "name": m.name,
+                "path": m.path.display().to_string().replace('\\', "/"),
+                "doc": m.doc,
+                "functions": m.functions.iter().map(function_json).collect::<Vec<_>>(),
+                "classes": classes,
+            })
+        })
+        .collect();
+    let apis: Vec<_> = POLY_APIS
+        .iter()
+        .map(|a| serde_json::json!({"name": a.name, "namespace": a.namespace, "params": a.params, "summary": a.summary}))
+        .collect();
+    serde_json::to_string_pretty(&serde_json::json!({"modules": modules, "system_api": apis})).unwrap_or_else(|_| "{}".to_string())
+}
+
+// ============================================================================
+// HTML
+// ============================================================================
+
+const STYLE: &str = "
+body { font-family: -apple-system, 'Segoe UI', sans-serif; margin: 0; display: flex; color: #1f2328; }
+nav { width: 240px; padding: 1.5rem; border-right: 1px solid #d0d7de; min-height: 100vh; background: #f6f8fa; }
+nav a { display: block; padding: 2px 0; }
+main { padding: 1.5rem 2.5rem; max-width: 860px; }
+a { color: #0969da; text-decoration: none; }
+a:hover { text-decoration: underline; }
+code, pre { font-family: ui-monospace, Consolas, monospace; font-size: 0.9em; }
+pre { background: #f6f8fa; padding: 0.75rem; border-radius: 6px; overflow-x: auto; }
+.item { border-top: 1px solid #d0d7de; padding-top: 0.5rem; margin-top: 1.5rem; }
+.signature { font-weight: 600; }
+.muted { color: #656d76; }
+table { border-collapse: collapse; width: 100%; }
+td, th { text-align: left; padding: 4px 8px; border-bottom: 1px solid #d0d7de; vertical-align: top; }
+";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Where every documented name lives, for cross-links
+struct Links {
+    /// `name` and `module.name` -> page#anchor
+    targets: HashMap<String, Vec<String>>,
+}
+
+impl Links {
+    fn new(modules: &[Module]) -> Self {
+        let mut targets: HashMap<String, Vec<String>> = HashMap::new();
+        let mut add = |key: String, url: String| targets.entry(key).or_default().push(url);
+        for m in modules {
+            let page = page_name(&m.name);
+            add(m.name.clone(), page.clone());
+            for f in &m.functions {
+                add(f.name.clone(), format!("{}#{}", page, f.name));
+                add(format!("{}.{}", m.name, f.name), format!("{}#{}", page, f.name));
+            }
+            for c in &m.classes {
+                add(c.name.clone(), format!("{}#{}", page, c.name));
+                add(format!("{}.{}", m.name, c.name), format!("{}#{}", page, c.name));
+                for method in &c.methods {
+                    add(format!("{}.{}", c.name, method.name), format!("{}#{}.{}", page, c.name, method.name));
+                }
+            }
+        }
+        for api in POLY_APIS {
+            add(api.name.to_string(), format!("system-api.html#{}", api.name));
+        }
+        Self { targets }
+    }
+
+    /// Link for a name, preferring a definition on the current page
+    fn resolve(&self, name: &str, page: &str) -> Option<String> {
+        let urls = self.targets.get(name.trim_end_matches("()"))?;
+        let local = urls.iter().find(|url| url.starts_with(&format!("{}#", page)) || *url == page);
+        Some(local.unwrap_or(&urls[0]).clone())
+    }
+
+    fn code(&self, name: &str, page: &str) -> String {
+        match self.resolve(name, page) {
+            Some(url) => format!("<a href=\"{}\"><code>{}</code></a>", url, escape(name)),
+            None => format!("<code>{}</code>", escape(name)),
+        }
+    }
+}
+
+fn page_name(module: &str) -> String {
+    format!("{}.html", module)
+}
+
+/// Docstring to HTML: blank lines separate paragraphs, indented or fenced
+/// blocks are code, and `backticked` names link to their definitions
+fn render_doc(doc: &str, links: &Links, page: &str) -> String {
+    let inline = |text: &str| {
+        text.split('`')
+            .enumerate()
+            .map(|(i, part)| if i % 2 == 1 { links.code(part, page) } else { escape(part) })
+            .collect::<String>()
+    };
+    let mut out = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut code: Option<Vec<&str>> = None;
+    let flush = |out: &mut String, paragraph: &mut Vec<&str>| {
+        if !paragraph.is_empty() {
+            out.push_str(&format!("<p>{}</p>\n", inline(&paragraph.join(" "))));
+            paragraph.clear();
+        }
+    };
+    for line in doc.lines() {
+        if let Some(block) = &mut code {
+            if line.trim_start().starts_with("```") {
+                out.push_str(&format!("<pre><code>{}</code></pre>\n", escape(&block.join("\n"))));
+                code = None;
+            } else {
+                block.push(line);
+            }
+        } else if line.trim_start().starts_with("```") {
+            flush(&mut out, &mut paragraph);
+            code = Some(Vec::new());
+        } else if let Some(indented) = line.strip_prefix("    ") {
+            flush(&mut out, &mut paragraph);
+            out.push_str(&format!("<pre><code>{}</code></pre>\n", escape(indented)));
+        } else if line.trim().is_empty() {
+            flush(&mut out, &mut paragraph);
+        } else {
+            paragraph.push(line.trim());
+        }
+    }
+    if let Some(block) = code {
+        out.push_str(&format!("<pre><code>{}</code></pre>\n", escape(&block.join("\n"))));
+    }
+    flush(&mut out, &mut paragraph);
+    // Consecutive indented lines become one block
+    out.replace("</code></pre>\n<pre><code>", "\n")
+}
+
+fn summary(doc: &Option<String>) -> String {
+    doc.as_deref().and_then(|d| d.split("\n\n").next()).unwrap_or("").replace('\n', " ")
+}
+
+fn layout(title: &str, modules: &[Module], body: &str) -> String {
+    let mut nav = String::from("<a href=\"index.html\"><strong>Index</strong></a>\n");
+    for m in modules {
+        nav.push_str(&format!("<a href=\"{}\">{}</a>\n", page_name(&m.name), escape(&m.name)));
+    }
+    nav.push_str("<a href=\"system-api.html\">System API</a>\n");
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<nav>\n{}</nav>\n<main>\n{}</main>\n</body>\n</html>\n",
+        escape(title),
+        STYLE,
+        nav,
+        body
+    )
+}
+
+fn render_function(f: &Function, anchor: &str, links: &Links, page: &str) -> String {
+    let mut out = format!("<div class=\"item\" id=\"{}\">\n<p class=\"signature\"><code>fn {}</code>", escape(anchor), escape(&f.signature()));
+    if let Some(line) = f.line {
+        out.push_str(&format!(" <span class=\"muted\">line {}</span>", line));
+    }
+    out.push_str("</p>\n");
+    if let Some(doc) = &f.doc {
+        out.push_str(&render_doc(doc, links, page));
+    }
+    out.push_str("</div>\n");
+    out
+}
+
+fn render_module(m: &Module, links: &Links) -> String {
+    let page = page_name(&m.name);
+    let mut body = format!("<h1>{}</h1>\n<p class=\"muted\">{}</p>\n", escape(&m.name), escape(&m.path.display().to_string()));
+    if let Some(doc) = &m.doc {
+        body.push_str(&render_doc(doc, links, &page));
+    }
+    if !m.classes.is_empty() {
+        body.push_str("<h2>Classes</h2>\n");
+        for c in &m.classes {
+            let params: Vec<String> = c.params.iter().map(|(n, d)| d.as_ref().map_or(n.clone(), |d| format!("{}={}", n, d))).collect();
+            body.push_str(&format!("<div class=\"item\" id=\"{}\">\n<p class=\"signature\"><code>class {}({})</code>", escape(&c.name), escape(&c.name), escape(&params.join(", "))));
+            if let Some(parent) = &c.parent {
+                body.push_str(&format!(" <span class=\"muted\">extends {}</span>", links.code(parent, &page)));
+            }
+            if let Some(line) = c.line {
+                body.push_str(&format!(" <span class=\"muted\">line {}</span>", line));
+            }
+            body.push_str("</p>\n");
+            if let Some(doc) = &c.doc {
+                body.push_str(&render_doc(doc, links, &page));
+            }
+            for method in &c.methods {
+                body.push_str(&render_function(method, &format!("{}.{}", c.name, method.name), links, &page));
+            }
+            body.push_str("</div>\n");
+        }
+    }
+    if !m.functions.is_empty() {
+        body.push_str("<h2>Functions</h2>\n");
+        for f in &m.functions {
+            body.push_str(&render_function(f, &f.name, links, &page));
+        }
+    }
+    body
+}
+
+fn render_index(modules: &[Module]) -> String {
+    let mut body = String::from("<h1>Documentation</h1>\n<table>\n");
+    for m in modules {
+        body.push_str(&format!("<tr><td><a href=\"{}\">{}</a></td><td>{}</td></tr>\n", page_name(&m.name), escape(&m.name), escape(&summary(&m.doc))));
+    }
+    body.push_str("<tr><td><a href=\"system-api.html\">System API</a></td><td>The <code>__poly_*</code> functions callable from JavaScript</td></tr>\n</table>\n");
+    body
+}
+
+fn render_system_api() -> String {
+    let mut body = String::from("<h1>System API</h1>\n<p>Built-in functions callable from JavaScript with <code>poly.invoke(name, args)</code>. Arguments are passed as one object.</p>\n");
+    for (namespace, apis) in api_namespaces() {
+        body.push_str(&format!("<h2 id=\"ns-{0}\">{0}</h2>\n<table>\n", escape(namespace)));
+        for api in apis {
+            body.push_str(&format!("<tr id=\"{}\"><td><code>{}</code></td><td>{}</td></tr>\n", api.name, escape(&api.signature()), escape(api.summary)));
+        }
+        body.push_str("</table>\n");
+    }
+    body
+}
+
+/// Write the HTML site into `out`. Returns the pages written.
+pub fn write_site(modules: &[Module], out: &Path) -> Result<Vec<PathBuf>, String> {
+    std::fs::create_dir_all(out).map_err(|e| format!("Cannot create {}: {}", out.display(), e))?;
+    let links = Links::new(modules);
+    let mut pages = vec![
+        ("index.html".to_string(), layout("Documentation", modules, &render_index(modules))),
+        ("system-api.html".to_string(), layout("System API", modules, &render_system_api())),
+    ];
+    for m in modules {
+        pages.push((page_name(&m.name), layout(&m.name, modules, &render_module(m, &links))));
+    }
+    let mut written = Vec::new();
+    for (name, html) in pages {
+        let path = out.join(name);
+        std::fs::write(&path, html).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+// ============================================================================
+// Markdown
+// ============================================================================
+
+/// Markdown reference of the `__poly_*` system API
+pub fn api_markdown() -> String {
+    let mut out = String::from("## System API Reference\n\n");
+    out.push_str("Every built-in backend function, callable with `poly.invoke(name, args)`. The `poly.*` wrappers documented above call these. ");
+    out.push_str("This section is generated from the interpreter with `poly doc --api-md docs/API.md`; edit `src/reference.rs` instead of this text.\n\n");
+    for (namespace, apis) in api_namespaces() {
+        out.push_str(&format!("### `{}`\n\n| Function | Arguments | Description |\n|----------|-----------|-------------|\n", namespace));
+        for api in apis {
+            let args = if api.params.is_empty() { "-".to_string() } else { api.params.iter().map(|p| format!("`{}`", p)).collect::<Vec<_>>().join(", ") };
+            out.push_str(&format!("| `{}` | {} | {} |\n", api.name, args, api.summary));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Replace the generated section of a Markdown document, or append one
+pub fn update_markdown(existing: &str) -> String {
+    let section = format!("{}\n{}{}", BEGIN_MARKER, api_markdown(), END_MARKER);
+    match (existing.find(BEGIN_MARKER), existing.find(END_MARKER)) {
+        (Some(start), Some(end)) if start < end => {
+            format!("{}{}{}", &existing[..start], section, &existing[end + END_MARKER.len()..])
+        }
+        _ => format!("{}\n\n---\n\n{}\n", existing.trim_end(), section),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#""""Shapes and helpers.
+
+See `area` and `Circle`.
+"""
+
+import math
+
+fn area(shape, scale=1.5, unit="cm", tags=[]):
+    """Area of a shape in square `unit`s.
+
+        area(Circle(2))
+    """
+    return shape.area() * scale
+
+fn _helper():
+    return none
+
+class Circle(Shape):
+    """A circle."""
+    fn __init__(self, radius, center=none):
+        self.radius = radius
+
+    fn area(self):
+        """Area using `math.pi`."""
+        return math.pi * self.radius ** 2
+"#;
+
+    #[test]
+    fn test_document_module() {
+        let module = document(SOURCE, "shapes", Path::new("shapes.poly")).unwrap();
+        assert_eq!(module.doc.as_deref(), Some("Shapes and helpers.\n\nSee `area` and `Circle`."));
+        assert_eq!(module.functions.len(), 1);
+        let area = &module.functions[0];
+        assert_eq!(area.signature(), "area(shape, scale=1.5, unit=\"cm\", tags=[])");
+        assert_eq!(area.doc.as_deref(), Some("Area of a shape in square `unit`s.\n\n    area(Circle(2))"));
+        assert_eq!(area.line, Some(8));
+        let circle = &module.classes[0];
+        assert_eq!((circle.name.as_str(), circle.parent.as_deref(), circle.doc.as_deref()), ("Circle", Some("Shape"), Some("A circle.")));
+        assert_eq!(circle.params, vec![("radius".to_string(), None), ("center".to_string(), Some("none".to_string()))]);
+        assert_eq!(circle.methods.iter().map(|m| m.signature()).collect::<Vec<_>>(), vec!["area()"]);
+
+        let json: serde_json::Value = serde_json::from_str(&to_json(std::slice::from_ref(&module))).unwrap();
+        assert_eq!(json["modules"][0]["functions"][0]["params"][1]["default"], "1.5");
+        assert!(json["system_api"].as_array().unwrap().len() == POLY_APIS.len());
+
+        let links = Links::new(std::slice::from_ref(&module));
+        let html = render_module(&module, &links);
+        assert!(html.contains("<a href=\"shapes.html#Circle\"><code>Circle</code></a>"));
+        assert!(html.contains("<pre><code>area(Circle(2))</code></pre>"));
+        assert!(html.contains("id=\"Circle.area\""));
+    }
+
+    #[test]
+    fn test_update_markdown_section() {
+        let first = update_markdown("# API\n\nHand-written intro.\n");
+        assert!(first.starts_with("# API\n\nHand-written intro.\n\n---\n\n<!-- BEGIN GENERATED"));
+        assert!(first.contains("| `__poly_app_exit` | `code` | Exit the application with a status code |"));
+        let edited = first.replace("Exit the application", "STALE");
+        assert_eq!(update_markdown(&edited), first);
+    }
+}
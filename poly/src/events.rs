@@ -0,0 +1,224 @@
+//! Event bus between the Poly backend and its pages
+//!
+//! Poly code calls `emit("topic", payload)` and pages listen with
+//! `poly.on("topic", cb)`; pages call `poly.emit("topic", payload)` to reach
+//! handlers registered in Poly with `on("topic", handler)`. Every page
+//! subscribes under a window label ("main" unless the page sets one), and
+//! `emit(..., window="settings")` targets only that window's pages.
+//!
+//! The dev server streams events to pages as server-sent events on
+//! `/__poly_events`; the native window drains its queue on the event loop and
+//! delivers them with `evaluate_script`. Each page's queue is bounded: when a
+//! page falls behind, the oldest events are dropped and the page receives a
+//! `poly:dropped` event with the count. Events from pages wait in a bounded
+//! inbox until the host dispatches them to the interpreter; when the inbox is
+//! full they are refused.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use serde_json::json;
+
+/// Events kept for a page that is not reading them
+pub const QUEUE_LIMIT: usize = 256;
+/// Events from pages waiting for the interpreter
+pub const INBOX_LIMIT: usize = 1024;
+
+/// A topic and its JSON payload
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub topic: String,
+    pub payload: serde_json::Value,
+}
+
+impl Event {
+    pub fn new(topic: impl Into<String>, payload: serde_json::Value) -> Self {
+        Event { topic: topic.into(), payload }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({"topic": self.topic, "payload": self.payload})
+    }
+
+    /// Script that hands the event to a page's `poly.on` listeners
+    pub fn dispatch_script(&self) -> String {
+        format!("window.__polyDispatch && window.__polyDispatch({});", self.to_json())
+    }
+}
+
+struct Subscriber {
+    window: String,
+    queue: VecDeque<Event>,
+    dropped: usize,
+}
+
+#[derive(Default)]
+struct Bus {
+    next_id: u64,
+    subscribers: HashMap<u64, Subscriber>,
+    inbox: VecDeque<Event>,
+}
+
+static BUS: Lazy<(Mutex<Bus>, Condvar)> = Lazy::new(|| (Mutex::new(Bus::default()), Condvar::new()));
+
+/// Register a page of `window`; returns its subscriber id
+pub fn subscribe(window: &str) -> u64 {
+    let mut bus = BUS.0.lock().unwrap();
+    bus.next_id += 1;
+    let id = bus.next_id;
+    bus.subscribers.insert(id, Subscriber { window: window.to_string(), queue: VecDeque::new(), dropped: 0 });
+    id
+}
+
+pub fn unsubscribe(id: u64) {
+    BUS.0.lock().unwrap().subscribers.remove(&id);
+    BUS.1.notify_all();
+}
+
+/// Send an event to every page, or only to the pages of `window`;
+/// returns how many pages it was queued for
+pub fn emit(topic: &str, payload: serde_json::Value, window: Option<&str>) -> usize {
+    let event = Event::new(topic, payload);
+    let mut bus = BUS.0.lock().unwrap();
+    let mut queued = 0;
+    for subscriber in bus.subscribers.values_mut() {
+        if window.is_some_and(|w| w != subscriber.window) {
+            continue;
+        }
+        if subscriber.queue.len() >= QUEUE_LIMIT {
+            subscriber.queue.pop_front();
+            subscriber.dropped += 1;
+        }
+        subscriber.queue.push_back(event.clone());
+        queued += 1;
+    }
+    drop(bus);
+    BUS.1.notify_all();
+    queued
+}
+
+/// Events queued for a subscriber, without waiting; `None` once it is gone
+pub fn take(id: u64) -> Option<Vec<Event>> {
+    let mut bus = BUS.0.lock().unwrap();
+    bus.subscribers.get_mut(&id).map(drain)
+}
+
+/// Wait up to `timeout` for events for a subscriber; `None` once it is gone
+pub fn wait(id: u64, timeout: Duration) -> Option<Vec<Event>> {
+    let deadline = Instant::now() + timeout;
+    let mut bus = BUS.0.lock().unwrap();
+    loop {
+        let subscriber = bus.subscribers.get_mut(&id)?;
+        let now = Instant::now();
+        if !subscriber.queue.is_empty() || subscriber.dropped > 0 || now >= deadline {
+            return Some(drain(subscriber));
+        }
+        bus = BUS.1.wait_timeout(bus, deadline - now).unwrap().0;
+    }
+}
+
+fn drain(subscriber: &mut Subscriber) -> Vec<Event> {
+    let mut events = Vec::with_capacity(subscriber.queue.len() + 1);
+    if subscriber.dropped > 0 {
+        events.push(Event::new("poly:dropped", json!({"count": subscriber.dropped})));
+        subscriber.dropped = 0;
+    }
+    events.extend(subscriber.queue.drain(..));
+    events
+}
+
+/// Queue an event from a page for the interpreter's `on` handlers
+pub fn send_to_backend(topic: &str, payload: serde_json::Value) -> Result<(), String> {
+    let mut bus = BUS.0.lock().unwrap();
+    if bus.inbox.len() >= INBOX_LIMIT {
+        return Err(format!("Event queue is full ({} events waiting)", INBOX_LIMIT));
+    }
+    bus.inbox.push_back(Event::new(topic, payload));
+    drop(bus);
+    BUS.1.notify_all();
+    Ok(())
+}
+
+/// Wait up to `timeout` for events from pages
+pub fn wait_backend(timeout: Duration) -> Vec<Event> {
+    let deadline = Instant::now() + timeout;
+    let mut bus = BUS.0.lock().unwrap();
+    loop {
+        let now = Instant::now();
+        if !bus.inbox.is_empty() || now >= deadline {
+            return bus.inbox.drain(..).collect();
+        }
+        bus = BUS.1.wait_timeout(bus, deadline - now).unwrap().0;
+    }
+}
+
+/// Parse a `poly.emit` body: `{"topic": ..., "payload": ...}`
+pub fn parse_emit(body: &str) -> Result<Event, String> {
+    let request: serde_json::Value = serde_json::from_str(body).map_err(|e| format!("Invalid JSON: {}", e))?;
+    let topic = request.get("topic").and_then(|t| t.as_str()).ok_or("Missing 'topic' field")?;
+    Ok(Event::new(topic, request.get("payload").cloned().unwrap_or(serde_json::Value::Null)))
+}
+
+/// Stream events to a page as server-sent events until it disconnects
+pub fn serve_sse(request: tiny_http::Request, window: &str) {
+    let mut writer = request.into_writer();
+    let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n";
+    if writer.write_all(head.as_bytes()).and_then(|_| writer.flush()).is_err() {
+        return;
+    }
+    let id = subscribe(window);
+    // An idle comment every few seconds notices pages that went away
+    while let Some(events) = wait(id, Duration::from_secs(15)) {
+        let mut chunk = String::new();
+        if events.is_empty() {
+            chunk.push_str(": idle\n\n");
+        }
+        for event in events {
+            chunk.push_str(&format!("data: {}\n\n", event.to_json()));
+        }
+        if writer.write_all(chunk.as_bytes()).and_then(|_| writer.flush()).is_err() {
+            break;
+        }
+    }
+    unsubscribe(id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_targeting_and_backpressure() {
+        let main = subscribe("test-main");
+        let other = subscribe("test-other");
+        assert_eq!(emit("greet", json!("hi"), Some("test-main")), 1);
+        assert_eq!(take(main).unwrap(), vec![Event::new("greet", json!("hi"))]);
+        assert_eq!(take(other).unwrap(), vec![]);
+
+        for i in 0..QUEUE_LIMIT + 3 {
+            emit("tick", json!(i), Some("test-other"));
+        }
+        let events = wait(other, Duration::from_millis(10)).unwrap();
+        assert_eq!(events.len(), QUEUE_LIMIT + 1);
+        assert_eq!(events[0], Event::new("poly:dropped", json!({"count": 3})));
+        assert_eq!(events[1].payload, json!(3));
+
+        unsubscribe(main);
+        unsubscribe(other);
+        assert!(take(main).is_none());
+    }
+
+    #[test]
+    fn test_backend_handlers() {
+        let source = "let saved = []\nfn on_save(doc):\n    saved.append(doc[\"title\"])\n    print(\"saved\", doc[\"title\"])\non(\"save\", on_save)\n";
+        let program = crate::parser::Parser::new(crate::lexer::Lexer::new(source).tokenize()).parse().unwrap();
+        let mut interp = crate::interpreter::Interpreter::new();
+        interp.run(&program).unwrap();
+        assert_eq!(interp.dispatch_event(&Event::new("save", json!({"title": "notes"}))).unwrap(), 1);
+        assert_eq!(interp.dispatch_event(&Event::new("other", json!(null))).unwrap(), 0);
+        assert_eq!(interp.get_output().to_vec(), vec!["saved notes"]);
+    }
+}
@@ -0,0 +1,575 @@
+//! Canonical formatter for Poly source (`poly fmt`)
+//!
+//! Works on the lexer's token stream with comments kept, so code is never
+//! reordered or dropped: every logical line is re-emitted with 4-space
+//! indentation, canonical operator spacing and double quotes, and lines
+//! longer than `MAX_WIDTH` are wrapped inside their largest bracket group.
+//! Formatting is idempotent, and the output is checked to lex to the same
+//! tokens as the input before it is returned.
+
+use std::path::{Path, PathBuf};
+
+use crate::lexer::{Lexer, SpannedToken, Token};
+use crate::parser::Parser;
+
+pub const MAX_WIDTH: usize = 100;
+const INDENT: &str = "    ";
+
+/// A token as it will be printed
+#[derive(Debug, Clone)]
+struct Piece {
+    token: Token,
+    text: String,
+    /// Whether a space separates this piece from the previous one
+    space: bool,
+}
+
+enum Content {
+    Code {
+        pieces: Vec<Piece>,
+        comment: Option<String>,
+        /// Original text, kept as written when comments sit inside brackets
+        verbatim: Option<String>,
+    },
+    Comment(String),
+}
+
+/// A statement or a standalone comment, with its original position
+struct Item {
+    content: Content,
+    level: usize,
+    column: usize,
+    first_line: usize,
+    last_line: usize,
+}
+
+impl Item {
+    fn opens_block(&self) -> bool {
+        match &self.content {
+            Content::Code { pieces, verbatim: None, .. } => pieces.last().is_some_and(|p| p.token == Token::Colon),
+            Content::Code { verbatim: Some(text), .. } => text.trim_end().ends_with(':'),
+            Content::Comment(_) => false,
+        }
+    }
+}
+
+/// Format Poly source. Fails if the source does not parse.
+pub fn format(source: &str) -> Result<String, String> {
+    let mut parser = Parser::new(Lexer::new(source).tokenize());
+    if let Err(e) = parser.parse() {
+        return Err(match parser.error_location() {
+            Some((line, column, _)) => format!("line {}, column {}: {}", line, column, e),
+            None => e,
+        });
+    }
+
+    let tokens = Lexer::new(source).with_comments().tokenize();
+    let mut items = collect_items(source, &tokens);
+    place_comments(&mut items);
+    let output = render(&items);
+
+    if normalized(source) != normalized(&output) {
+        return Err("Formatting would change the program; file left unchanged".to_string());
+    }
+    Ok(output)
+}
+
+/// Whether `source` is already formatted
+pub fn is_formatted(source: &str) -> Result<bool, String> {
+    Ok(format(source)? == source)
+}
+
+// ============================================================================
+// Collecting lines
+// ============================================================================
+
+fn collect_items(source: &str, tokens: &[SpannedToken]) -> Vec<Item> {
+    let mut items = Vec::new();
+    let mut level: usize = 0;
+    let mut current: Vec<&SpannedToken> = Vec::new();
+    let mut comment: Option<&SpannedToken> = None;
+    let mut verbatim = false;
+
+    for token in tokens {
+        match token.token {
+            Token::Indent => level += 1,
+            Token::Dedent => level = level.saturating_sub(1),
+            Token::Newline => {
+                if let Some(item) = code_item(source, &current, comment, verbatim, level) {
+                    items.push(item);
+                }
+                current.clear();
+                comment = None;
+                verbatim = false;
+            }
+            Token::Comment if current.is_empty() => items.push(Item {
+                content: Content::Comment(comment_text(&source[token.span.clone()])),
+                level,
+                column: token.column,
+                first_line: token.line,
+                last_line: token.line,
+            }),
+            Token::Comment => {
+                // A second comment on one logical line means the line spans brackets
+                verbatim |= comment.is_some();
+                comment = Some(token);
+            }
+            _ => {
+                verbatim |= comment.is_some();
+                current.push(token);
+            }
+        }
+    }
+    if let Some(item) = code_item(source, &current, comment, verbatim, level) {
+        items.push(item);
+    }
+    items
+}
+
+fn code_item(source: &str, tokens: &[&SpannedToken], comment: Option<&SpannedToken>, verbatim: bool, level: usize) -> Option<Item> {
+    let first = tokens.first()?;
+    let last = tokens.last()?;
+    let end = comment.map_or(last.span.end, |c| c.span.end.max(last.span.end));
+    let last_line = last.line + source[last.span.clone()].matches('\n').count();
+    let last_line = comment.map_or(last_line, |c| c.line.max(last_line));
+    let content = if verbatim {
+        Content::Code {
+            pieces: Vec::new(),
+            comment: None,
+            verbatim: Some(source[first.span.start..end].trim_end().to_string()),
+        }
+    } else {
+        Content::Code {
+            pieces: pieces(source, tokens),
+            comment: comment.map(|c| comment_text(&source[c.span.clone()])),
+            verbatim: None,
+        }
+    };
+    Some(Item { content, level, column: first.column, first_line: first.line, last_line })
+}
+
+/// `#comment` becomes `# comment`; shebangs and `#!`/`##` markers are kept
+fn comment_text(text: &str) -> String {
+    let text = text.trim_end();
+    let rest = &text[1..];
+    if rest.is_empty() || rest.starts_with([' ', '!', '#']) {
+        text.to_string()
+    } else {
+        format!("# {}", rest)
+    }
+}
+
+/// Standalone comments are lexed before the Indent/Dedent of the code that
+/// follows them, so pick their level from their original column: the
+/// deepest level between the previous and next statement they line up with.
+fn place_comments(items: &mut [Item]) {
+    let mut columns: Vec<usize> = vec![1];
+    for i in 0..items.len() {
+        if matches!(items[i].content, Content::Code { .. }) {
+            let (level, column) = (items[i].level, items[i].column);
+            columns.truncate(level + 1);
+            while columns.len() <= level {
+                columns.push(column);
+            }
+            columns[level] = column;
+            continue;
+        }
+        let (next_level, next_column) = items[i + 1..]
+            .iter()
+            .find(|item| matches!(item.content, Content::Code { .. }))
+            .map_or((0, 1), |item| (item.level, item.column));
+        let (prev_level, column) = (items[i].level, items[i].column);
+        items[i].level = if next_level > prev_level {
+            if column >= next_column { next_level } else { prev_level }
+        } else if next_level < prev_level {
+            (next_level..=prev_level)
+                .rev()
+                .find(|l| columns.get(*l).is_some_and(|c| *c <= column))
+                .unwrap_or(next_level)
+        } else {
+            prev_level
+        };
+    }
+}
+
+// ============================================================================
+// Spacing
+// ============================================================================
+
+/// Tokens that end an operand: a following `(`/`[` is a call or subscript,
+/// and a following `-` is binary
+fn ends_operand(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Identifier(_) | Token::SelfKw | Token::Integer(_) | Token::Float(_) | Token::BinaryInt(_)
+            | Token::OctalInt(_) | Token::HexInt(_) | Token::String(_) | Token::StringSingle(_)
+            | Token::MultiLineString(_) | Token::MultiLineStringSingle(_) | Token::FString(_)
+            | Token::FStringSingle(_) | Token::True | Token::False | Token::None
+            | Token::RParen | Token::RBracket | Token::RBrace
+    )
+}
+
+fn is_open(token: &Token) -> bool {
+    matches!(token, Token::LParen | Token::LBracket | Token::LBrace)
+}
+
+fn is_close(token: &Token) -> bool {
+    matches!(token, Token::RParen | Token::RBracket | Token::RBrace)
+}
+
+/// Source text of a token, with single-quoted strings switched to double
+/// quotes when that needs no escaping
+fn token_text(source: &str, token: &SpannedToken) -> String {
+    let text = &source[token.span.clone()];
+    let prefix = match token.token {
+        Token::StringSingle(_) => 0,
+        Token::FStringSingle(_) => 1,
+        _ => return text.to_string(),
+    };
+    let inner = &text[prefix + 1..text.len() - 1];
+    if inner.contains('"') {
+        text.to_string()
+    } else {
+        format!("{}\"{}\"", &text[..prefix], inner)
+    }
+}
+
+fn pieces(source: &str, tokens: &[&SpannedToken]) -> Vec<Piece> {
+    // Trailing commas are dropped here and re-added when a line is wrapped
+    let tokens: Vec<&SpannedToken> = tokens
+        .iter()
+        .enumerate()
+        .filter(|(i, t)| !(t.token == Token::Comma && tokens.get(i + 1).is_some_and(|n| is_close(&n.token))))
+        .map(|(_, t)| *t)
+        .collect();
+
+    let mut out: Vec<Piece> = Vec::with_capacity(tokens.len());
+    // Innermost bracket, whether a `lambda` in it still awaits its colon, and
+    // whether its current item has a type annotation
+    let mut brackets: Vec<(Token, bool, bool)> = vec![(Token::Newline, false, false)];
+    let mut prev_unary = false;
+    let mut prev_tight = false;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let current = &token.token;
+        let (inner, lambda, annotated) = brackets.last().cloned().unwrap_or((Token::Newline, false, false));
+        let prev = i.checked_sub(1).map(|j| &tokens[j].token);
+        // `=` for keyword arguments and unannotated defaults, and `:` in
+        // slices, take no spaces
+        let tight = match current {
+            Token::Eq => inner == Token::LParen && !annotated,
+            Token::Colon => inner == Token::LBracket && !lambda,
+            _ => false,
+        };
+
+        let space = match prev {
+            None => false,
+            Some(_) if matches!(current, Token::RParen | Token::RBracket | Token::RBrace | Token::Comma | Token::Colon | Token::Semicolon | Token::Dot) => false,
+            Some(p) if is_open(p) || *p == Token::Dot => false,
+            Some(Token::At) if i == 1 => false,
+            Some(p) if matches!(current, Token::LParen | Token::LBracket) && ends_operand(p) => false,
+            Some(_) if prev_unary || prev_tight || (tight && *current == Token::Eq) => false,
+            Some(_) => true,
+        };
+
+        prev_unary = matches!(current, Token::Minus | Token::Plus | Token::Tilde) && !prev.is_some_and(ends_operand);
+        prev_tight = tight;
+
+        match current {
+            t if is_open(t) => brackets.push((t.clone(), false, false)),
+            t if is_close(t) && brackets.len() > 1 => {
+                brackets.pop();
+            }
+            Token::Lambda => {
+                if let Some(last) = brackets.last_mut() {
+                    last.1 = true;
+                }
+            }
+            Token::Colon => {
+                if let Some(last) = brackets.last_mut() {
+                    last.2 |= last.0 == Token::LParen && !last.1;
+                    last.1 = false;
+                }
+            }
+            Token::Comma => {
+                if let Some(last) = brackets.last_mut() {
+                    last.2 = false;
+                }
+            }
+            _ => {}
+        }
+
+        out.push(Piece { token: current.clone(), text: token_text(source, token), space });
+    }
+    out
+}
+
+fn join(pieces: &[Piece]) -> String {
+    let mut line = String::new();
+    for (i, piece) in pieces.iter().enumerate() {
+        if i > 0 && piece.space {
+            line.push(' ');
+        }
+        line.push_str(&piece.text);
+    }
+    line
+}
+
+/// Printed width of a line; multi-line strings count their first and last lines
+fn width(text: &str) -> usize {
+    let first = text.split('\n').next().unwrap_or("");
+    let last = text.rsplit('\n').next().unwrap_or("");
+    first.chars().count().max(last.chars().count())
+}
+
+// ============================================================================
+// Wrapping
+// ============================================================================
+
+/// The bracket group (open, close) with the largest body at nesting depth 0
+fn widest_group(pieces: &[Piece]) -> Option<(usize, usize)> {
+    let mut depth = 0;
+    let mut open = 0;
+    let mut best: Option<(usize, usize)> = None;
+    for (i, piece) in pieces.iter().enumerate() {
+        if is_open(&piece.token) {
+            if depth == 0 {
+                open = i;
+            }
+            depth += 1;
+        } else if is_close(&piece.token) && depth > 0 {
+            depth -= 1;
+            if depth == 0 && i > open + 1 && best.is_none_or(|(o, c)| i - open >= c - o) {
+                best = Some((open, i));
+            }
+        }
+    }
+    best
+}
+
+/// Split a bracket body at its top-level commas (commas between lambda
+/// parameters belong to the lambda)
+fn elements(body: &[Piece]) -> Vec<&[Piece]> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut lambda = false;
+    let mut start = 0;
+    for (i, piece) in body.iter().enumerate() {
+        match &piece.token {
+            t if is_open(t) => depth += 1,
+            t if is_close(t) => depth -= 1,
+            Token::Lambda if depth == 0 => lambda = true,
+            Token::Colon if depth == 0 => lambda = false,
+            Token::Comma if depth == 0 && !lambda => {
+                parts.push(&body[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&body[start..]);
+    parts
+}
+
+fn layout(pieces: &[Piece], level: usize, trailing_comma: bool, out: &mut Vec<String>) {
+    let indent = INDENT.repeat(level);
+    let comma = if trailing_comma { "," } else { "" };
+    let flat = format!("{}{}", join(pieces), comma);
+    let Some((open, close)) = widest_group(pieces).filter(|_| indent.len() + width(&flat) > MAX_WIDTH) else {
+        out.push(format!("{}{}", indent, flat));
+        return;
+    };
+
+    out.push(format!("{}{}", indent, join(&pieces[..=open])));
+    let parts = elements(&pieces[open + 1..close]);
+    // Class headers do not accept a trailing comma
+    let trailing = parts.len() > 1 && pieces[0].token != Token::Class;
+    for (i, part) in parts.iter().enumerate() {
+        layout(part, level + 1, i + 1 < parts.len() || trailing, out);
+    }
+    out.push(format!("{}{}{}", indent, join(&pieces[close..]), comma));
+}
+
+// ============================================================================
+// Rendering
+// ============================================================================
+
+fn render(items: &[Item]) -> String {
+    let mut out = String::new();
+    let mut prev: Option<&Item> = None;
+
+    for item in items {
+        if let Some(prev) = prev {
+            let gap = item.first_line.saturating_sub(prev.last_line + 1);
+            let max = if item.level == 0 { 2 } else { 1 };
+            let blank = if prev.opens_block() && item.level > prev.level { 0 } else { gap.min(max) };
+            for _ in 0..blank {
+                out.push('\n');
+            }
+        }
+        let indent = INDENT.repeat(item.level);
+        match &item.content {
+            Content::Comment(text) => {
+                out.push_str(&indent);
+                out.push_str(text);
+                out.push('\n');
+            }
+            Content::Code { verbatim: Some(text), .. } => {
+                out.push_str(&indent);
+                out.push_str(text);
+                out.push('\n');
+            }
+            Content::Code { pieces, comment, verbatim: None } => {
+                let mut lines = Vec::new();
+                layout(pieces, item.level, false, &mut lines);
+                if let (Some(comment), Some(last)) = (comment, lines.last_mut()) {
+                    last.push_str("  ");
+                    last.push_str(comment);
+                }
+                for line in lines {
+                    out.push_str(&line);
+                    out.push('\n');
+                }
+            }
+        }
+        prev = Some(item);
+    }
+    out
+}
+
+/// Token stream used to check that formatting preserved the program: quote
+/// style, trailing commas and blank lines are not significant
+fn normalized(source: &str) -> Vec<Token> {
+    let tokens = Lexer::new(source).tokenize();
+    let mut out: Vec<Token> = Vec::with_capacity(tokens.len());
+    for (i, spanned) in tokens.iter().enumerate() {
+        let token = match &spanned.token {
+            Token::StringSingle(s) => Token::String(s.clone()),
+            Token::FStringSingle(s) => Token::FString(s.clone()),
+            Token::Comma if tokens.get(i + 1).is_some_and(|n| is_close(&n.token)) => continue,
+            Token::Newline if out.is_empty() || out.last() == Some(&Token::Newline) => continue,
+            Token::Dedent if out.last() == Some(&Token::Newline) => {
+                out.pop();
+                Token::Dedent
+            }
+            other => other.clone(),
+        };
+        out.push(token);
+    }
+    if out.last() == Some(&Token::Newline) {
+        out.pop();
+    }
+    out
+}
+
+// ============================================================================
+// Files
+// ============================================================================
+
+/// `.poly` files under `root` (or `root` itself if it is a file)
+pub fn discover(root: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if root.is_file() {
+        files.push(root.to_path_buf());
+    } else {
+        walk(root, &mut files);
+    }
+    files.sort();
+    files
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if path.is_dir() {
+            if !name.starts_with('.') && !crate::testing::SKIP_DIRS.contains(&name.as_str()) {
+                walk(&path, files);
+            }
+        } else if name.ends_with(".poly") {
+            files.push(path);
+        }
+    }
+}
+
+/// Format a file in place (or only compare when `check` is set). Returns
+/// whether the file was, or would be, changed.
+pub fn format_file(path: &Path, check: bool) -> Result<bool, String> {
+    let source = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let formatted = format(&source)?;
+    if formatted == source {
+        return Ok(false);
+    }
+    if !check {
+        std::fs::write(path, formatted).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_is_canonical_and_idempotent() {
+        let source = [
+            "# settings",
+            "let  names=['a','b' , \"c\"]",
+            "fn greet( name,greeting='hi' ):",
+            "  #say it",
+            "  if not name :",
+            "      return -1",
+            "  let parts = name[1 : ]   # tail",
+            "",
+            "",
+            "",
+            "  return greeting+\", \"+name",
+            "let total = add(1,",
+            "    2)",
+            "print(greet('bob', greeting = 'yo'), lambda x,y: x*y)",
+            "fn g(a:int,b: list[int]=[], c=1)->int:",
+            "    return a",
+            "",
+        ]
+        .join("\n");
+        let expected = [
+            "# settings",
+            "let names = [\"a\", \"b\", \"c\"]",
+            "fn greet(name, greeting=\"hi\"):",
+            "    # say it",
+            "    if not name:",
+            "        return -1",
+            "    let parts = name[1:]  # tail",
+            "",
+            "    return greeting + \", \" + name",
+            "let total = add(1, 2)",
+            "print(greet(\"bob\", greeting=\"yo\"), lambda x, y: x * y)",
+            "fn g(a: int, b: list[int] = [], c=1) -> int:",
+            "    return a",
+            "",
+        ]
+        .join("\n");
+        let formatted = format(&source).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted).unwrap(), formatted);
+        assert!(format("let = 1").is_err());
+    }
+
+    #[test]
+    fn test_long_lines_wrap_and_docstrings_survive() {
+        let args: Vec<String> = (0..12).map(|i| format!("argument_number_{}", i)).collect();
+        let source = format!(
+            "def run():\n    \"\"\"Runs\n    everything\"\"\"\n    return call({})\n# end\n",
+            args.join(", ")
+        );
+        let formatted = format(&source).unwrap();
+        let lines: Vec<&str> = formatted.lines().collect();
+        assert_eq!(&lines[..4], &["def run():", "    \"\"\"Runs", "    everything\"\"\"", "    return call("]);
+        assert_eq!(lines[5], "        argument_number_1,");
+        assert_eq!(lines[15], "        argument_number_11,");
+        assert_eq!(&lines[16..], &["    )", "# end"]);
+        assert!(lines.iter().all(|l| l.len() <= MAX_WIDTH));
+        assert_eq!(format(&formatted).unwrap(), formatted);
+    }
+}
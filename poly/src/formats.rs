@@ -0,0 +1,541 @@
+//! Data format modules for Poly (`import csv`, `toml`, `yaml`, `ini`)
+//!
+//! Every module exposes the same four functions:
+//! `parse(text)`, `stringify(value)`, `read(path)` and `write(path, value)`.
+//! Mappings become Poly dicts in document order and are written back in the
+//! same order, so a read/modify/write cycle produces a minimal diff.
+//! `csv.open(path)` additionally returns a streaming reader for large files.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+use crate::ast::Value;
+use crate::interpreter::split_kwargs;
+
+pub const MODULES: &[&str] = &["csv", "toml", "yaml", "ini"];
+
+const CSV_READER_CLASS: &str = "csv.Reader";
+
+/// Build the module value for `import <name>`
+pub fn module(name: &str) -> Value {
+    let mut functions = vec!["parse", "stringify", "read", "write"];
+    if name == "csv" {
+        functions.push("open");
+    }
+    crate::interpreter::module_value(
+        name,
+        functions
+            .into_iter()
+            .map(|f| (Value::String(f.to_string()), Value::NativeFunction(format!("{}.{}", name, f))))
+            .collect(),
+    )
+}
+
+/// Whether a native function name belongs to one of the format modules
+pub fn handles(name: &str) -> bool {
+    name.split_once('.').is_some_and(|(module, _)| MODULES.contains(&module))
+}
+
+/// Call `<module>.<function>`
+pub fn call(name: &str, args: Vec<Value>) -> Result<Value, String> {
+    let (module, function) = name.split_once('.').ok_or_else(|| format!("Unknown function: {}", name))?;
+    // Every function takes at most one data/path argument before the value
+    let positional = match function {
+        "write" => 2,
+        _ => 1,
+    };
+    let (args, kwargs) = split_kwargs(args, positional);
+    let opts = Options(kwargs);
+
+    match (module, function) {
+        (_, "parse") => parse(module, text_arg(&args, 0, name)?, &opts),
+        (_, "read") => {
+            let path = text_arg(&args, 0, name)?;
+            let text = std::fs::read_to_string(path).map_err(|e| format!("{}(): cannot read {}: {}", name, path, e))?;
+            parse(module, &text, &opts)
+        }
+        (_, "stringify") => {
+            let value = args.first().ok_or_else(|| format!("{}() requires a value", name))?;
+            stringify(module, value, &opts).map(Value::String)
+        }
+        (_, "write") => {
+            let path = text_arg(&args, 0, name)?;
+            let value = args.get(1).ok_or_else(|| format!("{}() requires a path and a value", name))?;
+            let text = stringify(module, value, &opts)?;
+            std::fs::write(path, text).map_err(|e| format!("{}(): cannot write {}: {}", name, path, e))?;
+            Ok(Value::None)
+        }
+        ("csv", "open") => csv_open(text_arg(&args, 0, name)?, &opts),
+        _ => Err(format!("{} has no function '{}'", module, function)),
+    }
+}
+
+fn parse(module: &str, text: &str, opts: &Options) -> Result<Value, String> {
+    match module {
+        "csv" => csv_parse(text, opts),
+        "toml" => toml_parse(text),
+        "yaml" => yaml_parse(text),
+        "ini" => ini_parse(text),
+        _ => Err(format!("Unknown format: {}", module)),
+    }
+}
+
+fn stringify(module: &str, value: &Value, opts: &Options) -> Result<String, String> {
+    match module {
+        "csv" => csv_stringify(value, opts),
+        "toml" => toml_stringify(value),
+        "yaml" => yaml_stringify(value),
+        "ini" => ini_stringify(value),
+        _ => Err(format!("Unknown format: {}", module)),
+    }
+}
+
+fn text_arg<'a>(args: &'a [Value], index: usize, func: &str) -> Result<&'a str, String> {
+    match args.get(index) {
+        Some(Value::String(s)) => Ok(s),
+        _ => Err(format!("{}() requires a string argument", func)),
+    }
+}
+
+/// Keyword options passed to a format function
+struct Options(Vec<(String, Value)>);
+
+impl Options {
+    fn get(&self, name: &str) -> Option<&Value> {
+        self.0.iter().find(|(k, _)| k == name).map(|(_, v)| v)
+    }
+
+    fn bool(&self, name: &str, default: bool) -> bool {
+        match self.get(name) {
+            Some(Value::Bool(b)) => *b,
+            _ => default,
+        }
+    }
+
+    fn byte(&self, name: &str, default: u8) -> Result<u8, String> {
+        match self.get(name) {
+            None => Ok(default),
+            Some(Value::String(s)) if s.len() == 1 => Ok(s.as_bytes()[0]),
+            Some(other) => Err(format!("{} must be a single ASCII character, got {}", name, other)),
+        }
+    }
+}
+
+// ============================================================================
+// CSV
+// ============================================================================
+
+/// Dialect settings: (delimiter, quote style, line terminator)
+struct Dialect {
+    delimiter: u8,
+    quote: u8,
+    quote_all: bool,
+    crlf: bool,
+}
+
+fn csv_dialect(opts: &Options) -> Result<Dialect, String> {
+    let mut dialect = match opts.get("dialect") {
+        None => Dialect { delimiter: b',', quote: b'"', quote_all: false, crlf: true },
+        Some(Value::String(name)) => match name.as_str() {
+            "excel" => Dialect { delimiter: b',', quote: b'"', quote_all: false, crlf: true },
+            "excel-tab" | "tsv" => Dialect { delimiter: b'\t', quote: b'"', quote_all: false, crlf: true },
+            "unix" => Dialect { delimiter: b',', quote: b'"', quote_all: true, crlf: false },
+            _ => return Err(format!("Unknown CSV dialect: {} (use excel, excel-tab or unix)", name)),
+        },
+        Some(other) => return Err(format!("dialect must be a string, got {}", other)),
+    };
+    dialect.delimiter = opts.byte("delimiter", dialect.delimiter)?;
+    dialect.quote = opts.byte("quote", dialect.quote)?;
+    dialect.quote_all = opts.bool("quote_all", dialect.quote_all);
+    Ok(dialect)
+}
+
+fn csv_reader_builder(opts: &Options) -> Result<csv::ReaderBuilder, String> {
+    let dialect = csv_dialect(opts)?;
+    let mut builder = csv::ReaderBuilder::new();
+    builder
+        .delimiter(dialect.delimiter)
+        .quote(dialect.quote)
+        .has_headers(opts.bool("header", true))
+        .flexible(true);
+    if opts.bool("trim", false) {
+        builder.trim(csv::Trim::All);
+    }
+    Ok(builder)
+}
+
+fn csv_row(record: &csv::StringRecord, headers: Option<&[String]>) -> Value {
+    match headers {
+        Some(headers) => Value::Dict(
+            headers
+                .iter()
+                .zip(record.iter())
+                .map(|(h, field)| (Value::String(h.clone()), Value::String(field.to_string())))
+                .collect(),
+        ),
+        None => Value::List(record.iter().map(|f| Value::String(f.to_string())).collect()),
+    }
+}
+
+fn csv_headers<R: std::io::Read>(reader: &mut csv::Reader<R>, opts: &Options) -> Result<Option<Vec<String>>, String> {
+    if !opts.bool("header", true) {
+        return Ok(None);
+    }
+    let headers = reader.headers().map_err(|e| format!("CSV error: {}", e))?;
+    Ok(Some(headers.iter().map(|h| h.to_string()).collect()))
+}
+
+fn csv_parse(text: &str, opts: &Options) -> Result<Value, String> {
+    let mut reader = csv_reader_builder(opts)?.from_reader(text.as_bytes());
+    let headers = csv_headers(&mut reader, opts)?;
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("CSV error: {}", e))?;
+        rows.push(csv_row(&record, headers.as_deref()));
+    }
+    Ok(Value::List(rows))
+}
+
+fn csv_field(value: &Value) -> String {
+    match value {
+        Value::None => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Write rows (lists, or dicts keyed by column) as CSV text
+fn csv_stringify(value: &Value, opts: &Options) -> Result<String, String> {
+    let Value::List(rows) = value else { return Err("csv.stringify() requires a list of rows".to_string()) };
+    let dialect = csv_dialect(opts)?;
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(dialect.delimiter)
+        .quote(dialect.quote)
+        .quote_style(if dialect.quote_all { csv::QuoteStyle::Always } else { csv::QuoteStyle::Necessary })
+        .terminator(if dialect.crlf { csv::Terminator::CRLF } else { csv::Terminator::Any(b'\n') })
+        .flexible(true)
+        .from_writer(Vec::new());
+
+    // Column order: explicit `columns=`, else first-seen order across dict rows
+    let mut columns: Vec<Value> = match opts.get("columns") {
+        Some(Value::List(cols)) => cols.clone(),
+        _ => Vec::new(),
+    };
+    if opts.get("columns").is_none() {
+        for row in rows {
+            if let Value::Dict(pairs) = row {
+                for (k, _) in pairs {
+                    if !columns.contains(k) {
+                        columns.push(k.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    let write_err = |e: csv::Error| format!("CSV error: {}", e);
+    if !columns.is_empty() && opts.bool("header", true) {
+        writer.write_record(columns.iter().map(csv_field)).map_err(write_err)?;
+    }
+    for row in rows {
+        match row {
+            Value::Dict(pairs) => {
+                let fields = columns.iter().map(|col| {
+                    pairs.iter().find(|(k, _)| k == col).map(|(_, v)| csv_field(v)).unwrap_or_default()
+                });
+                writer.write_record(fields).map_err(write_err)?;
+            }
+            Value::List(items) => writer.write_record(items.iter().map(csv_field)).map_err(write_err)?,
+            other => return Err(format!("CSV rows must be lists or dicts, got {}", other)),
+        }
+    }
+    let bytes = writer.into_inner().map_err(|e| format!("CSV error: {}", e))?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+struct CsvStream {
+    reader: csv::Reader<std::fs::File>,
+    headers: Option<Vec<String>>,
+}
+
+static CSV_STREAMS: Lazy<Mutex<HashMap<u64, CsvStream>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_STREAM_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Open a CSV file for row-by-row reading
+fn csv_open(path: &str, opts: &Options) -> Result<Value, String> {
+    let mut reader = csv_reader_builder(opts)?
+        .from_path(path)
+        .map_err(|e| format!("csv.open(): cannot open {}: {}", path, e))?;
+    let headers = csv_headers(&mut reader, opts)?;
+    let id = NEXT_STREAM_ID.fetch_add(1, Ordering::SeqCst);
+
+    let mut fields = HashMap::new();
+    fields.insert("id".to_string(), Value::Int(id as i64));
+    fields.insert("path".to_string(), Value::String(path.to_string()));
+    fields.insert(
+        "headers".to_string(),
+        headers
+            .as_ref()
+            .map(|h| Value::List(h.iter().map(|s| Value::String(s.clone())).collect()))
+            .unwrap_or(Value::None),
+    );
+    CSV_STREAMS.lock().unwrap().insert(id, CsvStream { reader, headers });
+    Ok(Value::Instance { class_name: CSV_READER_CLASS.to_string(), fields })
+}
+
+/// Methods on values returned by the format modules (currently `csv.open` readers)
+pub fn call_method(value: &Value, method: &str, args: Vec<Value>) -> Option<Result<Value, String>> {
+    let Value::Instance { class_name, fields } = value else { return None };
+    if class_name != CSV_READER_CLASS {
+        return None;
+    }
+    let Some(Value::Int(id)) = fields.get("id") else { return None };
+    let id = *id as u64;
+
+    let mut streams = CSV_STREAMS.lock().unwrap();
+    if method == "close" {
+        streams.remove(&id);
+        return Some(Ok(Value::None));
+    }
+    let Some(stream) = streams.get_mut(&id) else {
+        return Some(Err("CSV reader is closed".to_string()));
+    };
+    let limit = match (method, args.first()) {
+        ("next", _) => 1,
+        ("read", Some(Value::Int(n))) => (*n).max(0) as usize,
+        ("read", _) => usize::MAX,
+        _ => return Some(Err(format!("'{}' object has no method '{}'", class_name, method))),
+    };
+
+    let mut rows = Vec::new();
+    let mut record = csv::StringRecord::new();
+    while rows.len() < limit {
+        match stream.reader.read_record(&mut record) {
+            Ok(true) => rows.push(csv_row(&record, stream.headers.as_deref())),
+            Ok(false) => break,
+            Err(e) => return Some(Err(format!("CSV error: {}", e))),
+        }
+    }
+    if method == "next" {
+        return Some(Ok(rows.pop().unwrap_or(Value::None)));
+    }
+    Some(Ok(Value::List(rows)))
+}
+
+// ============================================================================
+// TOML
+// ============================================================================
+
+fn from_toml(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(n) => Value::Int(n),
+        toml::Value::Float(f) => Value::Float(f),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(dt) => {
+            let text = dt.to_string();
+            crate::datetime::fromisoformat(&text).unwrap_or(Value::String(text))
+        }
+        toml::Value::Array(items) => Value::List(items.into_iter().map(from_toml).collect()),
+        toml::Value::Table(table) => Value::Dict(
+            table.into_iter().map(|(k, v)| (Value::String(k), from_toml(v))).collect(),
+        ),
+    }
+}
+
+/// Convert to TOML; `None` means "omit" (TOML has no null)
+fn to_toml(value: &Value) -> Result<Option<toml::Value>, String> {
+    Ok(Some(match value {
+        Value::None => return Ok(None),
+        Value::Bool(b) => toml::Value::Boolean(*b),
+        Value::Int(n) => toml::Value::Integer(*n),
+        Value::Float(f) => toml::Value::Float(*f),
+        Value::String(s) => toml::Value::String(s.clone()),
+        Value::List(items) => {
+            let mut array = Vec::with_capacity(items.len());
+            for item in items {
+                array.push(to_toml(item)?.ok_or("TOML arrays cannot contain none")?);
+            }
+            toml::Value::Array(array)
+        }
+        Value::Dict(pairs) => toml::Value::Table(toml_table(pairs)?),
+        v @ Value::Instance { .. } if crate::datetime::is_temporal(v) => {
+            let text = crate::datetime::isoformat(v);
+            match text.parse::<toml::value::Datetime>() {
+                Ok(dt) => toml::Value::Datetime(dt),
+                Err(_) => toml::Value::String(text),
+            }
+        }
+        other => return Err(format!("Cannot convert {} to TOML", other)),
+    }))
+}
+
+fn toml_table(pairs: &[(Value, Value)]) -> Result<toml::Table, String> {
+    let mut table = toml::Table::new();
+    for (k, v) in pairs {
+        if let Some(v) = to_toml(v)? {
+            table.insert(k.to_string(), v);
+        }
+    }
+    Ok(table)
+}
+
+fn toml_parse(text: &str) -> Result<Value, String> {
+    let table: toml::Table = toml::from_str(text).map_err(|e| format!("TOML error: {}", e))?;
+    Ok(from_toml(toml::Value::Table(table)))
+}
+
+fn toml_stringify(value: &Value) -> Result<String, String> {
+    let Value::Dict(pairs) = value else { return Err("toml.stringify() requires a dict".to_string()) };
+    toml::to_string(&toml_table(pairs)?).map_err(|e| format!("TOML error: {}", e))
+}
+
+// ============================================================================
+// YAML
+// ============================================================================
+
+fn from_yaml(value: serde_yaml::Value) -> Value {
+    match value {
+        serde_yaml::Value::Null => Value::None,
+        serde_yaml::Value::Bool(b) => Value::Bool(b),
+        serde_yaml::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Int(i),
+            None => Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        serde_yaml::Value::String(s) => Value::String(s),
+        serde_yaml::Value::Sequence(items) => Value::List(items.into_iter().map(from_yaml).collect()),
+        serde_yaml::Value::Mapping(map) => Value::Dict(
+            map.into_iter().map(|(k, v)| (from_yaml(k), from_yaml(v))).collect(),
+        ),
+        serde_yaml::Value::Tagged(tagged) => from_yaml(tagged.value),
+    }
+}
+
+fn to_yaml(value: &Value) -> Result<serde_yaml::Value, String> {
+    Ok(match value {
+        Value::None => serde_yaml::Value::Null,
+        Value::Bool(b) => serde_yaml::Value::Bool(*b),
+        Value::Int(n) => serde_yaml::Value::Number((*n).into()),
+        Value::Float(f) => serde_yaml::Value::Number((*f).into()),
+        Value::String(s) => serde_yaml::Value::String(s.clone()),
+        Value::List(items) => serde_yaml::Value::Sequence(items.iter().map(to_yaml).collect::<Result<_, _>>()?),
+        Value::Dict(pairs) => {
+            let mut map = serde_yaml::Mapping::new();
+            for (k, v) in pairs {
+                map.insert(to_yaml(k)?, to_yaml(v)?);
+            }
+            serde_yaml::Value::Mapping(map)
+        }
+        v @ Value::Instance { .. } if crate::datetime::is_temporal(v) => {
+            serde_yaml::Value::String(crate::datetime::isoformat(v))
+        }
+        other => return Err(format!("Cannot convert {} to YAML", other)),
+    })
+}
+
+fn yaml_parse(text: &str) -> Result<Value, String> {
+    let value: serde_yaml::Value = serde_yaml::from_str(text).map_err(|e| format!("YAML error: {}", e))?;
+    Ok(from_yaml(value))
+}
+
+fn yaml_stringify(value: &Value) -> Result<String, String> {
+    serde_yaml::to_string(&to_yaml(value)?).map_err(|e| format!("YAML error: {}", e))
+}
+
+// ============================================================================
+// INI
+// ============================================================================
+
+/// Sections become nested dicts; keys outside any section stay top-level.
+/// INI is untyped, so every value is a string.
+fn ini_parse(text: &str) -> Result<Value, String> {
+    let ini = ini::Ini::load_from_str(text).map_err(|e| format!("INI error: {}", e))?;
+    let mut result = Vec::new();
+    for (section, props) in ini.iter() {
+        let pairs: Vec<(Value, Value)> = props
+            .iter()
+            .map(|(k, v)| (Value::String(k.to_string()), Value::String(v.to_string())))
+            .collect();
+        match section {
+            None => result.extend(pairs),
+            Some(name) => result.push((Value::String(name.to_string()), Value::Dict(pairs))),
+        }
+    }
+    Ok(Value::Dict(result))
+}
+
+fn ini_stringify(value: &Value) -> Result<String, String> {
+    let Value::Dict(pairs) = value else { return Err("ini.stringify() requires a dict".to_string()) };
+    let mut ini = ini::Ini::new();
+    // Top-level scalars first so they land before the first [section]
+    for (k, v) in pairs {
+        if !matches!(v, Value::Dict(_)) {
+            ini.with_general_section().set(k.to_string(), csv_field(v));
+        }
+    }
+    for (k, v) in pairs {
+        if let Value::Dict(section) = v {
+            let name = k.to_string();
+            // Make sure empty sections are still written
+            ini.entry(Some(name.clone())).or_insert_with(Default::default);
+            for (key, val) in section {
+                if matches!(val, Value::Dict(_) | Value::List(_)) {
+                    return Err(format!("INI values must be scalars ([{}] {})", name, key));
+                }
+                ini.with_section(Some(name.clone())).set(key.to_string(), csv_field(val));
+            }
+        }
+    }
+    let mut out = Vec::new();
+    ini.write_to(&mut out).map_err(|e| e.to_string())?;
+    String::from_utf8(out).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(text: &str) -> Value {
+        Value::String(text.to_string())
+    }
+
+    #[test]
+    fn test_csv_roundtrip_with_dialect() {
+        let rows = csv_parse("name,note\nada,\"hello, world\"\nbob,\n", &Options(vec![])).unwrap();
+        assert_eq!(rows, Value::List(vec![
+            Value::Dict(vec![(s("name"), s("ada")), (s("note"), s("hello, world"))]),
+            Value::Dict(vec![(s("name"), s("bob")), (s("note"), s(""))]),
+        ]));
+        let tsv = csv_stringify(&rows, &Options(vec![("dialect".to_string(), s("excel-tab"))])).unwrap();
+        assert_eq!(tsv, "name\tnote\r\nada\thello, world\r\nbob\t\r\n");
+    }
+
+    #[test]
+    fn test_toml_preserves_key_order() {
+        let text = "zeta = 1\nalpha = \"a\"\n\n[server]\nport = 8080\nhosts = [\"a\", \"b\"]\n";
+        let value = toml_parse(text).unwrap();
+        assert_eq!(toml_stringify(&value).unwrap(), text);
+        let dated = toml_parse("when = 1979-05-27T07:32:00Z").unwrap();
+        let Value::Dict(pairs) = dated else { panic!() };
+        assert_eq!(crate::datetime::isoformat(&pairs[0].1), "1979-05-27T07:32:00+00:00");
+    }
+
+    #[test]
+    fn test_yaml_and_ini() {
+        let value = yaml_parse("b: 1\na:\n  - x\n  - null\n").unwrap();
+        assert_eq!(value, Value::Dict(vec![
+            (s("b"), Value::Int(1)),
+            (s("a"), Value::List(vec![s("x"), Value::None])),
+        ]));
+        assert_eq!(yaml_stringify(&value).unwrap(), "b: 1\na:\n- x\n- null\n");
+
+        let ini = ini_parse("debug = true\n[db]\nhost = localhost\n").unwrap();
+        assert_eq!(ini, Value::Dict(vec![
+            (s("debug"), s("true")),
+            (s("db"), Value::Dict(vec![(s("host"), s("localhost"))])),
+        ]));
+        assert_eq!(ini_parse(&ini_stringify(&ini).unwrap()).unwrap(), ini);
+    }
+}
@@ -0,0 +1,391 @@
+//! Headless apps and the IPC test harness
+//!
+//! `App` boots a project the way the native window does (`[sovereignty]` from
+//! poly.toml, the entry module on the IPC workers, the command router and the
+//! event bus) without opening a window. Rust tests drive it directly;
+//! `poly run --headless` drives it with JSON lines on stdin and stdout. The
+//! sovereignty configuration and worker pool are process-wide; dropping the
+//! app puts back the ones it replaced.
+//!
+//! ```ignore
+//! let app = poly::headless::App::start(Path::new("examples/todo"))?;
+//! app.fakes().set_value("__poly_dialog_open", json!("/tmp/notes.txt"));
+//! assert_eq!(app.invoke("open_notes", json!([]))?, json!(3));
+//! assert!(app.wait_event("notes:loaded", Duration::from_secs(1)).is_some());
+//! ```
+//!
+//! Poly tests get the same calls in the interpreter under test through
+//! `ipc_invoke`, `ipc_emit`, `emitted`, `fake_api` and `fake_calls`.
+//!
+//! System APIs that reach the desktop are faked: file dialogs answer as if
+//! cancelled, `confirm` as if refused, the clipboard is kept in memory and
+//! notifications are only recorded. Fakes still need the permission the real
+//! command needs, so a missing `[sovereignty]` grant fails the test.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::events::{self, Event};
+use crate::interpreter::Interpreter;
+use crate::ipc::{Error, Request};
+use crate::sovereignty::SovereigntyConfig;
+use crate::workers::Pool;
+
+type Reply = dyn Fn(&Value) -> Result<Value, Error> + Send + Sync;
+
+#[derive(Default)]
+struct FakeState {
+    replies: HashMap<String, Arc<Reply>>,
+    calls: Vec<(String, Value)>,
+}
+
+/// Replacement replies for commands, and the calls they received
+#[derive(Clone, Default)]
+pub struct Fakes {
+    state: Arc<Mutex<FakeState>>,
+}
+
+impl Fakes {
+    /// Fakes for the dialog, clipboard and notification APIs
+    pub fn desktop() -> Self {
+        let fakes = Fakes::default();
+        for name in ["__poly_dialog_open", "__poly_dialog_open_multiple", "__poly_dialog_save", "__poly_dialog_folder"] {
+            fakes.set_value(name, Value::Null);
+        }
+        fakes.set_value("__poly_dialog_message", json!(true));
+        fakes.set_value("__poly_dialog_confirm", json!(false));
+        let clipboard = Arc::new(Mutex::new(String::new()));
+        let text = Arc::clone(&clipboard);
+        fakes.set("__poly_clipboard_read", move |_| Ok(json!(*text.lock().unwrap())));
+        let text = Arc::clone(&clipboard);
+        fakes.set("__poly_clipboard_write", move |args| {
+            *text.lock().unwrap() = args.get("text").and_then(Value::as_str).unwrap_or("").to_string();
+            Ok(json!(true))
+        });
+        fakes.set("__poly_clipboard_clear", move |_| {
+            clipboard.lock().unwrap().clear();
+            Ok(json!(true))
+        });
+        fakes.set_value("__poly_notification_show", json!(true));
+        fakes.set_value("__poly_notification_show_timeout", json!(true));
+        fakes
+    }
+
+    /// Answer `command` with `reply(args)`
+    pub fn set(&self, command: &str, reply: impl Fn(&Value) -> Result<Value, Error> + Send + Sync + 'static) {
+        self.state.lock().unwrap().replies.insert(command.to_string(), Arc::new(reply));
+    }
+
+    /// Answer `command` with `value`
+    pub fn set_value(&self, command: &str, value: Value) {
+        self.set(command, move |_| Ok(value.clone()));
+    }
+
+    /// Arguments of each call `command` received, oldest first
+    pub fn calls(&self, command: &str) -> Vec<Value> {
+        self.state.lock().unwrap().calls.iter().filter(|(name, _)| name == command).map(|(_, args)| args.clone()).collect()
+    }
+
+    /// Whether calls to `command` get a fake reply
+    pub fn is_faked(&self, command: &str) -> bool {
+        self.state.lock().unwrap().replies.contains_key(command)
+    }
+
+    /// Answer a call if `command` is faked, after the schema and permission
+    /// checks the registered command would make
+    pub fn answer(&self, command: &str, args: &Value) -> Option<Result<Value, Error>> {
+        let reply = Arc::clone(self.state.lock().unwrap().replies.get(command)?);
+        let args = match crate::router::validate(command, args.clone()) {
+            Ok(args) => args,
+            Err(e) => return Some(Err(e)),
+        };
+        self.state.lock().unwrap().calls.push((command.to_string(), args.clone()));
+        if let Some(permission) = crate::router::required_permission(command, &args) {
+            if let Err(e) = crate::sovereignty::check_permission(&permission) {
+                return Some(Err(Error::new("PERMISSION_DENIED", e)));
+            }
+        }
+        Some(reply(&args))
+    }
+}
+
+/// Send `request` where a page's call would go: a fake, a command on the
+/// router, or else `call` for a Poly function
+pub fn dispatch(fakes: Option<&Fakes>, request: &Request, call: impl FnOnce(&Request) -> Result<Value, Error>) -> Result<Value, Error> {
+    let faked = fakes.filter(|fakes| fakes.is_faked(&request.function));
+    let command = request.function.starts_with("__poly_") || crate::router::contains(&request.function);
+    if faked.is_none() && !command {
+        return call(request);
+    }
+    let args = request.command_args()?;
+    if let Some(result) = faked.and_then(|fakes| fakes.answer(&request.function, &args)) {
+        return result;
+    }
+    crate::router::dispatch(&request.function, args)
+}
+
+/// Test state of an interpreter running under `poly test`: its fakes and the
+/// events it emitted
+#[derive(Clone)]
+pub struct Harness {
+    pub fakes: Fakes,
+    events: Vec<Event>,
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Harness { fakes: Fakes::desktop(), events: Vec::new() }
+    }
+}
+
+impl Harness {
+    pub fn record(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    /// Events recorded since the last call, optionally only one topic's
+    pub fn take_events(&mut self, topic: Option<&str>) -> Vec<Event> {
+        let (taken, kept) = std::mem::take(&mut self.events).into_iter().partition(|e| topic.is_none_or(|t| t == e.topic));
+        self.events = kept;
+        taken
+    }
+}
+
+/// Process-wide state an `App` replaces while it runs, put back when it is
+/// dropped: the `[sovereignty]` configuration and the installed worker pool
+struct Globals {
+    sovereignty: SovereigntyConfig,
+    pool: Option<Arc<Pool>>,
+}
+
+impl Globals {
+    fn save() -> Self {
+        Globals { sovereignty: crate::sovereignty::current_config(), pool: crate::workers::current() }
+    }
+}
+
+impl Drop for Globals {
+    fn drop(&mut self) {
+        crate::sovereignty::set_config(std::mem::take(&mut self.sovereignty));
+        crate::workers::restore(self.pool.take());
+    }
+}
+
+/// An app running without windows
+pub struct App {
+    pool: Arc<Pool>,
+    fakes: Fakes,
+    page: u64,
+    pending: Mutex<VecDeque<Event>>,
+    _globals: Globals,
+}
+
+impl App {
+    /// Boot the app in `project`, a folder with an entry module or a `.poly`
+    /// file. Worker output is captured, not printed; see `take_output`.
+    pub fn start(project: &Path) -> Result<App, String> {
+        crate::register_builtin_commands();
+        let globals = Globals::save();
+        let (root, entry) = if project.is_file() {
+            (project.parent().unwrap_or(Path::new(".")).to_path_buf(), project.to_path_buf())
+        } else {
+            let entry = crate::find_entry_point(project).ok_or_else(|| format!("No entry point found in {}", project.display()))?;
+            (project.to_path_buf(), entry)
+        };
+        let toml = root.join("poly.toml");
+        if toml.exists() {
+            let name = root.canonicalize().ok().and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned())).unwrap_or_else(|| "Poly App".to_string());
+            crate::sovereignty::init_from_toml(&toml, &name);
+        } else {
+            crate::sovereignty::set_development_mode();
+        }
+        let config = crate::PolyConfig::load(&root);
+        let source = std::fs::read_to_string(&entry).map_err(|e| format!("Cannot read {}: {}", entry.display(), e))?;
+
+        // Subscribed first, so events emitted at startup are kept
+        let page = events::subscribe("main");
+        let mut interpreters = Vec::new();
+        for _ in 0..config.ipc.workers.max(1) {
+            let mut interp = Interpreter::new();
+            interp.set_echo_output(false);
+            if let Err(e) = crate::init_interpreter(&mut interp, &source) {
+                events::unsubscribe(page);
+                return Err(format!("{}: {}", entry.display(), e));
+            }
+            interpreters.push(Arc::new(Mutex::new(interp)));
+        }
+        let pool = Arc::new(Pool::new(interpreters, config.ipc.queue_limit));
+        crate::workers::install(Arc::clone(&pool));
+        Ok(App { pool, fakes: Fakes::desktop(), page, pending: Mutex::new(VecDeque::new()), _globals: globals })
+    }
+
+    pub fn fakes(&self) -> &Fakes {
+        &self.fakes
+    }
+
+    pub fn pool(&self) -> &Arc<Pool> {
+        &self.pool
+    }
+
+    /// Call a function or system API like `poly.invoke(function, args)`
+    pub fn invoke(&self, function: &str, args: Value) -> Result<Value, Error> {
+        self.request(Request { function: function.to_string(), args, ..Default::default() })
+    }
+
+    /// Send a full IPC request, with kwargs, id and priority
+    pub fn request(&self, request: Request) -> Result<Value, Error> {
+        dispatch(Some(&self.fakes), &request, |request| self.pool.call(request.clone()))
+    }
+
+    /// Send an event from the page to the backend's `on` handlers and wait
+    /// for them; returns how many ran
+    pub fn emit(&self, topic: &str, payload: Value) -> Result<usize, String> {
+        self.pool.workers()[0].lock().unwrap().dispatch_event(&Event::new(topic, payload))
+    }
+
+    /// Events the backend sent to pages since the last call
+    pub fn events(&self) -> Vec<Event> {
+        let mut pending = self.pending.lock().unwrap();
+        pending.extend(events::take(self.page).unwrap_or_default());
+        pending.drain(..).collect()
+    }
+
+    /// Wait up to `timeout` for an event on `topic`; other events stay
+    /// queued for `events`
+    pub fn wait_event(&self, topic: &str, timeout: Duration) -> Option<Event> {
+        let deadline = Instant::now() + timeout;
+        let mut pending = self.pending.lock().unwrap();
+        loop {
+            pending.extend(events::take(self.page).unwrap_or_default());
+            if let Some(index) = pending.iter().position(|e| e.topic == topic) {
+                return pending.remove(index);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            pending.extend(events::wait(self.page, deadline - now).unwrap_or_default());
+        }
+    }
+
+    /// Lines the workers printed since the last call
+    pub fn take_output(&self) -> Vec<String> {
+        self.pool.workers().iter().flat_map(|worker| worker.lock().unwrap().take_output()).collect()
+    }
+}
+
+impl Drop for App {
+    fn drop(&mut self) {
+        events::unsubscribe(self.page);
+    }
+}
+
+/// Handle one line of `poly run --headless` input and produce the reply:
+/// an IPC request as sent to `/__poly_invoke`, `{"emit": topic, "payload"}`
+/// for a page event, or `{"fake": command, "result"}` to fake a command
+pub fn handle_line(app: &App, line: &str) -> String {
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(e) => return crate::ipc::reply(Err(Error::new("INVALID_REQUEST", format!("Invalid JSON: {}", e)))),
+    };
+    let payload = message.get("payload").cloned().unwrap_or(Value::Null);
+    let result = if let Some(topic) = message.get("emit").and_then(Value::as_str) {
+        app.emit(topic, payload).map(|count| json!(count)).map_err(|e| Error::new("RUNTIME_ERROR", e))
+    } else if let Some(command) = message.get("fake").and_then(Value::as_str) {
+        app.fakes.set_value(command, message.get("result").cloned().unwrap_or(Value::Null));
+        Ok(Value::Null)
+    } else {
+        crate::ipc::parse_request(line).and_then(|request| app.request(request))
+    };
+    crate::ipc::reply(result)
+}
+
+/// Output line for an event sent to pages
+pub fn event_line(event: &Event) -> String {
+    json!({"event": event.topic, "payload": event.payload}).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Held by tests that start an `App`, which swaps process-wide state
+    static GLOBALS: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn test_headless_app_with_fakes_and_events() {
+        let _globals = GLOBALS.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = std::env::temp_dir().join(format!("poly_headless_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.poly"), r#"
+fn save(title):
+    shared_set("headless_test_title", title)
+    let count = shared_add("headless_test_saves")
+    emit("saved", {"title": title, "count": count})
+    return count
+
+fn on_rename(payload):
+    shared_set("headless_test_title", payload["title"])
+
+on("rename", on_rename)
+
+fn title():
+    print("title is", shared_get("headless_test_title"))
+    return shared_get("headless_test_title")
+"#).unwrap();
+        let app = App::start(&dir).unwrap();
+
+        assert_eq!(app.invoke("save", json!(["notes"])).unwrap(), json!(1));
+        let event = app.wait_event("saved", Duration::from_secs(1)).unwrap();
+        assert_eq!(event.payload, json!({"title": "notes", "count": 1}));
+        assert_eq!(app.emit("rename", json!({"title": "todo"})).unwrap(), 1);
+        assert_eq!(app.invoke("title", Value::Null).unwrap(), json!("todo"));
+        assert_eq!(app.take_output(), vec!["title is todo"]);
+        assert_eq!(app.invoke("missing", Value::Null).unwrap_err().code, "NOT_FOUND");
+
+        // Desktop APIs are faked, and a test may fake any other command
+        assert_eq!(app.invoke("__poly_clipboard_write", json!({"text": "copied"})).unwrap(), json!(true));
+        assert_eq!(app.invoke("__poly_clipboard_read", Value::Null).unwrap(), json!("copied"));
+        assert_eq!(app.invoke("__poly_dialog_open", json!({"title": "Open"})).unwrap(), Value::Null);
+        app.fakes().set_value("__poly_dialog_open", json!("/tmp/a.txt"));
+        assert_eq!(handle_line(&app, r#"{"fn": "__poly_dialog_open", "args": {}}"#), r#"{"result":"/tmp/a.txt"}"#);
+        assert_eq!(app.fakes().calls("__poly_dialog_open"), vec![json!({"title": "Open"}), json!({})]);
+        // Fakes take the arguments the real command takes
+        assert_eq!(app.invoke("__poly_clipboard_write", json!({})).unwrap_err().code, "INVALID_ARGUMENTS");
+        assert_eq!(app.invoke("__poly_dialog_open", json!({"title": 1})).unwrap_err().code, "INVALID_ARGUMENTS");
+        assert_eq!(app.invoke("__poly_dialog_open", json!(["Open"])).unwrap_err().code, "INVALID_ARGUMENTS");
+        assert_eq!(app.fakes().calls("__poly_dialog_open").len(), 2);
+
+        // The rest of the system APIs run for real
+        let notes = dir.join("notes.txt");
+        std::fs::write(&notes, "hello").unwrap();
+        assert_eq!(app.invoke("__poly_fs_read", json!({"path": notes})).unwrap(), json!("hello"));
+        assert_eq!(app.invoke("__poly_fs_read", json!({})).unwrap_err().code, "INVALID_ARGUMENTS");
+        assert!(app.invoke("__poly_ipc_metrics", Value::Null).unwrap()["workers"].is_number());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+    #[test]
+    fn test_dropping_app_restores_globals() {
+        let _globals = GLOBALS.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = std::env::temp_dir().join(format!("poly_headless_globals_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.poly"), "fn ping():\n    return 1\n").unwrap();
+        let before = crate::workers::current();
+        crate::sovereignty::set_config(crate::sovereignty::SovereigntyConfig { enabled: true, app_name: "outer".to_string(), ..Default::default() });
+
+        let app = App::start(&dir).unwrap();
+        assert!(!crate::sovereignty::is_enabled());
+        assert!(crate::workers::current().is_some_and(|pool| Arc::ptr_eq(&pool, app.pool())));
+        drop(app);
+
+        assert!(crate::sovereignty::is_enabled());
+        assert_eq!(crate::sovereignty::current_config().app_name, "outer");
+        assert_eq!(crate::workers::current().map(|pool| Arc::as_ptr(&pool)), before.map(|pool| Arc::as_ptr(&pool)));
+        crate::sovereignty::set_config(Default::default());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
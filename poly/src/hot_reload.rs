@@ -0,0 +1,129 @@
+//! Backend hot reload for `poly dev`
+//!
+//! When `.poly` files change, the running interpreter is updated in place
+//! rather than rebuilt: every changed module that has been imported runs
+//! again, then the entry file if it changed. Importing a module that is
+//! already loaded does nothing, so unchanged modules keep their definitions.
+//! All changed files are parsed before anything runs, and a run that fails
+//! rolls the interpreter back, so a broken edit leaves the previous code
+//! answering IPC calls while the error is shown in the browser.
+//!
+//! Before a file runs again, the routes it declared are dropped, so a route
+//! deleted from the source stops being served.
+//!
+//! The top-level dict named by `[dev] preserve_state` (default `state`) keeps
+//! its values across reloads; keys the new code adds get their new values.
+
+use std::path::{Path, PathBuf};
+
+use crate::ast::{Program, Value};
+use crate::interpreter::Interpreter;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+/// A reload that was refused, shown as the in-browser error overlay
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReloadError {
+    pub file: PathBuf,
+    pub message: String,
+}
+
+impl ReloadError {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({"file": self.file.display().to_string(), "message": self.message})
+    }
+}
+
+/// Apply changes to `changed` files to the interpreter; returns the files
+/// that ran again, modules first and the entry last
+pub fn reload(interp: &mut Interpreter, entry: &Path, changed: &[PathBuf], preserve: &str) -> Result<Vec<PathBuf>, ReloadError> {
+    let entry = std::fs::canonicalize(entry).unwrap_or_else(|_| entry.to_path_buf());
+    let mut files: Vec<PathBuf> = Vec::new();
+    for path in changed {
+        let Ok(path) = std::fs::canonicalize(path) else { continue };
+        if path != entry && interp.has_module(&path) && !files.contains(&path) {
+            files.push(path);
+        }
+    }
+    if changed.iter().any(|path| std::fs::canonicalize(path).is_ok_and(|path| path == entry)) {
+        files.push(entry.clone());
+    }
+
+    let programs = files.iter()
+        .map(|file| parse(file).map(|program| (file, program)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let state = interp.variable(preserve);
+    let snapshot = interp.snapshot();
+    let routes = crate::routes::snapshot();
+    for (file, program) in &programs {
+        let module = (**file != entry).then(|| file.to_path_buf());
+        crate::routes::clear_file(module.as_deref());
+        interp.set_module_file(module);
+        let result = interp.run(program);
+        interp.set_module_file(None);
+        if let Err(message) = result {
+            interp.restore(snapshot);
+            crate::routes::restore(routes);
+            return Err(ReloadError { file: file.to_path_buf(), message });
+        }
+    }
+    if let Some(state) = state {
+        let merged = merge_state(state, interp.variable(preserve));
+        interp.set_global(preserve, merged);
+    }
+    Ok(files)
+}
+
+fn parse(file: &Path) -> Result<Program, ReloadError> {
+    let error = |message: String| ReloadError { file: file.to_path_buf(), message };
+    let source = std::fs::read_to_string(file).map_err(|e| error(e.to_string()))?;
+    Parser::new(Lexer::new(&source).tokenize()).with_lines().parse().map_err(error)
+}
+
+/// Old values win; keys only the new dict has are added
+fn merge_state(old: Value, new: Option<Value>) -> Value {
+    match (old, new) {
+        (Value::Dict(mut pairs), Some(Value::Dict(fresh))) => {
+            for (key, value) in fresh {
+                if !pairs.iter().any(|(k, _)| *k == key) {
+                    pairs.push((key, value));
+                }
+            }
+            Value::Dict(pairs)
+        }
+        (old, _) => old,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload_keeps_state_and_rolls_back() {
+        let dir = std::env::temp_dir().join(format!("poly_hot_reload_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let entry = dir.join("main.poly");
+        std::fs::write(&entry, "let state = {\"count\": 0}\nfn bump():\n    return \"v1\"\nstate[\"count\"] = 2\n@get(\"/reload-api/old\")\nfn old():\n    return 1\n").unwrap();
+
+        let mut interp = Interpreter::new();
+        interp.run(&parse(&entry).unwrap()).unwrap();
+
+        assert!(crate::routes::matches("/reload-api/old"));
+        std::fs::write(&entry, "let state = {\"count\": 0, \"name\": \"x\"}\nfn bump():\n    return \"v2\"\n@get(\"/reload-api/new\")\nfn new():\n    return 2\n").unwrap();
+        assert_eq!(reload(&mut interp, &entry, std::slice::from_ref(&entry), "state").unwrap().len(), 1);
+        assert!(crate::routes::matches("/reload-api/new") && !crate::routes::matches("/reload-api/old"));
+        assert_eq!(interp.call("bump", vec![]).unwrap(), Value::String("v2".into()));
+        assert_eq!(format!("{}", interp.variable("state").unwrap()), "{count: 2, name: x}");
+
+        std::fs::write(&entry, "fn bump(:\n").unwrap();
+        assert!(reload(&mut interp, &entry, std::slice::from_ref(&entry), "state").is_err());
+        std::fs::write(&entry, "fn bump():\n    return \"v3\"\nraise \"boom\"\n").unwrap();
+        assert!(reload(&mut interp, &entry, std::slice::from_ref(&entry), "state").unwrap_err().message.contains("boom"));
+        assert_eq!(interp.call("bump", vec![]).unwrap(), Value::String("v2".into()));
+        assert!(crate::routes::matches("/reload-api/new"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
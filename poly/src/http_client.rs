@@ -0,0 +1,711 @@
+//! HTTP client for Poly (`import http`)
+//!
+//! A requests-style API on top of reqwest's blocking client:
+//!
+//! ```text
+//! import http
+//! let r = http.get("https://api.example.com/items", params={"page": 2})
+//! print(r.status, r.json())
+//!
+//! let s = http.session(headers={"Authorization": "Bearer ..."}, retries=3)
+//! s.post("https://api.example.com/items", json={"name": "x"})
+//! ```
+//!
+//! Every request (and every redirect hop) goes through the sovereignty HTTP
+//! check, and defaults come from `[network]` in poly.toml.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use reqwest::blocking::{multipart, Client, RequestBuilder, Response};
+use reqwest::cookie::{CookieStore, Jar};
+use reqwest::redirect::Policy;
+
+use crate::ast::Value;
+use crate::interpreter::split_kwargs;
+use crate::sovereignty::checks;
+
+const SESSION_CLASS: &str = "http.Session";
+const RESPONSE_CLASS: &str = "http.Response";
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+const METHODS: &[&str] = &["get", "post", "put", "patch", "delete", "head", "options"];
+
+/// Build the `http` module value
+pub fn module() -> Value {
+    let mut pairs: Vec<(Value, Value)> = METHODS
+        .iter()
+        .chain(["request", "session"].iter())
+        .map(|name| (Value::String(name.to_string()), Value::NativeFunction(format!("http.{}", name))))
+        .collect();
+    pairs.push((Value::String("Session".to_string()), Value::NativeFunction("http.session".to_string())));
+    crate::interpreter::module_value("http", pairs)
+}
+
+// ============================================================================
+// Sessions
+// ============================================================================
+
+#[derive(Clone)]
+enum Auth {
+    Basic(String, Option<String>),
+    Bearer(String),
+}
+
+/// Connection settings shared by every request made through a session
+#[derive(Clone)]
+struct Session {
+    headers: Vec<(String, String)>,
+    auth: Option<Auth>,
+    timeout: Option<Duration>,
+    retries: u32,
+    max_redirects: usize,
+    base_url: Option<String>,
+    jar: Option<Arc<Jar>>,
+    follow_client: Option<Client>,
+    no_follow_client: Option<Client>,
+}
+
+impl Session {
+    fn new(with_cookies: bool) -> Self {
+        Self {
+            headers: Vec::new(),
+            auth: None,
+            timeout: None,
+            retries: 0,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            base_url: None,
+            jar: with_cookies.then(|| Arc::new(Jar::default())),
+            follow_client: None,
+            no_follow_client: None,
+        }
+    }
+
+    /// Lazily build (and cache) the client for the given redirect mode
+    fn client(&mut self, follow: bool) -> Result<Client, String> {
+        let cached = if follow { &mut self.follow_client } else { &mut self.no_follow_client };
+        if let Some(client) = cached {
+            return Ok(client.clone());
+        }
+        let config = crate::config::get_config();
+        let max_redirects = self.max_redirects;
+        let policy = if follow {
+            Policy::custom(move |attempt| {
+                if attempt.previous().len() > max_redirects {
+                    attempt.error(format!("Too many redirects (max {})", max_redirects))
+                } else if let Err(e) = checks::http(attempt.url().as_str()) {
+                    attempt.error(e)
+                } else {
+                    attempt.follow()
+                }
+            })
+        } else {
+            Policy::none()
+        };
+        let mut builder = Client::builder()
+            .user_agent(config.get_user_agent())
+            .timeout(Duration::from_secs(config.network.timeout as u64))
+            .redirect(policy);
+        if let Some(jar) = &self.jar {
+            builder = builder.cookie_provider(jar.clone());
+        }
+        let client = builder.build().map_err(|e| format!("HTTP client error: {}", e))?;
+        *cached = Some(client.clone());
+        Ok(client)
+    }
+
+    fn resolve_url(&self, url: &str) -> String {
+        match &self.base_url {
+            Some(base) if !url.contains("://") => {
+                format!("{}/{}", base.trim_end_matches('/'), url.trim_start_matches('/'))
+            }
+            _ => url.to_string(),
+        }
+    }
+}
+
+static SESSIONS: Lazy<Mutex<HashMap<u64, Session>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static STREAMS: Lazy<Mutex<HashMap<u64, BufReader<Response>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Session used by module-level calls (no cookie jar, so nothing leaks between calls)
+static DEFAULT_SESSION: Lazy<Mutex<Session>> = Lazy::new(|| Mutex::new(Session::new(false)));
+
+// ============================================================================
+// Options
+// ============================================================================
+
+fn string_pairs(value: &Value, what: &str) -> Result<Vec<(String, String)>, String> {
+    match value {
+        Value::Dict(pairs) => Ok(pairs
+            .iter()
+            .filter(|(_, v)| !matches!(v, Value::None))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()),
+        Value::None => Ok(Vec::new()),
+        other => Err(format!("{} must be a dict, got {}", what, other)),
+    }
+}
+
+fn parse_auth(value: &Value) -> Result<Option<Auth>, String> {
+    match value {
+        Value::None => Ok(None),
+        Value::String(token) => Ok(Some(Auth::Bearer(token.clone()))),
+        Value::List(parts) => match parts.as_slice() {
+            [user] => Ok(Some(Auth::Basic(user.to_string(), None))),
+            [user, pass] => Ok(Some(Auth::Basic(user.to_string(), Some(pass.to_string())))),
+            _ => Err("auth must be [user, password] or a bearer token".to_string()),
+        },
+        other => Err(format!("auth must be [user, password] or a bearer token, got {}", other)),
+    }
+}
+
+fn parse_timeout(value: &Value) -> Result<Option<Duration>, String> {
+    match value {
+        Value::None => Ok(None),
+        Value::Int(n) if *n >= 0 => Ok(Some(Duration::from_secs(*n as u64))),
+        Value::Float(f) if *f >= 0.0 => Ok(Some(Duration::from_secs_f64(*f))),
+        other => Err(format!("timeout must be a number of seconds, got {}", other)),
+    }
+}
+
+enum Body {
+    Empty,
+    Raw(Vec<u8>, Option<&'static str>),
+    Form(Vec<(String, String)>),
+    Multipart(Vec<(String, String)>, Vec<(String, Value)>),
+}
+
+/// A fully described request, rebuilt for every retry attempt
+struct PreparedRequest {
+    method: reqwest::Method,
+    url: String,
+    params: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    auth: Option<Auth>,
+    timeout: Option<Duration>,
+    body: Body,
+    follow: bool,
+    stream: bool,
+    retries: u32,
+}
+
+fn prepare(session: &Session, method: &str, url: &str, kwargs: &[(String, Value)]) -> Result<PreparedRequest, String> {
+    let method = reqwest::Method::from_bytes(method.to_uppercase().as_bytes())
+        .map_err(|_| format!("Invalid HTTP method: {}", method))?;
+    let mut request = PreparedRequest {
+        method,
+        url: session.resolve_url(url),
+        params: Vec::new(),
+        headers: session.headers.clone(),
+        auth: session.auth.clone(),
+        timeout: session.timeout,
+        body: Body::Empty,
+        follow: true,
+        stream: false,
+        retries: session.retries,
+    };
+    let mut data = None;
+    let mut files = None;
+    for (key, value) in kwargs {
+        match key.as_str() {
+            "params" => request.params = string_pairs(value, "params")?,
+            "headers" => {
+                for (name, val) in string_pairs(value, "headers")? {
+                    request.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(&name));
+                    request.headers.push((name, val));
+                }
+            }
+            "auth" => request.auth = parse_auth(value)?,
+            "timeout" => request.timeout = parse_timeout(value)?.or(request.timeout),
+            "allow_redirects" => request.follow = !matches!(value, Value::Bool(false)),
+            "stream" => request.stream = matches!(value, Value::Bool(true)),
+            "retries" => match value {
+                Value::Int(n) if *n >= 0 => request.retries = *n as u32,
+                other => return Err(format!("retries must be a non-negative integer, got {}", other)),
+            },
+            "json" => {
+                let body = serde_json::to_vec(&value.to_serde_json()).map_err(|e| e.to_string())?;
+                request.body = Body::Raw(body, Some("application/json"));
+            }
+            "data" => data = Some(value.clone()),
+            "files" => files = Some(value.clone()),
+            _ => return Err(format!("Unknown request option: {}", key)),
+        }
+    }
+    match (data, files) {
+        (data, Some(Value::Dict(files))) => {
+            let fields = data.map(|d| string_pairs(&d, "data")).transpose()?.unwrap_or_default();
+            let files = files.into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+            request.body = Body::Multipart(fields, files);
+        }
+        (_, Some(other)) => return Err(format!("files must be a dict, got {}", other)),
+        (Some(Value::String(text)), None) => request.body = Body::Raw(text.into_bytes(), None),
+        (Some(Value::List(bytes)), None) => {
+            let raw = bytes
+                .iter()
+                .map(|b| match b {
+                    Value::Int(n) if (0..=255).contains(n) => Ok(*n as u8),
+                    _ => Err("data list must contain byte values 0-255".to_string()),
+                })
+                .collect::<Result<Vec<u8>, String>>()?;
+            request.body = Body::Raw(raw, Some("application/octet-stream"));
+        }
+        (Some(data @ Value::Dict(_)), None) => request.body = Body::Form(string_pairs(&data, "data")?),
+        (Some(Value::None) | None, None) => {}
+        (Some(other), None) => return Err(format!("data must be a dict, string or byte list, got {}", other)),
+    }
+    Ok(request)
+}
+
+/// One multipart file: a path, or {"filename", "content", "content_type"}
+fn multipart_part(value: &Value) -> Result<multipart::Part, String> {
+    match value {
+        Value::String(path) => {
+            checks::fs_read(path)?;
+            let bytes = std::fs::read(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+            let filename = std::path::Path::new(path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "file".to_string());
+            Ok(multipart::Part::bytes(bytes).file_name(filename))
+        }
+        Value::Dict(pairs) => {
+            let get = |name: &str| pairs.iter().find(|(k, _)| matches!(k, Value::String(s) if s == name)).map(|(_, v)| v);
+            let content = match get("content") {
+                Some(Value::String(s)) => s.clone().into_bytes(),
+                Some(other) => other.to_string().into_bytes(),
+                None => return Err("multipart file dict requires 'content'".to_string()),
+            };
+            let mut part = multipart::Part::bytes(content);
+            if let Some(name) = get("filename") {
+                part = part.file_name(name.to_string());
+            }
+            if let Some(mime) = get("content_type") {
+                part = part.mime_str(&mime.to_string()).map_err(|e| e.to_string())?;
+            }
+            Ok(part)
+        }
+        other => Err(format!("files values must be a path or a dict, got {}", other)),
+    }
+}
+
+fn build(client: &Client, request: &PreparedRequest) -> Result<RequestBuilder, String> {
+    let mut builder = client.request(request.method.clone(), &request.url);
+    if !request.params.is_empty() {
+        builder = builder.query(&request.params);
+    }
+    for (name, value) in &request.headers {
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+    builder = match &request.auth {
+        Some(Auth::Basic(user, pass)) => builder.basic_auth(user, pass.as_ref()),
+        Some(Auth::Bearer(token)) => builder.bearer_auth(token),
+        None => builder,
+    };
+    if let Some(timeout) = request.timeout {
+        builder = builder.timeout(timeout);
+    }
+    Ok(match &request.body {
+        Body::Empty => builder,
+        Body::Raw(bytes, content_type) => {
+            let has_type = request.headers.iter().any(|(n, _)| n.eq_ignore_ascii_case("content-type"));
+            match content_type {
+                Some(ct) if !has_type => builder.header("Content-Type", *ct).body(bytes.clone()),
+                _ => builder.body(bytes.clone()),
+            }
+        }
+        Body::Form(fields) => builder.form(fields),
+        Body::Multipart(fields, files) => {
+            let mut form = multipart::Form::new();
+            for (name, value) in fields {
+                form = form.text(name.clone(), value.clone());
+            }
+            for (name, file) in files {
+                form = form.part(name.clone(), multipart_part(file)?);
+            }
+            builder.multipart(form)
+        }
+    })
+}
+
+fn is_idempotent(method: &reqwest::Method) -> bool {
+    matches!(method.as_str(), "GET" | "HEAD" | "PUT" | "DELETE" | "OPTIONS")
+}
+
+/// Send with retries: connection errors always retry; 429/502/503/504 retry
+/// only for idempotent methods. Backoff doubles from 200ms.
+fn send(client: &Client, request: &PreparedRequest) -> Result<Response, String> {
+    checks::http(&request.url)?;
+    let mut attempt = 0;
+    loop {
+        let result = build(client, request)?.send();
+        let retryable = match &result {
+            Err(e) => e.is_connect() || e.is_timeout(),
+            Ok(resp) => is_idempotent(&request.method) && matches!(resp.status().as_u16(), 429 | 502 | 503 | 504),
+        };
+        if !retryable || attempt >= request.retries {
+            return result.map_err(|e| format!("HTTP request failed: {}", e));
+        }
+        std::thread::sleep(Duration::from_millis(200 * (1 << attempt.min(6))));
+        attempt += 1;
+    }
+}
+
+fn headers_value(response: &Response) -> Value {
+    let mut pairs: Vec<(Value, Value)> = Vec::new();
+    for (name, value) in response.headers() {
+        let value = String::from_utf8_lossy(value.as_bytes()).to_string();
+        let key = Value::String(name.as_str().to_string());
+        match pairs.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, Value::String(existing))) => {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            }
+            _ => pairs.push((key, Value::String(value))),
+        }
+    }
+    Value::Dict(pairs)
+}
+
+fn response_value(response: Response, stream: bool) -> Result<Value, String> {
+    let status = response.status();
+    let mut fields = HashMap::new();
+    fields.insert("status".to_string(), Value::Int(status.as_u16() as i64));
+    fields.insert("ok".to_string(), Value::Bool(status.is_success()));
+    fields.insert("reason".to_string(), Value::String(status.canonical_reason().unwrap_or("").to_string()));
+    fields.insert("url".to_string(), Value::String(response.url().to_string()));
+    fields.insert("headers".to_string(), headers_value(&response));
+
+    if stream {
+        let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+        STREAMS.lock().unwrap().insert(id, BufReader::new(response));
+        fields.insert("stream_id".to_string(), Value::Int(id as i64));
+    } else {
+        let limit = crate::config::get_config().network.max_body_size;
+        let mut bytes = Vec::new();
+        response
+            .take(limit.saturating_add(1))
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Failed to read response body: {}", e))?;
+        if bytes.len() as u64 > limit {
+            return Err(format!("Response body exceeds network.max_body_size ({} bytes)", limit));
+        }
+        fields.insert("text".to_string(), Value::String(String::from_utf8_lossy(&bytes).to_string()));
+        fields.insert("bytes".to_string(), Value::List(bytes.into_iter().map(|b| Value::Int(b as i64)).collect()));
+    }
+    Ok(Value::Instance { class_name: RESPONSE_CLASS.to_string(), fields })
+}
+
+/// Run a request through `session`; the lock is released before any I/O
+fn request_with(session: &Mutex<Session>, method: &str, url: &str, kwargs: &[(String, Value)]) -> Result<Value, String> {
+    let (client, request) = {
+        let mut session = session.lock().unwrap();
+        let request = prepare(&session, method, url, kwargs)?;
+        (session.client(request.follow)?, request)
+    };
+    let response = send(&client, &request)?;
+    response_value(response, request.stream)
+}
+
+fn request_in_registry(id: u64, method: &str, url: &str, kwargs: &[(String, Value)]) -> Result<Value, String> {
+    let (client, request) = {
+        let mut sessions = SESSIONS.lock().unwrap();
+        let session = sessions.get_mut(&id).ok_or("HTTP session is closed")?;
+        let request = prepare(session, method, url, kwargs)?;
+        (session.client(request.follow)?, request)
+    };
+    let response = send(&client, &request)?;
+    response_value(response, request.stream)
+}
+
+fn configure_session(session: &mut Session, kwargs: &[(String, Value)]) -> Result<(), String> {
+    for (key, value) in kwargs {
+        match key.as_str() {
+            "headers" => session.headers = string_pairs(value, "headers")?,
+            "auth" => session.auth = parse_auth(value)?,
+            "timeout" => session.timeout = parse_timeout(value)?,
+            "base_url" => session.base_url = Some(value.to_string()),
+            "retries" => match value {
+                Value::Int(n) if *n >= 0 => session.retries = *n as u32,
+                other => return Err(format!("retries must be a non-negative integer, got {}", other)),
+            },
+            "max_redirects" => match value {
+                Value::Int(n) if *n >= 0 => session.max_redirects = *n as usize,
+                other => return Err(format!("max_redirects must be a non-negative integer, got {}", other)),
+            },
+            _ => return Err(format!("Unknown session option: {}", key)),
+        }
+    }
+    // Redirect limits are baked into the clients
+    session.follow_client = None;
+    session.no_follow_client = None;
+    Ok(())
+}
+
+fn url_arg(args: &[Value], index: usize, func: &str) -> Result<String, String> {
+    match args.get(index) {
+        Some(Value::String(s)) => Ok(s.clone()),
+        _ => Err(format!("{}() requires a URL string", func)),
+    }
+}
+
+// ============================================================================
+// Module functions and methods
+// ============================================================================
+
+/// Call an `http.<name>` module function
+pub fn call(name: &str, args: Vec<Value>) -> Result<Value, String> {
+    let (args, kwargs) = split_kwargs(args, 0);
+    match name {
+        _ if METHODS.contains(&name) => request_with(&DEFAULT_SESSION, name, &url_arg(&args, 0, name)?, &kwargs),
+        "request" => {
+            let method = url_arg(&args, 0, name)?;
+            request_with(&DEFAULT_SESSION, &method, &url_arg(&args, 1, name)?, &kwargs)
+        }
+        "session" => {
+            let mut session = Session::new(true);
+            configure_session(&mut session, &kwargs)?;
+            let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+            SESSIONS.lock().unwrap().insert(id, session);
+            let mut fields = HashMap::new();
+            fields.insert("id".to_string(), Value::Int(id as i64));
+            Ok(Value::Instance { class_name: SESSION_CLASS.to_string(), fields })
+        }
+        _ => Err(format!("http has no function '{}'", name)),
+    }
+}
+
+/// Methods on `http.Session` and `http.Response` values
+pub fn call_method(value: &Value, method: &str, args: Vec<Value>) -> Option<Result<Value, String>> {
+    let Value::Instance { class_name, fields } = value else { return None };
+    match class_name.as_str() {
+        SESSION_CLASS => {
+            let Some(Value::Int(id)) = fields.get("id") else { return None };
+            Some(session_method(*id as u64, method, args))
+        }
+        RESPONSE_CLASS => Some(response_method(fields, method, args)),
+        _ => None,
+    }
+}
+
+fn session_method(id: u64, method: &str, args: Vec<Value>) -> Result<Value, String> {
+    let (args, kwargs) = split_kwargs(args, 0);
+    match method {
+        _ if METHODS.contains(&method) => request_in_registry(id, method, &url_arg(&args, 0, method)?, &kwargs),
+        "request" => {
+            let verb = url_arg(&args, 0, method)?;
+            request_in_registry(id, &verb, &url_arg(&args, 1, method)?, &kwargs)
+        }
+        "configure" => {
+            let mut sessions = SESSIONS.lock().unwrap();
+            let session = sessions.get_mut(&id).ok_or("HTTP session is closed")?;
+            configure_session(session, &kwargs)?;
+            Ok(Value::None)
+        }
+        "set_header" => {
+            let (Some(name), Some(val)) = (args.first(), args.get(1)) else {
+                return Err("set_header() requires a name and a value".to_string());
+            };
+            let mut sessions = SESSIONS.lock().unwrap();
+            let session = sessions.get_mut(&id).ok_or("HTTP session is closed")?;
+            let name = name.to_string();
+            session.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(&name));
+            if !matches!(val, Value::None) {
+                session.headers.push((name, val.to_string()));
+            }
+            Ok(Value::None)
+        }
+        "cookies" => {
+            let url = url_arg(&args, 0, "cookies")?;
+            let sessions = SESSIONS.lock().unwrap();
+            let session = sessions.get(&id).ok_or("HTTP session is closed")?;
+            let parsed = url::Url::parse(&session.resolve_url(&url)).map_err(|e| format!("Invalid URL: {}", e))?;
+            let header = session.jar.as_ref().and_then(|jar| jar.cookies(&parsed));
+            let cookies = header
+                .map(|h| String::from_utf8_lossy(h.as_bytes()).to_string())
+                .unwrap_or_default();
+            Ok(Value::Dict(
+                cookies
+                    .split("; ")
+                    .filter_map(|pair| pair.split_once('='))
+                    .map(|(k, v)| (Value::String(k.to_string()), Value::String(v.to_string())))
+                    .collect(),
+            ))
+        }
+        "close" => {
+            SESSIONS.lock().unwrap().remove(&id);
+            Ok(Value::None)
+        }
+        _ => Err(format!("'http.Session' object has no method '{}'", method)),
+    }
+}
+
+fn response_method(fields: &HashMap<String, Value>, method: &str, args: Vec<Value>) -> Result<Value, String> {
+    let stream_id = match fields.get("stream_id") {
+        Some(Value::Int(id)) => Some(*id as u64),
+        _ => None,
+    };
+    match method {
+        "json" => match fields.get("text") {
+            Some(Value::String(text)) => serde_json::from_str::<serde_json::Value>(text)
+                .map(|json| Value::from_serde_json(&json))
+                .map_err(|e| format!("Response is not valid JSON: {}", e)),
+            _ => Err("json() is not available on streamed responses; use read()".to_string()),
+        },
+        "raise_for_status" => match fields.get("status") {
+            Some(Value::Int(status)) if *status >= 400 => Err(format!(
+                "HTTP {} {} for {}",
+                status,
+                fields.get("reason").map(|r| r.to_string()).unwrap_or_default(),
+                fields.get("url").map(|u| u.to_string()).unwrap_or_default()
+            )),
+            _ => Ok(Value::None),
+        },
+        "read" | "read_line" => {
+            let id = stream_id.ok_or("read() requires a response opened with stream=true")?;
+            let mut streams = STREAMS.lock().unwrap();
+            let reader = streams.get_mut(&id).ok_or("Response stream is closed")?;
+            let mut buf = Vec::new();
+            if method == "read_line" {
+                reader.read_until(b'\n', &mut buf).map_err(|e| e.to_string())?;
+            } else {
+                let size = match args.first() {
+                    Some(Value::Int(n)) if *n > 0 => *n as usize,
+                    _ => 8192,
+                };
+                buf.resize(size, 0);
+                let n = reader.read(&mut buf).map_err(|e| e.to_string())?;
+                buf.truncate(n);
+            }
+            if buf.is_empty() {
+                streams.remove(&id);
+                return Ok(Value::None);
+            }
+            Ok(Value::String(String::from_utf8_lossy(&buf).to_string()))
+        }
+        "close" => {
+            if let Some(id) = stream_id {
+                STREAMS.lock().unwrap().remove(&id);
+            }
+            Ok(Value::None)
+        }
+        _ => Err(format!("'http.Response' object has no method '{}'", method)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serve canned routes on a local tiny_http server; returns the base URL
+    fn stub_server() -> String {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", server.server_addr().to_ip().unwrap());
+        std::thread::spawn(move || {
+            let mut flaky_hits = 0;
+            for mut request in server.incoming_requests() {
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).ok();
+                let header = |name: &str| {
+                    request
+                        .headers()
+                        .iter()
+                        .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case(name))
+                        .map(|h| h.value.to_string())
+                        .unwrap_or_default()
+                };
+                let url = request.url().to_string();
+                let (status, extra, text) = match url.split('?').next().unwrap_or("") {
+                    "/echo" => (200, None, serde_json::json!({
+                        "method": request.method().to_string(),
+                        "url": url,
+                        "auth": header("Authorization"),
+                        "token": header("X-Token"),
+                        "type": header("Content-Type"),
+                        "body": body,
+                    }).to_string()),
+                    "/login" => (200, Some(("Set-Cookie", "sid=abc123; Path=/")), "ok".to_string()),
+                    "/whoami" => (200, None, header("Cookie")),
+                    "/old" => (302, Some(("Location", "/echo")), String::new()),
+                    "/flaky" => {
+                        flaky_hits += 1;
+                        if flaky_hits < 3 { (503, None, "busy".to_string()) } else { (200, None, "recovered".to_string()) }
+                    }
+                    _ => (404, None, "missing".to_string()),
+                };
+                let mut response = tiny_http::Response::from_string(text).with_status_code(status);
+                if let Some((name, value)) = extra {
+                    response.add_header(tiny_http::Header::from_bytes(name, value).unwrap());
+                }
+                request.respond(response).ok();
+            }
+        });
+        base
+    }
+
+    fn kw(pairs: Vec<(&str, Value)>) -> Value {
+        Value::Dict(pairs.into_iter().map(|(k, v)| (Value::String(k.to_string()), v)).collect())
+    }
+
+    fn field(value: &Value, name: &str) -> Value {
+        match value {
+            Value::Instance { fields, .. } => fields.get(name).cloned().unwrap_or(Value::None),
+            _ => Value::None,
+        }
+    }
+
+    fn echo_field(response: &Value, name: &str) -> Value {
+        let Ok(Value::Dict(pairs)) = call_method(response, "json", vec![]).unwrap() else { panic!("not json") };
+        pairs.into_iter().find(|(k, _)| *k == Value::String(name.to_string())).unwrap().1
+    }
+
+    #[test]
+    fn test_methods_params_and_json_body() {
+        let base = stub_server();
+        let r = call("put", vec![
+            Value::String(format!("{}/echo", base)),
+            kw(vec![
+                ("params", kw(vec![("q", Value::String("a b&c".to_string()))])),
+                ("json", kw(vec![("n", Value::Int(1))])),
+                ("headers", kw(vec![("X-Token", Value::String("t1".to_string()))])),
+                ("auth", Value::String("secret".to_string())),
+            ]),
+        ]).unwrap();
+        assert_eq!(field(&r, "status"), Value::Int(200));
+        assert_eq!(echo_field(&r, "method"), Value::String("PUT".to_string()));
+        assert_eq!(echo_field(&r, "url"), Value::String("/echo?q=a+b%26c".to_string()));
+        assert_eq!(echo_field(&r, "body"), Value::String("{\"n\":1}".to_string()));
+        assert_eq!(echo_field(&r, "type"), Value::String("application/json".to_string()));
+        assert_eq!(echo_field(&r, "token"), Value::String("t1".to_string()));
+        assert_eq!(echo_field(&r, "auth"), Value::String("Bearer secret".to_string()));
+    }
+
+    #[test]
+    fn test_session_cookies_redirects_and_retries() {
+        let base = stub_server();
+        let session = call("session", vec![kw(vec![("base_url", Value::String(base.clone()))])]).unwrap();
+        let get = |path: &str, opts: Vec<(&str, Value)>| {
+            call_method(&session, "get", vec![Value::String(path.to_string()), kw(opts)]).unwrap().unwrap()
+        };
+
+        get("/login", vec![]);
+        assert_eq!(field(&get("/whoami", vec![]), "text"), Value::String("sid=abc123".to_string()));
+
+        let followed = get("/old", vec![]);
+        assert_eq!(field(&followed, "status"), Value::Int(200));
+        assert!(field(&followed, "url").to_string().ends_with("/echo"));
+        let held = get("/old", vec![("allow_redirects", Value::Bool(false))]);
+        assert_eq!(field(&held, "status"), Value::Int(302));
+
+        let flaky = get("/flaky", vec![("retries", Value::Int(3))]);
+        assert_eq!(field(&flaky, "text"), Value::String("recovered".to_string()));
+
+        let missing = get("/nope", vec![]);
+        assert!(call_method(&missing, "raise_for_status", vec![]).unwrap().is_err());
+        call_method(&session, "close", vec![]).unwrap().unwrap();
+    }
+}
@@ -1,5 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use crate::ast::*;
+use crate::debugger::DebugHook;
+use crate::profiler::Profiler;
+use crate::coverage::Coverage;
 
 // Global stream sessions for HTTP streaming
 #[cfg(feature = "native")]
@@ -19,8 +25,43 @@ static STREAM_SESSIONS: Lazy<Mutex<HashMap<u64, StreamSession>>> = Lazy::new(||
     Mutex::new(HashMap::new())
 });
 
+/// Split a trailing `name=value` dict off native call arguments.
+///
+/// Kwargs arrive as a final dict argument, so it is only treated as kwargs
+/// when more than `positional` arguments were passed and all keys are strings.
+pub(crate) fn split_kwargs(mut args: Vec<Value>, positional: usize) -> (Vec<Value>, Vec<(String, Value)>) {
+    if args.len() > positional {
+        if let Some(Value::Dict(pairs)) = args.last() {
+            if pairs.iter().all(|(k, _)| matches!(k, Value::String(_))) {
+                let kwargs = pairs.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+                args.pop();
+                return (args, kwargs);
+            }
+        }
+    }
+    (args, Vec::new())
+}
+
+/// Key that marks a dict as a native module
+pub(crate) const MODULE_KEY: &str = "__module__";
+
+/// A native module: a dict whose keys are also read as attributes (`math.pi`)
+pub(crate) fn module_value(name: &str, mut members: Vec<(Value, Value)>) -> Value {
+    members.insert(0, (Value::String(MODULE_KEY.to_string()), Value::String(name.to_string())));
+    Value::Dict(members)
+}
+
+/// A member of a native module dict; `None` for plain dicts
+fn module_member<'a>(pairs: &'a [(Value, Value)], name: &str) -> Option<&'a Value> {
+    let key = |k: &Value, name: &str| matches!(k, Value::String(s) if s == name);
+    if !pairs.iter().any(|(k, _)| key(k, MODULE_KEY)) {
+        return None;
+    }
+    pairs.iter().find(|(k, _)| key(k, name)).map(|(_, v)| v)
+}
+
 /// Process escape sequences in a string
-fn process_escapes(s: &str) -> String {
+pub(crate) fn process_escapes(s: &str) -> String {
     // Order matters: process \\ last to avoid double-processing
     s.replace("\\n", "\n")
      .replace("\\t", "\t")
@@ -68,13 +109,56 @@ pub struct Interpreter {
     should_break: bool,
     should_continue: bool,
     current_line: Option<usize>,
+    echo_output: bool,
+    debug_hook: Option<Box<dyn DebugHook>>,
+    /// Call stack, only tracked while a debug hook is installed or `invoke` runs
+    frames: Vec<Frame>,
+    trace: bool,
+    profiler: Option<Profiler>,
+    coverage: Option<Coverage>,
+    harness: Option<crate::headless::Harness>,
+    /// Handlers registered with `on(topic, handler)` for events from pages
+    listeners: HashMap<String, Vec<Value>>,
+    /// Files of the modules imported so far, each loaded once
+    modules: HashSet<PathBuf>,
+    /// Module file whose top level is running; `None` for the entry
+    module_file: Option<PathBuf>,
+    /// Set from another thread to stop the running call
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+/// Definitions of an interpreter, to roll back a reload that failed
+pub struct Snapshot {
+    globals: HashMap<String, Value>,
+    top_level: HashMap<String, Value>,
+    classes: HashMap<String, ClassDef>,
+    listeners: HashMap<String, Vec<Value>>,
+    modules: HashSet<PathBuf>,
+}
+
+/// One entry of the call stack seen by the debugger
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub name: String,
+    pub line: usize,
+    /// Index of the first scope belonging to this frame
+    depth: usize,
+}
+
+/// Why `Interpreter::invoke` failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallError {
+    /// No function defined in Poly under that name
+    NotFound(String),
+    /// The arguments do not match the parameters
+    InvalidArguments(String),
+    /// The function failed; `traceback` lists (function, line) outermost first
+    Runtime { message: String, traceback: Vec<(String, Option<usize>)> },
 }
 
 #[derive(Clone)]
 struct ClassDef {
-    #[allow(dead_code)]
     name: String,
-    #[allow(dead_code)]
     parent: Option<String>,
     methods: HashMap<String, (Vec<Param>, Vec<Statement>)>,
 }
@@ -91,6 +175,17 @@ impl Interpreter {
             should_break: false,
             should_continue: false,
             current_line: None,
+            echo_output: true,
+            debug_hook: None,
+            frames: Vec::new(),
+            trace: false,
+            profiler: None,
+            coverage: None,
+            harness: None,
+            listeners: HashMap::new(),
+            modules: HashSet::new(),
+            module_file: None,
+            cancel: None,
         };
         interp.register_builtins();
         interp
@@ -104,6 +199,8 @@ impl Interpreter {
             "isinstance", "hasattr", "getattr", "setattr", "list", "dict",
             "set", "tuple", "bool", "chr", "ord", "hex", "bin", "oct",
             "round", "pow", "divmod", "slice", "iter", "next", "open",
+            // Testing
+            "expect_raises", "ipc_invoke", "ipc_emit", "emitted", "fake_api", "fake_calls",
             // String methods
             "upper", "lower", "strip", "split", "join", "replace", "startswith", "endswith",
             "find", "count", "isdigit", "isalpha", "isalnum", "format",
@@ -121,6 +218,12 @@ impl Interpreter {
             "http_stream_start", "http_stream_poll", "http_stream_close",
             // JSON
             "json_parse", "json_stringify",
+            // Events between backend and pages
+            "emit", "on", "off",
+            // State shared by the IPC workers
+            "shared_get", "shared_set", "shared_add",
+            // HTTP routes, usually as decorators
+            "get", "post", "put", "patch", "delete", "middleware", "response",
             // System & Performance (Rust-powered)
             "env", "env_get", "env_set", "exec", "spawn",
             "hash_md5", "hash_sha256", "base64_encode", "base64_decode",
@@ -161,10 +264,356 @@ impl Interpreter {
 
     pub fn get_output(&self) -> &[String] { &self.output }
 
+    /// Output printed since the last call
+    pub fn take_output(&mut self) -> Vec<String> { std::mem::take(&mut self.output) }
+
+    /// Record emitted events and fake system APIs for `poly test`
+    pub fn set_harness(&mut self, harness: Option<crate::headless::Harness>) { self.harness = harness; }
+
+    /// Whether `print` also writes to stdout (output is always captured)
+    pub fn set_echo_output(&mut self, echo: bool) { self.echo_output = echo; }
+
+    /// Names currently defined in the global scope
+    pub fn global_names(&self) -> Vec<String> {
+        self.globals.keys().cloned().collect()
+    }
+
+    pub fn global(&self, name: &str) -> Option<&Value> {
+        self.globals.get(name)
+    }
+
+    /// Bind a top-level name where `let` at the top level would have put it
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        match self.scopes.first_mut() {
+            Some(scope) if !self.globals.contains_key(name) => scope.insert(name.to_string(), value),
+            _ => self.globals.insert(name.to_string(), value),
+        };
+    }
+
+    /// Run the next programs as the top level of the module file `file`
+    /// (`None` for the entry), which owns the routes they declare
+    pub fn set_module_file(&mut self, file: Option<PathBuf>) {
+        self.module_file = file;
+    }
+
+    /// Whether the module file at `path` has been imported
+    pub fn has_module(&self, path: &Path) -> bool {
+        std::fs::canonicalize(path).is_ok_and(|path| self.modules.contains(&path))
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            globals: self.globals.clone(),
+            top_level: self.scopes.first().cloned().unwrap_or_default(),
+            classes: self.classes.clone(),
+            listeners: self.listeners.clone(),
+            modules: self.modules.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        self.globals = snapshot.globals;
+        self.scopes.truncate(1);
+        if let Some(scope) = self.scopes.first_mut() {
+            *scope = snapshot.top_level;
+        }
+        self.classes = snapshot.classes;
+        self.listeners = snapshot.listeners;
+        self.modules = snapshot.modules;
+    }
+
+    /// Run `import <module>` against this interpreter
+    pub fn import(&mut self, module: &str) -> Result<(), String> {
+        self.import_module(module).map(|_| ())
+    }
+
+    /// Parameter names of a global function, if one is defined under `name`
+    pub fn function_params(&self, name: &str) -> Option<Vec<String>> {
+        match self.globals.get(name) {
+            Some(Value::Function { params, .. }) => Some(params.iter().map(|p| p.name.clone()).collect()),
+            _ => None,
+        }
+    }
+
+    /// Call a global function by name
+    pub fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value, String> {
+        let func = self.globals.get(name).cloned()
+            .ok_or_else(|| format!("Function '{}' is not defined", name))?;
+        self.call_isolated(func, args)
+    }
+
+    /// Call a function defined in Poly (never a builtin) with positional and
+    /// keyword arguments bound directly to its parameters
+    pub fn invoke(&mut self, name: &str, args: Vec<Value>, kwargs: Vec<(String, Value)>) -> Result<Value, CallError> {
+        let Some(Value::Function { params, .. }) = self.globals.get(name).cloned() else {
+            return Err(CallError::NotFound(format!("Function '{}' is not defined", name)));
+        };
+        if args.len() > params.len() {
+            return Err(CallError::InvalidArguments(format!("{}() takes {} argument(s) but {} were given", name, params.len(), args.len())));
+        }
+        if let Some((key, _)) = kwargs.iter().find(|(key, _)| !params.iter().any(|p| p.name == *key)) {
+            return Err(CallError::InvalidArguments(format!("{}() got an unexpected argument '{}'", name, key)));
+        }
+        if let Some(param) = params[..args.len()].iter().find(|p| kwargs.iter().any(|(key, _)| *key == p.name)) {
+            return Err(CallError::InvalidArguments(format!("{}() got multiple values for argument '{}'", name, param.name)));
+        }
+
+        // Positional values for every parameter up to the last one given
+        let given = params.iter().rposition(|p| kwargs.iter().any(|(key, _)| *key == p.name)).map_or(args.len(), |i| (i + 1).max(args.len()));
+        let mut values = args;
+        for param in &params[values.len()..given] {
+            if let Some((_, value)) = kwargs.iter().find(|(key, _)| *key == param.name) {
+                values.push(value.clone());
+            } else if let Some(default) = &param.default {
+                let value = self.evaluate(default).map_err(CallError::InvalidArguments)?;
+                values.push(value);
+            } else {
+                return Err(CallError::InvalidArguments(format!("{}() missing argument '{}'", name, param.name)));
+            }
+        }
+        if let Some(param) = params[values.len()..].iter().find(|p| p.default.is_none()) {
+            return Err(CallError::InvalidArguments(format!("{}() missing argument '{}'", name, param.name)));
+        }
+
+        let tracing = self.debug_hook.is_none();
+        let depth = self.scopes.len();
+        if tracing {
+            self.trace = true;
+            self.frames.clear();
+        }
+        let line = self.current_line;
+        let result = self.call(name, values);
+        let frames: Vec<Frame> = self.frames.iter().filter(|frame| frame.depth >= depth).cloned().collect();
+        let error_line = self.current_line;
+        if tracing {
+            self.trace = false;
+            self.frames.clear();
+        }
+        self.current_line = line;
+        result.map_err(|message| {
+            // A frame records the line it was called from, which is where its caller was
+            let lines = frames.iter().skip(1).map(|frame| Some(frame.line).filter(|l| *l > 0)).chain([error_line]);
+            let traceback = frames.iter().map(|frame| frame.name.clone()).zip(lines).collect();
+            CallError::Runtime { message, traceback }
+        })
+    }
+
+    /// Run the `on` handlers of an event from a page; returns how many ran
+    pub fn dispatch_event(&mut self, event: &crate::events::Event) -> Result<usize, String> {
+        let handlers = self.listeners.get(&event.topic).cloned().unwrap_or_default();
+        let payload = Value::from_serde_json(&event.payload);
+        for handler in &handlers {
+            self.call_isolated(handler.clone(), vec![payload.clone()])?;
+        }
+        Ok(handlers.len())
+    }
+
+    /// Value of a variable as code at the current position would see it
+    pub fn variable(&self, name: &str) -> Option<Value> {
+        self.get_var(name)
+    }
+
+    /// Every name visible from the current scope, sorted
+    pub fn variable_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.scopes.iter()
+            .flat_map(|scope| scope.keys())
+            .chain(self.globals.keys())
+            .cloned()
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Method names of a user-defined class, including inherited ones
+    pub fn class_methods(&self, class_name: &str) -> Vec<String> {
+        let mut methods: Vec<String> = self.class_chain(class_name)
+            .flat_map(|c| c.methods.keys().cloned())
+            .collect();
+        methods.sort();
+        methods.dedup();
+        methods
+    }
+
+    /// A class followed by its ancestors, stopping at an unknown or repeated parent
+    fn class_chain<'a>(&'a self, class_name: &str) -> impl Iterator<Item = &'a ClassDef> + 'a {
+        let mut seen = Vec::new();
+        let mut next = self.classes.get(class_name);
+        std::iter::from_fn(move || {
+            let class = next.take()?;
+            seen.push(class.name.as_str());
+            next = class.parent.as_deref()
+                .filter(|p| !seen.contains(p))
+                .and_then(|p| self.classes.get(p));
+            Some(class)
+        })
+    }
+
+    /// Resolve a method on a class or the nearest ancestor that defines it
+    fn find_method(&self, class_name: &str, method: &str) -> Option<(Vec<Param>, Vec<Statement>)> {
+        self.class_chain(class_name).find_map(|c| c.methods.get(method)).cloned()
+    }
+
+    /// Run a program and restore the scope stack even if it fails part-way,
+    /// so the interpreter stays usable for the next program (REPL, IPC)
+    pub fn run_isolated(&mut self, program: &Program) -> Result<Value, String> {
+        let depth = self.scopes.len();
+        let result = self.run(program);
+        self.scopes.truncate(depth);
+        self.should_return = false;
+        self.should_break = false;
+        self.should_continue = false;
+        result
+    }
+
+    /// Install a debugger that is called before every statement. Only programs
+    /// parsed with `Parser::with_lines` reach the hook.
+    pub fn set_debug_hook(&mut self, hook: Option<Box<dyn DebugHook>>) {
+        self.frames = match hook {
+            Some(_) => vec![Frame { name: "<module>".to_string(), line: self.current_line.unwrap_or(0), depth: 0 }],
+            None => Vec::new(),
+        };
+        self.debug_hook = hook;
+    }
+
+    /// Stop at the next statement once `flag` is set, with a "Cancelled" error
+    pub fn set_cancel_flag(&mut self, flag: Option<Arc<AtomicBool>>) {
+        self.cancel = flag;
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+
+    /// Record function and line timings until the profiler is taken back
+    pub fn set_profiler(&mut self, profiler: Option<Profiler>) {
+        self.profiler = profiler;
+    }
+
+    pub fn profiler_mut(&mut self) -> Option<&mut Profiler> {
+        self.profiler.as_mut()
+    }
+
+    pub fn take_profiler(&mut self) -> Option<Profiler> {
+        self.profiler.take()
+    }
+
+    /// Record line and branch hits of programs parsed with
+    /// `Parser::with_lines().with_source(..)`
+    pub fn set_coverage(&mut self, coverage: Option<Coverage>) {
+        self.coverage = coverage;
+    }
+
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    fn cover_branch(&mut self, line: Option<usize>, arm: usize, arms: usize) {
+        if let (Some(coverage), Some(line)) = (&mut self.coverage, line) {
+            coverage.branch(line, self.scopes.len(), arm, arms);
+        }
+    }
+
+    /// Current call stack, outermost first
+    pub fn frames(&self) -> &[Frame] { &self.frames }
+
+    /// Variables of a frame from `frames()`, sorted by name
+    pub fn frame_locals(&self, index: usize) -> Vec<(String, Value)> {
+        let Some(frame) = self.frames.get(index) else { return Vec::new() };
+        let end = self.frames.get(index + 1).map_or(self.scopes.len(), |f| f.depth);
+        let mut locals: HashMap<String, Value> = HashMap::new();
+        for scope in self.scopes.get(frame.depth..end).unwrap_or_default() {
+            locals.extend(scope.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        let mut locals: Vec<_> = locals.into_iter().collect();
+        locals.sort_by(|a, b| a.0.cmp(&b.0));
+        locals
+    }
+
+    /// Globals defined by the program (builtins excluded), sorted by name
+    pub fn user_globals(&self) -> Vec<(String, Value)> {
+        let mut globals: Vec<_> = self.globals.iter()
+            .filter(|(_, v)| !matches!(v, Value::NativeFunction(_)))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        globals.sort_by(|a, b| a.0.cmp(&b.0));
+        globals
+    }
+
+    /// Evaluate source code as if it ran inside frame `index`: names resolve to
+    /// that frame and the ones below it, and the call stack is left untouched
+    pub fn evaluate_in_frame(&mut self, source: &str, index: usize) -> Result<Value, String> {
+        let program = crate::parser::Parser::new(crate::lexer::Lexer::new(source).tokenize()).parse()?;
+        let end = self.frames.get(index + 1).map_or(self.scopes.len(), |f| f.depth);
+        let hidden = self.scopes.split_off(end.min(self.scopes.len()));
+        let frames = self.frames.clone();
+        let hook = self.debug_hook.take();
+        let profiler = self.profiler.take();
+        let coverage = self.coverage.take();
+        let flags = (self.should_return, self.return_value.take(), self.should_break, self.should_continue);
+        self.should_return = false;
+        self.should_break = false;
+        self.should_continue = false;
+
+        let result = self.run(&program);
+
+        self.scopes.truncate(end);
+        self.scopes.extend(hidden);
+        self.frames = frames;
+        if hook.is_some() {
+            self.debug_hook = hook;
+        }
+        self.profiler = profiler;
+        self.coverage = coverage;
+        (self.should_return, self.return_value, self.should_break, self.should_continue) = flags;
+        result
+    }
+
+    /// Enter a function call: push a scope and, while debugging, a frame
+    fn push_frame(&mut self, name: &str) {
+        if self.debug_hook.is_some() || self.trace {
+            let depth = self.scopes.len();
+            self.frames.retain(|frame| frame.depth < depth);
+            self.frames.push(Frame { name: name.to_string(), line: self.current_line.unwrap_or(0), depth });
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter(name, self.scopes.len());
+        }
+        self.scopes.push(HashMap::new());
+    }
+
+    fn pop_frame(&mut self) {
+        self.scopes.pop();
+        self.pop_frames();
+    }
+
+    /// Forget frames whose scopes are gone
+    fn pop_frames(&mut self) {
+        let depth = self.scopes.len();
+        self.frames.retain(|frame| frame.depth < depth);
+        if let Some(profiler) = &mut self.profiler {
+            profiler.exit(depth);
+        }
+    }
+
+    /// Call a function and restore the scope stack even if it fails part-way
+    fn call_isolated(&mut self, func: Value, args: Vec<Value>) -> Result<Value, String> {
+        let depth = self.scopes.len();
+        let result = self.call_function(func, args);
+        self.scopes.truncate(depth);
+        self.should_return = false;
+        self.should_break = false;
+        self.should_continue = false;
+        result
+    }
+
     fn execute_statement(&mut self, stmt: &Statement) -> Result<Value, String> {
         if self.should_return || self.should_break || self.should_continue {
             return Ok(Value::None);
         }
+        if self.cancelled() {
+            return Err("Cancelled".to_string());
+        }
 
         match stmt {
             Statement::Let(name, expr) => {
@@ -192,7 +641,7 @@ impl Interpreter {
             Statement::For { var, iter, body } => {
                 self.execute_for(var, iter, body)
             }
-            Statement::FnDef { name, params, body } => {
+            Statement::FnDef { name, params, body, .. } => {
                 let func = Value::Function {
                     name: name.clone(),
                     params: params.clone(),
@@ -209,7 +658,7 @@ impl Interpreter {
                 self.should_return = true;
                 Ok(self.return_value.clone().unwrap_or(Value::None))
             }
-            Statement::ClassDef { name, parent, methods } => {
+            Statement::ClassDef { name, parent, methods, .. } => {
                 self.define_class(name, parent, methods)
             }
             Statement::Expr(expr) => self.evaluate(expr),
@@ -225,6 +674,9 @@ impl Interpreter {
                 let val = self.evaluate(expr)?;
                 Err(format!("{}", val))
             }
+            Statement::With { context, alias, body } => {
+                self.execute_with(context, alias, body)
+            }
             Statement::Assert(condition, message) => {
                 let cond_val = self.evaluate(condition)?;
                 if !self.is_truthy(&cond_val) {
@@ -275,6 +727,32 @@ impl Interpreter {
                 }
                 Ok(Value::None)
             }
+            Statement::Source(file) => {
+                if let Some(coverage) = &mut self.coverage {
+                    coverage.enter(file, self.scopes.len());
+                }
+                Ok(Value::None)
+            }
+            Statement::Line(line) => {
+                self.current_line = Some(*line);
+                if let Some(profiler) = &mut self.profiler {
+                    profiler.line(*line);
+                }
+                if let Some(coverage) = &mut self.coverage {
+                    coverage.line(*line, self.scopes.len());
+                }
+                if let Some(mut hook) = self.debug_hook.take() {
+                    let depth = self.scopes.len();
+                    self.frames.retain(|frame| frame.depth < depth);
+                    if let Some(frame) = self.frames.last_mut() {
+                        frame.line = *line;
+                    }
+                    let result = hook.on_line(self, *line);
+                    self.debug_hook = Some(hook);
+                    result?;
+                }
+                Ok(Value::None)
+            }
         }
     }
     
@@ -283,7 +761,7 @@ impl Interpreter {
         match module {
             "math" => {
                 // Create math module as a dict
-                let math_module = Value::Dict(vec![
+                let math_module = module_value("math", vec![
                     (Value::String("pi".to_string()), Value::Float(std::f64::consts::PI)),
                     (Value::String("e".to_string()), Value::Float(std::f64::consts::E)),
                     (Value::String("tau".to_string()), Value::Float(std::f64::consts::TAU)),
@@ -313,6 +791,26 @@ impl Interpreter {
                 self.globals.insert("sleep".to_string(), Value::NativeFunction("sleep".to_string()));
                 Ok(Value::None)
             }
+            "datetime" => {
+                self.globals.insert("datetime".to_string(), crate::datetime::module());
+                Ok(Value::None)
+            }
+            "crypto" => {
+                self.globals.insert("crypto".to_string(), crate::crypto::module());
+                Ok(Value::None)
+            }
+            "http" => {
+                self.globals.insert("http".to_string(), crate::http_client::module());
+                Ok(Value::None)
+            }
+            "sqlite" => {
+                self.globals.insert("sqlite".to_string(), crate::sqlite::module());
+                Ok(Value::None)
+            }
+            "csv" | "toml" | "yaml" | "ini" => {
+                self.globals.insert(module.to_string(), crate::formats::module(module));
+                Ok(Value::None)
+            }
             "json" => {
                 self.globals.insert("json_dumps".to_string(), Value::NativeFunction("json_dumps".to_string()));
                 self.globals.insert("json_loads".to_string(), Value::NativeFunction("json_loads".to_string()));
@@ -322,19 +820,48 @@ impl Interpreter {
                 // Try to load from file
                 let file_path = format!("{}.poly", module);
                 if std::path::Path::new(&file_path).exists() {
+                    // A module runs once; importing it again reuses its definitions
+                    let path = std::fs::canonicalize(&file_path).ok();
+                    if path.as_ref().is_some_and(|path| self.modules.contains(path)) {
+                        return Ok(Value::None);
+                    }
                     let source = std::fs::read_to_string(&file_path)
                         .map_err(|e| format!("Failed to read module {}: {}", module, e))?;
                     
                     let lexer = crate::lexer::Lexer::new(&source);
                     let tokens = lexer.tokenize();
                     let mut parser = crate::parser::Parser::new(tokens);
-                    let program = parser.parse()?;
+                    
+                    // Under coverage, trace the module's statements back to its file
+                    let active = self.coverage.as_ref().map(|c| c.active_files());
+                    let program = match &mut self.coverage {
+                        Some(coverage) => {
+                            let name = std::fs::canonicalize(&file_path).map_or(file_path.clone(), |p| p.display().to_string());
+                            let program = parser.with_lines().with_source(&name).parse()?;
+                            coverage.register(&name, &program);
+                            program
+                        }
+                        None => parser.parse()?,
+                    };
                     
                     // Execute module in current scope
+                    self.modules.extend(path.clone());
+                    let outer = std::mem::replace(&mut self.module_file, path.clone());
+                    let mut result = Ok(Value::None);
                     for stmt in &program.statements {
-                        self.execute_statement(stmt)?;
+                        if let Err(e) = self.execute_statement(stmt) {
+                            result = Err(e);
+                            break;
+                        }
                     }
-                    Ok(Value::None)
+                    self.module_file = outer;
+                    if let (Some(coverage), Some(active)) = (&mut self.coverage, active) {
+                        coverage.set_active_files(active);
+                    }
+                    if let (Err(_), Some(path)) = (&result, &path) {
+                        self.modules.remove(path);
+                    }
+                    result
                 } else {
                     Err(format!("Module not found: {}", module))
                 }
@@ -393,17 +920,21 @@ impl Interpreter {
 
     fn execute_if(&mut self, condition: &Expr, then_body: &[Statement], 
                   elif_branches: &[(Expr, Vec<Statement>)], else_body: &Option<Vec<Statement>>) -> Result<Value, String> {
+        let line = self.current_line;
+        let arms = elif_branches.len() + 2;
         let cond_val = self.evaluate(condition)?;
         if self.is_truthy(&cond_val) {
+            self.cover_branch(line, 0, arms);
             for stmt in then_body {
                 self.execute_statement(stmt)?;
                 if self.should_return || self.should_break || self.should_continue { break; }
             }
         } else {
             let mut executed = false;
-            for (elif_cond, elif_body) in elif_branches {
+            for (i, (elif_cond, elif_body)) in elif_branches.iter().enumerate() {
                 let elif_val = self.evaluate(elif_cond)?;
                 if self.is_truthy(&elif_val) {
+                    self.cover_branch(line, i + 1, arms);
                     for stmt in elif_body {
                         self.execute_statement(stmt)?;
                         if self.should_return || self.should_break || self.should_continue { break; }
@@ -413,6 +944,7 @@ impl Interpreter {
                 }
             }
             if !executed {
+                self.cover_branch(line, arms - 1, arms);
                 if let Some(else_stmts) = else_body {
                     for stmt in else_stmts {
                         self.execute_statement(stmt)?;
@@ -425,9 +957,14 @@ impl Interpreter {
     }
 
     fn execute_while(&mut self, condition: &Expr, body: &[Statement]) -> Result<Value, String> {
+        let line = self.current_line;
         loop {
             let cond_val = self.evaluate(condition)?;
-            if !self.is_truthy(&cond_val) { break; }
+            if !self.is_truthy(&cond_val) {
+                self.cover_branch(line, 1, 2);
+                break;
+            }
+            self.cover_branch(line, 0, 2);
             
             for stmt in body {
                 self.execute_statement(stmt)?;
@@ -440,6 +977,7 @@ impl Interpreter {
     }
 
     fn execute_for(&mut self, var: &str, iter: &Expr, body: &[Statement]) -> Result<Value, String> {
+        let line = self.current_line;
         let iterable = self.evaluate(iter)?;
         let items = match iterable {
             Value::List(items) => items,
@@ -449,6 +987,7 @@ impl Interpreter {
         };
         
         for item in items {
+            self.cover_branch(line, 0, 2);
             self.set_var(var.to_string(), item);
             for stmt in body {
                 self.execute_statement(stmt)?;
@@ -457,12 +996,17 @@ impl Interpreter {
                 if self.should_continue { self.should_continue = false; break; }
             }
         }
+        self.cover_branch(line, 1, 2);
         Ok(Value::None)
     }
 
     fn execute_try(&mut self, try_body: &[Statement], except_body: &[Statement]) -> Result<Value, String> {
+        let depth = self.scopes.len();
         for stmt in try_body {
             if let Err(_) = self.execute_statement(stmt) {
+                // Drop the scopes of calls the error escaped from
+                self.scopes.truncate(depth);
+                self.pop_frames();
                 for except_stmt in except_body {
                     self.execute_statement(except_stmt)?;
                 }
@@ -472,6 +1016,69 @@ impl Interpreter {
         Ok(Value::None)
     }
 
+    /// `with ctx as name:` calls `__enter__`, runs the body, then always calls
+    /// `__exit__` with the error message (or None). A truthy result from
+    /// `__exit__` swallows the error.
+    fn execute_with(&mut self, context: &Expr, alias: &Option<String>, body: &[Statement]) -> Result<Value, String> {
+        let manager = self.evaluate(context)?;
+        let entered = self.call_protocol_method(&manager, "__enter__", vec![])?;
+        if let Some(name) = alias {
+            self.set_var(name.clone(), entered);
+        }
+
+        let mut outcome = Ok(Value::None);
+        for stmt in body {
+            outcome = self.execute_statement(stmt);
+            if outcome.is_err() || self.should_return || self.should_break || self.should_continue {
+                break;
+            }
+        }
+
+        let error = match &outcome {
+            Err(e) => Value::String(e.clone()),
+            Ok(_) => Value::None,
+        };
+        let suppress = self.call_protocol_method(&manager, "__exit__", vec![error])?;
+        match outcome {
+            Err(_) if self.is_truthy(&suppress) => Ok(Value::None),
+            other => other,
+        }
+    }
+
+    /// Call a dunder method on a user-class or native instance
+    fn call_protocol_method(&mut self, target: &Value, method: &str, args: Vec<Value>) -> Result<Value, String> {
+        if let Value::Instance { class_name, .. } = target {
+            let user_method = self.find_method(class_name, method);
+            if let Some((params, body)) = user_method {
+                self.push_frame(method);
+                self.set_var("self".to_string(), target.clone());
+                for (param, value) in params.iter().skip(1).zip(args) {
+                    self.set_var(param.name.clone(), value);
+                }
+                self.should_return = false;
+                self.return_value = None;
+                let mut result = Ok(());
+                for stmt in &body {
+                    if let Err(e) = self.execute_statement(stmt) {
+                        result = Err(e);
+                        break;
+                    }
+                    if self.should_return { break; }
+                }
+                self.pop_frame();
+                self.should_return = false;
+                result?;
+                return Ok(self.return_value.take().unwrap_or(Value::None));
+            }
+            if Self::is_native_instance(target) {
+                if let Some(result) = Self::call_native_method(target, method, args) {
+                    return result.map_err(|e| self.error(e));
+                }
+            }
+        }
+        Err(self.error(format!("'{}' object does not support the context manager protocol", target)))
+    }
+
     fn define_class(&mut self, name: &str, parent: &Option<String>, methods: &[Method]) -> Result<Value, String> {
         let mut method_map = HashMap::new();
         for m in methods {
@@ -580,17 +1187,19 @@ impl Interpreter {
                     // Handle dict method calls
                     if let Value::Dict(pairs) = &target_val {
                         let arg_values: Vec<Value> = args.iter().map(|a| self.evaluate(a)).collect::<Result<Vec<_>, _>>()?;
+                        // Modules (e.g. `datetime.now()`) hold their functions as values
+                        if let Some(func) = Self::dict_member_function(pairs, method_name) {
+                            return self.call_function(func, arg_values);
+                        }
                         return self.call_dict_method(pairs.clone(), method_name, arg_values);
                     }
                     
                     // Handle instance method calls
                     if let Value::Instance { class_name, fields: _ } = &target_val {
-                        let method = self.classes.get(class_name)
-                            .and_then(|c| c.methods.get(method_name))
-                            .cloned();
+                        let method = self.find_method(class_name, method_name);
                         
                         if let Some((params, body)) = method {
-                            self.scopes.push(HashMap::new());
+                            self.push_frame(method_name);
                             // Bind self
                             self.set_var("self".to_string(), target_val.clone());
                             // Bind other arguments (skip 'self' in params)
@@ -617,19 +1226,27 @@ impl Interpreter {
                             if let Some(modified_self) = self.get_var("self") {
                                 // Update the original instance variable
                                 if let Expr::Identifier(var_name) = target.as_ref() {
-                                    self.scopes.pop();
+                                    self.pop_frame();
                                     self.set_var(var_name.clone(), modified_self);
                                 } else {
-                                    self.scopes.pop();
+                                    self.pop_frame();
                                 }
                             } else {
-                                self.scopes.pop();
+                                self.pop_frame();
                             }
                             
                             let result = self.return_value.take().unwrap_or(Value::None);
                             self.should_return = false;
                             return Ok(result);
                         }
+
+                        // Built-in value types (datetime, csv readers, ...) implemented in Rust
+                        if Self::is_native_instance(&target_val) {
+                            let arg_values: Vec<Value> = args.iter().map(|a| self.evaluate(a)).collect::<Result<Vec<_>, _>>()?;
+                            if let Some(result) = Self::call_native_method(&target_val, method_name, arg_values) {
+                                return result.map_err(|e| self.error(e));
+                            }
+                        }
                     }
                 }
                 
@@ -646,7 +1263,19 @@ impl Interpreter {
             }
             Expr::CallWithKwargs(callee, args, kwargs) => {
                 // For now, treat kwargs as additional args or handle specially
-                let func = self.evaluate(callee)?;
+                let mut method_target = None;
+                let func = match callee.as_ref() {
+                    Expr::Attribute(target, method_name) => {
+                        let target_val = self.evaluate(target)?;
+                        if Self::is_native_instance(&target_val) {
+                            method_target = Some((target_val, method_name.clone()));
+                            Value::None
+                        } else {
+                            self.evaluate(callee)?
+                        }
+                    }
+                    _ => self.evaluate(callee)?,
+                };
                 let mut arg_values: Vec<Value> = args.iter().map(|a| self.evaluate(a)).collect::<Result<Vec<_>, _>>()?;
                 
                 // Store kwargs in a dict for functions that need them
@@ -660,6 +1289,13 @@ impl Interpreter {
                     arg_values.push(Value::Dict(kwargs_dict));
                 }
                 
+                if let Some((target_val, method_name)) = method_target {
+                    if let Some(result) = Self::call_native_method(&target_val, &method_name, arg_values) {
+                        return result.map_err(|e| self.error(e));
+                    }
+                    return Err(self.error(format!("No method '{}'", method_name)));
+                }
+                
                 self.call_function(func, arg_values)
             }
             Expr::Widget { widget_type, props, children } => {
@@ -790,9 +1426,7 @@ impl Interpreter {
                     return Ok(val.clone());
                 }
                 // Clone method info to avoid borrow issues
-                let method = self.classes.get(class_name)
-                    .and_then(|c| c.methods.get(attr))
-                    .cloned();
+                let method = self.find_method(class_name, attr);
                     
                 if let Some((params, body)) = method {
                     return Ok(Value::Function {
@@ -818,6 +1452,10 @@ impl Interpreter {
                 }
             }
             Value::Dict(pairs) => {
+                // Module members (`math.pi`, `datetime.UTC`)
+                if let Some(val) = module_member(pairs, attr) {
+                    return Ok(val.clone());
+                }
                 // Dict attributes
                 match attr {
                     "length" => Ok(Value::Int(pairs.len() as i64)),
@@ -834,7 +1472,7 @@ impl Interpreter {
     fn call_function(&mut self, func: Value, args: Vec<Value>) -> Result<Value, String> {
         match func {
             Value::Function { params, body, name } => {
-                self.scopes.push(HashMap::new());
+                self.push_frame(&name);
                 
                 // Bind parameters with default value support
                 let required_count = params.iter().filter(|p| p.default.is_none()).count();
@@ -870,7 +1508,7 @@ impl Interpreter {
                     if self.should_return { break; }
                 }
                 
-                self.scopes.pop();
+                self.pop_frame();
                 self.should_return = false;
                 Ok(self.return_value.take().unwrap_or(Value::None))
             }
@@ -881,12 +1519,10 @@ impl Interpreter {
                 };
                 
                 // Clone the method info to avoid borrow issues
-                let init_method = self.classes.get(&name)
-                    .and_then(|c| c.methods.get("__init__"))
-                    .cloned();
+                let init_method = self.find_method(&name, "__init__");
                 
                 if let Some((params, body)) = init_method {
-                    self.scopes.push(HashMap::new());
+                    self.push_frame(&format!("{}.__init__", name));
                     self.set_var("self".to_string(), instance.clone());
                     
                     // Bind parameters (skip 'self')
@@ -905,7 +1541,7 @@ impl Interpreter {
                         self.execute_statement(stmt)?; 
                     }
                     instance = self.get_var("self").unwrap_or(instance);
-                    self.scopes.pop();
+                    self.pop_frame();
                 }
                 Ok(instance)
             }
@@ -914,6 +1550,41 @@ impl Interpreter {
         }
     }
     
+    /// Instances whose methods are implemented in Rust rather than a Poly class.
+    /// Native classes use dotted names ("csv.Reader", "http.Session") that no
+    /// Poly class declaration can produce.
+    fn is_native_instance(value: &Value) -> bool {
+        match value {
+            Value::Instance { class_name, .. } => {
+                crate::datetime::is_temporal(value) || class_name.contains('.')
+            }
+            _ => false,
+        }
+    }
+
+    /// Dispatch a method call on a native instance
+    fn call_native_method(target: &Value, method: &str, args: Vec<Value>) -> Option<Result<Value, String>> {
+        if crate::datetime::is_temporal(target) {
+            return crate::datetime::call_method(target, method, args);
+        }
+        match target {
+            Value::Instance { class_name, .. } if class_name.starts_with("http.") => {
+                crate::http_client::call_method(target, method, args)
+            }
+            Value::Instance { class_name, .. } if class_name.starts_with("sqlite.") => {
+                crate::sqlite::call_method(target, method, args)
+            }
+            _ => crate::formats::call_method(target, method, args),
+        }
+    }
+
+    /// Find a callable stored under `name` in a module-style dict
+    fn dict_member_function(pairs: &[(Value, Value)], name: &str) -> Option<Value> {
+        module_member(pairs, name)
+            .filter(|v| matches!(v, Value::Function { .. } | Value::NativeFunction(_)))
+            .cloned()
+    }
+
     /// Call a string method
     fn call_string_method(&mut self, s: &str, method: &str, args: Vec<Value>) -> Result<Value, String> {
         match method {
@@ -1237,7 +1908,9 @@ impl Interpreter {
                 let line = output.join(" ");
                 self.output.push(line.clone());
                 #[cfg(not(target_arch = "wasm32"))]
-                println!("{}", line);
+                if self.echo_output {
+                    println!("{}", line);
+                }
                 Ok(Value::None)
             }
             "len" => match args.get(0) {
@@ -1275,23 +1948,9 @@ impl Interpreter {
                 _ => Err("float() requires a number or string".to_string()),
             }
             "bool" => Ok(Value::Bool(self.is_truthy(args.get(0).unwrap_or(&Value::None)))),
-            "type" => {
-                let type_name = match args.get(0) {
-                    Some(Value::None) => "NoneType",
-                    Some(Value::Bool(_)) => "bool",
-                    Some(Value::Int(_)) => "int",
-                    Some(Value::Float(_)) => "float",
-                    Some(Value::String(_)) => "str",
-                    Some(Value::List(_)) => "list",
-                    Some(Value::Dict(_)) => "dict",
-                    Some(Value::Function { .. }) => "function",
-                    Some(Value::NativeFunction(_)) => "builtin_function",
-                    Some(Value::Instance { class_name, .. }) => class_name,
-                    Some(Value::Class { name, .. }) => name,
-                    Some(Value::Widget(node)) => &node.widget_type,
-                    None => return Err("type() requires an argument".to_string()),
-                };
-                Ok(Value::String(type_name.to_string()))
+            "type" => match args.first() {
+                Some(value) => Ok(Value::String(value.type_name().to_string())),
+                None => Err("type() requires an argument".to_string()),
             }
             "abs" => match args.get(0) {
                 Some(Value::Int(n)) => Ok(Value::Int(n.abs())),
@@ -1392,32 +2051,31 @@ impl Interpreter {
                 Some(Value::Int(n)) => Ok(Value::Float((*n as f64).ln())),
                 _ => Err("log() requires a number".to_string()),
             }
-            // Random functions
-            "random" => {
-                use std::time::{SystemTime, UNIX_EPOCH};
-                let seed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
-                let random = ((seed * 1103515245 + 12345) % (1 << 31)) as f64 / (1u64 << 31) as f64;
-                Ok(Value::Float(random))
-            }
+            // Random functions (OS-backed CSPRNG)
+            "random" => crate::crypto::random_float().map(Value::Float),
             "randint" => match (args.get(0), args.get(1)) {
-                (Some(Value::Int(a)), Some(Value::Int(b))) => {
-                    use std::time::{SystemTime, UNIX_EPOCH};
-                    let seed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
-                    let range = (b - a + 1) as u128;
-                    let random = (seed % range) as i64 + a;
-                    Ok(Value::Int(random))
-                }
+                (Some(Value::Int(a)), Some(Value::Int(b))) => crate::crypto::random_range(*a, *b).map(Value::Int),
                 _ => Err("randint() requires two integers".to_string()),
             }
             "choice" => match args.get(0) {
                 Some(Value::List(items)) if !items.is_empty() => {
-                    use std::time::{SystemTime, UNIX_EPOCH};
-                    let seed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
-                    let idx = (seed as usize) % items.len();
-                    Ok(items[idx].clone())
+                    let idx = crate::crypto::random_range(0, items.len() as i64 - 1)?;
+                    Ok(items[idx as usize].clone())
                 }
                 _ => Err("choice() requires a non-empty list".to_string()),
             }
+            "shuffle" => match args.first() {
+                Some(Value::List(items)) => {
+                    // Fisher-Yates
+                    let mut items = items.clone();
+                    for i in (1..items.len()).rev() {
+                        let j = crate::crypto::random_range(0, i as i64)? as usize;
+                        items.swap(i, j);
+                    }
+                    Ok(Value::List(items))
+                }
+                _ => Err("shuffle() requires a list".to_string()),
+            }
             // Time functions
             "time" => {
                 use std::time::{SystemTime, UNIX_EPOCH};
@@ -1700,6 +2358,9 @@ impl Interpreter {
                                 }
                                 serde_json::Value::Object(map)
                             }
+                            v @ Value::Instance { .. } if crate::datetime::is_temporal(v) => {
+                                serde_json::Value::String(crate::datetime::isoformat(v))
+                            }
                             _ => serde_json::Value::Null,
                         }
                     }
@@ -1765,6 +2426,131 @@ impl Interpreter {
                     Err(self.error("http_post_json() requires native feature"))
                 }
             }
+            // Events
+            "emit" => {
+                // emit(topic, payload=none, window=none) -> number of pages reached
+                let (args, kwargs) = split_kwargs(args, 2);
+                let topic = match args.first() {
+                    Some(Value::String(s)) => s.clone(),
+                    _ => return Err(self.error("emit() requires a topic string")),
+                };
+                let payload = args.get(1).map(Value::to_serde_json).unwrap_or(serde_json::Value::Null);
+                let window = match kwargs.iter().find(|(k, _)| k == "window").map(|(_, v)| v) {
+                    Some(Value::String(w)) => Some(w.clone()),
+                    None | Some(Value::None) => None,
+                    Some(_) => return Err(self.error("emit() window must be a string")),
+                };
+                if let Some(harness) = &mut self.harness {
+                    harness.record(crate::events::Event::new(topic.clone(), payload.clone()));
+                }
+                Ok(Value::Int(crate::events::emit(&topic, payload, window.as_deref()) as i64))
+            }
+            "on" => {
+                // on(topic, handler) -> None; handler(payload) runs for events from pages
+                match (args.first(), args.get(1)) {
+                    (Some(Value::String(topic)), Some(handler @ Value::Function { .. })) => {
+                        self.listeners.entry(topic.clone()).or_default().push(handler.clone());
+                        Ok(Value::None)
+                    }
+                    _ => Err(self.error("on() requires a topic string and a function")),
+                }
+            }
+            "off" => {
+                // off(topic) -> None; removes every handler of the topic
+                match args.first() {
+                    Some(Value::String(topic)) => {
+                        self.listeners.remove(topic);
+                        Ok(Value::None)
+                    }
+                    _ => Err(self.error("off() requires a topic string")),
+                }
+            }
+            // HTTP routes
+            "get" | "post" | "put" | "patch" | "delete" => {
+                // get(path, handler) -> handler; `@get(path)` before a function
+                match (args.first(), args.get(1)) {
+                    (Some(Value::String(path)), Some(handler @ Value::Function { name: handler_name, .. })) => {
+                        crate::routes::add(name, path, handler_name, self.module_file.as_deref()).map_err(|e| self.error(e))?;
+                        Ok(handler.clone())
+                    }
+                    _ => Err(self.error(format!("{}() requires a path and a function", name))),
+                }
+            }
+            "middleware" => {
+                // middleware(prefix="/", handler) -> handler; handler(request) returns none or a response
+                let (prefix, handler) = match (args.first(), args.get(1)) {
+                    (Some(handler), None) => ("/".to_string(), handler),
+                    (Some(Value::String(prefix)), Some(handler)) => (prefix.clone(), handler),
+                    _ => return Err(self.error("middleware() requires a path prefix and a function")),
+                };
+                match handler {
+                    Value::Function { name: handler_name, .. } => {
+                        crate::routes::add_middleware(&prefix, handler_name, self.module_file.as_deref());
+                        Ok(handler.clone())
+                    }
+                    _ => Err(self.error("middleware() requires a function")),
+                }
+            }
+            "response" => {
+                // response(body=none, status=200, headers=none) -> response for a route
+                // A trailing dict is only taken as keywords when it names no other key
+                let is_kwargs = args.len() > 1 && matches!(args.last(), Some(Value::Dict(pairs))
+                    if pairs.iter().all(|(k, _)| matches!(k, Value::String(k) if ["body", "status", "headers"].contains(&k.as_str()))));
+                let (args, kwargs) = if is_kwargs { split_kwargs(args, 1) } else { (args, Vec::new()) };
+                let kwarg = |name: &str| kwargs.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone());
+                let status = match kwarg("status").or_else(|| args.get(1).cloned()) {
+                    None | Some(Value::None) => 200,
+                    Some(Value::Int(status)) if (100..=599).contains(&status) => status,
+                    Some(_) => return Err(self.error("response() status must be a number from 100 to 599")),
+                };
+                let headers = match kwarg("headers").or_else(|| args.get(2).cloned()) {
+                    None | Some(Value::None) => Value::Dict(Vec::new()),
+                    Some(headers @ Value::Dict(_)) => headers,
+                    Some(_) => return Err(self.error("response() headers must be a dict")),
+                };
+                let body = kwarg("body").or_else(|| args.first().cloned()).unwrap_or(Value::None);
+                Ok(Value::Dict(vec![
+                    (Value::String(crate::routes::RESPONSE_KEY.to_string()), Value::Bool(true)),
+                    (Value::String("status".to_string()), Value::Int(status)),
+                    (Value::String("headers".to_string()), headers),
+                    (Value::String("body".to_string()), body),
+                ]))
+            }
+            // State shared by the IPC workers
+            "shared_get" => {
+                // shared_get(key, default=none) -> value
+                match args.first() {
+                    Some(Value::String(key)) => Ok(crate::workers::shared_get(key)
+                        .map(|value| Value::from_serde_json(&value))
+                        .unwrap_or_else(|| args.get(1).cloned().unwrap_or(Value::None))),
+                    _ => Err(self.error("shared_get() requires a key string")),
+                }
+            }
+            "shared_set" => {
+                // shared_set(key, value) -> None; none removes the key
+                match (args.first(), args.get(1)) {
+                    (Some(Value::String(key)), Some(value)) => {
+                        crate::workers::shared_set(key, value.to_serde_json());
+                        Ok(Value::None)
+                    }
+                    _ => Err(self.error("shared_set() requires a key string and a value")),
+                }
+            }
+            "shared_add" => {
+                // shared_add(key, amount=1) -> new value, atomically across workers
+                let amount = match args.get(1) {
+                    None => 1.0,
+                    Some(Value::Int(n)) => *n as f64,
+                    Some(Value::Float(n)) => *n,
+                    Some(_) => return Err(self.error("shared_add() amount must be a number")),
+                };
+                match args.first() {
+                    Some(Value::String(key)) => crate::workers::shared_add(key, amount)
+                        .map(|value| Value::from_serde_json(&value))
+                        .map_err(|e| self.error(e)),
+                    _ => Err(self.error("shared_add() requires a key string")),
+                }
+            }
             // JSON functions
             "json_parse" => {
                 // json_parse(string) -> value
@@ -1822,6 +2608,9 @@ impl Interpreter {
                             }
                             serde_json::Value::Object(map)
                         }
+                        v @ Value::Instance { .. } if crate::datetime::is_temporal(v) => {
+                            serde_json::Value::String(crate::datetime::isoformat(v))
+                        }
                         _ => serde_json::Value::Null,
                     }
                 }
@@ -1862,6 +2651,9 @@ impl Interpreter {
                                 }
                                 serde_json::Value::Object(map)
                             }
+                            v @ Value::Instance { .. } if crate::datetime::is_temporal(v) => {
+                                serde_json::Value::String(crate::datetime::isoformat(v))
+                            }
                             _ => serde_json::Value::Null,
                         }
                     }
@@ -2071,6 +2863,86 @@ impl Interpreter {
                 }
                 _ => Err(self.error("all() requires a list")),
             }
+            "expect_raises" => {
+                // expect_raises(fn, match?) -> error message; fails if fn does not raise
+                let func = match args.first() {
+                    Some(f @ (Value::Function { .. } | Value::NativeFunction(_))) => f.clone(),
+                    _ => return Err(self.error("expect_raises() requires a function")),
+                };
+                let expected = args.get(1).map(|v| v.to_string());
+                match self.call_isolated(func, Vec::new()) {
+                    Ok(_) => Err(self.error("Expected an error but none was raised")),
+                    Err(message) => match expected {
+                        Some(fragment) if !message.contains(&fragment) => Err(self.error(format!(
+                            "Expected an error matching '{}' but got: {}", fragment, message
+                        ))),
+                        _ => Ok(Value::String(message)),
+                    },
+                }
+            }
+            "ipc_invoke" => {
+                // ipc_invoke(function, args=none) -> what poly.invoke would resolve to
+                let function = match args.first() {
+                    Some(Value::String(s)) => s.clone(),
+                    _ => return Err(self.error("ipc_invoke() requires a function name")),
+                };
+                let request = crate::ipc::Request {
+                    function,
+                    args: args.get(1).map(Value::to_serde_json).unwrap_or(serde_json::Value::Null),
+                    ..Default::default()
+                };
+                let fakes = self.harness.as_ref().map(|h| h.fakes.clone());
+                match crate::headless::dispatch(fakes.as_ref(), &request, |request| crate::ipc::invoke(self, request)) {
+                    Ok(result) => Ok(Value::from_serde_json(&result)),
+                    Err(e) => Err(self.error(format!("{}: {}", e.code, e.message))),
+                }
+            }
+            "ipc_emit" => {
+                // ipc_emit(topic, payload=none) -> number of on() handlers run
+                let topic = match args.first() {
+                    Some(Value::String(s)) => s.clone(),
+                    _ => return Err(self.error("ipc_emit() requires a topic string")),
+                };
+                let payload = args.get(1).map(Value::to_serde_json).unwrap_or(serde_json::Value::Null);
+                let count = self.dispatch_event(&crate::events::Event::new(topic, payload))?;
+                Ok(Value::Int(count as i64))
+            }
+            "emitted" => {
+                // emitted(topic=none) -> [{topic, payload}] sent since the last call
+                let topic = match args.first() {
+                    Some(Value::String(s)) => Some(s.clone()),
+                    None | Some(Value::None) => None,
+                    Some(_) => return Err(self.error("emitted() topic must be a string")),
+                };
+                let Some(harness) = &mut self.harness else {
+                    return Err(self.error("emitted() is only available under poly test"));
+                };
+                let events = harness.take_events(topic.as_deref());
+                Ok(Value::List(events.iter().map(|e| Value::from_serde_json(&e.to_json())).collect()))
+            }
+            "fake_api" => {
+                // fake_api(command, result) -> none; ipc_invoke(command) then returns result
+                let (command, result) = match (args.first(), args.get(1)) {
+                    (Some(Value::String(s)), result) => (s.clone(), result.map(Value::to_serde_json).unwrap_or(serde_json::Value::Null)),
+                    _ => return Err(self.error("fake_api() requires a command name")),
+                };
+                let Some(harness) = &self.harness else {
+                    return Err(self.error("fake_api() is only available under poly test"));
+                };
+                harness.fakes.set_value(&command, result);
+                Ok(Value::None)
+            }
+            "fake_calls" => {
+                // fake_calls(command) -> arguments of each call the fake received
+                let command = match args.first() {
+                    Some(Value::String(s)) => s.clone(),
+                    _ => return Err(self.error("fake_calls() requires a command name")),
+                };
+                let Some(harness) = &self.harness else {
+                    return Err(self.error("fake_calls() is only available under poly test"));
+                };
+                Ok(Value::List(harness.fakes.calls(&command).iter().map(Value::from_serde_json).collect()))
+            }
             "enumerate" => match args.get(0) {
                 Some(Value::List(items)) => {
                     let enumerated: Vec<Value> = items.iter().enumerate()
@@ -2450,6 +3322,9 @@ const {name_lower}Store = new {name}Store();
                     Err(self.error("exec() requires native feature"))
                 }
             }
+            _ if name.starts_with("datetime.") => {
+                crate::datetime::call(&name["datetime.".len()..], args).map_err(|e| self.error(e))
+            }
             "timestamp" => {
                 // timestamp() -> int (milliseconds since epoch)
                 use std::time::{SystemTime, UNIX_EPOCH};
@@ -2512,7 +3387,14 @@ const {name_lower}Store = new {name}Store();
                 // sleep_ms(milliseconds) -> None
                 match args.get(0) {
                     Some(Value::Int(ms)) => {
-                        std::thread::sleep(std::time::Duration::from_millis(*ms as u64));
+                        // In slices, so a cancelled call does not sleep on
+                        let until = std::time::Instant::now() + std::time::Duration::from_millis((*ms).max(0) as u64);
+                        while let Some(left) = until.checked_duration_since(std::time::Instant::now()).filter(|d| !d.is_zero()) {
+                            if self.cancelled() {
+                                return Err("Cancelled".to_string());
+                            }
+                            std::thread::sleep(left.min(std::time::Duration::from_millis(20)));
+                        }
                         Ok(Value::None)
                     }
                     _ => Err(self.error("sleep_ms() requires milliseconds")),
@@ -2520,20 +3402,7 @@ const {name_lower}Store = new {name}Store();
             }
             "uuid" => {
                 // uuid() -> string (UUID v4)
-                use std::time::{SystemTime, UNIX_EPOCH};
-                let seed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
-                let random1 = ((seed * 1103515245 + 12345) % (1u128 << 64)) as u64;
-                let random2 = ((seed * 6364136223846793005 + 1442695040888963407) % (1u128 << 64)) as u64;
-                
-                let uuid = format!(
-                    "{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
-                    (random1 >> 32) as u32,
-                    (random1 >> 16) as u16 & 0xFFFF,
-                    random1 as u16 & 0x0FFF,
-                    (random2 >> 48) as u16 & 0x3FFF | 0x8000,
-                    random2 & 0xFFFFFFFFFFFF
-                );
-                Ok(Value::String(uuid))
+                crate::crypto::uuid4().map(Value::String).map_err(|e| self.error(e))
             }
             
             // ============================================
@@ -2640,102 +3509,47 @@ const {name_lower}Store = new {name}Store();
             "hash_md5" => {
                 // hash_md5(string) -> string (hex)
                 match args.get(0) {
-                    Some(Value::String(s)) => {
-                        // Simple MD5 implementation would be too long, use a basic hash for now
-                        // In production, you'd use the md5 crate
-                        let hash = s.bytes().fold(0u128, |acc, b| acc.wrapping_mul(31).wrapping_add(b as u128));
-                        Ok(Value::String(format!("{:032x}", hash)))
-                    }
+                    Some(Value::String(s)) => Ok(Value::String(crate::crypto::md5(s.as_bytes()))),
                     _ => Err(self.error("hash_md5() requires a string")),
                 }
             }
             "hash_sha256" => {
                 // hash_sha256(string) -> string (hex)
                 match args.get(0) {
-                    Some(Value::String(s)) => {
-                        // Simple hash for demo - in production use sha2 crate
-                        let hash1 = s.bytes().fold(0u128, |acc, b| acc.wrapping_mul(31).wrapping_add(b as u128));
-                        let hash2 = s.bytes().rev().fold(0u128, |acc, b| acc.wrapping_mul(37).wrapping_add(b as u128));
-                        Ok(Value::String(format!("{:032x}{:032x}", hash1, hash2)))
-                    }
+                    Some(Value::String(s)) => Ok(Value::String(crate::crypto::sha256(s.as_bytes()))),
                     _ => Err(self.error("hash_sha256() requires a string")),
                 }
             }
+            "encrypt" | "decrypt" | "hmac" => {
+                // encrypt(plaintext, key) / decrypt(token, key) -> AES-256-GCM, base64 key
+                // hmac(key, data, algorithm="sha256") -> string (hex)
+                crate::crypto::call(name, args).map_err(|e| self.error(e))
+            }
+            _ if crate::formats::handles(name) => {
+                crate::formats::call(name, args).map_err(|e| self.error(e))
+            }
+            _ if name.starts_with("crypto.") => {
+                crate::crypto::call(&name["crypto.".len()..], args).map_err(|e| self.error(e))
+            }
+            _ if name.starts_with("http.") => {
+                crate::http_client::call(&name["http.".len()..], args).map_err(|e| self.error(e))
+            }
+            _ if name.starts_with("sqlite.") => {
+                crate::sqlite::call(&name["sqlite.".len()..], args).map_err(|e| self.error(e))
+            }
             "base64_encode" => {
                 // base64_encode(string) -> string
                 match args.get(0) {
-                    Some(Value::String(s)) => {
-                        const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
-                        let bytes = s.as_bytes();
-                        let mut result = String::new();
-                        
-                        for chunk in bytes.chunks(3) {
-                            let b0 = chunk[0] as usize;
-                            let b1 = chunk.get(1).copied().unwrap_or(0) as usize;
-                            let b2 = chunk.get(2).copied().unwrap_or(0) as usize;
-                            
-                            result.push(CHARS[b0 >> 2] as char);
-                            result.push(CHARS[((b0 & 0x03) << 4) | (b1 >> 4)] as char);
-                            
-                            if chunk.len() > 1 {
-                                result.push(CHARS[((b1 & 0x0F) << 2) | (b2 >> 6)] as char);
-                            } else {
-                                result.push('=');
-                            }
-                            
-                            if chunk.len() > 2 {
-                                result.push(CHARS[b2 & 0x3F] as char);
-                            } else {
-                                result.push('=');
-                            }
-                        }
-                        
-                        Ok(Value::String(result))
-                    }
+                    Some(Value::String(s)) => Ok(Value::String(crate::crypto::base64_encode(s.as_bytes()))),
                     _ => Err(self.error("base64_encode() requires a string")),
                 }
             }
             "base64_decode" => {
                 // base64_decode(string) -> string
                 match args.get(0) {
-                    Some(Value::String(s)) => {
-                        fn decode_char(c: char) -> Option<u8> {
-                            match c {
-                                'A'..='Z' => Some(c as u8 - b'A'),
-                                'a'..='z' => Some(c as u8 - b'a' + 26),
-                                '0'..='9' => Some(c as u8 - b'0' + 52),
-                                '+' => Some(62),
-                                '/' => Some(63),
-                                '=' => Some(0),
-                                _ => None,
-                            }
-                        }
-                        
-                        let chars: Vec<u8> = s.chars()
-                            .filter(|c| !c.is_whitespace())
-                            .filter_map(decode_char)
-                            .collect();
-                        
-                        let mut result = Vec::new();
-                        for chunk in chars.chunks(4) {
-                            if chunk.len() < 4 { break; }
-                            
-                            let b0 = (chunk[0] << 2) | (chunk[1] >> 4);
-                            result.push(b0);
-                            
-                            if s.chars().nth(s.len() - 2) != Some('=') {
-                                let b1 = ((chunk[1] & 0x0F) << 4) | (chunk[2] >> 2);
-                                result.push(b1);
-                            }
-                            
-                            if s.chars().last() != Some('=') {
-                                let b2 = ((chunk[2] & 0x03) << 6) | chunk[3];
-                                result.push(b2);
-                            }
-                        }
-                        
-                        Ok(Value::String(String::from_utf8_lossy(&result).to_string()))
-                    }
+                    Some(Value::String(s)) => crate::crypto::base64_decode(s)
+                        .map(|bytes| Value::String(String::from_utf8_lossy(&bytes).to_string()))
+                        .map_err(|e| self.error(e)),
                     _ => Err(self.error("base64_decode() requires a string")),
                 }
             }
@@ -2835,7 +3649,7 @@ const {name_lower}Store = new {name}Store();
                         // For now, just do sequential map (true parallel would need Arc<Mutex>)
                         let mut results = Vec::new();
                         for item in items {
-                            self.scopes.push(HashMap::new());
+                            self.push_frame("parallel_map");
                             if let Some(param) = params.get(0) {
                                 self.set_var(param.name.clone(), item.clone());
                             }
@@ -2850,7 +3664,7 @@ const {name_lower}Store = new {name}Store();
                             
                             let result = self.return_value.take().unwrap_or(Value::None);
                             self.should_return = false;
-                            self.scopes.pop();
+                            self.pop_frame();
                             results.push(result);
                         }
                         Ok(Value::List(results))
@@ -2864,7 +3678,7 @@ const {name_lower}Store = new {name}Store();
                     (Some(Value::Function { params, body, .. }), Some(Value::List(items))) => {
                         let mut results = Vec::new();
                         for item in items {
-                            self.scopes.push(HashMap::new());
+                            self.push_frame("parallel_filter");
                             if let Some(param) = params.get(0) {
                                 self.set_var(param.name.clone(), item.clone());
                             }
@@ -2879,7 +3693,7 @@ const {name_lower}Store = new {name}Store();
                             
                             let result = self.return_value.take().unwrap_or(Value::None);
                             self.should_return = false;
-                            self.scopes.pop();
+                            self.pop_frame();
                             
                             if self.is_truthy(&result) {
                                 results.push(item.clone());
@@ -2963,6 +3777,14 @@ const {name_lower}Store = new {name}Store();
             (_, BinOp::Eq, Value::None) | (Value::None, BinOp::Eq, _) => Ok(Value::Bool(false)),
             (_, BinOp::NotEq, Value::None) | (Value::None, BinOp::NotEq, _) => Ok(Value::Bool(true)),
             
+            // Dates, times and durations
+            (Value::Instance { .. }, _, _) | (_, _, Value::Instance { .. })
+                if !matches!(op, BinOp::In | BinOp::Is | BinOp::And | BinOp::Or)
+                    && (crate::datetime::is_temporal(left) || crate::datetime::is_temporal(right)) =>
+            {
+                crate::datetime::binary_op(left, op, right)
+            }
+            
             // Logical
             (Value::Bool(a), BinOp::And, Value::Bool(b)) => Ok(Value::Bool(*a && *b)),
             (Value::Bool(a), BinOp::Or, Value::Bool(b)) => Ok(Value::Bool(*a || *b)),
@@ -3004,11 +3826,14 @@ const {name_lower}Store = new {name}Store();
             (Value::Int(x), Value::Int(y)) => x.cmp(y),
             (Value::Float(x), Value::Float(y)) => x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal),
             (Value::String(x), Value::String(y)) => x.cmp(y),
+            (Value::Instance { .. }, Value::Instance { .. }) if crate::datetime::is_temporal(a) => {
+                crate::datetime::compare(a, b).unwrap_or(std::cmp::Ordering::Equal)
+            }
             _ => std::cmp::Ordering::Equal,
         }
     }
 
-    fn is_truthy(&self, val: &Value) -> bool {
+    pub fn is_truthy(&self, val: &Value) -> bool {
         match val {
             Value::None => false,
             Value::Bool(b) => *b,
@@ -0,0 +1,199 @@
+//! Typed IPC calls from the WebView (`poly.invoke(fn, args)`)
+//!
+//! A request is `{"fn": "name", "args": ..., "kwargs": {...}}`, optionally
+//! with an `id` to cancel it by and a `priority` (`"low"`, `"normal"` or
+//! `"high"`) for the worker queue. Arguments are
+//! converted straight from JSON into `Value`s and bound to the parameters of
+//! a function defined in Poly; nothing is turned back into source. `args` may
+//! be an array (positional), an object (keyword, or the whole object as the
+//! only argument of a one-parameter function whose name is not a key, as
+//! older apps expect) or a single value.
+//!
+//! The reply is `{"result": ...}` or
+//! `{"error": message, "code": ..., "traceback": [{"function", "line"}]}`.
+
+use serde_json::json;
+
+use crate::ast::Value;
+use crate::interpreter::{CallError, Interpreter};
+use crate::workers::Priority;
+
+/// A parsed `poly.invoke` request
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Request {
+    pub function: String,
+    pub args: serde_json::Value,
+    pub kwargs: serde_json::Map<String, serde_json::Value>,
+    pub id: Option<String>,
+    pub priority: Priority,
+}
+
+impl Request {
+    /// Argument object for a router command: `args` with `kwargs` merged in
+    pub fn command_args(&self) -> Result<serde_json::Value, Error> {
+        if self.kwargs.is_empty() {
+            return Ok(self.args.clone());
+        }
+        let mut args = match &self.args {
+            serde_json::Value::Null => serde_json::Map::new(),
+            serde_json::Value::Object(map) => map.clone(),
+            _ => return Err(Error::new("INVALID_ARGUMENTS", format!("{}() takes named arguments; 'args' must be an object when 'kwargs' is given", self.function))),
+        };
+        for (key, value) in &self.kwargs {
+            if args.insert(key.clone(), value.clone()).is_some() {
+                return Err(Error::new("INVALID_ARGUMENTS", format!("{}() got multiple values for argument '{}'", self.function, key)));
+            }
+        }
+        Ok(serde_json::Value::Object(args))
+    }
+}
+
+/// A failed call, sent to the WebView as the error envelope
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    /// `INVALID_REQUEST`, `NOT_FOUND`, `INVALID_ARGUMENTS`, `PERMISSION_DENIED`,
+    /// `RUNTIME_ERROR`, `CANCELLED` or `BUSY`
+    pub code: &'static str,
+    pub message: String,
+    /// (function, line), outermost call first
+    pub traceback: Vec<(String, Option<usize>)>,
+}
+
+impl Error {
+    pub fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Error { code, message: message.into(), traceback: Vec::new() }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let traceback: Vec<_> = self.traceback.iter()
+            .map(|(function, line)| json!({"function": function, "line": line}))
+            .collect();
+        json!({"error": self.message, "code": self.code, "traceback": traceback})
+    }
+}
+
+impl From<CallError> for Error {
+    fn from(error: CallError) -> Self {
+        match error {
+            CallError::NotFound(message) => Error::new("NOT_FOUND", message),
+            CallError::InvalidArguments(message) => Error::new("INVALID_ARGUMENTS", message),
+            CallError::Runtime { message, traceback } => Error { code: "RUNTIME_ERROR", message, traceback },
+        }
+    }
+}
+
+/// Parse the JSON body of an invoke request
+pub fn parse_request(body: &str) -> Result<Request, Error> {
+    let request: serde_json::Value = serde_json::from_str(body)
+        .map_err(|e| Error::new("INVALID_REQUEST", format!("Invalid JSON: {}", e)))?;
+    let function = request.get("fn").and_then(|f| f.as_str())
+        .ok_or_else(|| Error::new("INVALID_REQUEST", "Missing 'fn' field"))?;
+    let kwargs = match request.get("kwargs") {
+        None | Some(serde_json::Value::Null) => serde_json::Map::new(),
+        Some(serde_json::Value::Object(map)) => map.clone(),
+        Some(_) => return Err(Error::new("INVALID_REQUEST", "'kwargs' must be an object")),
+    };
+    let id = match request.get("id") {
+        None | Some(serde_json::Value::Null) => None,
+        Some(serde_json::Value::String(id)) => Some(id.clone()),
+        Some(_) => return Err(Error::new("INVALID_REQUEST", "'id' must be a string")),
+    };
+    let priority = match request.get("priority") {
+        None | Some(serde_json::Value::Null) => Priority::Normal,
+        Some(priority) => priority.as_str().and_then(Priority::parse)
+            .ok_or_else(|| Error::new("INVALID_REQUEST", "'priority' must be \"low\", \"normal\" or \"high\""))?,
+    };
+    Ok(Request {
+        function: function.to_string(),
+        args: request.get("args").cloned().unwrap_or(serde_json::Value::Null),
+        kwargs,
+        id,
+        priority,
+    })
+}
+
+/// Call the requested function and return its result as JSON
+pub fn invoke(interpreter: &mut Interpreter, request: &Request) -> Result<serde_json::Value, Error> {
+    let params = interpreter.function_params(&request.function)
+        .ok_or_else(|| Error::new("NOT_FOUND", format!("Function '{}' is not defined", request.function)))?;
+
+    let mut args = Vec::new();
+    let mut kwargs: Vec<(String, Value)> = Vec::new();
+    match &request.args {
+        serde_json::Value::Null => {}
+        serde_json::Value::Array(items) => args = items.iter().map(Value::from_serde_json).collect(),
+        serde_json::Value::Object(map) if map.keys().all(|key| params.contains(key)) => {
+            kwargs = map.iter().map(|(key, value)| (key.clone(), Value::from_serde_json(value))).collect();
+        }
+        value @ serde_json::Value::Object(_) if params.len() == 1 => args.push(Value::from_serde_json(value)),
+        serde_json::Value::Object(map) => {
+            let key = map.keys().find(|key| !params.contains(key)).cloned().unwrap_or_default();
+            return Err(Error::new("INVALID_ARGUMENTS", format!("{}() got an unexpected argument '{}'", request.function, key)));
+        }
+        value => args.push(Value::from_serde_json(value)),
+    }
+    kwargs.extend(request.kwargs.iter().map(|(key, value)| (key.clone(), Value::from_serde_json(value))));
+
+    let result = interpreter.invoke(&request.function, args, kwargs)?;
+    Ok(result.to_serde_json())
+}
+
+/// Handle a raw invoke body and produce the JSON reply
+pub fn handle(interpreter: &mut Interpreter, body: &str) -> String {
+    reply(parse_request(body).and_then(|request| invoke(interpreter, &request)))
+}
+
+/// Serialize the outcome of a call
+pub fn reply(result: Result<serde_json::Value, Error>) -> String {
+    match result {
+        Ok(value) => json!({"result": value}).to_string(),
+        Err(error) => error.to_json().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn interpreter(source: &str) -> Interpreter {
+        let mut parser = Parser::new(Lexer::new(source).tokenize()).with_lines();
+        let program = parser.parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.run(&program).unwrap();
+        interpreter
+    }
+
+    #[test]
+    fn test_binds_arguments() {
+        let mut interp = interpreter("fn greet(name, greeting=\"Hello\", punct=\"!\"):\n    return greeting + \", \" + name + punct\n\nfn save(data):\n    return data[\"title\"]\n");
+        let call = |interp: &mut Interpreter, body: &str| -> serde_json::Value {
+            serde_json::from_str(&handle(interp, body)).unwrap()
+        };
+        assert_eq!(call(&mut interp, r#"{"fn":"greet","args":["Ada"]}"#), json!({"result": "Hello, Ada!"}));
+        assert_eq!(call(&mut interp, r#"{"fn":"greet","args":{"name":"Ada","punct":"?"}}"#), json!({"result": "Hello, Ada?"}));
+        assert_eq!(call(&mut interp, r#"{"fn":"greet","args":["Ada"],"kwargs":{"greeting":"Hi"}}"#), json!({"result": "Hi, Ada!"}));
+        assert_eq!(call(&mut interp, r#"{"fn":"save","args":{"title":"x\"); exit(1) #"}}"#), json!({"result": "x\"); exit(1) #"}));
+
+        assert_eq!(call(&mut interp, r#"{"fn":"greet","args":[]}"#)["code"], "INVALID_ARGUMENTS");
+        assert_eq!(call(&mut interp, r#"{"fn":"greet","args":{"nme":"Ada"}}"#)["code"], "INVALID_ARGUMENTS");
+        assert_eq!(
+            call(&mut interp, r#"{"fn":"greet","args":["Ada"],"kwargs":{"name":"Bob"}}"#)["error"],
+            "greet() got multiple values for argument 'name'"
+        );
+        assert_eq!(call(&mut interp, r#"{"fn":"print","args":["hi"]}"#)["code"], "NOT_FOUND");
+        assert_eq!(call(&mut interp, r#"{"fn":"greet(\"a\")","args":[]}"#)["code"], "NOT_FOUND");
+        assert_eq!(call(&mut interp, r#"{"args":[]}"#)["code"], "INVALID_REQUEST");
+    }
+
+    #[test]
+    fn test_runtime_error_traceback() {
+        let mut interp = interpreter("fn inner(x):\n    raise \"bad \" + str(x)\n\nfn outer(x):\n    let y = x + 1\n    return inner(y)\n");
+        let reply: serde_json::Value = serde_json::from_str(&handle(&mut interp, r#"{"fn":"outer","args":[1]}"#)).unwrap();
+        assert_eq!(reply["code"], "RUNTIME_ERROR");
+        assert_eq!(reply["traceback"], json!([{"function": "outer", "line": 6}, {"function": "inner", "line": 2}]));
+        let reply: serde_json::Value = serde_json::from_str(&handle(&mut interp, r#"{"fn":"inner","args":[4]}"#)).unwrap();
+        assert_eq!(reply["traceback"], json!([{"function": "inner", "line": 2}]));
+    }
+}
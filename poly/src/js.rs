@@ -0,0 +1,1561 @@
+//! JavaScript target for `poly build --target js`
+//!
+//! Compiles a `Program` to a readable ES module so UI logic written in Poly
+//! can run in the WebView without a round-trip to the backend. Operations
+//! whose JavaScript meaning differs from Poly's go through a small runtime
+//! ([`RUNTIME`], written as `poly_runtime.js`): truthiness, `//` (truncating
+//! integer division), `+`/`*` on strings and lists, structural `==` and `in`,
+//! negative indexing, and dicts, which become insertion-ordered `Map`s.
+//! Comparisons and the remaining arithmetic compile to plain operators.
+//!
+//! Every top-level name is exported. `import module` is resolved at compile
+//! time to the names the module defines, so `build` compiles a whole source
+//! tree at once. Names that are neither Poly builtins nor defined in Poly
+//! (`document`, `window`, `console`) are left as JavaScript globals.
+//!
+//! Known differences: numbers are JavaScript doubles (integers are exact up
+//! to 2^53 and `type(2.0)` is `"int"`), and lists, dicts and instances are
+//! shared by reference instead of being copied on assignment.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::ast::{BinOp, Expr, FStringPart, Method, Param, Program, Statement, UnaryOp};
+use crate::interpreter::process_escapes;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+/// File name of the runtime, written next to the compiled modules
+pub const RUNTIME_FILE: &str = "poly_runtime.js";
+
+/// Builtins the runtime provides, called as `$.name`
+const BUILTINS: &[&str] = &[
+    "print", "len", "range", "str", "int", "float", "bool", "type", "abs", "min", "max", "sum",
+    "sorted", "reversed", "list", "isinstance", "hasattr", "round", "any", "all", "enumerate", "zip",
+    "chr", "ord", "hex", "bin", "oct", "input", "math", "math_sqrt", "math_sin", "math_cos",
+    "math_tan", "math_floor", "math_ceil", "math_log", "random", "randint", "choice", "shuffle",
+    "time", "timestamp", "uuid", "json_dumps", "json_loads", "json_parse", "json_stringify",
+    "html_escape", "regex_match", "regex_find", "regex_replace", "parallel_map", "parallel_filter",
+];
+
+/// Interpreter builtins that need the backend (files, network, processes)
+const BACKEND_ONLY: &[&str] = &[
+    "read_file", "write_file", "file_exists", "list_dir", "mkdir", "remove_file", "path_join",
+    "path_exists", "path_basename", "path_dirname", "path_ext", "http_get", "http_post",
+    "http_post_json", "http_stream_start", "http_stream_poll", "http_stream_close", "exec", "env",
+    "env_get", "env_set", "encrypt", "sleep", "sleep_ms", "emit", "on", "off",
+    "shared_get", "shared_set", "shared_add", "get", "post", "put", "patch", "delete", "middleware",
+    "response", "ipc_invoke", "ipc_emit", "emitted", "fake_api", "fake_calls",
+];
+
+/// Modules whose functions are runtime builtins, so importing them emits nothing
+const RUNTIME_MODULES: &[&str] = &["math", "random", "time", "json"];
+
+const RESERVED: &[&str] = &[
+    "arguments", "await", "case", "catch", "class", "const", "debugger", "default", "delete", "do",
+    "enum", "eval", "export", "extends", "finally", "function", "implements", "import", "in",
+    "instanceof", "interface", "let", "new", "null", "package", "private", "protected", "public",
+    "static", "super", "switch", "this", "throw", "typeof", "undefined", "var", "void", "with",
+    "yield", "NaN", "Infinity",
+];
+
+/// What another module needs to `import` a compiled module
+#[derive(Debug, Clone, Default)]
+pub struct Exports {
+    /// Import specifier relative to the importing module, e.g. `./utils.js`
+    pub path: String,
+    /// Top-level names in definition order
+    pub names: Vec<String>,
+    /// The subset of `names` that are classes (instantiated with `new`)
+    pub classes: Vec<String>,
+}
+
+/// The names a program defines at the top level
+pub fn exports(program: &Program, path: &str) -> Exports {
+    let mut names = Vec::new();
+    bindings(&program.statements, &mut names);
+    for name in global_declarations(&program.statements) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    let classes = program
+        .statements
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Statement::ClassDef { name, .. } => Some(name.clone()),
+            _ => None,
+        })
+        .collect();
+    Exports { path: path.to_string(), names, classes }
+}
+
+/// Names bound by a block (blocks nested in it included), in order, without
+/// entering function or class bodies
+fn bindings(stmts: &[Statement], names: &mut Vec<String>) {
+    fn add(name: &String, names: &mut Vec<String>) {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+    for stmt in stmts {
+        match stmt {
+            Statement::Let(name, _) | Statement::Assign(name, _) => add(name, names),
+            Statement::FnDef { name, .. } | Statement::ClassDef { name, .. } => add(name, names),
+            Statement::For { var, body, .. } => {
+                add(var, names);
+                bindings(body, names);
+            }
+            Statement::With { alias, body, .. } => {
+                if let Some(alias) = alias {
+                    add(alias, names);
+                }
+                bindings(body, names);
+            }
+            Statement::If { then_body, elif_branches, else_body, .. } => {
+                bindings(then_body, names);
+                for (_, body) in elif_branches {
+                    bindings(body, names);
+                }
+                if let Some(body) = else_body {
+                    bindings(body, names);
+                }
+            }
+            Statement::While { body, .. } => bindings(body, names),
+            Statement::Try { try_body, except_body, .. } => {
+                bindings(try_body, names);
+                bindings(except_body, names);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Names declared `global` anywhere in a program's functions
+fn global_declarations(stmts: &[Statement]) -> Vec<String> {
+    let mut names = Vec::new();
+    visit(stmts, &mut |stmt| {
+        if let Statement::Global(declared) = stmt {
+            names.extend(declared.iter().cloned());
+        }
+    });
+    names
+}
+
+fn visit(stmts: &[Statement], f: &mut dyn FnMut(&Statement)) {
+    for stmt in stmts {
+        f(stmt);
+        match stmt {
+            Statement::If { then_body, elif_branches, else_body, .. } => {
+                visit(then_body, f);
+                for (_, body) in elif_branches {
+                    visit(body, f);
+                }
+                if let Some(body) = else_body {
+                    visit(body, f);
+                }
+            }
+            Statement::While { body, .. } | Statement::For { body, .. } | Statement::FnDef { body, .. } | Statement::With { body, .. } => visit(body, f),
+            Statement::Try { try_body, except_body, .. } => {
+                visit(try_body, f);
+                visit(except_body, f);
+            }
+            Statement::ClassDef { methods, .. } => {
+                for method in methods {
+                    visit(&method.body, f);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn ident(name: &str) -> String {
+    if RESERVED.contains(&name) { format!("{}_", name) } else { name.to_string() }
+}
+
+fn string_literal(s: &str) -> String {
+    serde_json::to_string(&process_escapes(s)).unwrap_or_default()
+}
+
+fn docstring(body: &[Statement]) -> (Option<String>, &[Statement]) {
+    match body.first() {
+        Some(Statement::Expr(Expr::String(s))) => (Some(process_escapes(s)), &body[1..]),
+        _ => (None, body),
+    }
+}
+
+/// Names visible in one function (or the module) body
+#[derive(Default)]
+struct Scope {
+    names: HashSet<String>,
+    /// Declared with `let`/`function`/`class` so far
+    declared: HashSet<String>,
+    /// `global` names, assigned in the module scope instead
+    globals: HashSet<String>,
+}
+
+/// Binding precedence of a compiled expression, to parenthesize operands
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum Prec {
+    Low,
+    Binary,
+    Unary,
+    Atom,
+}
+
+struct Compiler<'a> {
+    out: String,
+    indent: usize,
+    imports: &'a HashMap<String, Exports>,
+    classes: HashSet<String>,
+    scopes: Vec<Scope>,
+    /// Name the current method binds `this` to (usually `self`)
+    this: Option<String>,
+    temps: usize,
+}
+
+/// Compile a program to an ES module. `runtime` is the import specifier of
+/// `poly_runtime.js`; `imports` maps module names to what they export.
+pub fn compile(program: &Program, runtime: &str, imports: &HashMap<String, Exports>) -> Result<String, String> {
+    let mut compiler = Compiler {
+        out: String::new(),
+        indent: 0,
+        imports,
+        classes: HashSet::new(),
+        scopes: Vec::new(),
+        this: None,
+        temps: 0,
+    };
+    compiler.module(program, runtime)?;
+    Ok(compiler.out)
+}
+
+impl Compiler<'_> {
+    fn line(&mut self, text: &str) {
+        if text.is_empty() {
+            self.out.push('\n');
+            return;
+        }
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn comment(&mut self, doc: &str) {
+        let lines: Vec<&str> = doc.trim().lines().map(|l| l.trim()).collect();
+        self.line("/**");
+        for line in lines {
+            self.line(format!(" * {}", line.replace("*/", "*\\/")).trim_end());
+        }
+        self.line(" */");
+    }
+
+    fn module(&mut self, program: &Program, runtime: &str) -> Result<(), String> {
+        let mut scope = Scope::default();
+        let mut names = Vec::new();
+        bindings(&program.statements, &mut names);
+        let globals: Vec<String> = global_declarations(&program.statements).into_iter().filter(|n| !names.contains(n)).collect();
+        scope.names.extend(names.iter().chain(&globals).cloned());
+        for stmt in &program.statements {
+            if let Statement::ClassDef { name, .. } = stmt {
+                self.classes.insert(name.clone());
+            }
+        }
+
+        self.line("// Generated by `poly build --target js`. Do not edit.");
+        self.line(&format!("import * as $ from \"{}\";", runtime));
+        let mut imported = HashSet::new();
+        visit(&program.statements, &mut |stmt| {
+            if let Statement::Import(module) | Statement::FromImport(module, _) = stmt {
+                imported.insert(module.clone());
+            }
+        });
+        let mut imported: Vec<_> = imported.into_iter().collect();
+        imported.sort();
+        for module in imported {
+            self.import(&program.statements, &module, &mut scope)?;
+        }
+        self.line("");
+
+        let mut hoisted = self.hoisted(&program.statements, &scope);
+        // Names only assigned through `global` in functions
+        for name in globals {
+            if !hoisted.contains(&name) {
+                hoisted.push(name);
+            }
+        }
+        if !hoisted.is_empty() {
+            self.line(&format!("export let {};", hoisted.iter().map(|n| ident(n)).collect::<Vec<_>>().join(", ")));
+            self.line("");
+        }
+        scope.declared.extend(hoisted);
+        self.scopes.push(scope);
+        let result = self.top_level(&program.statements);
+        self.scopes.pop();
+        result
+    }
+
+    /// Emit the `import` line for a module (all `import` / `from` statements of it)
+    fn import(&mut self, stmts: &[Statement], module: &str, scope: &mut Scope) -> Result<(), String> {
+        if RUNTIME_MODULES.contains(&module) {
+            return Ok(());
+        }
+        let imports = self.imports;
+        let exports = imports
+            .get(module)
+            .ok_or_else(|| format!("Cannot find module '{}' (backend modules such as datetime or sqlite are not available in the js target)", module))?;
+        let mut all = false;
+        let mut wanted: Vec<String> = Vec::new();
+        visit(stmts, &mut |stmt| match stmt {
+            Statement::Import(m) if m == module => all = true,
+            Statement::FromImport(m, names) if m == module => wanted.extend(names.iter().cloned()),
+            _ => {}
+        });
+        if all {
+            wanted = exports.names.clone();
+        }
+        for name in &wanted {
+            if !exports.names.contains(name) {
+                return Err(format!("Module '{}' has no name '{}'", module, name));
+            }
+        }
+        // Definitions in this module win over imported ones, as in the interpreter
+        let mut local = Vec::new();
+        bindings(stmts, &mut local);
+        let mut names: Vec<String> = Vec::new();
+        for name in wanted {
+            if !local.contains(&name) && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        for name in &names {
+            if exports.classes.contains(name) {
+                self.classes.insert(name.clone());
+            }
+            scope.names.insert(name.clone());
+            scope.declared.insert(name.clone());
+        }
+        if !names.is_empty() {
+            let list = names.iter().map(|n| ident(n)).collect::<Vec<_>>().join(", ");
+            self.line(&format!("import {{ {} }} from \"{}\";", list, exports.path));
+        }
+        Ok(())
+    }
+
+    /// Names first bound somewhere other than a direct `let`/`fn`/`class`
+    /// statement of the body, declared up front. This includes functions and
+    /// classes defined inside blocks, which JavaScript would scope to the block.
+    fn hoisted(&self, body: &[Statement], scope: &Scope) -> Vec<String> {
+        let mut seen: HashSet<String> = scope.declared.clone();
+        seen.extend(scope.globals.iter().cloned());
+        let mut hoisted = Vec::new();
+        for stmt in body {
+            match stmt {
+                Statement::Let(name, _) | Statement::Assign(name, _) | Statement::FnDef { name, .. } | Statement::ClassDef { name, .. } => {
+                    seen.insert(name.clone());
+                }
+                _ => {
+                    let mut names = Vec::new();
+                    bindings(std::slice::from_ref(stmt), &mut names);
+                    for name in names {
+                        if seen.insert(name.clone()) {
+                            hoisted.push(name);
+                        }
+                    }
+                }
+            }
+        }
+        hoisted
+    }
+
+    fn at_module_level(&self) -> bool {
+        self.scopes.len() == 1
+    }
+
+    /// Module statements, with functions and classes set off by blank lines
+    fn top_level(&mut self, stmts: &[Statement]) -> Result<(), String> {
+        let mut after_definition = false;
+        for stmt in stmts {
+            let definition = matches!(stmt, Statement::FnDef { .. } | Statement::ClassDef { .. });
+            if (definition || after_definition) && !self.out.ends_with("\n\n") {
+                self.line("");
+            }
+            self.statement(stmt, true)?;
+            after_definition = definition;
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, stmts: &[Statement]) -> Result<(), String> {
+        self.indent += 1;
+        for stmt in stmts {
+            self.statement(stmt, false)?;
+        }
+        self.indent -= 1;
+        Ok(())
+    }
+
+    fn scope(&mut self) -> &mut Scope {
+        self.scopes.last_mut().expect("compiler scope")
+    }
+
+    /// `name = value`, declaring the name on its first direct binding
+    fn assign(&mut self, name: &str, value: String, direct: bool) {
+        let scope = self.scopes.last().expect("compiler scope");
+        let declare = direct && !scope.declared.contains(name) && !scope.globals.contains(name);
+        if declare {
+            let export = if self.at_module_level() { "export " } else { "" };
+            self.scope().declared.insert(name.to_string());
+            self.line(&format!("{}let {} = {};", export, ident(name), value));
+        } else {
+            self.line(&format!("{} = {};", ident(name), value));
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement, direct: bool) -> Result<(), String> {
+        match stmt {
+            Statement::Let(name, value) | Statement::Assign(name, value) => {
+                let value = self.expr(value)?;
+                self.assign(name, value, direct);
+            }
+            Statement::IndexAssign(target, index, value) => {
+                let code = format!("$.setitem({}, {}, {});", self.expr(target)?, self.expr(index)?, self.expr(value)?);
+                self.line(&code);
+            }
+            Statement::AttrAssign(target, attr, value) => {
+                let code = format!("{}.{} = {};", self.operand(target, Prec::Atom)?, attr, self.expr(value)?);
+                self.line(&code);
+            }
+            Statement::If { condition, then_body, elif_branches, else_body } => {
+                let code = format!("if ({}) {{", self.condition(condition)?);
+                self.line(&code);
+                self.block(then_body)?;
+                for (condition, body) in elif_branches {
+                    let code = format!("}} else if ({}) {{", self.condition(condition)?);
+                    self.line(&code);
+                    self.block(body)?;
+                }
+                if let Some(body) = else_body {
+                    self.line("} else {");
+                    self.block(body)?;
+                }
+                self.line("}");
+            }
+            Statement::While { condition, body } => {
+                let code = format!("while ({}) {{", self.condition(condition)?);
+                self.line(&code);
+                self.block(body)?;
+                self.line("}");
+            }
+            Statement::For { var, iter, body } => {
+                let code = format!("for ({} of $.iter({})) {{", ident(var), self.expr(iter)?);
+                self.line(&code);
+                self.block(body)?;
+                self.line("}");
+            }
+            Statement::FnDef { name, params, body, .. } => self.function(name, params, body, direct)?,
+            Statement::Return(value) => {
+                let code = match value {
+                    Some(value) => format!("return {};", self.expr(value)?),
+                    None => "return null;".to_string(),
+                };
+                self.line(&code);
+            }
+            Statement::ClassDef { name, parent, methods, doc } => self.class(name, parent, methods, doc, direct)?,
+            Statement::Expr(Expr::String(s)) => {
+                // A bare string is a docstring or comment
+                self.comment(&process_escapes(s));
+            }
+            Statement::Expr(expr) => {
+                let code = format!("{};", self.expr(expr)?);
+                self.line(&code);
+            }
+            // Emitted at the top of the module
+            Statement::Import(_) | Statement::FromImport(..) => {}
+            Statement::Pass | Statement::Global(_) | Statement::Line(_) | Statement::Source(_) => {}
+            Statement::Break => self.line("break;"),
+            Statement::Continue => self.line("continue;"),
+            Statement::Assert(condition, message) => {
+                let code = match message {
+                    Some(message) => format!("$.assert({}, {});", self.condition(condition)?, self.expr(message)?),
+                    None => format!("$.assert({});", self.condition(condition)?),
+                };
+                self.line(&code);
+            }
+            Statement::Del(Expr::Identifier(name)) => self.line(&format!("{} = null;", ident(name))),
+            Statement::Del(Expr::Index(target, index)) => {
+                let code = format!("$.delitem({}, {});", self.expr(target)?, self.expr(index)?);
+                self.line(&code);
+            }
+            Statement::Del(_) => return Err("Invalid del target".to_string()),
+            Statement::Try { try_body, except_body, .. } => {
+                self.line("try {");
+                self.block(try_body)?;
+                self.line("} catch {");
+                self.block(except_body)?;
+                self.line("}");
+            }
+            Statement::Raise(value) => {
+                let code = format!("throw $.error({});", self.expr(value)?);
+                self.line(&code);
+            }
+            Statement::With { context, alias, body } => {
+                // `__exit__` gets the error message (or null); a truthy result swallows the error
+                self.temps += 1;
+                let (ctx, failed) = (format!("$ctx{}", self.temps), format!("$failed{}", self.temps));
+                let code = format!("const {} = {};", ctx, self.expr(context)?);
+                self.line(&code);
+                let entered = format!("$.enter({})", ctx);
+                match alias {
+                    Some(alias) => self.assign(alias, entered, false),
+                    None => self.line(&format!("{};", entered)),
+                }
+                self.line(&format!("let {} = false;", failed));
+                self.line("try {");
+                self.block(body)?;
+                self.line("} catch (e) {");
+                self.indent += 1;
+                self.line(&format!("{} = true;", failed));
+                self.line(&format!("if (!$.exit({}, e)) throw e;", ctx));
+                self.indent -= 1;
+                self.line("} finally {");
+                self.indent += 1;
+                self.line(&format!("if (!{}) $.exit({}, null);", failed, ctx));
+                self.indent -= 1;
+                self.line("}");
+            }
+        }
+        Ok(())
+    }
+
+    fn params(&mut self, params: &[Param]) -> Result<String, String> {
+        let mut out = Vec::new();
+        for param in params {
+            match &param.default {
+                Some(default) => out.push(format!("{} = {}", ident(&param.name), self.expr(default)?)),
+                None => out.push(ident(&param.name)),
+            }
+        }
+        Ok(out.join(", "))
+    }
+
+    /// Enter a function body: parameters and local names become a new scope
+    fn enter(&mut self, params: &[Param], body: &[Statement]) {
+        let mut scope = Scope::default();
+        scope.globals.extend(body.iter().flat_map(|stmt| match stmt {
+            Statement::Global(names) => names.clone(),
+            _ => Vec::new(),
+        }));
+        let mut names = Vec::new();
+        bindings(body, &mut names);
+        scope.names.extend(names.into_iter().filter(|n| !scope.globals.contains(n)));
+        for param in params {
+            scope.names.insert(param.name.clone());
+            scope.declared.insert(param.name.clone());
+        }
+        self.scopes.push(scope);
+    }
+
+    /// Emit a function body, with its hoisted `let` first
+    fn body(&mut self, body: &[Statement]) -> Result<(), String> {
+        let hoisted = {
+            let scope = self.scopes.last().expect("compiler scope");
+            self.hoisted(body, scope)
+        };
+        self.indent += 1;
+        if !hoisted.is_empty() {
+            self.line(&format!("let {};", hoisted.iter().map(|n| ident(n)).collect::<Vec<_>>().join(", ")));
+        }
+        self.scope().declared.extend(hoisted);
+        for stmt in body {
+            self.statement(stmt, true)?;
+        }
+        self.indent -= 1;
+        Ok(())
+    }
+
+    fn function(&mut self, name: &str, params: &[Param], body: &[Statement], direct: bool) -> Result<(), String> {
+        let (doc, body) = docstring(body);
+        if let Some(doc) = doc {
+            self.comment(&doc);
+        }
+        let declared = direct && !self.scopes.last().is_some_and(|s| s.declared.contains(name) || s.globals.contains(name));
+        let export = if self.at_module_level() { "export " } else { "" };
+        let params_code = self.params(params)?;
+        let this = self.this.take();
+        self.enter(params, body);
+        let header = format!("function {}({}) {{", ident(name), params_code);
+        if declared {
+            self.line(&format!("{}{}", export, header));
+        } else {
+            self.line(&format!("{} = {}", ident(name), header));
+        }
+        let result = self.body(body);
+        self.scopes.pop();
+        self.this = this;
+        result?;
+        self.line(if declared { "}" } else { "};" });
+        if declared {
+            self.scope().declared.insert(name.to_string());
+        }
+        Ok(())
+    }
+
+    fn class(&mut self, name: &str, parent: &Option<String>, methods: &[Method], doc: &Option<String>, direct: bool) -> Result<(), String> {
+        if let Some(doc) = doc {
+            self.comment(&process_escapes(doc));
+        }
+        self.classes.insert(name.to_string());
+        let declared = direct && !self.scopes.last().is_some_and(|s| s.declared.contains(name));
+        let base = match parent {
+            Some(parent) => self.resolve(parent)?,
+            None => "$.PolyObject".to_string(),
+        };
+        let header = format!("class {} extends {} {{", ident(name), base);
+        if declared {
+            let export = if self.at_module_level() { "export " } else { "" };
+            self.line(&format!("{}{}", export, header));
+        } else {
+            self.line(&format!("{} = {}", ident(name), header));
+        }
+        self.indent += 1;
+        for (i, method) in methods.iter().enumerate() {
+            if i > 0 {
+                self.line("");
+            }
+            let (doc, body) = docstring(&method.body);
+            if let Some(doc) = doc {
+                self.comment(&doc);
+            }
+            let (receiver, params) = match method.params.split_first() {
+                Some((receiver, params)) => (Some(receiver.name.clone()), params),
+                None => (None, &method.params[..]),
+            };
+            let params_code = self.params(params)?;
+            self.line(&format!("{}({}) {{", method.name, params_code));
+            let this = std::mem::replace(&mut self.this, receiver.clone());
+            self.enter(&method.params, body);
+            if let Some(receiver) = &receiver {
+                self.indent += 1;
+                self.line(&format!("const {} = this;", ident(receiver)));
+                self.indent -= 1;
+            }
+            let result = self.body(body);
+            self.scopes.pop();
+            self.this = this;
+            result?;
+            self.line("}");
+        }
+        self.indent -= 1;
+        self.line(if declared { "}" } else { "};" });
+        if declared {
+            self.scope().declared.insert(name.to_string());
+        }
+        Ok(())
+    }
+
+    /// JavaScript for a name read in the current scope
+    fn resolve(&self, name: &str) -> Result<String, String> {
+        if self.scopes.iter().any(|scope| scope.names.contains(name)) {
+            return Ok(ident(name));
+        }
+        if BUILTINS.contains(&name) {
+            return Ok(format!("$.{}", name));
+        }
+        if BACKEND_ONLY.contains(&name) {
+            return Err(format!("{}() needs the backend and is not available in the js target", name));
+        }
+        Ok(ident(name))
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<String, String> {
+        Ok(self.compile_expr(expr)?.0)
+    }
+
+    /// An operand of a native operator, parenthesized unless it binds tighter than `min`
+    fn operand(&mut self, expr: &Expr, min: Prec) -> Result<String, String> {
+        let (code, prec) = self.compile_expr(expr)?;
+        Ok(if prec < min { format!("({})", code) } else { code })
+    }
+
+    /// A condition: Poly truthiness unless the expression is already a bool
+    fn condition(&mut self, expr: &Expr) -> Result<String, String> {
+        let (code, _) = self.compile_expr(expr)?;
+        Ok(if is_bool(expr) { code } else { format!("$.truthy({})", code) })
+    }
+
+    fn args(&mut self, args: &[Expr]) -> Result<Vec<String>, String> {
+        args.iter().map(|arg| self.expr(arg)).collect()
+    }
+
+    fn compile_expr(&mut self, expr: &Expr) -> Result<(String, Prec), String> {
+        let atom = |code: String| -> Result<(String, Prec), String> { Ok((code, Prec::Atom)) };
+        match expr {
+            Expr::None => atom("null".to_string()),
+            Expr::Bool(b) => atom(b.to_string()),
+            Expr::Int(n) => Ok((n.to_string(), if *n < 0 { Prec::Unary } else { Prec::Atom })),
+            Expr::Float(f) if f.is_nan() => atom("NaN".to_string()),
+            Expr::Float(f) if f.is_infinite() => Ok((if *f > 0.0 { "Infinity" } else { "-Infinity" }.to_string(), Prec::Unary)),
+            Expr::Float(f) => Ok((format!("{:?}", f), if *f < 0.0 { Prec::Unary } else { Prec::Atom })),
+            Expr::String(s) => atom(string_literal(s)),
+            Expr::FString(parts) => {
+                let mut code = String::from("`");
+                for part in parts {
+                    match part {
+                        FStringPart::Literal(s) => {
+                            code.push_str(&process_escapes(s).replace('\\', "\\\\").replace('`', "\\`").replace("${", "\\${"));
+                        }
+                        FStringPart::Expr(expr) => code.push_str(&format!("${{$.str({})}}", self.expr(expr)?)),
+                    }
+                }
+                code.push('`');
+                atom(code)
+            }
+            Expr::List(items) => atom(format!("[{}]", self.args(items)?.join(", "))),
+            Expr::Dict(pairs) if pairs.is_empty() => atom("$.dict()".to_string()),
+            Expr::Dict(pairs) => {
+                let mut entries = Vec::new();
+                for (key, value) in pairs {
+                    entries.push(format!("[{}, {}]", self.expr(key)?, self.expr(value)?));
+                }
+                atom(format!("$.dict([{}])", entries.join(", ")))
+            }
+            Expr::ListComp { expr, var, iter, condition } => {
+                let iter = self.expr(iter)?;
+                self.scopes.push(Scope { names: HashSet::from([var.clone()]), ..Default::default() });
+                let result = (|| -> Result<String, String> {
+                    let mut code = format!("$.iter({})", iter);
+                    if let Some(condition) = condition {
+                        code.push_str(&format!(".filter(({}) => {})", ident(var), self.condition(condition)?));
+                    }
+                    code.push_str(&format!(".map(({}) => {})", ident(var), self.expr(expr)?));
+                    Ok(code)
+                })();
+                self.scopes.pop();
+                atom(result?)
+            }
+            Expr::Identifier(name) => atom(self.resolve(name)?),
+            Expr::Index(target, index) => atom(format!("$.index({}, {})", self.expr(target)?, self.expr(index)?)),
+            Expr::Slice(target, start, end) => {
+                let target = self.expr(target)?;
+                let start = match start {
+                    Some(start) => self.expr(start)?,
+                    None => "null".to_string(),
+                };
+                let end = match end {
+                    Some(end) => self.expr(end)?,
+                    None => "null".to_string(),
+                };
+                atom(format!("$.slice({}, {}, {})", target, start, end))
+            }
+            Expr::Attribute(target, attr) => {
+                if matches!(target.as_ref(), Expr::Identifier(name) if Some(name) == self.this.as_ref()) {
+                    return atom(format!("{}.{}", self.expr(target)?, attr));
+                }
+                atom(format!("$.attr({}, {})", self.expr(target)?, string_literal(attr)))
+            }
+            Expr::BinaryOp(left, op, right) => self.binary(left, op, right),
+            Expr::UnaryOp(op, operand) => match op {
+                UnaryOp::Neg => Ok((format!("-{}", self.operand(operand, Prec::Unary)?), Prec::Unary)),
+                UnaryOp::Not if is_bool(operand) => Ok((format!("!{}", self.operand(operand, Prec::Unary)?), Prec::Unary)),
+                UnaryOp::Not => Ok((format!("!$.truthy({})", self.expr(operand)?), Prec::Unary)),
+                UnaryOp::BitNot => atom(format!("$.bitnot({})", self.expr(operand)?)),
+            },
+            Expr::Ternary(condition, then, otherwise) => {
+                let code = format!("{} ? {} : {}", self.condition(condition)?, self.operand(then, Prec::Binary)?, self.operand(otherwise, Prec::Binary)?);
+                Ok((code, Prec::Low))
+            }
+            Expr::Call(callee, args) => {
+                let args = self.args(args)?;
+                atom(self.call(callee, args)?)
+            }
+            Expr::CallWithKwargs(callee, args, kwargs) => {
+                // As in the interpreter, keyword arguments arrive as one trailing dict
+                let mut args = self.args(args)?;
+                let mut entries = Vec::new();
+                for (key, value) in kwargs {
+                    entries.push(format!("[{}, {}]", string_literal(key), self.expr(value)?));
+                }
+                args.push(format!("$.dict([{}])", entries.join(", ")));
+                atom(self.call(callee, args)?)
+            }
+            Expr::Lambda(params, body) => {
+                let params_code = self.params(params)?;
+                self.scopes.push(Scope { names: params.iter().map(|p| p.name.clone()).collect(), ..Default::default() });
+                let body = self.operand(body, Prec::Binary);
+                self.scopes.pop();
+                Ok((format!("({}) => {}", params_code, body?), Prec::Low))
+            }
+            Expr::Widget { widget_type, props, children } => {
+                let mut entries = Vec::new();
+                for (key, value) in props {
+                    entries.push(format!("[{}, {}]", string_literal(key), self.expr(value)?));
+                }
+                let children = self.args(children)?;
+                atom(format!("$.widget({}, $.dict([{}]), [{}])", string_literal(widget_type), entries.join(", "), children.join(", ")))
+            }
+        }
+    }
+
+    fn call(&mut self, callee: &Expr, args: Vec<String>) -> Result<String, String> {
+        let args = args.join(", ");
+        match callee {
+            Expr::Identifier(name) if self.classes.contains(name) && !self.is_local(name) => {
+                Ok(format!("new {}({})", self.resolve(name)?, args))
+            }
+            Expr::Attribute(target, method) => {
+                if matches!(target.as_ref(), Expr::Identifier(name) if Some(name) == self.this.as_ref()) {
+                    return Ok(format!("{}.{}({})", self.expr(target)?, method, args));
+                }
+                let target = self.expr(target)?;
+                let sep = if args.is_empty() { "" } else { ", " };
+                Ok(format!("$.invoke({}, {}{}{})", target, string_literal(method), sep, args))
+            }
+            _ => Ok(format!("{}({})", self.operand(callee, Prec::Atom)?, args)),
+        }
+    }
+
+    /// Bound in a function scope (shadowing a module-level class of the same name)
+    fn is_local(&self, name: &str) -> bool {
+        self.scopes.iter().skip(1).any(|scope| scope.names.contains(name))
+    }
+
+    fn binary(&mut self, left: &Expr, op: &BinOp, right: &Expr) -> Result<(String, Prec), String> {
+        let runtime = |name: &str, this: &mut Self| -> Result<(String, Prec), String> {
+            Ok((format!("$.{}({}, {})", name, this.expr(left)?, this.expr(right)?), Prec::Atom))
+        };
+        let native = |symbol: &str, this: &mut Self| -> Result<(String, Prec), String> {
+            Ok((format!("{} {} {}", this.operand(left, Prec::Unary)?, symbol, this.operand(right, Prec::Unary)?), Prec::Binary))
+        };
+        match op {
+            BinOp::Add => runtime("add", self),
+            BinOp::Mul => runtime("mul", self),
+            BinOp::FloorDiv => runtime("floordiv", self),
+            BinOp::Eq | BinOp::Is => runtime("eq", self),
+            BinOp::NotEq => Ok((format!("!$.eq({}, {})", self.expr(left)?, self.expr(right)?), Prec::Unary)),
+            BinOp::In => Ok((format!("$.contains({}, {})", self.expr(right)?, self.expr(left)?), Prec::Atom)),
+            BinOp::BitAnd => runtime("bitand", self),
+            BinOp::BitOr => runtime("bitor", self),
+            BinOp::BitXor => runtime("bitxor", self),
+            BinOp::LShift => runtime("lshift", self),
+            BinOp::RShift => runtime("rshift", self),
+            BinOp::Sub => native("-", self),
+            BinOp::Div => native("/", self),
+            BinOp::Mod => native("%", self),
+            // `-2 ** 2` is a syntax error in JavaScript
+            BinOp::Pow => Ok((format!("{} ** {}", self.operand(left, Prec::Atom)?, self.operand(right, Prec::Unary)?), Prec::Binary)),
+            BinOp::Lt => native("<", self),
+            BinOp::Gt => native(">", self),
+            BinOp::LtEq => native("<=", self),
+            BinOp::GtEq => native(">=", self),
+            BinOp::And | BinOp::Or => {
+                let symbol = if *op == BinOp::And { "&&" } else { "||" };
+                let side = |expr: &Expr, this: &mut Self| -> Result<String, String> {
+                    match expr {
+                        // `a and b and c` needs no parentheses
+                        Expr::BinaryOp(_, inner, _) if inner == op => this.operand(expr, Prec::Binary),
+                        _ if is_bool(expr) => this.operand(expr, Prec::Unary),
+                        _ => Ok(format!("$.truthy({})", this.expr(expr)?)),
+                    }
+                };
+                let code = format!("{} {} {}", side(left, self)?, symbol, side(right, self)?);
+                Ok((code, Prec::Binary))
+            }
+        }
+    }
+}
+
+/// Whether an expression always produces a JavaScript boolean
+fn is_bool(expr: &Expr) -> bool {
+    match expr {
+        Expr::Bool(_) => true,
+        Expr::UnaryOp(UnaryOp::Not, _) => true,
+        Expr::BinaryOp(_, op, _) => matches!(
+            op,
+            BinOp::Eq | BinOp::NotEq | BinOp::Lt | BinOp::Gt | BinOp::LtEq | BinOp::GtEq | BinOp::In | BinOp::Is | BinOp::And | BinOp::Or
+        ),
+        _ => false,
+    }
+}
+
+// ============================================================================
+// Building a source tree
+// ============================================================================
+
+/// Compile every non-test `.poly` file under `src` (or `src` itself if it is
+/// a file) into `out`, mirroring the directory layout, and write the runtime
+/// next to them. `import name` resolves to `name.poly` beside the importing
+/// file, then at the top of `src`. Returns the files written.
+pub fn build(src: &Path, out: &Path) -> Result<Vec<PathBuf>, String> {
+    let (root, files) = if src.is_file() {
+        (src.parent().unwrap_or(Path::new(".")).to_path_buf(), vec![src.to_path_buf()])
+    } else {
+        let mut files = Vec::new();
+        walk(src, &mut files);
+        files.sort();
+        (src.to_path_buf(), files)
+    };
+
+    // Module path relative to the root, without extension, `/`-separated
+    let mut modules: Vec<(String, Program)> = Vec::new();
+    for file in &files {
+        let source = std::fs::read_to_string(file).map_err(|e| format!("Cannot read {}: {}", file.display(), e))?;
+        let program = Parser::new(Lexer::new(&source).tokenize()).parse().map_err(|e| format!("{}: {}", file.display(), e))?;
+        let relative = file.strip_prefix(&root).unwrap_or(file).with_extension("");
+        modules.push((relative.to_string_lossy().replace('\\', "/"), program));
+    }
+    let known: HashSet<&str> = modules.iter().map(|(m, _)| m.as_str()).collect();
+
+    std::fs::create_dir_all(out).map_err(|e| format!("Cannot create {}: {}", out.display(), e))?;
+    let mut written = Vec::new();
+    for (module, program) in &modules {
+        let dir = module.rsplit_once('/').map_or("", |(dir, _)| dir);
+        let mut imports = HashMap::new();
+        visit(&program.statements, &mut |stmt| {
+            if let Statement::Import(name) | Statement::FromImport(name, _) = stmt {
+                let beside = if dir.is_empty() { name.clone() } else { format!("{}/{}", dir, name) };
+                let target = [beside, name.clone()].into_iter().find(|m| known.contains(m.as_str()));
+                if let Some(target) = target {
+                    let (_, imported) = modules.iter().find(|(m, _)| *m == target).expect("known module");
+                    imports.insert(name.clone(), exports(imported, &relative_path(dir, &format!("{}.js", target))));
+                }
+            }
+        });
+        let code = compile(program, &relative_path(dir, RUNTIME_FILE), &imports).map_err(|e| format!("{}.poly: {}", module, e))?;
+        let path = out.join(format!("{}.js", module));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Cannot create {}: {}", parent.display(), e))?;
+        }
+        std::fs::write(&path, code).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+        written.push(path);
+    }
+    let runtime = out.join(RUNTIME_FILE);
+    std::fs::write(&runtime, RUNTIME).map_err(|e| format!("Cannot write {}: {}", runtime.display(), e))?;
+    written.push(runtime);
+    Ok(written)
+}
+
+/// Import specifier of `target` (relative to the output root) from a module in `dir`
+fn relative_path(dir: &str, target: &str) -> String {
+    if dir.is_empty() {
+        return format!("./{}", target);
+    }
+    format!("{}{}", "../".repeat(dir.split('/').count()), target)
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if path.is_dir() {
+            if !name.starts_with('.') && !crate::testing::SKIP_DIRS.contains(&name.as_str()) {
+                walk(&path, files);
+            }
+        } else if name.ends_with(".poly") && !name.starts_with("test_") {
+            files.push(path);
+        }
+    }
+}
+
+// ============================================================================
+// Runtime
+// ============================================================================
+
+/// `poly_runtime.js`: the builtins and operators compiled modules import as `$`
+pub const RUNTIME: &str = r#"// Poly runtime for modules compiled with `poly build --target js`
+
+export class PolyError extends Error {}
+
+/** Base class of compiled Poly classes: `new Point(1, 2)` runs `__init__(1, 2)` */
+export class PolyObject {
+    constructor(...args) {
+        if (typeof this.__init__ === "function") this.__init__(...args);
+    }
+}
+
+const isNone = (v) => v === null || v === undefined;
+const isDict = (v) => v instanceof Map;
+const isObject = (v) => v !== null && typeof v === "object" && !Array.isArray(v) && !(v instanceof Map);
+const isClass = (v) => typeof v === "function" && v.prototype instanceof PolyObject;
+
+export function error(value) {
+    return value instanceof Error ? value : new PolyError(str(value));
+}
+
+function fail(message) {
+    throw new PolyError(message);
+}
+
+// ---------------------------------------------------------------------------
+// Values
+// ---------------------------------------------------------------------------
+
+export function truthy(v) {
+    if (isNone(v)) return false;
+    if (typeof v === "boolean") return v;
+    if (typeof v === "number") return v !== 0;
+    if (typeof v === "string" || Array.isArray(v)) return v.length > 0;
+    if (isDict(v)) return v.size > 0;
+    return true;
+}
+
+export function dict(entries = []) {
+    return new Map(entries);
+}
+
+export function widget(type, props, children) {
+    return { widget_type: type, props, children };
+}
+
+export function str(v) {
+    if (isNone(v)) return "none";
+    if (typeof v === "string") return v;
+    if (typeof v === "number") {
+        if (v === Infinity) return "inf";
+        if (v === -Infinity) return "-inf";
+        return String(v);
+    }
+    if (typeof v === "boolean") return v ? "true" : "false";
+    if (Array.isArray(v)) return "[" + v.map(str).join(", ") + "]";
+    if (isDict(v)) return "{" + [...v].map(([k, x]) => str(k) + ": " + str(x)).join(", ") + "}";
+    if (isClass(v)) return "<class " + v.name + ">";
+    if (typeof v === "function") return "<fn " + (v.name || "lambda") + ">";
+    if (v instanceof PolyObject) return "<" + v.constructor.name + " instance>";
+    if (isObject(v)) return "{" + Object.entries(v).map(([k, x]) => k + ": " + str(x)).join(", ") + "}";
+    return String(v);
+}
+
+export function type(v) {
+    if (isNone(v)) return "NoneType";
+    if (typeof v === "boolean") return "bool";
+    if (typeof v === "number") return Number.isInteger(v) ? "int" : "float";
+    if (typeof v === "string") return "str";
+    if (Array.isArray(v)) return "list";
+    if (isDict(v) || isObject(v) && !(v instanceof PolyObject)) return "dict";
+    if (isClass(v)) return v.name;
+    if (typeof v === "function") return "function";
+    return v.constructor.name;
+}
+
+export function eq(a, b) {
+    if (isNone(a) || isNone(b)) return isNone(a) && isNone(b);
+    if (Array.isArray(a) && Array.isArray(b)) return a.length === b.length && a.every((x, i) => eq(x, b[i]));
+    if (isDict(a) && isDict(b)) {
+        return a.size === b.size && [...a].every(([k, v]) => b.has(k) && eq(v, b.get(k)));
+    }
+    return a === b;
+}
+
+function compare(a, b) {
+    if (typeof a === "number" && typeof b === "number") return a - b;
+    if (typeof a === "string" && typeof b === "string") return a < b ? -1 : a > b ? 1 : 0;
+    return 0;
+}
+
+// ---------------------------------------------------------------------------
+// Operators
+// ---------------------------------------------------------------------------
+
+function invalid(a, op, b) {
+    fail(`Invalid operation: ${type(a)} ${op} ${type(b)}`);
+}
+
+export function add(a, b) {
+    if (typeof a === "number" && typeof b === "number") return a + b;
+    if (typeof a === "string" && typeof b === "string") return a + b;
+    if (Array.isArray(a) && Array.isArray(b)) return [...a, ...b];
+    invalid(a, "+", b);
+}
+
+export function mul(a, b) {
+    if (typeof a === "number" && typeof b === "number") return a * b;
+    if (typeof a === "string" && Number.isInteger(b)) return a.repeat(Math.max(b, 0));
+    if (Number.isInteger(a) && typeof b === "string") return b.repeat(Math.max(a, 0));
+    if (Array.isArray(a) && Number.isInteger(b)) return Array.from({ length: Math.max(b, 0) }, () => a).flat();
+    invalid(a, "*", b);
+}
+
+/** `//` truncates toward zero, like the interpreter's integer division */
+export function floordiv(a, b) {
+    if (typeof a !== "number" || typeof b !== "number") invalid(a, "//", b);
+    if (b === 0) fail("Division by zero");
+    return Number.isInteger(a) && Number.isInteger(b) ? Math.trunc(a / b) : Math.floor(a / b);
+}
+
+// Bitwise operators work on 64-bit integers, not JavaScript's 32-bit ones
+const int64 = (v) => BigInt.asIntN(64, BigInt(v));
+export const bitand = (a, b) => Number(int64(a) & int64(b));
+export const bitor = (a, b) => Number(int64(a) | int64(b));
+export const bitxor = (a, b) => Number(int64(a) ^ int64(b));
+export const lshift = (a, b) => Number(BigInt.asIntN(64, int64(a) << BigInt(b)));
+export const rshift = (a, b) => Number(int64(a) >> BigInt(b));
+export const bitnot = (a) => Number(~int64(a));
+
+export function contains(container, item) {
+    if (Array.isArray(container)) return container.some((x) => eq(x, item));
+    if (typeof container === "string") return typeof item === "string" && container.includes(item);
+    if (isDict(container)) return container.has(item);
+    if (isObject(container)) return item in container;
+    fail(`Cannot use 'in' with ${type(container)}`);
+}
+
+// ---------------------------------------------------------------------------
+// Indexing and attributes
+// ---------------------------------------------------------------------------
+
+function position(items, i) {
+    if (!Number.isInteger(i)) fail("Index must be an integer");
+    const at = i < 0 ? items.length + i : i;
+    if (at < 0 || at >= items.length) fail("Index out of range");
+    return at;
+}
+
+export function index(target, i) {
+    if (Array.isArray(target)) return target[position(target, i)];
+    if (typeof target === "string") {
+        const chars = [...target];
+        return chars[position(chars, i)];
+    }
+    if (isDict(target)) {
+        if (!target.has(i)) fail(`Key not found: ${str(i)}`);
+        return target.get(i);
+    }
+    if (isObject(target) && i in target) return target[i];
+    fail(`Cannot index ${type(target)}`);
+}
+
+export function slice(target, start, end) {
+    const items = typeof target === "string" ? [...target] : target;
+    if (!Array.isArray(items)) fail(`Cannot slice ${type(target)}`);
+    const clamp = (i, fallback) => {
+        if (isNone(i)) return fallback;
+        return Math.min(Math.max(i < 0 ? items.length + i : i, 0), items.length);
+    };
+    const result = items.slice(clamp(start, 0), clamp(end, items.length));
+    return typeof target === "string" ? result.join("") : result;
+}
+
+export function setitem(target, i, value) {
+    if (Array.isArray(target)) target[position(target, i)] = value;
+    else if (isDict(target)) target.set(i, value);
+    else if (isObject(target)) target[i] = value;
+    else fail(`Cannot assign to an index of ${type(target)}`);
+}
+
+export function delitem(target, i) {
+    if (Array.isArray(target)) target.splice(position(target, i), 1);
+    else if (isDict(target)) target.delete(i);
+    else if (isObject(target)) delete target[i];
+}
+
+export function attr(target, name) {
+    if ((typeof target === "string" || Array.isArray(target)) && name === "length") return len(target);
+    if (isDict(target)) {
+        if (target.has(name)) return target.get(name);
+        fail(`No attribute '${name}' on dict`);
+    }
+    if (isNone(target) || !(name in Object(target))) fail(`No attribute '${name}' on ${type(target)}`);
+    const value = target[name];
+    return typeof value === "function" && !isClass(value) ? value.bind(target) : value;
+}
+
+/** `target.name(...args)`, with Poly's string, list and dict methods */
+export function invoke(target, name, ...args) {
+    let methods = null;
+    if (typeof target === "string") methods = STR_METHODS;
+    else if (Array.isArray(target)) methods = LIST_METHODS;
+    else if (isDict(target)) {
+        // Module-style dicts hold their functions as values
+        if (typeof target.get(name) === "function") return target.get(name)(...args);
+        methods = DICT_METHODS;
+    }
+    if (methods) {
+        if (!Object.hasOwn(methods, name)) fail(`No method '${name}' on ${type(target)}`);
+        return methods[name](target, ...args);
+    }
+    if (isNone(target) || typeof target[name] !== "function") fail(`No method '${name}' on ${type(target)}`);
+    return target[name](...args);
+}
+
+export function enter(manager) {
+    return invoke(manager, "__enter__");
+}
+
+export function exit(manager, err) {
+    const message = isNone(err) ? null : err instanceof Error ? err.message : str(err);
+    return truthy(invoke(manager, "__exit__", message));
+}
+
+export function assert(condition, message) {
+    if (!condition) fail(isNone(message) ? "Assertion failed" : str(message));
+}
+
+/** Values to iterate with `for`: lists, string characters, dict keys */
+export function iter(v) {
+    if (Array.isArray(v)) return v;
+    if (typeof v === "string") return [...v];
+    if (isDict(v)) return [...v.keys()];
+    if (isObject(v) && typeof v[Symbol.iterator] !== "function") return Object.keys(v);
+    if (!isNone(v) && typeof v[Symbol.iterator] === "function") return Array.from(v);
+    fail(`Cannot iterate over ${type(v)}`);
+}
+
+const whitespace = (s) => s.trim().split(/\s+/).filter((x) => x !== "");
+
+const STR_METHODS = {
+    upper: (s) => s.toUpperCase(),
+    lower: (s) => s.toLowerCase(),
+    strip: (s) => s.trim(),
+    trim: (s) => s.trim(),
+    lstrip: (s) => s.trimStart(),
+    rstrip: (s) => s.trimEnd(),
+    split: (s, sep) => isNone(sep) ? whitespace(s) : s.split(sep),
+    join: (s, items) => iter(items).map(str).join(s),
+    replace: (s, from, to) => s.split(from).join(to),
+    startswith: (s, prefix) => s.startsWith(prefix),
+    endswith: (s, suffix) => s.endsWith(suffix),
+    find: (s, sub) => s.indexOf(sub),
+    rfind: (s, sub) => s.lastIndexOf(sub),
+    count: (s, sub) => sub === "" ? 0 : s.split(sub).length - 1,
+    isdigit: (s) => /^[0-9]+$/.test(s),
+    isalpha: (s) => /^\p{L}+$/u.test(s),
+    isalnum: (s) => /^[\p{L}\p{N}]+$/u.test(s),
+    isspace: (s) => /^\s+$/.test(s),
+    isupper: (s) => s.length > 0 && s === s.toUpperCase(),
+    islower: (s) => s.length > 0 && s === s.toLowerCase(),
+    capitalize: (s) => s.charAt(0).toUpperCase() + s.slice(1).toLowerCase(),
+    title: (s) => s.replace(/\p{L}+/gu, (w) => w.charAt(0).toUpperCase() + w.slice(1).toLowerCase()),
+    center: (s, width, fill = " ") => {
+        const total = Math.max(width - s.length, 0);
+        const left = Math.floor(total / 2);
+        return fill.repeat(left) + s + fill.repeat(total - left);
+    },
+    zfill: (s, width) => s.padStart(width, "0"),
+};
+
+// Mutating methods return the list, as in the interpreter
+const LIST_METHODS = {
+    append: (l, v) => (l.push(v), l),
+    push: (l, v) => (l.push(v), l),
+    pop: (l, i) => {
+        if (l.length === 0) fail("pop from empty list");
+        return isNone(i) ? l.pop() : l.splice(position(l, i), 1)[0];
+    },
+    insert: (l, i, v) => (l.splice(i < 0 ? Math.max(l.length + i, 0) : i, 0, v), l),
+    remove: (l, v) => {
+        const at = l.findIndex((x) => eq(x, v));
+        if (at < 0) fail("Value not in list");
+        l.splice(at, 1);
+        return l;
+    },
+    clear: (l) => (l.length = 0, l),
+    copy: (l) => [...l],
+    extend: (l, items) => (l.push(...iter(items)), l),
+    index: (l, v) => {
+        const at = l.findIndex((x) => eq(x, v));
+        if (at < 0) fail("Value not in list");
+        return at;
+    },
+    count: (l, v) => l.filter((x) => eq(x, v)).length,
+    sort: (l) => l.sort(compare),
+    reverse: (l) => l.reverse(),
+    join: (l, sep = "") => l.map(str).join(sep),
+};
+
+const DICT_METHODS = {
+    keys: (d) => [...d.keys()],
+    values: (d) => [...d.values()],
+    items: (d) => [...d].map(([k, v]) => [k, v]),
+    get: (d, k, fallback = null) => d.has(k) ? d.get(k) : fallback,
+    pop: (d, k, fallback) => {
+        if (!d.has(k)) {
+            if (fallback === undefined) fail(`Key not found: ${str(k)}`);
+            return fallback;
+        }
+        const value = d.get(k);
+        d.delete(k);
+        return value;
+    },
+    update: (d, other) => {
+        for (const [k, v] of isDict(other) ? other : Object.entries(other)) d.set(k, v);
+        return d;
+    },
+    clear: (d) => (d.clear(), d),
+    copy: (d) => new Map(d),
+};
+
+// ---------------------------------------------------------------------------
+// Builtins
+// ---------------------------------------------------------------------------
+
+let output = (line) => console.log(line);
+
+/** Send `print` output somewhere other than the console */
+export function setOutput(fn) {
+    output = fn;
+}
+
+export function print(...args) {
+    output(args.map(str).join(" "));
+}
+
+export function len(v) {
+    if (typeof v === "string") return new TextEncoder().encode(v).length;
+    if (Array.isArray(v)) return v.length;
+    if (isDict(v)) return v.size;
+    if (isObject(v)) return Object.keys(v).length;
+    fail("len() requires a list, string, or dict");
+}
+
+export function range(a, b, step = 1) {
+    const [start, end] = isNone(b) ? [0, a] : [a, b];
+    if (step === 0) fail("range() step must not be zero");
+    const result = [];
+    for (let i = start; step > 0 ? i < end : i > end; i += step) result.push(i);
+    return result;
+}
+
+export function int(v) {
+    if (typeof v === "boolean") return v ? 1 : 0;
+    if (typeof v === "number") return Math.trunc(v);
+    if (typeof v === "string" && /^\s*[+-]?\d+\s*$/.test(v)) return parseInt(v, 10);
+    fail(typeof v === "string" ? "Cannot convert to int" : "int() requires a number or string");
+}
+
+export function float(v) {
+    if (typeof v === "number") return v;
+    if (typeof v === "string" && v.trim() !== "" && !isNaN(Number(v))) return Number(v);
+    fail(typeof v === "string" ? "Cannot convert to float" : "float() requires a number or string");
+}
+
+export const bool = (v) => truthy(v);
+export const abs = (v) => Math.abs(v);
+export const list = (v) => isNone(v) ? [] : [...iter(v)];
+export const reversed = (v) => [...iter(v)].reverse();
+export const sorted = (v) => [...iter(v)].sort(compare);
+export const sum = (v) => iter(v).reduce((total, x) => typeof x === "number" ? total + x : total, 0);
+export const any = (v) => iter(v).some(truthy);
+export const all = (v) => iter(v).every(truthy);
+export const enumerate = (v) => iter(v).map((x, i) => [i, x]);
+export const zip = (a, b) => iter(a).slice(0, iter(b).length).map((x, i) => [x, iter(b)[i]]);
+export const isinstance = (v, cls) => isClass(cls) && v instanceof cls;
+export const hasattr = (v, name) => isDict(v) ? v.has(name) : !isNone(v) && name in Object(v);
+export const chr = (n) => String.fromCodePoint(n);
+export const ord = (s) => s.codePointAt(0);
+export const hex = (n) => (n < 0 ? "-0x" : "0x") + Math.abs(n).toString(16);
+export const bin = (n) => (n < 0 ? "-0b" : "0b") + Math.abs(n).toString(2);
+export const oct = (n) => (n < 0 ? "-0o" : "0o") + Math.abs(n).toString(8);
+export const input = (message = "") => globalThis.prompt ? globalThis.prompt(str(message)) ?? "" : "";
+
+function extreme(args, sign, name) {
+    const items = args.length === 1 && Array.isArray(args[0]) ? args[0] : args;
+    if (items.length === 0) fail(`${name}() requires non-empty sequence`);
+    // Ties (and values that do not compare) keep the later item, as in the interpreter
+    return items.reduce((a, b) => compare(a, b) * sign > 0 ? a : b);
+}
+export const min = (...args) => extreme(args, -1, "min");
+export const max = (...args) => extreme(args, 1, "max");
+
+/** Halves round away from zero; without digits the result is an int */
+export function round(v, digits) {
+    const away = (x) => Math.sign(x) * Math.round(Math.abs(x));
+    if (Number.isInteger(v)) return v;
+    if (isNone(digits)) return away(v);
+    const factor = 10 ** digits;
+    return away(v * factor) / factor;
+}
+
+export const math = new Map([["pi", Math.PI], ["e", Math.E], ["tau", 2 * Math.PI], ["inf", Infinity]]);
+export const math_sqrt = Math.sqrt;
+export const math_sin = Math.sin;
+export const math_cos = Math.cos;
+export const math_tan = Math.tan;
+export const math_floor = Math.floor;
+export const math_ceil = Math.ceil;
+export const math_log = Math.log;
+
+export const random = () => Math.random();
+export const randint = (a, b) => a + Math.floor(Math.random() * (b - a + 1));
+export const choice = (items) => {
+    if (!Array.isArray(items) || items.length === 0) fail("choice() requires a non-empty list");
+    return items[randint(0, items.length - 1)];
+};
+export function shuffle(items) {
+    const result = [...items];
+    for (let i = result.length - 1; i > 0; i--) {
+        const j = randint(0, i);
+        [result[i], result[j]] = [result[j], result[i]];
+    }
+    return result;
+}
+
+export const time = () => Date.now() / 1000;
+export const timestamp = () => Math.floor(Date.now() / 1000);
+export const uuid = () => globalThis.crypto.randomUUID();
+
+/** Plain JavaScript data for a Poly value (dicts become objects) */
+export function toJs(v) {
+    if (Array.isArray(v)) return v.map(toJs);
+    if (isDict(v)) return Object.fromEntries([...v].map(([k, x]) => [str(k), toJs(x)]));
+    if (v instanceof PolyObject) return { __class__: v.constructor.name, ...Object.fromEntries(Object.entries(v).map(([k, x]) => [k, toJs(x)])) };
+    return v === undefined ? null : v;
+}
+
+/** A Poly value for plain JavaScript data (objects become dicts) */
+export function fromJs(v) {
+    if (Array.isArray(v)) return v.map(fromJs);
+    if (isObject(v) && Object.getPrototypeOf(v) === Object.prototype) {
+        return new Map(Object.entries(v).map(([k, x]) => [k, fromJs(x)]));
+    }
+    return v === undefined ? null : v;
+}
+
+export const json_dumps = (v) => JSON.stringify(toJs(v));
+export const json_stringify = json_dumps;
+export const json_loads = (s) => fromJs(JSON.parse(s));
+export const json_parse = json_loads;
+
+export const html_escape = (s) => str(s).replace(/&/g, "&amp;").replace(/</g, "&lt;").replace(/>/g, "&gt;").replace(/"/g, "&quot;").replace(/'/g, "&#39;");
+export const regex_match = (pattern, text) => new RegExp(pattern, "u").test(text);
+export const regex_find = (pattern, text) => text.match(new RegExp(pattern, "gu")) ?? [];
+export const regex_replace = (pattern, replacement, text) => text.replace(new RegExp(pattern, "gu"), replacement);
+export const parallel_map = (fn, items) => iter(items).map((x) => fn(x));
+export const parallel_filter = (fn, items) => iter(items).filter((x) => truthy(fn(x)));
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Program {
+        Parser::new(Lexer::new(source).tokenize()).parse().unwrap()
+    }
+
+    /// Operations whose JavaScript meaning differs from Poly's
+    const SEMANTICS: &str = "\
+print(-7 // 2, 7 // 2, -7 % 3, 7 % 3)
+print(not [], not {}, not \"\", not 0, not [0], not {\"a\": 1})
+let d = {\"b\": 1, \"a\": 2}
+d[\"c\"] = 3
+for k in d:
+    print(k)
+print(d)
+print([1, [2, 3]] == [1, [2, 3]], {\"a\": [1]} == {\"a\": [1]}, [1, 2] == [2, 1])
+let items = [10, 20, 30]
+print(items[-1], \"abc\"[-2], items[-3])
+print([1] + [2], \"ab\" * 2, 3 in [1, 2, 3], \"b\" in \"abc\")
+";
+
+    const SEMANTICS_OUTPUT: &[&str] = &[
+        "-3 3 -1 1",
+        "true true true true false false",
+        "b",
+        "a",
+        "c",
+        "{b: 1, a: 2, c: 3}",
+        "true true false",
+        "30 b 10",
+        "[1, 2] abab true true",
+    ];
+
+    #[test]
+    fn test_operators_use_runtime_helpers() {
+        let code = compile(&parse(SEMANTICS), "./poly_runtime.js", &HashMap::new()).unwrap();
+        for expected in [
+            "$.floordiv(-7, 2)",
+            "-7 % 3",
+            "!$.truthy([]), !$.truthy($.dict()), !$.truthy(\"\"), !$.truthy(0)",
+            "export let d = $.dict([[\"b\", 1], [\"a\", 2]]);",
+            "$.setitem(d, \"c\", 3);",
+            "for (k of $.iter(d)) {",
+            "$.eq([1, [2, 3]], [1, [2, 3]])",
+            "$.index(items, -1), $.index(\"abc\", -2)",
+            "$.add([1], [2]), $.mul(\"ab\", 2), $.contains([1, 2, 3], 3)",
+        ] {
+            assert!(code.contains(expected), "missing {:?} in\n{}", expected, code);
+        }
+
+        // The interpreter agrees, apart from list `==`, which it does not support
+        let source: String = SEMANTICS.lines().filter(|l| !l.contains("==")).map(|l| format!("{}\n", l)).collect();
+        let expected: Vec<&str> = SEMANTICS_OUTPUT.iter().copied().filter(|l| *l != "true true false").collect();
+        assert_eq!(crate::run(&source).unwrap(), expected);
+    }
+
+    /// Runs the compiled module and the runtime under Node when it is installed
+    #[test]
+    fn test_runtime_semantics_in_node() {
+        if std::process::Command::new("node").arg("--version").output().is_err() {
+            eprintln!("node not found; skipping");
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("poly_js_runtime_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let code = compile(&parse(SEMANTICS), "./poly_runtime.js", &HashMap::new()).unwrap();
+        std::fs::write(dir.join("main.js"), code).unwrap();
+        std::fs::write(dir.join(RUNTIME_FILE), RUNTIME).unwrap();
+        std::fs::write(dir.join("package.json"), "{\"type\": \"module\"}").unwrap();
+
+        let output = std::process::Command::new("node").arg(dir.join("main.js")).output().unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(stdout.lines().collect::<Vec<_>>(), SEMANTICS_OUTPUT);
+    }
+
+    #[test]
+    fn test_compile_module() {
+        let source = "\
+import shapes
+
+let count = 0
+
+fn bump(step = 1):
+    \"Add step to the counter\"
+    global count
+    count = count + step
+    if count // 2 and not (count in [3, 4]):
+        let label = f\"even {count}\"
+    else:
+        label = \"odd\"
+    return label
+
+class Counter(Point):
+    fn __init__(self, start):
+        self.value = start
+
+    fn next(self):
+        self.value = self.value + 1
+        return self.render()
+
+let c = Point(1, 2)
+for i in range(3):
+    print(document.title, i)
+";
+        let mut imports = HashMap::new();
+        imports.insert("shapes".to_string(), exports(&parse("class Point:\n    pass\nlet origin = 0\n"), "./shapes.js"));
+        let code = compile(&parse(source), "./poly_runtime.js", &imports).unwrap();
+
+        assert!(code.contains("import * as $ from \"./poly_runtime.js\";"));
+        assert!(code.contains("import { Point, origin } from \"./shapes.js\";"));
+        assert!(code.contains("export let i;"));
+        assert!(code.contains("export let count = 0;"));
+        assert!(code.contains(" * Add step to the counter\n"));
+        assert!(code.contains("export function bump(step = 1) {\n    let label;\n    count = $.add(count, step);"));
+        assert!(code.contains("if ($.truthy($.floordiv(count, 2)) && !$.contains([3, 4], count)) {"));
+        assert!(code.contains("label = `even ${$.str(count)}`;"));
+        assert!(code.contains("export class Counter extends Point {"));
+        assert!(code.contains("    __init__(start) {\n        const self = this;\n        self.value = start;"));
+        assert!(code.contains("return self.render();"));
+        assert!(code.contains("export let c = new Point(1, 2);"));
+        assert!(code.contains("for (i of $.iter($.range(3))) {\n    $.print($.attr(document, \"title\"), i);"));
+
+        let err = compile(&parse("read_file(\"x\")\n"), "./poly_runtime.js", &HashMap::new()).unwrap_err();
+        assert!(err.contains("read_file() needs the backend"));
+        let err = compile(&parse("import sqlite\n"), "./poly_runtime.js", &HashMap::new()).unwrap_err();
+        assert!(err.contains("Cannot find module 'sqlite'"));
+    }
+}
@@ -228,85 +228,121 @@ pub struct SpannedToken {
     pub column: usize,
 }
 
+/// A triple-quoted string that preprocessing collapsed onto one line, used to
+/// map positions in the processed text back to the original source
+struct Collapsed {
+    processed_end: usize,
+    original_end: usize,
+    newlines: usize,
+    /// Original offset just after the last newline inside the string
+    last_line_start: usize,
+}
+
 /// Lexer with proper indentation tracking
 pub struct Lexer<'a> {
     source: &'a str,
+    keep_comments: bool,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(source: &'a str) -> Self {
-        Self { source }
+        Self { source, keep_comments: false }
+    }
+
+    /// Also emit `Token::Comment` tokens (for tooling such as the formatter;
+    /// the parser does not accept them)
+    pub fn with_comments(mut self) -> Self {
+        self.keep_comments = true;
+        self
     }
 
+    /// Tokens with spans, lines and columns referring to the original source
     pub fn tokenize(self) -> Vec<SpannedToken> {
         // First, preprocess to handle multi-line strings
-        let processed = self.preprocess_multiline_strings();
+        let (processed, collapsed) = self.preprocess_multiline_strings();
         
         let mut raw_tokens = Vec::new();
         let mut inner = Token::lexer(&processed);
         let mut line = 1;
-        let mut column = 1;
+        let mut line_start = 0;
+        let mut shifts = collapsed.iter().peekable();
+        let mut delta = 0isize;
+        
+        // Account for collapsed strings ending at or before `pos` and map it to the original source
+        let mut to_original = |pos: usize, line: &mut usize, line_start: &mut usize| {
+            while let Some(shift) = shifts.next_if(|s| s.processed_end <= pos) {
+                delta = shift.original_end as isize - shift.processed_end as isize;
+                if shift.newlines > 0 {
+                    *line += shift.newlines;
+                    *line_start = shift.last_line_start;
+                }
+            }
+            (pos as isize + delta) as usize
+        };
         
         // First pass: collect raw tokens
         while let Some(result) = inner.next() {
-            match result {
-                Ok(token) => {
-                    let span = inner.span();
-                    
-                    if matches!(token, Token::Newline) {
-                        raw_tokens.push(SpannedToken {
-                            token,
-                            span: span.clone(),
-                            line,
-                            column,
-                        });
-                        line += 1;
-                        column = 1;
-                    } else if !matches!(token, Token::Comment) {
-                        raw_tokens.push(SpannedToken {
-                            token,
-                            span: span.clone(),
-                            line,
-                            column,
-                        });
-                        column += span.len();
-                    }
-                }
-                Err(_) => {
-                    column += 1;
+            if let Ok(token) = result {
+                let processed_span = inner.span();
+                let start = to_original(processed_span.start, &mut line, &mut line_start);
+                // 1-based column, counted from the start of the current line
+                let column = start - line_start + 1;
+                let token_line = line;
+                let end = to_original(processed_span.end, &mut line, &mut line_start);
+                
+                if matches!(token, Token::Newline) {
+                    raw_tokens.push(SpannedToken {
+                        token,
+                        span: start..end,
+                        line: token_line,
+                        column,
+                    });
+                    line += 1;
+                    line_start = end;
+                } else if self.keep_comments || !matches!(token, Token::Comment) {
+                    raw_tokens.push(SpannedToken {
+                        token,
+                        span: start..end,
+                        line: token_line,
+                        column,
+                    });
                 }
             }
         }
         
         // Second pass: add INDENT/DEDENT tokens
-        self.add_indentation(raw_tokens, &processed)
+        self.add_indentation(raw_tokens)
     }
     
     /// Preprocess source to convert multi-line strings to single-line with escape sequences
-    fn preprocess_multiline_strings(&self) -> String {
+    fn preprocess_multiline_strings(&self) -> (String, Vec<Collapsed>) {
         let mut result = String::new();
-        let mut chars = self.source.chars().peekable();
+        let mut collapsed = Vec::new();
+        let mut chars = self.source.char_indices().peekable();
         
-        while let Some(c) = chars.next() {
+        while let Some((_, c)) = chars.next() {
             // Check for triple quotes
             if c == '"' || c == '\'' {
                 let quote = c;
                 
                 // Check for triple quote
-                if chars.peek() == Some(&quote) {
+                if chars.peek().map(|(_, ch)| *ch) == Some(quote) {
                     chars.next();
-                    if chars.peek() == Some(&quote) {
-                        chars.next();
+                    if chars.peek().map(|(_, ch)| *ch) == Some(quote) {
+                        let (content_start, _) = chars.next().unwrap();
+                        let content_start = content_start + 1;
                         // Found triple quote - collect until closing triple quote
                         let mut content = String::new();
                         let mut found_end = false;
+                        let mut original_end = self.source.len();
                         
-                        while let Some(ch) = chars.next() {
+                        while let Some((_, ch)) = chars.next() {
                             if ch == quote {
-                                if chars.peek() == Some(&quote) {
+                                if chars.peek().map(|(_, ch)| *ch) == Some(quote) {
                                     chars.next();
-                                    if chars.peek() == Some(&quote) {
-                                        chars.next();
+                                    if chars.peek().map(|(_, ch)| *ch) == Some(quote) {
+                                        let (last, _) = chars.next().unwrap();
+                                        original_end = last + 1;
                                         found_end = true;
                                         break;
                                     } else {
@@ -359,6 +395,12 @@ impl<'a> Lexer<'a> {
                             result.push('"');
                             result.push_str(&escaped);
                             result.push('"');
+                            collapsed.push(Collapsed {
+                                processed_end: result.len(),
+                                original_end,
+                                newlines: content.matches('\n').count(),
+                                last_line_start: content.rfind('\n').map_or(0, |i| content_start + i + 1),
+                            });
                         } else {
                             // Unclosed triple quote - just output as-is
                             result.push(quote);
@@ -379,13 +421,13 @@ impl<'a> Lexer<'a> {
             result.push(c);
         }
         
-        result
+        (result, collapsed)
     }
     
-    fn add_indentation(&self, raw_tokens: Vec<SpannedToken>, processed_source: &str) -> Vec<SpannedToken> {
+    fn add_indentation(&self, raw_tokens: Vec<SpannedToken>) -> Vec<SpannedToken> {
         let mut result = Vec::new();
         let mut indent_stack: Vec<usize> = vec![0];
-        let lines: Vec<&str> = processed_source.lines().collect();
+        let lines: Vec<&str> = self.source.lines().collect();
         let mut i = 0;
         let mut current_line = 0;
         
@@ -413,10 +455,8 @@ impl<'a> Lexer<'a> {
                 result.push(token.clone());
                 current_line = token.line;
                 
-                // Look at the next non-empty line's indentation
-                if i + 1 < raw_tokens.len() {
-                    let next_token = &raw_tokens[i + 1];
-                    
+                // Look at the next non-empty line's indentation (comments do not count)
+                if let Some(next_token) = raw_tokens[i + 1..].iter().find(|t| !matches!(t.token, Token::Comment)) {
                     // Skip if next is also newline (empty line)
                     if matches!(next_token.token, Token::Newline) {
                         i += 1;
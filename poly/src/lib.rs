@@ -22,6 +22,33 @@ pub mod multiview;
 pub mod multiview_native;
 pub mod polyview;
 pub mod config;
+pub mod datetime;
+pub mod crypto;
+pub mod formats;
+pub mod http_client;
+pub mod sqlite;
+pub mod testing;
+pub mod reference;
+pub mod lsp;
+pub mod fmt;
+pub mod lint;
+pub mod debugger;
+pub mod repl;
+pub mod profiler;
+pub mod doc;
+pub mod coverage;
+pub mod js;
+pub mod ipc;
+pub mod events;
+pub mod hot_reload;
+pub mod dev_security;
+pub mod bindings;
+pub mod router;
+pub mod blob;
+pub mod workers;
+pub mod routes;
+pub mod headless;
+pub mod system;
 
 // Re-export WebView types
 pub use webview::{WebViewConfig, WebViewBounds, WebViewState, WebViewEvent, WebViewOperation};
@@ -48,6 +75,18 @@ pub use single_instance::{SingleInstanceConfig, try_acquire_instance, release_in
 pub use sovereignty::{SovereigntyConfig, Permission, check_permission, is_enabled as sovereignty_enabled};
 pub use config::{PolyConfig, init_global_config, get_config};
 
+/// Register the built-in `__poly_*` commands (system APIs, uploads and blobs,
+/// the IPC worker pool) with the router. Only the first call registers, so a
+/// command an application replaced afterwards stays replaced.
+pub fn register_builtin_commands() {
+    static REGISTERED: std::sync::Once = std::sync::Once::new();
+    REGISTERED.call_once(|| {
+        system::register_commands();
+        blob::register_commands();
+        workers::register_commands();
+    });
+}
+
 /// Run Poly source code and return the output
 pub fn run(source: &str) -> Result<Vec<String>, String> {
     let lexer = Lexer::new(source);
@@ -100,26 +139,37 @@ pub fn init_interpreter(interpreter: &mut Interpreter, source: &str) -> Result<(
     let lexer = Lexer::new(source);
     let tokens = lexer.tokenize();
     
-    let mut parser = Parser::new(tokens);
+    // Keep line numbers so IPC errors can report a traceback
+    let mut parser = Parser::new(tokens).with_lines();
     let program = parser.parse()?;
     
     interpreter.run(&program)?;
     Ok(())
 }
 
+/// The entry module of a project: main.poly, app.poly or index.poly, at the
+/// top level or in src/
+pub fn find_entry_point(project_path: &std::path::Path) -> Option<std::path::PathBuf> {
+    for candidate in ["main.poly", "src/main.poly", "app.poly", "src/app.poly", "index.poly"] {
+        let path = project_path.join(candidate);
+        if path.exists() { return Some(path); }
+    }
+    None
+}
+
 /// Call a function on an existing interpreter and return JSON result
+///
+/// `args_json` is a JSON array of positional arguments or an object of
+/// keyword arguments, as sent by `poly.invoke`.
 pub fn call_function(interpreter: &mut Interpreter, fn_name: &str, args_json: &str) -> Result<String, String> {
-    // Parse the call expression
-    let call_source = format!("{}({})", fn_name, args_json);
-    
-    let lexer = Lexer::new(&call_source);
-    let tokens = lexer.tokenize();
-    
-    let mut parser = Parser::new(tokens);
-    let program = parser.parse()?;
-    
-    let result = interpreter.run(&program)?;
-    Ok(result.to_json())
+    let args = if args_json.trim().is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::from_str(args_json).map_err(|e| format!("Invalid JSON arguments: {}", e))?
+    };
+    let request = ipc::Request { function: fn_name.to_string(), args, ..Default::default() };
+    let result = ipc::invoke(interpreter, &request).map_err(|e| e.message)?;
+    Ok(result.to_string())
 }
 
 // WASM bindings for web interop
@@ -199,4 +249,122 @@ if x > 5:
 "#).unwrap();
         assert_eq!(output, vec!["big"]);
     }
+
+    #[test]
+    fn test_datetime_module() {
+        let output = run(r#"
+import datetime
+let start = datetime.datetime(2024, 1, 15, 9, 30, tz="Europe/Paris")
+let later = start + datetime.timedelta(days=1, hours=2)
+print(later.isoformat())
+print(later.astimezone("UTC").strftime("%Y-%m-%d %H:%M %Z"))
+print(later - start)
+print(later > start)
+"#).unwrap();
+        assert_eq!(output, vec![
+            "2024-01-16T11:30:00+01:00",
+            "2024-01-16 10:30 UTC",
+            "1 day, 2:00:00",
+            "true",
+        ]);
+    }
+
+    #[test]
+    fn test_dict_keys_are_not_attributes() {
+        let output = run(r#"
+import math
+let d = {"length": 5, "keys": 1}
+print(d.length)
+print(len(d.keys()))
+print(math.pi > 3.0)
+"#).unwrap();
+        assert_eq!(output, vec!["2", "2", "true"]);
+    }
+
+    #[test]
+    fn test_crypto_module() {
+        let output = run(r#"
+import crypto
+print(crypto.sha256("abc"))
+print(hash_sha256("abc") == crypto.sha256("abc"))
+let key = crypto.generate_key()
+let token = crypto.encrypt("api-key", key, algorithm="chacha20-poly1305")
+print(crypto.decrypt(token, key, algorithm="chacha20-poly1305"))
+print(decrypt(encrypt("api-key", key), key))
+print(len(uuid()))
+print(base64_encode("hé!"))
+print(base64_decode(base64_encode("hé!")))
+"#).unwrap();
+        assert_eq!(output, vec![
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            "true",
+            "api-key",
+            "api-key",
+            "36",
+            "aMOpIQ==",
+            "hé!",
+        ]);
+    }
+
+    #[test]
+    fn test_sqlite_with_transactions() {
+        let output = run(r#"
+import sqlite
+with sqlite.connect(":memory:") as db:
+    db.execute("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)")
+    with db.transaction():
+        db.execute("INSERT INTO items (name) VALUES (?)", ["apple"])
+    try:
+        with db.transaction():
+            db.execute("INSERT INTO items (name) VALUES (:name)", {"name": "pear"})
+            raise "abort"
+    except:
+        print("rolled back")
+    for row in db.query("SELECT id, name FROM items"):
+        print(row["id"], row["name"])
+"#).unwrap();
+        assert_eq!(output, vec!["rolled back", "1 apple"]);
+    }
+
+    #[test]
+    fn test_inherited_methods() {
+        let output = run(r#"
+class Resource:
+    fn __init__(self, name):
+        self.name = name
+    fn __enter__(self):
+        print("open " + self.name)
+        return self
+    fn __exit__(self, error):
+        print("close " + self.name)
+
+class File(Resource):
+    fn size(self):
+        return 3
+
+with File("notes") as f:
+    print(f.size())
+"#).unwrap();
+        assert_eq!(output, vec!["open notes", "3", "close notes"]);
+    }
+
+    #[test]
+    fn test_format_modules() {
+        let dir = std::env::temp_dir().join(format!("poly_formats_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("app.toml");
+        std::fs::write(&config, "[app]\nname = \"demo\"\nport = 3000\n").unwrap();
+        let source = format!(r#"
+import toml
+import csv
+let config = toml.read("{}")
+print(config["app"]["port"] + 1)
+let rows = csv.parse("a;b", delimiter=";", header=false)
+print(rows[0][1])
+print(csv.stringify([{{"x": 1, "y": "q,r"}}], dialect="unix"))
+"#, config.display().to_string().replace('\\', "/"));
+        let output = run(&source).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(output, vec!["3001", "b", "\"x\",\"y\"\n\"1\",\"q,r\"\n"]);
+    }
 }
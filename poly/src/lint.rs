@@ -0,0 +1,1058 @@
+//! Static analysis for Poly (`poly lint`)
+//!
+//! Walks the parsed `ast::Program` (parsed with `Statement::Line` markers for
+//! positions) and reports:
+//! - unused local variables and unused imports
+//! - unreachable code after `return`, `raise`, `break` or `continue`
+//! - definitions that shadow a builtin
+//! - undefined names
+//! - builtin calls with the wrong number of arguments
+//! - IPC-exposed functions that need permissions missing from `[sovereignty]`
+//!
+//! Rule levels and extra globals come from the `[lint]` section of poly.toml.
+//! Unused imports and side-effect free unused variables can be fixed
+//! automatically.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::ast::{Expr, FStringPart, Param, Program, Statement};
+use crate::config::{LintConfig, PolyConfig};
+use crate::lexer::{Lexer, SpannedToken, Token};
+use crate::parser::Parser;
+use crate::reference;
+use crate::sovereignty::{Permission, SovereigntyConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// Rule name, default level and description
+pub const RULES: &[(&str, Severity, &str)] = &[
+    ("unused-variable", Severity::Warning, "Local variable is assigned but never read"),
+    ("unused-import", Severity::Warning, "Imported module or name is never used"),
+    ("unreachable-code", Severity::Warning, "Statement after return, raise, break or continue"),
+    ("shadowed-builtin", Severity::Warning, "Definition hides a builtin function"),
+    ("undefined-name", Severity::Error, "Name is never defined"),
+    ("wrong-arity", Severity::Error, "Builtin called with the wrong number of arguments"),
+    ("undeclared-permission", Severity::Error, "IPC-exposed function needs a permission missing from [sovereignty]"),
+];
+
+/// Builtins that need a sovereignty permission
+const PERMISSION_CALLS: &[(&str, &str)] = &[
+    ("read_file", "fs:read"),
+    ("file_exists", "fs:read"),
+    ("list_dir", "fs:read"),
+    ("write_file", "fs:write"),
+    ("mkdir", "fs:write"),
+    ("remove_file", "fs:write"),
+    ("http_get", "http"),
+    ("http_post", "http"),
+    ("http_post_json", "http"),
+    ("http_stream_start", "http"),
+    ("exec", "shell:execute"),
+    ("spawn", "shell:execute"),
+];
+
+/// Native modules whose functions need a sovereignty permission
+const PERMISSION_MODULES: &[(&str, &str)] = &[("http", "http"), ("sqlite", "database")];
+
+/// Replace one source line, or delete it when `replacement` is `None`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fix {
+    pub line: usize,
+    pub replacement: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    pub fix: Option<Fix>,
+}
+
+/// Rule levels, extra globals and declared permissions for a project
+pub struct LintContext {
+    levels: HashMap<&'static str, Option<Severity>>,
+    globals: HashSet<String>,
+    /// Only set when sovereignty is enabled for the project
+    sovereignty: Option<SovereigntyConfig>,
+}
+
+impl LintContext {
+    pub fn new(config: &LintConfig, sovereignty: Option<SovereigntyConfig>) -> Self {
+        let levels = RULES
+            .iter()
+            .map(|(rule, default, _)| {
+                let level = match config.rules.get(*rule).map(String::as_str) {
+                    Some("off") | Some("allow") | Some("none") => None,
+                    Some("warn") | Some("warning") => Some(Severity::Warning),
+                    Some("error") | Some("deny") => Some(Severity::Error),
+                    _ => Some(*default),
+                };
+                (*rule, level)
+            })
+            .collect();
+        Self {
+            levels,
+            globals: config.globals.iter().cloned().collect(),
+            sovereignty: sovereignty.filter(|s| s.enabled),
+        }
+    }
+
+    /// Context from the poly.toml nearest to `path`
+    pub fn for_path(path: &Path) -> Self {
+        let start = if path.is_file() { path.parent().unwrap_or(Path::new(".")) } else { path };
+        let Some(dir) = start.ancestors().find(|dir| dir.join("poly.toml").exists()) else {
+            return Self::new(&LintConfig::default(), None);
+        };
+        let config = PolyConfig::load(dir);
+        let sovereignty = std::fs::read_to_string(dir.join("poly.toml"))
+            .ok()
+            .map(|content| SovereigntyConfig::from_toml(&content, &config.package.name));
+        Self::new(&config.lint, sovereignty)
+    }
+}
+
+impl Default for LintContext {
+    fn default() -> Self {
+        Self::new(&LintConfig::default(), None)
+    }
+}
+
+/// Lint one source file. `path` is used to resolve `import` of sibling files.
+pub fn lint(source: &str, path: Option<&Path>, ctx: &LintContext) -> Result<Vec<Finding>, String> {
+    let tokens = Lexer::new(source).tokenize();
+    let mut parser = Parser::new(tokens.clone()).with_lines();
+    let program = match parser.parse() {
+        Ok(program) => program,
+        Err(e) => {
+            return Err(match parser.error_location() {
+                Some((line, column, _)) => format!("line {}, column {}: {}", line, column, e),
+                None => e,
+            })
+        }
+    };
+
+    let mut checker = Checker::new(source, tokens, path.and_then(Path::parent), ctx);
+    checker.check(&program);
+
+    let mut findings: Vec<Finding> = checker
+        .findings
+        .into_iter()
+        .filter_map(|mut finding| {
+            finding.severity = (*ctx.levels.get(finding.rule)?)?;
+            Some(finding)
+        })
+        .collect();
+    findings.sort_by(|a, b| (a.line, a.column, a.rule).cmp(&(b.line, b.column, b.rule)));
+    Ok(findings)
+}
+
+/// Apply the fixes attached to `findings`, skipping any that would leave the
+/// file unparseable. Returns the new source and the number of fixes applied.
+pub fn apply_fixes(source: &str, findings: &[Finding]) -> (String, usize) {
+    let mut fixes: Vec<&Fix> = findings.iter().filter_map(|f| f.fix.as_ref()).collect();
+    fixes.sort_by_key(|fix| std::cmp::Reverse(fix.line));
+    fixes.dedup_by_key(|fix| fix.line);
+
+    let mut lines: Vec<String> = source.lines().map(String::from).collect();
+    let trailing_newline = source.ends_with('\n');
+    let mut applied = 0;
+    for fix in fixes {
+        let index = fix.line - 1;
+        if index >= lines.len() {
+            continue;
+        }
+        let mut candidate = lines.clone();
+        match &fix.replacement {
+            Some(text) => candidate[index] = text.clone(),
+            None => {
+                candidate.remove(index);
+            }
+        }
+        let text = candidate.join("\n");
+        if Parser::new(Lexer::new(&text).tokenize()).parse().is_ok() {
+            lines = candidate;
+            applied += 1;
+        }
+    }
+
+    let mut out = lines.join("\n");
+    if trailing_newline {
+        out.push('\n');
+    }
+    (out, applied)
+}
+
+// ============================================================================
+// Checker
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LocalKind {
+    Variable,
+    Param,
+    Loop,
+    Function,
+    Class,
+}
+
+struct Local {
+    kind: LocalKind,
+    line: usize,
+    reads: usize,
+    assignments: usize,
+    fix: Option<Fix>,
+}
+
+#[derive(Default)]
+struct Scope {
+    locals: HashMap<String, Local>,
+    /// Names declared with `global`
+    globals: HashSet<String>,
+}
+
+struct ImportInfo {
+    line: usize,
+    module: String,
+    /// Names bound by `from module import ...`; empty for `import module`
+    names: Vec<String>,
+    /// Globals the import defines; `None` if the module could not be resolved
+    provides: Option<Vec<String>>,
+    /// Indentation of the statement and whether it is alone in its block
+    indent: String,
+    only_statement: bool,
+    /// Whether the statement sits alone on its line, so it can be rewritten
+    fixable: bool,
+}
+
+struct Checker<'a> {
+    lines: Vec<&'a str>,
+    tokens: Vec<SpannedToken>,
+    dir: Option<PathBuf>,
+    ctx: &'a LintContext,
+    line: usize,
+    builtins: HashSet<&'static str>,
+    /// Module-level names and the line they are first defined on
+    module: HashMap<String, usize>,
+    /// Names provided by imports (a subset of `module`)
+    imports: Vec<ImportInfo>,
+    /// Function scopes, innermost last; empty at module level
+    scopes: Vec<Scope>,
+    /// Every name read anywhere in the file
+    reads: HashSet<String>,
+    /// An import could not be resolved, so unknown names may come from it
+    open: bool,
+    findings: Vec<Finding>,
+    reported: HashSet<(&'static str, String, usize)>,
+    /// Top-level function being walked, with permissions it uses and functions it calls
+    current_fn: Option<String>,
+    functions: Vec<(String, usize)>,
+    permissions: HashMap<String, HashSet<&'static str>>,
+    calls: HashMap<String, HashSet<String>>,
+}
+
+impl<'a> Checker<'a> {
+    fn new(source: &'a str, tokens: Vec<SpannedToken>, dir: Option<&Path>, ctx: &'a LintContext) -> Self {
+        Self {
+            lines: source.lines().collect(),
+            tokens,
+            dir: dir.map(Path::to_path_buf),
+            ctx,
+            line: 1,
+            builtins: reference::BUILTINS.iter().map(|b| b.name).collect(),
+            module: HashMap::new(),
+            imports: Vec::new(),
+            scopes: Vec::new(),
+            reads: HashSet::new(),
+            open: false,
+            findings: Vec::new(),
+            reported: HashSet::new(),
+            current_fn: None,
+            functions: Vec::new(),
+            permissions: HashMap::new(),
+            calls: HashMap::new(),
+        }
+    }
+
+    fn check(&mut self, program: &Program) {
+        self.collect_module(&program.statements, 1, false);
+        self.block(&program.statements);
+        self.check_imports();
+        self.check_permissions();
+    }
+
+    fn report(&mut self, rule: &'static str, line: usize, column: usize, message: String, fix: Option<Fix>) {
+        let severity = RULES.iter().find(|(r, _, _)| *r == rule).map_or(Severity::Warning, |(_, s, _)| *s);
+        self.findings.push(Finding { rule, severity, line, column, message, fix });
+    }
+
+    /// Position of the first `name` token at or after `line`
+    fn locate(&self, line: usize, name: &str) -> (usize, usize) {
+        self.tokens
+            .iter()
+            .find(|t| t.line >= line && matches!(&t.token, Token::Identifier(n) if n == name))
+            .map_or((line, 1), |t| (t.line, t.column))
+    }
+
+    /// Whether `stmt` is alone on the current line, so the line can be
+    /// deleted or rewritten without touching anything else
+    fn alone_on_line(&self, stmt: &Statement) -> Option<String> {
+        let text = self.lines.get(self.line.checked_sub(1)?)?;
+        let parsed = Parser::new(Lexer::new(text.trim()).tokenize()).parse().ok()?;
+        if parsed.statements.len() != 1 || &parsed.statements[0] != stmt {
+            return None;
+        }
+        Some(text[..text.len() - text.trim_start().len()].to_string())
+    }
+
+    fn remove_fix(&self, stmt: &Statement, only_statement: bool) -> Option<Fix> {
+        let indent = self.alone_on_line(stmt)?;
+        Some(Fix { line: self.line, replacement: only_statement.then(|| format!("{}pass", indent)) })
+    }
+
+    // ------------------------------------------------------------------------
+    // Definitions
+    // ------------------------------------------------------------------------
+
+    /// Record module-level names, including `global` declarations inside functions
+    fn collect_module(&mut self, stmts: &[Statement], mut line: usize, in_function: bool) {
+        for stmt in stmts {
+            let define = |name: &str, line: usize, module: &mut HashMap<String, usize>| {
+                module.entry(name.to_string()).or_insert(line);
+            };
+            match stmt {
+                Statement::Line(n) => line = *n,
+                Statement::Global(names) => {
+                    for name in names {
+                        define(name, line, &mut self.module);
+                    }
+                }
+                Statement::FnDef { name, body, .. } => {
+                    if !in_function {
+                        define(name, line, &mut self.module);
+                    }
+                    self.collect_module(body, line, true);
+                }
+                Statement::ClassDef { name, methods, .. } => {
+                    if !in_function {
+                        define(name, line, &mut self.module);
+                    }
+                    for method in methods {
+                        self.collect_module(&method.body, line, true);
+                    }
+                }
+                _ if in_function => {
+                    for body in nested_blocks(stmt) {
+                        self.collect_module(body, line, true);
+                    }
+                }
+                Statement::Let(name, _) | Statement::Assign(name, _) | Statement::For { var: name, .. } => {
+                    define(name, line, &mut self.module);
+                    for body in nested_blocks(stmt) {
+                        self.collect_module(body, line, false);
+                    }
+                }
+                Statement::With { alias, body, .. } => {
+                    if let Some(alias) = alias {
+                        define(alias, line, &mut self.module);
+                    }
+                    self.collect_module(body, line, false);
+                }
+                Statement::Import(module) | Statement::FromImport(module, _) => {
+                    let provides = self.import_provides(module);
+                    let names = match stmt {
+                        Statement::FromImport(_, names) => names.clone(),
+                        _ => Vec::new(),
+                    };
+                    for name in provides.iter().flatten().chain(&names) {
+                        define(name, line, &mut self.module);
+                    }
+                    self.open |= provides.is_none();
+                }
+                _ => {
+                    for body in nested_blocks(stmt) {
+                        self.collect_module(body, line, false);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Globals defined by importing `module`: a native module's names, or the
+    /// top-level definitions of a sibling `.poly` file
+    fn import_provides(&self, module: &str) -> Option<Vec<String>> {
+        if let Some(names) = reference::import_names(module) {
+            return Some(names);
+        }
+        let path = self.dir.as_deref().unwrap_or(Path::new(".")).join(format!("{}.poly", module));
+        let source = std::fs::read_to_string(path).ok()?;
+        let program = Parser::new(Lexer::new(&source).tokenize()).parse().ok()?;
+        let mut names = Vec::new();
+        for stmt in &program.statements {
+            match stmt {
+                Statement::Let(name, _) | Statement::Assign(name, _) => names.push(name.clone()),
+                Statement::FnDef { name, .. } | Statement::ClassDef { name, .. } => names.push(name.clone()),
+                _ => {}
+            }
+        }
+        Some(names)
+    }
+
+    fn define(&mut self, name: &str, kind: LocalKind, fix: Option<Fix>) {
+        if self.builtins.contains(name) && name != "self" {
+            let (line, column) = self.locate(self.line, name);
+            if self.reported.insert(("shadowed-builtin", name.to_string(), self.scopes.len())) {
+                self.report("shadowed-builtin", line, column, format!("`{}` shadows the builtin of the same name", name), None);
+            }
+        }
+        let line = self.line;
+        let Some(scope) = self.scopes.last_mut() else { return };
+        if scope.globals.contains(name) {
+            return;
+        }
+        let local = scope.locals.entry(name.to_string()).or_insert(Local { kind, line, reads: 0, assignments: 0, fix: None });
+        if local.assignments == 0 {
+            local.line = line;
+            local.fix = fix;
+        }
+        local.assignments += 1;
+    }
+
+    fn read(&mut self, name: &str) {
+        self.reads.insert(name.to_string());
+        for scope in self.scopes.iter_mut().rev() {
+            if scope.globals.contains(name) {
+                break;
+            }
+            if let Some(local) = scope.locals.get_mut(name) {
+                local.reads += 1;
+                return;
+            }
+        }
+        if self.module.contains_key(name) || self.builtins.contains(name) || self.ctx.globals.contains(name) || self.open {
+            return;
+        }
+        let (line, column) = self.locate(self.line, name);
+        if self.reported.insert(("undefined-name", name.to_string(), line)) {
+            self.report("undefined-name", line, column, format!("Undefined name `{}`", name), None);
+        }
+    }
+
+    /// Whether `name` refers to the builtin rather than a user definition
+    fn is_builtin(&self, name: &str) -> bool {
+        self.builtins.contains(name)
+            && !self.module.contains_key(name)
+            && !self.scopes.iter().any(|scope| scope.locals.contains_key(name))
+    }
+
+    // ------------------------------------------------------------------------
+    // Statements
+    // ------------------------------------------------------------------------
+
+    fn block(&mut self, stmts: &[Statement]) {
+        let count = stmts.iter().filter(|s| !matches!(s, Statement::Line(_))).count();
+        let mut after: Option<&str> = None;
+        let mut reported = false;
+        for stmt in stmts {
+            if let Statement::Line(n) = stmt {
+                self.line = *n;
+                continue;
+            }
+            if let (Some(keyword), false) = (after, reported) {
+                let column = self.tokens.iter().find(|t| t.line == self.line).map_or(1, |t| t.column);
+                self.report("unreachable-code", self.line, column, format!("Unreachable code after `{}`", keyword), None);
+                reported = true;
+            }
+            self.statement(stmt, count == 1);
+            after = after.or(match stmt {
+                Statement::Return(_) => Some("return"),
+                Statement::Raise(_) => Some("raise"),
+                Statement::Break => Some("break"),
+                Statement::Continue => Some("continue"),
+                _ => None,
+            });
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement, only_statement: bool) {
+        match stmt {
+            Statement::Let(name, value) | Statement::Assign(name, value) => {
+                self.expr(value);
+                let fix = if is_pure(value) { self.remove_fix(stmt, only_statement) } else { None };
+                self.define(name, LocalKind::Variable, fix);
+            }
+            Statement::IndexAssign(target, index, value) => {
+                self.expr(target);
+                self.expr(index);
+                self.expr(value);
+            }
+            Statement::AttrAssign(target, _, value) => {
+                self.expr(target);
+                self.expr(value);
+            }
+            Statement::If { condition, then_body, elif_branches, else_body } => {
+                self.expr(condition);
+                self.block(then_body);
+                for (condition, body) in elif_branches {
+                    self.expr(condition);
+                    self.block(body);
+                }
+                if let Some(body) = else_body {
+                    self.block(body);
+                }
+            }
+            Statement::While { condition, body } => {
+                self.expr(condition);
+                self.block(body);
+            }
+            Statement::For { var, iter, body } => {
+                self.expr(iter);
+                self.define(var, LocalKind::Loop, None);
+                self.block(body);
+            }
+            Statement::FnDef { name, params, body, .. } => {
+                self.define(name, LocalKind::Function, None);
+                let top_level = self.scopes.is_empty();
+                if top_level {
+                    self.functions.push((name.clone(), self.line));
+                    self.current_fn = Some(name.clone());
+                }
+                self.function(params, body);
+                if top_level {
+                    self.current_fn = None;
+                }
+            }
+            Statement::ClassDef { name, methods, .. } => {
+                self.define(name, LocalKind::Class, None);
+                for method in methods {
+                    self.function(&method.params, &method.body);
+                }
+            }
+            Statement::Return(Some(expr)) | Statement::Expr(expr) | Statement::Raise(expr) | Statement::Del(expr) => {
+                self.expr(expr)
+            }
+            Statement::Assert(condition, message) => {
+                self.expr(condition);
+                if let Some(message) = message {
+                    self.expr(message);
+                }
+            }
+            Statement::Global(names) => {
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.globals.extend(names.iter().cloned());
+                }
+            }
+            Statement::Try { try_body, except_body, .. } => {
+                self.block(try_body);
+                self.block(except_body);
+            }
+            Statement::With { context, alias, body } => {
+                self.expr(context);
+                if let Some(alias) = alias {
+                    self.define(alias, LocalKind::Loop, None);
+                }
+                self.block(body);
+            }
+            Statement::Import(module) | Statement::FromImport(module, _) => {
+                let names = match stmt {
+                    Statement::FromImport(_, names) => names.clone(),
+                    _ => Vec::new(),
+                };
+                for name in &names {
+                    self.define(name, LocalKind::Variable, None);
+                }
+                let fixable = self.alone_on_line(stmt);
+                self.imports.push(ImportInfo {
+                    line: self.line,
+                    module: module.clone(),
+                    names,
+                    provides: self.import_provides(module),
+                    indent: fixable.clone().unwrap_or_default(),
+                    only_statement,
+                    fixable: fixable.is_some(),
+                });
+            }
+            Statement::Return(None) | Statement::Pass | Statement::Break | Statement::Continue | Statement::Line(_) | Statement::Source(_) => {}
+        }
+    }
+
+    fn function(&mut self, params: &[Param], body: &[Statement]) {
+        for param in params {
+            if let Some(default) = &param.default {
+                self.expr(default);
+            }
+        }
+        let mut scope = Scope::default();
+        for (name, kind) in collect_locals(body) {
+            scope.locals.insert(name, Local { kind, line: self.line, reads: 0, assignments: 0, fix: None });
+        }
+        for stmt in body {
+            if let Statement::Global(names) = stmt {
+                for name in names {
+                    scope.locals.remove(name);
+                }
+                scope.globals.extend(names.iter().cloned());
+            }
+        }
+        self.scopes.push(scope);
+        for param in params {
+            self.define(&param.name, LocalKind::Param, None);
+        }
+        self.block(body);
+
+        let scope = self.scopes.pop().unwrap_or_default();
+        let mut unused: Vec<(String, Local)> = scope
+            .locals
+            .into_iter()
+            .filter(|(name, local)| local.kind == LocalKind::Variable && local.reads == 0 && local.assignments > 0 && !name.starts_with('_'))
+            .collect();
+        unused.sort_by_key(|(_, local)| local.line);
+        for (name, local) in unused {
+            let (line, column) = self.locate(local.line, &name);
+            let fix = local.fix.filter(|_| local.assignments == 1);
+            self.report("unused-variable", line, column, format!("Local variable `{}` is assigned but never used", name), fix);
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Expressions
+    // ------------------------------------------------------------------------
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::None | Expr::Bool(_) | Expr::Int(_) | Expr::Float(_) | Expr::String(_) => {}
+            Expr::FString(parts) => {
+                for part in parts {
+                    if let FStringPart::Expr(expr) = part {
+                        self.expr(expr);
+                    }
+                }
+            }
+            Expr::List(items) => items.iter().for_each(|item| self.expr(item)),
+            Expr::Dict(pairs) => {
+                for (key, value) in pairs {
+                    self.expr(key);
+                    self.expr(value);
+                }
+            }
+            Expr::ListComp { expr, var, iter, condition } => {
+                self.expr(iter);
+                let mut scope = Scope::default();
+                scope.locals.insert(var.clone(), Local { kind: LocalKind::Loop, line: self.line, reads: 0, assignments: 1, fix: None });
+                self.scopes.push(scope);
+                self.expr(expr);
+                if let Some(condition) = condition {
+                    self.expr(condition);
+                }
+                self.scopes.pop();
+            }
+            Expr::Identifier(name) => self.read(name),
+            Expr::Index(target, index) => {
+                self.expr(target);
+                self.expr(index);
+            }
+            Expr::Slice(target, start, end) => {
+                self.expr(target);
+                for bound in [start, end].into_iter().flatten() {
+                    self.expr(bound);
+                }
+            }
+            Expr::Attribute(target, _) => self.expr(target),
+            Expr::BinaryOp(left, _, right) => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::UnaryOp(_, expr) => self.expr(expr),
+            Expr::Ternary(condition, then_expr, else_expr) => {
+                self.expr(condition);
+                self.expr(then_expr);
+                self.expr(else_expr);
+            }
+            Expr::Call(callee, args) => self.call(callee, args, &[]),
+            Expr::CallWithKwargs(callee, args, kwargs) => self.call(callee, args, kwargs),
+            Expr::Lambda(params, body) => {
+                let mut scope = Scope::default();
+                for param in params {
+                    scope.locals.insert(param.name.clone(), Local { kind: LocalKind::Param, line: self.line, reads: 0, assignments: 1, fix: None });
+                }
+                self.scopes.push(scope);
+                self.expr(body);
+                self.scopes.pop();
+            }
+            Expr::Widget { props, children, .. } => {
+                for (_, value) in props {
+                    self.expr(value);
+                }
+                for child in children {
+                    self.expr(child);
+                }
+            }
+        }
+    }
+
+    fn call(&mut self, callee: &Expr, args: &[Expr], kwargs: &[(String, Expr)]) {
+        self.expr(callee);
+        for arg in args {
+            self.expr(arg);
+        }
+        for (_, value) in kwargs {
+            self.expr(value);
+        }
+
+        match callee {
+            Expr::Identifier(name) if self.is_builtin(name) => {
+                self.check_arity(name, args.len(), !kwargs.is_empty());
+                if let Some((_, permission)) = PERMISSION_CALLS.iter().find(|(builtin, _)| builtin == name) {
+                    self.uses_permission(permission);
+                }
+            }
+            Expr::Identifier(name) => {
+                if let (Some(current), true) = (&self.current_fn, self.module.contains_key(name)) {
+                    self.calls.entry(current.clone()).or_default().insert(name.clone());
+                }
+            }
+            Expr::Attribute(target, _) => {
+                if let Expr::Identifier(module) = target.as_ref() {
+                    if let Some((_, permission)) = PERMISSION_MODULES.iter().find(|(m, _)| m == module) {
+                        self.uses_permission(permission);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_arity(&mut self, name: &str, given: usize, has_kwargs: bool) {
+        let Some(doc) = reference::builtin(name) else { return };
+        let too_many = doc.max_args.is_some_and(|max| given > max);
+        let too_few = !has_kwargs && given < doc.min_args;
+        if !too_many && !too_few {
+            return;
+        }
+        let expected = match doc.max_args {
+            Some(max) if max == doc.min_args => format!("{}", max),
+            Some(max) => format!("{} to {}", doc.min_args, max),
+            None => format!("at least {}", doc.min_args),
+        };
+        let (line, column) = self.locate(self.line, name);
+        self.report(
+            "wrong-arity",
+            line,
+            column,
+            format!("`{}` takes {} argument(s) but {} were given ({})", name, expected, given, doc.signature),
+            None,
+        );
+    }
+
+    fn uses_permission(&mut self, permission: &'static str) {
+        if let Some(current) = &self.current_fn {
+            self.permissions.entry(current.clone()).or_default().insert(permission);
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Whole-file rules
+    // ------------------------------------------------------------------------
+
+    fn check_imports(&mut self) {
+        let imports = std::mem::take(&mut self.imports);
+        for import in &imports {
+            let Some(provides) = &import.provides else { continue };
+            if import.names.is_empty() {
+                if provides.is_empty() || provides.iter().any(|name| self.reads.contains(name)) {
+                    continue;
+                }
+                let (line, column) = self.locate(import.line, &import.module);
+                let fix = import.fixable.then(|| Fix {
+                    line: import.line,
+                    replacement: import.only_statement.then(|| format!("{}pass", import.indent)),
+                });
+                self.report("unused-import", line, column, format!("`{}` imported but unused", import.module), fix);
+                continue;
+            }
+
+            let used: Vec<&String> = import.names.iter().filter(|name| self.reads.contains(*name)).collect();
+            let fix = import.fixable.then(|| Fix {
+                line: import.line,
+                replacement: match (used.is_empty(), import.only_statement) {
+                    (true, true) => Some(format!("{}pass", import.indent)),
+                    (true, false) => None,
+                    (false, _) => Some(format!(
+                        "{}from {} import {}",
+                        import.indent,
+                        import.module,
+                        used.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ")
+                    )),
+                },
+            });
+            let unused: Vec<&String> = import.names.iter().filter(|name| !self.reads.contains(*name)).collect();
+            for name in unused {
+                let (line, column) = self.locate(import.line, name);
+                self.report("unused-import", line, column, format!("`{}` imported from `{}` but unused", name, import.module), fix.clone());
+            }
+        }
+    }
+
+    fn check_permissions(&mut self) {
+        let Some(sovereignty) = &self.ctx.sovereignty else { return };
+        let mut missing_by_fn = Vec::new();
+        for (name, line) in &self.functions {
+            if name.starts_with('_') {
+                continue;
+            }
+            // Permissions used by this function and everything it calls in this file
+            let mut seen = HashSet::new();
+            let mut stack = vec![name.clone()];
+            let mut used: Vec<&'static str> = Vec::new();
+            while let Some(current) = stack.pop() {
+                if !seen.insert(current.clone()) {
+                    continue;
+                }
+                used.extend(self.permissions.get(&current).into_iter().flatten());
+                stack.extend(self.calls.get(&current).into_iter().flatten().cloned());
+            }
+            used.sort();
+            used.dedup();
+            let missing: Vec<&str> = used.into_iter().filter(|p| !granted(sovereignty, p)).collect();
+            if !missing.is_empty() {
+                missing_by_fn.push((name.clone(), *line, missing));
+            }
+        }
+        for (name, line, missing) in missing_by_fn {
+            let (line, column) = self.locate(line, &name);
+            self.report(
+                "undeclared-permission",
+                line,
+                column,
+                format!(
+                    "`{}` is callable over IPC and uses {}, which poly.toml [sovereignty] does not declare",
+                    name,
+                    missing.join(", ")
+                ),
+                None,
+            );
+        }
+    }
+}
+
+fn granted(config: &SovereigntyConfig, permission: &str) -> bool {
+    let any = |f: fn(&Permission) -> bool| config.permissions.iter().any(f);
+    match permission {
+        "fs:read" => !config.fs_allowlist.is_empty() || any(|p| matches!(p, Permission::FsRead(_) | Permission::FsWrite(_))),
+        "fs:write" => !config.fs_allowlist.is_empty() || any(|p| matches!(p, Permission::FsWrite(_))),
+        "http" => !config.http_allowlist.is_empty() || any(|p| matches!(p, Permission::HttpConnect(_))),
+        "database" => any(|p| matches!(p, Permission::Database)),
+        "shell:execute" => any(|p| matches!(p, Permission::ShellExecute)),
+        _ => true,
+    }
+}
+
+/// Blocks nested directly in a statement (not function or class bodies)
+fn nested_blocks(stmt: &Statement) -> Vec<&[Statement]> {
+    match stmt {
+        Statement::If { then_body, elif_branches, else_body, .. } => {
+            let mut blocks = vec![then_body.as_slice()];
+            blocks.extend(elif_branches.iter().map(|(_, body)| body.as_slice()));
+            blocks.extend(else_body.as_deref());
+            blocks
+        }
+        Statement::While { body, .. } | Statement::For { body, .. } | Statement::With { body, .. } => vec![body.as_slice()],
+        Statement::Try { try_body, except_body, .. } => vec![try_body.as_slice(), except_body.as_slice()],
+        _ => Vec::new(),
+    }
+}
+
+/// Names a function body binds locally (not looking into nested functions)
+fn collect_locals(body: &[Statement]) -> Vec<(String, LocalKind)> {
+    let mut locals = Vec::new();
+    for stmt in body {
+        match stmt {
+            Statement::Let(name, _) | Statement::Assign(name, _) => locals.push((name.clone(), LocalKind::Variable)),
+            Statement::For { var, .. } => locals.push((var.clone(), LocalKind::Loop)),
+            Statement::With { alias: Some(alias), .. } => locals.push((alias.clone(), LocalKind::Loop)),
+            Statement::FnDef { name, .. } => locals.push((name.clone(), LocalKind::Function)),
+            Statement::ClassDef { name, .. } => locals.push((name.clone(), LocalKind::Class)),
+            Statement::FromImport(_, names) => locals.extend(names.iter().map(|n| (n.clone(), LocalKind::Variable))),
+            _ => {}
+        }
+        for block in nested_blocks(stmt) {
+            locals.extend(collect_locals(block));
+        }
+    }
+    locals
+}
+
+/// Expressions that can be removed without changing behaviour
+fn is_pure(expr: &Expr) -> bool {
+    match expr {
+        Expr::None | Expr::Bool(_) | Expr::Int(_) | Expr::Float(_) | Expr::String(_) | Expr::Identifier(_) | Expr::Lambda(..) => true,
+        Expr::List(items) => items.iter().all(is_pure),
+        Expr::Dict(pairs) => pairs.iter().all(|(k, v)| is_pure(k) && is_pure(v)),
+        Expr::FString(parts) => parts.iter().all(|part| match part {
+            FStringPart::Literal(_) => true,
+            FStringPart::Expr(expr) => is_pure(expr),
+        }),
+        _ => false,
+    }
+}
+
+// ============================================================================
+// Files and reports
+// ============================================================================
+
+pub struct FileReport {
+    pub path: PathBuf,
+    pub findings: Vec<Finding>,
+    /// Parse or I/O error that stopped the file from being linted
+    pub error: Option<String>,
+    pub fixed: usize,
+}
+
+/// Lint every `.poly` file under `root`, applying safe fixes when `fix` is set
+pub fn lint_path(root: &Path, fix: bool) -> Vec<FileReport> {
+    let ctx = LintContext::for_path(root);
+    crate::fmt::discover(root)
+        .into_iter()
+        .map(|path| {
+            let mut report = FileReport { path: path.clone(), findings: Vec::new(), error: None, fixed: 0 };
+            let result = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Cannot read {}: {}", path.display(), e))
+                .and_then(|source| {
+                    let findings = lint(&source, Some(&path), &ctx)?;
+                    if !fix || findings.iter().all(|f| f.fix.is_none()) {
+                        return Ok(findings);
+                    }
+                    let (fixed, applied) = apply_fixes(&source, &findings);
+                    std::fs::write(&path, &fixed).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+                    report.fixed = applied;
+                    lint(&fixed, Some(&path), &ctx)
+                });
+            match result {
+                Ok(findings) => report.findings = findings,
+                Err(e) => report.error = Some(e),
+            }
+            report
+        })
+        .collect()
+}
+
+pub fn to_json(reports: &[FileReport]) -> String {
+    let mut items = Vec::new();
+    for report in reports {
+        let file = report.path.display().to_string();
+        if let Some(error) = &report.error {
+            items.push(serde_json::json!({"file": file, "rule": "parse-error", "severity": "error", "message": error}));
+        }
+        for f in &report.findings {
+            items.push(serde_json::json!({
+                "file": file,
+                "line": f.line,
+                "column": f.column,
+                "rule": f.rule,
+                "severity": f.severity.as_str(),
+                "message": f.message,
+                "fixable": f.fix.is_some(),
+            }));
+        }
+    }
+    serde_json::to_string_pretty(&items).unwrap_or_else(|_| "[]".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(findings: &[Finding]) -> Vec<(&'static str, usize)> {
+        findings.iter().map(|f| (f.rule, f.line)).collect()
+    }
+
+    #[test]
+    fn test_lint_rules() {
+        let source = [
+            "import json",
+            "import math",
+            "fn total(items):",
+            "    let unused = 0",
+            "    let len = 3",
+            "    let result = 0",
+            "    for item in items:",
+            "        result = result + item",
+            "    return result",
+            "    print(\"never\")",
+            "fn main():",
+            "    print(len(1, 2), missing)",
+            "    return json_dumps(total([1]))",
+        ]
+        .join("\n");
+        let findings = lint(&source, None, &LintContext::default()).unwrap();
+        assert_eq!(
+            rules(&findings),
+            vec![
+                ("unused-import", 2),
+                ("unused-variable", 4),
+                ("shadowed-builtin", 5),
+                ("unused-variable", 5),
+                ("unreachable-code", 10),
+                ("wrong-arity", 12),
+                ("undefined-name", 12),
+            ]
+        );
+        assert_eq!(findings[0].fix, Some(Fix { line: 2, replacement: None }));
+        // `len` is shadowed inside `total` only, so `main` still calls the builtin
+        assert_eq!(findings[5].column, 11);
+        assert_eq!(findings[6].column, 22);
+    }
+
+    #[test]
+    fn test_lint_config_fixes_and_permissions() {
+        let mut config = LintConfig::default();
+        config.rules.insert("unreachable-code".to_string(), "off".to_string());
+        config.globals.push("host_value".to_string());
+        let sovereignty = SovereigntyConfig::from_toml("[sovereignty]\npermissions = [\n  \"fs:read:documents\",\n]\n", "app");
+        let ctx = LintContext::new(&config, Some(sovereignty));
+
+        let source = [
+            "from math import math_sqrt, math_floor",
+            "fn save(text):",
+            "    let note = \"draft\"",
+            "    _write(text)",
+            "    return host_value",
+            "    pass",
+            "fn _write(text):",
+            "    write_file(\"notes.txt\", text)",
+            "fn load():",
+            "    return read_file(\"notes.txt\") + str(math_floor(1.5))",
+            "",
+        ]
+        .join("\n");
+        let findings = lint(&source, None, &ctx).unwrap();
+        assert_eq!(
+            rules(&findings),
+            vec![("unused-import", 1), ("undeclared-permission", 2), ("unused-variable", 3)]
+        );
+        assert_eq!(findings[1].severity, Severity::Error);
+        assert!(findings[1].message.contains("fs:write"));
+
+        let (fixed, applied) = apply_fixes(&source, &findings);
+        assert_eq!(applied, 2);
+        assert!(fixed.starts_with("from math import math_floor\nfn save(text):\n    _write(text)\n"));
+        let remaining = lint(&fixed, None, &ctx).unwrap();
+        assert_eq!(rules(&remaining), vec![("undeclared-permission", 2)]);
+    }
+}
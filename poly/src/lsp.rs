@@ -0,0 +1,792 @@
+//! Language server for Poly (`poly lsp`)
+//!
+//! Speaks LSP over stdio (Content-Length framed JSON-RPC) and works directly
+//! on the lexer's token stream, so it keeps answering while a file is
+//! half-typed and does not parse. Supports:
+//! - diagnostics for lex/parse errors
+//! - hover for builtins, `__poly_*` APIs and user functions (with docstrings)
+//! - completion of keywords, builtins, document names, module members and methods
+//! - go-to-definition, including across `import` / `from ... import`
+//! - document symbols and rename
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value as Json};
+
+use crate::lexer::{Lexer, SpannedToken, Token};
+use crate::parser::Parser;
+use crate::reference;
+
+// ============================================================================
+// Analysis
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Function,
+    Method,
+    Class,
+    Variable,
+    Parameter,
+}
+
+impl SymbolKind {
+    /// LSP `SymbolKind` number
+    fn lsp_symbol(self) -> u32 {
+        match self {
+            SymbolKind::Function => 12,
+            SymbolKind::Method => 6,
+            SymbolKind::Class => 5,
+            SymbolKind::Variable | SymbolKind::Parameter => 13,
+        }
+    }
+
+    /// LSP `CompletionItemKind` number
+    fn lsp_completion(self) -> u32 {
+        match self {
+            SymbolKind::Function => 3,
+            SymbolKind::Method => 2,
+            SymbolKind::Class => 7,
+            SymbolKind::Variable | SymbolKind::Parameter => 6,
+        }
+    }
+}
+
+/// A name defined in a document. Lines and columns are 1-based, as in the lexer.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub line: usize,
+    pub column: usize,
+    /// Enclosing class or function
+    pub container: Option<String>,
+    pub params: Vec<String>,
+    pub doc: Option<String>,
+}
+
+impl Symbol {
+    pub fn detail(&self) -> String {
+        match self.kind {
+            SymbolKind::Function | SymbolKind::Method => format!("fn {}({})", self.name, self.params.join(", ")),
+            SymbolKind::Class => format!("class {}", self.name),
+            SymbolKind::Variable => format!("let {}", self.name),
+            SymbolKind::Parameter => format!("(parameter) {}", self.name),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub module: String,
+    /// Names pulled in by `from module import a, b`; empty for `import module`
+    pub names: Vec<String>,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub message: String,
+}
+
+pub struct Analysis {
+    pub tokens: Vec<SpannedToken>,
+    pub symbols: Vec<Symbol>,
+    pub imports: Vec<Import>,
+}
+
+fn identifier(token: Option<&SpannedToken>) -> Option<&str> {
+    match token.map(|t| &t.token) {
+        Some(Token::Identifier(name)) => Some(name),
+        _ => None,
+    }
+}
+
+fn string_literal(token: &Token) -> Option<&str> {
+    match token {
+        Token::String(s) | Token::StringSingle(s) | Token::MultiLineString(s) | Token::MultiLineStringSingle(s) => Some(s),
+        _ => None,
+    }
+}
+
+/// Parameter names of the definition whose `(` is at `tokens[open]`, plus the
+/// index of the `:` ending the header
+fn parse_params(tokens: &[SpannedToken], open: usize) -> (Vec<(String, usize)>, usize) {
+    let mut params = Vec::new();
+    let mut depth = 0;
+    let mut expect_name = true;
+    let mut i = open;
+    while i < tokens.len() {
+        match &tokens[i].token {
+            Token::LParen | Token::LBracket | Token::LBrace => {
+                depth += 1;
+                expect_name = depth == 1;
+            }
+            Token::RParen | Token::RBracket | Token::RBrace => depth -= 1,
+            Token::Comma if depth == 1 => expect_name = true,
+            Token::Identifier(name) if depth == 1 && expect_name => {
+                params.push((name.clone(), i));
+                expect_name = false;
+            }
+            Token::Colon if depth == 0 => return (params, i),
+            Token::Newline if depth == 0 => return (params, i),
+            _ => {}
+        }
+        i += 1;
+    }
+    (params, i)
+}
+
+/// First statement of a body, if it is a string literal
+fn docstring(tokens: &[SpannedToken], colon: usize) -> Option<String> {
+    tokens[colon + 1..]
+        .iter()
+        .find(|t| !matches!(t.token, Token::Newline | Token::Indent))
+        .and_then(|t| string_literal(&t.token))
+        .map(|s| s.replace("\\n", "\n").trim().to_string())
+}
+
+pub fn analyze(text: &str) -> Analysis {
+    let tokens = Lexer::new(text).tokenize();
+    let mut symbols = Vec::new();
+    let mut imports = Vec::new();
+    // (name, is_class, indent level of the definition)
+    let mut scopes: Vec<(String, bool, usize)> = Vec::new();
+    let mut level: usize = 0;
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        match &token.token {
+            Token::Indent => level += 1,
+            Token::Dedent => {
+                level = level.saturating_sub(1);
+                while scopes.last().is_some_and(|(_, _, l)| *l >= level) {
+                    scopes.pop();
+                }
+            }
+            Token::Fn | Token::Def | Token::Class => {
+                let is_class = token.token == Token::Class;
+                if let Some(name) = identifier(tokens.get(i + 1)) {
+                    let name_token = &tokens[i + 1];
+                    let container = scopes.last().map(|(n, _, _)| n.clone());
+                    let in_class = scopes.last().is_some_and(|(_, c, _)| *c);
+                    let (params, colon) = if !is_class && matches!(tokens.get(i + 2).map(|t| &t.token), Some(Token::LParen)) {
+                        parse_params(&tokens, i + 2)
+                    } else {
+                        let colon = tokens[i..].iter().position(|t| t.token == Token::Colon).map_or(i, |p| i + p);
+                        (Vec::new(), colon)
+                    };
+                    let kind = match (is_class, in_class) {
+                        (true, _) => SymbolKind::Class,
+                        (false, true) => SymbolKind::Method,
+                        (false, false) => SymbolKind::Function,
+                    };
+                    symbols.push(Symbol {
+                        name: name.to_string(),
+                        kind,
+                        line: name_token.line,
+                        column: name_token.column,
+                        container,
+                        params: params.iter().map(|(p, _)| p.clone()).collect(),
+                        doc: docstring(&tokens, colon.min(tokens.len() - 1)),
+                    });
+                    for (param, index) in &params {
+                        if param == "self" {
+                            continue;
+                        }
+                        symbols.push(Symbol {
+                            name: param.clone(),
+                            kind: SymbolKind::Parameter,
+                            line: tokens[*index].line,
+                            column: tokens[*index].column,
+                            container: Some(name.to_string()),
+                            params: Vec::new(),
+                            doc: None,
+                        });
+                    }
+                    scopes.push((name.to_string(), is_class, level));
+                    i = colon.max(i + 1);
+                    continue;
+                }
+            }
+            Token::Let | Token::For => {
+                if let Some(name) = identifier(tokens.get(i + 1)) {
+                    symbols.push(Symbol {
+                        name: name.to_string(),
+                        kind: SymbolKind::Variable,
+                        line: tokens[i + 1].line,
+                        column: tokens[i + 1].column,
+                        container: scopes.last().map(|(n, _, _)| n.clone()),
+                        params: Vec::new(),
+                        doc: None,
+                    });
+                }
+            }
+            Token::Import => {
+                if let Some(module) = identifier(tokens.get(i + 1)) {
+                    imports.push(Import { module: module.to_string(), names: Vec::new(), line: tokens[i + 1].line, column: tokens[i + 1].column });
+                }
+            }
+            Token::From => {
+                if let Some(module) = identifier(tokens.get(i + 1)) {
+                    let names = tokens[i + 2..]
+                        .iter()
+                        .take_while(|t| !matches!(t.token, Token::Newline))
+                        .filter_map(|t| identifier(Some(t)).map(String::from))
+                        .collect();
+                    imports.push(Import { module: module.to_string(), names, line: tokens[i + 1].line, column: tokens[i + 1].column });
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    Analysis { tokens, symbols, imports }
+}
+
+/// Parse errors with the location where the parser stopped
+pub fn diagnostics(text: &str) -> Vec<Diagnostic> {
+    let tokens = Lexer::new(text).tokenize();
+    let mut parser = Parser::new(tokens);
+    match parser.parse() {
+        Ok(_) => Vec::new(),
+        Err(message) => {
+            let (line, column, length) = parser.error_location().unwrap_or((1, 1, 1));
+            vec![Diagnostic { line, column, length, message }]
+        }
+    }
+}
+
+/// Word, its start column (0-based) and the `qualifier.` before it, if any
+pub type WordAt = (String, usize, Option<String>);
+
+/// The identifier under a 0-based position, its start column (0-based) and
+/// the identifier before a `.` immediately preceding it (`obj` in `obj.name`)
+pub fn word_at(text: &str, line: usize, character: usize) -> Option<WordAt> {
+    let chars: Vec<char> = text.lines().nth(line)?.chars().collect();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut start = character.min(chars.len());
+    while start > 0 && is_word(chars[start - 1]) {
+        start -= 1;
+    }
+    let mut end = character.min(chars.len());
+    while end < chars.len() && is_word(chars[end]) {
+        end += 1;
+    }
+    let word: String = chars[start..end].iter().collect();
+    let qualifier = if start > 0 && chars[start - 1] == '.' {
+        let mut q = start - 1;
+        while q > 0 && is_word(chars[q - 1]) {
+            q -= 1;
+        }
+        Some(chars[q..start - 1].iter().collect::<String>()).filter(|s| !s.is_empty())
+    } else {
+        None
+    };
+    Some((word, start, qualifier))
+}
+
+/// Locate `<module>.poly` next to the document, then in the working directory
+pub fn resolve_module(document: Option<&Path>, module: &str) -> Option<PathBuf> {
+    let file = format!("{}.poly", module);
+    let mut candidates = Vec::new();
+    if let Some(dir) = document.and_then(|p| p.parent()) {
+        candidates.push(dir.join(&file));
+    }
+    candidates.push(PathBuf::from(&file));
+    candidates.into_iter().find(|p| p.is_file())
+}
+
+impl Analysis {
+    /// The definition of `name` visible from `line`: the closest one above it,
+    /// otherwise the first one in the file
+    pub fn definition(&self, name: &str, line: usize) -> Option<&Symbol> {
+        let candidates: Vec<&Symbol> = self.symbols.iter().filter(|s| s.name == name).collect();
+        candidates
+            .iter()
+            .filter(|s| s.line <= line)
+            .max_by_key(|s| s.line)
+            .or(candidates.first())
+            .copied()
+    }
+
+    pub fn top_level(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter().filter(|s| s.container.is_none())
+    }
+
+    /// Every identifier token spelling `name` that is not an attribute access
+    pub fn references(&self, name: &str) -> Vec<&SpannedToken> {
+        self.tokens
+            .iter()
+            .enumerate()
+            .filter(|(i, t)| {
+                matches!(&t.token, Token::Identifier(n) if n == name)
+                    && !(*i > 0 && self.tokens[i - 1].token == Token::Dot)
+            })
+            .map(|(_, t)| t)
+            .collect()
+    }
+
+    fn imported_module(&self, name: &str) -> Option<&Import> {
+        self.imports.iter().find(|imp| imp.module == name || imp.names.iter().any(|n| n == name))
+    }
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+struct Document {
+    text: String,
+    path: Option<PathBuf>,
+}
+
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    url::Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let absolute = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    url::Url::from_file_path(&absolute).map(|u| u.to_string()).unwrap_or_else(|_| absolute.display().to_string())
+}
+
+fn range(line: usize, column: usize, length: usize) -> Json {
+    let (line, column) = (line.saturating_sub(1), column.saturating_sub(1));
+    json!({
+        "start": {"line": line, "character": column},
+        "end": {"line": line, "character": column + length},
+    })
+}
+
+fn location(uri: &str, symbol: &Symbol) -> Json {
+    json!({"uri": uri, "range": range(symbol.line, symbol.column, symbol.name.len())})
+}
+
+fn markdown(value: String) -> Json {
+    json!({"contents": {"kind": "markdown", "value": value}})
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self { documents: HashMap::new(), shutdown: false }
+    }
+
+    /// Handle one JSON-RPC message; returns the messages to send back
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message.get("method").and_then(|m| m.as_str()).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+        let id = message.get("id").cloned();
+
+        let result = match method {
+            "initialize" => Some(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "completionProvider": {"triggerCharacters": ["."]},
+                    "definitionProvider": true,
+                    "documentSymbolProvider": true,
+                    "renameProvider": true,
+                    "documentFormattingProvider": true,
+                },
+                "serverInfo": {"name": "poly-lsp", "version": env!("CARGO_PKG_VERSION")},
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Some(Json::Null)
+            }
+            "textDocument/didOpen" | "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+                let text = if method == "textDocument/didOpen" {
+                    params["textDocument"]["text"].as_str().map(String::from)
+                } else {
+                    params["contentChanges"].as_array().and_then(|c| c.last()).and_then(|c| c["text"].as_str()).map(String::from)
+                };
+                if let Some(text) = text {
+                    let path = uri_to_path(&uri);
+                    self.documents.insert(uri.clone(), Document { text, path });
+                    return vec![self.publish_diagnostics(&uri)];
+                }
+                None
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+                self.documents.remove(uri);
+                return vec![json!({
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": {"uri": uri, "diagnostics": []},
+                })];
+            }
+            "textDocument/hover" => Some(self.hover(&params).unwrap_or(Json::Null)),
+            "textDocument/completion" => Some(self.completion(&params)),
+            "textDocument/definition" => Some(self.definition(&params).unwrap_or(Json::Null)),
+            "textDocument/documentSymbol" => Some(self.document_symbols(&params)),
+            "textDocument/formatting" => Some(self.formatting(&params)),
+            "textDocument/rename" => Some(match self.rename(&params) {
+                Ok(edit) => edit,
+                Err(e) => {
+                    return id
+                        .map(|id| vec![json!({"jsonrpc": "2.0", "id": id, "error": {"code": -32602, "message": e}})])
+                        .unwrap_or_default()
+                }
+            }),
+            _ => None,
+        };
+
+        match id {
+            Some(id) if result.is_some() => vec![json!({"jsonrpc": "2.0", "id": id, "result": result})],
+            Some(id) if !method.is_empty() => {
+                vec![json!({"jsonrpc": "2.0", "id": id, "error": {"code": -32601, "message": format!("Method not found: {}", method)}})]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn publish_diagnostics(&self, uri: &str) -> Json {
+        let diagnostics: Vec<Json> = self
+            .documents
+            .get(uri)
+            .map(|doc| diagnostics(&doc.text))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|d| json!({"range": range(d.line, d.column, d.length), "severity": 1, "source": "poly", "message": d.message}))
+            .collect();
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": {"uri": uri, "diagnostics": diagnostics},
+        })
+    }
+
+    /// Document, analysis and the word under the cursor for a position request
+    fn at_position<'a>(&'a self, params: &'a Json) -> Option<(&'a str, &'a Document, Analysis, WordAt, usize)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let doc = self.documents.get(uri)?;
+        let line = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+        let word = word_at(&doc.text, line, character)?;
+        Some((uri, doc, analyze(&doc.text), word, line + 1))
+    }
+
+    fn module_analysis(&self, doc: &Document, module: &str) -> Option<(PathBuf, Analysis)> {
+        let path = resolve_module(doc.path.as_deref(), module)?;
+        let text = std::fs::read_to_string(&path).ok()?;
+        Some((path, analyze(&text)))
+    }
+
+    fn hover(&self, params: &Json) -> Option<Json> {
+        let (_, doc, analysis, (word, _, qualifier), line) = self.at_position(params)?;
+        if word.is_empty() {
+            return None;
+        }
+        if let Some(api) = reference::poly_api(&word) {
+            return Some(markdown(format!("```js\n{}\n```\n{} (`{}`)", api.signature(), api.summary, api.namespace)));
+        }
+        if let Some(module) = qualifier {
+            if reference::NATIVE_MODULES.contains(&module.as_str()) {
+                return Some(markdown(format!("```poly\n{}.{}\n```\nMember of the native `{}` module", module, word, module)));
+            }
+            let (_, module_analysis) = self.module_analysis(doc, &module)?;
+            return module_analysis.top_level().find(|s| s.name == word).map(symbol_hover);
+        }
+        if let Some(symbol) = analysis.definition(&word, line) {
+            return Some(symbol_hover(symbol));
+        }
+        if let Some(builtin) = reference::builtin(&word) {
+            return Some(markdown(format!("```poly\n{}\n```\n{}", builtin.signature, builtin.summary)));
+        }
+        if let Some(import) = analysis.imported_module(&word) {
+            let (_, module_analysis) = self.module_analysis(doc, &import.module)?;
+            return module_analysis.top_level().find(|s| s.name == word).map(symbol_hover);
+        }
+        None
+    }
+
+    fn completion(&self, params: &Json) -> Json {
+        let Some((_, doc, analysis, (word, start, qualifier), _)) = self.at_position(params) else {
+            return json!([]);
+        };
+        // Only the part of the word left of the cursor filters the results
+        let typed = params["position"]["character"].as_u64().unwrap_or(0) as usize;
+        let prefix: String = word.chars().take(typed.saturating_sub(start)).collect();
+        let mut items: Vec<Json> = Vec::new();
+        let mut push = |label: &str, kind: u32, detail: &str| {
+            if label.starts_with(prefix.as_str()) && !items.iter().any(|i| i["label"] == label) {
+                items.push(json!({"label": label, "kind": kind, "detail": detail}));
+            }
+        };
+
+        if let Some(object) = qualifier {
+            if let Some(members) = reference::module_members(&object) {
+                for member in members {
+                    let name = member.strip_prefix(&format!("{}_", object)).unwrap_or(&member).to_string();
+                    push(&name, 3, &format!("{}.{}", object, name));
+                }
+            } else if let Some((_, module)) = self.module_analysis(doc, &object) {
+                for symbol in module.top_level() {
+                    push(&symbol.name, symbol.kind.lsp_completion(), &symbol.detail());
+                }
+            } else {
+                for method in reference::STRING_METHODS.iter().chain(reference::LIST_METHODS).chain(reference::DICT_METHODS) {
+                    push(method, 2, "method");
+                }
+                for symbol in analysis.symbols.iter().filter(|s| s.kind == SymbolKind::Method) {
+                    push(&symbol.name, 2, &symbol.detail());
+                }
+            }
+            return json!(items);
+        }
+
+        for symbol in &analysis.symbols {
+            push(&symbol.name, symbol.kind.lsp_completion(), &symbol.detail());
+        }
+        for import in &analysis.imports {
+            push(&import.module, 9, "module");
+            for name in &import.names {
+                push(name, 3, &format!("from {}", import.module));
+            }
+        }
+        for builtin in reference::BUILTINS {
+            push(builtin.name, 3, builtin.signature);
+        }
+        for module in reference::NATIVE_MODULES {
+            push(module, 9, "native module");
+        }
+        for keyword in reference::KEYWORDS {
+            push(keyword, 14, "keyword");
+        }
+        if prefix.starts_with("__") {
+            for api in reference::POLY_APIS {
+                push(api.name, 3, api.summary);
+            }
+        }
+        json!(items)
+    }
+
+    fn definition(&self, params: &Json) -> Option<Json> {
+        let (uri, doc, analysis, (word, _, qualifier), line) = self.at_position(params)?;
+        if let Some(module) = qualifier {
+            let (path, module_analysis) = self.module_analysis(doc, &module)?;
+            let symbol = module_analysis.top_level().find(|s| s.name == word)?;
+            return Some(location(&path_to_uri(&path), symbol));
+        }
+        // On the module name in an import: jump to the file
+        if analysis.imports.iter().any(|imp| imp.module == word && imp.line == line) {
+            let path = resolve_module(doc.path.as_deref(), &word)?;
+            return Some(json!({"uri": path_to_uri(&path), "range": range(1, 1, 0)}));
+        }
+        if let Some(symbol) = analysis.definition(&word, line) {
+            return Some(location(uri, symbol));
+        }
+        let import = analysis.imported_module(&word)?;
+        let (path, module_analysis) = self.module_analysis(doc, &import.module)?;
+        let found = match module_analysis.top_level().find(|s| s.name == word) {
+            Some(symbol) => location(&path_to_uri(&path), symbol),
+            None => json!({"uri": path_to_uri(&path), "range": range(1, 1, 0)}),
+        };
+        Some(found)
+    }
+
+    fn document_symbols(&self, params: &Json) -> Json {
+        let Some(doc) = params["textDocument"]["uri"].as_str().and_then(|uri| self.documents.get(uri)) else {
+            return json!([]);
+        };
+        let analysis = analyze(&doc.text);
+        let symbols: Vec<Json> = analysis
+            .symbols
+            .iter()
+            .filter(|s| s.kind != SymbolKind::Parameter)
+            .map(|s| {
+                let r = range(s.line, s.column, s.name.len());
+                let mut symbol = json!({
+                    "name": s.name,
+                    "detail": s.detail(),
+                    "kind": s.kind.lsp_symbol(),
+                    "range": r,
+                    "selectionRange": r,
+                });
+                if let Some(container) = &s.container {
+                    symbol["containerName"] = json!(container);
+                }
+                symbol
+            })
+            .collect();
+        json!(symbols)
+    }
+
+    /// Whole-document edit from `poly fmt`; no edits if the file does not parse
+    fn formatting(&self, params: &Json) -> Json {
+        let Some(doc) = params["textDocument"]["uri"].as_str().and_then(|uri| self.documents.get(uri)) else {
+            return json!([]);
+        };
+        match crate::fmt::format(&doc.text) {
+            Ok(formatted) if formatted != doc.text => {
+                let lines = doc.text.split('\n').count();
+                json!([{
+                    "range": {"start": {"line": 0, "character": 0}, "end": {"line": lines, "character": 0}},
+                    "newText": formatted,
+                }])
+            }
+            _ => json!([]),
+        }
+    }
+
+    fn rename(&self, params: &Json) -> Result<Json, String> {
+        let (uri, _, analysis, (word, _, qualifier), line) = self.at_position(params).ok_or("No symbol at this position")?;
+        let new_name = params["newName"].as_str().ok_or("Missing newName")?;
+        if qualifier.is_some() || analysis.definition(&word, line).is_none() {
+            return Err(format!("'{}' is not defined in this file", word));
+        }
+        let valid = new_name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && new_name.chars().all(|c| c.is_alphanumeric() || c == '_')
+            && !reference::KEYWORDS.contains(&new_name);
+        if !valid {
+            return Err(format!("'{}' is not a valid identifier", new_name));
+        }
+        let edits: Vec<Json> = analysis
+            .references(&word)
+            .iter()
+            .map(|t| json!({"range": range(t.line, t.column, word.len()), "newText": new_name}))
+            .collect();
+        Ok(json!({"changes": {uri: edits}}))
+    }
+}
+
+fn symbol_hover(symbol: &Symbol) -> Json {
+    let mut text = format!("```poly\n{}\n```", symbol.detail());
+    if let Some(doc) = &symbol.doc {
+        text.push('\n');
+        text.push_str(doc);
+    }
+    markdown(text)
+}
+
+/// Read one Content-Length framed message
+pub(crate) fn read_message(input: &mut impl BufRead) -> std::io::Result<Option<Json>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let Some(length) = length else { return Ok(Some(Json::Null)) };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body).unwrap_or(Json::Null)))
+}
+
+pub(crate) fn write_message(output: &mut impl Write, message: &Json) -> std::io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+/// Serve LSP over stdin/stdout until `exit`
+pub fn run_stdio() -> Result<(), String> {
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock();
+    let mut output = std::io::stdout();
+    let mut server = Server::new();
+    while let Some(message) = read_message(&mut input).map_err(|e| e.to_string())? {
+        if message.get("method").and_then(|m| m.as_str()) == Some("exit") {
+            return if server.shutdown { Ok(()) } else { Err("exit received before shutdown".to_string()) };
+        }
+        for reply in server.handle(&message) {
+            write_message(&mut output, &reply).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "import helpers\n\nclass Counter:\n    fn add(self, amount):\n        \"Add to the count\"\n        return amount\n\nfn total(items, start=0):\n    \"Sum items from start\"\n    let result = start\n    for item in items:\n        result = result + item\n    return result\n\nprint(total([1, 2]))\nprint(helpers.double(2))\n";
+
+    fn open(server: &mut Server, uri: &str, text: &str) -> Vec<Json> {
+        server.handle(&json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {"textDocument": {"uri": uri, "text": text}}}))
+    }
+
+    fn request(server: &mut Server, method: &str, uri: &str, line: u32, character: u32, extra: Json) -> Json {
+        let mut params = json!({"textDocument": {"uri": uri}, "position": {"line": line, "character": character}});
+        if let (Some(params), Some(extra)) = (params.as_object_mut(), extra.as_object()) {
+            params.extend(extra.clone());
+        }
+        server.handle(&json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params})).remove(0)["result"].clone()
+    }
+
+    #[test]
+    fn test_symbols_hover_and_rename() {
+        let analysis = analyze(SOURCE);
+        let total = analysis.definition("total", 15).unwrap();
+        assert_eq!((total.line, total.column, total.kind), (8, 4, SymbolKind::Function));
+        assert_eq!(total.params, vec!["items", "start"]);
+        assert_eq!(total.doc.as_deref(), Some("Sum items from start"));
+        let add = analysis.definition("add", 15).unwrap();
+        assert_eq!((add.kind, add.container.as_deref()), (SymbolKind::Method, Some("Counter")));
+
+        let mut server = Server::new();
+        let uri = "file:///tmp/poly_lsp_main.poly";
+        open(&mut server, uri, SOURCE);
+        let hover = request(&mut server, "textDocument/hover", uri, 14, 7, json!({}));
+        assert!(hover["contents"]["value"].as_str().unwrap().contains("fn total(items, start)"));
+        let hover = request(&mut server, "textDocument/hover", uri, 14, 1, json!({}));
+        assert!(hover["contents"]["value"].as_str().unwrap().contains("print(*values)"));
+
+        let edit = request(&mut server, "textDocument/rename", uri, 9, 9, json!({"newName": "acc"}));
+        let edits = edit["changes"][uri].as_array().unwrap();
+        assert_eq!(edits.len(), 4);
+        assert_eq!(edits[0]["range"]["start"], json!({"line": 9, "character": 8}));
+    }
+
+    #[test]
+    fn test_diagnostics_and_cross_file_definition() {
+        let broken = diagnostics("let x = 1\nlet = 2\n");
+        assert_eq!(broken.len(), 1);
+        assert_eq!((broken[0].line, broken[0].column), (2, 5));
+
+        let dir = std::env::temp_dir().join(format!("poly_lsp_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("helpers.poly"), "fn double(x):\n    return x * 2\n").unwrap();
+        let main = dir.join("main.poly");
+        let uri = url::Url::from_file_path(&main).unwrap().to_string();
+
+        let mut server = Server::new();
+        let published = open(&mut server, &uri, SOURCE);
+        assert_eq!(published[0]["params"]["diagnostics"], json!([]));
+        let location = request(&mut server, "textDocument/definition", &uri, 15, 15, json!({}));
+        assert!(location["uri"].as_str().unwrap().ends_with("helpers.poly"));
+        assert_eq!(location["range"]["start"], json!({"line": 0, "character": 3}));
+
+        let completions = request(&mut server, "textDocument/completion", &uri, 15, 14, json!({}));
+        assert!(completions.as_array().unwrap().iter().any(|c| c["label"] == "double"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,6 +1,6 @@
 ﻿use clap::{Parser, Subcommand};
 use std::fs;
-use std::io::{self, Write, BufRead};
+use std::io::{self, Write, BufRead, Read};
 use std::path::Path;
 use std::sync::mpsc::channel;
 use std::time::Duration;
@@ -59,9 +59,25 @@ enum Commands {
         #[arg(short, long, default_value = "3000")]
         port: u16,
         
+        /// Address to listen on (0.0.0.0 exposes the server to the network)
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        
+        /// Print every system API call with its outcome and duration
+        #[arg(long)]
+        log_ipc: bool,
+        
         /// Open browser automatically
         #[arg(long)]
         open: bool,
+        
+        /// Accept debugger connections (Debug Adapter Protocol) on this port
+        #[arg(long, value_name = "PORT", num_args = 0..=1, default_missing_value = "4711")]
+        debug: Option<u16>,
+        
+        /// Profile IPC commands and write <PATH>.folded, .svg and .txt while running
+        #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "poly-profile")]
+        profile: Option<String>,
     },
     
     /// Run the application
@@ -85,6 +101,14 @@ enum Commands {
         /// UI height in pixels for browser mode (default: 80)
         #[arg(long, default_value = "80")]
         ui_height: u32,
+        
+        /// Profile the run and write <PATH>.folded, .svg (flamegraph) and .txt
+        #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "poly-profile")]
+        profile: Option<String>,
+        
+        /// Run without windows, answering IPC requests sent as JSON lines on stdin
+        #[arg(long, conflicts_with_all = ["native", "browser", "profile"])]
+        headless: bool,
     },
     
     /// Build the application
@@ -93,7 +117,7 @@ enum Commands {
         #[arg(default_value = ".")]
         path: String,
         
-        /// Target platform (windows, macos, linux, current)
+        /// Target platform (windows, macos, linux, current), or js to compile Poly to JavaScript
         #[arg(short, long, default_value = "current")]
         target: String,
         
@@ -157,6 +181,101 @@ enum Commands {
         verify: bool,
     },
     
+    /// Run test_*.poly files
+    Test {
+        /// Project directory or test file
+        #[arg(default_value = ".")]
+        path: String,
+        
+        /// Only run tests whose id (file::name) contains this text
+        #[arg(short = 'k', long)]
+        filter: Option<String>,
+        
+        /// Number of parallel workers (default: one per CPU)
+        #[arg(short, long, default_value = "0")]
+        jobs: usize,
+        
+        /// Write a JUnit XML report to this file
+        #[arg(long)]
+        junit: Option<String>,
+        
+        /// Write a JSON report to this file
+        #[arg(long)]
+        json: Option<String>,
+        
+        /// Record line and branch coverage; writes lcov.info and cobertura.xml to DIR
+        #[arg(long, value_name = "DIR", num_args = 0..=1, default_missing_value = "coverage")]
+        coverage: Option<String>,
+    },
+    
+    /// Start the language server (LSP over stdio)
+    Lsp,
+    
+    /// Format .poly files in place
+    Fmt {
+        /// Files or directories to format ("-" reads stdin and writes stdout)
+        #[arg(default_value = ".")]
+        paths: Vec<String>,
+        
+        /// Only check formatting; exit with an error if any file would change
+        #[arg(long)]
+        check: bool,
+    },
+    
+    /// Debug a .poly file interactively
+    Debug {
+        /// File to debug
+        file: String,
+        
+        /// Set a breakpoint at this line (repeatable); without any, stop on entry
+        #[arg(short = 'b', long = "break", value_name = "LINE")]
+        breakpoints: Vec<usize>,
+        
+        /// Speak the Debug Adapter Protocol on stdin/stdout instead of the terminal UI
+        #[arg(long)]
+        dap: bool,
+    },
+    
+    /// Check .poly files for common mistakes
+    Lint {
+        /// File or directory to lint
+        #[arg(default_value = ".")]
+        path: String,
+        
+        /// Output format: text or json
+        #[arg(long, default_value = "text")]
+        format: String,
+        
+        /// Apply safe fixes (remove unused imports and variables)
+        #[arg(long)]
+        fix: bool,
+    },
+    
+    /// Generate documentation from docstrings
+    Doc {
+        /// File or directory to document
+        #[arg(default_value = ".")]
+        path: String,
+        
+        /// Output directory for the HTML site
+        #[arg(short, long, default_value = "doc")]
+        out: String,
+        
+        /// Output format: html or json (printed to stdout)
+        #[arg(long, default_value = "html")]
+        format: String,
+        
+        /// Only regenerate the system API reference section of a Markdown file (e.g. docs/API.md)
+        #[arg(long, value_name = "FILE")]
+        api_md: Option<String>,
+    },
+    
+    /// Generate code from the project
+    Gen {
+        #[command(subcommand)]
+        target: GenTarget,
+    },
+    
     /// Open a URL in a new Poly WebView window (internal use)
     #[command(hide = true)]
     OpenUrl {
@@ -205,15 +324,42 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum GenTarget {
+    /// Typed JS client and .d.ts for the entry module's functions and the system APIs
+    Bindings {
+        /// Project directory
+        #[arg(default_value = ".")]
+        path: String,
+        
+        /// Output directory (default: the project's web/ folder)
+        #[arg(short, long)]
+        out: Option<String>,
+    },
+}
+
 /// Cookie storage for PolyView proxy
 use std::sync::Mutex;
 lazy_static::lazy_static! {
     static ref POLYVIEW_COOKIES: Mutex<std::collections::HashMap<String, Vec<String>>> = Mutex::new(std::collections::HashMap::new());
 }
 
+/// Prefix of PolyView URLs on the native server, ending in `url=`; the session
+/// token comes first so the encoded target URL can run to the end
+fn polyview_base(port: u16, token: &str) -> String {
+    format!("http://localhost:{}/__polyview/?token={}&url=", port, token)
+}
+
+/// Target of a proxy request: everything after the `url=` parameter, decoded
+fn proxy_target(url: &str) -> String {
+    let query = url.split_once('?').map_or("", |(_, query)| query);
+    let target = query.strip_prefix("url=").or_else(|| query.split_once("&url=").map(|(_, target)| target));
+    urlencoding::decode(target.unwrap_or("")).unwrap_or_default().to_string()
+}
+
 /// Handle PolyView proxy request - fetches URL and rewrites content to bypass iframe restrictions
 /// This is "iframe2" - better than normal iframes because it bypasses ALL restrictions
-fn handle_polyview_proxy(target_url: &str, proxy_port: u16) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+fn handle_polyview_proxy(target_url: &str, proxy_base: &str) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
     
     
     println!("[PolyView] Proxying: {}", target_url);
@@ -264,7 +410,7 @@ fn handle_polyview_proxy(target_url: &str, proxy_port: u16) -> tiny_http::Respon
     
     match req.call() {
         Ok(response) => {
-            process_polyview_response(response, target_url, proxy_port, &domain, 200)
+            process_polyview_response(response, target_url, proxy_base, &domain, 200)
         }
         Err(ureq::Error::Status(code, response)) => {
             // Handle redirects (3xx) and error responses
@@ -272,11 +418,7 @@ fn handle_polyview_proxy(target_url: &str, proxy_port: u16) -> tiny_http::Respon
                 // Handle redirect - rewrite Location header
                 if let Some(location) = response.header("Location") {
                     let absolute_url = resolve_polyview_url(target_url, location);
-                    let proxied_location = format!(
-                        "http://localhost:{}/__polyview/?url={}",
-                        proxy_port,
-                        urlencoding::encode(&absolute_url)
-                    );
+                    let proxied_location = format!("{}{}", proxy_base, urlencoding::encode(&absolute_url));
                     
                     // Store any cookies from redirect response
                     store_polyview_cookies(&response, &domain);
@@ -284,11 +426,10 @@ fn handle_polyview_proxy(target_url: &str, proxy_port: u16) -> tiny_http::Respon
                     return tiny_http::Response::from_string("")
                         .with_status_code(code)
                         .with_header(tiny_http::Header::from_bytes(&b"Location"[..], proxied_location.as_bytes()).unwrap())
-                        .with_header(tiny_http::Header::from_bytes(&b"X-Frame-Options"[..], &b"ALLOWALL"[..]).unwrap())
-                        .with_header(tiny_http::Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..]).unwrap());
+                        .with_header(tiny_http::Header::from_bytes(&b"X-Frame-Options"[..], &b"ALLOWALL"[..]).unwrap());
                 }
             }
-            process_polyview_response(response, target_url, proxy_port, &domain, code)
+            process_polyview_response(response, target_url, proxy_base, &domain, code)
         }
         Err(e) => {
             println!("[PolyView] Error: {}", e);
@@ -336,7 +477,7 @@ fn store_polyview_cookies(response: &ureq::Response, domain: &str) {
 fn process_polyview_response(
     response: ureq::Response,
     target_url: &str,
-    proxy_port: u16,
+    proxy_base: &str,
     domain: &str,
     status: u16
 ) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
@@ -353,7 +494,7 @@ fn process_polyview_response(
         let mut body_str = String::new();
         let reader = response.into_reader();
         let _ = reader.take(50_000_000).read_to_string(&mut body_str);
-        rewrite_polyview_html(&body_str, target_url, proxy_port).into_bytes()
+        rewrite_polyview_html(&body_str, target_url, proxy_base).into_bytes()
     } else {
         let mut body = Vec::new();
         let reader = response.into_reader();
@@ -379,25 +520,11 @@ fn process_polyview_response(
             &b"frame-ancestors *; default-src * 'unsafe-inline' 'unsafe-eval' data: blob:; script-src * 'unsafe-inline' 'unsafe-eval'; style-src * 'unsafe-inline';"[..]
         ).unwrap()
     );
-    // Allow all origins
-    resp = resp.with_header(
-        tiny_http::Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..]).unwrap()
-    );
-    resp = resp.with_header(
-        tiny_http::Header::from_bytes(&b"Access-Control-Allow-Methods"[..], &b"GET, POST, PUT, DELETE, OPTIONS"[..]).unwrap()
-    );
-    resp = resp.with_header(
-        tiny_http::Header::from_bytes(&b"Access-Control-Allow-Headers"[..], &b"*"[..]).unwrap()
-    );
-    resp = resp.with_header(
-        tiny_http::Header::from_bytes(&b"Access-Control-Allow-Credentials"[..], &b"true"[..]).unwrap()
-    );
-    
     resp
 }
 
 /// Handle PolyView POST request - for form submissions
-fn handle_polyview_proxy_post(target_url: &str, proxy_port: u16, body: &[u8], content_type: &str) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+fn handle_polyview_proxy_post(target_url: &str, proxy_base: &str, body: &[u8], content_type: &str) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
     
     
     println!("[PolyView] POST: {}", target_url);
@@ -427,7 +554,7 @@ fn handle_polyview_proxy_post(target_url: &str, proxy_port: u16, body: &[u8], co
         .set("Content-Type", content_type)
         .set("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
         .set("Accept-Language", "en-US,en;q=0.9")
-        .set("Origin", &format!("http://localhost:{}", proxy_port))
+        .set("Origin", proxy_base.split("/__polyview").next().unwrap_or_default())
         .set("Sec-Ch-Ua", "\"Not_A Brand\";v=\"8\", \"Chromium\";v=\"120\"")
         .set("Sec-Ch-Ua-Mobile", "?0")
         .set("Sec-Ch-Ua-Platform", "\"Windows\"")
@@ -442,17 +569,13 @@ fn handle_polyview_proxy_post(target_url: &str, proxy_port: u16, body: &[u8], co
     
     match req.send_bytes(body) {
         Ok(response) => {
-            process_polyview_response(response, target_url, proxy_port, &domain, 200)
+            process_polyview_response(response, target_url, proxy_base, &domain, 200)
         }
         Err(ureq::Error::Status(code, response)) => {
             if (300..400).contains(&code) {
                 if let Some(location) = response.header("Location") {
                     let absolute_url = resolve_polyview_url(target_url, location);
-                    let proxied_location = format!(
-                        "http://localhost:{}/__polyview/?url={}",
-                        proxy_port,
-                        urlencoding::encode(&absolute_url)
-                    );
+                    let proxied_location = format!("{}{}", proxy_base, urlencoding::encode(&absolute_url));
                     
                     store_polyview_cookies(&response, &domain);
                     
@@ -462,7 +585,7 @@ fn handle_polyview_proxy_post(target_url: &str, proxy_port: u16, body: &[u8], co
                         .with_header(tiny_http::Header::from_bytes(&b"X-Frame-Options"[..], &b"ALLOWALL"[..]).unwrap());
                 }
             }
-            process_polyview_response(response, target_url, proxy_port, &domain, code)
+            process_polyview_response(response, target_url, proxy_base, &domain, code)
         }
         Err(e) => {
             println!("[PolyView] POST Error: {}", e);
@@ -499,9 +622,7 @@ fn resolve_polyview_url(base: &str, relative: &str) -> String {
 
 /// Rewrite HTML to proxy all URLs through PolyView
 /// This is the core of "iframe2" - making the content work seamlessly in an iframe
-fn rewrite_polyview_html(html: &str, base_url: &str, proxy_port: u16) -> String {
-    let proxy_base = format!("http://localhost:{}/__polyview/?url=", proxy_port);
-    
+fn rewrite_polyview_html(html: &str, base_url: &str, proxy_base: &str) -> String {
     let mut result = html.to_string();
     
     // Inject base tag for relative URLs
@@ -514,9 +635,9 @@ fn rewrite_polyview_html(html: &str, base_url: &str, proxy_port: u16) -> String
     }
     
     // Rewrite href, src, action attributes
-    result = rewrite_polyview_attribute(&result, "href", base_url, &proxy_base);
-    result = rewrite_polyview_attribute(&result, "src", base_url, &proxy_base);
-    result = rewrite_polyview_attribute(&result, "action", base_url, &proxy_base);
+    result = rewrite_polyview_attribute(&result, "href", base_url, proxy_base);
+    result = rewrite_polyview_attribute(&result, "src", base_url, proxy_base);
+    result = rewrite_polyview_attribute(&result, "action", base_url, proxy_base);
     
     // Inject PolyView client script - this is what makes iframe2 special
     // It intercepts navigation, form submissions, and reports state to parent
@@ -524,9 +645,8 @@ fn rewrite_polyview_html(html: &str, base_url: &str, proxy_port: u16) -> String
 <script>
 (function() {{
     // PolyView Client - "iframe2" magic
-    const PROXY_BASE = 'http://localhost:{}/__polyview/?url=';
+    const PROXY_BASE = '{}';
     const BASE_URL = '{}';
-    const PROXY_PORT = {};
     
     // Report to parent window
     function reportToParent(type, data) {{
@@ -637,7 +757,7 @@ fn rewrite_polyview_html(html: &str, base_url: &str, proxy_port: u16) -> String
     console.log('[PolyView] iframe2 client loaded for:', BASE_URL);
 }})();
 </script>
-"#, proxy_port, base_url, proxy_port);
+"#, proxy_base, base_url);
     
     if let Some(pos) = result.to_lowercase().find("</head>") {
         result.insert_str(pos, &client_script);
@@ -705,6 +825,7 @@ fn rewrite_polyview_attribute(html: &str, attr: &str, base_url: &str, proxy_base
 
 fn main() {
     let cli = Cli::parse();
+    poly::register_builtin_commands();
     
     // Handle version flag with update check
     if cli.version {
@@ -713,8 +834,15 @@ fn main() {
     }
     
     let result = match cli.command {
-        Some(Commands::Dev { path, port, open }) => { run_dev_server(&path, port, open); Ok(()) },
-        Some(Commands::Run { path, release, native, browser, ui_height }) => run_app_result(&path, release, native, browser, ui_height),
+        Some(Commands::Dev { path, port, host, log_ipc, open, debug, profile }) => {
+            if log_ipc {
+                poly::router::layer(poly::router::Logging);
+            }
+            run_dev_server(&path, port, &host, open, debug, profile);
+            Ok(())
+        },
+        Some(Commands::Run { path, headless: true, .. }) => run_headless(Path::new(&path)),
+        Some(Commands::Run { path, release, native, browser, ui_height, profile, .. }) => run_app_result(&path, release, native, browser, ui_height, profile.as_deref()),
         Some(Commands::Build { path, target, release, installer, sign, ci }) => { 
             if ci {
                 if let Err(e) = build::generate_ci_workflow(Path::new(&path)) {
@@ -732,6 +860,21 @@ fn main() {
         Some(Commands::Add { package, version }) => packages::add_package(&package, version.as_deref()),
         Some(Commands::Remove { package }) => packages::remove_package(&package),
         Some(Commands::Install { verify }) => packages::install_packages(verify),
+        Some(Commands::Test { path, filter, jobs, junit, json, coverage }) => {
+            run_tests(&path, filter, jobs, junit.as_deref(), json.as_deref(), coverage.as_deref())
+        },
+        Some(Commands::Lsp) => poly::lsp::run_stdio(),
+        Some(Commands::Fmt { paths, check }) => run_fmt(&paths, check),
+        Some(Commands::Lint { path, format, fix }) => run_lint(&path, &format, fix),
+        Some(Commands::Doc { path, out, format, api_md }) => run_doc(&path, &out, &format, api_md.as_deref()),
+        Some(Commands::Gen { target: GenTarget::Bindings { path, out } }) => run_gen_bindings(&path, out.as_deref()),
+        Some(Commands::Debug { file, breakpoints, dap }) => {
+            if dap {
+                poly::debugger::run_dap_stdio(Path::new(&file))
+            } else {
+                poly::debugger::run_terminal(Path::new(&file), &breakpoints)
+            }
+        },
         Some(Commands::OpenUrl { url, title, width, height }) => {
             open_url_window(&url, &title, width, height);
             Ok(())
@@ -779,8 +922,7 @@ fn main() {
             } else if let Some(file) = cli.file {
                 run_file_result(&file)
             } else if cli.repl || atty::is(atty::Stream::Stdin) {
-                run_repl();
-                Ok(())
+                poly::repl::run(VERSION)
             } else {
                 let stdin = io::stdin();
                 let source: String = stdin.lock().lines()
@@ -978,45 +1120,210 @@ fn run_file_result(file: &str) -> Result<(), String> {
     poly::run(&source).map(|_| ())
 }
 
-fn run_repl() {
+fn run_tests(path: &str, filter: Option<String>, jobs: usize, junit: Option<&str>, json: Option<&str>, coverage_dir: Option<&str>) -> Result<(), String> {
     println!();
-    println!("  {}POLY{} v{}", CYAN, RESET, VERSION);
-    println!("  {}Type 'exit' to quit{}", DIM, RESET);
+    println!("  {}POLY{} v{}  {}test{}", CYAN, RESET, VERSION, DIM, RESET);
     println!();
     
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
+    let options = poly::testing::TestOptions { filter, jobs, coverage: coverage_dir.is_some() };
+    let report = poly::testing::run(Path::new(path), &options)?;
     
-    loop {
-        print!("{}>{} ", CYAN, RESET);
-        stdout.flush().unwrap();
-        
-        let mut input = String::new();
-        if stdin.read_line(&mut input).is_err() {
-            break;
+    for (file, results) in report.by_file() {
+        println!("  {}{}{}", BOLD, file, RESET);
+        for result in results {
+            let ms = result.duration.as_millis();
+            match &result.outcome {
+                poly::testing::TestOutcome::Passed => {
+                    println!("    {}pass{} {} {}({}ms){}", GREEN, RESET, result.name, DIM, ms, RESET);
+                }
+                poly::testing::TestOutcome::Failed(message) => {
+                    println!("    {}FAIL{} {} {}({}ms){}", RED, RESET, result.name, DIM, ms, RESET);
+                    for line in message.lines() {
+                        println!("         {}{}{}", RED, line, RESET);
+                    }
+                    for line in &result.output {
+                        println!("         {}| {}{}", DIM, line, RESET);
+                    }
+                }
+            }
+        }
+    }
+    
+    if let Some(file) = junit {
+        fs::write(file, report.to_junit_xml()).map_err(|e| format!("Failed to write {}: {}", file, e))?;
+    }
+    if let Some(file) = json {
+        fs::write(file, report.to_json()).map_err(|e| format!("Failed to write {}: {}", file, e))?;
+    }
+    
+    let color = if report.failed() > 0 { RED } else { GREEN };
+    println!();
+    println!("  {}{} passed{}, {} failed in {}ms", color, report.passed(), RESET, report.failed(), report.duration.as_millis());
+    
+    if report.results.is_empty() {
+        println!("  {}No tests found (looking for test_*.poly files){}", YELLOW, RESET);
+    }
+    
+    let mut below = Vec::new();
+    if let (Some(coverage), Some(dir)) = (&report.coverage, coverage_dir) {
+        println!();
+        for line in coverage.summary().lines() {
+            println!("  {}", line);
         }
         
-        let input = input.trim();
-        if input.is_empty() { continue; }
-        if input == "exit" || input == "quit" { break; }
+        let root = Path::new(path);
+        let project = if root.is_file() { root.parent().unwrap_or(Path::new(".")) } else { root };
+        let source = fs::canonicalize(project).map_or_else(|_| project.display().to_string(), |p| p.display().to_string());
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir, e))?;
+        let lcov = Path::new(dir).join("lcov.info");
+        let cobertura = Path::new(dir).join("cobertura.xml");
+        fs::write(&lcov, coverage.to_lcov()).map_err(|e| format!("Failed to write {}: {}", lcov.display(), e))?;
+        fs::write(&cobertura, coverage.to_cobertura(&source)).map_err(|e| format!("Failed to write {}: {}", cobertura.display(), e))?;
+        println!();
+        println!("  {}Coverage written to {} and {}{}", DIM, lcov.display(), cobertura.display(), RESET);
         
-        match poly::eval(input) {
-            Ok(result) => {
-                if result != "none" {
-                    println!("{}", result);
-                }
+        let config = poly::PolyConfig::load(project);
+        below = coverage.check(&config.coverage);
+        for failure in &below {
+            println!("  {}{}{}", RED, failure, RESET);
+        }
+    }
+    
+    if report.failed() > 0 {
+        return Err(format!("{} test(s) failed", report.failed()));
+    }
+    if !below.is_empty() {
+        return Err(format!("{} coverage threshold(s) not met", below.len()));
+    }
+    Ok(())
+}
+
+fn run_fmt(paths: &[String], check: bool) -> Result<(), String> {
+    if paths.iter().any(|p| p == "-") {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source).map_err(|e| format!("Failed to read stdin: {}", e))?;
+        let formatted = poly::fmt::format(&source)?;
+        if check {
+            return if formatted == source { Ok(()) } else { Err("stdin would be reformatted".to_string()) };
+        }
+        print!("{}", formatted);
+        return Ok(());
+    }
+    
+    let files: Vec<_> = paths.iter().flat_map(|p| poly::fmt::discover(Path::new(p))).collect();
+    let mut changed = 0;
+    let mut errors = 0;
+    for file in &files {
+        match poly::fmt::format_file(file, check) {
+            Ok(true) => {
+                changed += 1;
+                let verb = if check { "would reformat" } else { "formatted" };
+                println!("  {}{}{} {}", YELLOW, verb, RESET, file.display());
+            }
+            Ok(false) => {}
+            Err(e) => {
+                errors += 1;
+                eprintln!("  {}error{} {}: {}", RED, RESET, file.display(), e);
             }
-            Err(_) => {
-                if let Err(e) = poly::run(input) {
-                    eprintln!("{}error{}: {}", RED, RESET, e);
-                }
+        }
+    }
+    
+    let unchanged = files.len() - changed - errors;
+    if check {
+        println!("  {} file(s) would be reformatted, {} already formatted", changed, unchanged);
+    } else {
+        println!("  {} file(s) reformatted, {} unchanged", changed, unchanged);
+    }
+    if errors > 0 {
+        return Err(format!("{} file(s) could not be formatted", errors));
+    }
+    if check && changed > 0 {
+        return Err(format!("{} file(s) need formatting (run `poly fmt`)", changed));
+    }
+    Ok(())
+}
+
+fn run_lint(path: &str, format: &str, fix: bool) -> Result<(), String> {
+    if format != "text" && format != "json" {
+        return Err(format!("Unknown format '{}' (expected text or json)", format));
+    }
+    let reports = poly::lint::lint_path(Path::new(path), fix);
+    let errors = reports.iter().filter(|r| r.error.is_some()).count()
+        + reports.iter().flat_map(|r| &r.findings).filter(|f| f.severity == poly::lint::Severity::Error).count();
+    
+    if format == "json" {
+        println!("{}", poly::lint::to_json(&reports));
+    } else {
+        let mut warnings = 0;
+        let mut fixable = 0;
+        for report in &reports {
+            if let Some(e) = &report.error {
+                eprintln!("{}: {}error{} {}", report.path.display(), RED, RESET, e);
+            }
+            for f in &report.findings {
+                let color = match f.severity {
+                    poly::lint::Severity::Error => RED,
+                    poly::lint::Severity::Warning => { warnings += 1; YELLOW }
+                };
+                if f.fix.is_some() { fixable += 1; }
+                println!("{}:{}:{}: {}{}{}[{}] {}", report.path.display(), f.line, f.column, color, f.severity.as_str(), RESET, f.rule, f.message);
             }
         }
+        let fixed: usize = reports.iter().map(|r| r.fixed).sum();
+        if fixed > 0 {
+            println!("  {}fixed{} {} issue(s)", GREEN, RESET, fixed);
+        }
+        println!("  {} error(s), {} warning(s) in {} file(s)", errors, warnings, reports.len());
+        if fixable > 0 {
+            println!("  {}{} issue(s) can be fixed with `poly lint --fix`{}", DIM, fixable, RESET);
+        }
+    }
+    
+    if errors > 0 {
+        return Err(format!("{} lint error(s)", errors));
     }
+    Ok(())
 }
 
+fn run_gen_bindings(path: &str, out: Option<&str>) -> Result<(), String> {
+    let project = Path::new(path);
+    let entry = find_entry_point(project).ok_or("No entry point found. Create main.poly or src/main.poly")?;
+    let out = out.map_or_else(|| project.join("web"), std::path::PathBuf::from);
+    let written = poly::bindings::write(&entry, &out)?;
+    println!("  {}generated{} {} in {} ({} changed)", GREEN, RESET, poly::bindings::FILE_STEM, out.display(), written.len());
+    Ok(())
+}
+
+fn run_doc(path: &str, out: &str, format: &str, api_md: Option<&str>) -> Result<(), String> {
+    if let Some(file) = api_md {
+        let existing = fs::read_to_string(file).unwrap_or_default();
+        fs::write(file, poly::doc::update_markdown(&existing)).map_err(|e| format!("Cannot write {}: {}", file, e))?;
+        println!("  {}updated{} system API reference in {}", GREEN, RESET, file);
+        return Ok(());
+    }
+    if format != "html" && format != "json" {
+        return Err(format!("Unknown format '{}' (expected html or json)", format));
+    }
+    
+    let (modules, errors) = poly::doc::collect(Path::new(path));
+    for e in &errors {
+        eprintln!("{}error{}: {}", RED, RESET, e);
+    }
+    if format == "json" {
+        println!("{}", poly::doc::to_json(&modules));
+    } else {
+        let pages = poly::doc::write_site(&modules, Path::new(out))?;
+        println!("  {}documented{} {} module(s) in {} page(s)", GREEN, RESET, modules.len(), pages.len());
+        println!("  {}>{} Open:    {}{}{}", DIM, RESET, CYAN, Path::new(out).join("index.html").display(), RESET);
+    }
+    
+    if !errors.is_empty() {
+        return Err(format!("{} file(s) could not be parsed", errors.len()));
+    }
+    Ok(())
+}
 
-fn run_dev_server(path: &str, port: u16, open_browser: bool) {
+fn run_dev_server(path: &str, port: u16, host: &str, open_browser: bool, debug: Option<u16>, profile: Option<String>) {
     use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
     use std::thread;
     
@@ -1048,7 +1355,30 @@ fn run_dev_server(path: &str, port: u16, open_browser: bool) {
     println!("  {}POLY{} v{}  {}dev server{}", CYAN, RESET, VERSION, DIM, RESET);
     println!();
     println!("  {}>{} Local:   {}http://localhost:{}{}", GREEN, RESET, CYAN, port, RESET);
+    if !matches!(host, "127.0.0.1" | "localhost" | "::1") {
+        println!("  {}>{} Network: {}http://{}:{}{} {}(IPC still needs the page's session token){}", YELLOW, RESET, CYAN, host, port, RESET, DIM, RESET);
+    }
     println!("  {}>{} Entry:   {}{}{}", DIM, RESET, DIM, entry.display(), RESET);
+    
+    // Debugger (DAP) server that VS Code can attach to
+    let debug_server = debug.and_then(|debug_port| match poly::debugger::DebugServer::listen(debug_port, &entry) {
+        Ok(server) => {
+            println!("  {}>{} Debug:   {}127.0.0.1:{}{} (attach with the Poly debugger)", DIM, RESET, CYAN, debug_port, RESET);
+            Some(server)
+        }
+        Err(e) => {
+            eprintln!("  {}error{}: {}", RED, RESET, e);
+            None
+        }
+    });
+    if let Some(stem) = &profile {
+        println!("  {}>{} Profile: {}{}.svg{} (updated as IPC commands run)", DIM, RESET, CYAN, stem, RESET);
+    }
+    let load = |interp: &mut poly::interpreter::Interpreter, source: &str| match &debug_server {
+        Some(server) => server.load(interp, source),
+        None if profile.is_some() => poly::profiler::run(interp, source).map(|_| ()),
+        None => poly::init_interpreter(interp, source),
+    };
     println!();
     
     let reload_counter = Arc::new(AtomicU64::new(0));
@@ -1059,40 +1389,81 @@ fn run_dev_server(path: &str, port: u16, open_browser: bool) {
     // Create persistent interpreter wrapped in Arc<Mutex>
     use std::sync::Mutex;
     let interpreter = Arc::new(Mutex::new(poly::create_interpreter()));
-    let interpreter_http = Arc::clone(&interpreter);
     
     // Initialize interpreter with source
+    let source = fs::read_to_string(&entry).unwrap_or_default();
     {
-        let source = fs::read_to_string(&entry).unwrap_or_default();
         let mut interp = interpreter.lock().unwrap();
-        if let Err(e) = poly::init_interpreter(&mut interp, &source) {
+        if profile.is_some() {
+            interp.set_profiler(Some(poly::profiler::Profiler::new()));
+        }
+        if let Err(e) = load(&mut interp, &source) {
             eprintln!("{}error{}: Failed to initialize interpreter: {}", RED, RESET, e);
         }
     }
     
-    // Channel for reloading interpreter
-    let (_reload_tx, reload_rx) = std::sync::mpsc::channel::<String>();
-    let interpreter_reload = Arc::clone(&interpreter);
-    
-    // Interpreter reload thread
-    std::thread::spawn(move || {
-        for source in reload_rx {
-            let mut interp = interpreter_reload.lock().unwrap();
-            // Reset interpreter and reinitialize
-            *interp = poly::create_interpreter();
-            if let Err(e) = poly::init_interpreter(&mut interp, &source) {
-                eprintln!("{}error{}: Reload failed: {}", RED, RESET, e);
-            }
-        }
-    });
+    // The debugger and profiler follow a single interpreter
+    let workers = if debug_server.is_some() || profile.is_some() { 1 } else { config.ipc.workers };
+    let pool = start_ipc_pool(&interpreter, &source, workers, config.ipc.queue_limit);
+    let pool_http = Arc::clone(&pool);
+    spawn_event_dispatcher(Arc::clone(&interpreter));
+    update_dev_bindings(project_path, &entry);
+    print_routes();
+    
+    // Last backend reload error, shown as an overlay in the browser
+    let reload_error: Arc<Mutex<Option<serde_json::Value>>> = Arc::new(Mutex::new(None));
+    let reload_error_http = Arc::clone(&reload_error);
+    let preserve_state = config.dev.preserve_state.clone();
+    
+    // Only the page served by this session may use the server
+    let guard = poly::dev_security::DevGuard::new(host, port);
+    let bind = if host.contains(':') { format!("[{}]:{}", host, port) } else { format!("{}:{}", host, port) };
     
     // HTTP server thread
     thread::spawn(move || {
-        let server = tiny_http::Server::http(format!("0.0.0.0:{}", port))
+        let server = tiny_http::Server::http(&bind)
             .expect("Failed to start HTTP server");
+        let web_root = project_path_http.join("web");
+        let packages_root = project_path_http.join("packages");
         
         for mut request in server.incoming_requests() {
             let url = request.url().to_string();
+            let path = url.split('?').next().unwrap_or("");
+            let needs_token = matches!(path, "/__poly_invoke" | "/__poly_emit" | "/__poly_events" | "/__poly_run") || poly::blob::handles(&url);
+            if let Err(e) = guard.check(&request, needs_token) {
+                let _ = request.respond(poly::dev_security::forbidden(&e));
+                continue;
+            }
+            // Binary transfers stream on their own thread
+            if poly::blob::handles(&url) {
+                std::thread::spawn(move || poly::blob::serve(request));
+                continue;
+            }
+            // Event stream for poly.on(), held open on its own thread
+            if let Some(query) = url.strip_prefix("/__poly_events") {
+                let window = event_window(query);
+                thread::spawn(move || poly::events::serve_sse(request, &window));
+                continue;
+            }
+            // IPC Bridge - call Poly functions from JavaScript, each call on
+            // its own thread so a slow one does not hold up the page
+            if path == "/__poly_invoke" {
+                let pool = Arc::clone(&pool_http);
+                thread::spawn(move || {
+                    let mut body = String::new();
+                    request.as_reader().read_to_string(&mut body).ok();
+                    let result = handle_ipc_invoke_stateful(&pool, &body);
+                    let _ = request.respond(tiny_http::Response::from_string(result)
+                        .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap()));
+                });
+                continue;
+            }
+            // Routes declared with @get, @post, ... run on the workers too
+            if poly::routes::matches(path) {
+                let pool = Arc::clone(&pool_http);
+                thread::spawn(move || poly::routes::serve(&pool, request));
+                continue;
+            }
             let response = match url.as_str() {
                 "/" | "/index.html" => {
                     // Check for custom index.html in web folder
@@ -1115,151 +1486,557 @@ fn run_dev_server(path: &str, port: u16, open_browser: bool) {
                         }
                         
                         // Inject hot reload script, IPC bridge, and icon initialization
-                        let reload_script = format!(r#"<script>
-// Poly Dialog System - Custom In-App Dialogs
-(function() {{
-  const style = document.createElement('style');
-  style.textContent = `
-    .poly-dialog-overlay {{
-      position: fixed; inset: 0; background: rgba(0,0,0,0.6); backdrop-filter: blur(4px);
-      display: flex; align-items: center; justify-content: center; z-index: 99999;
-      opacity: 0; transition: opacity 0.15s ease;
-    }}
-    .poly-dialog-overlay.show {{ opacity: 1; }}
-    .poly-dialog {{
-      background: #1a1a1f; border: 1px solid rgba(255,255,255,0.1); border-radius: 12px;
-      padding: 1.5rem; min-width: 320px; max-width: 90vw; box-shadow: 0 25px 50px rgba(0,0,0,0.5);
-      transform: scale(0.95) translateY(-10px); transition: transform 0.15s ease;
-    }}
-    .poly-dialog-overlay.show .poly-dialog {{ transform: scale(1) translateY(0); }}
-    .poly-dialog-icon {{ width: 48px; height: 48px; border-radius: 50%; display: flex; align-items: center; justify-content: center; margin: 0 auto 1rem; }}
-    .poly-dialog-icon.info {{ background: rgba(59,130,246,0.2); color: #3b82f6; }}
-    .poly-dialog-icon.warning {{ background: rgba(245,158,11,0.2); color: #f59e0b; }}
-    .poly-dialog-icon.error {{ background: rgba(239,68,68,0.2); color: #ef4444; }}
-    .poly-dialog-icon.confirm {{ background: rgba(93,193,210,0.2); color: #5dc1d2; }}
-    .poly-dialog-icon svg {{ width: 24px; height: 24px; }}
-    .poly-dialog-title {{ font-size: 1.1rem; font-weight: 600; color: #fff; text-align: center; margin-bottom: 0.5rem; }}
-    .poly-dialog-message {{ color: #888; text-align: center; font-size: 0.9rem; line-height: 1.5; margin-bottom: 1.5rem; }}
-    .poly-dialog-buttons {{ display: flex; gap: 0.75rem; justify-content: center; }}
-    .poly-dialog-btn {{
-      padding: 0.6rem 1.25rem; border-radius: 8px; font-size: 0.85rem; font-weight: 500;
-      cursor: pointer; border: none; transition: all 0.15s;
-    }}
-    .poly-dialog-btn-primary {{ background: linear-gradient(135deg, #5dc1d2, #1e80ad); color: #fff; }}
-    .poly-dialog-btn-primary:hover {{ transform: translateY(-1px); box-shadow: 0 4px 12px rgba(93,193,210,0.3); }}
-    .poly-dialog-btn-secondary {{ background: rgba(255,255,255,0.1); color: #888; }}
-    .poly-dialog-btn-secondary:hover {{ background: rgba(255,255,255,0.15); color: #fff; }}
-  `;
-  document.head.appendChild(style);
-
-  const icons = {{
-    info: '<svg viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2"><circle cx="12" cy="12" r="10"/><path d="M12 16v-4M12 8h.01"/></svg>',
-    warning: '<svg viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2"><path d="M10.29 3.86L1.82 18a2 2 0 001.71 3h16.94a2 2 0 001.71-3L13.71 3.86a2 2 0 00-3.42 0zM12 9v4M12 17h.01"/></svg>',
-    error: '<svg viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2"><circle cx="12" cy="12" r="10"/><path d="M15 9l-6 6M9 9l6 6"/></svg>',
-    confirm: '<svg viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2"><circle cx="12" cy="12" r="10"/><path d="M9.09 9a3 3 0 015.83 1c0 2-3 3-3 3M12 17h.01"/></svg>'
-  }};
-
-  window.__polyDialog = {{
-    show(type, title, message, buttons) {{
-      return new Promise(resolve => {{
-        const overlay = document.createElement('div');
-        overlay.className = 'poly-dialog-overlay';
-        overlay.innerHTML = `
-          <div class="poly-dialog">
-            <div class="poly-dialog-icon ${{type}}">${{icons[type] || icons.info}}</div>
-            <div class="poly-dialog-title">${{title}}</div>
-            <div class="poly-dialog-message">${{message}}</div>
-            <div class="poly-dialog-buttons"></div>
-          </div>
-        `;
-        const btnContainer = overlay.querySelector('.poly-dialog-buttons');
-        buttons.forEach((btn, i) => {{
-          const el = document.createElement('button');
-          el.className = `poly-dialog-btn ${{btn.primary ? 'poly-dialog-btn-primary' : 'poly-dialog-btn-secondary'}}`;
-          el.textContent = btn.text;
-          el.onclick = () => {{ overlay.classList.remove('show'); setTimeout(() => overlay.remove(), 150); resolve(btn.value); }};
-          btnContainer.appendChild(el);
-        }});
-        document.body.appendChild(overlay);
-        requestAnimationFrame(() => overlay.classList.add('show'));
-        overlay.addEventListener('click', e => {{ if (e.target === overlay) {{ overlay.classList.remove('show'); setTimeout(() => overlay.remove(), 150); resolve(null); }} }});
-      }});
-    }}
-  }};
-}})();
-
-// Poly IPC Bridge
-window.poly = {{
-  async invoke(fn, args = {{}}) {{
-    const r = await fetch('/__poly_invoke', {{
-      method: 'POST',
-      headers: {{ 'Content-Type': 'application/json' }},
-      body: JSON.stringify({{ fn, args }})
-    }});
-    const d = await r.json();
-    if (d.error) throw new Error(d.error);
-    return d.result;
-  }},
-  dialog: {{
-    async open(options = {{}}) {{ return poly.invoke('__poly_dialog_open', options); }},
-    async openMultiple(options = {{}}) {{ return poly.invoke('__poly_dialog_open_multiple', options); }},
-    async save(options = {{}}) {{ return poly.invoke('__poly_dialog_save', options); }},
-    async folder(options = {{}}) {{ return poly.invoke('__poly_dialog_folder', options); }},
-    async message(title, message, level = 'info') {{
-      return __polyDialog.show(level, title, message, [{{ text: 'OK', value: true, primary: true }}]);
-    }},
-    async confirm(title, message) {{
-      return __polyDialog.show('confirm', title, message, [
-        {{ text: 'Cancel', value: false }},
-        {{ text: 'Confirm', value: true, primary: true }}
-      ]);
-    }},
-    async custom(options) {{
-      return __polyDialog.show(options.type || 'info', options.title, options.message, options.buttons || [{{ text: 'OK', value: true, primary: true }}]);
+                        let reload_script = dev_bridge_script(reload_counter_http.load(Ordering::Relaxed), reload_interval, &guard.token_script());
+                        // Insert before </body> or at end
+                        if html.contains("</body>") {
+                            html = html.replace("</body>", &format!("{}</body>", reload_script));
+                        } else {
+                            html.push_str(&reload_script);
+                        }
+                        tiny_http::Response::from_string(html)
+                            .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..]).unwrap())
+                    } else {
+                        let html = generate_dev_html(&project_path_http, &entry_http, reload_counter_http.load(Ordering::Relaxed), guard.token());
+                        tiny_http::Response::from_string(html)
+                            .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..]).unwrap())
+                    }
+                }
+                "/__poly_reload" => {
+                    let current = reload_counter_http.load(Ordering::Relaxed);
+                    let error = reload_error_http.lock().unwrap().clone();
+                    tiny_http::Response::from_string(serde_json::json!({"version": current, "error": error}).to_string())
+                        .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap())
+                }
+                "/__poly_emit" => {
+                    // Events from poly.emit() for the backend's on() handlers
+                    let mut body = String::new();
+                    request.as_reader().read_to_string(&mut body).ok();
+                    handle_emit(&body)
+                }
+                "/__poly_run" => {
+                    let output = execute_poly_for_web(&entry_http);
+                    tiny_http::Response::from_string(output)
+                        .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap())
+                }
+                _ => {
+                    // Static files, confined to web/ and packages/
+                    let actual_path = poly::dev_security::static_path(&web_root, &packages_root, &url);
+                    
+                    if let Some(path) = actual_path {
+                        // Use read for binary files, read_to_string for text
+                        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+                        let is_binary = matches!(ext, "png" | "jpg" | "jpeg" | "gif" | "ico" | "woff" | "woff2" | "ttf" | "eot");
+                        
+                        if is_binary {
+                            match fs::read(&path) {
+                                Ok(content) => {
+                                    let ct = match ext {
+                                        "png" => "image/png",
+                                        "jpg" | "jpeg" => "image/jpeg",
+                                        "gif" => "image/gif",
+                                        "ico" => "image/x-icon",
+                                        "woff" => "font/woff",
+                                        "woff2" => "font/woff2",
+                                        "ttf" => "font/ttf",
+                                        "eot" => "application/vnd.ms-fontobject",
+                                        _ => "application/octet-stream",
+                                    };
+                                    tiny_http::Response::from_data(content)
+                                        .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], ct.as_bytes()).unwrap())
+                                }
+                                Err(_) => tiny_http::Response::from_string("Read Error").with_status_code(500)
+                            }
+                        } else {
+                            let content = fs::read_to_string(&path).unwrap_or_default();
+                            let ct = match ext {
+                                "html" => "text/html; charset=utf-8",
+                                "css" => "text/css; charset=utf-8",
+                                "js" => "application/javascript; charset=utf-8",
+                                "json" => "application/json",
+                                "svg" => "image/svg+xml",
+                                _ => "text/plain",
+                            };
+                            tiny_http::Response::from_string(content)
+                                .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], ct.as_bytes()).unwrap())
+                        }
+                    } else {
+                        tiny_http::Response::from_string("Not Found").with_status_code(404)
+                    }
+                }
+            };
+            let _ = request.respond(response);
+        }
+    });
+    
+    // File watcher
+    let (tx, rx) = channel();
+    let mut watcher = notify::recommended_watcher(move |res| { let _ = tx.send(res); })
+        .expect("Failed to create file watcher");
+    watcher.watch(project_path, RecursiveMode::Recursive).expect("Failed to watch directory");
+    
+    // Initial build
+    print!("  {}Building...{}", DIM, RESET);
+    io::stdout().flush().unwrap();
+    let start = std::time::Instant::now();
+    let _success = poly::run(&fs::read_to_string(&entry).unwrap_or_default()).is_ok();
+    println!("\r  {}ready{} in {}{}ms{}                    ", GREEN, RESET, BOLD, start.elapsed().as_millis(), RESET);
+    
+    if open_browser {
+        let _ = open_in_browser(&format!("http://localhost:{}", port));
+    }
+    
+    println!();
+    println!("  {}press h to show help{}", DIM, RESET);
+    println!();
+    
+    // Watch loop
+    let mut profile_written = std::time::Instant::now();
+    loop {
+        if let Some(stem) = profile.as_deref().filter(|_| profile_written.elapsed() >= Duration::from_secs(1)) {
+            profile_written = std::time::Instant::now();
+            let mut interp = interpreter.lock().unwrap();
+            if let Some(profiler) = interp.profiler_mut() {
+                if profiler.take_dirty() {
+                    if let Err(e) = profiler.write(Path::new(stem)) {
+                        eprintln!("{}error{}: {}", RED, RESET, e);
+                    }
+                }
+            }
+        }
+        match rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(Ok(event)) => {
+                if should_reload(&event) {
+                    let changed: Vec<_> = event.paths.iter()
+                        .filter_map(|p| p.file_name())
+                        .filter_map(|n| n.to_str())
+                        .collect();
+                    
+                    let time = chrono_time();
+                    print!("{}{}{}  {}hmr update{} {}", DIM, time, RESET, YELLOW, RESET, changed.join(", "));
+                    io::stdout().flush().unwrap();
+                    
+                    std::thread::sleep(Duration::from_millis(50));
+                    
+                    // Only reload Poly interpreter for .poly file changes
+                    let poly_changes: Vec<_> = event.paths.iter()
+                        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("poly"))
+                        .cloned()
+                        .collect();
+                    
+                    if poly_changes.is_empty() {
+                        // For HTML/CSS/JS, just signal reload (no Poly execution needed)
+                        reload_counter.fetch_add(1, Ordering::Relaxed);
+                        println!();
+                        continue;
+                    }
+                    
+                    let start = std::time::Instant::now();
+                    let mut interp = interpreter.lock().unwrap();
+                    let result = if debug_server.is_some() {
+                        // The debugger tracks the program it loaded, so rebuild from the entry
+                        fs::read_to_string(&entry).map_err(|e| e.to_string())
+                            .and_then(|source| poly::parser::Parser::new(poly::lexer::Lexer::new(&source).tokenize()).parse().map(|_| source))
+                            .map_err(|message| poly::hot_reload::ReloadError { file: entry.clone(), message })
+                            .and_then(|source| {
+                                let profiler = interp.take_profiler();
+                                *interp = poly::create_interpreter();
+                                interp.set_profiler(profiler);
+                                poly::routes::clear();
+                                load(&mut interp, &source)
+                                    .map(|_| vec![entry.clone()])
+                                    .map_err(|message| poly::hot_reload::ReloadError { file: entry.clone(), message })
+                            })
+                    } else {
+                        poly::hot_reload::reload(&mut interp, &entry, &poly_changes, &preserve_state)
+                    };
+                    drop(interp);
+                    if result.is_ok() {
+                        reload_workers(&pool, &entry, &poly_changes, &preserve_state);
+                    }
+                    match result {
+                        Ok(_) => {
+                            *reload_error.lock().unwrap() = None;
+                            reload_counter.fetch_add(1, Ordering::Relaxed);
+                            println!(" {}({}ms){}", DIM, start.elapsed().as_millis(), RESET);
+                            update_dev_bindings(&project_path_owned, &entry);
+                        }
+                        Err(e) => {
+                            // Keep the page and the previous backend; the page shows the error
+                            println!("\n  {}error{}: {}: {}", RED, RESET, e.file.display(), e.message);
+                            *reload_error.lock().unwrap() = Some(e.to_json());
+                        }
+                    }
+                }
+            }
+            Ok(Err(e)) => eprintln!("{}error{}: {:?}", RED, RESET, e),
+            Err(_) => {}
+        }
+    }
+}
+
+/// Keep web/poly-bindings.{js,d.ts} in step with the entry module
+fn update_dev_bindings(project_path: &Path, entry: &Path) {
+    let web = project_path.join("web");
+    if !web.is_dir() {
+        return;
+    }
+    match poly::bindings::write(entry, &web) {
+        Ok(written) if !written.is_empty() => println!("  {}>{} Bindings: {}{}{}", DIM, RESET, DIM, web.join(poly::bindings::FILE_STEM).display(), RESET),
+        Ok(_) => {}
+        Err(e) => eprintln!("  {}error{}: bindings: {}", RED, RESET, e),
+    }
+}
+
+fn chrono_time() -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let hours = (secs / 3600) % 24;
+    let mins = (secs / 60) % 60;
+    let secs = secs % 60;
+    format!("{:02}:{:02}:{:02}", hours, mins, secs)
+}
+
+fn execute_poly_for_web(entry: &Path) -> String {
+    match fs::read_to_string(entry) {
+        Ok(source) => match poly::run(&source) {
+            Ok(output) => serde_json::json!({"success": true, "output": output}).to_string(),
+            Err(e) => serde_json::json!({"success": false, "error": e}).to_string(),
+        },
+        Err(e) => serde_json::json!({"success": false, "error": format!("Failed to read: {}", e)}).to_string(),
+    }
+}
+
+/// Start the IPC worker pool: `primary` plus `workers - 1` interpreters
+/// that run `source` themselves, without echoing its output again
+fn start_ipc_pool(primary: &std::sync::Arc<Mutex<poly::interpreter::Interpreter>>, source: &str, workers: usize, queue_limit: usize) -> std::sync::Arc<poly::workers::Pool> {
+    use std::sync::Arc;
+    let mut interpreters = vec![Arc::clone(primary)];
+    for _ in 1..workers {
+        let mut interp = poly::create_interpreter();
+        interp.set_echo_output(false);
+        // Every worker runs the same source, so the rest would fail the same way
+        if let Err(e) = poly::init_interpreter(&mut interp, source) {
+            eprintln!("  {}error{}: Failed to start an IPC worker, running {} of {}: {}", RED, RESET, interpreters.len(), workers, e);
+            break;
+        }
+        interp.set_echo_output(true);
+        interpreters.push(Arc::new(Mutex::new(interp)));
+    }
+    let pool = Arc::new(poly::workers::Pool::new(interpreters, queue_limit));
+    poly::workers::install(Arc::clone(&pool));
+    pool
+}
+
+/// Apply a reload that succeeded on the first worker to the others, whose
+/// top-level output was already shown
+fn reload_workers(pool: &poly::workers::Pool, entry: &Path, changed: &[std::path::PathBuf], preserve: &str) {
+    for worker in &pool.workers()[1..] {
+        let mut interp = worker.lock().unwrap();
+        interp.set_echo_output(false);
+        if let Err(e) = poly::hot_reload::reload(&mut interp, entry, changed, preserve) {
+            eprintln!("  {}error{}: Reload failed in an IPC worker: {}: {}", RED, RESET, e.file.display(), e.message);
+        }
+        interp.set_echo_output(true);
+    }
+}
+
+/// Handle IPC invoke on the worker pool of persistent (stateful) interpreters
+fn handle_ipc_invoke_stateful(pool: &poly::workers::Pool, body: &str) -> String {
+    // Parse the request: { "fn": "function_name", "args": [...] | { ... }, "kwargs": { ... } }
+    let request = match poly::ipc::parse_request(body) {
+        Ok(request) => request,
+        Err(e) => return poly::ipc::reply(Err(e)),
+    };
+    
+    // System APIs and other commands implemented in Rust
+    if let Some(result) = dispatch_command(&request) {
+        return poly::ipc::reply(result);
+    }
+    
+    // Call function on a persistent interpreter
+    poly::ipc::reply(pool.call(request))
+}
+
+/// Run a request on the IPC router if a command (or a `__poly_*` name) matches
+fn dispatch_command(request: &poly::ipc::Request) -> Option<Result<serde_json::Value, poly::ipc::Error>> {
+    if !request.function.starts_with("__poly_") && !poly::router::contains(&request.function) {
+        return None;
+    }
+    Some(request.command_args().and_then(|args| poly::router::dispatch(&request.function, args)))
+}
+
+/// Handle IPC when there's no Poly backend (system APIs only)
+fn handle_ipc_invoke_system_only(body: &str) -> String {
+    let request = match poly::ipc::parse_request(body) {
+        Ok(request) => request,
+        Err(e) => return poly::ipc::reply(Err(e)),
+    };
+    
+    if let Some(result) = dispatch_command(&request) {
+        poly::ipc::reply(result)
+    } else {
+        poly::ipc::reply(Err(poly::ipc::Error::new("NOT_FOUND", "No Poly backend available")))
+    }
+}
+
+/// Window label of an `/__poly_events?window=...` request
+fn event_window(query: &str) -> String {
+    query.strip_prefix('?').unwrap_or("").split('&')
+        .find_map(|pair| pair.strip_prefix("window="))
+        .and_then(|window| urlencoding::decode(window).ok())
+        .map(|window| window.to_string())
+        .filter(|window| !window.is_empty())
+        .unwrap_or_else(|| "main".to_string())
+}
+
+/// Queue an event from `poly.emit()` for the interpreter
+fn handle_emit(body: &str) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let (status, reply) = match poly::events::parse_emit(body) {
+        Ok(event) => match poly::events::send_to_backend(&event.topic, event.payload) {
+            Ok(()) => (200, serde_json::json!({"success": true})),
+            Err(e) => (429, serde_json::json!({"error": e})),
+        },
+        Err(e) => (400, serde_json::json!({"error": e})),
+    };
+    tiny_http::Response::from_string(reply.to_string())
+        .with_status_code(status)
+        .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap())
+}
+
+/// Run the interpreter's `on()` handlers for events from pages as they arrive
+fn spawn_event_dispatcher(interpreter: std::sync::Arc<Mutex<poly::interpreter::Interpreter>>) {
+    std::thread::spawn(move || loop {
+        for event in poly::events::wait_backend(Duration::from_secs(1)) {
+            let mut interp = interpreter.lock().unwrap();
+            if let Err(e) = interp.dispatch_event(&event) {
+                eprintln!("{}error{}: '{}' event handler failed: {}", RED, RESET, event.topic, e);
+            }
+        }
+    });
+}
+
+/// Script injected into a project's own web/index.html: in-app dialogs, the
+/// `window.poly` bridge, the event stream and hot reload
+fn dev_bridge_script(version: u64, reload_interval: u32, token_script: &str) -> String {
+    format!(r#"{token_script}<script>
+// Poly Dialog System - Custom In-App Dialogs
+(function() {{
+  const style = document.createElement('style');
+  style.textContent = `
+    .poly-dialog-overlay {{
+      position: fixed; inset: 0; background: rgba(0,0,0,0.6); backdrop-filter: blur(4px);
+      display: flex; align-items: center; justify-content: center; z-index: 99999;
+      opacity: 0; transition: opacity 0.15s ease;
     }}
-  }},
-  fs: {{
-    async read(path) {{ return poly.invoke('__poly_fs_read', {{ path }}); }},
-    async write(path, content) {{ return poly.invoke('__poly_fs_write', {{ path, content }}); }},
-    async exists(path) {{ return poly.invoke('__poly_fs_exists', {{ path }}); }},
-    async readDir(path) {{ return poly.invoke('__poly_fs_read_dir', {{ path }}); }}
-  }},
-  updater: {{
-    async checkGithub(repo, currentVersion) {{ return poly.invoke('__poly_updater_check_github', {{ repo, currentVersion }}); }},
-    async checkUrl(url, currentVersion) {{ return poly.invoke('__poly_updater_check_url', {{ url, currentVersion }}); }},
-    async download(url) {{ return poly.invoke('__poly_updater_download', {{ url }}); }},
-    async install(path) {{ return poly.invoke('__poly_updater_install', {{ path }}); }},
-    async checkAndPrompt(options) {{
-      const info = options.repo 
-        ? await poly.updater.checkGithub(options.repo, options.currentVersion)
-        : await poly.updater.checkUrl(options.url, options.currentVersion);
-      if (info.update_available) {{
-        const confirmed = await poly.dialog.confirm('Update Available', 
-          `Version ${{info.latest_version}} is available. Download now?`);
-        if (confirmed && info.download_url) {{
-          const path = await poly.updater.download(info.download_url);
-          await poly.updater.install(path);
-        }}
-      }}
-      return info;
+    .poly-dialog-overlay.show {{ opacity: 1; }}
+    .poly-dialog {{
+      background: #1a1a1f; border: 1px solid rgba(255,255,255,0.1); border-radius: 12px;
+      padding: 1.5rem; min-width: 320px; max-width: 90vw; box-shadow: 0 25px 50px rgba(0,0,0,0.5);
+      transform: scale(0.95) translateY(-10px); transition: transform 0.15s ease;
     }}
-  }},
-  window: {{
-    minimize() {{ if (window.ipc) window.ipc.postMessage('minimize'); }},
-    maximize() {{ if (window.ipc) window.ipc.postMessage('maximize'); }},
-    close() {{ if (window.ipc) window.ipc.postMessage('close'); }},
-    hide() {{ if (window.ipc) window.ipc.postMessage('hide'); }},
-    show() {{ if (window.ipc) window.ipc.postMessage('show'); }},
-    async setTitle(title) {{ return poly.invoke('__poly_window_set_title', {{ title }}); }},
-    async getTitle() {{ return poly.invoke('__poly_window_get_title', {{}}); }},
-    async center() {{ return poly.invoke('__poly_window_center', {{}}); }},
-    async setSize(width, height) {{ return poly.invoke('__poly_window_set_size', {{ width, height }}); }},
-    async getSize() {{ return poly.invoke('__poly_window_get_size', {{}}); }},
-    async setPosition(x, y) {{ return poly.invoke('__poly_window_set_position', {{ x, y }}); }},
-    async getPosition() {{ return poly.invoke('__poly_window_get_position', {{}}); }},
-    async setMinSize(width, height) {{ return poly.invoke('__poly_window_set_min_size', {{ width, height }}); }},
-    async setMaxSize(width, height) {{ return poly.invoke('__poly_window_set_max_size', {{ width, height }}); }},
-    async setAlwaysOnTop(value) {{ return poly.invoke('__poly_window_set_always_on_top', {{ value }}); }},
+    .poly-dialog-overlay.show .poly-dialog {{ transform: scale(1) translateY(0); }}
+    .poly-dialog-icon {{ width: 48px; height: 48px; border-radius: 50%; display: flex; align-items: center; justify-content: center; margin: 0 auto 1rem; }}
+    .poly-dialog-icon.info {{ background: rgba(59,130,246,0.2); color: #3b82f6; }}
+    .poly-dialog-icon.warning {{ background: rgba(245,158,11,0.2); color: #f59e0b; }}
+    .poly-dialog-icon.error {{ background: rgba(239,68,68,0.2); color: #ef4444; }}
+    .poly-dialog-icon.confirm {{ background: rgba(93,193,210,0.2); color: #5dc1d2; }}
+    .poly-dialog-icon svg {{ width: 24px; height: 24px; }}
+    .poly-dialog-title {{ font-size: 1.1rem; font-weight: 600; color: #fff; text-align: center; margin-bottom: 0.5rem; }}
+    .poly-dialog-message {{ color: #888; text-align: center; font-size: 0.9rem; line-height: 1.5; margin-bottom: 1.5rem; }}
+    .poly-dialog-buttons {{ display: flex; gap: 0.75rem; justify-content: center; }}
+    .poly-dialog-btn {{
+      padding: 0.6rem 1.25rem; border-radius: 8px; font-size: 0.85rem; font-weight: 500;
+      cursor: pointer; border: none; transition: all 0.15s;
+    }}
+    .poly-dialog-btn-primary {{ background: linear-gradient(135deg, #5dc1d2, #1e80ad); color: #fff; }}
+    .poly-dialog-btn-primary:hover {{ transform: translateY(-1px); box-shadow: 0 4px 12px rgba(93,193,210,0.3); }}
+    .poly-dialog-btn-secondary {{ background: rgba(255,255,255,0.1); color: #888; }}
+    .poly-dialog-btn-secondary:hover {{ background: rgba(255,255,255,0.15); color: #fff; }}
+  `;
+  document.head.appendChild(style);
+
+  const icons = {{
+    info: '<svg viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2"><circle cx="12" cy="12" r="10"/><path d="M12 16v-4M12 8h.01"/></svg>',
+    warning: '<svg viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2"><path d="M10.29 3.86L1.82 18a2 2 0 001.71 3h16.94a2 2 0 001.71-3L13.71 3.86a2 2 0 00-3.42 0zM12 9v4M12 17h.01"/></svg>',
+    error: '<svg viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2"><circle cx="12" cy="12" r="10"/><path d="M15 9l-6 6M9 9l6 6"/></svg>',
+    confirm: '<svg viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2"><circle cx="12" cy="12" r="10"/><path d="M9.09 9a3 3 0 015.83 1c0 2-3 3-3 3M12 17h.01"/></svg>'
+  }};
+
+  window.__polyDialog = {{
+    show(type, title, message, buttons) {{
+      return new Promise(resolve => {{
+        const overlay = document.createElement('div');
+        overlay.className = 'poly-dialog-overlay';
+        overlay.innerHTML = `
+          <div class="poly-dialog">
+            <div class="poly-dialog-icon ${{type}}">${{icons[type] || icons.info}}</div>
+            <div class="poly-dialog-title">${{title}}</div>
+            <div class="poly-dialog-message">${{message}}</div>
+            <div class="poly-dialog-buttons"></div>
+          </div>
+        `;
+        const btnContainer = overlay.querySelector('.poly-dialog-buttons');
+        buttons.forEach((btn, i) => {{
+          const el = document.createElement('button');
+          el.className = `poly-dialog-btn ${{btn.primary ? 'poly-dialog-btn-primary' : 'poly-dialog-btn-secondary'}}`;
+          el.textContent = btn.text;
+          el.onclick = () => {{ overlay.classList.remove('show'); setTimeout(() => overlay.remove(), 150); resolve(btn.value); }};
+          btnContainer.appendChild(el);
+        }});
+        document.body.appendChild(overlay);
+        requestAnimationFrame(() => overlay.classList.add('show'));
+        overlay.addEventListener('click', e => {{ if (e.target === overlay) {{ overlay.classList.remove('show'); setTimeout(() => overlay.remove(), 150); resolve(null); }} }});
+      }});
+    }}
+  }};
+}})();
+
+// Poly IPC Bridge
+window.poly = {{
+  async invoke(fn, args = {{}}, {{ signal, priority }} = {{}}) {{
+    // Aborting the signal cancels the call in the backend too
+    const id = signal ? `${{Date.now().toString(36)}}-${{Math.random().toString(36).slice(2)}}` : undefined;
+    const cancel = () => poly.invoke('__poly_ipc_cancel', {{ id }}).catch(() => {{}});
+    signal?.addEventListener('abort', cancel, {{ once: true }});
+    let r;
+    try {{
+      r = await fetch('/__poly_invoke', {{
+        method: 'POST',
+        headers: {{ 'Content-Type': 'application/json', 'X-Poly-Token': window.__POLY_TOKEN__ }},
+        body: JSON.stringify({{ fn, args, id, priority }}),
+        signal
+      }});
+    }} finally {{
+      signal?.removeEventListener('abort', cancel);
+    }}
+    const d = await r.json();
+    if (d.error) {{
+      const err = new Error(d.error);
+      err.code = d.code;
+      err.traceback = d.traceback || [];
+      throw err;
+    }}
+    return d.result;
+  }},
+  windowLabel: new URLSearchParams(location.search).get('window') || 'main',
+  _listeners: {{}},
+  on(topic, cb) {{
+    (this._listeners[topic] = this._listeners[topic] || []).push(cb);
+    return () => this.off(topic, cb);
+  }},
+  off(topic, cb) {{
+    const list = this._listeners[topic] || [];
+    this._listeners[topic] = cb ? list.filter(f => f !== cb) : [];
+  }},
+  async emit(topic, payload = null) {{
+    const r = await fetch('/__poly_emit', {{
+      method: 'POST',
+      headers: {{ 'Content-Type': 'application/json', 'X-Poly-Token': window.__POLY_TOKEN__ }},
+      body: JSON.stringify({{ topic, payload }})
+    }});
+    const d = await r.json();
+    if (d.error) throw new Error(d.error);
+  }},
+  dialog: {{
+    async open(options = {{}}) {{ return poly.invoke('__poly_dialog_open', options); }},
+    async openMultiple(options = {{}}) {{ return poly.invoke('__poly_dialog_open_multiple', options); }},
+    async save(options = {{}}) {{ return poly.invoke('__poly_dialog_save', options); }},
+    async folder(options = {{}}) {{ return poly.invoke('__poly_dialog_folder', options); }},
+    async message(title, message, level = 'info') {{
+      return __polyDialog.show(level, title, message, [{{ text: 'OK', value: true, primary: true }}]);
+    }},
+    async confirm(title, message) {{
+      return __polyDialog.show('confirm', title, message, [
+        {{ text: 'Cancel', value: false }},
+        {{ text: 'Confirm', value: true, primary: true }}
+      ]);
+    }},
+    async custom(options) {{
+      return __polyDialog.show(options.type || 'info', options.title, options.message, options.buttons || [{{ text: 'OK', value: true, primary: true }}]);
+    }}
+  }},
+  fs: {{
+    async read(path) {{ return poly.invoke('__poly_fs_read', {{ path }}); }},
+    async write(path, content) {{ return poly.invoke('__poly_fs_write', {{ path, content }}); }},
+    async exists(path) {{ return poly.invoke('__poly_fs_exists', {{ path }}); }},
+    async readDir(path) {{ return poly.invoke('__poly_fs_read_dir', {{ path }}); }},
+    url(path) {{ return poly.blob.url('/__poly_file?path=' + encodeURIComponent(path)); }},
+    async readBytes(path, range) {{
+      const headers = range ? {{ Range: 'bytes=' + range.start + '-' + (range.end != null ? range.end : '') }} : {{}};
+      const r = await fetch(poly.fs.url(path), {{ headers }});
+      if (!r.ok) throw new Error((await r.json()).error);
+      return new Uint8Array(await r.arrayBuffer());
+    }},
+    async upload(data, path, options = {{}}) {{
+      const chunkSize = options.chunkSize || 4 * 1024 * 1024;
+      const blob = data instanceof Blob ? data : new Blob([data]);
+      const {{ id }} = await poly.invoke('__poly_upload_start', {{}});
+      try {{
+        for (let offset = 0; offset < blob.size; offset += chunkSize) {{
+          const r = await fetch('/__poly_upload/' + id + '?offset=' + offset, {{
+            method: 'PUT',
+            headers: {{ 'X-Poly-Token': window.__POLY_TOKEN__ }},
+            body: blob.slice(offset, offset + chunkSize)
+          }});
+          const d = await r.json();
+          if (d.error) throw new Error(d.error);
+          if (options.onProgress) options.onProgress(d.result.received, blob.size);
+        }}
+        return await poly.invoke('__poly_upload_finish', {{ id, path }});
+      }} catch (e) {{
+        poly.invoke('__poly_upload_cancel', {{ id }}).catch(() => {{}});
+        throw e;
+      }}
+    }}
+  }},
+  blob: {{
+    url(ref) {{
+      const url = typeof ref === 'string' ? ref : ref.$blob;
+      return url + (url.includes('?') ? '&' : '?') + 'token=' + encodeURIComponent(window.__POLY_TOKEN__ || '');
+    }},
+    async fetch(ref) {{
+      const r = await fetch(poly.blob.url(ref));
+      if (!r.ok) throw new Error((await r.json()).error);
+      return r.blob();
+    }},
+    async release(ref) {{ return poly.invoke('__poly_blob_release', {{ url: typeof ref === 'string' ? ref : ref.$blob }}); }}
+  }},
+  updater: {{
+    async checkGithub(repo, currentVersion) {{ return poly.invoke('__poly_updater_check_github', {{ repo, currentVersion }}); }},
+    async checkUrl(url, currentVersion) {{ return poly.invoke('__poly_updater_check_url', {{ url, currentVersion }}); }},
+    async download(url) {{ return poly.invoke('__poly_updater_download', {{ url }}); }},
+    async install(path) {{ return poly.invoke('__poly_updater_install', {{ path }}); }},
+    async checkAndPrompt(options) {{
+      const info = options.repo 
+        ? await poly.updater.checkGithub(options.repo, options.currentVersion)
+        : await poly.updater.checkUrl(options.url, options.currentVersion);
+      if (info.update_available) {{
+        const confirmed = await poly.dialog.confirm('Update Available', 
+          `Version ${{info.latest_version}} is available. Download now?`);
+        if (confirmed && info.download_url) {{
+          const path = await poly.updater.download(info.download_url);
+          await poly.updater.install(path);
+        }}
+      }}
+      return info;
+    }}
+  }},
+  window: {{
+    minimize() {{ if (window.ipc) window.ipc.postMessage('minimize'); }},
+    maximize() {{ if (window.ipc) window.ipc.postMessage('maximize'); }},
+    close() {{ if (window.ipc) window.ipc.postMessage('close'); }},
+    hide() {{ if (window.ipc) window.ipc.postMessage('hide'); }},
+    show() {{ if (window.ipc) window.ipc.postMessage('show'); }},
+    async setTitle(title) {{ return poly.invoke('__poly_window_set_title', {{ title }}); }},
+    async getTitle() {{ return poly.invoke('__poly_window_get_title', {{}}); }},
+    async center() {{ return poly.invoke('__poly_window_center', {{}}); }},
+    async setSize(width, height) {{ return poly.invoke('__poly_window_set_size', {{ width, height }}); }},
+    async getSize() {{ return poly.invoke('__poly_window_get_size', {{}}); }},
+    async setPosition(x, y) {{ return poly.invoke('__poly_window_set_position', {{ x, y }}); }},
+    async getPosition() {{ return poly.invoke('__poly_window_get_position', {{}}); }},
+    async setMinSize(width, height) {{ return poly.invoke('__poly_window_set_min_size', {{ width, height }}); }},
+    async setMaxSize(width, height) {{ return poly.invoke('__poly_window_set_max_size', {{ width, height }}); }},
+    async setAlwaysOnTop(value) {{ return poly.invoke('__poly_window_set_always_on_top', {{ value }}); }},
     async setFullscreen(value) {{ return poly.invoke('__poly_window_set_fullscreen', {{ value }}); }},
     async isFullscreen() {{ return poly.invoke('__poly_window_is_fullscreen', {{}}); }},
     async isMaximized() {{ return poly.invoke('__poly_window_is_maximized', {{}}); }},
@@ -1268,7 +2045,13 @@ window.poly = {{
   clipboard: {{
     async read() {{ return poly.invoke('__poly_clipboard_read', {{}}); }},
     async write(text) {{ return poly.invoke('__poly_clipboard_write', {{ text }}); }},
-    async clear() {{ return poly.invoke('__poly_clipboard_clear', {{}}); }}
+    async clear() {{ return poly.invoke('__poly_clipboard_clear', {{}}); }},
+    async readImage() {{
+      const ref = await poly.invoke('__poly_clipboard_read_image', {{}});
+      const image = await poly.blob.fetch(ref);
+      poly.blob.release(ref);
+      return image;
+    }}
   }},
   windows: {{
     async create(options = {{}}) {{ return poly.invoke('__poly_window_create', options); }},
@@ -1364,7 +2147,7 @@ window.poly = {{
     async clearHistory(id) {{ return poly.invoke('__poly_browser_clear_history', {{ id }}); }},
     async fetch(url) {{ return poly.invoke('__poly_browser_fetch', {{ url }}); }},
     // Proxy URL - use this to load external resources through the local server
-    proxyUrl(url) {{ return '/__poly_proxy?url=' + encodeURIComponent(url); }},
+    proxyUrl(url) {{ return '/__poly_proxy?token=' + encodeURIComponent(window.__POLY_TOKEN__ || '') + '&url=' + encodeURIComponent(url); }},
     // Navigate the current WebView to a URL (replaces current page)
     loadUrl(url) {{ window.location.href = url; }},
     // Go back in browser history
@@ -1450,2641 +2233,167 @@ window.poly = {{
     }},
     _emit(event, id, data) {{
       const key = `${{event}}:${{id}}`;
-      if (this._listeners[key]) {{
-        this._listeners[key].forEach(cb => cb(data));
-      }}
-      // Also emit to wildcard listeners
-      const wildcardKey = `${{event}}:*`;
-      if (this._listeners[wildcardKey]) {{
-        this._listeners[wildcardKey].forEach(cb => cb(id, data));
-      }}
-    }},
-    // Convenience event registration
-    onNavigate(id, cb) {{ this.on('navigate', id, cb); }},
-    onTitleChange(id, cb) {{ this.on('titleChange', id, cb); }},
-    onLoadStart(id, cb) {{ this.on('loadStart', id, cb); }},
-    onLoadFinish(id, cb) {{ this.on('loadFinish', id, cb); }},
-    onNewWindow(id, cb) {{ this.on('newWindow', id, cb); }},
-    onDownload(id, cb) {{ this.on('download', id, cb); }},
-    onClose(id, cb) {{ this.on('close', id, cb); }},
-    onHistoryChange(id, cb) {{ this.on('historyChange', id, cb); }}
-  }},
-  // MultiView API - Create windows with multiple WebViews
-  multiview: {{
-    // Create a new multi-view window
-    // views: array of {{ id, url, x, y, width, height }}
-    // Views are stacked: first in array = bottom, last = top (for UI)
-    async create(options) {{ return poly.invoke('__poly_multiview_create', options); }},
-    // Navigate a view to URL
-    async navigate(windowId, viewId, url) {{ return poly.invoke('__poly_multiview_navigate', {{ windowId, viewId, url }}); }},
-    // Send message to a view (triggers 'polymessage' event)
-    async postMessage(windowId, viewId, message) {{ return poly.invoke('__poly_multiview_post_message', {{ windowId, viewId, message: JSON.stringify(message) }}); }},
-    // Set view bounds
-    async setBounds(windowId, viewId, bounds) {{ return poly.invoke('__poly_multiview_set_bounds', {{ windowId, viewId, ...bounds }}); }},
-    // Close a multi-view window
-    async close(windowId) {{ return poly.invoke('__poly_multiview_close', {{ windowId }}); }},
-    // List all multi-view windows
-    async list() {{ return poly.invoke('__poly_multiview_list', {{}}); }},
-    // Get window info
-    async get(windowId) {{ return poly.invoke('__poly_multiview_get', {{ windowId }}); }}
-  }},
-  // PolyView API - "iframe2" that bypasses all iframe restrictions
-  // Use <poly-view src="https://example.com"></poly-view> in your HTML
-  polyview: {{
-    // Get the proxy URL for a target URL
-    proxyUrl(url) {{ return '/__polyview/?url=' + encodeURIComponent(url); }},
-    // Navigate a poly-view element
-    navigate(element, url) {{
-      if (element && element.navigate) element.navigate(url);
-      else if (element) element.src = this.proxyUrl(url);
-    }},
-    // Check if PolyView is available
-    isAvailable() {{ return typeof customElements !== 'undefined' && customElements.get('poly-view') !== undefined; }}
-  }},
-  // AI/LLM API - Chat with AI models
-  ai: {{
-    // Chat with Ollama (local)
-    async ollama(model, messages, options = {{}}) {{
-      return poly.invoke('__poly_ai_ollama', {{ model, messages, ...options }});
-    }},
-    // Chat with OpenAI
-    async openai(model, messages, apiKey, options = {{}}) {{
-      return poly.invoke('__poly_ai_openai', {{ model, messages, apiKey, ...options }});
-    }},
-    // Chat with Anthropic Claude
-    async anthropic(model, messages, apiKey, options = {{}}) {{
-      return poly.invoke('__poly_ai_anthropic', {{ model, messages, apiKey, ...options }});
-    }},
-    // Chat with custom OpenAI-compatible API
-    async custom(baseUrl, model, messages, options = {{}}) {{
-      return poly.invoke('__poly_ai_custom', {{ baseUrl, model, messages, ...options }});
-    }},
-    // Check if Ollama is running
-    async checkOllama() {{
-      return poly.invoke('__poly_ai_check_ollama', {{}});
-    }},
-    // List available Ollama models
-    async listModels() {{
-      return poly.invoke('__poly_ai_list_models', {{}});
-    }},
-    // Generic chat function (auto-detects provider)
-    async chat(options) {{
-      return poly.invoke('__poly_ai_chat', options);
-    }},
-    // Streaming API
-    stream: {{
-      // Start streaming chat with Ollama
-      async start(model, messages, options = {{}}) {{
-        return poly.invoke('__poly_ai_stream_start', {{ model, messages, ...options }});
-      }},
-      // Poll for new chunks (returns {{ chunks: [], done: bool }})
-      async poll(streamId) {{
-        return poly.invoke('__poly_ai_stream_poll', {{ streamId }});
-      }},
-      // Cancel/stop a stream
-      async cancel(streamId) {{
-        return poly.invoke('__poly_ai_stream_cancel', {{ streamId }});
-      }},
-      // List active streams
-      async list() {{
-        return poly.invoke('__poly_ai_stream_list', {{}});
-      }},
-      // Helper: Stream with callback (handles polling automatically)
-      async run(model, messages, options = {{}}, onChunk) {{
-        const result = await this.start(model, messages, options);
-        if (result.error) throw new Error(result.error);
-        const streamId = result.streamId;
-        
-        const poll = async () => {{
-          const {{ chunks, done }} = await this.poll(streamId);
-          for (const chunk of chunks) {{
-            if (onChunk) onChunk(chunk);
-          }}
-          if (!done) {{
-            await new Promise(r => setTimeout(r, 16)); // ~60fps polling
-            await poll();
-          }}
-        }};
-        
-        await poll();
-        return streamId;
-      }}
-    }}
-  }}
-}};
-// Initialize Lucide Icons
-if (typeof lucide !== 'undefined') lucide.createIcons();
-// Hot Reload
-(function() {{
-  let v = {}, polling = false;
-  async function check() {{
-    if (!document.hidden) {{
-      try {{ const r = await fetch('/__poly_reload'); const d = await r.json(); if (d.version > v) {{ v = d.version; location.reload(); }} }} catch(e) {{}}
-    }}
-    if (polling) setTimeout(check, {reload_interval});
-  }}
-  function start() {{ if (!polling) {{ polling = true; check(); }} }}
-  function stop() {{ polling = false; }}
-  document.addEventListener('visibilitychange', () => document.hidden ? stop() : start());
-  start();
-}})();
-</script>"#, reload_counter_http.load(Ordering::Relaxed), reload_interval = reload_interval);
-                        // Insert before </body> or at end
-                        if html.contains("</body>") {
-                            html = html.replace("</body>", &format!("{}</body>", reload_script));
-                        } else {
-                            html.push_str(&reload_script);
-                        }
-                        tiny_http::Response::from_string(html)
-                            .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..]).unwrap())
-                    } else {
-                        let html = generate_dev_html(&project_path_http, &entry_http, reload_counter_http.load(Ordering::Relaxed));
-                        tiny_http::Response::from_string(html)
-                            .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..]).unwrap())
-                    }
-                }
-                "/__poly_reload" => {
-                    let current = reload_counter_http.load(Ordering::Relaxed);
-                    tiny_http::Response::from_string(format!(r#"{{"version":{}}}"#, current))
-                        .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap())
-                }
-                "/__poly_invoke" => {
-                    // IPC Bridge - call Poly functions from JavaScript (stateful)
-                    let mut body = String::new();
-                    request.as_reader().read_to_string(&mut body).ok();
-                    
-                    let result = handle_ipc_invoke_stateful(&interpreter_http, &body);
-                    tiny_http::Response::from_string(result)
-                        .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap())
-                        .with_header(tiny_http::Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..]).unwrap())
-                }
-                "/__poly_run" => {
-                    let output = execute_poly_for_web(&entry_http);
-                    tiny_http::Response::from_string(output)
-                        .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap())
-                }
-                _ => {
-                    // First try the exact path, then try in web/ folder, then packages/
-                    let url_path = url.trim_start_matches('/');
-                    let file_path = project_path_http.join(url_path);
-                    let web_file_path = project_path_http.join("web").join(url_path);
-                    let packages_file_path = project_path_http.join(url_path);
-                    
-                    let actual_path = if file_path.exists() && file_path.is_file() {
-                        Some(file_path)
-                    } else if web_file_path.exists() && web_file_path.is_file() {
-                        Some(web_file_path)
-                    } else if packages_file_path.exists() && packages_file_path.is_file() {
-                        Some(packages_file_path)
-                    } else {
-                        None
-                    };
-                    
-                    if let Some(path) = actual_path {
-                        // Use read for binary files, read_to_string for text
-                        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
-                        let is_binary = matches!(ext, "png" | "jpg" | "jpeg" | "gif" | "ico" | "woff" | "woff2" | "ttf" | "eot");
-                        
-                        if is_binary {
-                            match fs::read(&path) {
-                                Ok(content) => {
-                                    let ct = match ext {
-                                        "png" => "image/png",
-                                        "jpg" | "jpeg" => "image/jpeg",
-                                        "gif" => "image/gif",
-                                        "ico" => "image/x-icon",
-                                        "woff" => "font/woff",
-                                        "woff2" => "font/woff2",
-                                        "ttf" => "font/ttf",
-                                        "eot" => "application/vnd.ms-fontobject",
-                                        _ => "application/octet-stream",
-                                    };
-                                    tiny_http::Response::from_data(content)
-                                        .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], ct.as_bytes()).unwrap())
-                                }
-                                Err(_) => tiny_http::Response::from_string("Read Error").with_status_code(500)
-                            }
-                        } else {
-                            let content = fs::read_to_string(&path).unwrap_or_default();
-                            let ct = match ext {
-                                "html" => "text/html; charset=utf-8",
-                                "css" => "text/css; charset=utf-8",
-                                "js" => "application/javascript; charset=utf-8",
-                                "json" => "application/json",
-                                "svg" => "image/svg+xml",
-                                _ => "text/plain",
-                            };
-                            tiny_http::Response::from_string(content)
-                                .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], ct.as_bytes()).unwrap())
-                        }
-                    } else {
-                        tiny_http::Response::from_string("Not Found").with_status_code(404)
-                    }
-                }
-            };
-            let _ = request.respond(response);
-        }
-    });
-    
-    // File watcher
-    let (tx, rx) = channel();
-    let mut watcher = notify::recommended_watcher(move |res| { let _ = tx.send(res); })
-        .expect("Failed to create file watcher");
-    watcher.watch(project_path, RecursiveMode::Recursive).expect("Failed to watch directory");
-    
-    // Initial build
-    print!("  {}Building...{}", DIM, RESET);
-    io::stdout().flush().unwrap();
-    let start = std::time::Instant::now();
-    let _success = poly::run(&fs::read_to_string(&entry).unwrap_or_default()).is_ok();
-    println!("\r  {}ready{} in {}{}ms{}                    ", GREEN, RESET, BOLD, start.elapsed().as_millis(), RESET);
-    
-    if open_browser {
-        let _ = open_in_browser(&format!("http://localhost:{}", port));
-    }
-    
-    println!();
-    println!("  {}press h to show help{}", DIM, RESET);
-    println!();
-    
-    // Watch loop
-    loop {
-        match rx.recv_timeout(Duration::from_millis(100)) {
-            Ok(Ok(event)) => {
-                if should_reload(&event) {
-                    let changed: Vec<_> = event.paths.iter()
-                        .filter_map(|p| p.file_name())
-                        .filter_map(|n| n.to_str())
-                        .collect();
-                    
-                    let time = chrono_time();
-                    print!("{}{}{}  {}hmr update{} {}", DIM, time, RESET, YELLOW, RESET, changed.join(", "));
-                    io::stdout().flush().unwrap();
-                    
-                    std::thread::sleep(Duration::from_millis(50));
-                    reload_counter.fetch_add(1, Ordering::Relaxed);
-                    
-                    // Only reload Poly interpreter for .poly file changes
-                    let has_poly_change = event.paths.iter().any(|p| {
-                        p.extension().and_then(|e| e.to_str()) == Some("poly")
-                    });
-                    
-                    if has_poly_change {
-                        let start = std::time::Instant::now();
-                        let source = fs::read_to_string(&entry).unwrap_or_default();
-                        
-                        // Reload the persistent interpreter
-                        let mut interp = interpreter.lock().unwrap();
-                        *interp = poly::create_interpreter();
-                        match poly::init_interpreter(&mut interp, &source) {
-                            Ok(_) => println!(" {}({}ms){}", DIM, start.elapsed().as_millis(), RESET),
-                            Err(e) => println!("\n  {}error{}: {}", RED, RESET, e),
-                        }
-                    } else {
-                        // For HTML/CSS/JS, just signal reload (no Poly execution needed)
-                        println!("");
-                    }
-                }
-            }
-            Ok(Err(e)) => eprintln!("{}error{}: {:?}", RED, RESET, e),
-            Err(_) => {}
-        }
-    }
-}
-
-fn chrono_time() -> String {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-    let hours = (secs / 3600) % 24;
-    let mins = (secs / 60) % 60;
-    let secs = secs % 60;
-    format!("{:02}:{:02}:{:02}", hours, mins, secs)
-}
-
-fn execute_poly_for_web(entry: &Path) -> String {
-    match fs::read_to_string(entry) {
-        Ok(source) => match poly::run(&source) {
-            Ok(output) => serde_json::json!({"success": true, "output": output}).to_string(),
-            Err(e) => serde_json::json!({"success": false, "error": e}).to_string(),
-        },
-        Err(e) => serde_json::json!({"success": false, "error": format!("Failed to read: {}", e)}).to_string(),
-    }
-}
-
-/// Handle IPC invoke with a persistent (stateful) interpreter
-fn handle_ipc_invoke_stateful(interpreter: &std::sync::Arc<std::sync::Mutex<poly::interpreter::Interpreter>>, body: &str) -> String {
-    // Parse the request: { "fn": "function_name", "args": { ... } }
-    let request: serde_json::Value = match serde_json::from_str(body) {
-        Ok(v) => v,
-        Err(e) => return serde_json::json!({"error": format!("Invalid JSON: {}", e)}).to_string(),
-    };
-    
-    let fn_name = match request.get("fn").and_then(|v| v.as_str()) {
-        Some(name) => name,
-        None => return serde_json::json!({"error": "Missing 'fn' field"}).to_string(),
-    };
-    
-    let args = request.get("args").cloned().unwrap_or(serde_json::json!({}));
-    
-    // Handle built-in system APIs (prefixed with __poly_)
-    if fn_name.starts_with("__poly_") {
-        return handle_system_api(fn_name, &args);
-    }
-    
-    // Build argument string from JSON - skip empty objects
-    let args_str = if args.is_object() && args.as_object().map(|o| o.is_empty()).unwrap_or(false) {
-        String::new() // No arguments
-    } else {
-        json_to_poly_value(&args)
-    };
-    
-    // Call function on persistent interpreter
-    let mut interp = interpreter.lock().unwrap();
-    match poly::call_function(&mut interp, fn_name, &args_str) {
-        Ok(json_result) => {
-            format!(r#"{{"result":{}}}"#, json_result)
-        }
-        Err(e) => serde_json::json!({"error": e}).to_string(),
-    }
-}
-
-#[allow(dead_code)]
-fn handle_ipc_invoke(entry: &Path, body: &str) -> String {
-    // Parse the request: { "fn": "function_name", "args": { ... } }
-    let request: serde_json::Value = match serde_json::from_str(body) {
-        Ok(v) => v,
-        Err(e) => return serde_json::json!({"error": format!("Invalid JSON: {}", e)}).to_string(),
-    };
-    
-    let fn_name = match request.get("fn").and_then(|v| v.as_str()) {
-        Some(name) => name,
-        None => return serde_json::json!({"error": "Missing 'fn' field"}).to_string(),
-    };
-    
-    let args = request.get("args").cloned().unwrap_or(serde_json::json!({}));
-    
-    // Handle built-in system APIs (prefixed with __poly_)
-    if fn_name.starts_with("__poly_") {
-        return handle_system_api(fn_name, &args);
-    }
-    
-    // Read the Poly source
-    let source = match fs::read_to_string(entry) {
-        Ok(s) => s,
-        Err(e) => return serde_json::json!({"error": format!("Failed to read: {}", e)}).to_string(),
-    };
-    
-    // Build argument string from JSON - skip empty objects
-    let args_str = if args.is_object() && args.as_object().map(|o| o.is_empty()).unwrap_or(false) {
-        String::new() // No arguments
-    } else {
-        json_to_poly_value(&args)
-    };
-    
-    // Create a call expression - the result will be the last evaluated value
-    let call_expr = format!("{fn_name}({args_str})");
-    let full_source = format!("{source}\n{call_expr}");
-    
-    // Use eval_json to get proper JSON output
-    match poly::eval_json(&full_source) {
-        Ok(json_result) => {
-            // The result is already valid JSON, wrap it in the response
-            format!(r#"{{"result":{}}}"#, json_result)
-        }
-        Err(e) => serde_json::json!({"error": e}).to_string(),
-    }
-}
-
-/// Handle built-in system APIs
-#[allow(unused_variables)]
-fn handle_system_api(fn_name: &str, args: &serde_json::Value) -> String {
-    match fn_name {
-        // File Dialogs
-        "__poly_dialog_open" => {
-            let title = args.get("title").and_then(|v| v.as_str());
-            let filters = parse_filters(args.get("filters"));
-            
-            #[cfg(feature = "native")]
-            {
-                let result = poly::native::dialog_open_file(title, filters);
-                serde_json::json!({"result": result}).to_string()
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"result": null}).to_string()
-            }
-        }
-        "__poly_dialog_open_multiple" => {
-            let title = args.get("title").and_then(|v| v.as_str());
-            let filters = parse_filters(args.get("filters"));
-            
-            #[cfg(feature = "native")]
-            {
-                let result = poly::native::dialog_open_files(title, filters);
-                serde_json::json!({"result": result}).to_string()
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"result": []}).to_string()
-            }
-        }
-        "__poly_dialog_save" => {
-            let title = args.get("title").and_then(|v| v.as_str());
-            let default_name = args.get("defaultName").and_then(|v| v.as_str());
-            let filters = parse_filters(args.get("filters"));
-            
-            #[cfg(feature = "native")]
-            {
-                let result = poly::native::dialog_save_file(title, default_name, filters);
-                serde_json::json!({"result": result}).to_string()
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"result": null}).to_string()
-            }
-        }
-        "__poly_dialog_folder" => {
-            let title = args.get("title").and_then(|v| v.as_str());
-            
-            #[cfg(feature = "native")]
-            {
-                let result = poly::native::dialog_pick_folder(title);
-                serde_json::json!({"result": result}).to_string()
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"result": null}).to_string()
-            }
-        }
-        "__poly_dialog_message" => {
-            let title = args.get("title").and_then(|v| v.as_str()).unwrap_or("Message");
-            let message = args.get("message").and_then(|v| v.as_str()).unwrap_or("");
-            let level = args.get("level").and_then(|v| v.as_str()).unwrap_or("info");
-            
-            #[cfg(feature = "native")]
-            {
-                let msg_level = match level {
-                    "warning" => poly::native::MessageLevel::Warning,
-                    "error" => poly::native::MessageLevel::Error,
-                    _ => poly::native::MessageLevel::Info,
-                };
-                poly::native::dialog_message(title, message, msg_level);
-                serde_json::json!({"result": true}).to_string()
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"result": false}).to_string()
-            }
-        }
-        "__poly_dialog_confirm" => {
-            let title = args.get("title").and_then(|v| v.as_str()).unwrap_or("Confirm");
-            let message = args.get("message").and_then(|v| v.as_str()).unwrap_or("");
-            
-            #[cfg(feature = "native")]
-            {
-                let result = poly::native::dialog_confirm(title, message);
-                serde_json::json!({"result": result}).to_string()
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"result": false}).to_string()
-            }
-        }
-        // File System
-        "__poly_fs_read" => {
-            let path = args.get("path").and_then(|v| v.as_str()).unwrap_or("");
-            match fs::read_to_string(path) {
-                Ok(content) => serde_json::json!({"result": content}).to_string(),
-                Err(e) => serde_json::json!({"error": e.to_string()}).to_string(),
-            }
-        }
-        "__poly_fs_write" => {
-            let path = args.get("path").and_then(|v| v.as_str()).unwrap_or("");
-            let content = args.get("content").and_then(|v| v.as_str()).unwrap_or("");
-            match fs::write(path, content) {
-                Ok(_) => serde_json::json!({"result": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e.to_string()}).to_string(),
-            }
-        }
-        "__poly_fs_exists" => {
-            let path = args.get("path").and_then(|v| v.as_str()).unwrap_or("");
-            let exists = Path::new(path).exists();
-            serde_json::json!({"result": exists}).to_string()
-        }
-        "__poly_fs_read_dir" => {
-            let path = args.get("path").and_then(|v| v.as_str()).unwrap_or(".");
-            match fs::read_dir(path) {
-                Ok(entries) => {
-                    let files: Vec<serde_json::Value> = entries
-                        .filter_map(|e| e.ok())
-                        .map(|e| {
-                            let path = e.path();
-                            serde_json::json!({
-                                "name": e.file_name().to_string_lossy(),
-                                "path": path.to_string_lossy(),
-                                "isDir": path.is_dir(),
-                            })
-                        })
-                        .collect();
-                    serde_json::json!({"result": files}).to_string()
-                }
-                Err(e) => serde_json::json!({"error": e.to_string()}).to_string(),
-            }
-        }
-        // Auto-Updater APIs
-        "__poly_updater_check_github" => {
-            let repo = args.get("repo").and_then(|v| v.as_str()).unwrap_or("");
-            let current_version = args.get("currentVersion").and_then(|v| v.as_str()).unwrap_or("0.0.0");
-            
-            #[cfg(feature = "native")]
-            {
-                match poly::check_github_updates(repo, current_version) {
-                    Ok(info) => serde_json::json!({"result": info}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_updater_check_url" => {
-            let url = args.get("url").and_then(|v| v.as_str()).unwrap_or("");
-            let current_version = args.get("currentVersion").and_then(|v| v.as_str()).unwrap_or("0.0.0");
-            
-            #[cfg(feature = "native")]
-            {
-                match poly::check_custom_updates(url, current_version) {
-                    Ok(info) => serde_json::json!({"result": info}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_updater_download" => {
-            let url = args.get("url").and_then(|v| v.as_str()).unwrap_or("");
-            
-            #[cfg(feature = "native")]
-            {
-                match poly::download_update(url, None) {
-                    Ok(path) => serde_json::json!({"result": path.to_string_lossy()}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_updater_install" => {
-            let path = args.get("path").and_then(|v| v.as_str()).unwrap_or("");
-            
-            #[cfg(feature = "native")]
-            {
-                match poly::install_update(Path::new(path)) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        // Clipboard APIs
-        "__poly_clipboard_read" => {
-            #[cfg(feature = "native")]
-            {
-                match poly::clipboard::read_text() {
-                    Ok(text) => serde_json::json!({"result": text}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_clipboard_write" => {
-            let text = args.get("text").and_then(|v| v.as_str()).unwrap_or("");
-            
-            #[cfg(feature = "native")]
-            {
-                match poly::clipboard::write_text(text) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_clipboard_clear" => {
-            #[cfg(feature = "native")]
-            {
-                match poly::clipboard::clear() {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        // Multi-Window APIs
-        "__poly_window_create" => {
-            let title = args.get("title").and_then(|v| v.as_str()).unwrap_or("New Window");
-            let width = args.get("width").and_then(|v| v.as_u64()).unwrap_or(800) as u32;
-            let height = args.get("height").and_then(|v| v.as_u64()).unwrap_or(600) as u32;
-            let url = args.get("url").and_then(|v| v.as_str());
-            let html = args.get("html").and_then(|v| v.as_str());
-            
-            #[cfg(feature = "native")]
-            {
-                let mut config = poly::window::WindowConfig::new(title)
-                    .with_size(width, height);
-                
-                if let Some(u) = url {
-                    config = config.with_url(u);
-                }
-                if let Some(h) = html {
-                    config = config.with_html(h);
-                }
-                
-                match poly::window::create_window(config) {
-                    Ok(handle) => serde_json::json!({"result": {"id": handle.id}}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_count" => {
-            #[cfg(feature = "native")]
-            {
-                let count = poly::window::window_count();
-                serde_json::json!({"result": count}).to_string()
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"result": 0}).to_string()
-            }
-        }
-        "__poly_window_close" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            
-            #[cfg(feature = "native")]
-            {
-                match poly::window::close_window(id) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_close_all" => {
-            #[cfg(feature = "native")]
-            {
-                match poly::window::close_all_windows() {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_list" => {
-            #[cfg(feature = "native")]
-            {
-                let ids = poly::window::list_windows();
-                serde_json::json!({"result": ids}).to_string()
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"result": []}).to_string()
-            }
-        }
-        "__poly_window_minimize" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            #[cfg(feature = "native")]
-            {
-                match poly::window::minimize_window(id) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_maximize" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            #[cfg(feature = "native")]
-            {
-                match poly::window::maximize_window(id) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_restore" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            #[cfg(feature = "native")]
-            {
-                match poly::window::restore_window(id) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_show" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            #[cfg(feature = "native")]
-            {
-                match poly::window::show_window(id) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_hide" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            #[cfg(feature = "native")]
-            {
-                match poly::window::hide_window(id) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_focus" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            #[cfg(feature = "native")]
-            {
-                match poly::window::focus_window(id) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_set_title" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            let title = args.get("title").and_then(|v| v.as_str()).unwrap_or("");
-            #[cfg(feature = "native")]
-            {
-                match poly::window::set_window_title(id, title) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_set_size" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            let width = args.get("width").and_then(|v| v.as_u64()).unwrap_or(800) as u32;
-            let height = args.get("height").and_then(|v| v.as_u64()).unwrap_or(600) as u32;
-            #[cfg(feature = "native")]
-            {
-                match poly::window::set_window_size(id, width, height) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_set_position" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            let x = args.get("x").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-            let y = args.get("y").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-            #[cfg(feature = "native")]
-            {
-                match poly::window::set_window_position(id, x, y) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_set_always_on_top" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            let value = args.get("value").and_then(|v| v.as_bool()).unwrap_or(false);
-            #[cfg(feature = "native")]
-            {
-                match poly::window::set_window_always_on_top(id, value) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_set_fullscreen" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            let value = args.get("value").and_then(|v| v.as_bool()).unwrap_or(false);
-            #[cfg(feature = "native")]
-            {
-                match poly::window::set_window_fullscreen(id, value) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_navigate" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            let url = args.get("url").and_then(|v| v.as_str()).unwrap_or("");
-            #[cfg(feature = "native")]
-            {
-                match poly::window::navigate_window(id, url) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_load_html" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            let html = args.get("html").and_then(|v| v.as_str()).unwrap_or("");
-            #[cfg(feature = "native")]
-            {
-                match poly::window::load_window_html(id, html) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_eval" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            let script = args.get("script").and_then(|v| v.as_str()).unwrap_or("");
-            #[cfg(feature = "native")]
-            {
-                match poly::window::eval_window_script(id, script) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_get_state" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            #[cfg(feature = "native")]
-            {
-                match poly::window::get_window_state(id) {
-                    Ok(state) => serde_json::json!({
-                        "result": {
-                            "id": state.id,
-                            "title": state.title,
-                            "width": state.width,
-                            "height": state.height,
-                            "x": state.x,
-                            "y": state.y,
-                            "isVisible": state.is_visible,
-                            "isMinimized": state.is_minimized,
-                            "isMaximized": state.is_maximized,
-                            "isFullscreen": state.is_fullscreen,
-                            "isFocused": state.is_focused
-                        }
-                    }).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_window_list_states" => {
-            #[cfg(feature = "native")]
-            {
-                let states: Vec<_> = poly::window::list_window_states()
-                    .into_iter()
-                    .map(|s| serde_json::json!({
-                        "id": s.id,
-                        "title": s.title,
-                        "width": s.width,
-                        "height": s.height,
-                        "x": s.x,
-                        "y": s.y,
-                        "isVisible": s.is_visible,
-                        "isMinimized": s.is_minimized,
-                        "isMaximized": s.is_maximized,
-                        "isFullscreen": s.is_fullscreen,
-                        "isFocused": s.is_focused
-                    }))
-                    .collect();
-                serde_json::json!({"result": states}).to_string()
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"result": []}).to_string()
-            }
-        }
-        // Notification APIs
-        "__poly_notification_show" => {
-            let title = args.get("title").and_then(|v| v.as_str()).unwrap_or("Notification");
-            let body = args.get("body").and_then(|v| v.as_str()).unwrap_or("");
-            let icon = args.get("icon").and_then(|v| v.as_str());
-            
-            #[cfg(feature = "native")]
-            {
-                match poly::notification::show(title, body, icon) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_notification_show_timeout" => {
-            let title = args.get("title").and_then(|v| v.as_str()).unwrap_or("Notification");
-            let body = args.get("body").and_then(|v| v.as_str()).unwrap_or("");
-            let timeout = args.get("timeout").and_then(|v| v.as_u64()).unwrap_or(5000) as u32;
-            
-            #[cfg(feature = "native")]
-            {
-                match poly::notification::show_with_timeout(title, body, timeout) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        // Deep Link APIs
-        "__poly_deeplink_register" => {
-            let protocol = args.get("protocol").and_then(|v| v.as_str()).unwrap_or("");
-            let app_name = args.get("appName").and_then(|v| v.as_str()).unwrap_or("Poly App");
-            
-            if protocol.is_empty() {
-                return serde_json::json!({"error": "Protocol name required"}).to_string();
-            }
-            
-            match poly::deeplink::register_protocol(protocol, app_name) {
-                Ok(_) => serde_json::json!({"result": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_deeplink_unregister" => {
-            let protocol = args.get("protocol").and_then(|v| v.as_str()).unwrap_or("");
-            
-            if protocol.is_empty() {
-                return serde_json::json!({"error": "Protocol name required"}).to_string();
-            }
-            
-            match poly::deeplink::unregister_protocol(protocol) {
-                Ok(_) => serde_json::json!({"result": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_deeplink_is_registered" => {
-            let protocol = args.get("protocol").and_then(|v| v.as_str()).unwrap_or("");
-            let registered = poly::deeplink::is_protocol_registered(protocol);
-            serde_json::json!({"result": registered}).to_string()
-        }
-        "__poly_deeplink_get" => {
-            let link = poly::deeplink::get_deep_link();
-            serde_json::json!({"result": link}).to_string()
-        }
-        "__poly_deeplink_has" => {
-            let has = poly::deeplink::has_deep_link();
-            serde_json::json!({"result": has}).to_string()
-        }
-        "__poly_tray_is_enabled" => {
-            // This is determined by poly.toml config, return false in dev mode
-            // In native mode, the actual tray state is managed by the window
-            serde_json::json!({"result": false}).to_string()
-        }
-        // Shell APIs
-        "__poly_shell_open" => {
-            let url = args.get("url").and_then(|v| v.as_str()).unwrap_or("");
-            #[cfg(target_os = "windows")]
-            {
-                let _ = std::process::Command::new("cmd")
-                    .args(["/C", "start", "", url])
-                    .spawn();
-            }
-            #[cfg(target_os = "macos")]
-            {
-                let _ = std::process::Command::new("open")
-                    .arg(url)
-                    .spawn();
-            }
-            #[cfg(target_os = "linux")]
-            {
-                let _ = std::process::Command::new("xdg-open")
-                    .arg(url)
-                    .spawn();
-            }
-            serde_json::json!({"result": true}).to_string()
-        }
-        "__poly_shell_open_path" => {
-            let path = args.get("path").and_then(|v| v.as_str()).unwrap_or("");
-            #[cfg(target_os = "windows")]
-            {
-                let _ = std::process::Command::new("explorer")
-                    .arg(path)
-                    .spawn();
-            }
-            #[cfg(target_os = "macos")]
-            {
-                let _ = std::process::Command::new("open")
-                    .arg(path)
-                    .spawn();
-            }
-            #[cfg(target_os = "linux")]
-            {
-                let _ = std::process::Command::new("xdg-open")
-                    .arg(path)
-                    .spawn();
-            }
-            serde_json::json!({"result": true}).to_string()
-        }
-        "__poly_shell_open_with" => {
-            let path = args.get("path").and_then(|v| v.as_str()).unwrap_or("");
-            let app = args.get("app").and_then(|v| v.as_str()).unwrap_or("");
-            #[cfg(target_os = "windows")]
-            {
-                let _ = std::process::Command::new(app)
-                    .arg(path)
-                    .spawn();
-            }
-            #[cfg(not(target_os = "windows"))]
-            {
-                let _ = std::process::Command::new(app)
-                    .arg(path)
-                    .spawn();
-            }
-            serde_json::json!({"result": true}).to_string()
-        }
-        // App APIs
-        "__poly_app_get_version" => {
-            serde_json::json!({"result": VERSION}).to_string()
-        }
-        "__poly_app_get_name" => {
-            let name = std::env::current_exe()
-                .ok()
-                .and_then(|p| p.file_stem().map(|s| s.to_string_lossy().to_string()))
-                .unwrap_or_else(|| "Poly App".to_string());
-            serde_json::json!({"result": name}).to_string()
-        }
-        "__poly_app_get_path" => {
-            let name = args.get("name").and_then(|v| v.as_str()).unwrap_or("data");
-            let path = match name {
-                "exe" => std::env::current_exe().ok().map(|p| p.to_string_lossy().to_string()),
-                "data" | "appData" => dirs_path("data"),
-                "config" => dirs_path("config"),
-                "cache" => dirs_path("cache"),
-                "temp" => Some(std::env::temp_dir().to_string_lossy().to_string()),
-                "home" => dirs_path("home"),
-                "desktop" => dirs_path("desktop"),
-                "documents" => dirs_path("documents"),
-                "downloads" => dirs_path("downloads"),
-                _ => None,
-            };
-            match path {
-                Some(p) => serde_json::json!({"result": p}).to_string(),
-                None => serde_json::json!({"result": null}).to_string(),
-            }
-        }
-        "__poly_app_exit" => {
-            let code = args.get("code").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-            std::process::exit(code);
-        }
-        "__poly_app_relaunch" => {
-            if let Ok(exe) = std::env::current_exe() {
-                let _ = std::process::Command::new(exe)
-                    .args(std::env::args().skip(1))
-                    .spawn();
-            }
-            std::process::exit(0);
-        }
-        // OS APIs
-        "__poly_os_platform" => {
-            let platform = if cfg!(target_os = "windows") { "windows" }
-                else if cfg!(target_os = "macos") { "macos" }
-                else if cfg!(target_os = "linux") { "linux" }
-                else { "unknown" };
-            serde_json::json!({"result": platform}).to_string()
-        }
-        "__poly_os_arch" => {
-            let arch = if cfg!(target_arch = "x86_64") { "x64" }
-                else if cfg!(target_arch = "aarch64") { "arm64" }
-                else if cfg!(target_arch = "x86") { "x86" }
-                else { "unknown" };
-            serde_json::json!({"result": arch}).to_string()
-        }
-        "__poly_os_version" => {
-            serde_json::json!({"result": std::env::consts::OS}).to_string()
-        }
-        "__poly_os_hostname" => {
-            #[cfg(target_os = "windows")]
-            let hostname = std::env::var("COMPUTERNAME").unwrap_or_else(|_| "unknown".to_string());
-            #[cfg(not(target_os = "windows"))]
-            let hostname = std::env::var("HOSTNAME")
-                .or_else(|_| std::env::var("HOST"))
-                .unwrap_or_else(|_| "unknown".to_string());
-            serde_json::json!({"result": hostname}).to_string()
-        }
-        "__poly_os_homedir" => {
-            let home = dirs_path("home").unwrap_or_default();
-            serde_json::json!({"result": home}).to_string()
-        }
-        "__poly_os_tempdir" => {
-            let temp = std::env::temp_dir().to_string_lossy().to_string();
-            serde_json::json!({"result": temp}).to_string()
-        }
-        // HTTP API
-        "__poly_http_get" | "__poly_http_post" | "__poly_http_put" | "__poly_http_patch" | "__poly_http_delete" | "__poly_http_request" => {
-            handle_http_request(fn_name, args)
-        }
-        // AI/LLM API
-        "__poly_ai_check_ollama" => {
-            #[cfg(feature = "native")]
-            {
-                match poly::ai::check_ollama() {
-                    Ok(available) => serde_json::json!({"result": available}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_ai_list_models" => {
-            #[cfg(feature = "native")]
-            {
-                match poly::ai::list_ollama_models() {
-                    Ok(models) => serde_json::json!({"result": models}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_ai_ollama" => {
-            #[cfg(feature = "native")]
-            {
-                let model = args.get("model").and_then(|v| v.as_str()).unwrap_or("llama3");
-                let messages = args.get("messages").and_then(|v| v.as_array());
-                let temperature = args.get("temperature").and_then(|v| v.as_f64()).unwrap_or(0.7) as f32;
-                let max_tokens = args.get("maxTokens").and_then(|v| v.as_u64()).map(|v| v as u32);
-                let think = args.get("think").and_then(|v| v.as_bool()).unwrap_or(false);
-                let tools_json = args.get("tools").and_then(|v| v.as_array());
-                let tool_choice = args.get("toolChoice").and_then(|v| v.as_str()).unwrap_or("auto");
-                
-                let chat_messages: Vec<poly::ai::ChatMessage> = messages
-                    .map(|arr| arr.iter().filter_map(|m| {
-                        let role = m.get("role")?.as_str()?;
-                        let content = m.get("content")?.as_str()?;
-                        Some(poly::ai::ChatMessage {
-                            role: match role {
-                                "system" => poly::ai::MessageRole::System,
-                                "assistant" => poly::ai::MessageRole::Assistant,
-                                _ => poly::ai::MessageRole::User,
-                            },
-                            content: content.to_string(),
-                        })
-                    }).collect())
-                    .unwrap_or_default();
-                
-                // Parse tools from JSON
-                let tools: Vec<poly::ai::Tool> = tools_json
-                    .map(|arr| arr.iter().filter_map(|t| {
-                        serde_json::from_value(t.clone()).ok()
-                    }).collect())
-                    .unwrap_or_default();
-                
-                let request = poly::ai::ChatRequest {
-                    provider: poly::ai::AiProvider::Ollama,
-                    base_url: None,
-                    api_key: None,
-                    model: model.to_string(),
-                    messages: chat_messages,
-                    temperature,
-                    max_tokens,
-                    stream: false,
-                    enable_thinking: think,
-                    thinking_budget: None,
-                    tools,
-                    tool_choice: tool_choice.to_string(),
-                };
-                
-                match poly::ai::chat(&request) {
-                    Ok(resp) => serde_json::json!({
-                        "result": {
-                            "content": resp.content,
-                            "thinking": resp.thinking,
-                            "model": resp.model,
-                            "usage": resp.usage,
-                            "toolCalls": resp.tool_calls,
-                            "finishReason": resp.finish_reason
-                        }
-                    }).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_ai_openai" => {
-            #[cfg(feature = "native")]
-            {
-                let model = args.get("model").and_then(|v| v.as_str()).unwrap_or("gpt-4");
-                let messages = args.get("messages").and_then(|v| v.as_array());
-                let api_key = args.get("apiKey").and_then(|v| v.as_str());
-                let temperature = args.get("temperature").and_then(|v| v.as_f64()).unwrap_or(0.7) as f32;
-                let max_tokens = args.get("maxTokens").and_then(|v| v.as_u64()).map(|v| v as u32);
-                let tools_json = args.get("tools").and_then(|v| v.as_array());
-                let tool_choice = args.get("toolChoice").and_then(|v| v.as_str()).unwrap_or("auto");
-                
-                let chat_messages: Vec<poly::ai::ChatMessage> = messages
-                    .map(|arr| arr.iter().filter_map(|m| {
-                        let role = m.get("role")?.as_str()?;
-                        let content = m.get("content")?.as_str()?;
-                        Some(poly::ai::ChatMessage {
-                            role: match role {
-                                "system" => poly::ai::MessageRole::System,
-                                "assistant" => poly::ai::MessageRole::Assistant,
-                                _ => poly::ai::MessageRole::User,
-                            },
-                            content: content.to_string(),
-                        })
-                    }).collect())
-                    .unwrap_or_default();
-                
-                // Parse tools from JSON
-                let tools: Vec<poly::ai::Tool> = tools_json
-                    .map(|arr| arr.iter().filter_map(|t| {
-                        serde_json::from_value(t.clone()).ok()
-                    }).collect())
-                    .unwrap_or_default();
-                
-                let request = poly::ai::ChatRequest {
-                    provider: poly::ai::AiProvider::OpenAI,
-                    base_url: None,
-                    api_key: api_key.map(|s| s.to_string()),
-                    model: model.to_string(),
-                    messages: chat_messages,
-                    temperature,
-                    max_tokens,
-                    stream: false,
-                    enable_thinking: false,
-                    thinking_budget: None,
-                    tools,
-                    tool_choice: tool_choice.to_string(),
-                };
-                
-                match poly::ai::chat(&request) {
-                    Ok(resp) => serde_json::json!({
-                        "result": {
-                            "content": resp.content,
-                            "thinking": resp.thinking,
-                            "model": resp.model,
-                            "usage": resp.usage,
-                            "toolCalls": resp.tool_calls,
-                            "finishReason": resp.finish_reason
-                        }
-                    }).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_ai_anthropic" => {
-            #[cfg(feature = "native")]
-            {
-                let model = args.get("model").and_then(|v| v.as_str()).unwrap_or("claude-3-5-sonnet-20241022");
-                let messages = args.get("messages").and_then(|v| v.as_array());
-                let api_key = args.get("apiKey").and_then(|v| v.as_str());
-                let temperature = args.get("temperature").and_then(|v| v.as_f64()).unwrap_or(0.7) as f32;
-                let max_tokens = args.get("maxTokens").and_then(|v| v.as_u64()).map(|v| v as u32);
-                let enable_thinking = args.get("enableThinking").and_then(|v| v.as_bool()).unwrap_or(false);
-                let thinking_budget = args.get("thinkingBudget").and_then(|v| v.as_u64()).map(|v| v as u32);
-                let tools_json = args.get("tools").and_then(|v| v.as_array());
-                let tool_choice = args.get("toolChoice").and_then(|v| v.as_str()).unwrap_or("auto");
-                
-                let chat_messages: Vec<poly::ai::ChatMessage> = messages
-                    .map(|arr| arr.iter().filter_map(|m| {
-                        let role = m.get("role")?.as_str()?;
-                        let content = m.get("content")?.as_str()?;
-                        Some(poly::ai::ChatMessage {
-                            role: match role {
-                                "system" => poly::ai::MessageRole::System,
-                                "assistant" => poly::ai::MessageRole::Assistant,
-                                _ => poly::ai::MessageRole::User,
-                            },
-                            content: content.to_string(),
-                        })
-                    }).collect())
-                    .unwrap_or_default();
-                
-                // Parse tools from JSON
-                let tools: Vec<poly::ai::Tool> = tools_json
-                    .map(|arr| arr.iter().filter_map(|t| {
-                        serde_json::from_value(t.clone()).ok()
-                    }).collect())
-                    .unwrap_or_default();
-                
-                let request = poly::ai::ChatRequest {
-                    provider: poly::ai::AiProvider::Anthropic,
-                    base_url: None,
-                    api_key: api_key.map(|s| s.to_string()),
-                    model: model.to_string(),
-                    messages: chat_messages,
-                    temperature,
-                    max_tokens,
-                    stream: false,
-                    enable_thinking,
-                    thinking_budget,
-                    tools,
-                    tool_choice: tool_choice.to_string(),
-                };
-                
-                match poly::ai::chat(&request) {
-                    Ok(resp) => serde_json::json!({
-                        "result": {
-                            "content": resp.content,
-                            "thinking": resp.thinking,
-                            "model": resp.model,
-                            "usage": resp.usage,
-                            "toolCalls": resp.tool_calls,
-                            "finishReason": resp.finish_reason
-                        }
-                    }).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_ai_custom" => {
-            #[cfg(feature = "native")]
-            {
-                let base_url = args.get("baseUrl").and_then(|v| v.as_str()).unwrap_or("http://localhost:8080");
-                let model = args.get("model").and_then(|v| v.as_str()).unwrap_or("default");
-                let messages = args.get("messages").and_then(|v| v.as_array());
-                let api_key = args.get("apiKey").and_then(|v| v.as_str());
-                let temperature = args.get("temperature").and_then(|v| v.as_f64()).unwrap_or(0.7) as f32;
-                let max_tokens = args.get("maxTokens").and_then(|v| v.as_u64()).map(|v| v as u32);
-                let tools_json = args.get("tools").and_then(|v| v.as_array());
-                let tool_choice = args.get("toolChoice").and_then(|v| v.as_str()).unwrap_or("auto");
-                
-                let chat_messages: Vec<poly::ai::ChatMessage> = messages
-                    .map(|arr| arr.iter().filter_map(|m| {
-                        let role = m.get("role")?.as_str()?;
-                        let content = m.get("content")?.as_str()?;
-                        Some(poly::ai::ChatMessage {
-                            role: match role {
-                                "system" => poly::ai::MessageRole::System,
-                                "assistant" => poly::ai::MessageRole::Assistant,
-                                _ => poly::ai::MessageRole::User,
-                            },
-                            content: content.to_string(),
-                        })
-                    }).collect())
-                    .unwrap_or_default();
-                
-                // Parse tools from JSON
-                let tools: Vec<poly::ai::Tool> = tools_json
-                    .map(|arr| arr.iter().filter_map(|t| {
-                        serde_json::from_value(t.clone()).ok()
-                    }).collect())
-                    .unwrap_or_default();
-                
-                let request = poly::ai::ChatRequest {
-                    provider: poly::ai::AiProvider::Custom,
-                    base_url: Some(base_url.to_string()),
-                    api_key: api_key.map(|s| s.to_string()),
-                    model: model.to_string(),
-                    messages: chat_messages,
-                    temperature,
-                    max_tokens,
-                    stream: false,
-                    enable_thinking: false,
-                    thinking_budget: None,
-                    tools,
-                    tool_choice: tool_choice.to_string(),
-                };
-                
-                match poly::ai::chat(&request) {
-                    Ok(resp) => serde_json::json!({
-                        "result": {
-                            "content": resp.content,
-                            "thinking": resp.thinking,
-                            "model": resp.model,
-                            "usage": resp.usage,
-                            "toolCalls": resp.tool_calls,
-                            "finishReason": resp.finish_reason
-                        }
-                    }).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_ai_chat" => {
-            #[cfg(feature = "native")]
-            {
-                let provider_str = args.get("provider").and_then(|v| v.as_str()).unwrap_or("ollama");
-                let model = args.get("model").and_then(|v| v.as_str()).unwrap_or("llama3");
-                let messages = args.get("messages").and_then(|v| v.as_array());
-                let base_url = args.get("baseUrl").and_then(|v| v.as_str());
-                let api_key = args.get("apiKey").and_then(|v| v.as_str());
-                let temperature = args.get("temperature").and_then(|v| v.as_f64()).unwrap_or(0.7) as f32;
-                let max_tokens = args.get("maxTokens").and_then(|v| v.as_u64()).map(|v| v as u32);
-                let enable_thinking = args.get("enableThinking").and_then(|v| v.as_bool()).unwrap_or(false);
-                let thinking_budget = args.get("thinkingBudget").and_then(|v| v.as_u64()).map(|v| v as u32);
-                let tools_json = args.get("tools").and_then(|v| v.as_array());
-                let tool_choice = args.get("toolChoice").and_then(|v| v.as_str()).unwrap_or("auto");
-                
-                let provider = match provider_str {
-                    "openai" => poly::ai::AiProvider::OpenAI,
-                    "anthropic" => poly::ai::AiProvider::Anthropic,
-                    "custom" => poly::ai::AiProvider::Custom,
-                    _ => poly::ai::AiProvider::Ollama,
-                };
-                
-                let chat_messages: Vec<poly::ai::ChatMessage> = messages
-                    .map(|arr| arr.iter().filter_map(|m| {
-                        let role = m.get("role")?.as_str()?;
-                        let content = m.get("content")?.as_str()?;
-                        Some(poly::ai::ChatMessage {
-                            role: match role {
-                                "system" => poly::ai::MessageRole::System,
-                                "assistant" => poly::ai::MessageRole::Assistant,
-                                _ => poly::ai::MessageRole::User,
-                            },
-                            content: content.to_string(),
-                        })
-                    }).collect())
-                    .unwrap_or_default();
-                
-                // Parse tools from JSON
-                let tools: Vec<poly::ai::Tool> = tools_json
-                    .map(|arr| arr.iter().filter_map(|t| {
-                        serde_json::from_value(t.clone()).ok()
-                    }).collect())
-                    .unwrap_or_default();
-                
-                let request = poly::ai::ChatRequest {
-                    provider,
-                    base_url: base_url.map(|s| s.to_string()),
-                    api_key: api_key.map(|s| s.to_string()),
-                    model: model.to_string(),
-                    messages: chat_messages,
-                    temperature,
-                    max_tokens,
-                    stream: false,
-                    enable_thinking,
-                    thinking_budget,
-                    tools,
-                    tool_choice: tool_choice.to_string(),
-                };
-                
-                match poly::ai::chat(&request) {
-                    Ok(resp) => serde_json::json!({
-                        "result": {
-                            "content": resp.content,
-                            "thinking": resp.thinking,
-                            "model": resp.model,
-                            "usage": resp.usage,
-                            "provider": resp.provider,
-                            "toolCalls": resp.tool_calls,
-                            "finishReason": resp.finish_reason
-                        }
-                    }).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        // AI Streaming API
-        "__poly_ai_stream_start" => {
-            #[cfg(feature = "native")]
-            {
-                let model = args.get("model").and_then(|v| v.as_str()).unwrap_or("llama3");
-                let messages = args.get("messages").and_then(|v| v.as_array());
-                let temperature = args.get("temperature").and_then(|v| v.as_f64()).unwrap_or(0.7) as f32;
-                let think = args.get("think").and_then(|v| v.as_bool()).unwrap_or(false);
-                
-                let chat_messages: Vec<poly::ai::ChatMessage> = messages
-                    .map(|arr| arr.iter().filter_map(|m| {
-                        let role = m.get("role")?.as_str()?;
-                        let content = m.get("content")?.as_str()?;
-                        Some(poly::ai::ChatMessage {
-                            role: match role {
-                                "system" => poly::ai::MessageRole::System,
-                                "assistant" => poly::ai::MessageRole::Assistant,
-                                _ => poly::ai::MessageRole::User,
-                            },
-                            content: content.to_string(),
-                        })
-                    }).collect())
-                    .unwrap_or_default();
-                
-                match poly::ai::stream_start_ollama(model, chat_messages, temperature, think) {
-                    Ok(stream_id) => serde_json::json!({"result": {"streamId": stream_id}}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "Native feature not enabled"}).to_string()
-            }
-        }
-        "__poly_ai_stream_poll" => {
-            let stream_id = args.get("streamId").and_then(|v| v.as_str()).unwrap_or("");
-            match poly::ai::stream_poll(stream_id) {
-                Ok((chunks, done)) => serde_json::json!({
-                    "result": {
-                        "chunks": chunks,
-                        "done": done
-                    }
-                }).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_ai_stream_cancel" => {
-            let stream_id = args.get("streamId").and_then(|v| v.as_str()).unwrap_or("");
-            match poly::ai::stream_cancel(stream_id) {
-                Ok(_) => serde_json::json!({"result": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_ai_stream_list" => {
-            let streams = poly::ai::stream_list();
-            serde_json::json!({"result": streams}).to_string()
-        }
-        // Database API
-        "__poly_db_open" | "__poly_db_close" | "__poly_db_execute" | "__poly_db_query" | "__poly_db_query_one" => {
-            handle_db_request(fn_name, args)
-        }
-        // Browser API - Tab Management
-        "__poly_browser_create_tab" => {
-            let url = args.get("url").and_then(|v| v.as_str());
-            let id = poly::browser::create_tab(url);
-            serde_json::json!({"result": id}).to_string()
-        }
-        "__poly_browser_close_tab" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            let success = poly::browser::close_tab(id);
-            serde_json::json!({"result": success}).to_string()
-        }
-        "__poly_browser_get_tab" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            match poly::browser::get_tab(id) {
-                Some(tab) => serde_json::json!({
-                    "result": {
-                        "id": tab.id,
-                        "url": tab.url,
-                        "title": tab.title,
-                        "canGoBack": tab.can_go_back,
-                        "canGoForward": tab.can_go_forward,
-                        "isLoading": tab.is_loading
-                    }
-                }).to_string(),
-                None => serde_json::json!({"result": null}).to_string(),
-            }
-        }
-        "__poly_browser_list_tabs" => {
-            let tabs: Vec<serde_json::Value> = poly::browser::list_tabs().iter().map(|tab| {
-                serde_json::json!({
-                    "id": tab.id,
-                    "url": tab.url,
-                    "title": tab.title,
-                    "canGoBack": tab.can_go_back,
-                    "canGoForward": tab.can_go_forward,
-                    "isLoading": tab.is_loading
-                })
-            }).collect();
-            serde_json::json!({"result": tabs}).to_string()
-        }
-        "__poly_browser_navigate" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            let url = args.get("url").and_then(|v| v.as_str()).unwrap_or("");
-            match poly::browser::navigate(id, url) {
-                Ok(_) => serde_json::json!({"result": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_browser_back" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            match poly::browser::go_back(id) {
-                Ok(url) => serde_json::json!({"result": url}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_browser_forward" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            match poly::browser::go_forward(id) {
-                Ok(url) => serde_json::json!({"result": url}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_browser_set_title" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            let title = args.get("title").and_then(|v| v.as_str()).unwrap_or("");
-            poly::browser::set_tab_title(id, title);
-            serde_json::json!({"result": true}).to_string()
-        }
-        "__poly_browser_set_loading" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            let loading = args.get("loading").and_then(|v| v.as_bool()).unwrap_or(false);
-            poly::browser::set_tab_loading(id, loading);
-            serde_json::json!({"result": true}).to_string()
-        }
-        "__poly_browser_get_history" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            let history = poly::browser::get_history(id);
-            serde_json::json!({"result": history}).to_string()
-        }
-        "__poly_browser_clear_history" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            poly::browser::clear_history(id);
-            serde_json::json!({"result": true}).to_string()
-        }
-        // Browser API - Fetch URL content (bypasses iframe restrictions)
-        "__poly_browser_fetch" => {
-            let url = args.get("url").and_then(|v| v.as_str()).unwrap_or("");
-            
-            // Check sovereignty - extract domain from URL
-            if poly::sovereignty_enabled() {
-                let domain = url.split("://")
-                    .nth(1)
-                    .and_then(|s| s.split('/').next())
-                    .unwrap_or(url);
-                let perm = poly::Permission::HttpConnect(poly::sovereignty::DomainScope::Domain(domain.to_string()));
-                if let Err(e) = poly::check_permission(&perm) {
-                    return serde_json::json!({"error": e}).to_string();
-                }
-            }
-            
-            match reqwest::blocking::get(url) {
-                Ok(response) => {
-                    let status = response.status().as_u16();
-                    let headers: std::collections::HashMap<String, String> = response.headers()
-                        .iter()
-                        .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.to_string(), v.to_string())))
-                        .collect();
-                    
-                    match response.text() {
-                        Ok(body) => serde_json::json!({
-                            "result": {
-                                "status": status,
-                                "headers": headers,
-                                "body": body
-                            }
-                        }).to_string(),
-                        Err(e) => serde_json::json!({"error": e.to_string()}).to_string(),
-                    }
-                }
-                Err(e) => serde_json::json!({"error": e.to_string()}).to_string(),
-            }
-        }
-        // Browser API - Open real WebView window
-        "__poly_browser_open_window" => {
-            let url = args.get("url").and_then(|v| v.as_str()).unwrap_or("about:blank");
-            let title = args.get("title").and_then(|v| v.as_str()).unwrap_or("Browser");
-            let width = args.get("width").and_then(|v| v.as_u64()).unwrap_or(1024) as u32;
-            let height = args.get("height").and_then(|v| v.as_u64()).unwrap_or(768) as u32;
-            
-            #[cfg(feature = "native")]
-            {
-                match poly::browser::open_webview(url, title, width, height) {
-                    Ok(id) => serde_json::json!({"result": id}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "WebView requires native feature"}).to_string()
-            }
-        }
-        "__poly_browser_window_navigate" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            let url = args.get("url").and_then(|v| v.as_str()).unwrap_or("");
-            
-            #[cfg(feature = "native")]
-            {
-                match poly::browser::webview_navigate(id, url) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "WebView requires native feature"}).to_string()
-            }
-        }
-        "__poly_browser_window_close" => {
-            let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-            
-            #[cfg(feature = "native")]
-            {
-                match poly::browser::webview_close(id) {
-                    Ok(_) => serde_json::json!({"result": true}).to_string(),
-                    Err(e) => serde_json::json!({"error": e}).to_string(),
-                }
-            }
-            #[cfg(not(feature = "native"))]
-            {
-                serde_json::json!({"error": "WebView requires native feature"}).to_string()
-            }
-        }
-        // Titlebar API
-        "__poly_titlebar_set" => {
-            let config = poly::titlebar::TitlebarConfig {
-                enabled: args.get("enabled").and_then(|v| v.as_bool()).unwrap_or(true),
-                height: args.get("height").and_then(|v| v.as_u64()).unwrap_or(40) as u32,
-                html: args.get("html").and_then(|v| v.as_str()).unwrap_or("").to_string(),
-                css: args.get("css").and_then(|v| v.as_str()).unwrap_or("").to_string(),
-                js: args.get("js").and_then(|v| v.as_str()).unwrap_or("").to_string(),
-                background: args.get("background").and_then(|v| v.as_str()).unwrap_or("#1a1a1f").to_string(),
-            };
-            poly::titlebar::set_titlebar(config);
-            serde_json::json!({"result": true}).to_string()
-        }
-        "__poly_titlebar_get" => {
-            let config = poly::titlebar::get_titlebar();
-            serde_json::json!({
-                "result": {
-                    "enabled": config.enabled,
-                    "height": config.height,
-                    "html": config.html,
-                    "css": config.css,
-                    "js": config.js,
-                    "background": config.background
-                }
-            }).to_string()
-        }
-        "__poly_titlebar_set_enabled" => {
-            let enabled = args.get("enabled").and_then(|v| v.as_bool()).unwrap_or(false);
-            poly::titlebar::set_enabled(enabled);
-            serde_json::json!({"result": true}).to_string()
-        }
-        "__poly_titlebar_set_height" => {
-            let height = args.get("height").and_then(|v| v.as_u64()).unwrap_or(40) as u32;
-            poly::titlebar::set_height(height);
-            serde_json::json!({"result": true}).to_string()
-        }
-        "__poly_titlebar_set_html" => {
-            let html = args.get("html").and_then(|v| v.as_str()).unwrap_or("");
-            poly::titlebar::set_html(html);
-            serde_json::json!({"result": true}).to_string()
-        }
-        "__poly_titlebar_set_css" => {
-            let css = args.get("css").and_then(|v| v.as_str()).unwrap_or("");
-            poly::titlebar::set_css(css);
-            serde_json::json!({"result": true}).to_string()
-        }
-        "__poly_titlebar_set_js" => {
-            let js = args.get("js").and_then(|v| v.as_str()).unwrap_or("");
-            poly::titlebar::set_js(js);
-            serde_json::json!({"result": true}).to_string()
-        }
-        "__poly_titlebar_navigate" => {
-            // This will be handled specially - it navigates the WebView but injects titlebar
-            let url = args.get("url").and_then(|v| v.as_str()).unwrap_or("");
-            // Store the URL for the WebView to navigate to
-            // The actual navigation happens in the native code
-            serde_json::json!({"result": url, "action": "navigate"}).to_string()
-        }
-        // WebView API - Multi-WebView management
-        "__poly_webview_create" => {
-            let id = args.get("id").and_then(|v| v.as_str()).unwrap_or("");
-            let url = args.get("url").and_then(|v| v.as_str()).unwrap_or("about:blank");
-            let html = args.get("html").and_then(|v| v.as_str()).map(|s| s.to_string());
-            let x = args.get("x").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-            let y = args.get("y").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-            let width = args.get("width").and_then(|v| v.as_u64()).unwrap_or(800) as u32;
-            let height = args.get("height").and_then(|v| v.as_u64()).unwrap_or(600) as u32;
-            let visible = args.get("visible").and_then(|v| v.as_bool()).unwrap_or(true);
-            let transparent = args.get("transparent").and_then(|v| v.as_bool()).unwrap_or(false);
-            let devtools = args.get("devtools").and_then(|v| v.as_bool()).unwrap_or(false);
-            let user_agent = args.get("userAgent").and_then(|v| v.as_str()).map(|s| s.to_string());
-            let zoom_level = args.get("zoomLevel").and_then(|v| v.as_f64()).unwrap_or(1.0);
-            let autoplay = args.get("autoplay").and_then(|v| v.as_bool()).unwrap_or(true);
-            
-            let config = poly::webview::WebViewConfig {
-                id: id.to_string(),
-                url: url.to_string(),
-                html,
-                bounds: poly::webview::WebViewBounds { x, y, width, height },
-                visible,
-                transparent,
-                devtools,
-                user_agent,
-                zoom_level,
-                autoplay,
-            };
-            
-            match poly::webview::create(config) {
-                Ok(_) => serde_json::json!({"success": true, "id": id}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_webview_navigate" => {
-            let id = args.get("id").and_then(|v| v.as_str()).unwrap_or("");
-            let url = args.get("url").and_then(|v| v.as_str()).unwrap_or("about:blank");
-            match poly::webview::navigate(id, url) {
-                Ok(_) => serde_json::json!({"success": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_webview_load_html" => {
-            let id = args.get("id").and_then(|v| v.as_str()).unwrap_or("");
-            let html = args.get("html").and_then(|v| v.as_str()).unwrap_or("");
-            match poly::webview::load_html(id, html) {
-                Ok(_) => serde_json::json!({"success": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_webview_go_back" => {
-            let id = args.get("id").and_then(|v| v.as_str()).unwrap_or("");
-            match poly::webview::go_back(id) {
-                Ok(_) => serde_json::json!({"success": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_webview_go_forward" => {
-            let id = args.get("id").and_then(|v| v.as_str()).unwrap_or("");
-            match poly::webview::go_forward(id) {
-                Ok(_) => serde_json::json!({"success": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_webview_reload" => {
-            let id = args.get("id").and_then(|v| v.as_str()).unwrap_or("");
-            match poly::webview::reload(id) {
-                Ok(_) => serde_json::json!({"success": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_webview_stop" => {
-            let id = args.get("id").and_then(|v| v.as_str()).unwrap_or("");
-            match poly::webview::stop(id) {
-                Ok(_) => serde_json::json!({"success": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_webview_set_bounds" => {
-            let id = args.get("id").and_then(|v| v.as_str()).unwrap_or("");
-            let x = args.get("x").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-            let y = args.get("y").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-            let width = args.get("width").and_then(|v| v.as_u64()).unwrap_or(800) as u32;
-            let height = args.get("height").and_then(|v| v.as_u64()).unwrap_or(600) as u32;
-            match poly::webview::set_bounds(id, poly::webview::WebViewBounds { x, y, width, height }) {
-                Ok(_) => serde_json::json!({"success": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_webview_get_bounds" => {
-            let id = args.get("id").and_then(|v| v.as_str()).unwrap_or("");
-            match poly::webview::get_bounds(id) {
-                Ok(b) => serde_json::json!({"x": b.x, "y": b.y, "width": b.width, "height": b.height}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_webview_eval" => {
-            let id = args.get("id").and_then(|v| v.as_str()).unwrap_or("");
-            let script = args.get("script").and_then(|v| v.as_str()).unwrap_or("");
-            match poly::webview::eval(id, script) {
-                Ok(_) => serde_json::json!({"success": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_webview_destroy" => {
-            let id = args.get("id").and_then(|v| v.as_str()).unwrap_or("");
-            match poly::webview::destroy(id) {
-                Ok(_) => serde_json::json!({"success": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_webview_list" => {
-            let list: Vec<_> = poly::webview::list().iter().map(|s| {
-                serde_json::json!({
-                    "id": s.id,
-                    "url": s.url,
-                    "title": s.title,
-                    "visible": s.visible,
-                    "isLoading": s.is_loading,
-                    "canGoBack": s.can_go_back,
-                    "canGoForward": s.can_go_forward,
-                    "zoomLevel": s.zoom_level,
-                    "bounds": { "x": s.bounds.x, "y": s.bounds.y, "width": s.bounds.width, "height": s.bounds.height }
-                })
-            }).collect();
-            serde_json::json!(list).to_string()
-        }
-        "__poly_webview_get" => {
-            let id = args.get("id").and_then(|v| v.as_str()).unwrap_or("");
-            match poly::webview::get(id) {
-                Some(s) => serde_json::json!({
-                    "id": s.id,
-                    "url": s.url,
-                    "title": s.title,
-                    "visible": s.visible,
-                    "isLoading": s.is_loading,
-                    "canGoBack": s.can_go_back,
-                    "canGoForward": s.can_go_forward,
-                    "zoomLevel": s.zoom_level,
-                    "bounds": { "x": s.bounds.x, "y": s.bounds.y, "width": s.bounds.width, "height": s.bounds.height }
-                }).to_string(),
-                None => serde_json::json!({"error": format!("WebView '{}' not found", id)}).to_string(),
-            }
-        }
-        "__poly_webview_set_zoom" => {
-            let id = args.get("id").and_then(|v| v.as_str()).unwrap_or("");
-            let level = args.get("level").and_then(|v| v.as_f64()).unwrap_or(1.0);
-            match poly::webview::set_zoom(id, level) {
-                Ok(_) => serde_json::json!({"success": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_webview_poll_events" => {
-            let events = poly::webview::take_events();
-            let json_events: Vec<_> = events.iter().map(|e| {
-                match e {
-                    poly::webview::WebViewEvent::NavigationStarted { id, url } => 
-                        serde_json::json!({"type": "navigate", "id": id, "url": url}),
-                    poly::webview::WebViewEvent::NavigationFinished { id, url } => 
-                        serde_json::json!({"type": "navigateFinish", "id": id, "url": url}),
-                    poly::webview::WebViewEvent::TitleChanged { id, title } => 
-                        serde_json::json!({"type": "titleChange", "id": id, "title": title}),
-                    poly::webview::WebViewEvent::LoadStarted { id } => 
-                        serde_json::json!({"type": "loadStart", "id": id}),
-                    poly::webview::WebViewEvent::LoadFinished { id } => 
-                        serde_json::json!({"type": "loadFinish", "id": id}),
-                    poly::webview::WebViewEvent::NewWindowRequested { id, url, target } => 
-                        serde_json::json!({"type": "newWindow", "id": id, "url": url, "target": target}),
-                    poly::webview::WebViewEvent::DownloadRequested { id, url, filename } => 
-                        serde_json::json!({"type": "download", "id": id, "url": url, "filename": filename}),
-                    poly::webview::WebViewEvent::Closed { id } => 
-                        serde_json::json!({"type": "close", "id": id}),
-                    poly::webview::WebViewEvent::Error { id, error } => 
-                        serde_json::json!({"type": "error", "id": id, "error": error}),
-                    poly::webview::WebViewEvent::FaviconChanged { id, url } => 
-                        serde_json::json!({"type": "favicon", "id": id, "url": url}),
-                    poly::webview::WebViewEvent::HistoryChanged { id, can_go_back, can_go_forward } => 
-                        serde_json::json!({"type": "historyChange", "id": id, "canGoBack": can_go_back, "canGoForward": can_go_forward}),
-                    poly::webview::WebViewEvent::FullscreenRequested { id, enter } => 
-                        serde_json::json!({"type": "fullscreen", "id": id, "enter": enter}),
-                    poly::webview::WebViewEvent::PermissionRequested { id, permission, origin } => 
-                        serde_json::json!({"type": "permission", "id": id, "permission": permission, "origin": origin}),
-                }
-            }).collect();
-            serde_json::json!({"events": json_events}).to_string()
-        }
-        "__poly_webview_respond_permission" => {
-            let id = args.get("id").and_then(|v| v.as_str()).unwrap_or("");
-            let permission = args.get("permission").and_then(|v| v.as_str()).unwrap_or("");
-            let granted = args.get("granted").and_then(|v| v.as_bool()).unwrap_or(false);
-            match poly::webview::respond_to_permission(id, permission, granted) {
-                Ok(_) => serde_json::json!({"success": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_webview_set_visible" => {
-            let id = args.get("id").and_then(|v| v.as_str()).unwrap_or("");
-            let visible = args.get("visible").and_then(|v| v.as_bool()).unwrap_or(true);
-            match poly::webview::set_visible(id, visible) {
-                Ok(_) => serde_json::json!({"success": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_webview_focus" => {
-            let id = args.get("id").and_then(|v| v.as_str()).unwrap_or("");
-            match poly::webview::focus(id) {
-                Ok(_) => serde_json::json!({"success": true}).to_string(),
-                Err(e) => serde_json::json!({"error": e}).to_string(),
-            }
-        }
-        "__poly_webview_set_main_bounds" => {
-            let x = args.get("x").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-            let y = args.get("y").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-            let width = args.get("width").and_then(|v| v.as_u64()).unwrap_or(800) as u32;
-            let height = args.get("height").and_then(|v| v.as_u64()).unwrap_or(600) as u32;
-            poly::webview::set_main_bounds(poly::webview::WebViewBounds { x, y, width, height });
-            serde_json::json!({"success": true}).to_string()
-        }
-        // MultiView API - Create windows with multiple WebViews
-        "__poly_multiview_create" => {
-            let title = args.get("title").and_then(|v| v.as_str()).unwrap_or("Poly MultiView").to_string();
-            let width = args.get("width").and_then(|v| v.as_u64()).unwrap_or(1024) as u32;
-            let height = args.get("height").and_then(|v| v.as_u64()).unwrap_or(768) as u32;
-            let decorations = args.get("decorations").and_then(|v| v.as_bool()).unwrap_or(false);
-            let resizable = args.get("resizable").and_then(|v| v.as_bool()).unwrap_or(true);
-            let icon_path = args.get("icon").and_then(|v| v.as_str()).map(|s| s.to_string());
-            
-            let views_arr = args.get("views").and_then(|v| v.as_array()).cloned().unwrap_or_default();
-            let views: Vec<poly::multiview::ViewConfig> = views_arr.iter().map(|v| {
-                poly::multiview::ViewConfig {
-                    id: v.get("id").and_then(|x| x.as_str()).unwrap_or("view").to_string(),
-                    url: v.get("url").and_then(|x| x.as_str()).unwrap_or("about:blank").to_string(),
-                    html: v.get("html").and_then(|x| x.as_str()).map(|s| s.to_string()),
-                    x: v.get("x").and_then(|x| x.as_i64()).unwrap_or(0) as i32,
-                    y: v.get("y").and_then(|x| x.as_i64()).unwrap_or(0) as i32,
-                    width: v.get("width").and_then(|x| x.as_u64()).unwrap_or(800) as u32,
-                    height: v.get("height").and_then(|x| x.as_u64()).unwrap_or(600) as u32,
-                    transparent: v.get("transparent").and_then(|x| x.as_bool()).unwrap_or(false),
-                    devtools: v.get("devtools").and_then(|x| x.as_bool()).unwrap_or(false),
-                }
-            }).collect();
-            
-            let config = poly::multiview::MultiViewWindowConfig {
-                title,
-                width,
-                height,
-                decorations,
-                resizable,
-                views,
-                icon_path,
-            };
-            
-            let window_id = poly::multiview::create_window(config.clone());
-            
-            // In native mode, actually create the window
-            #[cfg(feature = "native")]
-            {
-                if let Err(e) = poly::multiview_native::create_multiview_window(window_id, config) {
-                    return serde_json::json!({"error": e}).to_string();
-                }
-            }
-            
-            serde_json::json!({"id": window_id}).to_string()
-        }
-        "__poly_multiview_navigate" => {
-            let window_id = args.get("windowId").and_then(|v| v.as_u64()).unwrap_or(0);
-            let view_id = args.get("viewId").and_then(|v| v.as_str()).unwrap_or("");
-            let url = args.get("url").and_then(|v| v.as_str()).unwrap_or("");
-            
-            poly::multiview::navigate(window_id, view_id, url);
-            
-            #[cfg(feature = "native")]
-            poly::multiview_native::queue_navigation(window_id, view_id, url);
-            
-            serde_json::json!({"success": true}).to_string()
-        }
-        "__poly_multiview_post_message" => {
-            let window_id = args.get("windowId").and_then(|v| v.as_u64()).unwrap_or(0);
-            let view_id = args.get("viewId").and_then(|v| v.as_str()).unwrap_or("");
-            let message = args.get("message").and_then(|v| v.as_str()).unwrap_or("{}");
-            
-            poly::multiview::post_message(window_id, view_id, message);
-            serde_json::json!({"success": true}).to_string()
-        }
-        "__poly_multiview_set_bounds" => {
-            let window_id = args.get("windowId").and_then(|v| v.as_u64()).unwrap_or(0);
-            let view_id = args.get("viewId").and_then(|v| v.as_str()).unwrap_or("");
-            let x = args.get("x").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-            let y = args.get("y").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-            let width = args.get("width").and_then(|v| v.as_u64()).unwrap_or(800) as u32;
-            let height = args.get("height").and_then(|v| v.as_u64()).unwrap_or(600) as u32;
-            
-            poly::multiview::set_view_bounds(window_id, view_id, x, y, width, height);
-            serde_json::json!({"success": true}).to_string()
-        }
-        "__poly_multiview_close" => {
-            let window_id = args.get("windowId").and_then(|v| v.as_u64()).unwrap_or(0);
-            poly::multiview::close_window(window_id);
-            serde_json::json!({"success": true}).to_string()
-        }
-        "__poly_multiview_list" => {
-            let windows = poly::multiview::list_windows();
-            let list: Vec<serde_json::Value> = windows.iter().map(|w| {
-                serde_json::json!({
-                    "id": w.id,
-                    "title": w.title,
-                    "views": w.views
-                })
-            }).collect();
-            serde_json::json!({"windows": list}).to_string()
-        }
-        "__poly_multiview_get" => {
-            let window_id = args.get("windowId").and_then(|v| v.as_u64()).unwrap_or(0);
-            match poly::multiview::get_window(window_id) {
-                Some(w) => serde_json::json!({
-                    "id": w.id,
-                    "title": w.title,
-                    "views": w.views
-                }).to_string(),
-                None => serde_json::json!({"error": "Window not found"}).to_string(),
-            }
-        }
-        _ => serde_json::json!({"error": format!("Unknown system API: {}", fn_name)}).to_string(),
-    }
-}
-
-/// Get common directory paths
-fn dirs_path(name: &str) -> Option<String> {
-    match name {
-        "home" => std::env::var("HOME").ok()
-            .or_else(|| std::env::var("USERPROFILE").ok()),
-        "data" | "appData" => {
-            #[cfg(target_os = "windows")]
-            { std::env::var("APPDATA").ok() }
-            #[cfg(target_os = "macos")]
-            { std::env::var("HOME").ok().map(|h| format!("{}/Library/Application Support", h)) }
-            #[cfg(target_os = "linux")]
-            { std::env::var("XDG_DATA_HOME").ok().or_else(|| std::env::var("HOME").ok().map(|h| format!("{}/.local/share", h))) }
-        }
-        "config" => {
-            #[cfg(target_os = "windows")]
-            { std::env::var("APPDATA").ok() }
-            #[cfg(target_os = "macos")]
-            { std::env::var("HOME").ok().map(|h| format!("{}/Library/Preferences", h)) }
-            #[cfg(target_os = "linux")]
-            { std::env::var("XDG_CONFIG_HOME").ok().or_else(|| std::env::var("HOME").ok().map(|h| format!("{}/.config", h))) }
-        }
-        "cache" => {
-            #[cfg(target_os = "windows")]
-            { std::env::var("LOCALAPPDATA").ok().map(|p| format!("{}\\Temp", p)) }
-            #[cfg(target_os = "macos")]
-            { std::env::var("HOME").ok().map(|h| format!("{}/Library/Caches", h)) }
-            #[cfg(target_os = "linux")]
-            { std::env::var("XDG_CACHE_HOME").ok().or_else(|| std::env::var("HOME").ok().map(|h| format!("{}/.cache", h))) }
-        }
-        "desktop" => {
-            #[cfg(target_os = "windows")]
-            { std::env::var("USERPROFILE").ok().map(|h| format!("{}\\Desktop", h)) }
-            #[cfg(not(target_os = "windows"))]
-            { std::env::var("HOME").ok().map(|h| format!("{}/Desktop", h)) }
-        }
-        "documents" => {
-            #[cfg(target_os = "windows")]
-            { std::env::var("USERPROFILE").ok().map(|h| format!("{}\\Documents", h)) }
-            #[cfg(not(target_os = "windows"))]
-            { std::env::var("HOME").ok().map(|h| format!("{}/Documents", h)) }
-        }
-        "downloads" => {
-            #[cfg(target_os = "windows")]
-            { std::env::var("USERPROFILE").ok().map(|h| format!("{}\\Downloads", h)) }
-            #[cfg(not(target_os = "windows"))]
-            { std::env::var("HOME").ok().map(|h| format!("{}/Downloads", h)) }
-        }
-        _ => None,
-    }
-}
-
-/// Handle HTTP requests
-fn handle_http_request(fn_name: &str, args: &serde_json::Value) -> String {
-    #[cfg(feature = "native")]
-    {
-        use reqwest::blocking::Client;
-        use std::time::Duration;
-        
-        let url = args.get("url").and_then(|v| v.as_str()).unwrap_or("");
-        if url.is_empty() {
-            return serde_json::json!({"error": "URL is required"}).to_string();
-        }
-        
-        // Sovereignty check - verify HTTP permission for this domain
-        if let Err(e) = poly::sovereignty::checks::http(url) {
-            return serde_json::json!({"error": e}).to_string();
-        }
-        
-        let client = Client::builder()
-            .timeout(Duration::from_secs(args.get("timeout").and_then(|v| v.as_u64()).unwrap_or(30)))
-            .build();
-        
-        let client = match client {
-            Ok(c) => c,
-            Err(e) => return serde_json::json!({"error": format!("Failed to create HTTP client: {}", e)}).to_string(),
-        };
-        
-        // Build headers
-        let mut headers = reqwest::header::HeaderMap::new();
-        if let Some(h) = args.get("headers").and_then(|v| v.as_object()) {
-            for (key, value) in h {
-                if let Some(val) = value.as_str() {
-                    if let (Ok(name), Ok(val)) = (
-                        reqwest::header::HeaderName::from_bytes(key.as_bytes()),
-                        reqwest::header::HeaderValue::from_str(val)
-                    ) {
-                        headers.insert(name, val);
-                    }
-                }
-            }
-        }
-        
-        let method = match fn_name {
-            "__poly_http_get" => "GET",
-            "__poly_http_post" => "POST",
-            "__poly_http_put" => "PUT",
-            "__poly_http_patch" => "PATCH",
-            "__poly_http_delete" => "DELETE",
-            "__poly_http_request" => args.get("method").and_then(|v| v.as_str()).unwrap_or("GET"),
-            _ => "GET",
-        };
-        
-        let mut request = match method.to_uppercase().as_str() {
-            "GET" => client.get(url),
-            "POST" => client.post(url),
-            "PUT" => client.put(url),
-            "PATCH" => client.patch(url),
-            "DELETE" => client.delete(url),
-            "HEAD" => client.head(url),
-            _ => client.get(url),
-        };
-        
-        request = request.headers(headers);
-        
-        // Add body for POST/PUT/PATCH
-        if let Some(body) = args.get("body") {
-            if body.is_string() {
-                request = request.body(body.as_str().unwrap_or("").to_string());
-            } else {
-                request = request.json(body);
-            }
-        }
-        
-        match request.send() {
-            Ok(response) => {
-                let status = response.status().as_u16();
-                let headers: std::collections::HashMap<String, String> = response.headers()
-                    .iter()
-                    .filter_map(|(k, v)| v.to_str().ok().map(|val| (k.to_string(), val.to_string())))
-                    .collect();
-                
-                let body = response.text().unwrap_or_default();
-                
-                // Try to parse as JSON
-                let data: serde_json::Value = serde_json::from_str(&body)
-                    .unwrap_or_else(|_| serde_json::Value::String(body));
-                
-                serde_json::json!({
-                    "result": {
-                        "status": status,
-                        "headers": headers,
-                        "data": data
-                    }
-                }).to_string()
-            }
-            Err(e) => serde_json::json!({"error": format!("HTTP request failed: {}", e)}).to_string(),
-        }
-    }
-    
-    #[cfg(not(feature = "native"))]
-    {
-        let _ = (fn_name, args);
-        serde_json::json!({"error": "HTTP API requires native feature"}).to_string()
-    }
-}
-
-/// Database connection storage
-#[cfg(feature = "native")]
-static DB_CONNECTIONS: once_cell::sync::Lazy<std::sync::Mutex<std::collections::HashMap<u64, rusqlite::Connection>>> = 
-    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(std::collections::HashMap::new()));
-
-#[cfg(feature = "native")]
-static DB_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
-
-/// Handle database requests
-fn handle_db_request(fn_name: &str, args: &serde_json::Value) -> String {
-    #[cfg(feature = "native")]
-    {
-        use std::sync::atomic::Ordering;
-        
-        // Sovereignty check - verify database permission
-        if let Err(e) = poly::sovereignty::checks::database() {
-            return serde_json::json!({"error": e}).to_string();
-        }
-        
-        match fn_name {
-            "__poly_db_open" => {
-                let path = args.get("path").and_then(|v| v.as_str()).unwrap_or(":memory:");
-                
-                // Additional check for file path if not in-memory
-                if path != ":memory:" {
-                    if let Err(e) = poly::sovereignty::checks::fs_write(path) {
-                        return serde_json::json!({"error": e}).to_string();
-                    }
-                }
-                
-                match rusqlite::Connection::open(path) {
-                    Ok(conn) => {
-                        let id = DB_COUNTER.fetch_add(1, Ordering::SeqCst);
-                        DB_CONNECTIONS.lock().unwrap().insert(id, conn);
-                        serde_json::json!({"result": {"id": id}}).to_string()
-                    }
-                    Err(e) => serde_json::json!({"error": format!("Failed to open database: {}", e)}).to_string(),
-                }
-            }
-            "__poly_db_close" => {
-                let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-                
-                if DB_CONNECTIONS.lock().unwrap().remove(&id).is_some() {
-                    serde_json::json!({"result": true}).to_string()
-                } else {
-                    serde_json::json!({"error": "Database connection not found"}).to_string()
-                }
-            }
-            "__poly_db_execute" => {
-                let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-                let sql = args.get("sql").and_then(|v| v.as_str()).unwrap_or("");
-                let params = args.get("params").and_then(|v| v.as_array());
-                
-                let connections = DB_CONNECTIONS.lock().unwrap();
-                if let Some(conn) = connections.get(&id) {
-                    let params_vec: Vec<Box<dyn rusqlite::ToSql>> = params
-                        .map(|arr| arr.iter().map(json_to_sql_param).collect())
-                        .unwrap_or_default();
-                    
-                    let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
-                    
-                    match conn.execute(sql, params_refs.as_slice()) {
-                        Ok(rows) => serde_json::json!({"result": {"changes": rows}}).to_string(),
-                        Err(e) => serde_json::json!({"error": format!("SQL error: {}", e)}).to_string(),
-                    }
-                } else {
-                    serde_json::json!({"error": "Database connection not found"}).to_string()
-                }
-            }
-            "__poly_db_query" => {
-                let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-                let sql = args.get("sql").and_then(|v| v.as_str()).unwrap_or("");
-                let params = args.get("params").and_then(|v| v.as_array());
-                
-                let connections = DB_CONNECTIONS.lock().unwrap();
-                if let Some(conn) = connections.get(&id) {
-                    let params_vec: Vec<Box<dyn rusqlite::ToSql>> = params
-                        .map(|arr| arr.iter().map(json_to_sql_param).collect())
-                        .unwrap_or_default();
-                    
-                    let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
-                    
-                    match conn.prepare(sql) {
-                        Ok(mut stmt) => {
-                            let column_names: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
-                            
-                            match stmt.query(params_refs.as_slice()) {
-                                Ok(mut rows) => {
-                                    let mut results: Vec<serde_json::Value> = Vec::new();
-                                    
-                                    while let Ok(Some(row)) = rows.next() {
-                                        let mut obj = serde_json::Map::new();
-                                        for (i, name) in column_names.iter().enumerate() {
-                                            let value = sql_value_to_json(row, i);
-                                            obj.insert(name.clone(), value);
-                                        }
-                                        results.push(serde_json::Value::Object(obj));
-                                    }
-                                    
-                                    serde_json::json!({"result": results}).to_string()
-                                }
-                                Err(e) => serde_json::json!({"error": format!("Query error: {}", e)}).to_string(),
-                            }
-                        }
-                        Err(e) => serde_json::json!({"error": format!("Prepare error: {}", e)}).to_string(),
-                    }
-                } else {
-                    serde_json::json!({"error": "Database connection not found"}).to_string()
-                }
-            }
-            "__poly_db_query_one" => {
-                let id = args.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
-                let sql = args.get("sql").and_then(|v| v.as_str()).unwrap_or("");
-                let params = args.get("params").and_then(|v| v.as_array());
-                
-                let connections = DB_CONNECTIONS.lock().unwrap();
-                if let Some(conn) = connections.get(&id) {
-                    let params_vec: Vec<Box<dyn rusqlite::ToSql>> = params
-                        .map(|arr| arr.iter().map(json_to_sql_param).collect())
-                        .unwrap_or_default();
-                    
-                    let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
-                    
-                    match conn.prepare(sql) {
-                        Ok(mut stmt) => {
-                            let column_names: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
-                            
-                            match stmt.query_row(params_refs.as_slice(), |row| {
-                                let mut obj = serde_json::Map::new();
-                                for (i, name) in column_names.iter().enumerate() {
-                                    let value = sql_value_to_json(row, i);
-                                    obj.insert(name.clone(), value);
-                                }
-                                Ok(serde_json::Value::Object(obj))
-                            }) {
-                                Ok(result) => serde_json::json!({"result": result}).to_string(),
-                                Err(rusqlite::Error::QueryReturnedNoRows) => serde_json::json!({"result": null}).to_string(),
-                                Err(e) => serde_json::json!({"error": format!("Query error: {}", e)}).to_string(),
-                            }
-                        }
-                        Err(e) => serde_json::json!({"error": format!("Prepare error: {}", e)}).to_string(),
-                    }
-                } else {
-                    serde_json::json!({"error": "Database connection not found"}).to_string()
-                }
-            }
-            _ => serde_json::json!({"error": "Unknown database operation"}).to_string(),
-        }
-    }
-    
-    #[cfg(not(feature = "native"))]
-    {
-        let _ = (fn_name, args);
-        serde_json::json!({"error": "Database API requires native feature"}).to_string()
-    }
-}
-
-/// Convert JSON value to SQL parameter
-#[cfg(feature = "native")]
-fn json_to_sql_param(value: &serde_json::Value) -> Box<dyn rusqlite::ToSql> {
-    match value {
-        serde_json::Value::Null => Box::new(rusqlite::types::Null),
-        serde_json::Value::Bool(b) => Box::new(*b),
-        serde_json::Value::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                Box::new(i)
-            } else if let Some(f) = n.as_f64() {
-                Box::new(f)
-            } else {
-                Box::new(n.to_string())
-            }
-        }
-        serde_json::Value::String(s) => Box::new(s.clone()),
-        _ => Box::new(value.to_string()),
-    }
-}
-
-/// Convert SQL value to JSON
-#[cfg(feature = "native")]
-fn sql_value_to_json(row: &rusqlite::Row, idx: usize) -> serde_json::Value {
-    // Try different types
-    if let Ok(v) = row.get::<_, i64>(idx) {
-        return serde_json::Value::Number(v.into());
-    }
-    if let Ok(v) = row.get::<_, f64>(idx) {
-        return serde_json::json!(v);
-    }
-    if let Ok(v) = row.get::<_, String>(idx) {
-        return serde_json::Value::String(v);
-    }
-    if let Ok(v) = row.get::<_, Vec<u8>>(idx) {
-        return serde_json::Value::String(base64_encode(&v));
-    }
-    if let Ok(v) = row.get::<_, bool>(idx) {
-        return serde_json::Value::Bool(v);
-    }
-    serde_json::Value::Null
-}
-
-/// Simple base64 encoding for blob data
-#[cfg(feature = "native")]
-fn base64_encode(data: &[u8]) -> String {
-    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
-    let mut result = String::new();
-    for chunk in data.chunks(3) {
-        let b0 = chunk[0] as usize;
-        let b1 = chunk.get(1).copied().unwrap_or(0) as usize;
-        let b2 = chunk.get(2).copied().unwrap_or(0) as usize;
-        
-        result.push(CHARS[b0 >> 2] as char);
-        result.push(CHARS[((b0 & 0x03) << 4) | (b1 >> 4)] as char);
+      if (this._listeners[key]) {{
+        this._listeners[key].forEach(cb => cb(data));
+      }}
+      // Also emit to wildcard listeners
+      const wildcardKey = `${{event}}:*`;
+      if (this._listeners[wildcardKey]) {{
+        this._listeners[wildcardKey].forEach(cb => cb(id, data));
+      }}
+    }},
+    // Convenience event registration
+    onNavigate(id, cb) {{ this.on('navigate', id, cb); }},
+    onTitleChange(id, cb) {{ this.on('titleChange', id, cb); }},
+    onLoadStart(id, cb) {{ this.on('loadStart', id, cb); }},
+    onLoadFinish(id, cb) {{ this.on('loadFinish', id, cb); }},
+    onNewWindow(id, cb) {{ this.on('newWindow', id, cb); }},
+    onDownload(id, cb) {{ this.on('download', id, cb); }},
+    onClose(id, cb) {{ this.on('close', id, cb); }},
+    onHistoryChange(id, cb) {{ this.on('historyChange', id, cb); }}
+  }},
+  // MultiView API - Create windows with multiple WebViews
+  multiview: {{
+    // Create a new multi-view window
+    // views: array of {{ id, url, x, y, width, height }}
+    // Views are stacked: first in array = bottom, last = top (for UI)
+    async create(options) {{ return poly.invoke('__poly_multiview_create', options); }},
+    // Navigate a view to URL
+    async navigate(windowId, viewId, url) {{ return poly.invoke('__poly_multiview_navigate', {{ windowId, viewId, url }}); }},
+    // Send message to a view (triggers 'polymessage' event)
+    async postMessage(windowId, viewId, message) {{ return poly.invoke('__poly_multiview_post_message', {{ windowId, viewId, message: JSON.stringify(message) }}); }},
+    // Set view bounds
+    async setBounds(windowId, viewId, bounds) {{ return poly.invoke('__poly_multiview_set_bounds', {{ windowId, viewId, ...bounds }}); }},
+    // Close a multi-view window
+    async close(windowId) {{ return poly.invoke('__poly_multiview_close', {{ windowId }}); }},
+    // List all multi-view windows
+    async list() {{ return poly.invoke('__poly_multiview_list', {{}}); }},
+    // Get window info
+    async get(windowId) {{ return poly.invoke('__poly_multiview_get', {{ windowId }}); }}
+  }},
+  // PolyView API - "iframe2" that bypasses all iframe restrictions
+  // Use <poly-view src="https://example.com"></poly-view> in your HTML
+  polyview: {{
+    // Get the proxy URL for a target URL
+    proxyUrl(url) {{ return '/__polyview/?token=' + encodeURIComponent(window.__POLY_TOKEN__ || '') + '&url=' + encodeURIComponent(url); }},
+    // Navigate a poly-view element
+    navigate(element, url) {{
+      if (element && element.navigate) element.navigate(url);
+      else if (element) element.src = this.proxyUrl(url);
+    }},
+    // Check if PolyView is available
+    isAvailable() {{ return typeof customElements !== 'undefined' && customElements.get('poly-view') !== undefined; }}
+  }},
+  // AI/LLM API - Chat with AI models
+  ai: {{
+    // Chat with Ollama (local)
+    async ollama(model, messages, options = {{}}) {{
+      return poly.invoke('__poly_ai_ollama', {{ model, messages, ...options }});
+    }},
+    // Chat with OpenAI
+    async openai(model, messages, apiKey, options = {{}}) {{
+      return poly.invoke('__poly_ai_openai', {{ model, messages, apiKey, ...options }});
+    }},
+    // Chat with Anthropic Claude
+    async anthropic(model, messages, apiKey, options = {{}}) {{
+      return poly.invoke('__poly_ai_anthropic', {{ model, messages, apiKey, ...options }});
+    }},
+    // Chat with custom OpenAI-compatible API
+    async custom(baseUrl, model, messages, options = {{}}) {{
+      return poly.invoke('__poly_ai_custom', {{ baseUrl, model, messages, ...options }});
+    }},
+    // Check if Ollama is running
+    async checkOllama() {{
+      return poly.invoke('__poly_ai_check_ollama', {{}});
+    }},
+    // List available Ollama models
+    async listModels() {{
+      return poly.invoke('__poly_ai_list_models', {{}});
+    }},
+    // Generic chat function (auto-detects provider)
+    async chat(options) {{
+      return poly.invoke('__poly_ai_chat', options);
+    }},
+    // Streaming API
+    stream: {{
+      // Start streaming chat with Ollama
+      async start(model, messages, options = {{}}) {{
+        return poly.invoke('__poly_ai_stream_start', {{ model, messages, ...options }});
+      }},
+      // Poll for new chunks (returns {{ chunks: [], done: bool }})
+      async poll(streamId) {{
+        return poly.invoke('__poly_ai_stream_poll', {{ streamId }});
+      }},
+      // Cancel/stop a stream
+      async cancel(streamId) {{
+        return poly.invoke('__poly_ai_stream_cancel', {{ streamId }});
+      }},
+      // List active streams
+      async list() {{
+        return poly.invoke('__poly_ai_stream_list', {{}});
+      }},
+      // Helper: Stream with callback (handles polling automatically)
+      async run(model, messages, options = {{}}, onChunk) {{
+        const result = await this.start(model, messages, options);
+        if (result.error) throw new Error(result.error);
+        const streamId = result.streamId;
         
-        if chunk.len() > 1 {
-            result.push(CHARS[((b1 & 0x0f) << 2) | (b2 >> 6)] as char);
-        } else {
-            result.push('=');
-        }
+        const poll = async () => {{
+          const {{ chunks, done }} = await this.poll(streamId);
+          for (const chunk of chunks) {{
+            if (onChunk) onChunk(chunk);
+          }}
+          if (!done) {{
+            await new Promise(r => setTimeout(r, 16)); // ~60fps polling
+            await poll();
+          }}
+        }};
         
-        if chunk.len() > 2 {
-            result.push(CHARS[b2 & 0x3f] as char);
-        } else {
-            result.push('=');
-        }
-    }
-    result
-}
-
-/// Parse filter array from JSON: [["Images", ["png", "jpg"]], ["All", ["*"]]]
-fn parse_filters(_value: Option<&serde_json::Value>) -> Option<Vec<(&str, &[&str])>> {
-    // For simplicity, we don't parse complex filters in this version
-    // Users can pass simple filters or none
-    None
-}
-
-/// Handle IPC when there's no Poly backend (system APIs only)
-fn handle_ipc_invoke_system_only(body: &str) -> String {
-    let request: serde_json::Value = match serde_json::from_str(body) {
-        Ok(v) => v,
-        Err(e) => return serde_json::json!({"error": format!("Invalid JSON: {}", e)}).to_string(),
-    };
-    
-    let fn_name = match request.get("fn").and_then(|v| v.as_str()) {
-        Some(name) => name,
-        None => return serde_json::json!({"error": "Missing 'fn' field"}).to_string(),
-    };
-    
-    let args = request.get("args").cloned().unwrap_or(serde_json::json!({}));
-    
-    if fn_name.starts_with("__poly_") {
-        handle_system_api(fn_name, &args)
-    } else {
-        serde_json::json!({"error": "No Poly backend available"}).to_string()
-    }
-}
-
-
-fn json_to_poly_value(v: &serde_json::Value) -> String {
-    match v {
-        serde_json::Value::Null => "none".to_string(),
-        serde_json::Value::Bool(b) => if *b { "true" } else { "false" }.to_string(),
-        serde_json::Value::Number(n) => n.to_string(),
-        serde_json::Value::String(s) => format!("\"{}\"", s.replace("\"", "\\\"")),
-        serde_json::Value::Array(arr) => {
-            let items: Vec<_> = arr.iter().map(json_to_poly_value).collect();
-            format!("[{}]", items.join(", "))
-        }
-        serde_json::Value::Object(obj) => {
-            let items: Vec<_> = obj.iter()
-                .map(|(k, v)| format!("\"{}\": {}", k, json_to_poly_value(v)))
-                .collect();
-            format!("{{{}}}", items.join(", "))
-        }
-    }
+        await poll();
+        return streamId;
+      }}
+    }}
+  }}
+}};
+// Poly Event Bus - events from the backend arrive over server-sent events
+window.__polyDispatch = (e) => (poly._listeners[e.topic] || []).slice().forEach(cb => {{
+  try {{ cb(e.payload, e.topic); }} catch (err) {{ console.error(err); }}
+}});
+if (window.EventSource) {{
+  const events = new EventSource('/__poly_events?window=' + encodeURIComponent(poly.windowLabel) + '&token=' + encodeURIComponent(window.__POLY_TOKEN__));
+  events.onmessage = (m) => window.__polyDispatch(JSON.parse(m.data));
+}}
+// Initialize Lucide Icons
+if (typeof lucide !== 'undefined') lucide.createIcons();
+// Hot Reload
+(function() {{
+  let v = {}, polling = false;
+  function showError(e) {{
+    let el = document.getElementById('poly-error-overlay');
+    if (!e) {{ if (el) el.remove(); return; }}
+    if (!el) {{
+      el = document.createElement('div');
+      el.id = 'poly-error-overlay';
+      el.style.cssText = 'position:fixed;inset:0;z-index:100000;background:rgba(10,10,14,0.92);color:#f87171;font:13px/1.5 ui-monospace,monospace;padding:2rem;white-space:pre-wrap;overflow:auto';
+      document.body.appendChild(el);
+    }}
+    el.textContent = 'Backend reload failed - the previous code is still running\n\n' + e.file + '\n' + e.message;
+  }}
+  async function check() {{
+    if (!document.hidden) {{
+      try {{ const r = await fetch('/__poly_reload'); const d = await r.json(); showError(d.error); if (d.version > v) {{ v = d.version; location.reload(); }} }} catch(e) {{}}
+    }}
+    if (polling) setTimeout(check, {reload_interval});
+  }}
+  function start() {{ if (!polling) {{ polling = true; check(); }} }}
+  function stop() {{ polling = false; }}
+  document.addEventListener('visibilitychange', () => document.hidden ? stop() : start());
+  start();
+}})();
+</script>"#, version, reload_interval = reload_interval, token_script = token_script)
 }
 
-
-fn generate_dev_html(project_path: &Path, entry: &Path, version: u64) -> String {
+fn generate_dev_html(project_path: &Path, entry: &Path, version: u64, token: &str) -> String {
     let name = project_path.file_name().and_then(|n| n.to_str()).unwrap_or("Poly App");
     let entry_name = entry.file_name().and_then(|n| n.to_str()).unwrap_or("main.poly");
     
@@ -4144,7 +2453,7 @@ fn generate_dev_html(project_path: &Path, entry: &Path, version: u64) -> String
         async function runCode() {{
             document.getElementById('output').innerHTML = '<div class="line" style="color:#666">Running...</div>';
             try {{
-                const r = await fetch('/__poly_run');
+                const r = await fetch('/__poly_run', {{ headers: {{ 'X-Poly-Token': '{token}' }} }});
                 const d = await r.json();
                 document.getElementById('output').innerHTML = d.success 
                     ? d.output.map(l => `<div class="line">${{esc(l)}}</div>`).join('')
@@ -4169,7 +2478,7 @@ fn generate_dev_html(project_path: &Path, entry: &Path, version: u64) -> String
         runCode(); start();
     </script>
 </body>
-</html>"#, name, entry_name, version)
+</html>"#, name, entry_name, version, token = token)
 }
 
 fn should_reload(event: &Event) -> bool {
@@ -4180,6 +2489,10 @@ fn should_reload(event: &Event) -> bool {
         if path_str.contains("target") || path_str.contains(".git") {
             return false;
         }
+        // Written by update_dev_bindings after a reload
+        if poly::bindings::is_generated(p) {
+            return false;
+        }
         
         p.extension().and_then(|e| e.to_str())
             .map(|ext| matches!(ext, "poly" | "html" | "css" | "js"))
@@ -4188,11 +2501,7 @@ fn should_reload(event: &Event) -> bool {
 }
 
 fn find_entry_point(project_path: &Path) -> Option<std::path::PathBuf> {
-    for candidate in ["main.poly", "src/main.poly", "app.poly", "src/app.poly", "index.poly"] {
-        let path = project_path.join(candidate);
-        if path.exists() { return Some(path); }
-    }
-    None
+    poly::find_entry_point(project_path)
 }
 
 fn open_in_browser(url: &str) -> Result<(), Box<dyn std::error::Error>> {
@@ -4221,11 +2530,14 @@ fn open_in_browser(url: &str) -> Result<(), Box<dyn std::error::Error>> {
 }
 
 
-fn run_app_result(path: &str, release: bool, native: bool, browser: bool, ui_height: u32) -> Result<(), String> {
+fn run_app_result(path: &str, release: bool, native: bool, browser: bool, ui_height: u32, profile: Option<&str>) -> Result<(), String> {
     let project_path = Path::new(path);
     
     // If native mode, try to run in a WebView window
     if native {
+        if profile.is_some() {
+            eprintln!("  {}warning{}: --profile is not supported with --native", YELLOW, RESET);
+        }
         run_native_app(project_path, release, browser, ui_height);
         return Ok(());
     }
@@ -4234,7 +2546,7 @@ fn run_app_result(path: &str, release: bool, native: bool, browser: bool, ui_hei
         let start = std::time::Instant::now();
         let source = fs::read_to_string(project_path)
             .map_err(|e| format!("{}", e))?;
-        poly::run(&source)?;
+        run_source(&source, profile, project_path.parent().unwrap_or(Path::new(".")))?;
         println!("\n  {}done{} in {}ms", GREEN, RESET, start.elapsed().as_millis());
         return Ok(());
     }
@@ -4249,11 +2561,112 @@ fn run_app_result(path: &str, release: bool, native: bool, browser: bool, ui_hei
     let start = std::time::Instant::now();
     let source = fs::read_to_string(&entry)
         .map_err(|e| format!("{}", e))?;
-    poly::run(&source)?;
+    run_source(&source, profile, project_path)?;
     println!("\n  {}done{} in {}ms", GREEN, RESET, start.elapsed().as_millis());
     Ok(())
 }
 
+/// Run an app without windows for tests and CI. Each line on stdin is an IPC
+/// request, a page event or a fake (see `poly::headless::handle_line`); its
+/// reply is one line on stdout, after the events sent to pages meanwhile.
+/// What the app prints goes to stderr.
+fn run_headless(project_path: &Path) -> Result<(), String> {
+    let app = poly::headless::App::start(project_path)?;
+    eprintln!("  {}POLY{} v{}  {}headless{}", CYAN, RESET, VERSION, DIM, RESET);
+    if poly::sovereignty::is_enabled() {
+        eprintln!("  {}>{} SovereigntyEngine: {}enabled{}", DIM, RESET, GREEN, RESET);
+    }
+    let mut stdout = io::stdout();
+    for line in io::stdin().lock().lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = poly::headless::handle_line(&app, &line);
+        for output in app.take_output() {
+            eprintln!("{}", output);
+        }
+        for event in app.events() {
+            writeln!(stdout, "{}", poly::headless::event_line(&event)).map_err(|e| e.to_string())?;
+        }
+        writeln!(stdout, "{}", reply).and_then(|_| stdout.flush()).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Run a program, optionally under the profiler. The profile is written and
+/// summarized even when the program fails. A program that declares HTTP
+/// routes keeps running and serves them, with the static files of `project`.
+fn run_source(source: &str, profile: Option<&str>, project: &Path) -> Result<(), String> {
+    let Some(stem) = profile else {
+        let interpreter = std::sync::Arc::new(Mutex::new(poly::create_interpreter()));
+        poly::init_interpreter(&mut interpreter.lock().unwrap(), source)?;
+        if poly::routes::list().is_empty() {
+            return Ok(());
+        }
+        return serve_routes(project, &interpreter, source);
+    };
+    let mut interp = poly::create_interpreter();
+    interp.set_profiler(Some(poly::profiler::Profiler::new()));
+    let result = poly::profiler::run(&mut interp, source);
+    let profiler = interp.take_profiler().unwrap_or_default();
+    
+    println!();
+    print!("{}", profiler.summary(10));
+    println!();
+    for file in profiler.write(Path::new(stem))? {
+        println!("  {}>{} Profile: {}{}{}", DIM, RESET, CYAN, file.display(), RESET);
+    }
+    result.map(|_| ())
+}
+
+/// Print the HTTP routes the program declared
+fn print_routes() {
+    for (method, pattern, handler) in poly::routes::list() {
+        println!("  {}>{} Route:   {:<6} {}{}{} {}-> {}(){}", DIM, RESET, method, CYAN, pattern, RESET, DIM, handler, RESET);
+    }
+}
+
+/// Serve the routes of a program run by `poly run`, then the static files of
+/// `project`/web, on the `[server]` address until the process is stopped
+fn serve_routes(project: &Path, interpreter: &std::sync::Arc<Mutex<poly::interpreter::Interpreter>>, source: &str) -> Result<(), String> {
+    let config = poly::PolyConfig::load(project);
+    let (host, port) = (config.server.host.as_str(), config.server.port);
+    let bind = if host.contains(':') { format!("[{}]:{}", host, port) } else { format!("{}:{}", host, port) };
+    let server = tiny_http::Server::http(&bind).map_err(|e| format!("Cannot listen on {}: {}", bind, e))?;
+    let pool = start_ipc_pool(interpreter, source, config.ipc.workers, config.ipc.queue_limit);
+    spawn_event_dispatcher(std::sync::Arc::clone(interpreter));
+    
+    println!();
+    println!("  {}>{} Serving: {}http://{}{}", DIM, RESET, CYAN, bind, RESET);
+    print_routes();
+    
+    let guard = poly::dev_security::DevGuard::new(host, port);
+    let web_root = project.join("web");
+    let packages_root = project.join("packages");
+    for request in server.incoming_requests() {
+        let url = request.url().to_string();
+        if let Err(e) = guard.check(&request, false) {
+            let _ = request.respond(poly::dev_security::forbidden(&e));
+            continue;
+        }
+        if poly::routes::matches(url.split('?').next().unwrap_or("")) {
+            let pool = std::sync::Arc::clone(&pool);
+            std::thread::spawn(move || poly::routes::serve(&pool, request));
+            continue;
+        }
+        let file = poly::dev_security::static_path(&web_root, &packages_root, &url)
+            .and_then(|file| fs::read(&file).ok().map(|bytes| (poly::blob::content_type(&file), bytes)));
+        let response = match file {
+            Some((content_type, bytes)) => tiny_http::Response::from_data(bytes)
+                .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()).unwrap()),
+            None => tiny_http::Response::from_string("Not Found").with_status_code(404),
+        };
+        let _ = request.respond(response);
+    }
+    Ok(())
+}
+
 /// Open a URL in a standalone Poly WebView window
 fn open_url_window(url: &str, title: &str, width: u32, height: u32) {
     #[cfg(feature = "native")]
@@ -4853,8 +3266,6 @@ fn run_native_app(project_path: &Path, _release: bool, browser_flag: bool, ui_he
     // Create persistent interpreter for native mode
     use std::sync::Mutex;
     let interpreter: Arc<Mutex<poly::interpreter::Interpreter>> = Arc::new(Mutex::new(poly::create_interpreter()));
-    let interpreter_server = Arc::clone(&interpreter);
-    let interpreter_watcher = Arc::clone(&interpreter);
     
     // Start local HTTP server in background thread
     let web_dir_arc = Arc::new(web_dir);
@@ -4867,38 +3278,72 @@ fn run_native_app(project_path: &Path, _release: bool, browser_flag: bool, ui_he
     let decorations_for_server = window_decorations;
     
     // Initialize interpreter with source
-    if let Some(ref entry) = entry_path_for_init {
+    let pool = entry_path_for_init.as_ref().map(|entry| {
         let source = fs::read_to_string(entry).unwrap_or_default();
-        let mut interp = interpreter.lock().unwrap();
-        if let Err(e) = poly::init_interpreter(&mut interp, &source) {
+        if let Err(e) = poly::init_interpreter(&mut interpreter.lock().unwrap(), &source) {
             eprintln!("{}error{}: Failed to initialize interpreter: {}", RED, RESET, e);
         }
-    }
+        let ipc = poly::PolyConfig::load(project_path).ipc;
+        start_ipc_pool(&interpreter, &source, ipc.workers, ipc.queue_limit)
+    });
+    let pool_server = pool.clone();
+    spawn_event_dispatcher(Arc::clone(&interpreter));
+    
+    let guard = poly::dev_security::DevGuard::new("127.0.0.1", port);
     
     thread::spawn(move || {
         let server = tiny_http::Server::http(format!("127.0.0.1:{}", port))
             .expect("Failed to start local server");
         
         let entry_path = entry_path_for_server;
+        let packages_root = project_path_owned.join("packages");
         
         for mut request in server.incoming_requests() {
             let url = request.url().to_string();
+            let path = url.split('?').next().unwrap_or("").to_string();
+            // Everything that runs code, reaches the network or reveals the window setup
+            let needs_token = matches!(path.as_str(), "/__poly_invoke" | "/__poly_emit" | "/__poly_config" | "/__poly_proxy" | "/__poly_proxy/")
+                || path.starts_with("/__polyview")
+                || poly::blob::handles(&url);
+            if let Err(e) = guard.check(&request, needs_token) {
+                let _ = request.respond(poly::dev_security::forbidden(&e));
+                continue;
+            }
+            if poly::blob::handles(&url) {
+                thread::spawn(move || poly::blob::serve(request));
+                continue;
+            }
+            // Handle IPC invoke (stateful), each call on its own thread
+            if path == "/__poly_invoke" {
+                let pool = pool_server.clone();
+                thread::spawn(move || {
+                    let mut body = String::new();
+                    request.as_reader().read_to_string(&mut body).ok();
+                    let result = match &pool {
+                        Some(pool) => handle_ipc_invoke_stateful(pool, &body),
+                        // No entry point, only handle system APIs
+                        None => handle_ipc_invoke_system_only(&body),
+                    };
+                    let _ = request.respond(tiny_http::Response::from_string(result)
+                        .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap()));
+                });
+                continue;
+            }
+            if let Some(pool) = pool_server.clone().filter(|_| poly::routes::matches(&path)) {
+                thread::spawn(move || poly::routes::serve(&pool, request));
+                continue;
+            }
             
             // Hot reload endpoint
-            let response = if url == "/__poly_reload" {
+            let response = if path == "/__poly_reload" {
                 let current = reload_counter_server.load(Ordering::Relaxed);
                 tiny_http::Response::from_string(format!(r#"{{"version":{}}}"#, current))
                     .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap())
             }
             // Proxy endpoint - fetches external URLs and returns content
-            // Usage: /__poly_proxy?url=https://example.com/path
-            else if url.starts_with("/__poly_proxy") {
-                let target_url = url.strip_prefix("/__poly_proxy?url=")
-                    .or_else(|| url.strip_prefix("/__poly_proxy/?url="))
-                    .unwrap_or("");
-                
-                // URL decode
-                let target_url = urlencoding::decode(target_url).unwrap_or_default().to_string();
+            // Usage: /__poly_proxy?token=...&url=https://example.com/path
+            else if path == "/__poly_proxy" || path == "/__poly_proxy/" {
+                let target_url = proxy_target(&url);
                 
                 if target_url.is_empty() {
                     tiny_http::Response::from_string(r#"{"error":"Missing url parameter"}"#)
@@ -4932,15 +3377,9 @@ fn run_native_app(project_path: &Path, _release: bool, browser_flag: bool, ui_he
                                 
                                 match resp.bytes() {
                                     Ok(body) => {
-                                        let mut response = tiny_http::Response::from_data(body.to_vec());
-                                        response = response.with_header(
+                                        tiny_http::Response::from_data(body.to_vec()).with_header(
                                             tiny_http::Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()).unwrap()
-                                        );
-                                        // Add CORS headers
-                                        response = response.with_header(
-                                            tiny_http::Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..]).unwrap()
-                                        );
-                                        response
+                                        )
                                     }
                                     Err(e) => {
                                         tiny_http::Response::from_string(format!(r#"{{"error":"{}"}}"#, e))
@@ -4959,17 +3398,11 @@ fn run_native_app(project_path: &Path, _release: bool, browser_flag: bool, ui_he
                 }
             }
             // PolyView Proxy - "iframe2" that bypasses all iframe restrictions
-            // Usage: /__polyview/?url=https://example.com
+            // Usage: /__polyview/?token=...&url=https://example.com
             // Supports GET and POST requests
-            else if url.starts_with("/__polyview") {
-                let target_url = url.strip_prefix("/__polyview/?url=")
-                    .or_else(|| url.strip_prefix("/__polyview?url="))
-                    .or_else(|| url.strip_prefix("/__polyview/"))
-                    .or_else(|| url.strip_prefix("/__polyview"))
-                    .unwrap_or("");
-                
-                // URL decode
-                let target_url = urlencoding::decode(target_url).unwrap_or_default().to_string();
+            else if path.starts_with("/__polyview") {
+                let target_url = proxy_target(&url);
+                let proxy_base = polyview_base(port, guard.token());
                 
                 if target_url.is_empty() {
                     tiny_http::Response::from_string(r#"<html><body>Missing URL parameter</body></html>"#)
@@ -4990,45 +3423,27 @@ fn run_native_app(project_path: &Path, _release: bool, browser_flag: bool, ui_he
                             .map(|h| h.value.as_str().to_string())
                             .unwrap_or_else(|| "application/x-www-form-urlencoded".to_string());
                         
-                        handle_polyview_proxy_post(&target_url, port, &body, &content_type)
+                        handle_polyview_proxy_post(&target_url, &proxy_base, &body, &content_type)
                     } else {
                         // GET request
-                        handle_polyview_proxy(&target_url, port)
+                        handle_polyview_proxy(&target_url, &proxy_base)
                     }
                 }
             }
             // Serve window config (decorations, etc.)
-            else if url == "/__poly_config" {
+            else if path == "/__poly_config" {
                 tiny_http::Response::from_string(format!(r#"{{"decorations":{}}}"#, decorations_for_server))
                     .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap())
             }
-            // Handle IPC invoke (stateful)
-            else if url == "/__poly_invoke" {
+            // Events from poly.emit() when the page has no native IPC
+            else if path == "/__poly_emit" {
                 let mut body = String::new();
                 request.as_reader().read_to_string(&mut body).ok();
-                
-                let result = if entry_path.is_some() {
-                    handle_ipc_invoke_stateful(&interpreter_server, &body)
-                } else {
-                    // No entry point, only handle system APIs
-                    handle_ipc_invoke_system_only(&body)
-                };
-                
-                tiny_http::Response::from_string(result)
-                    .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap())
-            } else {
-                // Try web dir first, then packages dir
-                let url_path = url.trim_start_matches('/');
-                let file_path = if url == "/" || url == "/index.html" {
-                    web_dir_server.join("index.html")
-                } else if url_path.starts_with("packages/") {
-                    // Serve from packages directory (project root)
-                    project_path_owned.join(url_path)
-                } else {
-                    web_dir_server.join(url_path)
-                };
-                
-                if file_path.exists() && file_path.is_file() {
+                handle_emit(&body)
+            }
+            else {
+                // Static files, confined to web/ and packages/
+                if let Some(file_path) = poly::dev_security::static_path(&web_dir_server, &packages_root, &url) {
                     let content = fs::read(&file_path).unwrap_or_default();
                     let ct = match file_path.extension().and_then(|e| e.to_str()) {
                         Some("html") => "text/html; charset=utf-8",
@@ -5082,7 +3497,7 @@ fn run_native_app(project_path: &Path, _release: bool, browser_flag: bool, ui_he
       close: () => sendIPC('close'),
       drag: () => sendIPC('drag'),
       // Check if running in frameless mode
-      isFrameless: () => fetch('/__poly_config').then(r => r.json()).then(d => d.decorations === false).catch(() => false)
+      isFrameless: () => fetch('/__poly_config', { headers: { 'X-Poly-Token': window.__POLY_TOKEN__ } }).then(r => r.json()).then(d => d.decorations === false).catch(() => false)
     };
     
     console.log('[Poly] Window API ready: polyWindow.minimize(), polyWindow.maximize(), polyWindow.close(), polyWindow.drag()');
@@ -5163,15 +3578,53 @@ fn run_native_app(project_path: &Path, _release: bool, browser_flag: bool, ui_he
 
 // Poly IPC Bridge
 window.poly = {
-  async invoke(fn, args = {}) {
-    const r = await fetch('/__poly_invoke', {
+  async invoke(fn, args = {}, { signal, priority } = {}) {
+    // Aborting the signal cancels the call in the backend too
+    const id = signal ? `${Date.now().toString(36)}-${Math.random().toString(36).slice(2)}` : undefined;
+    const cancel = () => poly.invoke('__poly_ipc_cancel', { id }).catch(() => {});
+    signal?.addEventListener('abort', cancel, { once: true });
+    let r;
+    try {
+      r = await fetch('/__poly_invoke', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json', 'X-Poly-Token': window.__POLY_TOKEN__ },
+        body: JSON.stringify({ fn, args, id, priority }),
+        signal
+      });
+    } finally {
+      signal?.removeEventListener('abort', cancel);
+    }
+    const d = await r.json();
+    if (d.error) {
+      const err = new Error(d.error);
+      err.code = d.code;
+      err.traceback = d.traceback || [];
+      throw err;
+    }
+    return d.result;
+  },
+  windowLabel: 'main',
+  _listeners: {},
+  on(topic, cb) {
+    (this._listeners[topic] = this._listeners[topic] || []).push(cb);
+    return () => this.off(topic, cb);
+  },
+  off(topic, cb) {
+    const list = this._listeners[topic] || [];
+    this._listeners[topic] = cb ? list.filter(f => f !== cb) : [];
+  },
+  async emit(topic, payload = null) {
+    if (window.ipc) {
+      window.ipc.postMessage('emit:' + JSON.stringify({ topic, payload }));
+      return;
+    }
+    const r = await fetch('/__poly_emit', {
       method: 'POST',
-      headers: { 'Content-Type': 'application/json' },
-      body: JSON.stringify({ fn, args })
+      headers: { 'Content-Type': 'application/json', 'X-Poly-Token': window.__POLY_TOKEN__ },
+      body: JSON.stringify({ topic, payload })
     });
     const d = await r.json();
     if (d.error) throw new Error(d.error);
-    return d.result;
   },
   dialog: {
     async open(options = {}) { return poly.invoke('__poly_dialog_open', options); },
@@ -5195,7 +3648,47 @@ window.poly = {
     async read(path) { return poly.invoke('__poly_fs_read', { path }); },
     async write(path, content) { return poly.invoke('__poly_fs_write', { path, content }); },
     async exists(path) { return poly.invoke('__poly_fs_exists', { path }); },
-    async readDir(path) { return poly.invoke('__poly_fs_read_dir', { path }); }
+    async readDir(path) { return poly.invoke('__poly_fs_read_dir', { path }); },
+    url(path) { return poly.blob.url('/__poly_file?path=' + encodeURIComponent(path)); },
+    async readBytes(path, range) {
+      const headers = range ? { Range: 'bytes=' + range.start + '-' + (range.end != null ? range.end : '') } : {};
+      const r = await fetch(poly.fs.url(path), { headers });
+      if (!r.ok) throw new Error((await r.json()).error);
+      return new Uint8Array(await r.arrayBuffer());
+    },
+    async upload(data, path, options = {}) {
+      const chunkSize = options.chunkSize || 4 * 1024 * 1024;
+      const blob = data instanceof Blob ? data : new Blob([data]);
+      const { id } = await poly.invoke('__poly_upload_start', {});
+      try {
+        for (let offset = 0; offset < blob.size; offset += chunkSize) {
+          const r = await fetch('/__poly_upload/' + id + '?offset=' + offset, {
+            method: 'PUT',
+            headers: { 'X-Poly-Token': window.__POLY_TOKEN__ },
+            body: blob.slice(offset, offset + chunkSize)
+          });
+          const d = await r.json();
+          if (d.error) throw new Error(d.error);
+          if (options.onProgress) options.onProgress(d.result.received, blob.size);
+        }
+        return await poly.invoke('__poly_upload_finish', { id, path });
+      } catch (e) {
+        poly.invoke('__poly_upload_cancel', { id }).catch(() => {});
+        throw e;
+      }
+    }
+  },
+  blob: {
+    url(ref) {
+      const url = typeof ref === 'string' ? ref : ref.$blob;
+      return url + (url.includes('?') ? '&' : '?') + 'token=' + encodeURIComponent(window.__POLY_TOKEN__ || '');
+    },
+    async fetch(ref) {
+      const r = await fetch(poly.blob.url(ref));
+      if (!r.ok) throw new Error((await r.json()).error);
+      return r.blob();
+    },
+    async release(ref) { return poly.invoke('__poly_blob_release', { url: typeof ref === 'string' ? ref : ref.$blob }); }
   },
   updater: {
     async checkGithub(repo, currentVersion) {
@@ -5280,7 +3773,13 @@ window.poly = {
   clipboard: {
     async read() { return poly.invoke('__poly_clipboard_read', {}); },
     async write(text) { return poly.invoke('__poly_clipboard_write', { text }); },
-    async clear() { return poly.invoke('__poly_clipboard_clear', {}); }
+    async clear() { return poly.invoke('__poly_clipboard_clear', {}); },
+    async readImage() {
+      const ref = await poly.invoke('__poly_clipboard_read_image', {});
+      const image = await poly.blob.fetch(ref);
+      poly.blob.release(ref);
+      return image;
+    }
   },
   windows: {
     async create(options = {}) { return poly.invoke('__poly_window_create', options); },
@@ -5376,7 +3875,7 @@ window.poly = {
     async clearHistory(id) { return poly.invoke('__poly_browser_clear_history', { id }); },
     async fetch(url) { return poly.invoke('__poly_browser_fetch', { url }); },
     // Proxy URL - use this to load external resources through the local server
-    proxyUrl(url) { return '/__poly_proxy?url=' + encodeURIComponent(url); },
+    proxyUrl(url) { return '/__poly_proxy?token=' + encodeURIComponent(window.__POLY_TOKEN__ || '') + '&url=' + encodeURIComponent(url); },
     // Navigate the current WebView to a URL (replaces current page)
     loadUrl(url) { window.location.href = url; },
     // Go back in browser history
@@ -5559,6 +4058,10 @@ window.poly = {
     }
   }
 };
+// Poly Event Bus - the native window delivers events with __polyDispatch
+window.__polyDispatch = (e) => (poly._listeners[e.topic] || []).slice().forEach(cb => {
+  try { cb(e.payload, e.topic); } catch (err) { console.error(err); }
+});
 // Initialize Lucide Icons
 if (typeof lucide !== 'undefined') {
   lucide.createIcons();
@@ -5568,7 +4071,7 @@ if (typeof lucide !== 'undefined') {
 // Note: Hot reload is only active during development
 </script>"##;
                         if html.contains("</body>") {
-                            html = html.replace("</body>", &format!("{}</body>", body_script));
+                            html = html.replace("</body>", &format!("{}{}</body>", guard.token_script(), body_script));
                         }
                         
                         html.into_bytes()
@@ -5628,14 +4131,18 @@ if (typeof lucide !== 'undefined') {
                         p.extension().and_then(|e| e.to_str()) == Some("poly")
                     });
                     
-                    // Reload interpreter for .poly changes
+                    // Reload changed modules for .poly changes, keeping the old code if that fails
+                    let mut backend_failed = false;
                     if has_poly_change {
-                        if let Some(ref entry) = entry_path_watcher {
-                            let source = fs::read_to_string(entry).unwrap_or_default();
-                            let mut interp = interpreter_watcher.lock().unwrap();
-                            *interp = poly::create_interpreter();
-                            if let Err(e) = poly::init_interpreter(&mut interp, &source) {
-                                eprintln!("  {}error{}: Reload failed: {}", RED, RESET, e);
+                        if let (Some(entry), Some(pool)) = (&entry_path_watcher, &pool) {
+                            let preserve = poly::PolyConfig::load(&project_path_for_watcher).dev.preserve_state;
+                            let result = poly::hot_reload::reload(&mut pool.workers()[0].lock().unwrap(), entry, &event.paths, &preserve);
+                            match result {
+                                Ok(_) => reload_workers(pool, entry, &event.paths, &preserve),
+                                Err(e) => {
+                                    eprintln!("  {}error{}: Reload failed: {}: {}", RED, RESET, e.file.display(), e.message);
+                                    backend_failed = true;
+                                }
                             }
                         }
                     }
@@ -5646,7 +4153,7 @@ if (typeof lucide !== 'undefined') {
                         matches!(ext, Some("html") | Some("css") | Some("js") | Some("json") | Some("svg") | Some("poly"))
                     });
                     
-                    if dominated_by_relevant {
+                    if dominated_by_relevant && !backend_failed {
                         reload_counter_watcher.fetch_add(1, Ordering::Relaxed);
                     }
                 }
@@ -5683,6 +4190,17 @@ fn build_app(path: &str, target: &str, release: bool, installer: bool, sign: boo
             println!();
             return;
         }
+        "js" => {
+            println!();
+            println!("  {}POLY{} {}build{}", CYAN, RESET, DIM, RESET);
+            println!();
+            if let Err(e) = build_js(project_path) {
+                eprintln!("{}error{}: {}", RED, RESET, e);
+                std::process::exit(1);
+            }
+            println!();
+            return;
+        }
         "all" => {
             // Build both web and native
             println!();
@@ -5728,6 +4246,18 @@ fn build_web(project_path: &Path, _release: bool) {
     println!("  {}>{} dist/web/index.html", GREEN, RESET);
 }
 
+/// Compile the project's Poly sources (`src/`, or the project itself) to ES modules in dist/js
+fn build_js(project_path: &Path) -> Result<(), String> {
+    let src = if project_path.join("src").is_dir() { project_path.join("src") } else { project_path.to_path_buf() };
+    let dist = project_path.join("dist/js");
+    let written = poly::js::build(&src, &dist)?;
+    for file in written {
+        let shown = file.strip_prefix(project_path).unwrap_or(&file);
+        println!("  {}>{} {}", GREEN, RESET, shown.display().to_string().replace('\\', "/"));
+    }
+    Ok(())
+}
+
 fn copy_dir_recursive(src: &Path, dst: &Path) -> io::Result<()> {
     fs::create_dir_all(dst)?;
     for entry in fs::read_dir(src)? {
@@ -5993,3 +4523,45 @@ print("Backend ready")
     println!();
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dev_bridge_subscribes_with_window_label() {
+        let script = dev_bridge_script(1, 2000, "");
+        assert!(script.contains("windowLabel: new URLSearchParams(location.search).get('window') || 'main',"));
+        assert!(script.contains("/__poly_events?window=' + encodeURIComponent(poly.windowLabel)"));
+        // `window` is the window-control API object and must only be defined once
+        assert_eq!(script.matches("\n  window: {").count(), 1);
+    }
+
+    #[test]
+    fn test_generated_bindings_do_not_trigger_reload() {
+        let modified = |path: &str| Event::new(EventKind::Modify(notify::event::ModifyKind::Any)).add_path(std::path::PathBuf::from(path));
+        assert!(should_reload(&modified("app/web/app.js")));
+        assert!(should_reload(&modified("app/src/main.poly")));
+        assert!(!should_reload(&modified("app/web/poly-bindings.js")));
+        assert!(!should_reload(&modified("app/web/poly-bindings.d.ts")));
+    }
+
+    #[test]
+    fn test_dispatch_command_with_kwargs_only() {
+        poly::router::register(poly::router::Command::new("test_kwargs_greet", |call| {
+            Ok(serde_json::json!(format!("{}, {}!", call.str("greeting").unwrap_or_default(), call.str("name").unwrap_or_default())))
+        }).arg("name", poly::router::ArgKind::String).arg("greeting", poly::router::ArgKind::String));
+
+        let mut request = poly::ipc::Request { function: "test_kwargs_greet".to_string(), ..Default::default() };
+        request.kwargs.insert("name".to_string(), serde_json::json!("Ada"));
+        request.kwargs.insert("greeting".to_string(), serde_json::json!("Hi"));
+        assert_eq!(dispatch_command(&request).unwrap().unwrap(), serde_json::json!("Hi, Ada!"));
+
+        request.args = serde_json::json!({"name": "Bob"});
+        assert_eq!(dispatch_command(&request).unwrap().unwrap_err().code, "INVALID_ARGUMENTS");
+        request.kwargs.remove("greeting");
+        request.kwargs.insert("name".to_string(), serde_json::json!(3));
+        request.args = serde_json::json!({"greeting": "Hi"});
+        assert_eq!(dispatch_command(&request).unwrap().unwrap_err().code, "INVALID_ARGUMENTS");
+    }
+}
@@ -472,7 +472,16 @@ pub fn run_native_url(url: &str, config: NativeConfig) -> Result<(), Box<dyn std
                     window_clone.set_visible(true);
                     window_clone.set_focus();
                 }
-                _ => {}
+                other => {
+                    // poly.emit() from the page, for the backend's on() handlers
+                    if let Some(json) = other.strip_prefix("emit:") {
+                        let result = crate::events::parse_emit(json)
+                            .and_then(|event| crate::events::send_to_backend(&event.topic, event.payload));
+                        if let Err(e) = result {
+                            eprintln!("[Events] {}", e);
+                        }
+                    }
+                }
             }
         })
         .build(&window)?;
@@ -483,12 +492,24 @@ pub fn run_native_url(url: &str, config: NativeConfig) -> Result<(), Box<dyn std
     let mut webview_manager = crate::webview_native::WebViewManager::new();
     webview_manager.set_main_webview(webview);
     
+    // Backend events for the page, delivered from the event loop
+    let events_subscriber = crate::events::subscribe("main");
+    
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Poll; // Use Poll to process pending operations
         
         // Process pending WebView operations
         webview_manager.process_pending_operations(&window);
         
+        // Deliver events from emit() to the page
+        if let Some(events) = crate::events::take(events_subscriber) {
+            if let Some(wv) = webview_manager.main_webview() {
+                for event in events {
+                    let _ = wv.evaluate_script(&event.dispatch_script());
+                }
+            }
+        }
+        
         // Handle tray events
         if let Some(ref tray) = *tray_handle.lock().unwrap() {
             while let Some(tray_event) = tray.poll_event() {
@@ -109,7 +109,7 @@ pub fn download_and_extract(client: &reqwest::blocking::Client, pkg: &NpmPackage
     // Calculate hash
     let mut hasher = Sha256::new();
     hasher.update(&bytes);
-    let hash = format!("sha256-{}", base64_encode(&hasher.finalize()));
+    let hash = format!("sha256-{}", poly::crypto::base64_encode(&hasher.finalize()));
     
     // Create directory
     fs::create_dir_all(dest).ok();
@@ -148,22 +148,6 @@ pub fn download_and_extract(_client: &(), _pkg: &NpmPackage, _dest: &Path) -> Re
     Err("Requires native feature".to_string())
 }
 
-#[allow(dead_code)]
-fn base64_encode(data: &[u8]) -> String {
-    const ALPHA: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
-    let mut result = String::new();
-    for chunk in data.chunks(3) {
-        let b0 = chunk[0] as usize;
-        let b1 = chunk.get(1).copied().unwrap_or(0) as usize;
-        let b2 = chunk.get(2).copied().unwrap_or(0) as usize;
-        result.push(ALPHA[b0 >> 2] as char);
-        result.push(ALPHA[((b0 & 0x03) << 4) | (b1 >> 4)] as char);
-        result.push(if chunk.len() > 1 { ALPHA[((b1 & 0x0f) << 2) | (b2 >> 6)] as char } else { '=' });
-        result.push(if chunk.len() > 2 { ALPHA[b2 & 0x3f] as char } else { '=' });
-    }
-    result
-}
-
 pub fn read_lockfile() -> HashMap<String, LockEntry> {
     let path = Path::new("poly.lock");
     if !path.exists() { return HashMap::new(); }
@@ -9,26 +9,73 @@ enum CallArg {
 
 /// Helper to create a simple param without default
 fn simple_param(name: &str) -> Param {
-    Param { name: name.to_string(), default: None }
+    Param { name: name.to_string(), default: None, annotation: None }
 }
 
 pub struct Parser {
     tokens: Vec<SpannedToken>,
     pos: usize,
+    lines: bool,
+    source: Option<String>,
+    /// Statements that follow the one just parsed (decorator calls)
+    pending: Vec<Statement>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<SpannedToken>) -> Self {
-        Self { tokens, pos: 0 }
+        Self { tokens, pos: 0, lines: false, source: None, pending: Vec::new() }
+    }
+
+    /// Emit a `Statement::Line` marker before every statement in a block, for
+    /// tooling that needs source positions (linter, debugger, coverage)
+    pub fn with_lines(mut self) -> Self {
+        self.lines = true;
+        self
+    }
+
+    /// Emit a `Statement::Source` marker at the start of the program and of
+    /// every function body, so code can be traced back to its file after it
+    /// was imported or called from elsewhere (coverage)
+    pub fn with_source(mut self, name: &str) -> Self {
+        self.source = Some(name.to_string());
+        self
+    }
+
+    fn push_line(&self, statements: &mut Vec<Statement>) {
+        if self.lines {
+            if let Some(token) = self.tokens.get(self.pos) {
+                statements.push(Statement::Line(token.line));
+            }
+        }
+    }
+
+    /// Line, column and length of the token the parser stopped at, for
+    /// reporting where a parse error happened
+    pub fn error_location(&self) -> Option<(usize, usize, usize)> {
+        let token = self.tokens.get(self.pos).or_else(|| self.tokens.last())?;
+        Some((token.line, token.column, token.span.len().max(1)))
+    }
+
+    /// Whether parsing stopped because the input ran out (only newlines and
+    /// dedents were left), so more input could still make it valid
+    pub fn reached_end(&self) -> bool {
+        self.tokens[self.pos.min(self.tokens.len())..]
+            .iter()
+            .all(|t| matches!(t.token, Token::Newline | Token::Indent | Token::Dedent))
     }
 
     pub fn parse(&mut self) -> Result<Program, String> {
         let mut statements = Vec::new();
+        if let Some(source) = &self.source {
+            statements.push(Statement::Source(source.clone()));
+        }
         
         while !self.is_at_end() {
             self.skip_newlines();
             if !self.is_at_end() {
+                self.push_line(&mut statements);
                 statements.push(self.parse_statement()?);
+                statements.append(&mut self.pending);
             }
         }
         
@@ -41,6 +88,7 @@ impl Parser {
         match self.peek() {
             Some(Token::Let) => self.parse_let(),
             Some(Token::Fn) | Some(Token::Def) => self.parse_fn_def(),
+            Some(Token::At) => self.parse_decorated(),
             Some(Token::Class) => self.parse_class(),
             Some(Token::If) => self.parse_if(),
             Some(Token::While) => self.parse_while(),
@@ -56,6 +104,7 @@ impl Parser {
             Some(Token::Global) => self.parse_global(),
             Some(Token::Try) => self.parse_try(),
             Some(Token::Raise) => self.parse_raise(),
+            Some(Token::With) => self.parse_with(),
             _ => self.parse_expr_or_assign(),
         }
     }
@@ -68,6 +117,38 @@ impl Parser {
         Ok(Statement::Let(name, value))
     }
 
+    /// `@name(args)` lines before a function. Once the function is defined,
+    /// each decorator, innermost first, is called with the arguments it was
+    /// given followed by the function: `@get("/")` runs `get("/", fn)`.
+    fn parse_decorated(&mut self) -> Result<Statement, String> {
+        let mut decorators = Vec::new();
+        while self.check(&Token::At) {
+            self.advance();
+            decorators.push(self.parse_call()?);
+            self.skip_newlines();
+        }
+        if !matches!(self.peek(), Some(Token::Fn) | Some(Token::Def)) {
+            return Err(format!("Expected a function after decorator, got {:?}", self.peek()));
+        }
+        let def = self.parse_fn_def()?;
+        let Statement::FnDef { name, .. } = &def else { unreachable!() };
+        for decorator in decorators.into_iter().rev() {
+            let function = Expr::Identifier(name.clone());
+            self.pending.push(Statement::Expr(match decorator {
+                Expr::Call(callee, mut args) => {
+                    args.push(function);
+                    Expr::Call(callee, args)
+                }
+                Expr::CallWithKwargs(callee, mut args, kwargs) => {
+                    args.push(function);
+                    Expr::CallWithKwargs(callee, args, kwargs)
+                }
+                callee => Expr::Call(Box::new(callee), vec![function]),
+            }));
+        }
+        Ok(def)
+    }
+
     fn parse_fn_def(&mut self) -> Result<Statement, String> {
         self.advance(); // consume 'fn' or 'def'
         let name = self.expect_identifier()?;
@@ -89,6 +170,14 @@ impl Parser {
             while !self.check(&Token::RParen) {
                 let param_name = self.expect_identifier()?;
                 
+                // Optional type annotation: param: type
+                let annotation = if self.check(&Token::Colon) {
+                    self.advance();
+                    Some(self.parse_type_annotation()?)
+                } else {
+                    None
+                };
+                
                 // Check for default value: param=value
                 let default = if self.check(&Token::Eq) {
                     self.advance();
@@ -101,7 +190,7 @@ impl Parser {
                     None
                 };
                 
-                params.push(Param { name: param_name, default });
+                params.push(Param { name: param_name, default, annotation });
                 
                 if !self.check(&Token::Comma) {
                     break;
@@ -110,11 +199,50 @@ impl Parser {
             }
         }
         self.expect(Token::RParen)?;
+        let returns = if self.check(&Token::Arrow) {
+            self.advance();
+            Some(self.parse_type_annotation()?)
+        } else {
+            None
+        };
         self.expect(Token::Colon)?;
         
-        let body = self.parse_block()?;
+        let mut body = self.parse_block()?;
+        if let Some(source) = &self.source {
+            body.insert(0, Statement::Source(source.clone()));
+        }
         
-        Ok(Statement::FnDef { name, params, body })
+        Ok(Statement::FnDef { name, params, body, returns })
+    }
+
+    /// A type annotation: `name`, `name[type, ...]`, or a union `type | type`,
+    /// returned as normalised source text
+    fn parse_type_annotation(&mut self) -> Result<String, String> {
+        let mut union = Vec::new();
+        loop {
+            let mut text = if self.check(&Token::None) {
+                self.advance();
+                "none".to_string()
+            } else {
+                self.expect_identifier().map_err(|_| format!("Expected type name, got {:?}", self.peek()))?
+            };
+            if self.check(&Token::LBracket) {
+                self.advance();
+                let mut args = vec![self.parse_type_annotation()?];
+                while self.check(&Token::Comma) {
+                    self.advance();
+                    args.push(self.parse_type_annotation()?);
+                }
+                self.expect(Token::RBracket)?;
+                text = format!("{}[{}]", text, args.join(", "));
+            }
+            union.push(text);
+            if !self.check(&Token::Pipe) {
+                break;
+            }
+            self.advance();
+        }
+        Ok(union.join(" | "))
     }
 
     fn parse_class(&mut self) -> Result<Statement, String> {
@@ -136,15 +264,21 @@ impl Parser {
         // Parse class body (methods and attributes)
         let body = self.parse_block()?;
         
+        // A leading string literal is the class docstring
+        let doc = match body.iter().find(|stmt| !matches!(stmt, Statement::Line(_))) {
+            Some(Statement::Expr(Expr::String(s))) => Some(s.clone()),
+            _ => None,
+        };
+        
         // Extract methods from body
         let mut methods = Vec::new();
         for stmt in body {
-            if let Statement::FnDef { name, params, body } = stmt {
+            if let Statement::FnDef { name, params, body, .. } = stmt {
                 methods.push(Method { name, params, body });
             }
         }
         
-        Ok(Statement::ClassDef { name, parent, methods })
+        Ok(Statement::ClassDef { name, parent, methods, doc })
     }
 
     fn parse_if(&mut self) -> Result<Statement, String> {
@@ -255,6 +389,23 @@ impl Parser {
         })
     }
 
+    fn parse_with(&mut self) -> Result<Statement, String> {
+        self.advance(); // consume 'with'
+        let context = self.parse_expr()?;
+        
+        let alias = if self.check(&Token::As) {
+            self.advance();
+            Some(self.expect_identifier()?)
+        } else {
+            None
+        };
+        
+        self.expect(Token::Colon)?;
+        let body = self.parse_block()?;
+        
+        Ok(Statement::With { context, alias, body })
+    }
+
     fn parse_raise(&mut self) -> Result<Statement, String> {
         self.advance(); // consume 'raise'
         let expr = self.parse_expr()?;
@@ -345,7 +496,9 @@ impl Parser {
         if !self.check(&Token::Indent) {
             // Single-line block (no indent)
             if !self.is_at_end() && !self.check(&Token::Newline) && !self.check(&Token::Dedent) {
+                self.push_line(&mut statements);
                 statements.push(self.parse_statement()?);
+                statements.append(&mut self.pending);
             }
             return Ok(statements);
         }
@@ -360,7 +513,9 @@ impl Parser {
                 break;
             }
             
+            self.push_line(&mut statements);
             statements.push(self.parse_statement()?);
+            statements.append(&mut self.pending);
             self.skip_newlines();
         }
         
@@ -0,0 +1,300 @@
+//! Profiler for `poly run --profile` and `poly dev --profile`
+//!
+//! The interpreter reports every function entry and exit and, for programs
+//! parsed with `Parser::with_lines`, every statement. From that the profiler
+//! keeps per-function and per-line timings and call counts, collapsed stacks
+//! (the `a;b;c 123` format flamegraph tools read) and the time spent in each
+//! IPC command. Stack counts are microseconds of self time.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::ast::Value;
+use crate::interpreter::Interpreter;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+/// Timings of a function or IPC command
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub calls: u64,
+    /// Time including callees (recursive calls are counted once)
+    pub total: Duration,
+    /// Time excluding callees
+    pub self_time: Duration,
+    pub max: Duration,
+}
+
+/// Timings of one source line
+#[derive(Debug, Clone, Default)]
+pub struct LineStats {
+    pub function: String,
+    pub hits: u64,
+    /// Time until the next statement, excluding calls made from the line
+    pub time: Duration,
+}
+
+struct Call {
+    name: String,
+    /// Scope depth the call started at, as in `interpreter::Frame`
+    depth: usize,
+    start: Instant,
+    children: Duration,
+    /// Line to resume timing when the call returns
+    caller_line: Option<usize>,
+}
+
+#[derive(Default)]
+pub struct Profiler {
+    stack: Vec<Call>,
+    functions: HashMap<String, Stats>,
+    lines: HashMap<usize, LineStats>,
+    commands: HashMap<String, Stats>,
+    stacks: HashMap<String, u64>,
+    current_line: Option<(usize, Instant)>,
+    dirty: bool,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open a root frame, such as `<module>`, that later calls nest under
+    pub fn start(&mut self, name: &str) {
+        self.enter(name, 0);
+    }
+
+    /// Close every open frame
+    pub fn finish(&mut self) {
+        self.unwind(0);
+    }
+
+    /// A function was called with its scope starting at `depth`. Frames at
+    /// or above that depth were left by an error and are closed first.
+    pub fn enter(&mut self, name: &str, depth: usize) {
+        self.unwind(depth);
+        let now = Instant::now();
+        let caller_line = self.close_line(now);
+        self.stack.push(Call { name: name.to_string(), depth, start: now, children: Duration::ZERO, caller_line });
+    }
+
+    /// The scope stack shrank back to `depth`
+    pub fn exit(&mut self, depth: usize) {
+        self.unwind(depth);
+    }
+
+    /// A statement on `line` is about to run
+    pub fn line(&mut self, line: usize) {
+        let now = Instant::now();
+        self.close_line(now);
+        let function = self.stack.last().map_or("<module>", |call| call.name.as_str());
+        let stats = self.lines.entry(line).or_insert_with(|| LineStats { function: function.to_string(), ..Default::default() });
+        stats.hits += 1;
+        self.current_line = Some((line, now));
+    }
+
+    /// An IPC command finished after `elapsed`
+    pub fn command(&mut self, name: &str, elapsed: Duration) {
+        self.unwind(0);
+        self.current_line = None;
+        let stats = self.commands.entry(name.to_string()).or_default();
+        stats.calls += 1;
+        stats.total += elapsed;
+        stats.self_time += elapsed;
+        stats.max = stats.max.max(elapsed);
+        self.dirty = true;
+    }
+
+    fn close_line(&mut self, now: Instant) -> Option<usize> {
+        let (line, since) = self.current_line.take()?;
+        if let Some(stats) = self.lines.get_mut(&line) {
+            stats.time += now - since;
+        }
+        Some(line)
+    }
+
+    fn unwind(&mut self, depth: usize) {
+        while self.stack.last().is_some_and(|call| call.depth >= depth) {
+            let Some(call) = self.stack.pop() else { break };
+            let now = Instant::now();
+            self.close_line(now);
+            self.current_line = call.caller_line.map(|line| (line, now));
+
+            let total = now - call.start;
+            let self_time = total.saturating_sub(call.children);
+            if let Some(parent) = self.stack.last_mut() {
+                parent.children += total;
+            }
+            let path: Vec<&str> = self.stack.iter().map(|c| c.name.as_str()).chain([call.name.as_str()]).collect();
+            *self.stacks.entry(path.join(";")).or_default() += self_time.as_micros() as u64;
+
+            let recursive = self.stack.iter().any(|c| c.name == call.name);
+            let stats = self.functions.entry(call.name).or_default();
+            stats.calls += 1;
+            stats.self_time += self_time;
+            if !recursive {
+                stats.total += total;
+            }
+            stats.max = stats.max.max(total);
+            self.dirty = true;
+        }
+    }
+
+    pub fn functions(&self) -> &HashMap<String, Stats> {
+        &self.functions
+    }
+
+    pub fn lines(&self) -> &HashMap<usize, LineStats> {
+        &self.lines
+    }
+
+    pub fn commands(&self) -> &HashMap<String, Stats> {
+        &self.commands
+    }
+
+    /// Whether anything was recorded since the last call
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Collapsed stacks, one `frame;frame;frame microseconds` per line
+    pub fn collapsed(&self) -> String {
+        let mut stacks: Vec<_> = self.stacks.iter().filter(|(_, us)| **us > 0).collect();
+        stacks.sort();
+        stacks.iter().map(|(stack, us)| format!("{} {}\n", stack, us)).collect()
+    }
+
+    /// Flamegraph of the collapsed stacks as a standalone, clickable SVG
+    pub fn flamegraph(&self) -> Result<String, String> {
+        let collapsed = self.collapsed();
+        if collapsed.is_empty() {
+            return Err("No samples recorded".to_string());
+        }
+        let mut options = inferno::flamegraph::Options::default();
+        options.title = "Poly profile".to_string();
+        options.count_name = "us".to_string();
+        let mut svg = Vec::new();
+        inferno::flamegraph::from_lines(&mut options, collapsed.lines(), &mut svg).map_err(|e| e.to_string())?;
+        String::from_utf8(svg).map_err(|e| e.to_string())
+    }
+
+    /// Text tables of the hottest functions, lines and IPC commands
+    pub fn summary(&self, limit: usize) -> String {
+        let ms = |d: Duration| format!("{:.2}", d.as_secs_f64() * 1000.0);
+        let mut out = String::new();
+
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_by(|a, b| b.1.self_time.cmp(&a.1.self_time).then(a.0.cmp(b.0)));
+        out.push_str(&format!("{:<32} {:>8} {:>12} {:>12} {:>12}\n", "function", "calls", "self ms", "total ms", "avg ms"));
+        for (name, stats) in functions.iter().take(limit) {
+            let avg = stats.total / stats.calls.max(1) as u32;
+            out.push_str(&format!("{:<32} {:>8} {:>12} {:>12} {:>12}\n", name, stats.calls, ms(stats.self_time), ms(stats.total), ms(avg)));
+        }
+
+        if !self.lines.is_empty() {
+            let mut lines: Vec<_> = self.lines.iter().collect();
+            lines.sort_by(|a, b| b.1.time.cmp(&a.1.time).then(a.0.cmp(b.0)));
+            out.push_str(&format!("\n{:<32} {:>8} {:>12}\n", "line", "hits", "ms"));
+            for (line, stats) in lines.iter().take(limit) {
+                let label = format!("{} ({})", line, stats.function);
+                out.push_str(&format!("{:<32} {:>8} {:>12}\n", label, stats.hits, ms(stats.time)));
+            }
+        }
+
+        if !self.commands.is_empty() {
+            let mut commands: Vec<_> = self.commands.iter().collect();
+            commands.sort_by(|a, b| b.1.total.cmp(&a.1.total).then(a.0.cmp(b.0)));
+            out.push_str(&format!("\n{:<32} {:>8} {:>12} {:>12} {:>12}\n", "ipc command", "calls", "total ms", "avg ms", "max ms"));
+            for (name, stats) in commands.iter().take(limit) {
+                let avg = stats.total / stats.calls.max(1) as u32;
+                out.push_str(&format!("{:<32} {:>8} {:>12} {:>12} {:>12}\n", name, stats.calls, ms(stats.total), ms(avg), ms(stats.max)));
+            }
+        }
+        out
+    }
+
+    /// Write `<stem>.folded`, `<stem>.svg` and `<stem>.txt`. Returns the paths written.
+    pub fn write(&self, stem: &Path) -> Result<Vec<PathBuf>, String> {
+        let path = |ext: &str| PathBuf::from(format!("{}.{}", stem.display(), ext));
+        let mut files = vec![(path("folded"), self.collapsed()), (path("txt"), self.summary(20))];
+        if let Ok(svg) = self.flamegraph() {
+            files.push((path("svg"), svg));
+        }
+        for (file, contents) in &files {
+            std::fs::write(file, contents).map_err(|e| format!("Cannot write {}: {}", file.display(), e))?;
+        }
+        Ok(files.into_iter().map(|(file, _)| file).collect())
+    }
+}
+
+/// Run source with line markers so the installed profiler sees every
+/// statement. Top-level code is recorded under `<module>`.
+pub fn run(interpreter: &mut Interpreter, source: &str) -> Result<Value, String> {
+    let program = Parser::new(Lexer::new(source).tokenize()).with_lines().parse()?;
+    if let Some(profiler) = interpreter.profiler_mut() {
+        profiler.start("<module>");
+    }
+    let result = interpreter.run(&program);
+    if let Some(profiler) = interpreter.profiler_mut() {
+        profiler.finish();
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_functions_lines_and_stacks() {
+        let source = "\
+fn inner(x):
+    return x * 2
+
+fn outer(n):
+    let total = 0
+    for i in range(n):
+        total = total + inner(i)
+    return total
+
+fn broken():
+    raise \"boom\"
+
+print(outer(5))
+try:
+    broken()
+except:
+    pass
+print(outer(2))
+";
+        let mut interp = Interpreter::new();
+        interp.set_profiler(Some(Profiler::new()));
+        run(&mut interp, source).unwrap();
+        assert_eq!(interp.get_output(), &["20".to_string(), "2".to_string()]);
+        let mut profiler = interp.take_profiler().unwrap();
+
+        assert_eq!(profiler.functions()["outer"].calls, 2);
+        assert_eq!(profiler.functions()["inner"].calls, 7);
+        assert_eq!(profiler.functions()["broken"].calls, 1);
+        assert_eq!(profiler.functions()["<module>"].calls, 1);
+        assert_eq!(profiler.lines()[&2].hits, 7);
+        assert_eq!(profiler.lines()[&2].function, "inner");
+        assert_eq!(profiler.lines()[&13].function, "<module>");
+        let stacks: Vec<&str> = profiler.stacks.keys().map(|s| s.as_str()).collect();
+        assert!(stacks.contains(&"<module>;outer;inner"));
+        assert!(stacks.contains(&"<module>;broken"));
+
+        profiler.command("load_items", Duration::from_millis(3));
+        profiler.command("load_items", Duration::from_millis(5));
+        assert_eq!(profiler.commands()["load_items"].calls, 2);
+        assert_eq!(profiler.commands()["load_items"].max, Duration::from_millis(5));
+        assert!(profiler.take_dirty());
+        assert!(!profiler.take_dirty());
+
+        let summary = profiler.summary(10);
+        assert!(summary.contains("outer") && summary.contains("load_items"));
+        assert!(profiler.flamegraph().unwrap().contains("<svg"));
+    }
+}
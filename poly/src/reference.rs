@@ -0,0 +1,391 @@
+//! Reference data for Poly's builtins, native modules and the `__poly_*`
+//! IPC API. Editor tooling (`poly lsp`), the linter and generated docs read
+//! from here so they stay in sync with the interpreter.
+
+use crate::ast::Value;
+use crate::interpreter::Interpreter;
+
+/// A builtin function registered by the interpreter
+#[derive(Debug, Clone, Copy)]
+pub struct BuiltinDoc {
+    pub name: &'static str,
+    pub signature: &'static str,
+    pub summary: &'static str,
+    pub min_args: usize,
+    /// `None` for variadic builtins
+    pub max_args: Option<usize>,
+}
+
+/// A `__poly_*` function callable from JavaScript via IPC
+#[derive(Debug, Clone, Copy)]
+pub struct ApiDoc {
+    pub name: &'static str,
+    pub namespace: &'static str,
+    /// Keys read from the argument object
+    pub params: &'static [&'static str],
+    pub summary: &'static str,
+}
+
+impl ApiDoc {
+    pub fn signature(&self) -> String {
+        if self.params.is_empty() {
+            format!("{}()", self.name)
+        } else {
+            format!("{}({{ {} }})", self.name, self.params.join(", "))
+        }
+    }
+}
+
+pub const BUILTINS: &[BuiltinDoc] = &[
+    BuiltinDoc { name: "print", signature: "print(*values)", summary: "Print values separated by spaces", min_args: 0, max_args: None },
+    BuiltinDoc { name: "len", signature: "len(value) -> int", summary: "Length of a string, list or dict", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "range", signature: "range(end) / range(start, end, step?) -> list", summary: "List of integers", min_args: 1, max_args: Some(3) },
+    BuiltinDoc { name: "str", signature: "str(value) -> string", summary: "Convert a value to a string", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "int", signature: "int(value) -> int", summary: "Convert a number, string or bool to an int", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "float", signature: "float(value) -> float", summary: "Convert a number or string to a float", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "type", signature: "type(value) -> string", summary: "Name of a value's type", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "input", signature: "input(prompt?) -> string", summary: "Read a line from stdin", min_args: 0, max_args: Some(1) },
+    BuiltinDoc { name: "append", signature: "append(list, value)", summary: "Append a value to a list in place", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "abs", signature: "abs(number) -> number", summary: "Absolute value", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "min", signature: "min(list) / min(a, b, ...) -> value", summary: "Smallest value", min_args: 1, max_args: None },
+    BuiltinDoc { name: "max", signature: "max(list) / max(a, b, ...) -> value", summary: "Largest value", min_args: 1, max_args: None },
+    BuiltinDoc { name: "sum", signature: "sum(list) -> number", summary: "Sum of a list of numbers", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "sorted", signature: "sorted(list) -> list", summary: "Sorted copy of a list", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "reversed", signature: "reversed(list) -> list", summary: "Reversed copy of a list", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "enumerate", signature: "enumerate(list) -> list", summary: "List of [index, value] pairs", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "zip", signature: "zip(a, b) -> list", summary: "List of pairs from two lists", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "map", signature: "map(fn, list) -> list", summary: "Apply a function to every item", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "filter", signature: "filter(fn, list) -> list", summary: "Items for which the function is truthy", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "any", signature: "any(list) -> bool", summary: "Whether any item is truthy", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "all", signature: "all(list) -> bool", summary: "Whether every item is truthy", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "isinstance", signature: "isinstance(value, Class) -> bool", summary: "Whether a value is an instance of a class", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "hasattr", signature: "hasattr(obj, name) -> bool", summary: "Whether an instance has a field", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "getattr", signature: "getattr(obj, name, default?) -> value", summary: "Read an instance field by name", min_args: 2, max_args: Some(3) },
+    BuiltinDoc { name: "setattr", signature: "setattr(obj, name, value)", summary: "Set an instance field by name", min_args: 3, max_args: Some(3) },
+    BuiltinDoc { name: "list", signature: "list(value?) -> list", summary: "Convert a value to a list", min_args: 0, max_args: Some(1) },
+    BuiltinDoc { name: "dict", signature: "dict(pairs?) -> dict", summary: "Create a dict", min_args: 0, max_args: Some(1) },
+    BuiltinDoc { name: "expect_raises", signature: "expect_raises(fn, match?) -> string", summary: "Call fn and return its error message; fails if it does not raise", min_args: 1, max_args: Some(2) },
+    BuiltinDoc { name: "ipc_invoke", signature: "ipc_invoke(function, args?) -> value", summary: "Call a function or system API the way poly.invoke does, through fakes in tests", min_args: 1, max_args: Some(2) },
+    BuiltinDoc { name: "ipc_emit", signature: "ipc_emit(topic, payload?) -> int", summary: "Send a page event to the on() handlers, returning how many ran", min_args: 1, max_args: Some(2) },
+    BuiltinDoc { name: "emitted", signature: "emitted(topic?) -> list", summary: "Events emitted since the last call, as {topic, payload} (poly test only)", min_args: 0, max_args: Some(1) },
+    BuiltinDoc { name: "fake_api", signature: "fake_api(command, result)", summary: "Make ipc_invoke(command) return result (poly test only)", min_args: 1, max_args: Some(2) },
+    BuiltinDoc { name: "fake_calls", signature: "fake_calls(command) -> list", summary: "Arguments of each call a faked command received (poly test only)", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "set", signature: "set(list?) -> list", summary: "List with duplicates removed", min_args: 0, max_args: Some(1) },
+    BuiltinDoc { name: "tuple", signature: "tuple(list?) -> list", summary: "Convert a value to a list", min_args: 0, max_args: Some(1) },
+    BuiltinDoc { name: "bool", signature: "bool(value) -> bool", summary: "Truthiness of a value", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "chr", signature: "chr(code) -> string", summary: "Character for a Unicode code point", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "ord", signature: "ord(char) -> int", summary: "Unicode code point of a character", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "hex", signature: "hex(int) -> string", summary: "Hexadecimal representation", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "bin", signature: "bin(int) -> string", summary: "Binary representation", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "oct", signature: "oct(int) -> string", summary: "Octal representation", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "round", signature: "round(number, digits?) -> number", summary: "Round to a number of decimal digits", min_args: 1, max_args: Some(2) },
+    BuiltinDoc { name: "pow", signature: "pow(base, exp) -> number", summary: "Raise to a power", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "divmod", signature: "divmod(a, b) -> list", summary: "Quotient and remainder", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "slice", signature: "slice(list, start, end?) -> list", summary: "Sub-list between two indexes", min_args: 2, max_args: Some(3) },
+    BuiltinDoc { name: "iter", signature: "iter(value) -> list", summary: "Iterable view of a value", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "next", signature: "next(list, default?) -> value", summary: "First item of a list", min_args: 1, max_args: Some(2) },
+    BuiltinDoc { name: "open", signature: "open(path, mode?) -> string", summary: "Read a file", min_args: 1, max_args: Some(2) },
+    BuiltinDoc { name: "upper", signature: "upper(string) -> string", summary: "Uppercase copy", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "lower", signature: "lower(string) -> string", summary: "Lowercase copy", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "strip", signature: "strip(string) -> string", summary: "Copy without surrounding whitespace", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "split", signature: "split(string, sep?) -> list", summary: "Split a string", min_args: 1, max_args: Some(2) },
+    BuiltinDoc { name: "join", signature: "join(list, sep) -> string", summary: "Join strings with a separator", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "replace", signature: "replace(string, old, new) -> string", summary: "Replace every occurrence", min_args: 3, max_args: Some(3) },
+    BuiltinDoc { name: "startswith", signature: "startswith(string, prefix) -> bool", summary: "Whether a string starts with a prefix", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "endswith", signature: "endswith(string, suffix) -> bool", summary: "Whether a string ends with a suffix", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "find", signature: "find(string, sub) -> int", summary: "Index of a substring, or -1", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "count", signature: "count(string, sub) -> int", summary: "Number of occurrences", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "isdigit", signature: "isdigit(string) -> bool", summary: "Whether every character is a digit", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "isalpha", signature: "isalpha(string) -> bool", summary: "Whether every character is a letter", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "isalnum", signature: "isalnum(string) -> bool", summary: "Whether every character is a letter or digit", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "format", signature: "format(template, *values) -> string", summary: "Fill {} placeholders", min_args: 1, max_args: None },
+    BuiltinDoc { name: "html", signature: "html(title, body, styles?, scripts?) -> string", summary: "Full HTML document", min_args: 2, max_args: Some(4) },
+    BuiltinDoc { name: "html_escape", signature: "html_escape(string) -> string", summary: "Escape HTML special characters", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "html_tag", signature: "html_tag(tag, content, attrs?) -> string", summary: "Single HTML element", min_args: 2, max_args: Some(3) },
+    BuiltinDoc { name: "router", signature: "router(routes) -> dict", summary: "Create a router from a dict of routes", min_args: 1, max_args: Some(2) },
+    BuiltinDoc { name: "route", signature: "route(path, html) -> dict", summary: "A single route entry", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "component", signature: "component(name, template, props?) -> dict", summary: "Reusable component", min_args: 2, max_args: Some(3) },
+    BuiltinDoc { name: "store", signature: "store(name, state, actions?) -> dict", summary: "Reactive state store", min_args: 2, max_args: Some(3) },
+    BuiltinDoc { name: "live_reload", signature: "live_reload(port?) -> string", summary: "WebSocket live reload script", min_args: 0, max_args: Some(1) },
+    BuiltinDoc { name: "push", signature: "push(list, value)", summary: "Append a value to a list", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "pop", signature: "pop(list, index?) -> value", summary: "Remove and return an item", min_args: 1, max_args: Some(2) },
+    BuiltinDoc { name: "insert", signature: "insert(list, index, value)", summary: "Insert a value at an index", min_args: 3, max_args: Some(3) },
+    BuiltinDoc { name: "remove", signature: "remove(list, value)", summary: "Remove the first matching value", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "index", signature: "index(list, value) -> int", summary: "Index of a value", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "clear", signature: "clear(list)", summary: "Remove every item", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "copy", signature: "copy(value) -> value", summary: "Shallow copy", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "extend", signature: "extend(list, other)", summary: "Append every item of another list", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "read_file", signature: "read_file(path) -> string", summary: "Read a text file", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "write_file", signature: "write_file(path, content)", summary: "Write a text file", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "file_exists", signature: "file_exists(path) -> bool", summary: "Whether a file exists", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "list_dir", signature: "list_dir(path) -> list", summary: "File names in a directory", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "mkdir", signature: "mkdir(path) -> bool", summary: "Create a directory and its parents", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "remove_file", signature: "remove_file(path) -> bool", summary: "Delete a file", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "http_get", signature: "http_get(url) -> dict", summary: "GET a URL, returning status and body", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "http_post", signature: "http_post(url, body, content_type?) -> dict", summary: "POST a body, returning status and body", min_args: 2, max_args: Some(3) },
+    BuiltinDoc { name: "http_post_json", signature: "http_post_json(url, data) -> dict", summary: "POST JSON and parse the JSON response", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "http_stream_start", signature: "http_stream_start(url, body) -> string", summary: "Start a streaming request", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "http_stream_poll", signature: "http_stream_poll(id) -> dict", summary: "New chunks from a streaming request", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "http_stream_close", signature: "http_stream_close(id) -> bool", summary: "Close a streaming request", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "json_parse", signature: "json_parse(string) -> value", summary: "Parse JSON", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "json_stringify", signature: "json_stringify(value) -> string", summary: "Serialize to JSON", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "emit", signature: "emit(topic, payload?, window?) -> int", summary: "Send an event to pages listening with poly.on(); returns how many received it", min_args: 1, max_args: Some(3) },
+    BuiltinDoc { name: "on", signature: "on(topic, handler)", summary: "Run handler(payload) for events sent by poly.emit()", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "off", signature: "off(topic)", summary: "Remove the handlers of a topic", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "get", signature: "get(path, handler) -> handler", summary: "Serve GET requests for a path such as \"/api/items/<int:id>\"; usually written @get(path)", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "post", signature: "post(path, handler) -> handler", summary: "Serve POST requests for a path; usually written @post(path)", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "put", signature: "put(path, handler) -> handler", summary: "Serve PUT requests for a path; usually written @put(path)", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "patch", signature: "patch(path, handler) -> handler", summary: "Serve PATCH requests for a path; usually written @patch(path)", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "delete", signature: "delete(path, handler) -> handler", summary: "Serve DELETE requests for a path; usually written @delete(path)", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "middleware", signature: "middleware(prefix?, handler) -> handler", summary: "Run handler(request) before routes under prefix; a returned response answers the request", min_args: 1, max_args: Some(2) },
+    BuiltinDoc { name: "response", signature: "response(body?, status?, headers?) -> dict", summary: "Route response with a status code and headers", min_args: 0, max_args: Some(3) },
+    BuiltinDoc { name: "shared_get", signature: "shared_get(key, default?) -> value", summary: "Read a value shared by every IPC worker", min_args: 1, max_args: Some(2) },
+    BuiltinDoc { name: "shared_set", signature: "shared_set(key, value)", summary: "Store a value for every IPC worker; none removes it", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "shared_add", signature: "shared_add(key, amount?) -> number", summary: "Add to a shared number atomically, returning the new value", min_args: 1, max_args: Some(2) },
+    BuiltinDoc { name: "env", signature: "env() -> dict", summary: "All environment variables", min_args: 0, max_args: Some(0) },
+    BuiltinDoc { name: "env_get", signature: "env_get(name, default?) -> string", summary: "Read an environment variable", min_args: 1, max_args: Some(2) },
+    BuiltinDoc { name: "env_set", signature: "env_set(name, value)", summary: "Set an environment variable", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "exec", signature: "exec(command) -> dict", summary: "Run a shell command, returning stdout, stderr and code", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "spawn", signature: "spawn(command) -> int", summary: "Start a background process", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "hash_md5", signature: "hash_md5(string) -> string", summary: "MD5 digest as hex", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "hash_sha256", signature: "hash_sha256(string) -> string", summary: "SHA-256 digest as hex", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "base64_encode", signature: "base64_encode(string) -> string", summary: "Base64-encode a string", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "base64_decode", signature: "base64_decode(string) -> string", summary: "Decode a Base64 string", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "uuid", signature: "uuid() -> string", summary: "Random UUID v4", min_args: 0, max_args: Some(0) },
+    BuiltinDoc { name: "timestamp", signature: "timestamp() -> int", summary: "Milliseconds since the Unix epoch", min_args: 0, max_args: Some(0) },
+    BuiltinDoc { name: "datetime", signature: "datetime() -> dict", summary: "Current local date and time fields", min_args: 0, max_args: Some(0) },
+    BuiltinDoc { name: "sleep_ms", signature: "sleep_ms(milliseconds)", summary: "Pause execution", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "parallel_map", signature: "parallel_map(fn, list) -> list", summary: "Map over a list using threads", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "parallel_filter", signature: "parallel_filter(fn, list) -> list", summary: "Filter a list using threads", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "encrypt", signature: "encrypt(plaintext, key, algorithm?) -> string", summary: "Authenticated encryption with a base64 key", min_args: 2, max_args: Some(3) },
+    BuiltinDoc { name: "decrypt", signature: "decrypt(token, key, algorithm?) -> string", summary: "Decrypt a token produced by encrypt()", min_args: 2, max_args: Some(3) },
+    BuiltinDoc { name: "hmac", signature: "hmac(key, data, algorithm?) -> string", summary: "HMAC digest as hex", min_args: 2, max_args: Some(3) },
+    BuiltinDoc { name: "path_join", signature: "path_join(*parts) -> string", summary: "Join path segments", min_args: 1, max_args: None },
+    BuiltinDoc { name: "path_exists", signature: "path_exists(path) -> bool", summary: "Whether a path exists", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "path_basename", signature: "path_basename(path) -> string", summary: "Final path component", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "path_dirname", signature: "path_dirname(path) -> string", summary: "Parent directory", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "path_ext", signature: "path_ext(path) -> string", summary: "File extension", min_args: 1, max_args: Some(1) },
+    BuiltinDoc { name: "regex_match", signature: "regex_match(pattern, string) -> bool", summary: "Whether a regex matches", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "regex_find", signature: "regex_find(pattern, string) -> list", summary: "All regex matches", min_args: 2, max_args: Some(2) },
+    BuiltinDoc { name: "regex_replace", signature: "regex_replace(pattern, replacement, string) -> string", summary: "Replace regex matches", min_args: 3, max_args: Some(3) },
+];
+
+pub const POLY_APIS: &[ApiDoc] = &[
+    ApiDoc { name: "__poly_ai_anthropic", namespace: "ai", params: &["model", "messages", "apiKey", "temperature", "maxTokens", "enableThinking", "thinkingBudget", "tools", "toolChoice"], summary: "Chat completion via the Anthropic API" },
+    ApiDoc { name: "__poly_ai_chat", namespace: "ai", params: &["provider", "model", "messages", "baseUrl", "apiKey", "temperature", "maxTokens", "enableThinking", "thinkingBudget", "tools", "toolChoice"], summary: "Chat completion with any configured provider" },
+    ApiDoc { name: "__poly_ai_check_ollama", namespace: "ai", params: &[], summary: "Check whether a local Ollama server is running" },
+    ApiDoc { name: "__poly_ai_custom", namespace: "ai", params: &["baseUrl", "model", "messages", "apiKey", "temperature", "maxTokens", "tools", "toolChoice"], summary: "Chat completion via an OpenAI-compatible endpoint" },
+    ApiDoc { name: "__poly_ai_list_models", namespace: "ai", params: &[], summary: "List models available in Ollama" },
+    ApiDoc { name: "__poly_ai_ollama", namespace: "ai", params: &["model", "messages", "temperature", "maxTokens", "think", "tools", "toolChoice"], summary: "Chat completion via a local Ollama model" },
+    ApiDoc { name: "__poly_ai_openai", namespace: "ai", params: &["model", "messages", "apiKey", "temperature", "maxTokens", "tools", "toolChoice"], summary: "Chat completion via the OpenAI API" },
+    ApiDoc { name: "__poly_ai_stream_cancel", namespace: "ai", params: &["streamId"], summary: "Cancel a streaming completion" },
+    ApiDoc { name: "__poly_ai_stream_list", namespace: "ai", params: &[], summary: "List active streaming completions" },
+    ApiDoc { name: "__poly_ai_stream_poll", namespace: "ai", params: &["streamId"], summary: "Fetch new chunks from a streaming completion" },
+    ApiDoc { name: "__poly_ai_stream_start", namespace: "ai", params: &["model", "messages", "temperature", "think"], summary: "Start a streaming completion" },
+    ApiDoc { name: "__poly_app_exit", namespace: "app", params: &["code"], summary: "Exit the application with a status code" },
+    ApiDoc { name: "__poly_app_get_name", namespace: "app", params: &[], summary: "Application name from poly.toml" },
+    ApiDoc { name: "__poly_app_get_path", namespace: "app", params: &["name"], summary: "Well-known directory path (home, data, config, ...)" },
+    ApiDoc { name: "__poly_app_get_version", namespace: "app", params: &[], summary: "Application version from poly.toml" },
+    ApiDoc { name: "__poly_app_relaunch", namespace: "app", params: &[], summary: "Restart the application" },
+    ApiDoc { name: "__poly_browser_back", namespace: "browser", params: &["id"], summary: "Go back in a tab" },
+    ApiDoc { name: "__poly_browser_clear_history", namespace: "browser", params: &["id"], summary: "Clear the history of a tab" },
+    ApiDoc { name: "__poly_browser_close_tab", namespace: "browser", params: &["id"], summary: "Close a tab" },
+    ApiDoc { name: "__poly_browser_create_tab", namespace: "browser", params: &["url"], summary: "Open a new tab" },
+    ApiDoc { name: "__poly_browser_fetch", namespace: "browser", params: &["url"], summary: "Fetch a page bypassing CORS" },
+    ApiDoc { name: "__poly_browser_forward", namespace: "browser", params: &["id"], summary: "Go forward in a tab" },
+    ApiDoc { name: "__poly_browser_get_history", namespace: "browser", params: &["id"], summary: "History entries of a tab" },
+    ApiDoc { name: "__poly_browser_get_tab", namespace: "browser", params: &["id"], summary: "State of a tab" },
+    ApiDoc { name: "__poly_browser_list_tabs", namespace: "browser", params: &[], summary: "List open tabs" },
+    ApiDoc { name: "__poly_browser_navigate", namespace: "browser", params: &["id", "url"], summary: "Navigate a tab to a URL" },
+    ApiDoc { name: "__poly_browser_open_window", namespace: "browser", params: &["url", "title", "width", "height"], summary: "Open a URL in a new browser window" },
+    ApiDoc { name: "__poly_browser_set_loading", namespace: "browser", params: &["id", "loading"], summary: "Set the loading flag of a tab" },
+    ApiDoc { name: "__poly_browser_set_title", namespace: "browser", params: &["id", "title"], summary: "Set the title of a tab" },
+    ApiDoc { name: "__poly_browser_window_close", namespace: "browser", params: &["id"], summary: "Close a browser window" },
+    ApiDoc { name: "__poly_browser_window_navigate", namespace: "browser", params: &["id", "url"], summary: "Navigate a browser window to a URL" },
+    ApiDoc { name: "__poly_blob_release", namespace: "blob", params: &["url"], summary: "Free an in-memory blob before it is evicted" },
+    ApiDoc { name: "__poly_clipboard_clear", namespace: "clipboard", params: &[], summary: "Clear the clipboard" },
+    ApiDoc { name: "__poly_clipboard_read", namespace: "clipboard", params: &[], summary: "Read text from the clipboard" },
+    ApiDoc { name: "__poly_clipboard_read_image", namespace: "clipboard", params: &[], summary: "Read the clipboard image as a PNG blob reference" },
+    ApiDoc { name: "__poly_clipboard_write", namespace: "clipboard", params: &["text"], summary: "Write text to the clipboard" },
+    ApiDoc { name: "__poly_db_close", namespace: "db", params: &["id"], summary: "Close a database connection" },
+    ApiDoc { name: "__poly_db_execute", namespace: "db", params: &["id", "sql", "params"], summary: "Run a SQL statement, returning the number of changes" },
+    ApiDoc { name: "__poly_db_open", namespace: "db", params: &["path"], summary: "Open a SQLite database (`:memory:` by default)" },
+    ApiDoc { name: "__poly_db_query", namespace: "db", params: &["id", "sql", "params"], summary: "Run a query, returning rows as objects" },
+    ApiDoc { name: "__poly_db_query_one", namespace: "db", params: &["id", "sql", "params"], summary: "Run a query, returning the first row or null" },
+    ApiDoc { name: "__poly_deeplink_get", namespace: "deeplink", params: &[], summary: "The deep link the app was launched with" },
+    ApiDoc { name: "__poly_deeplink_has", namespace: "deeplink", params: &[], summary: "Whether the app was launched from a deep link" },
+    ApiDoc { name: "__poly_deeplink_is_registered", namespace: "deeplink", params: &["protocol"], summary: "Whether a protocol handler is registered" },
+    ApiDoc { name: "__poly_deeplink_register", namespace: "deeplink", params: &["protocol", "appName"], summary: "Register a custom protocol handler" },
+    ApiDoc { name: "__poly_deeplink_unregister", namespace: "deeplink", params: &["protocol"], summary: "Remove a custom protocol handler" },
+    ApiDoc { name: "__poly_dialog_confirm", namespace: "dialog", params: &["title", "message"], summary: "Ask a yes/no question" },
+    ApiDoc { name: "__poly_dialog_folder", namespace: "dialog", params: &["title"], summary: "Pick a folder" },
+    ApiDoc { name: "__poly_dialog_message", namespace: "dialog", params: &["title", "message", "level"], summary: "Show a message box" },
+    ApiDoc { name: "__poly_dialog_open", namespace: "dialog", params: &["title", "filters"], summary: "Pick a file to open" },
+    ApiDoc { name: "__poly_dialog_open_multiple", namespace: "dialog", params: &["title", "filters"], summary: "Pick several files to open" },
+    ApiDoc { name: "__poly_dialog_save", namespace: "dialog", params: &["title", "defaultName", "filters"], summary: "Pick a path to save to" },
+    ApiDoc { name: "__poly_fs_exists", namespace: "fs", params: &["path"], summary: "Whether a path exists" },
+    ApiDoc { name: "__poly_fs_read", namespace: "fs", params: &["path"], summary: "Read a text file" },
+    ApiDoc { name: "__poly_fs_read_dir", namespace: "fs", params: &["path"], summary: "List a directory" },
+    ApiDoc { name: "__poly_fs_write", namespace: "fs", params: &["path", "content"], summary: "Write a text file" },
+    ApiDoc { name: "__poly_http_delete", namespace: "http", params: &["url", "headers", "timeout"], summary: "HTTP DELETE request" },
+    ApiDoc { name: "__poly_http_get", namespace: "http", params: &["url", "headers", "timeout"], summary: "HTTP GET request" },
+    ApiDoc { name: "__poly_http_patch", namespace: "http", params: &["url", "body", "headers", "timeout"], summary: "HTTP PATCH request" },
+    ApiDoc { name: "__poly_http_post", namespace: "http", params: &["url", "body", "headers", "timeout"], summary: "HTTP POST request" },
+    ApiDoc { name: "__poly_http_put", namespace: "http", params: &["url", "body", "headers", "timeout"], summary: "HTTP PUT request" },
+    ApiDoc { name: "__poly_http_request", namespace: "http", params: &["method", "url", "body", "headers", "timeout"], summary: "HTTP request with any method" },
+    ApiDoc { name: "__poly_ipc_cancel", namespace: "ipc", params: &["id"], summary: "Cancel the queued or running calls sent with an id" },
+    ApiDoc { name: "__poly_ipc_metrics", namespace: "ipc", params: &[], summary: "Worker count, busy workers and queue depth of the IPC pool" },
+    ApiDoc { name: "__poly_multiview_close", namespace: "multiview", params: &["windowId"], summary: "Close a multi-view window" },
+    ApiDoc { name: "__poly_multiview_create", namespace: "multiview", params: &["title", "width", "height", "decorations", "resizable", "icon", "views"], summary: "Create a window hosting several WebViews" },
+    ApiDoc { name: "__poly_multiview_get", namespace: "multiview", params: &["windowId"], summary: "State of a multi-view window" },
+    ApiDoc { name: "__poly_multiview_list", namespace: "multiview", params: &[], summary: "List multi-view windows" },
+    ApiDoc { name: "__poly_multiview_navigate", namespace: "multiview", params: &["windowId", "viewId", "url"], summary: "Navigate one view of a multi-view window" },
+    ApiDoc { name: "__poly_multiview_post_message", namespace: "multiview", params: &["windowId", "viewId", "message"], summary: "Send a message to one view" },
+    ApiDoc { name: "__poly_multiview_set_bounds", namespace: "multiview", params: &["windowId", "viewId", "x", "y", "width", "height"], summary: "Move or resize one view" },
+    ApiDoc { name: "__poly_notification_show", namespace: "notification", params: &["title", "body", "icon"], summary: "Show a desktop notification" },
+    ApiDoc { name: "__poly_notification_show_timeout", namespace: "notification", params: &["title", "body", "timeout"], summary: "Show a notification that closes after a timeout" },
+    ApiDoc { name: "__poly_os_arch", namespace: "os", params: &[], summary: "CPU architecture" },
+    ApiDoc { name: "__poly_os_homedir", namespace: "os", params: &[], summary: "Home directory" },
+    ApiDoc { name: "__poly_os_hostname", namespace: "os", params: &[], summary: "Host name" },
+    ApiDoc { name: "__poly_os_platform", namespace: "os", params: &[], summary: "Operating system name" },
+    ApiDoc { name: "__poly_os_tempdir", namespace: "os", params: &[], summary: "Temporary directory" },
+    ApiDoc { name: "__poly_os_version", namespace: "os", params: &[], summary: "Operating system version" },
+    ApiDoc { name: "__poly_shell_open", namespace: "shell", params: &["url"], summary: "Open a URL in the default browser" },
+    ApiDoc { name: "__poly_shell_open_path", namespace: "shell", params: &["path"], summary: "Open a file or folder with the default app" },
+    ApiDoc { name: "__poly_shell_open_with", namespace: "shell", params: &["path", "app"], summary: "Open a file with a specific app" },
+    ApiDoc { name: "__poly_titlebar_get", namespace: "titlebar", params: &[], summary: "Current custom title bar settings" },
+    ApiDoc { name: "__poly_titlebar_navigate", namespace: "titlebar", params: &["url"], summary: "Navigate the title bar WebView" },
+    ApiDoc { name: "__poly_titlebar_set", namespace: "titlebar", params: &["enabled", "height", "html", "css", "js", "background"], summary: "Configure the custom title bar" },
+    ApiDoc { name: "__poly_titlebar_set_css", namespace: "titlebar", params: &["css"], summary: "Set the title bar CSS" },
+    ApiDoc { name: "__poly_titlebar_set_enabled", namespace: "titlebar", params: &["enabled"], summary: "Enable or disable the custom title bar" },
+    ApiDoc { name: "__poly_titlebar_set_height", namespace: "titlebar", params: &["height"], summary: "Set the title bar height" },
+    ApiDoc { name: "__poly_titlebar_set_html", namespace: "titlebar", params: &["html"], summary: "Set the title bar HTML" },
+    ApiDoc { name: "__poly_titlebar_set_js", namespace: "titlebar", params: &["js"], summary: "Set the title bar script" },
+    ApiDoc { name: "__poly_tray_is_enabled", namespace: "tray", params: &[], summary: "Whether the system tray icon is enabled" },
+    ApiDoc { name: "__poly_updater_check_github", namespace: "updater", params: &["repo", "currentVersion"], summary: "Check GitHub releases for an update" },
+    ApiDoc { name: "__poly_updater_check_url", namespace: "updater", params: &["url", "currentVersion"], summary: "Check a custom endpoint for an update" },
+    ApiDoc { name: "__poly_updater_download", namespace: "updater", params: &["url"], summary: "Download an update" },
+    ApiDoc { name: "__poly_updater_install", namespace: "updater", params: &["path"], summary: "Install a downloaded update" },
+    ApiDoc { name: "__poly_upload_cancel", namespace: "upload", params: &["id"], summary: "Discard an unfinished upload" },
+    ApiDoc { name: "__poly_upload_finish", namespace: "upload", params: &["id", "path"], summary: "Move a finished upload to a path" },
+    ApiDoc { name: "__poly_upload_start", namespace: "upload", params: &[], summary: "Begin a chunked upload, returning its id" },
+    ApiDoc { name: "__poly_webview_create", namespace: "webview", params: &["id", "url", "html", "x", "y", "width", "height", "visible", "transparent", "devtools", "userAgent", "zoomLevel", "autoplay"], summary: "Create an embedded WebView" },
+    ApiDoc { name: "__poly_webview_destroy", namespace: "webview", params: &["id"], summary: "Destroy a WebView" },
+    ApiDoc { name: "__poly_webview_eval", namespace: "webview", params: &["id", "script"], summary: "Run a script in a WebView" },
+    ApiDoc { name: "__poly_webview_focus", namespace: "webview", params: &["id"], summary: "Focus a WebView" },
+    ApiDoc { name: "__poly_webview_get", namespace: "webview", params: &["id"], summary: "State of a WebView" },
+    ApiDoc { name: "__poly_webview_get_bounds", namespace: "webview", params: &["id"], summary: "Position and size of a WebView" },
+    ApiDoc { name: "__poly_webview_go_back", namespace: "webview", params: &["id"], summary: "Go back in a WebView" },
+    ApiDoc { name: "__poly_webview_go_forward", namespace: "webview", params: &["id"], summary: "Go forward in a WebView" },
+    ApiDoc { name: "__poly_webview_list", namespace: "webview", params: &[], summary: "List WebViews" },
+    ApiDoc { name: "__poly_webview_load_html", namespace: "webview", params: &["id", "html"], summary: "Load HTML into a WebView" },
+    ApiDoc { name: "__poly_webview_navigate", namespace: "webview", params: &["id", "url"], summary: "Navigate a WebView to a URL" },
+    ApiDoc { name: "__poly_webview_poll_events", namespace: "webview", params: &[], summary: "Fetch pending WebView events" },
+    ApiDoc { name: "__poly_webview_reload", namespace: "webview", params: &["id"], summary: "Reload a WebView" },
+    ApiDoc { name: "__poly_webview_respond_permission", namespace: "webview", params: &["id", "permission", "granted"], summary: "Answer a WebView permission request" },
+    ApiDoc { name: "__poly_webview_set_bounds", namespace: "webview", params: &["id", "x", "y", "width", "height"], summary: "Move or resize a WebView" },
+    ApiDoc { name: "__poly_webview_set_main_bounds", namespace: "webview", params: &["x", "y", "width", "height"], summary: "Move or resize the main WebView" },
+    ApiDoc { name: "__poly_webview_set_visible", namespace: "webview", params: &["id", "visible"], summary: "Show or hide a WebView" },
+    ApiDoc { name: "__poly_webview_set_zoom", namespace: "webview", params: &["id", "level"], summary: "Set the zoom level of a WebView" },
+    ApiDoc { name: "__poly_webview_stop", namespace: "webview", params: &["id"], summary: "Stop loading a WebView" },
+    ApiDoc { name: "__poly_window_close", namespace: "window", params: &["id"], summary: "Close a window" },
+    ApiDoc { name: "__poly_window_close_all", namespace: "window", params: &[], summary: "Close every secondary window" },
+    ApiDoc { name: "__poly_window_count", namespace: "window", params: &[], summary: "Number of open windows" },
+    ApiDoc { name: "__poly_window_create", namespace: "window", params: &["title", "width", "height", "url", "html"], summary: "Open a new window" },
+    ApiDoc { name: "__poly_window_eval", namespace: "window", params: &["id", "script"], summary: "Run a script in a window" },
+    ApiDoc { name: "__poly_window_focus", namespace: "window", params: &["id"], summary: "Focus a window" },
+    ApiDoc { name: "__poly_window_get_state", namespace: "window", params: &["id"], summary: "Position, size and flags of a window" },
+    ApiDoc { name: "__poly_window_hide", namespace: "window", params: &["id"], summary: "Hide a window" },
+    ApiDoc { name: "__poly_window_list", namespace: "window", params: &[], summary: "List open windows" },
+    ApiDoc { name: "__poly_window_list_states", namespace: "window", params: &[], summary: "State of every window" },
+    ApiDoc { name: "__poly_window_load_html", namespace: "window", params: &["id", "html"], summary: "Load HTML into a window" },
+    ApiDoc { name: "__poly_window_maximize", namespace: "window", params: &["id"], summary: "Maximize a window" },
+    ApiDoc { name: "__poly_window_minimize", namespace: "window", params: &["id"], summary: "Minimize a window" },
+    ApiDoc { name: "__poly_window_navigate", namespace: "window", params: &["id", "url"], summary: "Navigate a window to a URL" },
+    ApiDoc { name: "__poly_window_restore", namespace: "window", params: &["id"], summary: "Restore a minimized or maximized window" },
+    ApiDoc { name: "__poly_window_set_always_on_top", namespace: "window", params: &["id", "value"], summary: "Keep a window above others" },
+    ApiDoc { name: "__poly_window_set_fullscreen", namespace: "window", params: &["id", "value"], summary: "Toggle fullscreen" },
+    ApiDoc { name: "__poly_window_set_position", namespace: "window", params: &["id", "x", "y"], summary: "Move a window" },
+    ApiDoc { name: "__poly_window_set_size", namespace: "window", params: &["id", "width", "height"], summary: "Resize a window" },
+    ApiDoc { name: "__poly_window_set_title", namespace: "window", params: &["id", "title"], summary: "Set the title of a window" },
+    ApiDoc { name: "__poly_window_show", namespace: "window", params: &["id"], summary: "Show a hidden window" },
+];
+
+/// Modules handled by the interpreter itself (everything else is a `.poly` file)
+pub const NATIVE_MODULES: &[&str] = &[
+    "math", "random", "time", "json", "datetime", "crypto", "http", "sqlite", "csv", "toml", "yaml", "ini",
+];
+
+pub const STRING_METHODS: &[&str] = &[
+    "capitalize", "center", "count", "encode", "endswith", "find", "isalnum", "isalpha", "isdigit", "islower",
+    "isspace", "isupper", "join", "lower", "lstrip", "replace", "rfind", "rstrip", "split", "startswith", "strip",
+    "title", "trim", "upper", "zfill",
+];
+
+pub const LIST_METHODS: &[&str] = &[
+    "append", "clear", "copy", "count", "extend", "index", "insert", "join", "pop", "push", "remove", "reverse", "sort",
+];
+
+pub const DICT_METHODS: &[&str] = &["clear", "copy", "get", "items", "keys", "pop", "update", "values"];
+
+pub const KEYWORDS: &[&str] = &[
+    "let", "fn", "def", "class", "if", "elif", "else", "while", "for", "in", "return", "import", "from",
+    "try", "except", "finally", "raise", "with", "as", "and", "or", "not", "is", "lambda", "assert", "del",
+    "global", "pass", "break", "continue", "true", "false", "none",
+];
+
+pub fn builtin(name: &str) -> Option<&'static BuiltinDoc> {
+    BUILTINS.iter().find(|b| b.name == name)
+}
+
+pub fn poly_api(name: &str) -> Option<&'static ApiDoc> {
+    POLY_APIS.iter().find(|a| a.name == name)
+}
+
+/// Globals that `import <module>` defines for a native module (not always
+/// the module name itself: `import json` only adds `json_dumps`/`json_loads`)
+pub fn import_names(module: &str) -> Option<Vec<String>> {
+    if !NATIVE_MODULES.contains(&module) {
+        return None;
+    }
+    let mut interp = Interpreter::new();
+    let before = interp.global_names();
+    interp.import(module).ok()?;
+    let mut names: Vec<String> = interp
+        .global_names()
+        .into_iter()
+        .filter(|name| !before.contains(name) || name == module)
+        .collect();
+    names.sort();
+    Some(names)
+}
+
+/// Names a native module defines: globals added by `import` plus the keys
+/// of the module dict itself (`datetime.now`, `math.pi`, ...)
+pub fn module_members(module: &str) -> Option<Vec<String>> {
+    let mut members: Vec<String> = import_names(module)?.into_iter().filter(|name| name != module).collect();
+    let mut interp = Interpreter::new();
+    interp.import(module).ok()?;
+    if let Some(Value::Dict(pairs)) = interp.global(module) {
+        members.extend(pairs.iter().map(|(k, _)| k.to_string()).filter(|k| k != crate::interpreter::MODULE_KEY));
+    }
+    members.sort();
+    members.dedup();
+    Some(members)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_covers_registered_builtins() {
+        let interp = Interpreter::new();
+        for name in interp.global_names() {
+            assert!(builtin(&name).is_some(), "builtin '{}' has no reference entry", name);
+        }
+        assert!(module_members("sqlite").unwrap().contains(&"connect".to_string()));
+        assert!(module_members("math").unwrap().contains(&"math_sqrt".to_string()));
+        assert_eq!(poly_api("__poly_db_query").unwrap().signature(), "__poly_db_query({ id, sql, params })");
+    }
+}
@@ -0,0 +1,424 @@
+//! Interactive REPL (`poly` with no arguments, or `poly --repl`)
+//!
+//! Input is buffered until the parser accepts it: a statement that ends
+//! early (an open block or bracket) continues on a `...` prompt, and a block
+//! is closed by an empty line. Results are pretty-printed with a colorized
+//! repr. Line editing, persistent history and tab completion of globals,
+//! attributes and module members come from rustyline.
+
+use std::borrow::Cow;
+use std::path::PathBuf;
+
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+
+use crate::ast::{Program, Statement, Value};
+use crate::interpreter::Interpreter;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::reference;
+
+const RESET: &str = "\x1b[0m";
+const DIM: &str = "\x1b[2m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const MAGENTA: &str = "\x1b[35m";
+const CYAN: &str = "\x1b[36m";
+
+/// Values wider than this are printed one item per line
+const WIDTH: usize = 80;
+
+const COMMANDS: &[&str] = &[":help", ":type", ":load", ":quit"];
+
+const HELP: &str = "\
+  :help          show this help
+  :type EXPR     show the type of an expression
+  :load FILE     run a .poly file in this session
+  :quit          leave the REPL (also exit, quit or Ctrl-D)
+
+  Blocks continue on `...` lines and end with an empty line.
+  Tab completes names, attributes and module members.";
+
+/// Result of checking buffered input with the parser
+#[derive(Debug)]
+pub enum Input {
+    Complete(Program),
+    /// Needs more lines: an open block or bracket, or a block not yet ended
+    /// by an empty line
+    Incomplete,
+    Invalid(String),
+}
+
+pub fn classify(source: &str) -> Input {
+    let mut parser = Parser::new(Lexer::new(source).tokenize());
+    match parser.parse() {
+        Ok(program) => {
+            let opens_block = source.lines().any(|line| line.trim_end().ends_with(':'));
+            if opens_block && !source.ends_with("\n\n") {
+                Input::Incomplete
+            } else {
+                Input::Complete(program)
+            }
+        }
+        Err(_) if parser.reached_end() => Input::Incomplete,
+        Err(e) => Input::Invalid(match parser.error_location() {
+            Some((line, column, _)) => format!("line {}, column {}: {}", line, column, e),
+            None => e,
+        }),
+    }
+}
+
+// ============================================================================
+// Pretty printing
+// ============================================================================
+
+fn paint(text: &str, color: &str, enabled: bool) -> String {
+    if enabled {
+        format!("{}{}{}", color, text, RESET)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Single-line repr of a value
+fn flat(value: &Value, color: bool) -> String {
+    match value {
+        Value::None => paint("none", MAGENTA, color),
+        Value::Bool(b) => paint(&b.to_string(), MAGENTA, color),
+        Value::Int(_) | Value::Float(_) => paint(&value.to_string(), YELLOW, color),
+        Value::String(s) => paint(&format!("{:?}", s), GREEN, color),
+        Value::List(items) => {
+            let items: Vec<String> = items.iter().map(|v| flat(v, color)).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Dict(pairs) => {
+            let pairs: Vec<String> = pairs.iter().map(|(k, v)| format!("{}: {}", flat(k, color), flat(v, color))).collect();
+            format!("{{{}}}", pairs.join(", "))
+        }
+        Value::Function { name, params, .. } => {
+            let params: Vec<&str> = params.iter().map(|p| p.name.as_str()).collect();
+            paint(&format!("<fn {}({})>", name, params.join(", ")), CYAN, color)
+        }
+        Value::Instance { .. } if crate::datetime::is_temporal(value) => paint(&value.to_string(), CYAN, color),
+        Value::Instance { class_name, fields } => {
+            let mut fields: Vec<_> = fields.iter().collect();
+            fields.sort_by(|a, b| a.0.cmp(b.0));
+            let fields: Vec<String> = fields.iter().map(|(k, v)| format!("{}={}", k, flat(v, color))).collect();
+            format!("{}({})", paint(class_name, CYAN, color), fields.join(", "))
+        }
+        other => paint(&other.to_string(), CYAN, color),
+    }
+}
+
+fn write_value(out: &mut String, value: &Value, indent: usize, color: bool) {
+    let fits = indent + flat(value, false).len() <= WIDTH;
+    let pad = " ".repeat(indent + 2);
+    match value {
+        Value::List(items) if !fits && !items.is_empty() => {
+            out.push_str("[\n");
+            for item in items {
+                out.push_str(&pad);
+                write_value(out, item, indent + 2, color);
+                out.push_str(",\n");
+            }
+            out.push_str(&" ".repeat(indent));
+            out.push(']');
+        }
+        Value::Dict(pairs) if !fits && !pairs.is_empty() => {
+            out.push_str("{\n");
+            for (key, item) in pairs {
+                out.push_str(&pad);
+                out.push_str(&flat(key, color));
+                out.push_str(": ");
+                write_value(out, item, indent + 2, color);
+                out.push_str(",\n");
+            }
+            out.push_str(&" ".repeat(indent));
+            out.push('}');
+        }
+        _ => out.push_str(&flat(value, color)),
+    }
+}
+
+/// Repr of a value as the REPL shows it, split over several lines when wide
+pub fn pretty(value: &Value, color: bool) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, 0, color);
+    out
+}
+
+// ============================================================================
+// Completion
+// ============================================================================
+
+/// Follow a dotted path (`config.db`) through variables, dict keys and fields
+fn resolve(interpreter: &Interpreter, path: &str) -> Option<Value> {
+    let mut parts = path.split('.');
+    let mut value = interpreter.variable(parts.next()?)?;
+    for part in parts {
+        value = match value {
+            Value::Dict(pairs) => pairs.into_iter().find(|(k, _)| matches!(k, Value::String(s) if s == part))?.1,
+            Value::Instance { mut fields, .. } => fields.remove(part)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+fn attributes(interpreter: &Interpreter, target: &str) -> Vec<String> {
+    let Some(value) = resolve(interpreter, target) else {
+        return reference::module_members(target).unwrap_or_default();
+    };
+    let mut names: Vec<String> = match &value {
+        Value::Dict(pairs) => pairs
+            .iter()
+            .filter_map(|(k, _)| match k {
+                Value::String(s) => Some(s.clone()),
+                _ => None,
+            })
+            .chain(reference::DICT_METHODS.iter().map(|m| m.to_string()))
+            .collect(),
+        Value::Instance { class_name, fields } => {
+            fields.keys().cloned().chain(interpreter.class_methods(class_name)).collect()
+        }
+        Value::String(_) => reference::STRING_METHODS.iter().map(|m| m.to_string()).collect(),
+        Value::List(_) => reference::LIST_METHODS.iter().map(|m| m.to_string()).collect(),
+        _ => Vec::new(),
+    };
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Completions for the word before `pos`: the position the word starts at
+/// and the candidates that could replace it
+pub fn completions(interpreter: &Interpreter, line: &str, pos: usize) -> (usize, Vec<String>) {
+    let before = &line[..pos];
+    let start = before
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.' || c == ':'))
+        .map_or(0, |i| i + 1);
+    let word = &before[start..];
+
+    if word.starts_with(':') && start == 0 {
+        let matches = COMMANDS.iter().filter(|c| c.starts_with(word)).map(|c| c.to_string()).collect();
+        return (start, matches);
+    }
+    if let Some((target, prefix)) = word.rsplit_once('.') {
+        let matches = attributes(interpreter, target)
+            .into_iter()
+            .filter(|name| name.starts_with(prefix) && (prefix.starts_with('_') || !name.starts_with("__")))
+            .collect();
+        return (pos - prefix.len(), matches);
+    }
+
+    let after_import = before[..start].trim_end().ends_with("import");
+    let mut matches: Vec<String> = if after_import {
+        reference::NATIVE_MODULES.iter().map(|m| m.to_string()).collect()
+    } else {
+        interpreter
+            .variable_names()
+            .into_iter()
+            .chain(reference::KEYWORDS.iter().map(|k| k.to_string()))
+            .collect()
+    };
+    matches.retain(|name| name.starts_with(word));
+    matches.sort();
+    matches.dedup();
+    (start, matches)
+}
+
+// ============================================================================
+// Session
+// ============================================================================
+
+/// A REPL session: the interpreter plus the rustyline hooks that need it
+pub struct Repl {
+    pub interpreter: Interpreter,
+    color: bool,
+    files: FilenameCompleter,
+}
+
+impl Repl {
+    pub fn new(color: bool) -> Self {
+        Self { interpreter: Interpreter::new(), color, files: FilenameCompleter::new() }
+    }
+
+    /// Run a `:command`. Returns the text to show.
+    pub fn command(&mut self, input: &str) -> Result<String, String> {
+        let (command, rest) = input.split_once(char::is_whitespace).map_or((input, ""), |(c, r)| (c, r.trim()));
+        match command {
+            ":help" | ":h" => Ok(HELP.to_string()),
+            ":type" | ":t" => {
+                let value = self.interpreter.evaluate_in_frame(rest, 0)?;
+                Ok(paint(value.type_name(), CYAN, self.color))
+            }
+            ":load" | ":l" => {
+                let source = std::fs::read_to_string(rest).map_err(|e| format!("Cannot read {}: {}", rest, e))?;
+                match classify(&format!("{}\n\n", source)) {
+                    Input::Complete(program) => self.interpreter.run_isolated(&program)?,
+                    Input::Incomplete => return Err(format!("{}: unexpected end of file", rest)),
+                    Input::Invalid(e) => return Err(format!("{}: {}", rest, e)),
+                };
+                Ok(paint(&format!("loaded {}", rest), DIM, self.color))
+            }
+            _ => Err(format!("Unknown command {} (try :help)", command)),
+        }
+    }
+
+    /// Run a complete program. Returns the repr of the result when the last
+    /// statement is an expression with a value.
+    pub fn execute(&mut self, program: &Program) -> Result<Option<String>, String> {
+        let value = self.interpreter.run_isolated(program)?;
+        let is_expression = matches!(program.statements.last(), Some(Statement::Expr(_)));
+        Ok((is_expression && value != Value::None).then(|| pretty(&value, self.color)))
+    }
+}
+
+impl Completer for Repl {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        if line.starts_with(":load ") || line.starts_with(":l ") {
+            return self.files.complete(line, pos, ctx);
+        }
+        let (start, matches) = completions(&self.interpreter, line, pos);
+        let pairs = matches.into_iter().map(|m| Pair { display: m.clone(), replacement: m }).collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for Repl {
+    type Hint = String;
+}
+
+impl Highlighter for Repl {
+    fn highlight_prompt<'b, 's: 'b, 'p: 'b>(&'s self, prompt: &'p str, _default: bool) -> Cow<'b, str> {
+        if self.color {
+            Cow::Owned(format!("{}{}{}", CYAN, prompt, RESET))
+        } else {
+            Cow::Borrowed(prompt)
+        }
+    }
+}
+
+impl Validator for Repl {}
+
+impl Helper for Repl {}
+
+fn history_path() -> Option<PathBuf> {
+    let home = std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")).ok()?;
+    Some(PathBuf::from(home).join(".poly_history"))
+}
+
+/// Run the REPL on the terminal until `:quit` or end of input
+pub fn run(version: &str) -> Result<(), String> {
+    let color = atty::is(atty::Stream::Stdout);
+    let mut editor: Editor<Repl, DefaultHistory> = Editor::new().map_err(|e| e.to_string())?;
+    editor.set_helper(Some(Repl::new(color)));
+    let history = history_path();
+    if let Some(path) = &history {
+        let _ = editor.load_history(path);
+    }
+
+    println!();
+    println!("  {}POLY{} v{}", CYAN, RESET, version);
+    println!("  {}Type :help for commands, :quit to exit{}", DIM, RESET);
+    println!();
+
+    let mut buffer = String::new();
+    loop {
+        let prompt = if buffer.is_empty() { "> " } else { "... " };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                buffer.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.to_string()),
+        };
+        if !line.trim().is_empty() {
+            let _ = editor.add_history_entry(line.as_str());
+            if let Some(path) = &history {
+                let _ = editor.append_history(path);
+            }
+        }
+        let Some(repl) = editor.helper_mut() else { break };
+
+        if buffer.is_empty() {
+            let trimmed = line.trim();
+            match trimmed {
+                "" => continue,
+                "exit" | "quit" | ":quit" | ":q" => break,
+                _ if trimmed.starts_with(':') => {
+                    match repl.command(trimmed) {
+                        Ok(text) => println!("{}", text),
+                        Err(e) => eprintln!("{}error{}: {}", RED, RESET, e),
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        buffer.push_str(&line);
+        buffer.push('\n');
+        match classify(&buffer) {
+            Input::Incomplete => continue,
+            Input::Invalid(e) => eprintln!("{}error{}: {}", RED, RESET, e),
+            Input::Complete(program) => match repl.execute(&program) {
+                Ok(Some(text)) => println!("{}", text),
+                Ok(None) => {}
+                Err(e) => eprintln!("{}error{}: {}", RED, RESET, e),
+            },
+        }
+        buffer.clear();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_multiline_input() {
+        assert!(matches!(classify("let x = 1\n"), Input::Complete(_)));
+        assert!(matches!(classify("fn double(x):\n"), Input::Incomplete));
+        assert!(matches!(classify("fn double(x):\n    return x * 2\n"), Input::Incomplete));
+        assert!(matches!(classify("fn double(x):\n    return x * 2\n\n"), Input::Complete(_)));
+        assert!(matches!(classify("let items = [1,\n"), Input::Incomplete));
+        assert!(matches!(classify("let = 5\n"), Input::Invalid(_)));
+    }
+
+    #[test]
+    fn test_session_completion_and_pretty() {
+        let mut repl = Repl::new(false);
+        let program = |src: &str| match classify(src) {
+            Input::Complete(program) => program,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(repl.execute(&program("class Point:\n    fn __init__(self, x):\n        self.x = x\n    fn norm(self):\n        return self.x\n\n")).unwrap(), None);
+        assert_eq!(repl.execute(&program("let point = Point(3)\nimport datetime\nlet config = {\"name\": \"demo\"}\n")).unwrap(), None);
+        assert_eq!(repl.execute(&program("point\n")).unwrap().as_deref(), Some("Point(x=3)"));
+        assert_eq!(repl.command(":type config").unwrap(), "dict");
+        assert!(repl.command(":load /nonexistent.poly").is_err());
+
+        assert_eq!(completions(&repl.interpreter, "print(poi", 9), (6, vec!["point".to_string()]));
+        assert_eq!(completions(&repl.interpreter, "point.", 6), (6, vec!["norm".to_string(), "x".to_string()]));
+        assert_eq!(completions(&repl.interpreter, "config.na", 9), (7, vec!["name".to_string()]));
+        assert!(completions(&repl.interpreter, "datetime.no", 11).1.contains(&"now".to_string()));
+        assert_eq!(completions(&repl.interpreter, ":ty", 3), (0, vec![":type".to_string()]));
+
+        let long = Value::List((0..30).map(Value::Int).collect());
+        assert!(pretty(&long, false).starts_with("[\n  0,\n  1,\n"));
+        let dict = Value::Dict(vec![(Value::String("a".to_string()), Value::List(vec![Value::Bool(true), Value::None]))]);
+        assert_eq!(pretty(&dict, false), "{\"a\": [true, none]}");
+    }
+}
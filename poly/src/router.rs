@@ -0,0 +1,394 @@
+//! Command router for IPC calls handled in Rust
+//!
+//! The `__poly_*` system APIs, and any commands an embedding application
+//! adds, are registered once with a name, an argument schema, the
+//! `Permission` they need and a handler. The dev server, the native window's
+//! local server and `window.ipc` all dispatch through the global router, so a
+//! command behaves the same on every transport. Middleware wraps each call;
+//! `Permissions` is installed by default, `Logging` and `Timing` on request.
+//!
+//! ```ignore
+//! poly::router::register(
+//!     Command::new("greet", |call| Ok(json!(format!("Hello, {}", call.str("name").unwrap_or("you")))))
+//!         .arg("name", ArgKind::String),
+//! );
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use serde_json::Value;
+
+use crate::ipc::Error;
+use crate::sovereignty::Permission;
+
+/// One call of a command; `args` is always an object
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub command: String,
+    pub args: Value,
+}
+
+impl Call {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.args.get(key)
+    }
+
+    pub fn str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(|v| v.as_str())
+    }
+
+    pub fn u64(&self, key: &str) -> Option<u64> {
+        self.get(key).and_then(|v| v.as_u64())
+    }
+
+    pub fn i64(&self, key: &str) -> Option<i64> {
+        self.get(key).and_then(|v| v.as_i64())
+    }
+
+    pub fn f64(&self, key: &str) -> Option<f64> {
+        self.get(key).and_then(|v| v.as_f64())
+    }
+
+    pub fn bool(&self, key: &str) -> Option<bool> {
+        self.get(key).and_then(|v| v.as_bool())
+    }
+}
+
+/// JSON type an argument must have when it is given
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    Any,
+    String,
+    Number,
+    Bool,
+    Array,
+    Object,
+}
+
+impl ArgKind {
+    fn accepts(self, value: &Value) -> bool {
+        match self {
+            ArgKind::Any => true,
+            ArgKind::String => value.is_string(),
+            ArgKind::Number => value.is_number(),
+            ArgKind::Bool => value.is_boolean(),
+            ArgKind::Array => value.is_array(),
+            ArgKind::Object => value.is_object(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arg {
+    pub name: String,
+    pub kind: ArgKind,
+    pub required: bool,
+}
+
+pub type Handler = dyn Fn(&Call) -> Result<Value, Error> + Send + Sync;
+type PermissionFor = dyn Fn(&Value) -> Option<Permission> + Send + Sync;
+
+/// A registered command
+pub struct Command {
+    name: String,
+    args: Vec<Arg>,
+    permission: Option<Arc<PermissionFor>>,
+    handler: Arc<Handler>,
+}
+
+impl Command {
+    pub fn new(name: impl Into<String>, handler: impl Fn(&Call) -> Result<Value, Error> + Send + Sync + 'static) -> Self {
+        Command { name: name.into(), args: Vec::new(), permission: None, handler: Arc::new(handler) }
+    }
+
+    /// A required argument
+    pub fn arg(mut self, name: &str, kind: ArgKind) -> Self {
+        self.args.push(Arg { name: name.to_string(), kind, required: true });
+        self
+    }
+
+    /// An argument that may be missing or `null`
+    pub fn optional(mut self, name: &str, kind: ArgKind) -> Self {
+        self.args.push(Arg { name: name.to_string(), kind, required: false });
+        self
+    }
+
+    /// Permission every call needs
+    pub fn permission(self, permission: Permission) -> Self {
+        self.permission_for(move |_| Some(permission.clone()))
+    }
+
+    /// Permission that depends on the arguments (a path or URL scope)
+    pub fn permission_for(mut self, permission: impl Fn(&Value) -> Option<Permission> + Send + Sync + 'static) -> Self {
+        self.permission = Some(Arc::new(permission));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn args(&self) -> &[Arg] {
+        &self.args
+    }
+
+    /// Permission a call with `args` needs
+    pub fn required_permission(&self, args: &Value) -> Option<Permission> {
+        self.permission.as_ref().and_then(|permission| permission(args))
+    }
+
+    /// Check `args` against the schema; keys it does not name are allowed
+    pub fn validate(&self, args: &Value) -> Result<(), Error> {
+        for arg in &self.args {
+            match args.get(&arg.name) {
+                None | Some(Value::Null) if arg.required => {
+                    return Err(Error::new("INVALID_ARGUMENTS", format!("{}() missing argument '{}'", self.name, arg.name)));
+                }
+                Some(value) if !value.is_null() && !arg.kind.accepts(value) => {
+                    return Err(Error::new("INVALID_ARGUMENTS", format!("{}() argument '{}' must be {}", self.name, arg.name, format!("{:?}", arg.kind).to_lowercase())));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rest of the chain, ending in the command's handler
+pub type Next<'a> = &'a dyn Fn(&Call) -> Result<Value, Error>;
+
+/// Code run around every call
+pub trait Middleware: Send + Sync {
+    fn handle(&self, command: &Command, call: &Call, next: Next) -> Result<Value, Error>;
+}
+
+/// Shared middleware, e.g. `Timing` whose report is read later
+impl<M: Middleware> Middleware for Arc<M> {
+    fn handle(&self, command: &Command, call: &Call, next: Next) -> Result<Value, Error> {
+        self.as_ref().handle(command, call, next)
+    }
+}
+
+/// Refuses calls whose permission is not granted in `[sovereignty]`
+pub struct Permissions {
+    check: fn(&Permission) -> Result<(), String>,
+}
+
+impl Permissions {
+    /// Use `check` instead of the global sovereignty configuration
+    pub fn with(check: fn(&Permission) -> Result<(), String>) -> Self {
+        Permissions { check }
+    }
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Permissions { check: crate::sovereignty::check_permission }
+    }
+}
+
+impl Middleware for Permissions {
+    fn handle(&self, command: &Command, call: &Call, next: Next) -> Result<Value, Error> {
+        if let Some(permission) = command.required_permission(&call.args) {
+            (self.check)(&permission).map_err(|e| Error::new("PERMISSION_DENIED", e))?;
+        }
+        next(call)
+    }
+}
+
+/// Prints each call with its outcome and duration to stderr
+pub struct Logging;
+
+impl Middleware for Logging {
+    fn handle(&self, _command: &Command, call: &Call, next: Next) -> Result<Value, Error> {
+        let start = Instant::now();
+        let result = next(call);
+        let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+        match &result {
+            Ok(_) => eprintln!("[ipc] {} ok ({:.1}ms)", call.command, elapsed),
+            Err(e) => eprintln!("[ipc] {} {} ({:.1}ms): {}", call.command, e.code, elapsed, e.message),
+        }
+        result
+    }
+}
+
+/// Call counts and total time per command
+#[derive(Default)]
+pub struct Timing {
+    stats: Mutex<HashMap<String, (u64, Duration)>>,
+}
+
+impl Timing {
+    /// `(command, calls, total time)`, slowest first
+    pub fn report(&self) -> Vec<(String, u64, Duration)> {
+        let mut report: Vec<_> = self.stats.lock().unwrap().iter().map(|(name, (calls, total))| (name.clone(), *calls, *total)).collect();
+        report.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        report
+    }
+}
+
+impl Middleware for Timing {
+    fn handle(&self, _command: &Command, call: &Call, next: Next) -> Result<Value, Error> {
+        let start = Instant::now();
+        let result = next(call);
+        let mut stats = self.stats.lock().unwrap();
+        let entry = stats.entry(call.command.clone()).or_default();
+        entry.0 += 1;
+        entry.1 += start.elapsed();
+        result
+    }
+}
+
+/// Commands and the middleware around them
+pub struct Router {
+    commands: HashMap<String, Arc<Command>>,
+    middleware: Vec<Arc<dyn Middleware>>,
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Router { commands: HashMap::new(), middleware: vec![Arc::new(Permissions::default())] }
+    }
+}
+
+impl Router {
+    /// A router with no middleware
+    pub fn empty() -> Self {
+        Router { commands: HashMap::new(), middleware: Vec::new() }
+    }
+
+    /// Add a command, replacing one with the same name
+    pub fn register(&mut self, command: Command) {
+        self.commands.insert(command.name.clone(), Arc::new(command));
+    }
+
+    /// Add middleware; the first added runs outermost
+    pub fn layer(&mut self, middleware: impl Middleware + 'static) {
+        self.middleware.push(Arc::new(middleware));
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.commands.contains_key(name)
+    }
+
+    pub fn command(&self, name: &str) -> Option<&Command> {
+        self.commands.get(name).map(|command| command.as_ref())
+    }
+
+    /// Registered command names, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.commands.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn dispatch(&self, name: &str, args: Value) -> Result<Value, Error> {
+        let command = self.commands.get(name).cloned()
+            .ok_or_else(|| Error::new("NOT_FOUND", format!("Unknown command: {}", name)))?;
+        run(&command, &self.middleware, name, args)
+    }
+}
+
+fn run(command: &Command, middleware: &[Arc<dyn Middleware>], name: &str, args: Value) -> Result<Value, Error> {
+    let args = checked_args(command, name, args)?;
+    chain(command, middleware, &Call { command: name.to_string(), args })
+}
+
+/// `args` as the handler gets them, once they pass the schema
+fn checked_args(command: &Command, name: &str, args: Value) -> Result<Value, Error> {
+    let args = match args {
+        Value::Null => Value::Object(Default::default()),
+        Value::Object(_) => args,
+        _ => return Err(Error::new("INVALID_ARGUMENTS", format!("{}() takes its arguments as an object", name))),
+    };
+    command.validate(&args)?;
+    Ok(args)
+}
+
+fn chain(command: &Command, middleware: &[Arc<dyn Middleware>], call: &Call) -> Result<Value, Error> {
+    match middleware.split_first() {
+        Some((first, rest)) => first.handle(command, call, &|call| chain(command, rest, call)),
+        None => (command.handler)(call),
+    }
+}
+
+static ROUTER: Lazy<RwLock<Router>> = Lazy::new(|| RwLock::new(Router::default()));
+
+/// Register a command with the global router used by every transport
+pub fn register(command: Command) {
+    ROUTER.write().unwrap().register(command);
+}
+
+/// Add middleware to the global router
+pub fn layer(middleware: impl Middleware + 'static) {
+    ROUTER.write().unwrap().layer(middleware);
+}
+
+pub fn contains(name: &str) -> bool {
+    ROUTER.read().unwrap().contains(name)
+}
+
+/// Argument schema of a registered command
+pub fn args(name: &str) -> Option<Vec<Arg>> {
+    ROUTER.read().unwrap().command(name).map(|command| command.args().to_vec())
+}
+
+/// Permission a call of a registered command with `args` needs
+pub fn required_permission(name: &str, args: &Value) -> Option<Permission> {
+    ROUTER.read().unwrap().command(name).and_then(|command| command.required_permission(args))
+}
+
+/// Check a call of a registered command against its schema without running
+/// it; returns the arguments its handler would get. Unknown names pass.
+pub fn validate(name: &str, args: Value) -> Result<Value, Error> {
+    match ROUTER.read().unwrap().command(name) {
+        Some(command) => checked_args(command, name, args),
+        None => Ok(args),
+    }
+}
+
+/// Run a command on the global router. The lock is released before the
+/// handler runs, so handlers may dispatch or register commands themselves.
+pub fn dispatch(name: &str, args: Value) -> Result<Value, Error> {
+    let (command, middleware) = {
+        let router = ROUTER.read().unwrap();
+        let command = router.commands.get(name).cloned()
+            .ok_or_else(|| Error::new("NOT_FOUND", format!("Unknown command: {}", name)))?;
+        (command, router.middleware.clone())
+    };
+    run(&command, &middleware, name, args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_schema_middleware_and_permissions() {
+        let mut router = Router::empty();
+        router.layer(Permissions::with(|permission| match permission {
+            Permission::Database => Err("database access was not declared".to_string()),
+            _ => Ok(()),
+        }));
+        let timing = Arc::new(Timing::default());
+        router.layer(Arc::clone(&timing));
+        router.register(Command::new("greet", |call| Ok(json!(format!("Hello, {}", call.str("name").unwrap()))))
+            .arg("name", ArgKind::String)
+            .optional("loud", ArgKind::Bool));
+        router.register(Command::new("query", |_| Ok(json!([]))).permission(Permission::Database));
+
+        assert_eq!(router.dispatch("greet", json!({"name": "Ada"})).unwrap(), json!("Hello, Ada"));
+        let error = |result: Result<Value, Error>| result.unwrap_err().code;
+        assert_eq!(error(router.dispatch("greet", json!({}))), "INVALID_ARGUMENTS");
+        assert_eq!(error(router.dispatch("greet", json!({"name": 1}))), "INVALID_ARGUMENTS");
+        assert_eq!(error(router.dispatch("greet", json!(["Ada"]))), "INVALID_ARGUMENTS");
+        assert_eq!(error(router.dispatch("query", Value::Null)), "PERMISSION_DENIED");
+        assert_eq!(error(router.dispatch("missing", Value::Null)), "NOT_FOUND");
+        // Refused calls never reach the handler or the middleware after the check
+        assert_eq!(timing.report().iter().map(|(name, calls, _)| (name.as_str(), *calls)).collect::<Vec<_>>(), vec![("greet", 1)]);
+    }
+}
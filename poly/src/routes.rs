@@ -0,0 +1,415 @@
+//! HTTP routes declared in Poly
+//!
+//! ```poly
+//! @get("/api/items/<int:id>")
+//! fn item(id, verbose = "no"):
+//!     return {"id": id, "verbose": verbose}
+//!
+//! @post("/api/items")
+//! fn create(body):
+//!     return response({"created": body}, status=201)
+//! ```
+//!
+//! `poly dev`, `poly run` and the native window's local server answer
+//! matching requests before serving static files. A handler's parameters are
+//! bound by name: path parameters (`<name>`, `<int:name>`, `<path:name>`),
+//! then `request`, `body` (the parsed JSON body, or the text), `query` and
+//! `headers`, then query string values. It returns the response body: `none`
+//! is 204, a string is text and anything else JSON, unless it is wrapped in
+//! `response(body, status, headers)`. `@middleware` functions run first
+//! with the request and answer it themselves by returning a response.
+//!
+//! Routes are kept for the process, so every IPC worker that runs the entry
+//! module registers the same table, and a reload replaces routes by method
+//! and path. Each route remembers the file that declared it, and a file's
+//! routes and middleware are dropped before hot reload runs it again.
+
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+use serde_json::json;
+
+use crate::ast::Value;
+use crate::interpreter::{CallError, Interpreter};
+
+/// Key marking a dict built by `response()`
+pub const RESPONSE_KEY: &str = "$response";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Str(String),
+    Int(String),
+    /// The rest of the path, slashes included
+    Path(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Route {
+    method: String,
+    pattern: String,
+    segments: Vec<Segment>,
+    handler: String,
+    /// Module that declared it; `None` for the entry
+    file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Middleware {
+    prefix: String,
+    handler: String,
+    file: Option<PathBuf>,
+}
+
+impl Middleware {
+    /// Whether `path` is the prefix or under it, so `/api` covers `/api/x`
+    /// but not `/apix`
+    fn covers(&self, path: &str) -> bool {
+        let prefix = self.prefix.trim_end_matches('/');
+        path.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
+
+static ROUTES: Lazy<RwLock<Vec<Route>>> = Lazy::new(|| RwLock::new(Vec::new()));
+static MIDDLEWARE: Lazy<RwLock<Vec<Middleware>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// The route table, to put back with [`restore`]
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    routes: Vec<Route>,
+    middleware: Vec<Middleware>,
+}
+
+fn compile(pattern: &str) -> Result<Vec<Segment>, String> {
+    if !pattern.starts_with('/') {
+        return Err(format!("route '{}' must start with '/'", pattern));
+    }
+    let parts: Vec<&str> = pattern.trim_matches('/').split('/').filter(|p| !p.is_empty()).collect();
+    parts.iter().enumerate().map(|(i, part)| {
+        let Some(inner) = part.strip_prefix('<').and_then(|p| p.strip_suffix('>')) else {
+            return Ok(Segment::Literal(part.to_string()));
+        };
+        let (kind, name) = inner.split_once(':').unwrap_or(("str", inner));
+        match kind {
+            _ if name.is_empty() => Err(format!("route '{}' has an unnamed parameter", pattern)),
+            "str" => Ok(Segment::Str(name.to_string())),
+            "int" => Ok(Segment::Int(name.to_string())),
+            "path" if i + 1 == parts.len() => Ok(Segment::Path(name.to_string())),
+            "path" => Err(format!("route '{}': <path:{}> must come last", pattern, name)),
+            _ => Err(format!("route '{}': unknown parameter type '{}'", pattern, kind)),
+        }
+    }).collect()
+}
+
+/// Path parameters if `path` matches `segments`
+fn match_path(segments: &[Segment], path: &str) -> Option<Vec<(String, Value)>> {
+    let parts: Vec<&str> = path.trim_matches('/').split('/').filter(|p| !p.is_empty()).collect();
+    let mut params = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let decode = |part: &str| urlencoding::decode(part).map(|p| p.into_owned()).unwrap_or_else(|_| part.to_string());
+        match segment {
+            Segment::Path(name) if i < parts.len() => {
+                params.push((name.clone(), Value::String(decode(&parts[i..].join("/")))));
+                return Some(params);
+            }
+            _ if i >= parts.len() => return None,
+            Segment::Literal(literal) if literal == parts[i] => {}
+            Segment::Literal(_) | Segment::Path(_) => return None,
+            Segment::Str(name) => params.push((name.clone(), Value::String(decode(parts[i])))),
+            Segment::Int(name) => params.push((name.clone(), Value::Int(parts[i].parse().ok()?))),
+        }
+    }
+    (segments.len() == parts.len()).then_some(params)
+}
+
+/// Add a route declared by `file` (`None` for the entry), replacing one with
+/// the same method and pattern
+pub fn add(method: &str, pattern: &str, handler: &str, file: Option<&Path>) -> Result<(), String> {
+    let route = Route {
+        method: method.to_ascii_uppercase(),
+        pattern: pattern.to_string(),
+        segments: compile(pattern)?,
+        handler: handler.to_string(),
+        file: file.map(Path::to_path_buf),
+    };
+    let mut routes = ROUTES.write().unwrap();
+    match routes.iter_mut().find(|r| r.method == route.method && r.pattern == route.pattern) {
+        Some(existing) => *existing = route,
+        None => routes.push(route),
+    }
+    Ok(())
+}
+
+/// Run `handler(request)` before the routes under `prefix`
+pub fn add_middleware(prefix: &str, handler: &str, file: Option<&Path>) {
+    let mut middleware = MIDDLEWARE.write().unwrap();
+    if !middleware.iter().any(|m| m.prefix == prefix && m.handler == handler) {
+        middleware.push(Middleware { prefix: prefix.to_string(), handler: handler.to_string(), file: file.map(Path::to_path_buf) });
+    }
+}
+
+/// Drop the routes and middleware declared by `file` (`None` for the entry)
+pub fn clear_file(file: Option<&Path>) {
+    ROUTES.write().unwrap().retain(|r| r.file.as_deref() != file);
+    MIDDLEWARE.write().unwrap().retain(|m| m.file.as_deref() != file);
+}
+
+/// Drop every route and middleware
+pub fn clear() {
+    restore(Snapshot::default());
+}
+
+pub fn snapshot() -> Snapshot {
+    Snapshot { routes: ROUTES.read().unwrap().clone(), middleware: MIDDLEWARE.read().unwrap().clone() }
+}
+
+pub fn restore(snapshot: Snapshot) {
+    *ROUTES.write().unwrap() = snapshot.routes;
+    *MIDDLEWARE.write().unwrap() = snapshot.middleware;
+}
+
+/// `(method, pattern, handler)` of every route, in the order added
+pub fn list() -> Vec<(String, String, String)> {
+    ROUTES.read().unwrap().iter().map(|r| (r.method.clone(), r.pattern.clone(), r.handler.clone())).collect()
+}
+
+/// Whether some route matches `path` (with any method)
+pub fn matches(path: &str) -> bool {
+    !path.starts_with("/__poly") && ROUTES.read().unwrap().iter().any(|r| match_path(&r.segments, path).is_some())
+}
+
+/// An HTTP request for a route
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    /// Names in lower case
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    /// Read method, URL, headers and body of a server request
+    pub fn read(request: &mut tiny_http::Request) -> Self {
+        let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+        let decode = |s: &str| urlencoding::decode(&s.replace('+', " ")).map(|s| s.into_owned()).unwrap_or_default();
+        let query = query.split('&').filter(|p| !p.is_empty())
+            .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+            .map(|(key, value)| (decode(key), decode(value)))
+            .collect();
+        let path = path.to_string();
+        let headers = request.headers().iter()
+            .map(|h| (h.field.as_str().as_str().to_ascii_lowercase(), h.value.as_str().to_string()))
+            .collect();
+        let mut body = String::new();
+        let _ = request.as_reader().read_to_string(&mut body);
+        Request { method: request.method().as_str().to_ascii_uppercase(), path, query, headers, body }
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+}
+
+/// What a route answered
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Response {
+    fn error(status: u16, code: &str, message: &str) -> Self {
+        Response { status, headers: vec![("Content-Type".into(), "application/json".into())], body: json!({"error": message, "code": code}).to_string() }
+    }
+
+    pub fn to_tiny(&self) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+        let mut response = tiny_http::Response::from_string(self.body.clone()).with_status_code(self.status);
+        for (name, value) in &self.headers {
+            if let Ok(header) = tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+                response.add_header(header);
+            }
+        }
+        response
+    }
+}
+
+fn dict(pairs: impl IntoIterator<Item = (String, Value)>) -> Value {
+    Value::Dict(pairs.into_iter().map(|(key, value)| (Value::String(key), value)).collect())
+}
+
+/// Turn a handler's return value into a response
+fn respond(value: Value) -> Response {
+    let mut status = 200;
+    let mut headers = Vec::new();
+    let mut body = value;
+    if let Value::Dict(pairs) = &body {
+        if pairs.iter().any(|(key, _)| matches!(key, Value::String(k) if k == RESPONSE_KEY)) {
+            let field = |name: &str| pairs.iter().find(|(key, _)| matches!(key, Value::String(k) if k == name)).map(|(_, v)| v.clone());
+            if let Some(Value::Int(code)) = field("status") {
+                status = code.clamp(100, 599) as u16;
+            }
+            if let Some(Value::Dict(items)) = field("headers") {
+                headers = items.iter().map(|(k, v)| (k.to_string(), match v { Value::String(s) => s.clone(), other => other.to_string() })).collect();
+            }
+            body = field("body").unwrap_or(Value::None);
+        }
+    }
+    let has_type = headers.iter().any(|(name, _): &(String, String)| name.eq_ignore_ascii_case("content-type"));
+    let (body, content_type) = match body {
+        Value::None if status == 200 => {
+            status = 204;
+            (String::new(), None)
+        }
+        Value::None => (String::new(), None),
+        Value::String(text) => (text, Some("text/plain; charset=utf-8")),
+        other => (other.to_serde_json().to_string(), Some("application/json")),
+    };
+    if let (Some(content_type), false) = (content_type, has_type) {
+        headers.push(("Content-Type".to_string(), content_type.to_string()));
+    }
+    Response { status, headers, body }
+}
+
+/// Answer a server request for a route on one of `pool`'s workers
+pub fn serve(pool: &crate::workers::Pool, mut request: tiny_http::Request) {
+    let route_request = Request::read(&mut request);
+    let label = format!("{} {}", route_request.method, route_request.path);
+    let response = pool.run(&label, crate::workers::Priority::Normal, move |interp| handle(interp, &route_request))
+        .unwrap_or_else(|e| Response::error(if e.code == "BUSY" { 503 } else { 500 }, e.code, &e.message));
+    let _ = request.respond(response.to_tiny());
+}
+
+/// Answer `request` with the matching route of `interp`
+pub fn handle(interp: &mut Interpreter, request: &Request) -> Response {
+    let routes = ROUTES.read().unwrap().clone();
+    let matched: Vec<(&Route, Vec<(String, Value)>)> = routes.iter()
+        .filter_map(|route| match_path(&route.segments, &request.path).map(|params| (route, params)))
+        .collect();
+    let Some((route, params)) = matched.iter().find(|(route, _)| route.method == request.method || (route.method == "GET" && request.method == "HEAD")) else {
+        if matched.is_empty() {
+            return Response::error(404, "NOT_FOUND", &format!("No route for {}", request.path));
+        }
+        let mut response = Response::error(405, "METHOD_NOT_ALLOWED", &format!("{} is not allowed for {}", request.method, request.path));
+        let allowed: Vec<&str> = matched.iter().map(|(route, _)| route.method.as_str()).collect();
+        response.headers.push(("Allow".to_string(), allowed.join(", ")));
+        return response;
+    };
+
+    let is_json = request.header("content-type").is_some_and(|t| t.starts_with("application/json"));
+    let body = match is_json && !request.body.trim().is_empty() {
+        true => match serde_json::from_str::<serde_json::Value>(&request.body) {
+            Ok(json) => Value::from_serde_json(&json),
+            Err(e) => return Response::error(400, "INVALID_REQUEST", &format!("Invalid JSON body: {}", e)),
+        },
+        false => Value::String(request.body.clone()),
+    };
+    let query = dict(request.query.iter().map(|(k, v)| (k.clone(), Value::String(v.clone()))));
+    let headers = dict(request.headers.iter().map(|(k, v)| (k.clone(), Value::String(v.clone()))));
+    let request_value = dict([
+        ("method".to_string(), Value::String(request.method.clone())),
+        ("path".to_string(), Value::String(request.path.clone())),
+        ("params".to_string(), dict(params.clone())),
+        ("query".to_string(), query.clone()),
+        ("headers".to_string(), headers.clone()),
+        ("body".to_string(), body.clone()),
+    ]);
+
+    let middleware = MIDDLEWARE.read().unwrap().clone();
+    for m in middleware.iter().filter(|m| m.covers(&request.path)) {
+        match call(interp, &m.handler, vec![request_value.clone()], Vec::new()) {
+            Ok(Value::None) => {}
+            Ok(value) => return respond(value),
+            Err(response) => return response,
+        }
+    }
+
+    let Some(names) = interp.function_params(&route.handler) else {
+        return Response::error(500, "INTERNAL_ERROR", &format!("Route handler '{}' is not defined", route.handler));
+    };
+    let kwargs = names.iter().filter_map(|name| {
+        let value = params.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone())
+            .or_else(|| match name.as_str() {
+                "request" => Some(request_value.clone()),
+                "body" => Some(body.clone()),
+                "query" => Some(query.clone()),
+                "headers" => Some(headers.clone()),
+                _ => None,
+            })
+            .or_else(|| request.query.iter().rev().find(|(key, _)| key == name).map(|(_, value)| Value::String(value.clone())));
+        value.map(|value| (name.clone(), value))
+    }).collect();
+    match call(interp, &route.handler, Vec::new(), kwargs) {
+        Ok(value) => {
+            let mut response = respond(value);
+            if request.method == "HEAD" {
+                response.body.clear();
+            }
+            response
+        }
+        Err(response) => response,
+    }
+}
+
+fn call(interp: &mut Interpreter, handler: &str, args: Vec<Value>, kwargs: Vec<(String, Value)>) -> Result<Value, Response> {
+    interp.invoke(handler, args, kwargs).map_err(|error| match error {
+        CallError::NotFound(message) => Response::error(500, "INTERNAL_ERROR", &message),
+        CallError::InvalidArguments(message) => Response::error(400, "INVALID_ARGUMENTS", &message),
+        CallError::Runtime { message, traceback } => {
+            let mut response = Response::error(500, "RUNTIME_ERROR", &message);
+            response.body = crate::ipc::Error { code: "RUNTIME_ERROR", message, traceback }.to_json().to_string();
+            response
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_decorated_routes() {
+        let source = "@get(\"/test-api/items/<int:id>\")\nfn item(id, verbose = \"no\"):\n    return {\"id\": id, \"verbose\": verbose}\n\n@post(\"/test-api/items\")\nfn create(body):\n    return response({\"created\": body[\"name\"]}, status=201, headers={\"X-Id\": \"7\"})\n\n@get(\"/test-api/files/<path:rest>\")\nfn file(rest):\n    return rest\n\n@middleware(\"/test-api/private\")\nfn deny(request):\n    return response(\"no\", status=401)\n\n@get(\"/test-api/private\")\nfn secret():\n    return \"secret\"\n\n@get(\"/test-api/privately\")\nfn public():\n    return \"public\"\n";
+        let program = Parser::new(Lexer::new(source).tokenize()).parse().unwrap();
+        let mut interp = Interpreter::new();
+        interp.run(&program).unwrap();
+        assert!(matches("/test-api/items/3") && !matches("/test-api/other"));
+
+        let request = |method: &str, path: &str, query: &[(&str, &str)], body: &str| Request {
+            method: method.into(),
+            path: path.into(),
+            query: query.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            headers: vec![("content-type".into(), "application/json".into())],
+            body: body.into(),
+        };
+        let response = handle(&mut interp, &request("GET", "/test-api/items/42", &[("verbose", "yes")], ""));
+        assert_eq!((response.status, response.body.as_str()), (200, r#"{"id":42,"verbose":"yes"}"#));
+        assert_eq!(handle(&mut interp, &request("GET", "/test-api/items/x", &[], "")).status, 404);
+
+        let response = handle(&mut interp, &request("POST", "/test-api/items", &[], r#"{"name": "pen"}"#));
+        assert_eq!((response.status, response.body.as_str()), (201, r#"{"created":"pen"}"#));
+        assert!(response.headers.contains(&("X-Id".to_string(), "7".to_string())));
+        assert_eq!(handle(&mut interp, &request("POST", "/test-api/items", &[], "{")).status, 400);
+
+        let response = handle(&mut interp, &request("DELETE", "/test-api/items", &[], ""));
+        assert_eq!(response.status, 405);
+        assert!(response.headers.contains(&("Allow".to_string(), "POST".to_string())));
+
+        assert_eq!(handle(&mut interp, &request("GET", "/test-api/files/a/b%20c.txt", &[], "")).body, "a/b c.txt");
+        let response = handle(&mut interp, &request("GET", "/test-api/private", &[], ""));
+        assert_eq!((response.status, response.body.as_str()), (401, "no"));
+        // Middleware covers whole path segments only
+        let response = handle(&mut interp, &request("GET", "/test-api/privately", &[], ""));
+        assert_eq!((response.status, response.body.as_str()), (200, "public"));
+
+        // An interpreter that never defined the handler
+        let response = handle(&mut Interpreter::new(), &request("GET", "/test-api/items/1", &[], ""));
+        assert_eq!(response.status, 500);
+        assert!(response.body.contains("INTERNAL_ERROR"));
+    }
+}
@@ -130,81 +130,34 @@ impl SovereigntyConfig {
             ..Default::default()
         };
         
-        let mut in_sovereignty = false;
-        let mut in_permissions = false;
-        let mut in_http_allowlist = false;
-        let mut in_http_blocklist = false;
-        let mut in_fs_allowlist = false;
-        
-        for line in content.lines() {
-            let line = line.trim();
-            
-            // Section detection
-            if line == "[sovereignty]" {
-                in_sovereignty = true;
-                in_permissions = false;
-                in_http_allowlist = false;
-                in_http_blocklist = false;
-                in_fs_allowlist = false;
-                continue;
-            } else if line.starts_with('[') && line != "[sovereignty]" {
-                in_sovereignty = false;
-                in_permissions = false;
-                in_http_allowlist = false;
-                in_http_blocklist = false;
-                in_fs_allowlist = false;
-                continue;
-            }
-            
-            if !in_sovereignty {
-                continue;
-            }
-            
-            // Parse sovereignty options
-            if let Some(val) = line.strip_prefix("enabled").and_then(|s| s.trim().strip_prefix('=')) {
-                config.enabled = val.trim() == "true";
-            } else if let Some(val) = line.strip_prefix("audit_log").and_then(|s| s.trim().strip_prefix('=')) {
-                config.audit_log = val.trim() == "true";
-            } else if line.starts_with("permissions") && line.contains('[') {
-                in_permissions = true;
-            } else if line.starts_with("http_allowlist") && line.contains('[') {
-                in_http_allowlist = true;
-            } else if line.starts_with("http_blocklist") && line.contains('[') {
-                in_http_blocklist = true;
-            } else if line.starts_with("fs_allowlist") && line.contains('[') {
-                in_fs_allowlist = true;
-            } else if line == "]" {
-                in_permissions = false;
-                in_http_allowlist = false;
-                in_http_blocklist = false;
-                in_fs_allowlist = false;
-            } else if in_permissions {
-                // Parse permission string
-                let perm = line.trim().trim_matches('"').trim_matches(',').trim_matches('"');
-                if !perm.is_empty() {
-                    if let Some(p) = parse_permission(perm) {
-                        config.permissions.insert(p);
-                    }
-                }
-            } else if in_http_allowlist {
-                let domain = line.trim().trim_matches('"').trim_matches(',').trim_matches('"');
-                if !domain.is_empty() {
-                    config.http_allowlist.insert(domain.to_lowercase());
-                }
-            } else if in_http_blocklist {
-                let domain = line.trim().trim_matches('"').trim_matches(',').trim_matches('"');
-                if !domain.is_empty() {
-                    config.http_blocklist.insert(domain.to_lowercase());
-                }
-            } else if in_fs_allowlist {
-                let path = line.trim().trim_matches('"').trim_matches(',').trim_matches('"');
-                if !path.is_empty() {
-                    if let Some(scope) = parse_path_scope(path) {
-                        config.fs_allowlist.insert(scope);
-                    }
-                }
+        // A broken file grants nothing rather than falling back to permissive mode
+        let table = match content.parse::<toml::Table>() {
+            Ok(table) => table,
+            Err(e) => {
+                eprintln!("warning: poly.toml: {}; no permissions are granted", e.message());
+                return config;
             }
+        };
+        let Some(section) = table.get("sovereignty").and_then(|s| s.as_table()) else {
+            config.enabled = false;
+            return config;
+        };
+        let strings = |key: &str| -> Vec<String> {
+            section.get(key).and_then(|v| v.as_array()).into_iter().flatten()
+                .filter_map(|item| item.as_str())
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        };
+        
+        if let Some(enabled) = section.get("enabled") {
+            config.enabled = enabled.as_bool() == Some(true);
         }
+        config.audit_log = section.get("audit_log").and_then(|v| v.as_bool()) == Some(true);
+        config.permissions = strings("permissions").iter().filter_map(|p| parse_permission(p)).collect();
+        config.http_allowlist = strings("http_allowlist").iter().map(|d| d.to_lowercase()).collect();
+        config.http_blocklist = strings("http_blocklist").iter().map(|d| d.to_lowercase()).collect();
+        config.fs_allowlist = strings("fs_allowlist").iter().filter_map(|p| parse_path_scope(p)).collect();
         
         // If no sovereignty section, use permissive defaults for backwards compatibility
         if config.permissions.is_empty() && config.http_allowlist.is_empty() {
@@ -523,6 +476,15 @@ pub fn set_development_mode() {
     *SOVEREIGNTY.write().unwrap() = SovereigntyConfig::development();
 }
 
+/// The configuration in force, e.g. to put back later with `set_config`
+pub fn current_config() -> SovereigntyConfig {
+    SOVEREIGNTY.read().unwrap().clone()
+}
+
+pub fn set_config(config: SovereigntyConfig) {
+    *SOVEREIGNTY.write().unwrap() = config;
+}
+
 /// Check if sovereignty is enabled
 pub fn is_enabled() -> bool {
     SOVEREIGNTY.read().unwrap().enabled
@@ -594,7 +556,7 @@ pub mod checks {
     }
     
     /// Convert a file path to a PathScope
-    fn path_to_scope(path: &str) -> PathScope {
+    pub fn path_to_scope(path: &str) -> PathScope {
         let path_lower = path.to_lowercase();
         
         // Check for special directories
@@ -650,3 +612,23 @@ pub mod checks {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_toml() {
+        let config = SovereigntyConfig::from_toml(
+            "[package]\nname = \"app\"\n\n[sovereignty]\npermissions = [\"db\", \"clipboard:write\"]  # inline\nhttp_allowlist = [\n  \"API.example.com\",\n]\naudit_log = true\n",
+            "app",
+        );
+        assert!(config.enabled && config.audit_log);
+        assert_eq!(config.permissions, HashSet::from([Permission::Database, Permission::ClipboardWrite]));
+        assert_eq!(config.http_allowlist, HashSet::from(["api.example.com".to_string()]));
+
+        assert!(!SovereigntyConfig::from_toml("[package]\nname = \"app\"\n", "app").enabled);
+        let broken = SovereigntyConfig::from_toml("[sovereignty\npermissions = [\"db\"]\n", "app");
+        assert!(broken.enabled && broken.permissions.is_empty());
+    }
+}
@@ -0,0 +1,489 @@
+//! Embedded SQLite for Poly (`import sqlite`)
+//!
+//! ```text
+//! import sqlite
+//! let db = sqlite.connect("app.db")
+//! db.migrate()
+//! with db.transaction():
+//!     db.execute("INSERT INTO items (name) VALUES (?)", ["apple"])
+//! for row in db.query("SELECT * FROM items WHERE name = :name", {"name": "apple"}):
+//!     print(row["id"], row["name"])
+//! ```
+//!
+//! Connections live in a process-wide registry that the JS bridge
+//! (`__poly_db_*`) uses too, so both sides go through the same
+//! `Permission::Database` check in [`open`].
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use once_cell::sync::Lazy;
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{CachedStatement, Connection};
+
+use crate::ast::Value;
+use crate::interpreter::split_kwargs;
+use crate::sovereignty::checks;
+
+const CONNECTION_CLASS: &str = "sqlite.Connection";
+const TRANSACTION_CLASS: &str = "sqlite.Transaction";
+const DEFAULT_STATEMENT_CACHE: usize = 64;
+const MIGRATIONS_TABLE: &str = "_poly_migrations";
+
+/// Each connection has its own lock, so a slow query only blocks its own connection
+static CONNECTIONS: Lazy<Mutex<HashMap<u64, Arc<Mutex<Connection>>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+/// Open transactions: id -> whether it is a savepoint nested in an outer transaction
+static TRANSACTIONS: Lazy<Mutex<HashMap<u64, bool>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Build the `sqlite` module value
+pub fn module() -> Value {
+    let pairs = ["connect", "open"]
+        .iter()
+        .map(|name| (Value::String(name.to_string()), Value::NativeFunction("sqlite.connect".to_string())))
+        .collect();
+    crate::interpreter::module_value("sqlite", pairs)
+}
+
+// ============================================================================
+// Shared connection registry (Poly and JS bridge)
+// ============================================================================
+
+/// Open a connection after the database permission check; file databases also
+/// need filesystem write access. Returns the registry id.
+pub fn open(path: &str) -> Result<u64, String> {
+    checks::database()?;
+    if path != ":memory:" {
+        checks::fs_write(path)?;
+    }
+    let conn = Connection::open(path).map_err(|e| format!("Failed to open database: {}", e))?;
+    conn.set_prepared_statement_cache_capacity(DEFAULT_STATEMENT_CACHE);
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    CONNECTIONS.lock().unwrap().insert(id, Arc::new(Mutex::new(conn)));
+    Ok(id)
+}
+
+/// Close a connection; returns false if it was not open
+pub fn close(id: u64) -> bool {
+    CONNECTIONS.lock().unwrap().remove(&id).is_some()
+}
+
+/// Run `f` against an open connection
+pub fn with_connection<T>(id: u64, f: impl FnOnce(&Connection) -> Result<T, String>) -> Result<T, String> {
+    checks::database()?;
+    let conn = CONNECTIONS.lock().unwrap().get(&id).cloned().ok_or("Database connection not found")?;
+    let conn = conn.lock().unwrap_or_else(|e| e.into_inner());
+    f(&conn)
+}
+
+// ============================================================================
+// Value conversion
+// ============================================================================
+
+fn to_sql_value(value: &Value) -> SqlValue {
+    match value {
+        Value::None => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(*b as i64),
+        Value::Int(n) => SqlValue::Integer(*n),
+        Value::Float(f) => SqlValue::Real(*f),
+        Value::String(s) => SqlValue::Text(s.clone()),
+        v @ Value::Instance { .. } if crate::datetime::is_temporal(v) => SqlValue::Text(v.to_string()),
+        other => SqlValue::Text(other.to_serde_json().to_string()),
+    }
+}
+
+fn from_sql_value(value: ValueRef<'_>) -> Value {
+    match value {
+        ValueRef::Null => Value::None,
+        ValueRef::Integer(n) => Value::Int(n),
+        ValueRef::Real(f) => Value::Float(f),
+        ValueRef::Text(t) => Value::String(String::from_utf8_lossy(t).to_string()),
+        ValueRef::Blob(b) => Value::List(b.iter().map(|byte| Value::Int(*byte as i64)).collect()),
+    }
+}
+
+/// Statement parameters: a list binds positionally (`?`), a dict by name (`:name`)
+enum Params {
+    Positional(Vec<SqlValue>),
+    Named(Vec<(String, SqlValue)>),
+}
+
+impl Params {
+    fn from_value(value: Option<&Value>) -> Result<Self, String> {
+        match value {
+            None | Some(Value::None) => Ok(Params::Positional(Vec::new())),
+            Some(Value::List(items)) => Ok(Params::Positional(items.iter().map(to_sql_value).collect())),
+            Some(Value::Dict(pairs)) => Ok(Params::Named(
+                pairs
+                    .iter()
+                    .map(|(k, v)| {
+                        let key = k.to_string();
+                        let key = if key.starts_with([':', '@', '$']) { key } else { format!(":{}", key) };
+                        (key, to_sql_value(v))
+                    })
+                    .collect(),
+            )),
+            Some(other) => Err(format!("SQL parameters must be a list or dict, got {}", other)),
+        }
+    }
+
+    fn bind(&self, stmt: &mut CachedStatement<'_>) -> Result<(), String> {
+        match self {
+            Params::Positional(values) => {
+                if values.len() != stmt.parameter_count() {
+                    return Err(format!(
+                        "SQL statement expects {} parameter(s) but {} were given",
+                        stmt.parameter_count(),
+                        values.len()
+                    ));
+                }
+                for (i, value) in values.iter().enumerate() {
+                    stmt.raw_bind_parameter(i + 1, value).map_err(sql_error)?;
+                }
+            }
+            Params::Named(values) => {
+                for (name, value) in values {
+                    let index = stmt
+                        .parameter_index(name)
+                        .map_err(sql_error)?
+                        .ok_or_else(|| format!("SQL statement has no parameter named {}", name))?;
+                    stmt.raw_bind_parameter(index, value).map_err(sql_error)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Queries
+// ============================================================================
+
+fn sql_error(e: rusqlite::Error) -> String {
+    format!("SQL error: {}", e)
+}
+
+/// Run a statement, returning the number of changed rows
+pub fn execute(conn: &Connection, sql: &str, params: Option<&Value>) -> Result<usize, String> {
+    let params = Params::from_value(params)?;
+    let mut stmt = conn.prepare_cached(sql).map_err(sql_error)?;
+    params.bind(&mut stmt)?;
+    stmt.raw_execute().map_err(sql_error)
+}
+
+/// Run a query, returning every row as a dict keyed by column name
+pub fn query(conn: &Connection, sql: &str, params: Option<&Value>, limit: Option<usize>) -> Result<Vec<Value>, String> {
+    let params = Params::from_value(params)?;
+    let mut stmt = conn.prepare_cached(sql).map_err(sql_error)?;
+    let columns: Vec<Value> = stmt.column_names().into_iter().map(|c| Value::String(c.to_string())).collect();
+    params.bind(&mut stmt)?;
+    let mut rows = stmt.raw_query();
+    let mut results = Vec::new();
+    while let Some(row) = rows.next().map_err(sql_error)? {
+        let mut pairs = Vec::with_capacity(columns.len());
+        for (i, column) in columns.iter().enumerate() {
+            pairs.push((column.clone(), from_sql_value(row.get_ref(i).map_err(sql_error)?)));
+        }
+        results.push(Value::Dict(pairs));
+        if limit.is_some_and(|n| results.len() >= n) {
+            break;
+        }
+    }
+    Ok(results)
+}
+
+/// Apply every `*.sql` file in `dir` (sorted by name) that has not been
+/// applied yet, each in its own transaction. Returns the applied file names.
+pub fn migrate(conn: &Connection, dir: &Path) -> Result<Vec<String>, String> {
+    checks::fs_read(&dir.to_string_lossy())?;
+    conn.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS {} (name TEXT PRIMARY KEY, applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP)",
+        MIGRATIONS_TABLE
+    ))
+    .map_err(sql_error)?;
+
+    let mut files: Vec<_> = std::fs::read_dir(dir)
+        .map_err(|e| format!("Cannot read migrations directory {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sql"))
+        .collect();
+    files.sort();
+
+    let mut applied = Vec::new();
+    for path in files {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let done: bool = conn
+            .query_row(&format!("SELECT COUNT(*) > 0 FROM {} WHERE name = ?1", MIGRATIONS_TABLE), [&name], |row| row.get(0))
+            .map_err(sql_error)?;
+        if done {
+            continue;
+        }
+        let sql = std::fs::read_to_string(&path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let script = format!(
+            "BEGIN;\n{}\n;INSERT INTO {} (name) VALUES ('{}');\nCOMMIT;",
+            sql,
+            MIGRATIONS_TABLE,
+            name.replace('\'', "''")
+        );
+        if let Err(e) = conn.execute_batch(&script) {
+            conn.execute_batch("ROLLBACK").ok();
+            return Err(format!("Migration {} failed: {}", name, e));
+        }
+        applied.push(name);
+    }
+    Ok(applied)
+}
+
+// ============================================================================
+// Module functions and methods
+// ============================================================================
+
+fn instance(class_name: &str, fields: Vec<(&str, Value)>) -> Value {
+    Value::Instance {
+        class_name: class_name.to_string(),
+        fields: fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect(),
+    }
+}
+
+fn sql_arg(args: &[Value], method: &str) -> Result<String, String> {
+    match args.first() {
+        Some(Value::String(s)) => Ok(s.clone()),
+        _ => Err(format!("{}() requires a SQL string", method)),
+    }
+}
+
+/// Call a `sqlite.<name>` module function
+pub fn call(name: &str, args: Vec<Value>) -> Result<Value, String> {
+    let (args, kwargs) = split_kwargs(args, 1);
+    match name {
+        "connect" => {
+            let path = match args.first() {
+                Some(Value::String(s)) => s.clone(),
+                None => ":memory:".to_string(),
+                Some(other) => return Err(format!("connect() requires a path string, got {}", other)),
+            };
+            let id = open(&path)?;
+            for (key, value) in &kwargs {
+                match (key.as_str(), value) {
+                    ("cache_size", Value::Int(n)) if *n >= 0 => with_connection(id, |conn| {
+                        conn.set_prepared_statement_cache_capacity(*n as usize);
+                        Ok(())
+                    })?,
+                    ("foreign_keys", flag) => with_connection(id, |conn| {
+                        let on = !matches!(flag, Value::Bool(false));
+                        conn.pragma_update(None, "foreign_keys", on).map_err(sql_error)
+                    })?,
+                    ("timeout", Value::Int(ms)) if *ms >= 0 => with_connection(id, |conn| {
+                        conn.busy_timeout(std::time::Duration::from_millis(*ms as u64)).map_err(sql_error)
+                    })?,
+                    _ => {
+                        close(id);
+                        return Err(format!("Invalid connect() option: {}={}", key, value));
+                    }
+                }
+            }
+            Ok(instance(CONNECTION_CLASS, vec![("id", Value::Int(id as i64)), ("path", Value::String(path))]))
+        }
+        _ => Err(format!("sqlite has no function '{}'", name)),
+    }
+}
+
+/// Methods on `sqlite.Connection` and `sqlite.Transaction` values
+pub fn call_method(value: &Value, method: &str, args: Vec<Value>) -> Option<Result<Value, String>> {
+    let Value::Instance { class_name, fields } = value else { return None };
+    let Some(Value::Int(id)) = fields.get("id") else { return None };
+    match class_name.as_str() {
+        CONNECTION_CLASS => Some(connection_method(value, *id as u64, method, args)),
+        TRANSACTION_CLASS => Some(transaction_method(value, *id as u64, method, args)),
+        _ => None,
+    }
+}
+
+fn connection_method(value: &Value, id: u64, method: &str, args: Vec<Value>) -> Result<Value, String> {
+    match method {
+        "execute" => {
+            let sql = sql_arg(&args, method)?;
+            with_connection(id, |conn| execute(conn, &sql, args.get(1)).map(|n| Value::Int(n as i64)))
+        }
+        "executemany" => {
+            let sql = sql_arg(&args, method)?;
+            let Some(Value::List(batches)) = args.get(1) else {
+                return Err("executemany() requires a list of parameter lists".to_string());
+            };
+            with_connection(id, |conn| {
+                let mut total = 0;
+                for params in batches {
+                    total += execute(conn, &sql, Some(params))?;
+                }
+                Ok(Value::Int(total as i64))
+            })
+        }
+        "executescript" => {
+            let sql = sql_arg(&args, method)?;
+            with_connection(id, |conn| conn.execute_batch(&sql).map_err(sql_error))?;
+            Ok(Value::None)
+        }
+        "query" => {
+            let sql = sql_arg(&args, method)?;
+            with_connection(id, |conn| query(conn, &sql, args.get(1), None).map(Value::List))
+        }
+        "query_one" => {
+            let sql = sql_arg(&args, method)?;
+            let rows = with_connection(id, |conn| query(conn, &sql, args.get(1), Some(1)))?;
+            Ok(rows.into_iter().next().unwrap_or(Value::None))
+        }
+        "query_value" => {
+            let sql = sql_arg(&args, method)?;
+            let rows = with_connection(id, |conn| query(conn, &sql, args.get(1), Some(1)))?;
+            Ok(match rows.into_iter().next() {
+                Some(Value::Dict(pairs)) => pairs.into_iter().next().map(|(_, v)| v).unwrap_or(Value::None),
+                _ => Value::None,
+            })
+        }
+        "last_insert_id" => with_connection(id, |conn| Ok(Value::Int(conn.last_insert_rowid()))),
+        "changes" => with_connection(id, |conn| Ok(Value::Int(conn.changes() as i64))),
+        "in_transaction" => with_connection(id, |conn| Ok(Value::Bool(!conn.is_autocommit()))),
+        "begin" | "commit" | "rollback" => {
+            let sql = method.to_uppercase();
+            with_connection(id, |conn| conn.execute_batch(&sql).map_err(sql_error))?;
+            Ok(Value::None)
+        }
+        "transaction" => {
+            let tx = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+            Ok(instance(TRANSACTION_CLASS, vec![("id", Value::Int(id as i64)), ("tx", Value::Int(tx as i64))]))
+        }
+        "migrate" => {
+            let dir = match args.first() {
+                Some(Value::String(s)) => s.clone(),
+                _ => "migrations".to_string(),
+            };
+            let applied = with_connection(id, |conn| migrate(conn, Path::new(&dir)))?;
+            Ok(Value::List(applied.into_iter().map(Value::String).collect()))
+        }
+        "tables" => {
+            let rows = with_connection(id, |conn| {
+                query(conn, "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name", None, None)
+            })?;
+            Ok(Value::List(
+                rows.into_iter()
+                    .filter_map(|row| match row {
+                        Value::Dict(pairs) => pairs.into_iter().next().map(|(_, v)| v),
+                        _ => None,
+                    })
+                    .collect(),
+            ))
+        }
+        "__enter__" => Ok(value.clone()),
+        "__exit__" | "close" => {
+            close(id);
+            Ok(Value::None)
+        }
+        _ => Err(format!("'sqlite.Connection' object has no method '{}'", method)),
+    }
+}
+
+/// `with db.transaction():` begins on enter and commits or rolls back on exit.
+/// Nested transactions become savepoints.
+fn transaction_method(value: &Value, id: u64, method: &str, args: Vec<Value>) -> Result<Value, String> {
+    let Value::Instance { fields, .. } = value else { unreachable!() };
+    let Some(Value::Int(tx)) = fields.get("tx") else {
+        return Err("Invalid transaction object".to_string());
+    };
+    let tx = *tx as u64;
+    let savepoint = format!("poly_sp_{}", tx);
+    match method {
+        "__enter__" | "begin" => {
+            if TRANSACTIONS.lock().unwrap().contains_key(&tx) {
+                return Err("Transaction is already active".to_string());
+            }
+            let nested = with_connection(id, |conn| {
+                let nested = !conn.is_autocommit();
+                let sql = if nested { format!("SAVEPOINT {}", savepoint) } else { "BEGIN".to_string() };
+                conn.execute_batch(&sql).map_err(sql_error)?;
+                Ok(nested)
+            })?;
+            TRANSACTIONS.lock().unwrap().insert(tx, nested);
+            Ok(value.clone())
+        }
+        "__exit__" | "commit" | "rollback" => {
+            let nested = TRANSACTIONS.lock().unwrap().remove(&tx).ok_or("Transaction is not active")?;
+            let failed = match method {
+                "__exit__" => !matches!(args.first(), None | Some(Value::None)),
+                _ => method == "rollback",
+            };
+            let sql = match (nested, failed) {
+                (true, false) => format!("RELEASE SAVEPOINT {}", savepoint),
+                (true, true) => format!("ROLLBACK TO SAVEPOINT {0}; RELEASE SAVEPOINT {0}", savepoint),
+                (false, false) => "COMMIT".to_string(),
+                (false, true) => "ROLLBACK".to_string(),
+            };
+            with_connection(id, |conn| conn.execute_batch(&sql).map_err(sql_error))?;
+            Ok(Value::Bool(false))
+        }
+        _ => Err(format!("'sqlite.Transaction' object has no method '{}'", method)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connect() -> Value {
+        call("connect", vec![Value::String(":memory:".to_string())]).unwrap()
+    }
+
+    fn method(db: &Value, name: &str, args: Vec<Value>) -> Result<Value, String> {
+        call_method(db, name, args).unwrap()
+    }
+
+    fn s(text: &str) -> Value {
+        Value::String(text.to_string())
+    }
+
+    #[test]
+    fn test_params_rows_and_transactions() {
+        let db = connect();
+        method(&db, "execute", vec![s("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT, score REAL)")]).unwrap();
+        method(&db, "execute", vec![s("INSERT INTO t (name, score) VALUES (?, ?)"), Value::List(vec![s("a"), Value::Float(1.5)])]).unwrap();
+        let named = Value::Dict(vec![(s("name"), s("b")), (s("score"), Value::None)]);
+        method(&db, "execute", vec![s("INSERT INTO t (name, score) VALUES (:name, :score)"), named]).unwrap();
+        assert_eq!(method(&db, "last_insert_id", vec![]).unwrap(), Value::Int(2));
+
+        let row = method(&db, "query_one", vec![s("SELECT name, score FROM t WHERE id = ?"), Value::List(vec![Value::Int(1)])]).unwrap();
+        assert_eq!(row, Value::Dict(vec![(s("name"), s("a")), (s("score"), Value::Float(1.5))]));
+
+        // Committed transaction, then a rolled-back savepoint inside another
+        let outer = method(&db, "transaction", vec![]).unwrap();
+        method(&outer, "__enter__", vec![]).unwrap();
+        method(&db, "execute", vec![s("INSERT INTO t (name) VALUES ('c')")]).unwrap();
+        let inner = method(&db, "transaction", vec![]).unwrap();
+        method(&inner, "__enter__", vec![]).unwrap();
+        method(&db, "execute", vec![s("INSERT INTO t (name) VALUES ('d')")]).unwrap();
+        method(&inner, "__exit__", vec![s("boom")]).unwrap();
+        method(&outer, "__exit__", vec![Value::None]).unwrap();
+
+        let count = method(&db, "query_value", vec![s("SELECT COUNT(*) FROM t")]).unwrap();
+        assert_eq!(count, Value::Int(3));
+        assert_eq!(method(&db, "in_transaction", vec![]).unwrap(), Value::Bool(false));
+        method(&db, "close", vec![]).unwrap();
+        assert!(method(&db, "query", vec![s("SELECT 1")]).is_err());
+    }
+
+    #[test]
+    fn test_migrations_apply_once() {
+        let dir = std::env::temp_dir().join(format!("poly_migrations_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("001_items.sql"), "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT);").unwrap();
+        std::fs::write(dir.join("002_seed.sql"), "INSERT INTO items (name) VALUES ('x');").unwrap();
+
+        let db = connect();
+        let path = s(&dir.to_string_lossy());
+        let applied = method(&db, "migrate", vec![path.clone()]).unwrap();
+        assert_eq!(applied, Value::List(vec![s("001_items.sql"), s("002_seed.sql")]));
+        assert_eq!(method(&db, "migrate", vec![path]).unwrap(), Value::List(vec![]));
+        assert_eq!(method(&db, "query_value", vec![s("SELECT COUNT(*) FROM items")]).unwrap(), Value::Int(1));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
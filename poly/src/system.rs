@@ -0,0 +1,1325 @@
+//! Built-in `__poly_*` system APIs
+//!
+//! Dialogs, files, windows, clipboard, HTTP, AI, databases and the rest of
+//! what `poly.*` in the page calls, registered with the IPC router so the dev
+//! server, the native window and the headless harness share one schema and
+//! one permission per command. APIs that need a native window fail with
+//! "Native feature not enabled" in builds without it.
+
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::ai::{AiProvider, ChatMessage, ChatRequest, MessageRole, Tool};
+use crate::ipc::Error;
+use crate::router::{register, ArgKind, Call, Command};
+use crate::sovereignty::checks::path_to_scope;
+use crate::sovereignty::Permission;
+
+fn runtime(message: impl Into<String>) -> Error {
+    Error::new("RUNTIME_ERROR", message)
+}
+
+#[cfg(not(feature = "native"))]
+fn native_only() -> Error {
+    runtime("Native feature not enabled")
+}
+
+/// Numeric `id` of a window, tab or database; ids start at 1
+fn id(call: &Call) -> u64 {
+    call.u64("id").unwrap_or(0)
+}
+
+fn fs_read(args: &Value) -> Option<Permission> {
+    Some(Permission::FsRead(path_to_scope(args.get("path").and_then(|v| v.as_str()).unwrap_or(""))))
+}
+
+fn fs_write(args: &Value) -> Option<Permission> {
+    Some(Permission::FsWrite(path_to_scope(args.get("path").and_then(|v| v.as_str()).unwrap_or(""))))
+}
+
+/// Register every built-in system API with the IPC router
+pub fn register_commands() {
+    register_dialogs();
+    register_files();
+    register_updater();
+    register_clipboard();
+    register_windows();
+    register_desktop();
+    register_app();
+    register_http();
+    register_ai();
+    register_db();
+    register_browser();
+    register_titlebar();
+    register_webviews();
+    register_multiview();
+}
+
+#[allow(unused_variables)]
+fn register_dialogs() {
+    register(Command::new("__poly_dialog_open", |call| {
+        let title = call.str("title");
+        let filters = parse_filters(call.get("filters"));
+        #[cfg(feature = "native")]
+        { Ok(json!(crate::native::dialog_open_file(title, filters))) }
+        #[cfg(not(feature = "native"))]
+        { Ok(Value::Null) }
+    }).optional("title", ArgKind::String).optional("filters", ArgKind::Array).permission(Permission::Dialogs));
+    register(Command::new("__poly_dialog_open_multiple", |call| {
+        let title = call.str("title");
+        let filters = parse_filters(call.get("filters"));
+        #[cfg(feature = "native")]
+        { Ok(json!(crate::native::dialog_open_files(title, filters))) }
+        #[cfg(not(feature = "native"))]
+        { Ok(json!([])) }
+    }).optional("title", ArgKind::String).optional("filters", ArgKind::Array).permission(Permission::Dialogs));
+    register(Command::new("__poly_dialog_save", |call| {
+        let title = call.str("title");
+        let default_name = call.str("defaultName");
+        let filters = parse_filters(call.get("filters"));
+        #[cfg(feature = "native")]
+        { Ok(json!(crate::native::dialog_save_file(title, default_name, filters))) }
+        #[cfg(not(feature = "native"))]
+        { Ok(Value::Null) }
+    }).optional("title", ArgKind::String).optional("defaultName", ArgKind::String).optional("filters", ArgKind::Array).permission(Permission::Dialogs));
+    register(Command::new("__poly_dialog_folder", |call| {
+        let title = call.str("title");
+        #[cfg(feature = "native")]
+        { Ok(json!(crate::native::dialog_pick_folder(title))) }
+        #[cfg(not(feature = "native"))]
+        { Ok(Value::Null) }
+    }).optional("title", ArgKind::String).permission(Permission::Dialogs));
+    register(Command::new("__poly_dialog_message", |call| {
+        let title = call.str("title").unwrap_or("Message");
+        let message = call.str("message").unwrap_or("");
+        let level = call.str("level").unwrap_or("info");
+        #[cfg(feature = "native")]
+        {
+            let level = match level {
+                "warning" => crate::native::MessageLevel::Warning,
+                "error" => crate::native::MessageLevel::Error,
+                _ => crate::native::MessageLevel::Info,
+            };
+            crate::native::dialog_message(title, message, level);
+            Ok(json!(true))
+        }
+        #[cfg(not(feature = "native"))]
+        { Ok(json!(false)) }
+    }).optional("title", ArgKind::String).arg("message", ArgKind::String).optional("level", ArgKind::String).permission(Permission::Dialogs));
+    register(Command::new("__poly_dialog_confirm", |call| {
+        let title = call.str("title").unwrap_or("Confirm");
+        let message = call.str("message").unwrap_or("");
+        #[cfg(feature = "native")]
+        { Ok(json!(crate::native::dialog_confirm(title, message))) }
+        #[cfg(not(feature = "native"))]
+        { Ok(json!(false)) }
+    }).optional("title", ArgKind::String).arg("message", ArgKind::String).permission(Permission::Dialogs));
+}
+
+/// Parse filter array from JSON: [["Images", ["png", "jpg"]], ["All", ["*"]]]
+fn parse_filters(_value: Option<&Value>) -> Option<Vec<(&str, &[&str])>> {
+    // For simplicity, we don't parse complex filters in this version
+    // Users can pass simple filters or none
+    None
+}
+
+fn register_files() {
+    register(Command::new("__poly_fs_read", |call| {
+        fs::read_to_string(call.str("path").unwrap_or_default()).map(Value::String).map_err(|e| runtime(e.to_string()))
+    }).arg("path", ArgKind::String).permission_for(fs_read));
+    register(Command::new("__poly_fs_write", |call| {
+        fs::write(call.str("path").unwrap_or_default(), call.str("content").unwrap_or_default())
+            .map(|_| json!(true))
+            .map_err(|e| runtime(e.to_string()))
+    }).arg("path", ArgKind::String).arg("content", ArgKind::String).permission_for(fs_write));
+    register(Command::new("__poly_fs_exists", |call| {
+        Ok(json!(Path::new(call.str("path").unwrap_or_default()).exists()))
+    }).arg("path", ArgKind::String).permission_for(fs_read));
+    register(Command::new("__poly_fs_read_dir", |call| {
+        let entries = fs::read_dir(call.str("path").unwrap_or(".")).map_err(|e| runtime(e.to_string()))?;
+        let files: Vec<Value> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| {
+                let path = e.path();
+                json!({
+                    "name": e.file_name().to_string_lossy(),
+                    "path": path.to_string_lossy(),
+                    "isDir": path.is_dir(),
+                })
+            })
+            .collect();
+        Ok(json!(files))
+    }).optional("path", ArgKind::String).permission_for(fs_read));
+}
+
+#[allow(unused_variables)]
+fn register_updater() {
+    register(Command::new("__poly_updater_check_github", |call| {
+        let repo = call.str("repo").unwrap_or("");
+        let current_version = call.str("currentVersion").unwrap_or("0.0.0");
+        #[cfg(feature = "native")]
+        { crate::updater::check_github_updates(repo, current_version).map(|info| json!(info)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("repo", ArgKind::String).optional("currentVersion", ArgKind::String));
+    register(Command::new("__poly_updater_check_url", |call| {
+        let url = call.str("url").unwrap_or("");
+        let current_version = call.str("currentVersion").unwrap_or("0.0.0");
+        #[cfg(feature = "native")]
+        { crate::updater::check_custom_updates(url, current_version).map(|info| json!(info)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("url", ArgKind::String).optional("currentVersion", ArgKind::String));
+    register(Command::new("__poly_updater_download", |call| {
+        let url = call.str("url").unwrap_or("");
+        #[cfg(feature = "native")]
+        { crate::updater::download_update(url, None).map(|path| json!(path.to_string_lossy())).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("url", ArgKind::String));
+    register(Command::new("__poly_updater_install", |call| {
+        let path = call.str("path").unwrap_or("");
+        #[cfg(feature = "native")]
+        { crate::updater::install_update(Path::new(path)).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("path", ArgKind::String));
+}
+
+#[allow(unused_variables)]
+fn register_clipboard() {
+    register(Command::new("__poly_clipboard_read", |_| {
+        #[cfg(feature = "native")]
+        { crate::clipboard::read_text().map(Value::String).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).permission(Permission::ClipboardRead));
+    register(Command::new("__poly_clipboard_write", |call| {
+        let text = call.str("text").unwrap_or("");
+        #[cfg(feature = "native")]
+        { crate::clipboard::write_text(text).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("text", ArgKind::String).permission(Permission::ClipboardWrite));
+    register(Command::new("__poly_clipboard_clear", |_| {
+        #[cfg(feature = "native")]
+        { crate::clipboard::clear().map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).permission(Permission::ClipboardWrite));
+}
+
+#[allow(unused_variables)]
+fn register_windows() {
+    register(Command::new("__poly_window_create", |call| {
+        let title = call.str("title").unwrap_or("New Window");
+        let width = call.u64("width").unwrap_or(800) as u32;
+        let height = call.u64("height").unwrap_or(600) as u32;
+        let url = call.str("url");
+        let html = call.str("html");
+        #[cfg(feature = "native")]
+        {
+            let mut config = crate::window::WindowConfig::new(title).with_size(width, height);
+            if let Some(u) = url {
+                config = config.with_url(u);
+            }
+            if let Some(h) = html {
+                config = config.with_html(h);
+            }
+            crate::window::create_window(config).map(|handle| json!({"id": handle.id})).map_err(runtime)
+        }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    })
+    .optional("title", ArgKind::String)
+    .optional("width", ArgKind::Number)
+    .optional("height", ArgKind::Number)
+    .optional("url", ArgKind::String)
+    .optional("html", ArgKind::String)
+    .permission(Permission::WindowCreate));
+    register(Command::new("__poly_window_count", |_| {
+        #[cfg(feature = "native")]
+        { Ok(json!(crate::window::window_count())) }
+        #[cfg(not(feature = "native"))]
+        { Ok(json!(0)) }
+    }));
+    register(Command::new("__poly_window_close", |call| {
+        #[cfg(feature = "native")]
+        { crate::window::close_window(id(call)).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("id", ArgKind::Number));
+    register(Command::new("__poly_window_close_all", |_| {
+        #[cfg(feature = "native")]
+        { crate::window::close_all_windows().map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }));
+    register(Command::new("__poly_window_list", |_| {
+        #[cfg(feature = "native")]
+        { Ok(json!(crate::window::list_windows())) }
+        #[cfg(not(feature = "native"))]
+        { Ok(json!([])) }
+    }));
+    register(Command::new("__poly_window_minimize", |call| {
+        #[cfg(feature = "native")]
+        { crate::window::minimize_window(id(call)).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("id", ArgKind::Number));
+    register(Command::new("__poly_window_maximize", |call| {
+        #[cfg(feature = "native")]
+        { crate::window::maximize_window(id(call)).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("id", ArgKind::Number));
+    register(Command::new("__poly_window_restore", |call| {
+        #[cfg(feature = "native")]
+        { crate::window::restore_window(id(call)).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("id", ArgKind::Number));
+    register(Command::new("__poly_window_show", |call| {
+        #[cfg(feature = "native")]
+        { crate::window::show_window(id(call)).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("id", ArgKind::Number));
+    register(Command::new("__poly_window_hide", |call| {
+        #[cfg(feature = "native")]
+        { crate::window::hide_window(id(call)).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("id", ArgKind::Number));
+    register(Command::new("__poly_window_focus", |call| {
+        #[cfg(feature = "native")]
+        { crate::window::focus_window(id(call)).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("id", ArgKind::Number));
+    register(Command::new("__poly_window_set_title", |call| {
+        let title = call.str("title").unwrap_or("");
+        #[cfg(feature = "native")]
+        { crate::window::set_window_title(id(call), title).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("id", ArgKind::Number).arg("title", ArgKind::String));
+    register(Command::new("__poly_window_set_size", |call| {
+        let width = call.u64("width").unwrap_or(800) as u32;
+        let height = call.u64("height").unwrap_or(600) as u32;
+        #[cfg(feature = "native")]
+        { crate::window::set_window_size(id(call), width, height).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("id", ArgKind::Number).arg("width", ArgKind::Number).arg("height", ArgKind::Number));
+    register(Command::new("__poly_window_set_position", |call| {
+        let x = call.i64("x").unwrap_or(0) as i32;
+        let y = call.i64("y").unwrap_or(0) as i32;
+        #[cfg(feature = "native")]
+        { crate::window::set_window_position(id(call), x, y).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("id", ArgKind::Number).arg("x", ArgKind::Number).arg("y", ArgKind::Number));
+    register(Command::new("__poly_window_set_always_on_top", |call| {
+        let value = call.bool("value").unwrap_or(false);
+        #[cfg(feature = "native")]
+        { crate::window::set_window_always_on_top(id(call), value).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("id", ArgKind::Number).arg("value", ArgKind::Bool));
+    register(Command::new("__poly_window_set_fullscreen", |call| {
+        let value = call.bool("value").unwrap_or(false);
+        #[cfg(feature = "native")]
+        { crate::window::set_window_fullscreen(id(call), value).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("id", ArgKind::Number).arg("value", ArgKind::Bool));
+    register(Command::new("__poly_window_navigate", |call| {
+        let url = call.str("url").unwrap_or("");
+        #[cfg(feature = "native")]
+        { crate::window::navigate_window(id(call), url).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("id", ArgKind::Number).arg("url", ArgKind::String));
+    register(Command::new("__poly_window_load_html", |call| {
+        let html = call.str("html").unwrap_or("");
+        #[cfg(feature = "native")]
+        { crate::window::load_window_html(id(call), html).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("id", ArgKind::Number).arg("html", ArgKind::String));
+    register(Command::new("__poly_window_eval", |call| {
+        let script = call.str("script").unwrap_or("");
+        #[cfg(feature = "native")]
+        { crate::window::eval_window_script(id(call), script).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("id", ArgKind::Number).arg("script", ArgKind::String));
+    register(Command::new("__poly_window_get_state", |call| {
+        #[cfg(feature = "native")]
+        { crate::window::get_window_state(id(call)).map(window_state).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }).arg("id", ArgKind::Number));
+    register(Command::new("__poly_window_list_states", |_| {
+        #[cfg(feature = "native")]
+        { Ok(json!(crate::window::list_window_states().into_iter().map(window_state).collect::<Vec<_>>())) }
+        #[cfg(not(feature = "native"))]
+        { Ok(json!([])) }
+    }));
+}
+
+#[cfg(feature = "native")]
+fn window_state(state: crate::window::WindowState) -> Value {
+    json!({
+        "id": state.id,
+        "title": state.title,
+        "width": state.width,
+        "height": state.height,
+        "x": state.x,
+        "y": state.y,
+        "isVisible": state.is_visible,
+        "isMinimized": state.is_minimized,
+        "isMaximized": state.is_maximized,
+        "isFullscreen": state.is_fullscreen,
+        "isFocused": state.is_focused
+    })
+}
+
+/// Notifications, deep links, the tray and the shell
+#[allow(unused_variables)]
+fn register_desktop() {
+    register(Command::new("__poly_notification_show", |call| {
+        let title = call.str("title").unwrap_or("Notification");
+        let body = call.str("body").unwrap_or("");
+        let icon = call.str("icon");
+        #[cfg(feature = "native")]
+        { crate::notification::show(title, body, icon).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    })
+    .optional("title", ArgKind::String)
+    .optional("body", ArgKind::String)
+    .optional("icon", ArgKind::String)
+    .permission(Permission::Notifications));
+    register(Command::new("__poly_notification_show_timeout", |call| {
+        let title = call.str("title").unwrap_or("Notification");
+        let body = call.str("body").unwrap_or("");
+        let timeout = call.u64("timeout").unwrap_or(5000) as u32;
+        #[cfg(feature = "native")]
+        { crate::notification::show_with_timeout(title, body, timeout).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    })
+    .optional("title", ArgKind::String)
+    .optional("body", ArgKind::String)
+    .optional("timeout", ArgKind::Number)
+    .permission(Permission::Notifications));
+
+    register(Command::new("__poly_deeplink_register", |call| {
+        let protocol = call.str("protocol").unwrap_or("");
+        if protocol.is_empty() {
+            return Err(runtime("Protocol name required"));
+        }
+        crate::deeplink::register_protocol(protocol, call.str("appName").unwrap_or("Poly App")).map(|_| json!(true)).map_err(runtime)
+    }).arg("protocol", ArgKind::String).optional("appName", ArgKind::String).permission(Permission::DeepLinks));
+    register(Command::new("__poly_deeplink_unregister", |call| {
+        let protocol = call.str("protocol").unwrap_or("");
+        if protocol.is_empty() {
+            return Err(runtime("Protocol name required"));
+        }
+        crate::deeplink::unregister_protocol(protocol).map(|_| json!(true)).map_err(runtime)
+    }).arg("protocol", ArgKind::String).permission(Permission::DeepLinks));
+    register(Command::new("__poly_deeplink_is_registered", |call| {
+        Ok(json!(crate::deeplink::is_protocol_registered(call.str("protocol").unwrap_or(""))))
+    }).arg("protocol", ArgKind::String));
+    register(Command::new("__poly_deeplink_get", |_| Ok(json!(crate::deeplink::get_deep_link()))));
+    register(Command::new("__poly_deeplink_has", |_| Ok(json!(crate::deeplink::has_deep_link()))));
+
+    // Set by poly.toml; the native window manages the tray itself
+    register(Command::new("__poly_tray_is_enabled", |_| Ok(json!(false))));
+
+    register(Command::new("__poly_shell_open", |call| {
+        let url = call.str("url").unwrap_or("");
+        #[cfg(target_os = "windows")]
+        let _ = std::process::Command::new("cmd").args(["/C", "start", "", url]).spawn();
+        #[cfg(target_os = "macos")]
+        let _ = std::process::Command::new("open").arg(url).spawn();
+        #[cfg(target_os = "linux")]
+        let _ = std::process::Command::new("xdg-open").arg(url).spawn();
+        Ok(json!(true))
+    }).arg("url", ArgKind::String).permission(Permission::ShellOpen));
+    register(Command::new("__poly_shell_open_path", |call| {
+        let path = call.str("path").unwrap_or("");
+        #[cfg(target_os = "windows")]
+        let _ = std::process::Command::new("explorer").arg(path).spawn();
+        #[cfg(target_os = "macos")]
+        let _ = std::process::Command::new("open").arg(path).spawn();
+        #[cfg(target_os = "linux")]
+        let _ = std::process::Command::new("xdg-open").arg(path).spawn();
+        Ok(json!(true))
+    }).arg("path", ArgKind::String).permission(Permission::ShellOpenPath));
+    register(Command::new("__poly_shell_open_with", |call| {
+        let _ = std::process::Command::new(call.str("app").unwrap_or("")).arg(call.str("path").unwrap_or("")).spawn();
+        Ok(json!(true))
+    }).arg("path", ArgKind::String).arg("app", ArgKind::String).permission(Permission::ShellOpenPath));
+}
+
+/// The app itself and the OS it runs on
+fn register_app() {
+    register(Command::new("__poly_app_get_version", |_| Ok(json!(env!("CARGO_PKG_VERSION")))));
+    register(Command::new("__poly_app_get_name", |_| {
+        let name = std::env::current_exe()
+            .ok()
+            .and_then(|p| p.file_stem().map(|s| s.to_string_lossy().to_string()))
+            .unwrap_or_else(|| "Poly App".to_string());
+        Ok(json!(name))
+    }));
+    register(Command::new("__poly_app_get_path", |call| {
+        let path = match call.str("name").unwrap_or("data") {
+            "exe" => std::env::current_exe().ok().map(|p| p.to_string_lossy().to_string()),
+            "data" | "appData" => dirs_path("data"),
+            "temp" => Some(std::env::temp_dir().to_string_lossy().to_string()),
+            name @ ("config" | "cache" | "home" | "desktop" | "documents" | "downloads") => dirs_path(name),
+            _ => None,
+        };
+        Ok(json!(path))
+    }).optional("name", ArgKind::String));
+    register(Command::new("__poly_app_exit", |call| {
+        std::process::exit(call.i64("code").unwrap_or(0) as i32);
+    }).optional("code", ArgKind::Number).permission(Permission::AppExit));
+    register(Command::new("__poly_app_relaunch", |_| {
+        if let Ok(exe) = std::env::current_exe() {
+            let _ = std::process::Command::new(exe).args(std::env::args().skip(1)).spawn();
+        }
+        std::process::exit(0);
+    }).permission(Permission::AppRelaunch));
+
+    register(Command::new("__poly_os_platform", |_| {
+        let platform = if cfg!(target_os = "windows") { "windows" }
+            else if cfg!(target_os = "macos") { "macos" }
+            else if cfg!(target_os = "linux") { "linux" }
+            else { "unknown" };
+        Ok(json!(platform))
+    }).permission(Permission::OsInfo));
+    register(Command::new("__poly_os_arch", |_| {
+        let arch = if cfg!(target_arch = "x86_64") { "x64" }
+            else if cfg!(target_arch = "aarch64") { "arm64" }
+            else if cfg!(target_arch = "x86") { "x86" }
+            else { "unknown" };
+        Ok(json!(arch))
+    }).permission(Permission::OsInfo));
+    register(Command::new("__poly_os_version", |_| Ok(json!(std::env::consts::OS))).permission(Permission::OsInfo));
+    register(Command::new("__poly_os_hostname", |_| {
+        #[cfg(target_os = "windows")]
+        let hostname = std::env::var("COMPUTERNAME").unwrap_or_else(|_| "unknown".to_string());
+        #[cfg(not(target_os = "windows"))]
+        let hostname = std::env::var("HOSTNAME")
+            .or_else(|_| std::env::var("HOST"))
+            .unwrap_or_else(|_| "unknown".to_string());
+        Ok(json!(hostname))
+    }).permission(Permission::OsInfo));
+    register(Command::new("__poly_os_homedir", |_| Ok(json!(dirs_path("home").unwrap_or_default()))).permission(Permission::OsInfo));
+    register(Command::new("__poly_os_tempdir", |_| Ok(json!(std::env::temp_dir().to_string_lossy()))).permission(Permission::OsInfo));
+}
+
+/// Get common directory paths
+fn dirs_path(name: &str) -> Option<String> {
+    match name {
+        "home" => std::env::var("HOME").ok()
+            .or_else(|| std::env::var("USERPROFILE").ok()),
+        "data" | "appData" => {
+            #[cfg(target_os = "windows")]
+            { std::env::var("APPDATA").ok() }
+            #[cfg(target_os = "macos")]
+            { std::env::var("HOME").ok().map(|h| format!("{}/Library/Application Support", h)) }
+            #[cfg(target_os = "linux")]
+            { std::env::var("XDG_DATA_HOME").ok().or_else(|| std::env::var("HOME").ok().map(|h| format!("{}/.local/share", h))) }
+        }
+        "config" => {
+            #[cfg(target_os = "windows")]
+            { std::env::var("APPDATA").ok() }
+            #[cfg(target_os = "macos")]
+            { std::env::var("HOME").ok().map(|h| format!("{}/Library/Preferences", h)) }
+            #[cfg(target_os = "linux")]
+            { std::env::var("XDG_CONFIG_HOME").ok().or_else(|| std::env::var("HOME").ok().map(|h| format!("{}/.config", h))) }
+        }
+        "cache" => {
+            #[cfg(target_os = "windows")]
+            { std::env::var("LOCALAPPDATA").ok().map(|p| format!("{}\\Temp", p)) }
+            #[cfg(target_os = "macos")]
+            { std::env::var("HOME").ok().map(|h| format!("{}/Library/Caches", h)) }
+            #[cfg(target_os = "linux")]
+            { std::env::var("XDG_CACHE_HOME").ok().or_else(|| std::env::var("HOME").ok().map(|h| format!("{}/.cache", h))) }
+        }
+        "desktop" => {
+            #[cfg(target_os = "windows")]
+            { std::env::var("USERPROFILE").ok().map(|h| format!("{}\\Desktop", h)) }
+            #[cfg(not(target_os = "windows"))]
+            { std::env::var("HOME").ok().map(|h| format!("{}/Desktop", h)) }
+        }
+        "documents" => {
+            #[cfg(target_os = "windows")]
+            { std::env::var("USERPROFILE").ok().map(|h| format!("{}\\Documents", h)) }
+            #[cfg(not(target_os = "windows"))]
+            { std::env::var("HOME").ok().map(|h| format!("{}/Documents", h)) }
+        }
+        "downloads" => {
+            #[cfg(target_os = "windows")]
+            { std::env::var("USERPROFILE").ok().map(|h| format!("{}\\Downloads", h)) }
+            #[cfg(not(target_os = "windows"))]
+            { std::env::var("HOME").ok().map(|h| format!("{}/Downloads", h)) }
+        }
+        _ => None,
+    }
+}
+
+/// HTTP requests check their domain under `[sovereignty]` themselves
+fn register_http() {
+    for (name, body) in [
+        ("__poly_http_get", false),
+        ("__poly_http_post", true),
+        ("__poly_http_put", true),
+        ("__poly_http_patch", true),
+        ("__poly_http_delete", false),
+        ("__poly_http_request", true),
+    ] {
+        let mut command = Command::new(name, http_request);
+        if name == "__poly_http_request" {
+            command = command.optional("method", ArgKind::String);
+        }
+        command = command.arg("url", ArgKind::String);
+        if body {
+            command = command.optional("body", ArgKind::Any);
+        }
+        register(command.optional("headers", ArgKind::Object).optional("timeout", ArgKind::Number));
+    }
+}
+
+fn http_request(call: &Call) -> Result<Value, Error> {
+    #[cfg(feature = "native")]
+    {
+        use reqwest::blocking::Client;
+        use std::time::Duration;
+
+        let url = call.str("url").unwrap_or("");
+        if url.is_empty() {
+            return Err(runtime("URL is required"));
+        }
+
+        // Sovereignty check - verify HTTP permission for this domain
+        crate::sovereignty::checks::http(url).map_err(runtime)?;
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(call.u64("timeout").unwrap_or(30)))
+            .build()
+            .map_err(|e| runtime(format!("Failed to create HTTP client: {}", e)))?;
+
+        // Build headers
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(h) = call.get("headers").and_then(|v| v.as_object()) {
+            for (key, value) in h {
+                if let Some(val) = value.as_str() {
+                    if let (Ok(name), Ok(val)) = (
+                        reqwest::header::HeaderName::from_bytes(key.as_bytes()),
+                        reqwest::header::HeaderValue::from_str(val)
+                    ) {
+                        headers.insert(name, val);
+                    }
+                }
+            }
+        }
+
+        let method = match call.command.as_str() {
+            "__poly_http_post" => "POST",
+            "__poly_http_put" => "PUT",
+            "__poly_http_patch" => "PATCH",
+            "__poly_http_delete" => "DELETE",
+            "__poly_http_request" => call.str("method").unwrap_or("GET"),
+            _ => "GET",
+        };
+
+        let mut request = match method.to_uppercase().as_str() {
+            "POST" => client.post(url),
+            "PUT" => client.put(url),
+            "PATCH" => client.patch(url),
+            "DELETE" => client.delete(url),
+            "HEAD" => client.head(url),
+            _ => client.get(url),
+        };
+
+        request = request.headers(headers);
+
+        // Add body for POST/PUT/PATCH
+        if let Some(body) = call.get("body") {
+            if let Some(text) = body.as_str() {
+                request = request.body(text.to_string());
+            } else {
+                request = request.json(body);
+            }
+        }
+
+        let response = request.send().map_err(|e| runtime(format!("HTTP request failed: {}", e)))?;
+        let status = response.status().as_u16();
+        let headers: std::collections::HashMap<String, String> = response.headers()
+            .iter()
+            .filter_map(|(k, v)| v.to_str().ok().map(|val| (k.to_string(), val.to_string())))
+            .collect();
+
+        let body = response.text().unwrap_or_default();
+
+        // Try to parse as JSON
+        let data: Value = serde_json::from_str(&body).unwrap_or(Value::String(body));
+
+        Ok(json!({
+            "status": status,
+            "headers": headers,
+            "data": data
+        }))
+    }
+
+    #[cfg(not(feature = "native"))]
+    {
+        let _ = call;
+        Err(runtime("HTTP API requires native feature"))
+    }
+}
+
+#[allow(unused_variables)]
+fn register_ai() {
+    register(chat_command("__poly_ai_ollama", |call| ChatRequest {
+        enable_thinking: call.bool("think").unwrap_or(false),
+        ..chat_request(call, AiProvider::Ollama, "llama3")
+    }).optional("think", ArgKind::Bool).optional("tools", ArgKind::Array).optional("toolChoice", ArgKind::String));
+    register(chat_command("__poly_ai_openai", |call| ChatRequest {
+        api_key: call.str("apiKey").map(str::to_string),
+        ..chat_request(call, AiProvider::OpenAI, "gpt-4")
+    }).optional("tools", ArgKind::Array).optional("toolChoice", ArgKind::String));
+    register(chat_command("__poly_ai_anthropic", |call| ChatRequest {
+        api_key: call.str("apiKey").map(str::to_string),
+        enable_thinking: call.bool("enableThinking").unwrap_or(false),
+        thinking_budget: call.u64("thinkingBudget").map(|v| v as u32),
+        ..chat_request(call, AiProvider::Anthropic, "claude-3-5-sonnet-20241022")
+    })
+    .optional("enableThinking", ArgKind::Bool)
+    .optional("thinkingBudget", ArgKind::Number)
+    .optional("tools", ArgKind::Array)
+    .optional("toolChoice", ArgKind::String));
+    register(chat_command("__poly_ai_custom", |call| ChatRequest {
+        base_url: Some(call.str("baseUrl").unwrap_or("http://localhost:8080").to_string()),
+        api_key: call.str("apiKey").map(str::to_string),
+        ..chat_request(call, AiProvider::Custom, "default")
+    }).optional("tools", ArgKind::Array).optional("toolChoice", ArgKind::String));
+    register(chat_command("__poly_ai_chat", |call| {
+        let provider = match call.str("provider").unwrap_or("ollama") {
+            "openai" => AiProvider::OpenAI,
+            "anthropic" => AiProvider::Anthropic,
+            "custom" => AiProvider::Custom,
+            _ => AiProvider::Ollama,
+        };
+        ChatRequest {
+            base_url: call.str("baseUrl").map(str::to_string),
+            api_key: call.str("apiKey").map(str::to_string),
+            enable_thinking: call.bool("enableThinking").unwrap_or(false),
+            thinking_budget: call.u64("thinkingBudget").map(|v| v as u32),
+            ..chat_request(call, provider, "llama3")
+        }
+    })
+    .optional("enableThinking", ArgKind::Bool)
+    .optional("thinkingBudget", ArgKind::Number)
+    .optional("tools", ArgKind::Array)
+    .optional("toolChoice", ArgKind::String));
+    register(Command::new("__poly_ai_check_ollama", |_| {
+        #[cfg(feature = "native")]
+        { crate::ai::check_ollama().map(|available| json!(available)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }));
+    register(Command::new("__poly_ai_list_models", |_| {
+        #[cfg(feature = "native")]
+        { crate::ai::list_ollama_models().map(|models| json!(models)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    }));
+
+    register(Command::new("__poly_ai_stream_start", |call| {
+        let model = call.str("model").unwrap_or("llama3");
+        let messages = chat_messages(call);
+        let temperature = call.f64("temperature").unwrap_or(0.7) as f32;
+        let think = call.bool("think").unwrap_or(false);
+        #[cfg(feature = "native")]
+        { crate::ai::stream_start_ollama(model, messages, temperature, think).map(|id| json!({"streamId": id})).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(native_only()) }
+    })
+    .optional("model", ArgKind::String)
+    .arg("messages", ArgKind::Array)
+    .optional("temperature", ArgKind::Number)
+    .optional("think", ArgKind::Bool));
+    register(Command::new("__poly_ai_stream_poll", |call| {
+        crate::ai::stream_poll(call.str("streamId").unwrap_or(""))
+            .map(|(chunks, done)| json!({"chunks": chunks, "done": done}))
+            .map_err(runtime)
+    }).arg("streamId", ArgKind::String));
+    register(Command::new("__poly_ai_stream_cancel", |call| {
+        crate::ai::stream_cancel(call.str("streamId").unwrap_or("")).map(|_| json!(true)).map_err(runtime)
+    }).arg("streamId", ArgKind::String));
+    register(Command::new("__poly_ai_stream_list", |_| Ok(json!(crate::ai::stream_list()))));
+}
+
+/// A chat completion command; `request` reads the provider's own options.
+/// The shared ones (model, messages, temperature, maxTokens) are declared
+/// here, in the order the docs list them.
+fn chat_command(name: &'static str, request: fn(&Call) -> ChatRequest) -> Command {
+    let command = Command::new(name, move |call| {
+        let request = request(call);
+        #[cfg(feature = "native")]
+        {
+            crate::ai::chat(&request)
+                .map(|resp| json!({
+                    "content": resp.content,
+                    "thinking": resp.thinking,
+                    "model": resp.model,
+                    "usage": resp.usage,
+                    "provider": resp.provider,
+                    "toolCalls": resp.tool_calls,
+                    "finishReason": resp.finish_reason
+                }))
+                .map_err(runtime)
+        }
+        #[cfg(not(feature = "native"))]
+        {
+            let _ = request;
+            Err(native_only())
+        }
+    });
+    let command = match name {
+        "__poly_ai_chat" => command.optional("provider", ArgKind::String),
+        "__poly_ai_custom" => command.optional("baseUrl", ArgKind::String),
+        _ => command,
+    };
+    let command = command.optional("model", ArgKind::String).arg("messages", ArgKind::Array);
+    let command = match name {
+        "__poly_ai_chat" => command.optional("baseUrl", ArgKind::String).optional("apiKey", ArgKind::String),
+        "__poly_ai_openai" | "__poly_ai_anthropic" | "__poly_ai_custom" => command.optional("apiKey", ArgKind::String),
+        _ => command,
+    };
+    command.optional("temperature", ArgKind::Number).optional("maxTokens", ArgKind::Number)
+}
+
+/// Request with the options every provider shares
+fn chat_request(call: &Call, provider: AiProvider, model: &str) -> ChatRequest {
+    let tools: Vec<Tool> = call.get("tools")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|t| serde_json::from_value(t.clone()).ok()).collect())
+        .unwrap_or_default();
+    ChatRequest {
+        provider,
+        base_url: None,
+        api_key: None,
+        model: call.str("model").unwrap_or(model).to_string(),
+        messages: chat_messages(call),
+        temperature: call.f64("temperature").unwrap_or(0.7) as f32,
+        max_tokens: call.u64("maxTokens").map(|v| v as u32),
+        stream: false,
+        enable_thinking: false,
+        thinking_budget: None,
+        tools,
+        tool_choice: call.str("toolChoice").unwrap_or("auto").to_string(),
+    }
+}
+
+fn chat_messages(call: &Call) -> Vec<ChatMessage> {
+    call.get("messages")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|m| {
+            let role = m.get("role")?.as_str()?;
+            let content = m.get("content")?.as_str()?;
+            Some(ChatMessage {
+                role: match role {
+                    "system" => MessageRole::System,
+                    "assistant" => MessageRole::Assistant,
+                    _ => MessageRole::User,
+                },
+                content: content.to_string(),
+            })
+        }).collect())
+        .unwrap_or_default()
+}
+
+/// Connections live in `sqlite`, shared with the Poly `sqlite` module, so
+/// the database permission check happens in one place
+fn register_db() {
+    register(Command::new("__poly_db_open", |call| {
+        crate::sqlite::open(call.str("path").unwrap_or(":memory:")).map(|id| json!({"id": id})).map_err(runtime)
+    }).optional("path", ArgKind::String).permission(Permission::Database));
+    register(Command::new("__poly_db_close", |call| {
+        if crate::sqlite::close(id(call)) {
+            Ok(json!(true))
+        } else {
+            Err(runtime("Database connection not found"))
+        }
+    }).arg("id", ArgKind::Number).permission(Permission::Database));
+    for name in ["__poly_db_execute", "__poly_db_query", "__poly_db_query_one"] {
+        register(
+            Command::new(name, |call| db_statement(call).map_err(runtime))
+                .arg("id", ArgKind::Number)
+                .arg("sql", ArgKind::String)
+                .optional("params", ArgKind::Array)
+                .permission(Permission::Database),
+        );
+    }
+}
+
+fn db_statement(call: &Call) -> Result<Value, String> {
+    let sql = call.str("sql").unwrap_or("");
+    let params: Vec<Box<dyn rusqlite::ToSql>> = call.get("params")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().map(json_to_sql_param).collect())
+        .unwrap_or_default();
+    let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+    let query_one = call.command == "__poly_db_query_one";
+
+    crate::sqlite::with_connection(id(call), |conn| {
+        if call.command == "__poly_db_execute" {
+            return conn.execute(sql, params_refs.as_slice())
+                .map(|rows| json!({"changes": rows}))
+                .map_err(|e| format!("SQL error: {}", e));
+        }
+        let mut stmt = conn.prepare_cached(sql).map_err(|e| format!("Prepare error: {}", e))?;
+        let column_names: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+        let mut rows = stmt.query(params_refs.as_slice()).map_err(|e| format!("Query error: {}", e))?;
+
+        let mut results: Vec<Value> = Vec::new();
+        while let Ok(Some(row)) = rows.next() {
+            let mut obj = serde_json::Map::new();
+            for (i, name) in column_names.iter().enumerate() {
+                obj.insert(name.clone(), sql_value_to_json(row, i));
+            }
+            results.push(Value::Object(obj));
+            if query_one {
+                break;
+            }
+        }
+
+        if query_one {
+            Ok(results.into_iter().next().unwrap_or(Value::Null))
+        } else {
+            Ok(Value::Array(results))
+        }
+    })
+}
+
+/// Convert JSON value to SQL parameter
+fn json_to_sql_param(value: &Value) -> Box<dyn rusqlite::ToSql> {
+    match value {
+        Value::Null => Box::new(rusqlite::types::Null),
+        Value::Bool(b) => Box::new(*b),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Box::new(i)
+            } else if let Some(f) = n.as_f64() {
+                Box::new(f)
+            } else {
+                Box::new(n.to_string())
+            }
+        }
+        Value::String(s) => Box::new(s.clone()),
+        _ => Box::new(value.to_string()),
+    }
+}
+
+/// Convert SQL value to JSON
+fn sql_value_to_json(row: &rusqlite::Row, idx: usize) -> Value {
+    // Try different types
+    if let Ok(v) = row.get::<_, i64>(idx) {
+        return Value::Number(v.into());
+    }
+    if let Ok(v) = row.get::<_, f64>(idx) {
+        return json!(v);
+    }
+    if let Ok(v) = row.get::<_, String>(idx) {
+        return Value::String(v);
+    }
+    if let Ok(v) = row.get::<_, Vec<u8>>(idx) {
+        return crate::blob::put(v, "application/octet-stream");
+    }
+    if let Ok(v) = row.get::<_, bool>(idx) {
+        return Value::Bool(v);
+    }
+    Value::Null
+}
+
+#[allow(unused_variables)]
+fn register_browser() {
+    register(Command::new("__poly_browser_create_tab", |call| Ok(json!(crate::browser::create_tab(call.str("url")))))
+        .optional("url", ArgKind::String));
+    register(Command::new("__poly_browser_close_tab", |call| Ok(json!(crate::browser::close_tab(id(call)))))
+        .arg("id", ArgKind::Number));
+    register(Command::new("__poly_browser_get_tab", |call| Ok(crate::browser::get_tab(id(call)).map_or(Value::Null, |tab| tab_json(&tab))))
+        .arg("id", ArgKind::Number));
+    register(Command::new("__poly_browser_list_tabs", |_| Ok(json!(crate::browser::list_tabs().iter().map(tab_json).collect::<Vec<_>>()))));
+    register(Command::new("__poly_browser_navigate", |call| {
+        crate::browser::navigate(id(call), call.str("url").unwrap_or("")).map(|_| json!(true)).map_err(runtime)
+    }).arg("id", ArgKind::Number).arg("url", ArgKind::String));
+    register(Command::new("__poly_browser_back", |call| crate::browser::go_back(id(call)).map(|url| json!(url)).map_err(runtime))
+        .arg("id", ArgKind::Number));
+    register(Command::new("__poly_browser_forward", |call| crate::browser::go_forward(id(call)).map(|url| json!(url)).map_err(runtime))
+        .arg("id", ArgKind::Number));
+    register(Command::new("__poly_browser_set_title", |call| {
+        crate::browser::set_tab_title(id(call), call.str("title").unwrap_or(""));
+        Ok(json!(true))
+    }).arg("id", ArgKind::Number).arg("title", ArgKind::String));
+    register(Command::new("__poly_browser_set_loading", |call| {
+        crate::browser::set_tab_loading(id(call), call.bool("loading").unwrap_or(false));
+        Ok(json!(true))
+    }).arg("id", ArgKind::Number).arg("loading", ArgKind::Bool));
+    register(Command::new("__poly_browser_get_history", |call| Ok(json!(crate::browser::get_history(id(call)))))
+        .arg("id", ArgKind::Number));
+    register(Command::new("__poly_browser_clear_history", |call| {
+        crate::browser::clear_history(id(call));
+        Ok(json!(true))
+    }).arg("id", ArgKind::Number));
+
+    // Fetch URL content (bypasses iframe restrictions)
+    register(Command::new("__poly_browser_fetch", |call| {
+        let url = call.str("url").unwrap_or("");
+        if crate::sovereignty::is_enabled() {
+            let domain = url.split("://")
+                .nth(1)
+                .and_then(|s| s.split('/').next())
+                .unwrap_or(url);
+            let permission = Permission::HttpConnect(crate::sovereignty::DomainScope::Domain(domain.to_string()));
+            crate::sovereignty::check_permission(&permission).map_err(runtime)?;
+        }
+
+        let response = reqwest::blocking::get(url).map_err(|e| runtime(e.to_string()))?;
+        let status = response.status().as_u16();
+        let headers: std::collections::HashMap<String, String> = response.headers()
+            .iter()
+            .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.to_string(), v.to_string())))
+            .collect();
+        let body = response.text().map_err(|e| runtime(e.to_string()))?;
+        Ok(json!({
+            "status": status,
+            "headers": headers,
+            "body": body
+        }))
+    }).arg("url", ArgKind::String));
+
+    // Real WebView windows
+    register(Command::new("__poly_browser_open_window", |call| {
+        let url = call.str("url").unwrap_or("about:blank");
+        let title = call.str("title").unwrap_or("Browser");
+        let width = call.u64("width").unwrap_or(1024) as u32;
+        let height = call.u64("height").unwrap_or(768) as u32;
+        #[cfg(feature = "native")]
+        { crate::browser::open_webview(url, title, width, height).map(|id| json!(id)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(runtime("WebView requires native feature")) }
+    })
+    .optional("url", ArgKind::String)
+    .optional("title", ArgKind::String)
+    .optional("width", ArgKind::Number)
+    .optional("height", ArgKind::Number));
+    register(Command::new("__poly_browser_window_navigate", |call| {
+        let url = call.str("url").unwrap_or("");
+        #[cfg(feature = "native")]
+        { crate::browser::webview_navigate(id(call), url).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(runtime("WebView requires native feature")) }
+    }).arg("id", ArgKind::Number).arg("url", ArgKind::String));
+    register(Command::new("__poly_browser_window_close", |call| {
+        #[cfg(feature = "native")]
+        { crate::browser::webview_close(id(call)).map(|_| json!(true)).map_err(runtime) }
+        #[cfg(not(feature = "native"))]
+        { Err(runtime("WebView requires native feature")) }
+    }).arg("id", ArgKind::Number));
+}
+
+fn tab_json(tab: &crate::browser::BrowserTab) -> Value {
+    json!({
+        "id": tab.id,
+        "url": tab.url,
+        "title": tab.title,
+        "canGoBack": tab.can_go_back,
+        "canGoForward": tab.can_go_forward,
+        "isLoading": tab.is_loading
+    })
+}
+
+fn register_titlebar() {
+    register(Command::new("__poly_titlebar_set", |call| {
+        crate::titlebar::set_titlebar(crate::titlebar::TitlebarConfig {
+            enabled: call.bool("enabled").unwrap_or(true),
+            height: call.u64("height").unwrap_or(40) as u32,
+            html: call.str("html").unwrap_or("").to_string(),
+            css: call.str("css").unwrap_or("").to_string(),
+            js: call.str("js").unwrap_or("").to_string(),
+            background: call.str("background").unwrap_or("#1a1a1f").to_string(),
+        });
+        Ok(json!(true))
+    })
+    .optional("enabled", ArgKind::Bool)
+    .optional("height", ArgKind::Number)
+    .optional("html", ArgKind::String)
+    .optional("css", ArgKind::String)
+    .optional("js", ArgKind::String)
+    .optional("background", ArgKind::String));
+    register(Command::new("__poly_titlebar_get", |_| {
+        let config = crate::titlebar::get_titlebar();
+        Ok(json!({
+            "enabled": config.enabled,
+            "height": config.height,
+            "html": config.html,
+            "css": config.css,
+            "js": config.js,
+            "background": config.background
+        }))
+    }));
+    register(Command::new("__poly_titlebar_set_enabled", |call| {
+        crate::titlebar::set_enabled(call.bool("enabled").unwrap_or(false));
+        Ok(json!(true))
+    }).arg("enabled", ArgKind::Bool));
+    register(Command::new("__poly_titlebar_set_height", |call| {
+        crate::titlebar::set_height(call.u64("height").unwrap_or(40) as u32);
+        Ok(json!(true))
+    }).arg("height", ArgKind::Number));
+    register(Command::new("__poly_titlebar_set_html", |call| {
+        crate::titlebar::set_html(call.str("html").unwrap_or(""));
+        Ok(json!(true))
+    }).arg("html", ArgKind::String));
+    register(Command::new("__poly_titlebar_set_css", |call| {
+        crate::titlebar::set_css(call.str("css").unwrap_or(""));
+        Ok(json!(true))
+    }).arg("css", ArgKind::String));
+    register(Command::new("__poly_titlebar_set_js", |call| {
+        crate::titlebar::set_js(call.str("js").unwrap_or(""));
+        Ok(json!(true))
+    }).arg("js", ArgKind::String));
+    // The native window navigates its WebView and injects the titlebar
+    register(Command::new("__poly_titlebar_navigate", |call| Ok(json!(call.str("url").unwrap_or(""))))
+        .arg("url", ArgKind::String));
+}
+
+/// Bounds from `x`, `y`, `width` and `height`, defaulting to 800x600 at the origin
+fn bounds(call: &Call) -> crate::webview::WebViewBounds {
+    crate::webview::WebViewBounds {
+        x: call.i64("x").unwrap_or(0) as i32,
+        y: call.i64("y").unwrap_or(0) as i32,
+        width: call.u64("width").unwrap_or(800) as u32,
+        height: call.u64("height").unwrap_or(600) as u32,
+    }
+}
+
+fn bounds_args(command: Command) -> Command {
+    command
+        .optional("x", ArgKind::Number)
+        .optional("y", ArgKind::Number)
+        .optional("width", ArgKind::Number)
+        .optional("height", ArgKind::Number)
+}
+
+fn webview_state(s: &crate::webview::WebViewState) -> Value {
+    json!({
+        "id": s.id,
+        "url": s.url,
+        "title": s.title,
+        "visible": s.visible,
+        "isLoading": s.is_loading,
+        "canGoBack": s.can_go_back,
+        "canGoForward": s.can_go_forward,
+        "zoomLevel": s.zoom_level,
+        "bounds": { "x": s.bounds.x, "y": s.bounds.y, "width": s.bounds.width, "height": s.bounds.height }
+    })
+}
+
+/// WebView replies are `{"success": true}` rather than a bare `true`
+fn register_webviews() {
+    let success = |result: Result<(), String>| result.map(|_| json!({"success": true})).map_err(runtime);
+    let webview = |call: &Call| call.str("id").unwrap_or("").to_string();
+
+    register(bounds_args(
+        Command::new("__poly_webview_create", |call| {
+            let id = call.str("id").unwrap_or("");
+            let config = crate::webview::WebViewConfig {
+                id: id.to_string(),
+                url: call.str("url").unwrap_or("about:blank").to_string(),
+                html: call.str("html").map(str::to_string),
+                bounds: bounds(call),
+                visible: call.bool("visible").unwrap_or(true),
+                transparent: call.bool("transparent").unwrap_or(false),
+                devtools: call.bool("devtools").unwrap_or(false),
+                user_agent: call.str("userAgent").map(str::to_string),
+                zoom_level: call.f64("zoomLevel").unwrap_or(1.0),
+                autoplay: call.bool("autoplay").unwrap_or(true),
+            };
+            crate::webview::create(config).map(|_| json!({"success": true, "id": id})).map_err(runtime)
+        })
+        .arg("id", ArgKind::String)
+        .optional("url", ArgKind::String)
+        .optional("html", ArgKind::String),
+    )
+    .optional("visible", ArgKind::Bool)
+    .optional("transparent", ArgKind::Bool)
+    .optional("devtools", ArgKind::Bool)
+    .optional("userAgent", ArgKind::String)
+    .optional("zoomLevel", ArgKind::Number)
+    .optional("autoplay", ArgKind::Bool));
+    register(Command::new("__poly_webview_navigate", move |call| success(crate::webview::navigate(&webview(call), call.str("url").unwrap_or("about:blank"))))
+        .arg("id", ArgKind::String)
+        .arg("url", ArgKind::String));
+    register(Command::new("__poly_webview_load_html", move |call| success(crate::webview::load_html(&webview(call), call.str("html").unwrap_or(""))))
+        .arg("id", ArgKind::String)
+        .arg("html", ArgKind::String));
+    register(Command::new("__poly_webview_go_back", move |call| success(crate::webview::go_back(&webview(call)))).arg("id", ArgKind::String));
+    register(Command::new("__poly_webview_go_forward", move |call| success(crate::webview::go_forward(&webview(call)))).arg("id", ArgKind::String));
+    register(Command::new("__poly_webview_reload", move |call| success(crate::webview::reload(&webview(call)))).arg("id", ArgKind::String));
+    register(Command::new("__poly_webview_stop", move |call| success(crate::webview::stop(&webview(call)))).arg("id", ArgKind::String));
+    register(bounds_args(
+        Command::new("__poly_webview_set_bounds", move |call| success(crate::webview::set_bounds(&webview(call), bounds(call))))
+            .arg("id", ArgKind::String),
+    ));
+    register(Command::new("__poly_webview_get_bounds", move |call| {
+        crate::webview::get_bounds(&webview(call))
+            .map(|b| json!({"x": b.x, "y": b.y, "width": b.width, "height": b.height}))
+            .map_err(runtime)
+    }).arg("id", ArgKind::String));
+    register(Command::new("__poly_webview_eval", move |call| success(crate::webview::eval(&webview(call), call.str("script").unwrap_or(""))))
+        .arg("id", ArgKind::String)
+        .arg("script", ArgKind::String));
+    register(Command::new("__poly_webview_destroy", move |call| success(crate::webview::destroy(&webview(call)))).arg("id", ArgKind::String));
+    register(Command::new("__poly_webview_list", |_| Ok(json!(crate::webview::list().iter().map(webview_state).collect::<Vec<_>>()))));
+    register(Command::new("__poly_webview_get", move |call| {
+        let id = webview(call);
+        crate::webview::get(&id).map(|s| webview_state(&s)).ok_or_else(|| runtime(format!("WebView '{}' not found", id)))
+    }).arg("id", ArgKind::String));
+    register(Command::new("__poly_webview_set_zoom", move |call| success(crate::webview::set_zoom(&webview(call), call.f64("level").unwrap_or(1.0))))
+        .arg("id", ArgKind::String)
+        .arg("level", ArgKind::Number));
+    register(Command::new("__poly_webview_poll_events", |_| {
+        let events: Vec<Value> = crate::webview::take_events().iter().map(|e| e.to_json()).collect();
+        Ok(json!({"events": events}))
+    }));
+    register(Command::new("__poly_webview_respond_permission", move |call| {
+        success(crate::webview::respond_to_permission(&webview(call), call.str("permission").unwrap_or(""), call.bool("granted").unwrap_or(false)))
+    })
+    .arg("id", ArgKind::String)
+    .arg("permission", ArgKind::String)
+    .arg("granted", ArgKind::Bool));
+    register(Command::new("__poly_webview_set_visible", move |call| success(crate::webview::set_visible(&webview(call), call.bool("visible").unwrap_or(true))))
+        .arg("id", ArgKind::String)
+        .arg("visible", ArgKind::Bool));
+    register(Command::new("__poly_webview_focus", move |call| success(crate::webview::focus(&webview(call)))).arg("id", ArgKind::String));
+    register(bounds_args(Command::new("__poly_webview_set_main_bounds", |call| {
+        crate::webview::set_main_bounds(bounds(call));
+        Ok(json!({"success": true}))
+    })));
+}
+
+/// Windows holding several WebViews
+fn register_multiview() {
+    register(Command::new("__poly_multiview_create", |call| {
+        let views: Vec<crate::multiview::ViewConfig> = call.get("views").and_then(|v| v.as_array()).map_or_else(Vec::new, |views| {
+            views.iter().map(|v| crate::multiview::ViewConfig {
+                id: v.get("id").and_then(|x| x.as_str()).unwrap_or("view").to_string(),
+                url: v.get("url").and_then(|x| x.as_str()).unwrap_or("about:blank").to_string(),
+                html: v.get("html").and_then(|x| x.as_str()).map(|s| s.to_string()),
+                x: v.get("x").and_then(|x| x.as_i64()).unwrap_or(0) as i32,
+                y: v.get("y").and_then(|x| x.as_i64()).unwrap_or(0) as i32,
+                width: v.get("width").and_then(|x| x.as_u64()).unwrap_or(800) as u32,
+                height: v.get("height").and_then(|x| x.as_u64()).unwrap_or(600) as u32,
+                transparent: v.get("transparent").and_then(|x| x.as_bool()).unwrap_or(false),
+                devtools: v.get("devtools").and_then(|x| x.as_bool()).unwrap_or(false),
+            }).collect()
+        });
+        let config = crate::multiview::MultiViewWindowConfig {
+            title: call.str("title").unwrap_or("Poly MultiView").to_string(),
+            width: call.u64("width").unwrap_or(1024) as u32,
+            height: call.u64("height").unwrap_or(768) as u32,
+            decorations: call.bool("decorations").unwrap_or(false),
+            resizable: call.bool("resizable").unwrap_or(true),
+            views,
+            icon_path: call.str("icon").map(str::to_string),
+        };
+
+        let window_id = crate::multiview::create_window(config.clone());
+        #[cfg(feature = "native")]
+        crate::multiview_native::create_multiview_window(window_id, config).map_err(runtime)?;
+        Ok(json!({"id": window_id}))
+    })
+    .optional("title", ArgKind::String)
+    .optional("width", ArgKind::Number)
+    .optional("height", ArgKind::Number)
+    .optional("decorations", ArgKind::Bool)
+    .optional("resizable", ArgKind::Bool)
+    .optional("icon", ArgKind::String)
+    .optional("views", ArgKind::Array));
+    register(Command::new("__poly_multiview_navigate", |call| {
+        let window_id = call.u64("windowId").unwrap_or(0);
+        let view_id = call.str("viewId").unwrap_or("");
+        let url = call.str("url").unwrap_or("");
+        crate::multiview::navigate(window_id, view_id, url);
+        #[cfg(feature = "native")]
+        crate::multiview_native::queue_navigation(window_id, view_id, url);
+        Ok(json!({"success": true}))
+    })
+    .arg("windowId", ArgKind::Number)
+    .arg("viewId", ArgKind::String)
+    .arg("url", ArgKind::String));
+    register(Command::new("__poly_multiview_post_message", |call| {
+        crate::multiview::post_message(call.u64("windowId").unwrap_or(0), call.str("viewId").unwrap_or(""), call.str("message").unwrap_or("{}"));
+        Ok(json!({"success": true}))
+    })
+    .arg("windowId", ArgKind::Number)
+    .arg("viewId", ArgKind::String)
+    .arg("message", ArgKind::String));
+    register(bounds_args(
+        Command::new("__poly_multiview_set_bounds", |call| {
+            let b = bounds(call);
+            crate::multiview::set_view_bounds(call.u64("windowId").unwrap_or(0), call.str("viewId").unwrap_or(""), b.x, b.y, b.width, b.height);
+            Ok(json!({"success": true}))
+        })
+        .arg("windowId", ArgKind::Number)
+        .arg("viewId", ArgKind::String),
+    ));
+    register(Command::new("__poly_multiview_close", |call| {
+        crate::multiview::close_window(call.u64("windowId").unwrap_or(0));
+        Ok(json!({"success": true}))
+    }).arg("windowId", ArgKind::Number));
+    register(Command::new("__poly_multiview_list", |_| {
+        let windows: Vec<Value> = crate::multiview::list_windows().iter().map(|w| json!({
+            "id": w.id,
+            "title": w.title,
+            "views": w.views
+        })).collect();
+        Ok(json!({"windows": windows}))
+    }));
+    register(Command::new("__poly_multiview_get", |call| {
+        crate::multiview::get_window(call.u64("windowId").unwrap_or(0))
+            .map(|w| json!({"id": w.id, "title": w.title, "views": w.views}))
+            .ok_or_else(|| runtime("Window not found"))
+    }).arg("windowId", ArgKind::Number));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schemas_match_reference() {
+        crate::register_builtin_commands();
+        for api in crate::reference::POLY_APIS {
+            let names: Vec<String> = crate::router::args(api.name).map(|args| args.into_iter().map(|arg| arg.name).collect::<Vec<_>>())
+                .unwrap_or_else(|| panic!("{} is not registered", api.name));
+            assert_eq!(names, api.params, "{}", api.name);
+        }
+
+        let missing = crate::router::dispatch("__poly_fs_read", json!({})).unwrap_err();
+        assert_eq!(missing.code, "INVALID_ARGUMENTS");
+        let wrong = crate::router::dispatch("__poly_fs_read", json!({"path": 1})).unwrap_err();
+        assert!(wrong.message.contains("must be string"), "{}", wrong.message);
+    }
+}
@@ -0,0 +1,514 @@
+//! Test runner behind `poly test`
+//!
+//! Discovers `test_*.poly` files and the `test_*` functions inside them. Each
+//! test runs in a fresh interpreter (the file's top level is re-executed), so
+//! tests cannot leak state into each other and can run in parallel.
+//!
+//! Per-test hooks, all optional:
+//! - `setup()` / `teardown()` run before and after every test
+//! - a test parameter `db` is filled by calling `fixture_db()`, and
+//!   `teardown_db(value)` runs afterwards if defined
+//!
+//! With `coverage` set, every test also records line and branch hits of the
+//! `.poly` files under the root, test files excluded.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::ast::{Program, Statement};
+use crate::coverage::Coverage;
+use crate::interpreter::Interpreter;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+/// Directories never searched for tests
+pub(crate) const SKIP_DIRS: &[&str] = &["node_modules", "packages", "dist", "target", "build"];
+
+#[derive(Debug, Clone, Default)]
+pub struct TestOptions {
+    /// Only run tests whose id (`file::name`) contains this substring
+    pub filter: Option<String>,
+    /// Worker threads (0 = one per CPU)
+    pub jobs: usize,
+    /// Record line and branch coverage
+    pub coverage: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TestOutcome {
+    Passed,
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct TestResult {
+    pub file: String,
+    pub name: String,
+    pub outcome: TestOutcome,
+    pub duration: Duration,
+    /// Everything the test printed
+    pub output: Vec<String>,
+}
+
+impl TestResult {
+    pub fn id(&self) -> String {
+        format!("{}::{}", self.file, self.name)
+    }
+
+    pub fn passed(&self) -> bool {
+        self.outcome == TestOutcome::Passed
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TestReport {
+    pub results: Vec<TestResult>,
+    pub duration: Duration,
+    /// Hits per source file, relative to the root, when coverage was requested
+    pub coverage: Option<Coverage>,
+}
+
+impl TestReport {
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|r| r.passed()).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.results.len() - self.passed()
+    }
+
+    /// JUnit XML, one `<testsuite>` per file
+    pub fn to_junit_xml(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites name=\"poly\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+            self.results.len(),
+            self.failed(),
+            self.duration.as_secs_f64()
+        ));
+        for (file, results) in self.by_file() {
+            let failures = results.iter().filter(|r| !r.passed()).count();
+            let time: f64 = results.iter().map(|r| r.duration.as_secs_f64()).sum();
+            xml.push_str(&format!(
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+                xml_escape(file),
+                results.len(),
+                failures,
+                time
+            ));
+            for result in results {
+                xml.push_str(&format!(
+                    "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+                    xml_escape(file),
+                    xml_escape(&result.name),
+                    result.duration.as_secs_f64()
+                ));
+                if result.passed() && result.output.is_empty() {
+                    xml.push_str("/>\n");
+                    continue;
+                }
+                xml.push_str(">\n");
+                if let TestOutcome::Failed(message) = &result.outcome {
+                    let first_line = message.lines().next().unwrap_or("");
+                    xml.push_str(&format!(
+                        "      <failure message=\"{}\">{}</failure>\n",
+                        xml_escape(first_line),
+                        xml_escape(message)
+                    ));
+                }
+                if !result.output.is_empty() {
+                    xml.push_str(&format!("      <system-out>{}</system-out>\n", xml_escape(&result.output.join("\n"))));
+                }
+                xml.push_str("    </testcase>\n");
+            }
+            xml.push_str("  </testsuite>\n");
+        }
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+
+    pub fn to_json(&self) -> String {
+        let tests: Vec<serde_json::Value> = self
+            .results
+            .iter()
+            .map(|r| {
+                let (status, message) = match &r.outcome {
+                    TestOutcome::Passed => ("passed", serde_json::Value::Null),
+                    TestOutcome::Failed(m) => ("failed", serde_json::Value::String(m.clone())),
+                };
+                serde_json::json!({
+                    "id": r.id(),
+                    "file": r.file,
+                    "name": r.name,
+                    "status": status,
+                    "message": message,
+                    "duration_ms": r.duration.as_secs_f64() * 1000.0,
+                    "output": r.output,
+                })
+            })
+            .collect();
+        serde_json::to_string_pretty(&serde_json::json!({
+            "summary": {
+                "total": self.results.len(),
+                "passed": self.passed(),
+                "failed": self.failed(),
+                "duration_ms": self.duration.as_secs_f64() * 1000.0,
+            },
+            "tests": tests,
+        }))
+        .unwrap_or_default()
+    }
+
+    /// Results grouped by file, in run order
+    pub fn by_file(&self) -> Vec<(&str, Vec<&TestResult>)> {
+        let mut groups: Vec<(&str, Vec<&TestResult>)> = Vec::new();
+        for result in &self.results {
+            match groups.iter_mut().find(|(file, _)| *file == result.file) {
+                Some((_, items)) => items.push(result),
+                None => groups.push((&result.file, vec![result])),
+            }
+        }
+        groups
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// ============================================================================
+// Discovery
+// ============================================================================
+
+/// Find `test_*.poly` files under `root` (or `root` itself if it is a file)
+pub fn discover(root: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if root.is_file() {
+        files.push(root.to_path_buf());
+    } else {
+        walk(root, &mut files, true);
+    }
+    files.sort();
+    files
+}
+
+/// The `.poly` files under `root` that are not tests
+fn sources(root: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    walk(root, &mut files, false);
+    files.sort();
+    files
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>, tests: bool) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if path.is_dir() {
+            if !name.starts_with('.') && !SKIP_DIRS.contains(&name.as_str()) {
+                walk(&path, files, tests);
+            }
+        } else if name.starts_with("test_") == tests && name.ends_with(".poly") {
+            files.push(path);
+        }
+    }
+}
+
+/// A parsed test file and the test functions it defines, in source order
+pub struct TestFile {
+    pub path: String,
+    pub program: Program,
+    pub tests: Vec<String>,
+}
+
+pub fn load(path: &Path) -> Result<TestFile, String> {
+    load_with(path, false)
+}
+
+/// Parse a source file, with line and file markers for coverage if asked
+fn parse_file(path: &Path, coverage: bool) -> Result<Program, String> {
+    let source = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let mut parser = Parser::new(Lexer::new(&source).tokenize());
+    if coverage {
+        let name = std::fs::canonicalize(path).map_or_else(|_| path.display().to_string(), |p| p.display().to_string());
+        parser.with_lines().with_source(&name).parse()
+    } else {
+        parser.parse()
+    }
+}
+
+fn load_with(path: &Path, coverage: bool) -> Result<TestFile, String> {
+    let program = parse_file(path, coverage)?;
+    let tests = program
+        .statements
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Statement::FnDef { name, .. } if name.starts_with("test_") => Some(name.clone()),
+            _ => None,
+        })
+        .collect();
+    Ok(TestFile { path: path.to_string_lossy().replace('\\', "/"), program, tests })
+}
+
+// ============================================================================
+// Running
+// ============================================================================
+
+/// Run one test in a fresh interpreter
+pub fn run_test(program: &Program, name: &str) -> (TestOutcome, Vec<String>) {
+    let (outcome, output, _) = run_test_with(program, name, None);
+    (outcome, output)
+}
+
+fn run_test_with(program: &Program, name: &str, coverage: Option<Coverage>) -> (TestOutcome, Vec<String>, Option<Coverage>) {
+    let mut interp = Interpreter::new();
+    interp.set_echo_output(false);
+    interp.set_coverage(coverage);
+    interp.set_harness(Some(crate::headless::Harness::default()));
+    let outcome = match run_in(&mut interp, program, name) {
+        Ok(()) => TestOutcome::Passed,
+        Err(e) => TestOutcome::Failed(e),
+    };
+    (outcome, interp.get_output().to_vec(), interp.take_coverage())
+}
+
+fn run_in(interp: &mut Interpreter, program: &Program, name: &str) -> Result<(), String> {
+    interp.run(program).map_err(|e| format!("Error while loading test file: {}", e))?;
+    let params = interp.function_params(name).ok_or_else(|| format!("{} is not a function", name))?;
+
+    let mut result = match interp.function_params("setup") {
+        Some(_) => interp.call("setup", vec![]).map(|_| ()),
+        None => Ok(()),
+    };
+
+    // Fixtures are created in parameter order and torn down in reverse
+    let mut fixtures = Vec::new();
+    if result.is_ok() {
+        for param in &params {
+            match interp.call(&format!("fixture_{}", param), vec![]) {
+                Ok(value) => fixtures.push((param.clone(), value)),
+                Err(e) => {
+                    result = Err(format!("Fixture '{}' failed: {}", param, e));
+                    break;
+                }
+            }
+        }
+    }
+    if result.is_ok() {
+        let args = fixtures.iter().map(|(_, v)| v.clone()).collect();
+        result = interp.call(name, args).map(|_| ());
+    }
+
+    for (param, value) in fixtures.into_iter().rev() {
+        let hook = format!("teardown_{}", param);
+        if interp.function_params(&hook).is_some() {
+            if let Err(e) = interp.call(&hook, vec![value]) {
+                result = result.and(Err(format!("Fixture teardown '{}' failed: {}", param, e)));
+            }
+        }
+    }
+    if interp.function_params("teardown").is_some() {
+        if let Err(e) = interp.call("teardown", vec![]) {
+            result = result.and(Err(format!("teardown() failed: {}", e)));
+        }
+    }
+    result
+}
+
+/// Discover and run every test under `root`
+pub fn run(root: &Path, options: &TestOptions) -> Result<TestReport, String> {
+    // `ipc_invoke` checks system API calls, faked or not, against their schemas
+    crate::register_builtin_commands();
+    let start = Instant::now();
+    let mut cases: Vec<(Arc<TestFile>, String)> = Vec::new();
+    let mut load_errors = Vec::new();
+
+    for path in discover(root) {
+        match load_with(&path, options.coverage) {
+            Ok(file) => {
+                let file = Arc::new(file);
+                for test in &file.tests {
+                    let id = format!("{}::{}", file.path, test);
+                    if options.filter.as_ref().is_none_or(|f| id.contains(f.as_str())) {
+                        cases.push((file.clone(), test.clone()));
+                    }
+                }
+            }
+            Err(e) => load_errors.push(TestResult {
+                file: path.to_string_lossy().replace('\\', "/"),
+                name: "<load>".to_string(),
+                outcome: TestOutcome::Failed(e),
+                duration: Duration::ZERO,
+                output: Vec::new(),
+            }),
+        }
+    }
+
+    let jobs = match options.jobs {
+        0 => std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        n => n,
+    }
+    .min(cases.len().max(1));
+
+    let queue = Arc::new(Mutex::new(cases.into_iter().enumerate().collect::<VecDeque<_>>()));
+    let results = Arc::new(Mutex::new(Vec::new()));
+    let coverage = Arc::new(Mutex::new(Coverage::new()));
+    let track = options.coverage;
+    let workers: Vec<_> = (0..jobs)
+        .map(|_| {
+            let queue = queue.clone();
+            let results = results.clone();
+            let coverage = coverage.clone();
+            std::thread::spawn(move || loop {
+                let Some((index, (file, name))) = queue.lock().unwrap().pop_front() else { break };
+                let started = Instant::now();
+                let (outcome, output, hits) = run_test_with(&file.program, &name, track.then(Coverage::new));
+                if let Some(hits) = hits {
+                    coverage.lock().unwrap().merge(&hits);
+                }
+                results.lock().unwrap().push((index, TestResult {
+                    file: file.path.clone(),
+                    name,
+                    outcome,
+                    duration: started.elapsed(),
+                    output,
+                }));
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().map_err(|_| "Test worker panicked".to_string())?;
+    }
+
+    let mut results = std::mem::take(&mut *results.lock().unwrap());
+    results.sort_by_key(|(index, _)| *index);
+    let mut all = load_errors;
+    all.extend(results.into_iter().map(|(_, r)| r));
+    let coverage = options.coverage.then(|| source_coverage(root, &coverage.lock().unwrap()));
+    Ok(TestReport { results: all, duration: start.elapsed(), coverage })
+}
+
+/// Coverage of the non-test files under `root`, including ones no test touched
+fn source_coverage(root: &Path, hits: &Coverage) -> Coverage {
+    let dir = if root.is_file() { root.parent().unwrap_or(Path::new(".")) } else { root };
+    let dir = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    let mut coverage = Coverage::new();
+    for path in sources(&dir) {
+        if let Ok(program) = parse_file(&path, true) {
+            coverage.register(&path.display().to_string(), &program);
+        }
+    }
+    let mut recorded = hits.clone();
+    recorded.files.retain(|name, _| coverage.files.contains_key(name));
+    coverage.merge(&recorded);
+    coverage.relative_to(&dir);
+    coverage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_suite(name: &str, source: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("poly_testing_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(dir.join("node_modules")).unwrap();
+        std::fs::write(dir.join("test_suite.poly"), source).unwrap();
+        std::fs::write(dir.join("node_modules").join("test_ignored.poly"), "fn test_never():\n    assert false\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_fixtures_hooks_and_failures() {
+        let dir = write_suite("hooks", r#"
+fn setup():
+    print("setup")
+
+fn teardown():
+    print("teardown")
+
+fn fixture_numbers():
+    return [1, 2, 3]
+
+fn test_sum(numbers):
+    assert sum(numbers) == 6
+
+fn reject():
+    raise "invalid input"
+
+fn test_raises():
+    expect_raises(lambda: int("x"))
+    assert expect_raises(reject, "invalid") == "invalid input"
+
+fn test_fails():
+    print("about to fail")
+    assert 1 == 2, "one is not two"
+
+fn test_no_raise():
+    expect_raises(lambda: 1)
+"#);
+        let report = run(&dir, &TestOptions::default()).unwrap();
+        let names: Vec<&str> = report.results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["test_sum", "test_raises", "test_fails", "test_no_raise"]);
+        assert_eq!(report.passed(), 2);
+
+        let failed = &report.results[2];
+        assert!(matches!(&failed.outcome, TestOutcome::Failed(m) if m.contains("one is not two")));
+        assert_eq!(failed.output, vec!["setup", "about to fail", "teardown"]);
+        assert!(matches!(&report.results[3].outcome, TestOutcome::Failed(m) if m.contains("none was raised")));
+
+        let xml = report.to_junit_xml();
+        assert!(xml.contains("tests=\"4\" failures=\"2\""));
+        assert!(xml.contains("<failure message=\"Error: one is not two\">"));
+
+        let filtered = run(&dir, &TestOptions { filter: Some("sum".to_string()), jobs: 1, coverage: false }).unwrap();
+        assert_eq!(filtered.results.len(), 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_ipc_harness() {
+        let dir = write_suite("harness", r#"
+fn add(text):
+    emit("added", {"text": text})
+    return shared_add("harness_test_todos")
+
+fn on_clear(payload):
+    print("cleared", payload)
+
+on("clear", on_clear)
+
+fn test_invoke_and_events():
+    assert ipc_invoke("add", ["milk"]) == 1
+    assert ipc_invoke("add", {"text": "eggs"}) == 2
+    let events = emitted("added")
+    assert len(events) == 2
+    assert events[1]["topic"] == "added" and events[1]["payload"]["text"] == "eggs"
+    assert len(emitted()) == 0
+    assert ipc_emit("clear", "all") == 1
+    expect_raises(lambda: ipc_invoke("add", ["a", "b"]), "INVALID_ARGUMENTS")
+
+fn test_fakes():
+    assert ipc_invoke("__poly_dialog_confirm", {"title": "Delete?", "message": "Delete the list?"}) == false
+    fake_api("__poly_dialog_confirm", true)
+    assert ipc_invoke("__poly_dialog_confirm", {"title": "Sure?", "message": "Really?"}) == true
+    expect_raises(lambda: ipc_invoke("__poly_dialog_confirm", {"title": "Sure?"}), "INVALID_ARGUMENTS")
+    let calls = fake_calls("__poly_dialog_confirm")
+    assert len(calls) == 2 and calls[0]["title"] == "Delete?"
+    ipc_invoke("__poly_clipboard_write", {"text": "copied"})
+    assert ipc_invoke("__poly_clipboard_read") == "copied"
+"#);
+        let report = run(&dir, &TestOptions { jobs: 1, ..Default::default() }).unwrap();
+        for result in &report.results {
+            assert_eq!(result.outcome, TestOutcome::Passed, "{}", result.name);
+        }
+        assert_eq!(report.passed(), 2);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
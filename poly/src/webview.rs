@@ -138,6 +138,41 @@ pub enum WebViewEvent {
     PermissionRequested { id: String, permission: String, origin: String },
 }
 
+impl WebViewEvent {
+    /// JSON shape seen by pages, from `pollEvents()` or the `webview` event topic
+    pub fn to_json(&self) -> serde_json::Value {
+        use serde_json::json;
+        match self {
+            WebViewEvent::NavigationStarted { id, url } => 
+                json!({"type": "navigate", "id": id, "url": url}),
+            WebViewEvent::NavigationFinished { id, url } => 
+                json!({"type": "navigateFinish", "id": id, "url": url}),
+            WebViewEvent::TitleChanged { id, title } => 
+                json!({"type": "titleChange", "id": id, "title": title}),
+            WebViewEvent::LoadStarted { id } => 
+                json!({"type": "loadStart", "id": id}),
+            WebViewEvent::LoadFinished { id } => 
+                json!({"type": "loadFinish", "id": id}),
+            WebViewEvent::NewWindowRequested { id, url, target } => 
+                json!({"type": "newWindow", "id": id, "url": url, "target": target}),
+            WebViewEvent::DownloadRequested { id, url, filename } => 
+                json!({"type": "download", "id": id, "url": url, "filename": filename}),
+            WebViewEvent::Closed { id } => 
+                json!({"type": "close", "id": id}),
+            WebViewEvent::Error { id, error } => 
+                json!({"type": "error", "id": id, "error": error}),
+            WebViewEvent::FaviconChanged { id, url } => 
+                json!({"type": "favicon", "id": id, "url": url}),
+            WebViewEvent::HistoryChanged { id, can_go_back, can_go_forward } => 
+                json!({"type": "historyChange", "id": id, "canGoBack": can_go_back, "canGoForward": can_go_forward}),
+            WebViewEvent::FullscreenRequested { id, enter } => 
+                json!({"type": "fullscreen", "id": id, "enter": enter}),
+            WebViewEvent::PermissionRequested { id, permission, origin } => 
+                json!({"type": "permission", "id": id, "permission": permission, "origin": origin}),
+        }
+    }
+}
+
 // ============================================
 // Operations - Commands to execute
 // ============================================
@@ -523,6 +558,7 @@ pub fn take_events() -> Vec<WebViewEvent> {
 
 /// Push an event (called by native code when something happens)
 pub fn push_event(event: WebViewEvent) {
+    crate::events::emit("webview", event.to_json(), None);
     if let Ok(mut events) = EVENT_QUEUE.lock() {
         events.push(event);
     }
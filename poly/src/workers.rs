@@ -0,0 +1,409 @@
+//! Worker pool for IPC calls into Poly functions
+//!
+//! Each worker is an interpreter that has run the entry module, so with
+//! `[ipc] workers = n` up to n calls run at once and one slow AI request or
+//! HTTP fetch no longer holds up the page. Module-level variables belong to
+//! one worker; state every worker must see goes through `shared_get`,
+//! `shared_set` and `shared_add`, and `on()` handlers for page events run on
+//! the first worker. Waiting calls are taken highest priority first, in
+//! arrival order within a priority. A call sent with an `id` can be
+//! cancelled with `__poly_ipc_cancel`: a queued call is dropped and a running
+//! one stops at its next statement.
+
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex, RwLock};
+use std::time::Instant;
+
+use once_cell::sync::Lazy;
+use serde_json::json;
+
+use crate::interpreter::Interpreter;
+use crate::ipc::{Error, Request};
+use crate::router::{ArgKind, Command};
+
+/// Order in which waiting calls are taken
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "low" => Some(Priority::Low),
+            "normal" => Some(Priority::Normal),
+            "high" => Some(Priority::High),
+            _ => None,
+        }
+    }
+}
+
+type Reply = mpsc::Sender<Result<serde_json::Value, Error>>;
+type Task = Box<dyn FnOnce(&mut Interpreter) -> Result<serde_json::Value, Error> + Send>;
+
+struct Job {
+    seq: u64,
+    priority: Priority,
+    id: Option<String>,
+    /// Name the profiler records the call under
+    label: String,
+    task: Task,
+    cancel: Arc<AtomicBool>,
+    reply: Reply,
+}
+
+impl Job {
+    fn key(&self) -> (Priority, std::cmp::Reverse<u64>) {
+        (self.priority, std::cmp::Reverse(self.seq))
+    }
+}
+
+impl PartialEq for Job {
+    fn eq(&self, other: &Self) -> bool {
+        self.seq == other.seq
+    }
+}
+
+impl Eq for Job {}
+
+impl PartialOrd for Job {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Job {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        self.key().cmp(&other.key())
+    }
+}
+
+#[derive(Default)]
+struct State {
+    queue: BinaryHeap<Job>,
+    /// Calls being run, by id, with their cancel flags
+    running: Vec<(String, Arc<AtomicBool>)>,
+    next_seq: u64,
+    busy: usize,
+    peak_queued: usize,
+    completed: u64,
+    cancelled: u64,
+    rejected: u64,
+    stopped: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    ready: Condvar,
+    queue_limit: usize,
+}
+
+/// Queue depth and call counts of a pool
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metrics {
+    pub workers: usize,
+    /// Workers running a call
+    pub busy: usize,
+    /// Calls waiting for a worker, by priority (low, normal, high)
+    pub queued: [usize; 3],
+    pub peak_queued: usize,
+    pub completed: u64,
+    pub cancelled: u64,
+    /// Calls refused because the queue was full
+    pub rejected: u64,
+}
+
+impl Metrics {
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "workers": self.workers,
+            "busy": self.busy,
+            "queued": self.queued.iter().sum::<usize>(),
+            "queuedByPriority": {"low": self.queued[0], "normal": self.queued[1], "high": self.queued[2]},
+            "peakQueued": self.peak_queued,
+            "completed": self.completed,
+            "cancelled": self.cancelled,
+            "rejected": self.rejected,
+        })
+    }
+}
+
+/// Interpreters serving IPC calls, each on its own thread
+pub struct Pool {
+    workers: Vec<Arc<Mutex<Interpreter>>>,
+    shared: Arc<Shared>,
+}
+
+impl Pool {
+    /// Serve calls with `workers`, which have already run the entry module.
+    /// At most `queue_limit` calls wait; more fail with `BUSY`.
+    pub fn new(workers: Vec<Arc<Mutex<Interpreter>>>, queue_limit: usize) -> Self {
+        let shared = Arc::new(Shared { state: Mutex::new(State::default()), ready: Condvar::new(), queue_limit });
+        for (index, worker) in workers.iter().enumerate() {
+            let (worker, shared) = (Arc::clone(worker), Arc::clone(&shared));
+            std::thread::Builder::new()
+                .name(format!("poly-ipc-{}", index))
+                .spawn(move || work(&worker, &shared))
+                .expect("Failed to start IPC worker");
+        }
+        Pool { workers, shared }
+    }
+
+    /// The interpreters, first one first; lock one to reload it
+    pub fn workers(&self) -> &[Arc<Mutex<Interpreter>>] {
+        &self.workers
+    }
+
+    /// Queue a call; the result arrives on the returned channel
+    pub fn submit(&self, request: Request) -> Result<mpsc::Receiver<Result<serde_json::Value, Error>>, Error> {
+        let (priority, id, label) = (request.priority, request.id.clone(), request.function.clone());
+        self.queue(priority, id, label, Box::new(move |interp| crate::ipc::invoke(interp, &request)))
+    }
+
+    /// Run `task` on a worker and wait for what it returns
+    pub fn run<T: Send + 'static>(&self, label: &str, priority: Priority, task: impl FnOnce(&mut Interpreter) -> T + Send + 'static) -> Result<T, Error> {
+        let (sender, output) = mpsc::channel();
+        let result = self.queue(priority, None, label.to_string(), Box::new(move |interp| {
+            let _ = sender.send(task(interp));
+            Ok(serde_json::Value::Null)
+        }))?;
+        result.recv().unwrap_or_else(|_| Err(stopped()))?;
+        output.recv().map_err(|_| stopped())
+    }
+
+    fn queue(&self, priority: Priority, id: Option<String>, label: String, task: Task) -> Result<mpsc::Receiver<Result<serde_json::Value, Error>>, Error> {
+        let (reply, result) = mpsc::channel();
+        let mut state = self.shared.state.lock().unwrap();
+        if state.queue.len() >= self.shared.queue_limit {
+            state.rejected += 1;
+            return Err(Error::new("BUSY", format!("{} calls are already waiting", state.queue.len())));
+        }
+        state.next_seq += 1;
+        let seq = state.next_seq;
+        state.queue.push(Job { seq, priority, id, label, task, cancel: Arc::new(AtomicBool::new(false)), reply });
+        state.peak_queued = state.peak_queued.max(state.queue.len());
+        drop(state);
+        self.shared.ready.notify_one();
+        Ok(result)
+    }
+
+    /// Run a call and wait for its result
+    pub fn call(&self, request: Request) -> Result<serde_json::Value, Error> {
+        self.submit(request)?.recv().unwrap_or_else(|_| Err(stopped()))
+    }
+
+    /// Cancel the calls sent with `id`; returns whether any was found
+    pub fn cancel(&self, id: &str) -> bool {
+        let mut state = self.shared.state.lock().unwrap();
+        let mut found = false;
+        for (running, flag) in &state.running {
+            if running == id {
+                flag.store(true, Ordering::Relaxed);
+                found = true;
+            }
+        }
+        let (dropped, kept): (Vec<Job>, Vec<Job>) = std::mem::take(&mut state.queue).into_iter()
+            .partition(|job| job.id.as_deref() == Some(id));
+        state.queue = kept.into();
+        state.cancelled += dropped.len() as u64;
+        for job in dropped {
+            let _ = job.reply.send(Err(cancelled()));
+            found = true;
+        }
+        found
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        let state = self.shared.state.lock().unwrap();
+        let mut queued = [0; 3];
+        for job in state.queue.iter() {
+            queued[job.priority as usize] += 1;
+        }
+        Metrics {
+            workers: self.workers.len(),
+            busy: state.busy,
+            queued,
+            peak_queued: state.peak_queued,
+            completed: state.completed,
+            cancelled: state.cancelled,
+            rejected: state.rejected,
+        }
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().stopped = true;
+        self.shared.ready.notify_all();
+    }
+}
+
+fn cancelled() -> Error {
+    Error::new("CANCELLED", "The call was cancelled")
+}
+
+fn stopped() -> Error {
+    Error::new("RUNTIME_ERROR", "The IPC worker stopped")
+}
+
+fn work(worker: &Mutex<Interpreter>, shared: &Shared) {
+    loop {
+        let job = {
+            let mut state = shared.state.lock().unwrap();
+            loop {
+                if state.stopped {
+                    return;
+                }
+                if let Some(job) = state.queue.pop() {
+                    state.busy += 1;
+                    if let Some(id) = &job.id {
+                        state.running.push((id.clone(), Arc::clone(&job.cancel)));
+                    }
+                    break job;
+                }
+                state = shared.ready.wait(state).unwrap();
+            }
+        };
+
+        let result = {
+            let mut interp = worker.lock().unwrap();
+            interp.set_cancel_flag(Some(Arc::clone(&job.cancel)));
+            let start = Instant::now();
+            let result = (job.task)(&mut interp);
+            interp.set_cancel_flag(None);
+            if let Some(profiler) = interp.profiler_mut() {
+                profiler.command(&job.label, start.elapsed());
+            }
+            result
+        };
+        let was_cancelled = job.cancel.load(Ordering::Relaxed);
+
+        {
+            let mut state = shared.state.lock().unwrap();
+            state.busy -= 1;
+            state.running.retain(|(_, flag)| !Arc::ptr_eq(flag, &job.cancel));
+            if was_cancelled {
+                state.cancelled += 1;
+            } else {
+                state.completed += 1;
+            }
+        }
+        let _ = job.reply.send(if was_cancelled { Err(cancelled()) } else { result });
+    }
+}
+
+static POOL: Lazy<RwLock<Option<Arc<Pool>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Make `pool` the one `__poly_ipc_cancel` and `__poly_ipc_metrics` act on
+pub fn install(pool: Arc<Pool>) {
+    *POOL.write().unwrap() = Some(pool);
+}
+
+pub fn current() -> Option<Arc<Pool>> {
+    POOL.read().unwrap().clone()
+}
+
+/// Put back a pool returned by `current`, or none
+pub fn restore(pool: Option<Arc<Pool>>) {
+    *POOL.write().unwrap() = pool;
+}
+
+/// Register `__poly_ipc_cancel` and `__poly_ipc_metrics`
+pub fn register_commands() {
+    crate::router::register(Command::new("__poly_ipc_cancel", |call| {
+        let id = call.str("id").unwrap_or_default();
+        Ok(json!(current().is_some_and(|pool| pool.cancel(id))))
+    }).arg("id", ArgKind::String));
+    crate::router::register(Command::new("__poly_ipc_metrics", |_| {
+        Ok(current().map_or_else(|| json!({"workers": 0, "busy": 0, "queued": 0}), |pool| pool.metrics().to_json()))
+    }));
+}
+
+static SHARED: Lazy<Mutex<HashMap<String, serde_json::Value>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Value stored with `shared_set`
+pub fn shared_get(key: &str) -> Option<serde_json::Value> {
+    SHARED.lock().unwrap().get(key).cloned()
+}
+
+/// Store a value for every worker; `null` removes the key
+pub fn shared_set(key: &str, value: serde_json::Value) {
+    let mut shared = SHARED.lock().unwrap();
+    if value.is_null() {
+        shared.remove(key);
+    } else {
+        shared.insert(key.to_string(), value);
+    }
+}
+
+/// Add to a shared number (missing counts as 0) and return the new value
+pub fn shared_add(key: &str, amount: f64) -> Result<serde_json::Value, String> {
+    let mut shared = SHARED.lock().unwrap();
+    let current = match shared.get(key) {
+        None => 0.0,
+        Some(value) => value.as_f64().ok_or_else(|| format!("shared value '{}' is not a number", key))?,
+    };
+    let sum = current + amount;
+    let value = if sum.fract() == 0.0 && sum.abs() < 9e15 { json!(sum as i64) } else { json!(sum) };
+    shared.insert(key.to_string(), value.clone());
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use std::time::Duration;
+
+    fn worker(source: &str) -> Arc<Mutex<Interpreter>> {
+        let program = Parser::new(Lexer::new(source).tokenize()).parse().unwrap();
+        let mut interp = Interpreter::new();
+        interp.run(&program).unwrap();
+        Arc::new(Mutex::new(interp))
+    }
+
+    fn request(function: &str, id: Option<&str>, priority: Priority) -> Request {
+        Request { function: function.into(), args: json!([]), id: id.map(String::from), priority, ..Default::default() }
+    }
+
+    #[test]
+    fn test_concurrency_priority_and_cancellation() {
+        let source = "fn slow():\n    sleep_ms(5000)\n    return 1\nfn order(name):\n    shared_set(\"order\", shared_get(\"order\", \"\") + name)\n    return shared_add(\"test_calls\")\n";
+        let pool = Pool::new(vec![worker(source)], 3);
+
+        let running = pool.submit(request("slow", Some("a"), Priority::Normal)).unwrap();
+        while pool.metrics().busy < 1 {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let call = |name: &str, priority| {
+            let mut request = request("order", None, priority);
+            request.args = json!([name]);
+            pool.submit(request)
+        };
+        let low = call("L", Priority::Low).unwrap();
+        let queued = pool.submit(request("slow", Some("b"), Priority::Normal)).unwrap();
+        let high = call("H", Priority::High).unwrap();
+        assert_eq!(call("X", Priority::Normal).unwrap_err().code, "BUSY");
+        assert_eq!(pool.metrics().queued, [1, 1, 1]);
+
+        // A queued call is dropped; the running one stops long before 5s
+        let start = Instant::now();
+        assert!(pool.cancel("b") && pool.cancel("a"));
+        assert!(!pool.cancel("missing"));
+        assert_eq!(queued.recv().unwrap().unwrap_err().code, "CANCELLED");
+        assert_eq!(running.recv().unwrap().unwrap_err().code, "CANCELLED");
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(low.recv().unwrap().unwrap(), json!(2));
+        high.recv().unwrap().unwrap();
+        assert_eq!(shared_get("order"), Some(json!("HL")));
+
+        let metrics = pool.metrics();
+        assert_eq!((metrics.busy, metrics.completed, metrics.cancelled, metrics.rejected, metrics.peak_queued), (0, 2, 2, 1, 3));
+    }
+}